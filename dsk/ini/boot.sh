@@ -1,5 +1,8 @@
 vga set font /ini/fonts/zap-light-8x16.psf
 shell /ini/palettes/gruvbox-dark.sh
 read /ini/banner.txt
+mount proc /proc
+net config load
+fw load
 user login
 shell
@@ -1,6 +1,6 @@
 use crate::sys;
 
-use alloc::string::ToString;
+use alloc::string::{String, ToString};
 use core::fmt;
 
 pub use crate::sys::console::{EOT_KEY, ETX_KEY};
@@ -108,6 +108,22 @@ pub fn is_printable(c: char) -> bool {
     }
 }
 
+// Remove the color escape sequences of a line, for example when reading
+// back the output of a command saved into a file
+pub fn strip_escapes(line: &str) -> String {
+    let mut res = String::new();
+    let mut escape = false;
+    for c in line.chars() {
+        match c {
+            '\x1B' => escape = true,
+            'm' if escape => escape = false,
+            _ if escape => {}
+            _ => res.push(c),
+        }
+    }
+    res
+}
+
 // The size of the screen in VGA Text Mode is 80x25
 
 pub fn cols() -> usize {
@@ -0,0 +1,477 @@
+// See RFC 2104 (HMAC), RFC 5869 (HKDF), RFC 7748 (X25519) and RFC 8439
+// (ChaCha20 and Poly1305)
+
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use sha2::{Digest, Sha256};
+
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+pub fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut k = [0; 64];
+    if key.len() > 64 {
+        k[0..32].copy_from_slice(&sha256(key));
+    } else {
+        k[0..key.len()].copy_from_slice(key);
+    }
+    let mut inner = Sha256::new();
+    inner.update(k.map(|b| b ^ 0x36));
+    inner.update(data);
+    let mut outer = Sha256::new();
+    outer.update(k.map(|b| b ^ 0x5C));
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
+
+pub fn hkdf_extract(salt: &[u8], ikm: &[u8]) -> [u8; 32] {
+    hmac_sha256(salt, ikm)
+}
+
+pub fn hkdf_expand(prk: &[u8], info: &[u8], len: usize) -> Vec<u8> {
+    let mut res = Vec::with_capacity(len);
+    let mut block = Vec::new();
+    let mut i = 1;
+    while res.len() < len {
+        block.extend_from_slice(info);
+        block.push(i);
+        block = hmac_sha256(prk, &block).to_vec();
+        res.extend_from_slice(&block);
+        i += 1;
+    }
+    res.truncate(len);
+    res
+}
+
+// ChaCha20
+
+fn quarter_round(s: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    s[a] = s[a].wrapping_add(s[b]); s[d] = (s[d] ^ s[a]).rotate_left(16);
+    s[c] = s[c].wrapping_add(s[d]); s[b] = (s[b] ^ s[c]).rotate_left(12);
+    s[a] = s[a].wrapping_add(s[b]); s[d] = (s[d] ^ s[a]).rotate_left(8);
+    s[c] = s[c].wrapping_add(s[d]); s[b] = (s[b] ^ s[c]).rotate_left(7);
+}
+
+fn chacha20_block(key: &[u8; 32], counter: u32, nonce: &[u8; 12]) -> [u8; 64] {
+    let mut state = [0; 16];
+    state[0..4].copy_from_slice(&[
+        0x61707865, 0x3320646E, 0x79622D32, 0x6B206574
+    ]);
+    for i in 0..8 {
+        state[4 + i] = le32(&key[i * 4..]);
+    }
+    state[12] = counter;
+    for i in 0..3 {
+        state[13 + i] = le32(&nonce[i * 4..]);
+    }
+    let mut s = state;
+    for _ in 0..10 {
+        quarter_round(&mut s, 0, 4, 8, 12);
+        quarter_round(&mut s, 1, 5, 9, 13);
+        quarter_round(&mut s, 2, 6, 10, 14);
+        quarter_round(&mut s, 3, 7, 11, 15);
+        quarter_round(&mut s, 0, 5, 10, 15);
+        quarter_round(&mut s, 1, 6, 11, 12);
+        quarter_round(&mut s, 2, 7, 8, 13);
+        quarter_round(&mut s, 3, 4, 9, 14);
+    }
+    let mut res = [0; 64];
+    for i in 0..16 {
+        let word = s[i].wrapping_add(state[i]);
+        res[i * 4..i * 4 + 4].copy_from_slice(&word.to_le_bytes());
+    }
+    res
+}
+
+pub fn chacha20(
+    key: &[u8; 32], counter: u32, nonce: &[u8; 12], buf: &mut [u8]
+) {
+    for (i, chunk) in buf.chunks_mut(64).enumerate() {
+        let block = chacha20_block(key, counter + i as u32, nonce);
+        for (b, k) in chunk.iter_mut().zip(block.iter()) {
+            *b ^= k;
+        }
+    }
+}
+
+// Poly1305
+
+pub fn poly1305(key: &[u8; 32], msg: &[u8]) -> [u8; 16] {
+    let mask = 0x3FFFFFF;
+    let r0 = le32(&key[0..]) & 0x3FFFFFF;
+    let r1 = (le32(&key[3..]) >> 2) & 0x3FFFF03;
+    let r2 = (le32(&key[6..]) >> 4) & 0x3FFC0FF;
+    let r3 = (le32(&key[9..]) >> 6) & 0x3F03FFF;
+    let r4 = (le32(&key[12..]) >> 8) & 0x00FFFFF;
+    let (s1, s2, s3, s4) = (r1 * 5, r2 * 5, r3 * 5, r4 * 5);
+    let (r0, r1, r2, r3, r4) = (
+        r0 as u64, r1 as u64, r2 as u64, r3 as u64, r4 as u64
+    );
+    let (s1, s2, s3, s4) = (s1 as u64, s2 as u64, s3 as u64, s4 as u64);
+    let mut h = [0u32; 5];
+
+    for chunk in msg.chunks(16) {
+        let mut m = [0; 17];
+        m[0..chunk.len()].copy_from_slice(chunk);
+        let hibit = if chunk.len() == 16 {
+            1 << 24
+        } else {
+            m[chunk.len()] = 1;
+            0
+        };
+        h[0] += le32(&m[0..]) & mask;
+        h[1] += (le32(&m[3..]) >> 2) & mask;
+        h[2] += (le32(&m[6..]) >> 4) & mask;
+        h[3] += (le32(&m[9..]) >> 6) & mask;
+        h[4] += (le32(&m[12..]) >> 8) | hibit;
+
+        let (h0, h1, h2, h3, h4) = (
+            h[0] as u64, h[1] as u64, h[2] as u64, h[3] as u64, h[4] as u64
+        );
+        let d0 = h0 * r0 + h1 * s4 + h2 * s3 + h3 * s2 + h4 * s1;
+        let mut d1 = h0 * r1 + h1 * r0 + h2 * s4 + h3 * s3 + h4 * s2;
+        let mut d2 = h0 * r2 + h1 * r1 + h2 * r0 + h3 * s4 + h4 * s3;
+        let mut d3 = h0 * r3 + h1 * r2 + h2 * r1 + h3 * r0 + h4 * s4;
+        let mut d4 = h0 * r4 + h1 * r3 + h2 * r2 + h3 * r1 + h4 * r0;
+
+        d1 += d0 >> 26;
+        d2 += d1 >> 26;
+        d3 += d2 >> 26;
+        d4 += d3 >> 26;
+        let d0 = (d0 & 0x3FFFFFF) + (d4 >> 26) * 5;
+        h[0] = (d0 as u32) & mask;
+        h[1] = ((d1 as u32) & mask) + ((d0 >> 26) as u32);
+        h[2] = (d2 as u32) & mask;
+        h[3] = (d3 as u32) & mask;
+        h[4] = (d4 as u32) & mask;
+    }
+
+    // Fully carry h
+    for i in 1..5 {
+        h[i] += h[i - 1] >> 26;
+        h[i - 1] &= mask;
+    }
+    h[0] += (h[4] >> 26) * 5;
+    h[4] &= mask;
+    h[1] += h[0] >> 26;
+    h[0] &= mask;
+
+    // Compute h - p and select it if it is not negative
+    let mut g = [0u32; 5];
+    let mut c = 5;
+    for i in 0..5 {
+        g[i] = h[i].wrapping_add(c);
+        c = g[i] >> 26;
+        g[i] &= mask;
+    }
+    g[4] = g[4].wrapping_add(c << 26).wrapping_sub(1 << 26);
+    let select = (g[4] >> 31).wrapping_sub(1);
+    for i in 0..5 {
+        h[i] = (h[i] & !select) | (g[i] & select);
+    }
+
+    // Add the pad
+    let words = [
+        h[0] | (h[1] << 26),
+        (h[1] >> 6) | (h[2] << 20),
+        (h[2] >> 12) | (h[3] << 14),
+        (h[3] >> 18) | (h[4] << 8),
+    ];
+    let mut res = [0; 16];
+    let mut f = 0u64;
+    for i in 0..4 {
+        f = (words[i] as u64) + (le32(&key[16 + i * 4..]) as u64) + (f >> 32);
+        res[i * 4..i * 4 + 4].copy_from_slice(&(f as u32).to_le_bytes());
+    }
+    res
+}
+
+// ChaCha20-Poly1305 AEAD
+
+fn aead_tag(
+    key: &[u8; 32], nonce: &[u8; 12], aad: &[u8], ct: &[u8]
+) -> [u8; 16] {
+    let mut otk = [0; 32];
+    otk.copy_from_slice(&chacha20_block(key, 0, nonce)[0..32]);
+    let mut data = Vec::with_capacity(aad.len() + ct.len() + 32);
+    data.extend_from_slice(aad);
+    data.resize((data.len() + 15) / 16 * 16, 0);
+    data.extend_from_slice(ct);
+    data.resize((data.len() + 15) / 16 * 16, 0);
+    data.extend_from_slice(&(aad.len() as u64).to_le_bytes());
+    data.extend_from_slice(&(ct.len() as u64).to_le_bytes());
+    poly1305(&otk, &data)
+}
+
+// Encrypt the buffer in place and append the authentication tag
+pub fn seal(key: &[u8; 32], nonce: &[u8; 12], aad: &[u8], buf: &mut Vec<u8>) {
+    chacha20(key, 1, nonce, buf);
+    let tag = aead_tag(key, nonce, aad, buf);
+    buf.extend_from_slice(&tag);
+}
+
+// Check the authentication tag at the end of the buffer and decrypt it
+pub fn open(
+    key: &[u8; 32], nonce: &[u8; 12], aad: &[u8], buf: &mut Vec<u8>
+) -> Result<(), ()> {
+    if buf.len() < 16 {
+        return Err(());
+    }
+    let n = buf.len() - 16;
+    let tag = aead_tag(key, nonce, aad, &buf[..n]);
+    let diff = tag.iter().zip(&buf[n..]).fold(0, |acc, (a, b)| acc | (a ^ b));
+    if diff != 0 {
+        return Err(());
+    }
+    buf.truncate(n);
+    chacha20(key, 1, nonce, buf);
+    Ok(())
+}
+
+// X25519 with field elements made of 16 limbs of 16 bits
+
+type Field = [i64; 16];
+
+fn carry(o: &mut Field) {
+    for i in 0..16 {
+        o[i] += 1 << 16;
+        let c = o[i] >> 16;
+        if i < 15 {
+            o[i + 1] += c - 1;
+        } else {
+            o[0] += 38 * (c - 1);
+        }
+        o[i] -= c << 16;
+    }
+}
+
+fn swap(p: &mut Field, q: &mut Field, b: i64) {
+    let c = !(b - 1);
+    for i in 0..16 {
+        let t = c & (p[i] ^ q[i]);
+        p[i] ^= t;
+        q[i] ^= t;
+    }
+}
+
+fn pack(n: &Field) -> [u8; 32] {
+    let mut t = *n;
+    carry(&mut t);
+    carry(&mut t);
+    carry(&mut t);
+    for _ in 0..2 {
+        let mut m = [0; 16];
+        m[0] = t[0] - 0xFFED;
+        for i in 1..15 {
+            m[i] = t[i] - 0xFFFF - ((m[i - 1] >> 16) & 1);
+            m[i - 1] &= 0xFFFF;
+        }
+        m[15] = t[15] - 0x7FFF - ((m[14] >> 16) & 1);
+        let b = (m[15] >> 16) & 1;
+        m[14] &= 0xFFFF;
+        swap(&mut t, &mut m, 1 - b);
+    }
+    let mut res = [0; 32];
+    for i in 0..16 {
+        res[2 * i] = t[i] as u8;
+        res[2 * i + 1] = (t[i] >> 8) as u8;
+    }
+    res
+}
+
+fn unpack(n: &[u8; 32]) -> Field {
+    let mut res = [0; 16];
+    for i in 0..16 {
+        res[i] = (n[2 * i] as i64) + ((n[2 * i + 1] as i64) << 8);
+    }
+    res[15] &= 0x7FFF;
+    res
+}
+
+fn add(a: &Field, b: &Field) -> Field {
+    let mut res = [0; 16];
+    for i in 0..16 {
+        res[i] = a[i] + b[i];
+    }
+    res
+}
+
+fn sub(a: &Field, b: &Field) -> Field {
+    let mut res = [0; 16];
+    for i in 0..16 {
+        res[i] = a[i] - b[i];
+    }
+    res
+}
+
+fn mul(a: &Field, b: &Field) -> Field {
+    let mut t = [0; 31];
+    for i in 0..16 {
+        for j in 0..16 {
+            t[i + j] += a[i] * b[j];
+        }
+    }
+    for i in 0..15 {
+        t[i] += 38 * t[i + 16];
+    }
+    let mut res = [0; 16];
+    res.copy_from_slice(&t[0..16]);
+    carry(&mut res);
+    carry(&mut res);
+    res
+}
+
+fn inv(a: &Field) -> Field {
+    let mut c = *a;
+    for i in (0..254).rev() {
+        c = mul(&c, &c);
+        if i != 2 && i != 4 {
+            c = mul(&c, a);
+        }
+    }
+    c
+}
+
+pub fn x25519(scalar: &[u8; 32], point: &[u8; 32]) -> [u8; 32] {
+    let mut z = *scalar;
+    z[31] = (z[31] & 127) | 64;
+    z[0] &= 248;
+    let x = unpack(point);
+    let mut a = [0; 16];
+    let mut b = x;
+    let mut c = [0; 16];
+    let mut d = [0; 16];
+    let mut k = [0; 16];
+    a[0] = 1;
+    d[0] = 1;
+    k[0] = 0xDB41;
+    k[1] = 1;
+    for i in (0..255).rev() {
+        let r = ((z[i >> 3] >> (i & 7)) & 1) as i64;
+        swap(&mut a, &mut b, r);
+        swap(&mut c, &mut d, r);
+        let e = add(&a, &c);
+        a = sub(&a, &c);
+        c = add(&b, &d);
+        b = sub(&b, &d);
+        d = mul(&e, &e);
+        let f = mul(&a, &a);
+        a = mul(&c, &a);
+        c = mul(&b, &e);
+        let e = add(&a, &c);
+        a = sub(&a, &c);
+        b = mul(&a, &a);
+        c = sub(&d, &f);
+        a = mul(&c, &k);
+        a = add(&a, &d);
+        c = mul(&c, &a);
+        a = mul(&d, &f);
+        d = mul(&b, &x);
+        b = mul(&e, &e);
+        swap(&mut a, &mut b, r);
+        swap(&mut c, &mut d, r);
+    }
+    pack(&mul(&a, &inv(&c)))
+}
+
+pub fn x25519_base(scalar: &[u8; 32]) -> [u8; 32] {
+    let mut base = [0; 32];
+    base[0] = 9;
+    x25519(scalar, &base)
+}
+
+fn le32(buf: &[u8]) -> u32 {
+    u32::from_le_bytes(buf[0..4].try_into().unwrap())
+}
+
+#[cfg(test)]
+fn hex(s: &str) -> Vec<u8> {
+    (0..s.len()).step_by(2).map(|i| {
+        u8::from_str_radix(&s[i..i + 2], 16).unwrap()
+    }).collect()
+}
+
+#[test_case]
+fn test_hmac_sha256() {
+    // RFC 4231 test case 2
+    let mac = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+    assert_eq!(mac.to_vec(), hex(
+        "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    ));
+}
+
+#[test_case]
+fn test_hkdf() {
+    // RFC 5869 test case 1
+    let ikm = [0x0B; 22];
+    let salt = hex("000102030405060708090a0b0c");
+    let info = hex("f0f1f2f3f4f5f6f7f8f9");
+    let prk = hkdf_extract(&salt, &ikm);
+    assert_eq!(prk.to_vec(), hex(
+        "077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5"
+    ));
+    assert_eq!(hkdf_expand(&prk, &info, 42), hex(
+        "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf\
+         34007208d5b887185865"
+    ));
+}
+
+#[test_case]
+fn test_chacha20_poly1305() {
+    // RFC 8439 section 2.8.2
+    let key: [u8; 32] = hex(
+        "808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f"
+    ).try_into().unwrap();
+    let nonce: [u8; 12] = hex("070000004041424344454647").try_into().unwrap();
+    let aad = hex("50515253c0c1c2c3c4c5c6c7");
+    let msg = b"Ladies and Gentlemen of the class of '99: If I could offer \
+        you only one tip for the future, sunscreen would be it.";
+    let mut buf = msg.to_vec();
+    seal(&key, &nonce, &aad, &mut buf);
+    assert_eq!(buf[msg.len()..].to_vec(), hex(
+        "1ae10b594f09e26a7e902ecbd0600691"
+    ));
+    assert_eq!(buf[0..16].to_vec(), hex("d31a8d34648e60db7b86afbc53ef7ec2"));
+    assert!(open(&key, &nonce, &aad, &mut buf).is_ok());
+    assert_eq!(buf, msg.to_vec());
+
+    buf = msg.to_vec();
+    seal(&key, &nonce, &aad, &mut buf);
+    buf[0] ^= 1;
+    assert!(open(&key, &nonce, &aad, &mut buf).is_err());
+}
+
+#[test_case]
+fn test_poly1305() {
+    // RFC 8439 section 2.5.2
+    let key: [u8; 32] = hex(
+        "85d6be7857556d337f4452fe42d506a80103808afb0db2fd4abff6af4149f51b"
+    ).try_into().unwrap();
+    let tag = poly1305(&key, b"Cryptographic Forum Research Group");
+    assert_eq!(tag.to_vec(), hex("a8061dc1305136c6c22b8baf0c0127a9"));
+}
+
+#[test_case]
+fn test_x25519() {
+    // RFC 7748 section 6.1
+    let a: [u8; 32] = hex(
+        "77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a"
+    ).try_into().unwrap();
+    let b: [u8; 32] = hex(
+        "5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb"
+    ).try_into().unwrap();
+    let pub_a = x25519_base(&a);
+    let pub_b = x25519_base(&b);
+    assert_eq!(pub_a.to_vec(), hex(
+        "8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a"
+    ));
+    assert_eq!(pub_b.to_vec(), hex(
+        "de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f"
+    ));
+    let shared = x25519(&a, &pub_b);
+    assert_eq!(shared, x25519(&b, &pub_a));
+    assert_eq!(shared.to_vec(), hex(
+        "4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742"
+    ));
+}
@@ -7,7 +7,9 @@ use alloc::string::{String, ToString};
 use alloc::vec;
 use alloc::vec::Vec;
 
-pub use crate::sys::fs::{DeviceType, FileInfo};
+pub use crate::sys::fs::{
+    DeviceType, FileInfo, FileType, Permission, SeekFrom
+};
 
 #[derive(Clone, Copy)]
 pub enum IO {
@@ -64,10 +66,68 @@ pub fn is_dir(path: &str) -> bool {
     }
 }
 
+pub fn is_file(path: &str) -> bool {
+    if let Some(info) = syscall::info(path) {
+        info.is_file()
+    } else {
+        false
+    }
+}
+
+pub fn is_device(path: &str) -> bool {
+    if let Some(info) = syscall::info(path) {
+        info.is_device()
+    } else {
+        false
+    }
+}
+
+pub fn is_executable(path: &str) -> bool {
+    if let Some(info) = syscall::info(path) {
+        Permission::Exec.is_granted(&info)
+    } else {
+        false
+    }
+}
+
+pub fn is_link(path: &str) -> bool {
+    syscall::read_link(path).is_some()
+}
+
 pub fn delete(path: &str) -> Result<(), ()> {
     syscall::delete(path)
 }
 
+pub fn symlink(target: &str, path: &str) -> Result<(), ()> {
+    syscall::symlink(target, path)
+}
+
+pub fn link(source: &str, path: &str) -> Result<(), ()> {
+    syscall::link(source, path)
+}
+
+// Move a file to another path, replacing the file already there if any
+pub fn rename(source: &str, dest: &str) -> Result<(), ()> {
+    syscall::rename(source, dest)
+}
+
+pub fn chmod(path: &str, mode: u16) -> Result<(), ()> {
+    syscall::chmod(path, mode)
+}
+
+pub fn chown(path: &str, uid: u16, gid: u16) -> Result<(), ()> {
+    syscall::chown(path, uid, gid)
+}
+
+// Set the access and modification times of a file in seconds since Unix Epoch
+pub fn utime(path: &str, atime: u64, mtime: u64) -> Result<(), ()> {
+    syscall::utime(path, atime, mtime)
+}
+
+pub fn read_link(path: &str) -> Option<String> {
+    syscall::read_link(path)
+}
+
 pub fn open_file(path: &str) -> Option<usize> {
     let flags = 0;
     syscall::open(path, flags)
@@ -78,8 +138,9 @@ pub fn append_file(path: &str) -> Option<usize> {
     syscall::open(path, flags)
 }
 
+// An existing file is truncated
 pub fn create_file(path: &str) -> Option<usize> {
-    let flags = OpenFlag::Create as usize;
+    let flags = OpenFlag::Create as usize | OpenFlag::Truncate as usize;
     syscall::open(path, flags)
 }
 
@@ -153,8 +214,9 @@ pub fn read_to_bytes(path: &str) -> Result<Vec<u8>, ()> {
 
 pub fn write(path: &str, buf: &[u8]) -> Result<usize, ()> {
     if let Some(handle) = create_file(path) {
-        if let Some(bytes) = syscall::write(handle, buf) {
-            syscall::close(handle);
+        let res = syscall::write(handle, buf);
+        syscall::close(handle);
+        if let Some(bytes) = res {
             return Ok(bytes);
         }
     }
@@ -172,8 +234,9 @@ pub fn append(path: &str, buf: &[u8]) -> Result<usize, ()> {
         create_file(path)
     };
     if let Some(handle) = res {
-        if let Some(bytes) = syscall::write(handle, buf) {
-            syscall::close(handle);
+        let res = syscall::write(handle, buf);
+        syscall::close(handle);
+        if let Some(bytes) = res {
             return Ok(bytes);
         }
     }
@@ -189,6 +252,9 @@ pub fn reopen(path: &str, handle: usize, append: bool) -> Result<usize, ()> {
         } else {
             open_file(path)
         }
+    } else if append {
+        let flags = OpenFlag::Create as usize | OpenFlag::Append as usize;
+        syscall::open(path, flags)
     } else {
         create_file(path)
     };
@@ -207,8 +273,9 @@ pub fn read_dir(path: &str) -> Result<Vec<FileInfo>, ()> {
                 let mut res = Vec::new();
                 let mut i = 0;
                 let n = buf.len();
-                while i < n {
-                    let j = i + 14 + buf[i + 13] as usize;
+                let len = FileInfo::empty_len();
+                while i + len <= n {
+                    let j = i + len + buf[i + len - 1] as usize;
                     if j > n {
                         break;
                     }
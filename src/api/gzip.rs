@@ -0,0 +1,442 @@
+// See RFC 1951 (DEFLATE) and RFC 1952 (GZIP)
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+
+const LEN_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59,
+    67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+
+const LEN_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4,
+    5, 5, 5, 5, 0,
+];
+
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513,
+    769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10,
+    11, 11, 12, 12, 13, 13,
+];
+
+// Order of the code lengths of the code length alphabet in dynamic blocks
+const CLEN_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+const WINDOW_SIZE: usize = 32768;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const MAX_CHAIN: usize = 128;
+const HASH_SIZE: usize = 1 << 15;
+
+const ID1: u8 = 0x1F;
+const ID2: u8 = 0x8B;
+const FHCRC: u8 = 1 << 1;
+const FEXTRA: u8 = 1 << 2;
+const FNAME: u8 = 1 << 3;
+const FCOMMENT: u8 = 1 << 4;
+
+pub fn is_gzip(buf: &[u8]) -> bool {
+    buf.len() > 2 && buf[0] == ID1 && buf[1] == ID2
+}
+
+pub fn compress(buf: &[u8]) -> Vec<u8> {
+    let mut res = vec![ID1, ID2, 8, 0, 0, 0, 0, 0, 0, 255];
+    res.extend_from_slice(&deflate(buf));
+    res.extend_from_slice(&crc32(buf).to_le_bytes());
+    res.extend_from_slice(&(buf.len() as u32).to_le_bytes());
+    res
+}
+
+pub fn decompress(buf: &[u8]) -> Result<Vec<u8>, ()> {
+    if buf.len() < 18 || !is_gzip(buf) || buf[2] != 8 {
+        return Err(());
+    }
+    let flags = buf[3];
+    let mut i = 10;
+    if flags & FEXTRA != 0 {
+        let n = u16::from_le_bytes([buf[i], buf[i + 1]]) as usize;
+        i += 2 + n;
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            while *buf.get(i).ok_or(())? != 0 {
+                i += 1;
+            }
+            i += 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        i += 2;
+    }
+    let (res, n) = inflate(buf.get(i..).ok_or(())?)?;
+    let trailer = buf.get(i + n..i + n + 8).ok_or(())?;
+    let crc = u32::from_le_bytes(trailer[0..4].try_into().unwrap());
+    let len = u32::from_le_bytes(trailer[4..8].try_into().unwrap());
+    if crc != crc32(&res) || len != res.len() as u32 {
+        return Err(());
+    }
+    Ok(res)
+}
+
+pub fn crc32(buf: &[u8]) -> u32 {
+    let mut crc = 0xFFFFFFFF;
+    for byte in buf {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (!(crc & 1)).wrapping_add(1);
+            crc = (crc >> 1) ^ (0xEDB88320 & mask);
+        }
+    }
+    !crc
+}
+
+struct BitReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    bit: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0, bit: 0 }
+    }
+
+    fn bits(&mut self, n: usize) -> Result<u32, ()> {
+        let mut res = 0;
+        for i in 0..n {
+            let byte = *self.buf.get(self.pos).ok_or(())?;
+            res |= (((byte >> self.bit) & 1) as u32) << i;
+            self.bit += 1;
+            if self.bit == 8 {
+                self.bit = 0;
+                self.pos += 1;
+            }
+        }
+        Ok(res)
+    }
+
+    fn align(&mut self) {
+        if self.bit > 0 {
+            self.bit = 0;
+            self.pos += 1;
+        }
+    }
+}
+
+// Canonical Huffman code described by the number of codes of each length
+// and the symbols sorted by code.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0; 16];
+        for len in lengths {
+            counts[*len as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0; 16];
+        for len in 1..15 {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, len) in lengths.iter().enumerate() {
+            if *len > 0 {
+                symbols[offsets[*len as usize] as usize] = symbol as u16;
+                offsets[*len as usize] += 1;
+            }
+        }
+        Self { counts, symbols }
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, ()> {
+        let mut code = 0;
+        let mut first = 0;
+        let mut index = 0;
+        for len in 1..16 {
+            code |= reader.bits(1)? as i32;
+            let count = self.counts[len] as i32;
+            if code - count < first {
+                let i = (index + code - first) as usize;
+                return self.symbols.get(i).copied().ok_or(());
+            }
+            index += count;
+            first += count;
+            first <<= 1;
+            code <<= 1;
+        }
+        Err(())
+    }
+}
+
+fn fixed_lengths() -> (Vec<u8>, Vec<u8>) {
+    let mut lit = vec![8; 288];
+    lit[144..256].fill(9);
+    lit[256..280].fill(7);
+    (lit, vec![5; 30])
+}
+
+// Decode a DEFLATE stream and return the data with the size of the stream
+fn inflate(buf: &[u8]) -> Result<(Vec<u8>, usize), ()> {
+    let mut res = Vec::new();
+    let mut reader = BitReader::new(buf);
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align();
+                let i = reader.pos;
+                let header = buf.get(i..i + 4).ok_or(())?;
+                let len = u16::from_le_bytes([header[0], header[1]]);
+                let nlen = u16::from_le_bytes([header[2], header[3]]);
+                if len ^ nlen != 0xFFFF {
+                    return Err(());
+                }
+                let n = len as usize;
+                res.extend_from_slice(buf.get(i + 4..i + 4 + n).ok_or(())?);
+                reader.pos += 4 + n;
+            }
+            1 => {
+                let (lit, dist) = fixed_lengths();
+                let lit = Huffman::new(&lit);
+                let dist = Huffman::new(&dist);
+                inflate_block(&mut reader, &mut res, &lit, &dist)?;
+            }
+            2 => {
+                let (lit, dist) = read_dynamic_tables(&mut reader)?;
+                inflate_block(&mut reader, &mut res, &lit, &dist)?;
+            }
+            _ => return Err(()),
+        }
+        if last {
+            break;
+        }
+    }
+    reader.align();
+    Ok((res, reader.pos))
+}
+
+fn read_dynamic_tables(
+    reader: &mut BitReader
+) -> Result<(Huffman, Huffman), ()> {
+    let nlen = reader.bits(5)? as usize + 257;
+    let ndist = reader.bits(5)? as usize + 1;
+    let ncode = reader.bits(4)? as usize + 4;
+    if nlen > 286 || ndist > 30 {
+        return Err(());
+    }
+    let mut lengths = [0; 19];
+    for i in CLEN_ORDER.iter().take(ncode) {
+        lengths[*i] = reader.bits(3)? as u8;
+    }
+    let clen = Huffman::new(&lengths);
+
+    let mut lengths = Vec::with_capacity(nlen + ndist);
+    while lengths.len() < nlen + ndist {
+        let symbol = clen.decode(reader)?;
+        let (len, n) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => (*lengths.last().ok_or(())?, 3 + reader.bits(2)?),
+            17 => (0, 3 + reader.bits(3)?),
+            18 => (0, 11 + reader.bits(7)?),
+            _ => return Err(()),
+        };
+        for _ in 0..n {
+            lengths.push(len);
+        }
+    }
+    if lengths.len() > nlen + ndist {
+        return Err(());
+    }
+    let lit = Huffman::new(&lengths[..nlen]);
+    let dist = Huffman::new(&lengths[nlen..]);
+    Ok((lit, dist))
+}
+
+fn inflate_block(
+    reader: &mut BitReader,
+    res: &mut Vec<u8>,
+    lit: &Huffman,
+    dist: &Huffman
+) -> Result<(), ()> {
+    loop {
+        let symbol = lit.decode(reader)? as usize;
+        match symbol {
+            0..=255 => res.push(symbol as u8),
+            256 => return Ok(()),
+            257..=285 => {
+                let i = symbol - 257;
+                let extra = reader.bits(LEN_EXTRA[i] as usize)?;
+                let len = LEN_BASE[i] as usize + extra as usize;
+                let i = dist.decode(reader)? as usize;
+                if i >= DIST_BASE.len() {
+                    return Err(());
+                }
+                let extra = reader.bits(DIST_EXTRA[i] as usize)?;
+                let d = DIST_BASE[i] as usize + extra as usize;
+                if d > res.len() {
+                    return Err(());
+                }
+                let start = res.len() - d;
+                for j in 0..len {
+                    res.push(res[start + j]);
+                }
+            }
+            _ => return Err(()),
+        }
+    }
+}
+
+struct BitWriter {
+    buf: Vec<u8>,
+    bits: u32,
+    n: usize,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        Self { buf: Vec::new(), bits: 0, n: 0 }
+    }
+
+    // Write the lowest bits of the value starting from the least significant
+    fn write(&mut self, value: u32, n: usize) {
+        for i in 0..n {
+            self.bits |= ((value >> i) & 1) << self.n;
+            self.n += 1;
+            if self.n == 8 {
+                self.buf.push(self.bits as u8);
+                self.bits = 0;
+                self.n = 0;
+            }
+        }
+    }
+
+    // Huffman codes are written starting from the most significant bit
+    fn write_code(&mut self, code: u32, n: usize) {
+        let reversed = code.reverse_bits() >> (32 - n);
+        self.write(reversed, n);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.n > 0 {
+            self.buf.push(self.bits as u8);
+        }
+        self.buf
+    }
+}
+
+// Write a literal or a length symbol with the fixed Huffman code
+fn write_symbol(writer: &mut BitWriter, symbol: u32) {
+    match symbol {
+        0..=143 => writer.write_code(0x30 + symbol, 8),
+        144..=255 => writer.write_code(0x190 + symbol - 144, 9),
+        256..=279 => writer.write_code(symbol - 256, 7),
+        _ => writer.write_code(0xC0 + symbol - 280, 8),
+    }
+}
+
+fn write_match(writer: &mut BitWriter, len: usize, dist: usize) {
+    let i = LEN_BASE.iter().rposition(|base| *base as usize <= len).unwrap();
+    write_symbol(writer, 257 + i as u32);
+    writer.write((len - LEN_BASE[i] as usize) as u32, LEN_EXTRA[i] as usize);
+    let i = DIST_BASE.iter().rposition(|base| *base as usize <= dist).unwrap();
+    writer.write_code(i as u32, 5);
+    writer.write((dist - DIST_BASE[i] as usize) as u32, DIST_EXTRA[i] as usize);
+}
+
+fn hash(buf: &[u8], i: usize) -> usize {
+    let h = (buf[i] as usize) << 10 ^ (buf[i + 1] as usize) << 5 ^
+        buf[i + 2] as usize;
+    h % HASH_SIZE
+}
+
+// Add the position to the hash chains of the previous positions
+fn insert(buf: &[u8], i: usize, head: &mut [usize], prev: &mut [usize]) {
+    if i + MIN_MATCH <= buf.len() {
+        let h = hash(buf, i);
+        prev[i] = head[h];
+        head[h] = i;
+    }
+}
+
+// Encode the data into a single block using LZ77 with hash chains to find
+// the matches and the fixed Huffman code.
+fn deflate(buf: &[u8]) -> Vec<u8> {
+    let n = buf.len();
+    let mut writer = BitWriter::new();
+    writer.write(1, 1); // Last block
+    writer.write(1, 2); // Fixed Huffman code
+
+    let mut head = vec![usize::MAX; HASH_SIZE];
+    let mut prev = vec![usize::MAX; n];
+    let mut i = 0;
+    while i < n {
+        let mut best_len = 0;
+        let mut best_dist = 0;
+        if i + MIN_MATCH <= n {
+            let max = (n - i).min(MAX_MATCH);
+            let mut j = head[hash(buf, i)];
+            let mut chain = 0;
+            while j != usize::MAX && i - j <= WINDOW_SIZE && chain < MAX_CHAIN {
+                let len = (0..max).take_while(|k| {
+                    buf[j + k] == buf[i + k]
+                }).count();
+                if len > best_len {
+                    best_len = len;
+                    best_dist = i - j;
+                    if len == max {
+                        break;
+                    }
+                }
+                j = prev[j];
+                chain += 1;
+            }
+        }
+        if best_len >= MIN_MATCH {
+            write_match(&mut writer, best_len, best_dist);
+            for k in i..i + best_len {
+                insert(buf, k, &mut head, &mut prev);
+            }
+            i += best_len;
+        } else {
+            write_symbol(&mut writer, buf[i] as u32);
+            insert(buf, i, &mut head, &mut prev);
+            i += 1;
+        }
+    }
+    write_symbol(&mut writer, 256);
+    writer.finish()
+}
+
+#[test_case]
+fn test_gzip() {
+    assert_eq!(crc32(b""), 0);
+    assert_eq!(crc32(b"123456789"), 0xCBF43926);
+
+    // Generated with `printf "Hello, World!\n" | gzip -n`
+    let buf = [
+        0x1F, 0x8B, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xF3, 0x48,
+        0xCD, 0xC9, 0xC9, 0xD7, 0x51, 0x08, 0xCF, 0x2F, 0xCA, 0x49, 0x51, 0xE4,
+        0x02, 0x00, 0x84, 0x9E, 0xE8, 0xB4, 0x0E, 0x00, 0x00, 0x00,
+    ];
+    assert_eq!(decompress(&buf), Ok(b"Hello, World!\n".to_vec()));
+    assert!(decompress(&buf[..20]).is_err());
+
+    let mut buf = Vec::new();
+    for i in 0..1000 {
+        buf.extend_from_slice(&(i % 64 * 7u32).to_le_bytes());
+    }
+    let compressed = compress(&buf);
+    assert!(compressed.len() < buf.len() / 4);
+    assert_eq!(decompress(&compressed), Ok(buf));
+    assert_eq!(decompress(&compress(b"")), Ok(Vec::new()));
+}
@@ -1,3 +1,4 @@
+use crate::api::console;
 use crate::api::syscall;
 
 use alloc::string::{String, ToString};
@@ -33,6 +34,24 @@ impl Stdin {
             String::new()
         }
     }
+
+    // Read until the end of a redirected file or until ^D is typed on the
+    // console.
+    pub fn read_to_string(&self) -> String {
+        let mut res = String::new();
+        loop {
+            let line = self.read_line();
+            if line.is_empty() {
+                break;
+            }
+            if let Some(i) = line.find(console::EOT_KEY) {
+                res.push_str(&line[..i]);
+                break;
+            }
+            res.push_str(&line);
+        }
+        res
+    }
 }
 
 impl Stdout {
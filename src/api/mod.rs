@@ -11,7 +11,13 @@ macro_rules! entry_point {
         }
 
         #[export_name = "_start"]
-        pub unsafe extern "sysv64" fn __impl_start(ptr: u64, len: usize) {
+        pub unsafe extern "sysv64" fn __impl_start(
+            ptr: u64,
+            len: usize,
+            env_ptr: u64,
+            env_len: usize
+        ) {
+            $crate::api::process::init_env(env_ptr, env_len);
             let args = core::slice::from_raw_parts(ptr as *const _, len);
             let f: fn(&[&str]) = $path;
             f(args);
@@ -84,8 +90,10 @@ macro_rules! warning {
 pub mod allocator;
 pub mod clock;
 pub mod console;
+pub mod crypto;
 pub mod font;
 pub mod fs;
+pub mod gzip;
 pub mod io;
 pub mod process;
 pub mod prompt;
@@ -93,6 +101,7 @@ pub mod rng;
 pub mod regex;
 pub mod syscall;
 pub mod time;
+pub mod tls;
 pub mod unit;
 pub mod vga;
 // TODO: add mod wildcard
@@ -1,5 +1,10 @@
 use crate::api::syscall;
 
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+static ENV_PTR: AtomicU64 = AtomicU64::new(0);
+static ENV_LEN: AtomicUsize = AtomicUsize::new(0);
+
 #[derive(Copy, Clone, PartialEq, Eq)]
 #[repr(u8)]
 pub enum ExitCode {
@@ -37,3 +42,25 @@ pub fn spawn(path: &str, args: &[&str]) -> Result<(), ExitCode> {
         Err(ExitCode::OpenError)
     }
 }
+
+// Called by the entry point of a program with the environment block given
+// by the kernel, which is a list of `KEY=VALUE` strings.
+pub fn init_env(ptr: u64, len: usize) {
+    ENV_PTR.store(ptr, Ordering::SeqCst);
+    ENV_LEN.store(len, Ordering::SeqCst);
+}
+
+pub fn envs() -> &'static [&'static str] {
+    let ptr = ENV_PTR.load(Ordering::SeqCst);
+    let len = ENV_LEN.load(Ordering::SeqCst);
+    if ptr == 0 {
+        return &[];
+    }
+    unsafe { core::slice::from_raw_parts(ptr as *const &str, len) }
+}
+
+pub fn env(key: &str) -> Option<&'static str> {
+    envs().iter().find_map(|var| {
+        var.strip_prefix(key).and_then(|s| s.strip_prefix('='))
+    })
+}
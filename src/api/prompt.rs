@@ -1,5 +1,6 @@
 use crate::api::{console, fs, io};
 use alloc::boxed::Box;
+use alloc::format;
 use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 use vte::{Params, Parser, Perform};
@@ -7,9 +8,18 @@ use vte::{Params, Parser, Perform};
 pub struct Prompt {
     pub completion: Completion,
     pub history: History,
+    prompt: String,
     offset: usize, // Offset line by the length of the prompt string
     cursor: usize,
     line: Vec<char>, // UTF-32
+    search: Option<Search>,
+}
+
+// Incremental reverse search in the history
+struct Search {
+    query: String,
+    pos: Option<usize>,
+    line: Vec<char>, // Line edited before the search
 }
 
 impl Prompt {
@@ -17,19 +27,25 @@ impl Prompt {
         Self {
             completion: Completion::new(),
             history: History::new(),
+            prompt: String::new(),
             offset: 0,
             cursor: 0,
             line: Vec::with_capacity(80),
+            search: None,
         }
     }
 
     pub fn input(&mut self, prompt: &str) -> Option<String> {
         print!("{}", prompt);
+        self.prompt = prompt.to_string();
         self.offset = offset_from_prompt(prompt);
         self.cursor = self.offset;
         self.line = Vec::with_capacity(80);
         let mut parser = Parser::new();
         while let Some(c) = io::stdin().read_char() {
+            if self.search.is_some() && self.handle_search_char(c) {
+                continue;
+            }
             match c {
                 console::ETX_KEY => { // End of Text (^C)
                     self.update_completion();
@@ -67,76 +83,193 @@ impl Prompt {
 
     fn update_completion(&mut self) {
         if let Some(i) = self.completion.pos {
-            let complete = self.completion.entries[i].chars();
-            self.cursor += complete.clone().count();
-            self.line.extend(complete);
-            self.completion.pos = None;
-            self.completion.entries = Vec::new();
+            let complete = self.completion.entries[i].clone();
+            self.line_insert(&complete);
+        }
+        self.completion.pos = None;
+        self.completion.entries = Vec::new();
+        self.completion.tabs = 0;
+    }
+
+    // Insert a string at the cursor and print the rest of the line after it
+    fn line_insert(&mut self, s: &str) {
+        let i = self.cursor - self.offset;
+        let chars: Vec<char> = s.chars().collect();
+        let n = chars.len();
+        self.line.splice(i..i, chars);
+        self.cursor += n;
+    }
+
+    fn print_insert(&mut self, s: &str) {
+        let i = self.cursor - self.offset;
+        let rest: String = self.line[i..].iter().collect();
+        print!("{}{}", s, rest);
+        let n = rest.chars().count();
+        if n > 0 {
+            print!("\x1b[{}D", n);
         }
+        self.line_insert(s);
     }
 
+    // The first tab completes the longest prefix common to all the entries
+    // found before the cursor, the second tab lists them, and the next ones
+    // cycle through them.
     fn handle_tab_key(&mut self) {
+        self.handle_completion(true);
+    }
+
+    fn handle_backtab_key(&mut self) {
+        self.handle_completion(false);
+    }
+
+    fn handle_completion(&mut self, forward: bool) {
         self.update_history();
         let (bs, pos) = match self.completion.pos {
             Some(pos) => {
                 let n = self.completion.entries.len();
-                if n == 1 {
-                    self.update_completion();
-                    return;
-                }
                 let bs = self.completion.entries[pos].chars().count();
-                if pos + 1 < n {
-                    (bs, pos + 1)
+                if forward {
+                    (bs, (pos + 1) % n)
                 } else {
-                    (bs, 0)
+                    (bs, (pos + n - 1) % n)
                 }
             }
-            None => {
-                let line: String = self.line.iter().collect();
-                self.completion.entries = (self.completion.completer)(&line);
-                if !self.completion.entries.is_empty() {
-                    (0, 0)
-                } else {
-                    return;
+            None if self.completion.tabs == 0 => {
+                let i = self.cursor - self.offset;
+                let line: String = self.line[..i].iter().collect();
+                let entries = (self.completion.completer)(&line);
+                let prefix = common_prefix(&entries);
+                self.print_insert(&prefix);
+                if entries.len() > 1 {
+                    let n = prefix.len();
+                    self.completion.entries = entries.iter().map(|entry| {
+                        entry[n..].to_string()
+                    }).collect();
+                    self.completion.tabs = 1;
                 }
+                return;
+            }
+            None if self.completion.tabs == 1 => {
+                self.print_entries();
+                self.completion.tabs = 2;
+                return;
             }
+            None => (0, 0),
         };
-        let erase = "\x08".repeat(bs);
-        let complete = &self.completion.entries[pos];
-        print!("{}{}", erase, complete);
+        self.print_entry(bs, pos);
         self.completion.pos = Some(pos);
     }
 
-    fn handle_backtab_key(&mut self) {
-        self.update_history();
-        let (bs, pos) = match self.completion.pos {
-            Some(pos) => {
-                let n = self.completion.entries.len();
-                if n == 1 {
-                    self.update_completion();
-                    return;
+    // Print the entry in place of the previous one in front of the cursor
+    fn print_entry(&self, bs: usize, pos: usize) {
+        let i = self.cursor - self.offset;
+        let rest: String = self.line[i..].iter().collect();
+        let complete = &self.completion.entries[pos];
+        let blank = bs.saturating_sub(complete.chars().count());
+        let erase = "\x08".repeat(bs);
+        print!("{}{}{}{}", erase, complete, rest, " ".repeat(blank));
+        let n = rest.chars().count() + blank;
+        if n > 0 {
+            print!("\x1b[{}D", n);
+        }
+    }
+
+    // Print the entries below the line with the end of the word they
+    // complete, and print the prompt and the line again.
+    fn print_entries(&self) {
+        let i = self.cursor - self.offset;
+        let line: String = self.line[..i].iter().collect();
+        let word = line.rsplit([' ', '/']).next().unwrap_or_default();
+        let entries: Vec<String> = self.completion.entries.iter().map(|entry| {
+            format!("{}{}", word, entry)
+        }).collect();
+        println!();
+        println!("{}", entries.join("  "));
+        let line: String = self.line.iter().collect();
+        print!("{}{}", self.prompt, line);
+        let n = self.line.len() - i;
+        if n > 0 {
+            print!("\x1b[{}D", n);
+        }
+    }
+
+    // Return true if the char was consumed by the search, otherwise the
+    // search is ended with its match and the char is handled normally.
+    fn handle_search_char(&mut self, c: char) -> bool {
+        match c {
+            '\x12' => self.handle_search_key(),
+            console::ETX_KEY => self.end_search(false),
+            '\x08' => {
+                if let Some(search) = &mut self.search {
+                    search.query.pop();
+                    let n = self.history.entries.len();
+                    search.pos = self.history.search(&search.query, n);
                 }
-                let bs = self.completion.entries[pos].chars().count();
-                if pos == 0 {
-                    (bs, n - 1)
-                } else {
-                    (bs, pos - 1)
+                self.print_search();
+            }
+            c if console::is_printable(c) && c != '\x7f' => {
+                if let Some(search) = &mut self.search {
+                    search.query.push(c);
+                    let n = self.history.entries.len();
+                    let before = search.pos.map_or(n, |i| i + 1);
+                    search.pos = self.history.search(&search.query, before);
                 }
+                self.print_search();
             }
-            None => {
-                let line: String = self.line.iter().collect();
-                self.completion.entries = (self.completion.completer)(&line);
-                if !self.completion.entries.is_empty() {
-                    (0, 0)
-                } else {
-                    return;
+            _ => {
+                self.end_search(true);
+                return false;
+            }
+        }
+        true
+    }
+
+    // The first ^R starts the search and the next ones go to older matches
+    fn handle_search_key(&mut self) {
+        match &mut self.search {
+            Some(search) => {
+                if let Some(i) = search.pos {
+                    if let Some(j) = self.history.search(&search.query, i) {
+                        search.pos = Some(j);
+                    }
                 }
             }
-        };
-        let erase = "\x08".repeat(bs);
-        let complete = &self.completion.entries[pos];
-        print!("{}{}", erase, complete);
-        self.completion.pos = Some(pos);
+            None => {
+                self.update_completion();
+                self.update_history();
+                self.search = Some(Search {
+                    query: String::new(),
+                    pos: None,
+                    line: self.line.clone(),
+                });
+            }
+        }
+        self.print_search();
+    }
+
+    fn print_search(&self) {
+        if let Some(search) = &self.search {
+            let entry = match search.pos {
+                Some(i) => self.history.entries[i].as_str(),
+                None => "",
+            };
+            print!("\r\x1b[K(search)'{}': {}", search.query, entry);
+        }
+    }
+
+    // Redraw the prompt with either the match or the line edited before
+    // the search, with the cursor at the end.
+    fn end_search(&mut self, accept: bool) {
+        if let Some(search) = self.search.take() {
+            self.line = match search.pos {
+                Some(i) if accept => self.history.entries[i].chars().collect(),
+                _ => search.line,
+            };
+            self.cursor = self.offset + self.line.len();
+            let prompt = self.prompt.rsplit('\n').next().unwrap_or("");
+            let line: String = self.line.iter().collect();
+            print!("\r\x1b[K{}{}", prompt, line);
+        }
     }
 
     fn handle_up_key(&mut self) {
@@ -248,6 +381,7 @@ impl Perform for Prompt {
         match c {
             '\x08' => self.handle_backspace_key(),
             '\t' => self.handle_tab_key(),
+            '\x12' => self.handle_search_key(), // Reverse search (^R)
             _ => {}
         }
     }
@@ -282,6 +416,21 @@ pub struct Completion {
     completer: Box<dyn Fn(&str) -> Vec<String>>,
     entries: Vec<String>,
     pos: Option<usize>,
+    tabs: usize,
+}
+
+fn common_prefix(entries: &[String]) -> String {
+    let mut prefix = match entries.first() {
+        Some(entry) => entry.as_str(),
+        None => return String::new(),
+    };
+    for entry in &entries[1..] {
+        while !entry.starts_with(prefix) {
+            let n = prefix.chars().count();
+            prefix = &prefix[..prefix.char_indices().nth(n - 1).unwrap().0];
+        }
+    }
+    prefix.to_string()
 }
 
 fn empty_completer(_line: &str) -> Vec<String> {
@@ -294,6 +443,7 @@ impl Completion {
             completer: Box::new(empty_completer),
             entries: Vec::new(),
             pos: None,
+            tabs: 0,
         }
     }
     pub fn set(&mut self, completer: &'static dyn Fn(&str) -> Vec<String>) {
@@ -318,7 +468,10 @@ impl History {
 
     pub fn load(&mut self, path: &str) {
         if let Ok(lines) = fs::read_to_string(path) {
-            self.entries = lines.split('\n').map(|s| s.to_string()).collect();
+            self.entries = lines.lines()
+                .filter(|s| !s.trim().is_empty())
+                .map(|s| s.to_string())
+                .collect();
         }
     }
 
@@ -327,6 +480,10 @@ impl History {
     }
 
     pub fn add(&mut self, entry: &str) {
+        if entry.trim().is_empty() {
+            return;
+        }
+
         // Remove duplicated entries
         let mut i = 0;
         while i < self.entries.len() {
@@ -344,6 +501,15 @@ impl History {
             self.entries.remove(0);
         }
     }
+
+    // Find the most recent entry containing the query before the given index
+    fn search(&self, query: &str, before: usize) -> Option<usize> {
+        if query.is_empty() {
+            return None;
+        }
+        let n = before.min(self.entries.len());
+        (0..n).rev().find(|&i| self.entries[i].contains(query))
+    }
 }
 
 struct Offset(usize);
@@ -363,3 +529,29 @@ fn offset_from_prompt(s: &str) -> usize {
     }
     offset.0
 }
+
+#[test_case]
+fn test_common_prefix() {
+    let entries = |v: &[&str]| -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    };
+    assert_eq!(common_prefix(&entries(&[])), "");
+    assert_eq!(common_prefix(&entries(&["abc"])), "abc");
+    assert_eq!(common_prefix(&entries(&["abc", "abd", "ab"])), "ab");
+    assert_eq!(common_prefix(&entries(&["abc", "xyz"])), "");
+}
+
+#[test_case]
+fn test_history_search() {
+    let mut history = History::new();
+    history.add("print foo");
+    history.add("list /tmp");
+    history.add("print bar");
+    history.add("");
+    assert_eq!(history.entries.len(), 3);
+    assert_eq!(history.search("print", 3), Some(2));
+    assert_eq!(history.search("print", 2), Some(0));
+    assert_eq!(history.search("print", 0), None);
+    assert_eq!(history.search("tmp", 3), Some(1));
+    assert_eq!(history.search("", 3), None);
+}
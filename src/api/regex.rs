@@ -14,6 +14,7 @@ enum MetaChar {
     NonWhitespace,
     NonAlphanumeric,
     Literal(char),
+    Class(Vec<(char, char)>, bool),
 }
 
 impl From<char> for MetaChar {
@@ -52,6 +53,9 @@ impl MetaCharExt for MetaChar {
             MetaChar::NonWhitespace => !c.is_whitespace(),
             MetaChar::NonAlphanumeric => !c.is_alphanumeric(),
             MetaChar::Literal(lc) => c == *lc,
+            MetaChar::Class(ranges, negated) => {
+                ranges.iter().any(|(a, b)| *a <= c && c <= *b) != *negated
+            }
         }
     }
 }
@@ -122,6 +126,8 @@ fn is_match_here(
     }
     let (mc, i) = if re.len() > 1 && re[0] == '\\' {
         (MetaChar::from_escaped(re[1]), 1)
+    } else if let Some((mc, i)) = parse_class(re) {
+        (mc, i)
     } else {
         (MetaChar::from(re[0]), 0)
     };
@@ -144,6 +150,48 @@ fn is_match_here(
     false
 }
 
+// Parse a bracket expression like `[a-z_]` or `[^0-9]` at the beginning of
+// the regex and return it with the index of its closing bracket.
+fn parse_class(re: &[char]) -> Option<(MetaChar, usize)> {
+    if re.is_empty() || re[0] != '[' {
+        return None;
+    }
+    let mut i = 1;
+    let negated = i < re.len() && re[i] == '^';
+    if negated {
+        i += 1;
+    }
+    let mut chars = Vec::new();
+    let mut first = true;
+    while i < re.len() {
+        match re[i] {
+            ']' if !first => break,
+            '\\' if i + 1 < re.len() => {
+                i += 1;
+                chars.push(re[i]);
+            }
+            c => chars.push(c),
+        }
+        first = false;
+        i += 1;
+    }
+    if i == re.len() {
+        return None; // Unclosed bracket
+    }
+    let mut ranges = Vec::new();
+    let mut j = 0;
+    while j < chars.len() {
+        if j + 2 < chars.len() && chars[j + 1] == '-' {
+            ranges.push((chars[j], chars[j + 2]));
+            j += 3;
+        } else {
+            ranges.push((chars[j], chars[j]));
+            j += 1;
+        }
+    }
+    Some((MetaChar::Class(ranges, negated), i))
+}
+
 fn is_match_star(
     lazy: bool,
     mc: MetaChar,
@@ -282,6 +330,18 @@ fn test_regex() {
         ("a\\.*d", "a..d", true),
         ("a\\.*d", "a.cd", false),
         ("a\\w*d", "abcd", true),
+        ("a[bc]d", "abd", true),
+        ("a[bc]d", "acd", true),
+        ("a[bc]d", "aed", false),
+        ("a[^bc]d", "abd", false),
+        ("a[^bc]d", "aed", true),
+        ("[a-z]+", "abc", true),
+        ("^[a-z]+$", "aBc", false),
+        ("^[a-zA-Z_]+$", "aB_c", true),
+        ("a[]]b", "a]b", true),
+        ("a[\\]]b", "a]b", true),
+        ("a[.]b", "abb", false),
+        ("a[b", "a[b", true),
     ];
     for (re, text, is_match) in tests {
         assert!(
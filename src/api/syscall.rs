@@ -1,9 +1,11 @@
 use crate::api::fs::IO;
 use crate::api::process::ExitCode;
-use crate::sys::fs::FileInfo;
+use crate::sys::fs::{FileInfo, SeekFrom};
 use crate::sys::syscall::number::*;
 use crate::syscall;
 
+use alloc::string::String;
+use alloc::vec;
 use smoltcp::wire::IpAddress;
 use smoltcp::wire::Ipv4Address;
 
@@ -59,6 +61,22 @@ pub fn dup(old_handle: usize, new_handle: usize) -> Option<usize> {
     }
 }
 
+pub fn seek(handle: usize, pos: SeekFrom) -> Option<usize> {
+    let (offset, whence) = match pos {
+        SeekFrom::Start(i) => (i as isize, 0),
+        SeekFrom::Current(i) => (i as isize, 1),
+        SeekFrom::End(i) => (i as isize, 2),
+    };
+    let res = unsafe {
+        syscall!(SEEK, handle, offset as usize, whence)
+    } as isize;
+    if res >= 0 {
+        Some(res as usize)
+    } else {
+        None
+    }
+}
+
 pub fn read(handle: usize, buf: &mut [u8]) -> Option<usize> {
     let ptr = buf.as_ptr() as usize;
     let len = buf.len();
@@ -144,14 +162,17 @@ pub fn listen(handle: usize, port: u16) -> Result<(), ()> {
     }
 }
 
-pub fn accept(handle: usize) -> Result<IpAddress, ()> {
+// Return the handle of a new socket for the connection accepted by a
+// listening socket, with the address of the remote host.
+pub fn accept(handle: usize) -> Result<(usize, IpAddress), ()> {
     let addr = IpAddress::v4(0, 0, 0, 0);
     let buf = addr.as_bytes();
     let ptr = buf.as_ptr() as usize;
     let len = buf.len();
     let res = unsafe { syscall!(ACCEPT, handle, ptr, len) } as isize;
     if res >= 0 {
-        Ok(IpAddress::from(Ipv4Address::from_bytes(buf)))
+        let addr = IpAddress::from(Ipv4Address::from_bytes(buf));
+        Ok((res as usize, addr))
     } else {
         Err(())
     }
@@ -167,10 +188,131 @@ pub fn free(ptr: *mut u8, size: usize, align: usize) {
     }
 }
 
+pub fn pipe() -> Option<usize> {
+    let res = unsafe { syscall!(PIPE) } as isize;
+    if res >= 0 {
+        Some(res as usize)
+    } else {
+        None
+    }
+}
+
+pub fn symlink(target: &str, path: &str) -> Result<(), ()> {
+    let target_ptr = target.as_ptr() as usize;
+    let target_len = target.len();
+    let path_ptr = path.as_ptr() as usize;
+    let path_len = path.len();
+    let res = unsafe {
+        syscall!(SYMLINK, target_ptr, target_len, path_ptr, path_len)
+    } as isize;
+    if res >= 0 {
+        Ok(())
+    } else {
+        Err(())
+    }
+}
+
+pub fn link(source: &str, path: &str) -> Result<(), ()> {
+    let source_ptr = source.as_ptr() as usize;
+    let source_len = source.len();
+    let path_ptr = path.as_ptr() as usize;
+    let path_len = path.len();
+    let res = unsafe {
+        syscall!(LINK, source_ptr, source_len, path_ptr, path_len)
+    } as isize;
+    if res >= 0 {
+        Ok(())
+    } else {
+        Err(())
+    }
+}
+
+pub fn chmod(path: &str, mode: u16) -> Result<(), ()> {
+    let path_ptr = path.as_ptr() as usize;
+    let path_len = path.len();
+    let res = unsafe { syscall!(CHMOD, path_ptr, path_len, mode) } as isize;
+    if res >= 0 {
+        Ok(())
+    } else {
+        Err(())
+    }
+}
+
+pub fn chown(path: &str, uid: u16, gid: u16) -> Result<(), ()> {
+    let path_ptr = path.as_ptr() as usize;
+    let path_len = path.len();
+    let res = unsafe {
+        syscall!(CHOWN, path_ptr, path_len, uid, gid)
+    } as isize;
+    if res >= 0 {
+        Ok(())
+    } else {
+        Err(())
+    }
+}
+
+pub fn rename(source: &str, dest: &str) -> Result<(), ()> {
+    let source_ptr = source.as_ptr() as usize;
+    let source_len = source.len();
+    let dest_ptr = dest.as_ptr() as usize;
+    let dest_len = dest.len();
+    let res = unsafe {
+        syscall!(RENAME, source_ptr, source_len, dest_ptr, dest_len)
+    } as isize;
+    if res >= 0 {
+        Ok(())
+    } else {
+        Err(())
+    }
+}
+
+pub fn sync() -> Result<(), ()> {
+    let res = unsafe { syscall!(SYNC) } as isize;
+    if res >= 0 {
+        Ok(())
+    } else {
+        Err(())
+    }
+}
+
+pub fn utime(path: &str, atime: u64, mtime: u64) -> Result<(), ()> {
+    let path_ptr = path.as_ptr() as usize;
+    let path_len = path.len();
+    let res = unsafe {
+        syscall!(UTIME, path_ptr, path_len, atime as usize, mtime as usize)
+    } as isize;
+    if res >= 0 {
+        Ok(())
+    } else {
+        Err(())
+    }
+}
+
+pub fn read_link(path: &str) -> Option<String> {
+    let path_ptr = path.as_ptr() as usize;
+    let path_len = path.len();
+    let mut buf = vec![0; 256];
+    loop {
+        let buf_ptr = buf.as_mut_ptr() as usize;
+        let buf_len = buf.len();
+        let res = unsafe {
+            syscall!(READLINK, path_ptr, path_len, buf_ptr, buf_len)
+        } as isize;
+        if res < 0 {
+            return None;
+        }
+        let n = res as usize;
+        if n <= buf_len {
+            buf.truncate(n);
+            return Some(String::from_utf8_lossy(&buf).into());
+        }
+        buf.resize(n, 0); // The target was truncated
+    }
+}
+
 #[test_case]
 fn test_file() {
     use crate::sys::fs::{dismount, format_mem, mount_mem, OpenFlag};
-    use alloc::vec;
     mount_mem();
     format_mem();
 
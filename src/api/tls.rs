@@ -0,0 +1,453 @@
+// See RFC 8446 (TLS 1.3)
+//
+// This is a minimal client supporting only the TLS_CHACHA20_POLY1305_SHA256
+// cipher suite with a X25519 key exchange.
+//
+// WARNING: The certificate of the server is not verified, so the connection
+// is encrypted but the server is not authenticated and anyone in the path
+// can intercept it. The cryptographic primitives of `api::crypto` have also
+// not been reviewed. The client is experimental and only built with the
+// `tls` feature, and the commands using it only call `connect_insecure`
+// when asked to with `--insecure`.
+
+use crate::api::crypto;
+use crate::api::rng;
+use crate::api::syscall;
+
+use core::str::FromStr;
+use smoltcp::wire::Ipv4Address;
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+const CHANGE_CIPHER_SPEC: u8 = 20;
+const ALERT: u8 = 21;
+const HANDSHAKE: u8 = 22;
+const APPLICATION_DATA: u8 = 23;
+
+const SERVER_HELLO: u8 = 2;
+const ENCRYPTED_EXTENSIONS: u8 = 8;
+const CERTIFICATE: u8 = 11;
+const CERTIFICATE_VERIFY: u8 = 15;
+const FINISHED: u8 = 20;
+
+const TLS_CHACHA20_POLY1305_SHA256: u16 = 0x1303;
+const X25519: u16 = 0x001D;
+const TLS_13: u16 = 0x0304;
+
+const MAX_RECORD_LEN: usize = 1 << 14;
+
+// Random value of a ServerHello used as a HelloRetryRequest
+const RETRY_RANDOM: [u8; 32] = [
+    0xCF, 0x21, 0xAD, 0x74, 0xE5, 0x9A, 0x61, 0x11, 0xBE, 0x1D, 0x8C, 0x02,
+    0x1E, 0x65, 0xB8, 0x91, 0xC2, 0xA2, 0x11, 0x16, 0x7A, 0xBB, 0x8C, 0x5E,
+    0x07, 0x9E, 0x09, 0xE2, 0xC8, 0xA8, 0x33, 0x9C,
+];
+
+struct Keys {
+    key: [u8; 32],
+    iv: [u8; 12],
+    seq: u64,
+}
+
+impl Keys {
+    fn new(secret: &[u8]) -> Self {
+        let mut key = [0; 32];
+        let mut iv = [0; 12];
+        key.copy_from_slice(&expand_label(secret, "key", &[], 32));
+        iv.copy_from_slice(&expand_label(secret, "iv", &[], 12));
+        Self { key, iv, seq: 0 }
+    }
+
+    fn nonce(&mut self) -> [u8; 12] {
+        let mut nonce = self.iv;
+        for (i, b) in self.seq.to_be_bytes().iter().enumerate() {
+            nonce[4 + i] ^= b;
+        }
+        self.seq += 1;
+        nonce
+    }
+}
+
+pub struct TlsStream {
+    handle: usize,
+    received: Vec<u8>,
+    plaintext: Vec<u8>,
+    client_keys: Option<Keys>,
+    server_keys: Option<Keys>,
+    is_closed: bool,
+}
+
+impl TlsStream {
+    pub fn is_enabled() -> bool {
+        cfg!(feature = "tls")
+    }
+
+    // Perform a handshake on a connected TCP socket without authenticating
+    // the server
+    pub fn connect_insecure(
+        handle: usize,
+        server_name: &str
+    ) -> Result<Self, ()> {
+        if !Self::is_enabled() {
+            return Err(());
+        }
+        let mut stream = Self {
+            handle,
+            received: Vec::new(),
+            plaintext: Vec::new(),
+            client_keys: None,
+            server_keys: None,
+            is_closed: false,
+        };
+        stream.handshake(server_name)?;
+        Ok(stream)
+    }
+
+    // Read decrypted application data, returning 0 when the connection
+    // is closed
+    pub fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        while self.plaintext.is_empty() && !self.is_closed {
+            match self.read_record() {
+                Ok((APPLICATION_DATA, data)) => {
+                    self.plaintext.extend_from_slice(&data);
+                }
+                Ok((ALERT, _)) => {
+                    self.is_closed = true;
+                }
+                Ok(_) => {
+                    // Ignore post-handshake messages like NewSessionTicket
+                }
+                Err(()) if self.is_closed => {}
+                Err(()) => return None,
+            }
+        }
+        let n = buf.len().min(self.plaintext.len());
+        buf[0..n].copy_from_slice(&self.plaintext[0..n]);
+        self.plaintext.drain(0..n);
+        Some(n)
+    }
+
+    pub fn write(&mut self, buf: &[u8]) -> Option<usize> {
+        for chunk in buf.chunks(MAX_RECORD_LEN) {
+            self.write_record(APPLICATION_DATA, chunk).ok()?;
+        }
+        Some(buf.len())
+    }
+
+    // Send a close_notify alert
+    pub fn close(&mut self) {
+        self.write_record(ALERT, &[1, 0]).ok();
+    }
+
+    fn handshake(&mut self, server_name: &str) -> Result<(), ()> {
+        let mut secret = [0; 32];
+        for chunk in secret.chunks_mut(8) {
+            chunk.copy_from_slice(&rng::get_u64().to_le_bytes());
+        }
+        let public = crypto::x25519_base(&secret);
+
+        let client_hello = client_hello(server_name, &public);
+        self.write_record(HANDSHAKE, &client_hello)?;
+        let mut transcript = client_hello;
+
+        let mut messages = Vec::new();
+        let server_hello = self.read_handshake(&mut messages)?;
+        let server_public = parse_server_hello(&server_hello)?;
+        transcript.extend_from_slice(&server_hello);
+
+        let shared = crypto::x25519(&secret, &server_public);
+        let early_secret = crypto::hkdf_extract(&[0; 32], &[0; 32]);
+        let salt = derive_secret(&early_secret, "derived", &[]);
+        let handshake_secret = crypto::hkdf_extract(&salt, &shared);
+        let client_secret = derive_secret(
+            &handshake_secret, "c hs traffic", &transcript
+        );
+        let server_secret = derive_secret(
+            &handshake_secret, "s hs traffic", &transcript
+        );
+        self.client_keys = Some(Keys::new(&client_secret));
+        self.server_keys = Some(Keys::new(&server_secret));
+
+        loop {
+            let message = self.read_handshake(&mut messages)?;
+            match message[0] {
+                ENCRYPTED_EXTENSIONS | CERTIFICATE | CERTIFICATE_VERIFY => {
+                    // The certificate is only added to the transcript since
+                    // the connection is insecure
+                }
+                FINISHED => {
+                    let key = expand_label(&server_secret, "finished", &[], 32);
+                    let hash = crypto::sha256(&transcript);
+                    let verify_data = crypto::hmac_sha256(&key, &hash);
+                    if message[4..] != verify_data {
+                        return Err(());
+                    }
+                    transcript.extend_from_slice(&message);
+                    break;
+                }
+                _ => {
+                    return Err(());
+                }
+            }
+            transcript.extend_from_slice(&message);
+        }
+
+        let salt = derive_secret(&handshake_secret, "derived", &[]);
+        let master_secret = crypto::hkdf_extract(&salt, &[0; 32]);
+        let client_app_secret = derive_secret(
+            &master_secret, "c ap traffic", &transcript
+        );
+        let server_app_secret = derive_secret(
+            &master_secret, "s ap traffic", &transcript
+        );
+
+        // Middlebox compatibility mode
+        self.write_raw(&[CHANGE_CIPHER_SPEC, 3, 3, 0, 1, 1])?;
+
+        let key = expand_label(&client_secret, "finished", &[], 32);
+        let hash = crypto::sha256(&transcript);
+        let mut finished = vec![FINISHED, 0, 0, 32];
+        finished.extend_from_slice(&crypto::hmac_sha256(&key, &hash));
+        self.write_record(HANDSHAKE, &finished)?;
+
+        self.client_keys = Some(Keys::new(&client_app_secret));
+        self.server_keys = Some(Keys::new(&server_app_secret));
+        Ok(())
+    }
+
+    // Read the next handshake message, which can be split across records
+    fn read_handshake(
+        &mut self, messages: &mut Vec<u8>
+    ) -> Result<Vec<u8>, ()> {
+        loop {
+            if messages.len() >= 4 {
+                let n = 4 + be24(&messages[1..4]);
+                if messages.len() >= n {
+                    return Ok(messages.drain(0..n).collect());
+                }
+            }
+            match self.read_record()? {
+                (HANDSHAKE, data) => messages.extend_from_slice(&data),
+                (CHANGE_CIPHER_SPEC, _) => {}
+                _ => return Err(()),
+            }
+        }
+    }
+
+    // Read the next record, decrypting it when the keys are known
+    fn read_record(&mut self) -> Result<(u8, Vec<u8>), ()> {
+        while self.received.len() < 5
+            || self.received.len() < 5 + be16(&self.received[3..5])
+        {
+            let mut buf = vec![0; 2048];
+            match syscall::read(self.handle, &mut buf) {
+                Some(0) | None => {
+                    self.is_closed = true;
+                    return Err(());
+                }
+                Some(n) => self.received.extend_from_slice(&buf[0..n]),
+            }
+        }
+        let n = 5 + be16(&self.received[3..5]);
+        let header: Vec<u8> = self.received.drain(0..5).collect();
+        let mut data: Vec<u8> = self.received.drain(0..n - 5).collect();
+        let kind = header[0];
+        if kind != APPLICATION_DATA {
+            return Ok((kind, data));
+        }
+        let keys = self.server_keys.as_mut().ok_or(())?;
+        let nonce = keys.nonce();
+        crypto::open(&keys.key, &nonce, &header, &mut data)?;
+
+        // Remove the padding and the real content type of the record
+        while data.last() == Some(&0) {
+            data.pop();
+        }
+        let kind = data.pop().ok_or(())?;
+        Ok((kind, data))
+    }
+
+    fn write_record(&mut self, kind: u8, data: &[u8]) -> Result<(), ()> {
+        let mut record = vec![kind, 3, 3];
+        match self.client_keys.as_mut() {
+            None => {
+                record[2] = 1; // Legacy version of the first record
+                record.extend_from_slice(&(data.len() as u16).to_be_bytes());
+                record.extend_from_slice(data);
+            }
+            Some(keys) => {
+                let mut buf = data.to_vec();
+                buf.push(kind);
+                let n = buf.len() + 16;
+                record[0] = APPLICATION_DATA;
+                record.extend_from_slice(&(n as u16).to_be_bytes());
+                let nonce = keys.nonce();
+                crypto::seal(&keys.key, &nonce, &record, &mut buf);
+                record.extend_from_slice(&buf);
+            }
+        }
+        self.write_raw(&record)
+    }
+
+    fn write_raw(&mut self, buf: &[u8]) -> Result<(), ()> {
+        let mut i = 0;
+        while i < buf.len() {
+            match syscall::write(self.handle, &buf[i..]) {
+                Some(n) if n > 0 => i += n,
+                _ => return Err(()),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn client_hello(server_name: &str, public: &[u8; 32]) -> Vec<u8> {
+    let mut extensions = Vec::new();
+
+    // IP addresses are not allowed in the server name extension
+    if Ipv4Address::from_str(server_name).is_err() {
+        let name = server_name.as_bytes();
+        let n = name.len() as u16;
+        let mut data = Vec::new();
+        data.extend_from_slice(&(n + 3).to_be_bytes());
+        data.push(0); // Host name
+        data.extend_from_slice(&n.to_be_bytes());
+        data.extend_from_slice(name);
+        push_extension(&mut extensions, 0x0000, &data); // Server name
+    }
+
+    let data = [0, 2, 0x00, 0x1D];
+    push_extension(&mut extensions, 0x000A, &data); // Supported groups
+
+    let algorithms: [u16; 8] = [
+        0x0403, 0x0503, 0x0804, 0x0805, 0x0806, 0x0401, 0x0501, 0x0601
+    ];
+    let mut data = Vec::new();
+    data.extend_from_slice(&(2 * algorithms.len() as u16).to_be_bytes());
+    for algorithm in algorithms {
+        data.extend_from_slice(&algorithm.to_be_bytes());
+    }
+    push_extension(&mut extensions, 0x000D, &data); // Signature algorithms
+
+    let data = [2, 0x03, 0x04];
+    push_extension(&mut extensions, 0x002B, &data); // Supported versions
+
+    let mut data = vec![0, 36, 0x00, 0x1D, 0, 32];
+    data.extend_from_slice(public);
+    push_extension(&mut extensions, 0x0033, &data); // Key share
+
+    let mut body = vec![3, 3];
+    for _ in 0..4 {
+        body.extend_from_slice(&rng::get_u64().to_le_bytes()); // Random
+    }
+    body.push(32); // Legacy session id
+    for _ in 0..4 {
+        body.extend_from_slice(&rng::get_u64().to_le_bytes());
+    }
+    body.extend_from_slice(&[0, 2]);
+    body.extend_from_slice(&TLS_CHACHA20_POLY1305_SHA256.to_be_bytes());
+    body.extend_from_slice(&[1, 0]); // Legacy compression methods
+    body.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+    body.extend_from_slice(&extensions);
+
+    let mut message = vec![1]; // ClientHello
+    message.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..4]);
+    message.extend_from_slice(&body);
+    message
+}
+
+fn push_extension(extensions: &mut Vec<u8>, kind: u16, data: &[u8]) {
+    extensions.extend_from_slice(&kind.to_be_bytes());
+    extensions.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    extensions.extend_from_slice(data);
+}
+
+// Return the public key of the server from its ServerHello message
+fn parse_server_hello(message: &[u8]) -> Result<[u8; 32], ()> {
+    let body = message.get(4..).ok_or(())?;
+    if message[0] != SERVER_HELLO || body.len() < 35 {
+        return Err(());
+    }
+    if body[2..34] == RETRY_RANDOM {
+        return Err(()); // The server does not support X25519
+    }
+    let mut i = 35 + body[34] as usize; // Skip legacy session id
+    let suite = be16(body.get(i..i + 2).ok_or(())?) as u16;
+    if suite != TLS_CHACHA20_POLY1305_SHA256 {
+        return Err(());
+    }
+    i += 5; // Skip cipher suite, compression method, and extensions length
+
+    let mut version = 0;
+    let mut public = None;
+    while i + 4 <= body.len() {
+        let kind = be16(&body[i..]) as u16;
+        let n = be16(&body[i + 2..]);
+        let data = body.get(i + 4..i + 4 + n).ok_or(())?;
+        match kind {
+            0x002B if n == 2 => {
+                version = be16(data) as u16;
+            }
+            0x0033 if n == 36 && be16(data) as u16 == X25519 => {
+                let mut key = [0; 32];
+                key.copy_from_slice(&data[4..]);
+                public = Some(key);
+            }
+            _ => {}
+        }
+        i += 4 + n;
+    }
+    if version != TLS_13 {
+        return Err(());
+    }
+    public.ok_or(())
+}
+
+fn expand_label(
+    secret: &[u8], label: &str, context: &[u8], len: usize
+) -> Vec<u8> {
+    let label = [b"tls13 ", label.as_bytes()].concat();
+    let mut info = Vec::new();
+    info.extend_from_slice(&(len as u16).to_be_bytes());
+    info.push(label.len() as u8);
+    info.extend_from_slice(&label);
+    info.push(context.len() as u8);
+    info.extend_from_slice(context);
+    crypto::hkdf_expand(secret, &info, len)
+}
+
+fn derive_secret(secret: &[u8], label: &str, transcript: &[u8]) -> Vec<u8> {
+    expand_label(secret, label, &crypto::sha256(transcript), 32)
+}
+
+fn be16(buf: &[u8]) -> usize {
+    ((buf[0] as usize) << 8) | (buf[1] as usize)
+}
+
+fn be24(buf: &[u8]) -> usize {
+    ((buf[0] as usize) << 16) | ((buf[1] as usize) << 8) | (buf[2] as usize)
+}
+
+#[test_case]
+fn test_expand_label() {
+    // RFC 8448 (Simple 1-RTT Handshake)
+    let secret = [
+        0xB6, 0x7B, 0x7D, 0x69, 0x0C, 0xC1, 0x6C, 0x4E, 0x75, 0xE5, 0x42, 0x13,
+        0xCB, 0x2D, 0x37, 0xB4, 0xE9, 0xC9, 0x12, 0xBC, 0xDE, 0xD9, 0x10, 0x5D,
+        0x42, 0xBE, 0xFD, 0x59, 0xD3, 0x91, 0xAD, 0x38,
+    ];
+    let iv = [
+        0x5D, 0x31, 0x3E, 0xB2, 0x67, 0x12, 0x76, 0xEE, 0x13, 0x00, 0x0B, 0x30,
+    ];
+    assert_eq!(expand_label(&secret, "iv", &[], 12), iv);
+}
+
+#[test_case]
+fn test_client_hello_server_name() {
+    let public = [0; 32];
+    let sni = b"\x00\x00\x00\x0C\x00\x0A\x00\x00\x07";
+    let has_sni = |buf: &[u8]| buf.windows(sni.len()).any(|w| w == sni);
+    assert!(has_sni(&client_hello("host123", &public)));
+    assert!(!has_sni(&client_hello("10.0.2.2", &public)));
+}
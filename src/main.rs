@@ -19,6 +19,7 @@ fn main(boot_info: &'static BootInfo) -> ! {
             let prompt = usr::shell::prompt_string(true);
             println!("{}{}", prompt, cmd);
             usr::shell::exec(cmd).ok();
+            sys::fs::sync().ok();
             sys::acpi::shutdown();
         } else {
             user_boot();
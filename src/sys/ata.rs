@@ -4,6 +4,7 @@ use crate::api::fs::{FileIO, IO};
 use alloc::string::String;
 use alloc::vec::Vec;
 use bit_field::BitField;
+use core::cmp;
 use core::convert::TryInto;
 use core::fmt;
 use core::hint::spin_loop;
@@ -17,20 +18,29 @@ use x86_64::instructions::port::{Port, PortReadOnly, PortWriteOnly};
 
 pub const BLOCK_SIZE: usize = 512;
 
+// Packet devices like CD-ROM drives have bigger blocks
+pub const ATAPI_BLOCK_SIZE: usize = 2048;
+
 // Keep track of the last selected bus and drive pair to speed up operations
 pub static LAST_SELECTED: Mutex<Option<(u8, u8)>> = Mutex::new(None);
 
 #[repr(u16)]
 #[derive(Debug, Clone, Copy)]
 enum Command {
-    Read     = 0x20,
-    Write    = 0x30,
-    Identify = 0xEC,
+    Read           = 0x20,
+    Write          = 0x30,
+    FlushCache     = 0xE7,
+    Packet         = 0xA0,
+    IdentifyPacket = 0xA1,
+    Identify       = 0xEC,
 }
 
+// SCSI commands sent in packets to ATAPI devices
+const SCSI_READ: u8 = 0x28;
+
 enum IdentifyResponse {
     Ata([u16; 256]),
-    Atapi,
+    Atapi([u16; 256]),
     Sata,
     None,
 }
@@ -254,6 +264,85 @@ impl Bus {
         }
     }
 
+    // Wait until the blocks kept in the write cache of the drive have been
+    // written to the disk
+    fn flush_cache(&mut self, drive: u8) -> Result<(), ()> {
+        self.select_drive(drive)?;
+        unsafe { self.command_register.write(Command::FlushCache as u8) }
+        self.wait(400); // Wait at least 400 ns
+        self.poll(Status::BSY, false)?;
+        if self.is_error() {
+            debug!("ATA flush: cache error");
+            self.debug();
+            Err(())
+        } else {
+            Ok(())
+        }
+    }
+
+    // Send a SCSI command packet to an ATAPI device and read its response,
+    // returning the number of bytes read
+    fn packet(
+        &mut self,
+        drive: u8,
+        packet: &[u8; 12],
+        buf: &mut [u8]
+    ) -> Result<usize, ()> {
+        self.select_drive(drive)?;
+        let max = (buf.len() as u16).to_le_bytes();
+        unsafe {
+            self.features_register.write(0); // PIO mode
+            self.lba1_register.write(max[0]);
+            self.lba2_register.write(max[1]);
+        }
+        self.write_command(Command::Packet)?;
+        for chunk in packet.chunks(2) {
+            let data = u16::from_le_bytes(chunk.try_into().unwrap());
+            self.write_data(data);
+        }
+        self.wait(400); // Wait at least 400 ns
+        self.poll(Status::BSY, false)?;
+        if self.is_error() {
+            return Err(());
+        }
+        self.poll(Status::DRQ, true)?;
+
+        // The device gives the number of bytes it will transfer
+        let n = u16::from_le_bytes([self.lba1(), self.lba2()]) as usize;
+        let n = cmp::min(n, buf.len());
+        for chunk in buf[0..n].chunks_mut(2) {
+            let data = self.read_data().to_le_bytes();
+            chunk.clone_from_slice(&data[0..chunk.len()]);
+        }
+        if self.is_error() {
+            debug!("ATAPI read: data error");
+            self.debug();
+            Err(())
+        } else {
+            Ok(n)
+        }
+    }
+
+    fn read_atapi(
+        &mut self,
+        drive: u8,
+        block: u32,
+        buf: &mut [u8]
+    ) -> Result<(), ()> {
+        debug_assert!(buf.len() == ATAPI_BLOCK_SIZE);
+        let b = block.to_be_bytes();
+        let packet = [SCSI_READ, 0, b[0], b[1], b[2], b[3], 0, 0, 1, 0, 0, 0];
+        match self.packet(drive, &packet, buf)? {
+            ATAPI_BLOCK_SIZE => Ok(()),
+            _ => Err(()),
+        }
+    }
+
+    fn identify_packet_drive(&mut self) -> Result<IdentifyResponse, ()> {
+        self.write_command(Command::IdentifyPacket)?;
+        Ok(IdentifyResponse::Atapi([(); 256].map(|_| self.read_data())))
+    }
+
     fn identify_drive(&mut self, drive: u8) -> Result<IdentifyResponse, ()> {
         if self.check_floating_bus().is_err() {
             return Ok(IdentifyResponse::None);
@@ -263,15 +352,18 @@ impl Bus {
         if self.write_command(Command::Identify).is_err() {
             if self.status() == 0 {
                 return Ok(IdentifyResponse::None);
-            } else {
-                return Err(());
             }
+            // Packet devices abort the command leaving their signature
+            if (self.lba1(), self.lba2()) == (0x14, 0xEB) {
+                return self.identify_packet_drive();
+            }
+            return Err(());
         }
         match (self.lba1(), self.lba2()) {
             (0x00, 0x00) => {
                 Ok(IdentifyResponse::Ata([(); 256].map(|_| self.read_data())))
             }
-            (0x14, 0xEB) => Ok(IdentifyResponse::Atapi),
+            (0x14, 0xEB) => self.identify_packet_drive(),
             (0x3C, 0xC3) => Ok(IdentifyResponse::Sata),
             (_, _) => Err(()),
         }
@@ -316,6 +408,16 @@ pub fn init() {
     for drive in list() {
         log!("ATA {}:{} {}", drive.bus, drive.dsk, drive);
     }
+    for bus in 0..2 {
+        for dsk in 0..2 {
+            let res = BUSES.lock()[bus as usize].identify_drive(dsk);
+            if let Ok(IdentifyResponse::Atapi(res)) = res {
+                let buf = res.map(u16::to_be_bytes).concat();
+                let model = String::from_utf8_lossy(&buf[54..94]);
+                log!("ATAPI {}:{} {}", bus, dsk, model.trim());
+            }
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -379,20 +481,38 @@ impl Drive {
 }
 
 impl FileIO for Drive {
+    // Read one block at a time
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, ()> {
         if self.block_index == self.block_count {
             return Ok(0);
         }
 
+        let mut block = [0; BLOCK_SIZE];
         let mut buses = BUSES.lock();
-        let _ = buses[self.bus as usize].read(self.dsk, self.block_index, buf);
-        let n = buf.len();
+        buses[self.bus as usize].read(self.dsk, self.block_index, &mut block)?;
+        let n = cmp::min(buf.len(), BLOCK_SIZE);
+        buf[0..n].clone_from_slice(&block[0..n]);
         self.block_index += 1;
         Ok(n)
     }
 
-    fn write(&mut self, _buf: &[u8]) -> Result<usize, ()> {
-        unimplemented!();
+    // Write one block at a time, keeping the end of a partial block intact
+    fn write(&mut self, buf: &[u8]) -> Result<usize, ()> {
+        if self.block_index == self.block_count {
+            return Err(());
+        }
+
+        let mut block = [0; BLOCK_SIZE];
+        let mut buses = BUSES.lock();
+        let bus = &mut buses[self.bus as usize];
+        let n = cmp::min(buf.len(), BLOCK_SIZE);
+        if n < BLOCK_SIZE {
+            bus.read(self.dsk, self.block_index, &mut block)?;
+        }
+        block[0..n].clone_from_slice(&buf[0..n]);
+        bus.write(self.dsk, self.block_index, &block)?;
+        self.block_index += 1;
+        Ok(n)
     }
 
     fn close(&mut self) {
@@ -401,7 +521,7 @@ impl FileIO for Drive {
     fn poll(&mut self, event: IO) -> bool {
         match event {
             IO::Read => true,
-            IO::Write => false,
+            IO::Write => true,
         }
     }
 }
@@ -434,3 +554,26 @@ pub fn write(bus: u8, drive: u8, block: u32, buf: &[u8]) -> Result<(), ()> {
     let mut buses = BUSES.lock();
     buses[bus as usize].write(drive, block, buf)
 }
+
+pub fn flush_cache(bus: u8, drive: u8) -> Result<(), ()> {
+    let mut buses = BUSES.lock();
+    buses[bus as usize].flush_cache(drive)
+}
+
+pub fn is_atapi(bus: u8, drive: u8) -> bool {
+    let mut buses = BUSES.lock();
+    let res = buses[bus as usize].identify_drive(drive);
+    matches!(res, Ok(IdentifyResponse::Atapi(_)))
+}
+
+// Read a block of 2048 bytes from an ATAPI device
+pub fn read_atapi(
+    bus: u8,
+    drive: u8,
+    block: u32,
+    buf: &mut [u8]
+) -> Result<(), ()> {
+    let mut buses = BUSES.lock();
+    buses[bus as usize].read_atapi(drive, block, buf)
+}
+
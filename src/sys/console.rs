@@ -137,11 +137,16 @@ pub fn drain() {
     interrupts::without_interrupts(|| STDIN.lock().clear())
 }
 
+// Return the input typed so far without consuming it
+pub fn peek() -> String {
+    interrupts::without_interrupts(|| STDIN.lock().clone())
+}
+
 pub fn read_char() -> char {
     sys::console::disable_echo();
     sys::console::enable_raw();
     loop {
-        sys::time::halt();
+        sys::time::idle();
         let res = interrupts::without_interrupts(|| {
             let mut stdin = STDIN.lock();
             if !stdin.is_empty() {
@@ -160,7 +165,7 @@ pub fn read_char() -> char {
 
 pub fn read_line() -> String {
     loop {
-        sys::time::halt();
+        sys::time::idle();
         let res = interrupts::without_interrupts(|| {
             let mut stdin = STDIN.lock();
             match stdin.chars().next_back() {
@@ -1,4 +1,5 @@
 use super::block::Block;
+use super::block_device::{device, global_addr};
 use super::super_block;
 use super::super_block::SuperBlock;
 
@@ -12,16 +13,16 @@ pub struct BitmapBlock {}
 
 impl BitmapBlock {
     fn block_index(addr: u32) -> u32 {
-        let sb = SuperBlock::read();
+        let sb = SuperBlock::read(device(addr));
         let size = sb.block_size();
         let i = addr - sb.data_area();
         sb.bitmap_area() + (i / size / 8)
     }
 
     fn buffer_index(addr: u32) -> usize {
-        let sb = SuperBlock::read();
+        let sb = SuperBlock::read(device(addr));
         let i = (addr - sb.data_area()) as usize;
-        i % sb.block_size() as usize
+        i % BITMAP_SIZE
     }
 
     pub fn alloc(addr: u32) {
@@ -31,7 +32,7 @@ impl BitmapBlock {
         if !bitmap[i / 8].get_bit(i % 8) {
             bitmap[i / 8].set_bit(i % 8, true);
             block.write();
-            super_block::inc_alloc_count();
+            super_block::inc_alloc_count(device(addr));
         }
     }
 
@@ -41,22 +42,27 @@ impl BitmapBlock {
         let i = BitmapBlock::buffer_index(addr);
         bitmap[i / 8].set_bit(i % 8, false);
         block.write();
-        super_block::dec_alloc_count();
+        super_block::dec_alloc_count(device(addr));
     }
 
-    pub fn next_free_addr() -> Option<u32> {
-        let sb = SuperBlock::read();
+    // Find a free block on a device, within the blocks tracked by the
+    // bitmap area and before the end of the device
+    pub fn next_free_addr(dev: usize) -> Option<u32> {
+        let sb = SuperBlock::read(dev);
         let size = sb.block_size();
-        let n = sb.block_count() / size / 8;
+        let n = sb.data_area() - sb.bitmap_area();
+        let end = global_addr(dev, sb.block_count());
         for i in 0..n {
             let block = Block::read(sb.bitmap_area() + i);
             let bitmap = block.data();
-            let m = size / 8;
-            for j in 0..m {
+            for j in 0..size {
                 for k in 0..8 {
                     if !bitmap[j as usize].get_bit(k) {
                         let bs = BITMAP_SIZE as u32;
                         let addr = sb.data_area() + i * bs + j * 8 + k as u32;
+                        if addr >= end {
+                            return None;
+                        }
                         return Some(addr);
                     }
                 }
@@ -66,8 +72,8 @@ impl BitmapBlock {
     }
 }
 
-pub fn free_all() {
-    let sb = SuperBlock::read();
+pub fn free_all(dev: usize) {
+    let sb = SuperBlock::read(dev);
     let a = sb.bitmap_area();
     let b = sb.data_area();
     for addr in a..b {
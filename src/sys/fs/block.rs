@@ -1,6 +1,6 @@
 use super::bitmap_block::BitmapBlock;
-use super::block_device::BlockDeviceIO;
-use super::block_device::BLOCK_DEVICE;
+use super::block_device;
+use super::block_device::{device, global_addr, local_addr};
 
 use core::convert::TryInto;
 
@@ -21,8 +21,9 @@ impl Block {
         Self { addr, buf }
     }
 
-    pub fn alloc() -> Option<Self> {
-        match BitmapBlock::next_free_addr() {
+    // Allocate a block on a device
+    pub fn alloc(dev: usize) -> Option<Self> {
+        match BitmapBlock::next_free_addr(dev) {
             None => None,
             Some(addr) => {
                 BitmapBlock::alloc(addr);
@@ -41,19 +42,22 @@ impl Block {
 
     pub fn read(addr: u32) -> Self {
         let mut buf = [0; super::BLOCK_SIZE];
-        if let Some(ref mut block_device) = *BLOCK_DEVICE.lock() {
-            if block_device.read(addr, &mut buf).is_err() {
-                debug!("MFS: could not read block {:#X}", addr);
-            }
+        if block_device::read(addr, &mut buf).is_err() {
+            debug!("MFS: could not read block {:#X}", addr);
         }
         Self { addr, buf }
     }
 
     pub fn write(&self) {
-        if let Some(ref mut block_device) = *BLOCK_DEVICE.lock() {
-            if block_device.write(self.addr, &self.buf).is_err() {
-                debug!("MFS: could not write block {:#X}", self.addr);
-            }
+        if block_device::write(self.addr, &self.buf).is_err() {
+            debug!("MFS: could not write block {:#X}", self.addr);
+        }
+    }
+
+    // Write the content of a file, which is not journaled
+    pub fn write_data(&self) {
+        if block_device::write_data(self.addr, &self.buf).is_err() {
+            debug!("MFS: could not write block {:#X}", self.addr);
         }
     }
 
@@ -87,8 +91,8 @@ impl LinkedBlock {
         }
     }
 
-    pub fn alloc() -> Option<Self> {
-        Block::alloc().map(|block| Self { block })
+    pub fn alloc(dev: usize) -> Option<Self> {
+        Block::alloc(dev).map(|block| Self { block })
     }
 
     pub fn read(addr: u32) -> Self {
@@ -101,6 +105,10 @@ impl LinkedBlock {
         self.block.write()
     }
 
+    pub fn write_data(&self) {
+        self.block.write_data()
+    }
+
     pub fn addr(&self) -> u32 {
         self.block.addr()
     }
@@ -122,18 +130,19 @@ impl LinkedBlock {
         if addr == 0 {
             None
         } else {
-            Some(Self::read(addr))
+            Some(Self::read(global_addr(device(self.addr()), addr)))
         }
     }
 
     pub fn alloc_next(&mut self) -> Option<Self> {
-        let new_block = LinkedBlock::alloc()?;
+        let new_block = LinkedBlock::alloc(device(self.addr()))?;
         self.set_next_addr(new_block.addr());
         self.write();
         Some(new_block)
     }
 
     pub fn set_next_addr(&mut self, addr: u32) {
+        let addr = local_addr(addr);
         self.block.buf[0..4].clone_from_slice(&addr.to_be_bytes());
     }
 }
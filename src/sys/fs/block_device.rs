@@ -1,23 +1,151 @@
 use super::bitmap_block::BitmapBlock;
 use super::dir::Dir;
-use super::super_block::SuperBlock;
+use super::iso9660::Iso;
+use super::journal::{self, Journal, JOURNAL_SIZE};
+use super::super_block::{SuperBlock, SUPERBLOCK_ADDR};
 
 use crate::sys;
 
-use alloc::vec;
+use alloc::boxed::Box;
+use alloc::collections::btree_map::BTreeMap;
+use alloc::format;
+use alloc::string::String;
 use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use spin::Mutex;
 
-pub static BLOCK_DEVICE: Mutex<Option<BlockDevice>> = Mutex::new(None);
+// The block devices of the mounted filesystems are kept in a table where the
+// filesystem mounted on '/' has index 0. The address of a block in memory is
+// made of the index of its device in the upper bits and of its address on the
+// device in the lower bits, so that a block can be read from any mounted
+// filesystem without having to pass its device around.
+pub static MOUNTS: Mutex<Vec<Option<Mount>>> = Mutex::new(Vec::new());
+
+const DEVICE_SHIFT: u32 = 24;
+
+// Seconds after which the blocks cached by the filesystems are written back
+// to their devices when the system is idle
+const SYNC_INTERVAL: f64 = 5.0;
+
+static LAST_SYNC: AtomicU64 = AtomicU64::new(0);
+static SYNC_FAILED: AtomicBool = AtomicBool::new(false);
+
+// Maximum number of blocks that can be addressed on a device
+pub const MAX_BLOCKS: u32 = 1 << DEVICE_SHIFT;
+
+pub struct Mount {
+    pub path: String,
+    pub fs: Filesystem,
+
+    // Address of the dir hidden by the root dir of the filesystem
+    pub covered: u32,
+}
+
+impl Mount {
+    // Block device of a mounted MFS filesystem
+    pub fn block_device(&mut self) -> Option<&mut BlockDevice> {
+        match &mut self.fs {
+            Filesystem::Mfs(dev) => Some(dev),
+            _ => None,
+        }
+    }
+}
+
+pub enum Filesystem {
+    Mfs(BlockDevice),
+    Iso(Iso),
+    Proc,
+}
+
+impl Filesystem {
+    pub fn source(&self) -> String {
+        match self {
+            Filesystem::Mfs(dev) => dev.source(),
+            Filesystem::Iso(iso) => iso.source(),
+            Filesystem::Proc => "proc".into(),
+        }
+    }
+}
+
+// Index of the device of a block
+pub fn device(addr: u32) -> usize {
+    (addr >> DEVICE_SHIFT) as usize
+}
+
+// Address of a block on its device, to be written on disk
+pub fn local_addr(addr: u32) -> u32 {
+    addr & (MAX_BLOCKS - 1)
+}
+
+// Address of a block read on a device, a null address being left untouched
+pub fn global_addr(dev: usize, addr: u32) -> u32 {
+    if addr == 0 {
+        0
+    } else {
+        ((dev as u32) << DEVICE_SHIFT) | local_addr(addr)
+    }
+}
+
+// Read a block from the device of its address
+pub fn read(addr: u32, buf: &mut [u8]) -> Result<(), ()> {
+    let mut mounts = MOUNTS.lock();
+    match mounts.get_mut(device(addr)) {
+        Some(Some(mount)) => match mount.block_device() {
+            Some(dev) => dev.read(local_addr(addr), buf),
+            None => Err(()),
+        },
+        _ => Err(()),
+    }
+}
+
+// Write a block to the device of its address
+pub fn write(addr: u32, buf: &[u8]) -> Result<(), ()> {
+    let mut mounts = MOUNTS.lock();
+    match mounts.get_mut(device(addr)) {
+        Some(Some(mount)) => match mount.block_device() {
+            Some(dev) => dev.write(local_addr(addr), buf),
+            None => Err(()),
+        },
+        _ => Err(()),
+    }
+}
+
+// Write a block with the content of a file, which is not journaled
+pub fn write_data(addr: u32, buf: &[u8]) -> Result<(), ()> {
+    let mut mounts = MOUNTS.lock();
+    match mounts.get_mut(device(addr)) {
+        Some(Some(mount)) => match mount.block_device() {
+            Some(BlockDevice::Ata(dev)) => {
+                dev.write_data(local_addr(addr), buf)
+            }
+            Some(dev) => dev.write(local_addr(addr), buf),
+            None => Err(()),
+        },
+        _ => Err(()),
+    }
+}
 
 pub enum BlockDevice {
     Mem(MemBlockDevice),
     Ata(AtaBlockDevice),
 }
 
+impl BlockDevice {
+    // Path of the device used as the source of a mount
+    pub fn source(&self) -> String {
+        match self {
+            BlockDevice::Mem(_) => "mem".into(),
+            BlockDevice::Ata(dev) => {
+                format!("/dev/ata/{}/{}", dev.dev.bus, dev.dev.dsk)
+            }
+        }
+    }
+}
+
 pub trait BlockDeviceIO {
     fn read(&mut self, addr: u32, buf: &mut [u8]) -> Result<(), ()>;
     fn write(&mut self, addr: u32, buf: &[u8]) -> Result<(), ()>;
+    fn flush(&mut self) -> Result<(), ()>;
     fn block_size(&self) -> usize;
     fn block_count(&self) -> usize;
 }
@@ -37,6 +165,13 @@ impl BlockDeviceIO for BlockDevice {
         }
     }
 
+    fn flush(&mut self) -> Result<(), ()> {
+        match self {
+            BlockDevice::Mem(dev) => dev.flush(),
+            BlockDevice::Ata(dev) => dev.flush(),
+        }
+    }
+
     fn block_size(&self) -> usize {
         match self {
             BlockDevice::Mem(dev) => dev.block_size(),
@@ -52,27 +187,59 @@ impl BlockDeviceIO for BlockDevice {
     }
 }
 
+// The blocks of a device in memory are only allocated when they are written
+// with something else than zeros, the other blocks being read as zeros, so
+// that the length of the device is a limit rather than a reservation of
+// memory, and that its reserved area and free blocks take no memory.
 pub struct MemBlockDevice {
-    dev: Vec<[u8; super::BLOCK_SIZE]>,
+    dev: BTreeMap<u32, Box<[u8; super::BLOCK_SIZE]>>,
+    len: usize,
 }
 
 impl MemBlockDevice {
     pub fn new(len: usize) -> Self {
-        let dev = vec![[0; super::BLOCK_SIZE]; len];
-        Self { dev }
+        let dev = BTreeMap::new();
+        Self { dev, len }
+    }
+
+    // Create a device with room for the given number of bytes in the data
+    // area of its filesystem, rounded up to the number of blocks tracked by
+    // a bitmap block
+    pub fn with_capacity(size: usize) -> Self {
+        let bs = super::BITMAP_SIZE;
+        let n = size.div_ceil(super::BLOCK_SIZE).div_ceil(bs).max(1);
+        Self::new(SUPERBLOCK_ADDR as usize + 2 + n + n * bs)
     }
 }
 
 impl BlockDeviceIO for MemBlockDevice {
     fn read(&mut self, block_index: u32, buf: &mut [u8]) -> Result<(), ()> {
-        // TODO: check for overflow
-        buf[..].clone_from_slice(&self.dev[block_index as usize][..]);
+        if block_index as usize >= self.len {
+            return Err(());
+        }
+        match self.dev.get(&block_index) {
+            Some(block) => buf[..].clone_from_slice(&block[..]),
+            None => buf.fill(0),
+        }
         Ok(())
     }
 
     fn write(&mut self, block_index: u32, buf: &[u8]) -> Result<(), ()> {
-        // TODO: check for overflow
-        self.dev[block_index as usize][..].clone_from_slice(buf);
+        if block_index as usize >= self.len {
+            return Err(());
+        }
+        if buf.iter().all(|b| *b == 0) {
+            self.dev.remove(&block_index);
+        } else {
+            let block = self.dev.entry(block_index).or_insert_with(|| {
+                Box::new([0; super::BLOCK_SIZE])
+            });
+            block[..].clone_from_slice(buf);
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), ()> {
         Ok(())
     }
 
@@ -81,39 +248,62 @@ impl BlockDeviceIO for MemBlockDevice {
     }
 
     fn block_count(&self) -> usize {
-        self.dev.len()
+        self.len
     }
 }
 
+// Mount a filesystem in memory on '/' with a quarter of the allocatable
+// memory, like the filesystems mounted in memory on a dir
 pub fn mount_mem() {
-    let mem = sys::allocator::memory_size() / 2; // Half the allocatable memory
-    let len = mem / super::BLOCK_SIZE; // TODO: take a size argument
-    let dev = MemBlockDevice::new(len);
-    *BLOCK_DEVICE.lock() = Some(BlockDevice::Mem(dev));
+    let size = sys::allocator::memory_size() / 4;
+    let dev = MemBlockDevice::with_capacity(size);
+    mount_root(BlockDevice::Mem(dev));
 }
 
 pub fn format_mem() {
     debug_assert!(is_mounted());
-    if let Some(sb) = SuperBlock::new() {
-        sb.write();
-        let root = Dir::root();
-        BitmapBlock::alloc(root.addr());
-    }
+    format(0).ok();
+}
+
+// Write an empty filesystem on a device
+fn format(dev: usize) -> Result<(), ()> {
+    let sb = SuperBlock::new(dev).ok_or(())?;
+    sb.write();
+    let root = Dir::root_of(dev);
+    BitmapBlock::alloc(root.addr());
+    Ok(())
 }
 
 const ATA_CACHE_SIZE: usize = 1024;
 
-#[derive(Clone)]
+// A block kept in the cache of a drive, with its content, whether it has
+// been written since it was read from the drive, and whether it contains
+// metadata rather than the content of a file
+struct CachedBlock {
+    addr: u32,
+    buf: Vec<u8>,
+    is_dirty: bool,
+    is_metadata: bool,
+}
+
+// The blocks of a drive are cached when they are read or written, and the
+// written blocks are only written back to the drive when they are evicted
+// from the cache to make room for another block, or when the cache is
+// flushed. The metadata is written through the journal of the filesystem
+// when it has one. The cache is allocated on the heap because it would not
+// fit on the stack of the kernel.
 pub struct AtaBlockDevice {
-    cache: [Option<(u32, Vec<u8>)>; ATA_CACHE_SIZE],
+    cache: Box<[Option<CachedBlock>]>,
     dev: sys::ata::Drive,
+    journal: Option<Journal>,
 }
 
 impl AtaBlockDevice {
     pub fn new(bus: u8, dsk: u8) -> Option<Self> {
         sys::ata::Drive::open(bus, dsk).map(|dev| {
-            let cache = [(); ATA_CACHE_SIZE].map(|_| None);
-            Self { dev, cache }
+            let cache = (0..ATA_CACHE_SIZE).map(|_| None).collect();
+            let journal = None;
+            Self { dev, cache, journal }
         })
     }
 
@@ -129,22 +319,68 @@ impl AtaBlockDevice {
 
     fn cached_block(&self, block_addr: u32) -> Option<&[u8]> {
         let h = self.hash(block_addr);
-        if let Some((cached_addr, cached_buf)) = &self.cache[h] {
-            if block_addr == *cached_addr {
-                return Some(cached_buf);
+        if let Some(cached) = &self.cache[h] {
+            if block_addr == cached.addr {
+                return Some(&cached.buf);
             }
         }
         None
     }
 
-    fn set_cached_block(&mut self, block_addr: u32, buf: &[u8]) {
+    // Put a block in the cache, writing back the block it replaces if it
+    // has not been written to the drive yet. The metadata cannot be written
+    // alone so the whole cache is flushed in that case.
+    fn set_cached_block(
+        &mut self,
+        block_addr: u32,
+        buf: &[u8],
+        is_dirty: bool,
+        is_metadata: bool
+    ) -> Result<(), ()> {
         let h = self.hash(block_addr);
-        self.cache[h] = Some((block_addr, buf.to_vec()));
+        let mut is_metadata = is_metadata;
+        match &self.cache[h] {
+            Some(cached) if cached.addr == block_addr => {
+                // A block holding metadata keeps holding it until flushed
+                is_metadata |= cached.is_dirty && cached.is_metadata;
+            }
+            Some(cached) if cached.is_dirty && cached.is_metadata => {
+                self.flush()?;
+            }
+            Some(cached) if cached.is_dirty => {
+                let (bus, dsk) = (self.dev.bus, self.dev.dsk);
+                sys::ata::write(bus, dsk, cached.addr, &cached.buf)?;
+            }
+            _ => {}
+        }
+        let addr = block_addr;
+        let buf = buf.to_vec();
+        let block = CachedBlock { addr, buf, is_dirty, is_metadata };
+        self.cache[h] = Some(block);
+        Ok(())
     }
 
-    fn unset_cached_block(&mut self, block_addr: u32) {
-        let h = self.hash(block_addr);
-        self.cache[h] = None;
+    pub fn write_data(
+        &mut self,
+        block_addr: u32,
+        buf: &[u8]
+    ) -> Result<(), ()> {
+        self.set_cached_block(block_addr, buf, true, false)
+    }
+
+    pub fn set_journal(&mut self, journal: Option<Journal>) {
+        self.journal = journal;
+    }
+
+    // Indexes of the dirty blocks of the cache in the order of their
+    // addresses to limit the seeks of the drive
+    fn dirty_blocks(&self, is_metadata: bool) -> Vec<usize> {
+        let mut dirty: Vec<usize> = (0..self.cache.len()).filter(|i| {
+            matches!(&self.cache[*i], Some(cached) if cached.is_dirty &&
+                cached.is_metadata == is_metadata)
+        }).collect();
+        dirty.sort_by_key(|i| self.cache[*i].as_ref().map(|c| c.addr));
+        dirty
     }
 }
 
@@ -156,13 +392,51 @@ impl BlockDeviceIO for AtaBlockDevice {
         }
 
         sys::ata::read(self.dev.bus, self.dev.dsk, block_addr, buf)?;
-        self.set_cached_block(block_addr, buf);
-        Ok(())
+        self.set_cached_block(block_addr, buf, false, false)
     }
 
     fn write(&mut self, block_addr: u32, buf: &[u8]) -> Result<(), ()> {
-        sys::ata::write(self.dev.bus, self.dev.dsk, block_addr, buf)?;
-        self.unset_cached_block(block_addr);
+        self.set_cached_block(block_addr, buf, true, true)
+    }
+
+    // The content of the files is written before the metadata pointing to
+    // it, and the metadata is written in transactions through the journal.
+    // The write cache of the drive is flushed before the first transaction,
+    // so a committed transaction never points to content that is not on the
+    // disk yet, including the blocks evicted from the cache before the flush.
+    // The content itself is not journaled. The metadata is committed in a
+    // single transaction so that an operation is never split between two of
+    // them, and it is written in place when it doesn't fit in the journal.
+    fn flush(&mut self) -> Result<(), ()> {
+        let (bus, dsk) = (self.dev.bus, self.dev.dsk);
+        for i in self.dirty_blocks(false) {
+            if let Some(cached) = &mut self.cache[i] {
+                sys::ata::write(bus, dsk, cached.addr, &cached.buf)?;
+                cached.is_dirty = false;
+            }
+        }
+        let dirty = self.dirty_blocks(true);
+        if !dirty.is_empty() {
+            sys::ata::flush_cache(bus, dsk)?;
+        }
+        let blocks: Vec<(u32, &[u8])> = dirty.iter().filter_map(|i| {
+            self.cache[*i].as_ref().map(|c| (c.addr, c.buf.as_slice()))
+        }).collect();
+        match &self.journal {
+            Some(journal) if blocks.len() <= JOURNAL_SIZE => {
+                journal.commit(&self.dev, &blocks)?;
+            }
+            _ => {
+                for (addr, buf) in blocks {
+                    sys::ata::write(bus, dsk, addr, buf)?;
+                }
+            }
+        }
+        for i in dirty {
+            if let Some(cached) = &mut self.cache[i] {
+                cached.is_dirty = false;
+            }
+        }
         Ok(())
     }
 
@@ -176,30 +450,228 @@ impl BlockDeviceIO for AtaBlockDevice {
 }
 
 pub fn mount_ata(bus: u8, dsk: u8) {
-    *BLOCK_DEVICE.lock() = AtaBlockDevice::new(bus, dsk).map(BlockDevice::Ata);
+    if let Some(dev) = AtaBlockDevice::new(bus, dsk) {
+        mount_root(BlockDevice::Ata(dev));
+        if SuperBlock::check(0) && journal::load(0).is_err() {
+            debug!("MFS: could not load journal");
+        }
+    } else {
+        dismount();
+    }
 }
 
 pub fn format_ata() {
-    if let Some(sb) = SuperBlock::new() {
+    // The journal of a previous filesystem is in the data area
+    journal::set(0, None);
+
+    if let Some(sb) = SuperBlock::new(0) {
         // Write super_block
         sb.write();
 
         // Write zeros into block bitmaps
-        super::bitmap_block::free_all();
+        super::bitmap_block::free_all(0);
 
         // Allocate root dir
         debug_assert!(is_mounted());
         let root = Dir::root();
         BitmapBlock::alloc(root.addr());
+
+        if journal::load(0).is_err() {
+            debug!("MFS: could not create journal");
+        }
     }
 }
 
+// Replace the filesystem mounted on '/' along with every other mount
+fn mount_root(dev: BlockDevice) {
+    let mut mounts = MOUNTS.lock();
+    flush(&mut mounts).ok();
+    mounts.clear();
+    let path = "/".into();
+    let fs = Filesystem::Mfs(dev);
+    mounts.push(Some(Mount { path, fs, covered: 0 }));
+}
+
+// Mount a filesystem on an existing dir, formatting it first if it is in
+// memory
+pub fn mount(fs: Filesystem, path: &str, covered: u32) -> Result<(), ()> {
+    if !is_mounted() || is_mount_point(path) {
+        return Err(());
+    }
+    let is_mfs = matches!(fs, Filesystem::Mfs(_));
+    let is_mem = matches!(fs, Filesystem::Mfs(BlockDevice::Mem(_)));
+    let i = {
+        let mut mounts = MOUNTS.lock();
+        let path = path.into();
+        let mount = Some(Mount { path, fs, covered });
+        match mounts.iter().position(|m| m.is_none()) {
+            Some(i) => {
+                mounts[i] = mount;
+                i
+            }
+            None => {
+                mounts.push(mount);
+                mounts.len() - 1
+            }
+        }
+    };
+    let res = if is_mem {
+        format(i)
+    } else if !is_mfs {
+        Ok(())
+    } else if SuperBlock::check(i) {
+        journal::load(i)
+    } else {
+        Err(())
+    };
+    if res.is_err() {
+        MOUNTS.lock()[i] = None;
+    }
+    res
+}
+
+// Unmount the filesystem of a mount point unless other filesystems are
+// mounted below it
+pub fn umount(path: &str) -> Result<(), ()> {
+    if path == "/" {
+        return Err(());
+    }
+    let prefix = format!("{}/", path);
+    let mut mounts = MOUNTS.lock();
+    if mounts.iter().flatten().any(|m| m.path.starts_with(&prefix)) {
+        return Err(());
+    }
+    for mount in mounts.iter_mut() {
+        if let Some(m) = mount.as_mut().filter(|m| m.path == path) {
+            if let Some(dev) = m.block_device() {
+                dev.flush()?;
+            }
+            *mount = None;
+            return Ok(());
+        }
+    }
+    Err(())
+}
+
+pub fn is_mount_point(path: &str) -> bool {
+    MOUNTS.lock().iter().flatten().any(|m| m.path == path)
+}
+
+// Path of the mount point of the filesystem containing a path
+pub fn mount_point(path: &str) -> Option<String> {
+    let prefix = |m: &&Mount| format!("{}/", m.path.trim_end_matches('/'));
+    MOUNTS.lock().iter().flatten().filter(|m| {
+        path == m.path || path.starts_with(&prefix(m))
+    }).max_by_key(|m| m.path.len()).map(|m| m.path.clone())
+}
+
+// Find the filesystem mounted on a path that is not reached through the dir
+// it covers, like an image or a virtual filesystem, and return what the
+// closure takes from it along with the path of the file in the filesystem
+pub fn find_mount<T, F>(path: &str, f: F) -> Option<(T, String)>
+where
+    F: Fn(&Filesystem) -> Option<T>,
+{
+    let mounts = MOUNTS.lock();
+    for mount in mounts.iter().flatten() {
+        if let Some(fs) = f(&mount.fs) {
+            if path == mount.path {
+                return Some((fs, "/".into()));
+            }
+            let prefix = format!("{}/", mount.path);
+            if let Some(rest) = path.strip_prefix(&prefix) {
+                return Some((fs, format!("/{}", rest)));
+            }
+        }
+    }
+    None
+}
+
+// Check if a path is the mount point of a filesystem that is not MFS, the
+// rest of a path below it being found by the filesystem itself
+pub fn is_path_mount_point(path: &str) -> bool {
+    MOUNTS.lock().iter().flatten().any(|m| {
+        m.path == path && !matches!(m.fs, Filesystem::Mfs(_))
+    })
+}
+
+// Index of the device of the MFS filesystem mounted on a path
+pub fn mfs_device(path: &str) -> Option<usize> {
+    MOUNTS.lock().iter().position(|mount| match mount {
+        Some(m) => m.path == path && matches!(m.fs, Filesystem::Mfs(_)),
+        None => false,
+    })
+}
+
+// Index and path of each mounted MFS filesystem
+pub fn mfs_mounts() -> Vec<(usize, String)> {
+    MOUNTS.lock().iter().enumerate().filter_map(|(i, mount)| match mount {
+        Some(m) if matches!(m.fs, Filesystem::Mfs(_)) => {
+            Some((i, m.path.clone()))
+        }
+        _ => None,
+    }).collect()
+}
+
+// Index of the device mounted on the dir at the given address
+pub fn mounted_device(covered: u32) -> Option<usize> {
+    MOUNTS.lock().iter().position(|mount| {
+        matches!(mount, Some(m) if m.covered == covered && covered != 0)
+    })
+}
+
+// List the source and path of each mounted filesystem
+pub fn mounts() -> Vec<(String, String)> {
+    MOUNTS.lock().iter().flatten().map(|mount| {
+        (mount.fs.source(), mount.path.clone())
+    }).collect()
+}
+
 pub fn is_mounted() -> bool {
-    BLOCK_DEVICE.lock().is_some()
+    matches!(MOUNTS.lock().first(), Some(Some(_)))
 }
 
 pub fn dismount() {
-    *BLOCK_DEVICE.lock() = None;
+    let mut mounts = MOUNTS.lock();
+    flush(&mut mounts).ok();
+    mounts.clear();
+}
+
+fn flush(mounts: &mut [Option<Mount>]) -> Result<(), ()> {
+    let mut res = Ok(());
+    for mount in mounts.iter_mut().flatten() {
+        if let Some(dev) = mount.block_device() {
+            if dev.flush().is_err() {
+                res = Err(());
+            }
+        }
+    }
+    LAST_SYNC.store(sys::clock::uptime().to_bits(), Ordering::Relaxed);
+    res
+}
+
+// Write the blocks cached by the mounted filesystems to their devices
+pub fn sync() -> Result<(), ()> {
+    flush(&mut MOUNTS.lock())
+}
+
+// Sync the filesystems when their cached blocks are getting old, unless
+// they are being used. The blocks that could not be written stay dirty to be
+// written again at the next sync, and the failure is only logged once until
+// a sync succeeds.
+pub fn sync_if_due() {
+    let last = f64::from_bits(LAST_SYNC.load(Ordering::Relaxed));
+    if sys::clock::uptime() - last < SYNC_INTERVAL {
+        return;
+    }
+    if let Some(mut mounts) = MOUNTS.try_lock() {
+        let failed = flush(&mut mounts).is_err();
+        if failed && !SYNC_FAILED.swap(true, Ordering::Relaxed) {
+            log!("MFS: could not write cached blocks");
+        } else if !failed {
+            SYNC_FAILED.store(false, Ordering::Relaxed);
+        }
+    }
 }
 
 #[test_case]
@@ -0,0 +1,467 @@
+use super::bitmap_block::{BitmapBlock, BITMAP_SIZE};
+use super::block::{Block, LinkedBlock};
+use super::block_device::{device, global_addr, local_addr};
+use super::dir::Dir;
+use super::journal;
+use super::node::{Node, NODE_FLAG};
+use super::super_block::SuperBlock;
+
+use alloc::collections::btree_map::BTreeMap;
+use alloc::collections::btree_set::BTreeSet;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use bit_field::BitField;
+use core::convert::TryInto;
+use core::fmt;
+
+// A filesystem is checked by walking its tree from the root dir to find the
+// owner of every block used by the dirs, the files, the nodes of the hard
+// links and the journal, then by comparing them with the allocation bitmap.
+//
+// The entries of a dir with a broken chain of blocks are not read until it
+// has been repaired, so the blocks allocated without being used are only
+// reported when the whole tree has been walked.
+
+// Location of the address of a block
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Link {
+    Entry(u32, usize), // Offset of an entry in the data of a dir block
+    Node(u32),         // Node of a hard link
+    Block(u32),        // Previous block of a chain
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    // Total of blocks allocated in the superblock and in the bitmap
+    AllocCount(u32, u32),
+
+    // Blocks used by files but free in the bitmap
+    Unallocated(Vec<u32>),
+
+    // Node with the number of entries pointing to it
+    Links(String, u32, u32),
+
+    // Chain of blocks coming back to one of its blocks
+    Loop(String, Link),
+
+    // Blocks allocated in the bitmap but used by no file
+    Orphans(Vec<u32>),
+
+    // Address outside of the data area or of a node used by another file
+    InvalidAddr(String, Link),
+
+    // Block already used by another file
+    CrossLinked(String, u32, Link),
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::AllocCount(a, b) => {
+                write!(f, "Superblock counts {} allocated blocks instead of {}",
+                    a, b)
+            }
+            Problem::Unallocated(addrs) => {
+                write!(f, "{} blocks used by files are free in the bitmap",
+                    addrs.len())
+            }
+            Problem::Links(path, _, n) => {
+                write!(f, "Node of '{}' should have {} links", path, n)
+            }
+            Problem::Loop(path, _) => {
+                write!(f, "Blocks of '{}' are linked in a loop", path)
+            }
+            Problem::Orphans(addrs) => {
+                write!(f, "{} blocks are allocated but not used",
+                    addrs.len())
+            }
+            Problem::InvalidAddr(path, _) => {
+                write!(f, "Blocks of '{}' have an invalid address", path)
+            }
+            Problem::CrossLinked(path, addr, _) => {
+                write!(f, "Block {:#X} of '{}' is used by another file",
+                    local_addr(*addr), path)
+            }
+        }
+    }
+}
+
+impl Problem {
+    // Explain how the problem would be repaired
+    pub fn repair_info(&self) -> &str {
+        match self {
+            Problem::AllocCount(..) => "Update count",
+            Problem::Unallocated(_) => "Allocate blocks",
+            Problem::Links(..) => "Update links",
+            Problem::Loop(..) => "Truncate file",
+            Problem::Orphans(_) => "Free blocks",
+            Problem::InvalidAddr(_, Link::Block(_)) => "Truncate file",
+            Problem::InvalidAddr(..) => "Empty file",
+            Problem::CrossLinked(..) => "Copy blocks",
+        }
+    }
+
+    // Repair the problem found in the filesystem of a mounted device
+    pub fn repair(&self, dev: usize) -> Result<(), ()> {
+        match self {
+            Problem::AllocCount(_, n) => {
+                let mut sb = SuperBlock::read(dev);
+                sb.alloc_count = *n;
+                sb.write();
+            }
+            Problem::Unallocated(addrs) => {
+                for addr in addrs {
+                    BitmapBlock::alloc(*addr);
+                }
+            }
+            Problem::Links(_, addr, n) => {
+                let mut node = Node::read(*addr);
+                node.set_links(*n);
+                node.write();
+            }
+            Problem::Loop(_, link) => {
+                set_link(*link, 0);
+            }
+            Problem::Orphans(addrs) => {
+                for addr in addrs {
+                    BitmapBlock::free(*addr);
+                }
+            }
+            Problem::InvalidAddr(_, Link::Block(addr)) => {
+                set_link(Link::Block(*addr), 0);
+            }
+            Problem::InvalidAddr(_, link) => {
+                let block = LinkedBlock::alloc(dev).ok_or(())?;
+                set_link(*link, block.addr());
+                clear_size(*link);
+            }
+            Problem::CrossLinked(_, addr, link) => {
+                let copy = copy_chain(*addr).ok_or(())?;
+                set_link(*link, copy);
+            }
+        }
+        Ok(())
+    }
+}
+
+// Address of the next block of a chain
+fn next_addr(addr: u32) -> u32 {
+    let block = Block::read(addr);
+    let next = u32::from_be_bytes(block.data()[0..4].try_into().unwrap());
+    global_addr(device(addr), next)
+}
+
+// Point a link to a block or to nothing with a null address
+fn set_link(link: Link, addr: u32) {
+    let bytes = local_addr(addr).to_be_bytes();
+    match link {
+        Link::Entry(block_addr, i) => {
+            // The entry points to the data of the file instead of a node
+            let mut block = LinkedBlock::read(block_addr);
+            let data = block.data_mut();
+            data[i] &= !NODE_FLAG;
+            data[(i + 1)..(i + 5)].clone_from_slice(&bytes);
+            block.write();
+        }
+        Link::Node(node_addr) => {
+            let mut block = Block::read(node_addr);
+            block.data_mut()[4..8].clone_from_slice(&bytes);
+            block.write();
+        }
+        Link::Block(block_addr) => {
+            let mut block = LinkedBlock::read(block_addr);
+            block.set_next_addr(addr);
+            block.write();
+        }
+    }
+}
+
+fn clear_size(link: Link) {
+    match link {
+        Link::Entry(block_addr, i) => {
+            let mut block = LinkedBlock::read(block_addr);
+            block.data_mut()[(i + 5)..(i + 9)].clone_from_slice(&[0; 4]);
+            block.write();
+        }
+        Link::Node(node_addr) => {
+            let mut node = Node::read(node_addr);
+            node.set_size(0);
+            node.write();
+        }
+        Link::Block(_) => {}
+    }
+}
+
+// Copy a chain of blocks from one of its blocks into new blocks
+fn copy_chain(addr: u32) -> Option<u32> {
+    let mut src = LinkedBlock::read(addr);
+    let mut dst = LinkedBlock::alloc(device(addr))?;
+    let first = dst.addr();
+    let mut copied = BTreeSet::new();
+    loop {
+        copied.insert(src.addr());
+        dst.data_mut().clone_from_slice(src.data());
+        match src.next() {
+            Some(next) if !copied.contains(&next.addr()) => {
+                dst = dst.alloc_next()?;
+                src = next;
+            }
+            _ => {
+                dst.write();
+                return Some(first);
+            }
+        }
+    }
+}
+
+struct Checker {
+    dev: usize,
+    data_area: u32,
+    end: u32,
+    paths: Vec<String>,
+    owners: BTreeMap<u32, usize>,
+    nodes: BTreeMap<u32, (usize, u32)>,
+    problems: Vec<Problem>,
+    is_complete: bool,
+}
+
+impl Checker {
+    fn new(dev: usize) -> Self {
+        let sb = SuperBlock::read(dev);
+        Self {
+            dev,
+            data_area: sb.data_area(),
+            end: global_addr(dev, sb.block_count()),
+            paths: Vec::new(),
+            owners: BTreeMap::new(),
+            nodes: BTreeMap::new(),
+            problems: Vec::new(),
+            is_complete: true,
+        }
+    }
+
+    fn is_valid(&self, addr: u32) -> bool {
+        self.data_area <= addr && addr < self.end
+    }
+
+    fn add_file(&mut self, path: String) -> usize {
+        self.paths.push(path);
+        self.paths.len() - 1
+    }
+
+    // Mark the blocks of a chain, returning false if it is broken
+    fn walk(&mut self, file: usize, addr: u32, link: Link) -> bool {
+        let (mut addr, mut link) = (addr, link);
+        loop {
+            let path = self.paths[file].clone();
+            if !self.is_valid(addr) {
+                self.problems.push(Problem::InvalidAddr(path, link));
+                return false;
+            }
+            match self.owners.get(&addr) {
+                Some(owner) if *owner == file => {
+                    self.problems.push(Problem::Loop(path, link));
+                    return false;
+                }
+                Some(_) => {
+                    let problem = Problem::CrossLinked(path, addr, link);
+                    self.problems.push(problem);
+                    return false;
+                }
+                None => {
+                    self.owners.insert(addr, file);
+                }
+            }
+            match next_addr(addr) {
+                0 => return true,
+                next => {
+                    link = Link::Block(addr);
+                    addr = next;
+                }
+            }
+        }
+    }
+
+    // Mark the node of a hard link and the blocks of its file the first
+    // time it is found
+    fn walk_node(&mut self, file: usize, addr: u32, link: Link) -> bool {
+        if let Some((_, links)) = self.nodes.get_mut(&addr) {
+            *links += 1;
+            return false;
+        }
+        if !self.is_valid(addr) || self.owners.contains_key(&addr) {
+            let path = self.paths[file].clone();
+            self.problems.push(Problem::InvalidAddr(path, link));
+            return false;
+        }
+        self.owners.insert(addr, file);
+        self.nodes.insert(addr, (file, 1));
+        let data_addr = Node::read(addr).data_addr();
+        self.walk(file, data_addr, Link::Node(addr))
+    }
+
+    fn walk_tree(&mut self) {
+        let root = Dir::root_of(self.dev);
+        let file = self.add_file(String::from("/"));
+        self.owners.insert(root.addr(), file);
+        let next = next_addr(root.addr());
+        let mut dirs = Vec::new();
+        if next == 0 || self.walk(file, next, Link::Block(root.addr())) {
+            dirs.push((file, root));
+        } else {
+            self.is_complete = false;
+        }
+
+        while let Some((parent, dir)) = dirs.pop() {
+            let dirname = self.paths[parent].trim_end_matches('/');
+            let dirname = String::from(dirname);
+            let mut entries = dir.entries();
+            while let Some(entry) = entries.next() {
+                let path = format!("{}/{}", dirname, entry.name());
+                let i = entries.block_offset() - entry.len();
+                let link = Link::Entry(entries.block_addr(), i);
+                let file = self.add_file(path);
+                let is_walked = match entry.node() {
+                    Some(addr) => self.walk_node(file, addr, link),
+                    None => self.walk(file, entry.addr(), link),
+                };
+                if entry.is_dir() {
+                    if is_walked {
+                        dirs.push((file, Dir::from_entry(entry)));
+                    } else if entry.node().is_none() {
+                        self.is_complete = false;
+                    }
+                }
+            }
+        }
+    }
+
+    fn walk_journal(&mut self) {
+        let file = self.add_file(String::from("journal"));
+        for addr in journal::blocks(self.dev) {
+            if self.is_valid(addr) {
+                self.owners.insert(addr, file);
+            }
+        }
+    }
+
+    fn check_links(&mut self) {
+        for (addr, (file, links)) in &self.nodes {
+            if Node::read(*addr).links() != *links {
+                let path = self.paths[*file].clone();
+                self.problems.push(Problem::Links(path, *addr, *links));
+            }
+        }
+    }
+
+    // Compare the blocks found with the bitmap
+    fn check_bitmap(&mut self) {
+        let sb = SuperBlock::read(self.dev);
+        let mut count = 0;
+        let mut unallocated = Vec::new();
+        let mut orphans = Vec::new();
+        let bs = BITMAP_SIZE as u32;
+        let n = sb.data_area() - sb.bitmap_area();
+        for i in 0..n {
+            let block = Block::read(sb.bitmap_area() + i);
+            let bitmap = block.data();
+            for j in 0..bs {
+                let addr = self.data_area + i * bs + j;
+                if addr >= self.end {
+                    break;
+                }
+                let k = j as usize;
+                let is_alloc = bitmap[k / 8].get_bit(k % 8);
+                let is_used = self.owners.contains_key(&addr);
+                if is_alloc {
+                    count += 1;
+                }
+                match (is_alloc, is_used) {
+                    (true, false) => orphans.push(addr),
+                    (false, true) => unallocated.push(addr),
+                    _ => {}
+                }
+            }
+        }
+        if sb.alloc_count != count {
+            self.problems.push(Problem::AllocCount(sb.alloc_count, count));
+        }
+        if !unallocated.is_empty() {
+            self.problems.push(Problem::Unallocated(unallocated));
+        }
+        if !orphans.is_empty() && self.is_complete {
+            self.problems.push(Problem::Orphans(orphans));
+        }
+    }
+}
+
+// Check the filesystem of a mounted device, returning its problems in the
+// order they should be repaired: the bitmap is fixed before allocating the
+// blocks needed by the other repairs
+pub fn check(dev: usize) -> Vec<Problem> {
+    let mut checker = Checker::new(dev);
+    checker.walk_journal();
+    checker.walk_tree();
+    checker.check_links();
+    checker.check_bitmap();
+
+    let mut problems = checker.problems;
+    problems.sort_by_key(|problem| match problem {
+        Problem::AllocCount(..) => 0,
+        Problem::Unallocated(_) => 1,
+        Problem::Links(..) => 2,
+        Problem::Loop(..) => 3,
+        Problem::Orphans(_) => 4,
+        Problem::InvalidAddr(..) => 5,
+        Problem::CrossLinked(..) => 6,
+    });
+    problems
+}
+
+#[test_case]
+fn test_check() {
+    use super::{File, FileIO};
+    use alloc::vec;
+
+    super::mount_mem();
+    super::format_mem();
+    assert_eq!(check(0), vec![]);
+
+    let mut file = File::create("/a.txt").unwrap();
+    assert_eq!(file.write(&[b'a'; 1000]), Ok(1000));
+    assert!(File::create("/b.txt").is_some());
+    assert!(super::link("/a.txt", "/c.txt").is_ok());
+    assert_eq!(check(0), vec![]);
+
+    // Cross link the first block of b.txt to the second block of a.txt
+    let root = Dir::root();
+    let a = root.find("a.txt").unwrap().addr();
+    let mut entries = root.entries();
+    let b = entries.find(|entry| entry.name() == "b.txt").unwrap();
+    let i = entries.block_offset() - b.len();
+    let link = Link::Entry(entries.block_addr(), i);
+    BitmapBlock::free(b.addr());
+    set_link(link, next_addr(a));
+    let problems = check(0);
+    let addr = next_addr(a);
+    assert_eq!(problems, [Problem::CrossLinked("/b.txt".into(), addr, link)]);
+    assert!(problems[0].repair(0).is_ok());
+    assert_eq!(check(0), vec![]);
+
+    // Orphaned blocks and wrong counts
+    let block = Block::alloc(0).unwrap();
+    let mut sb = SuperBlock::read(0);
+    sb.alloc_count += 1;
+    sb.write();
+    let n = sb.alloc_count;
+    let problems = check(0);
+    assert_eq!(problems[0], Problem::AllocCount(n, n - 1));
+    assert_eq!(problems[1], Problem::Orphans(vec![block.addr()]));
+    for problem in problems {
+        assert!(problem.repair(0).is_ok());
+    }
+    assert_eq!(check(0), vec![]);
+
+    super::dismount();
+}
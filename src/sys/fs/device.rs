@@ -1,14 +1,18 @@
 use super::block::LinkedBlock;
 use super::dir::Dir;
 use super::file::File;
-use super::{dirname, filename, realpath, FileIO, IO};
+use super::DiskUsage;
+use super::{dirname, filename, resolve, FileIO, IO};
 
 use crate::sys::ata::Drive;
 use crate::sys::clock::{Realtime, Uptime};
 use crate::sys::cmos::RTC;
 use crate::sys::console::Console;
+use crate::sys::net::socket::icmp::IcmpSocket;
 use crate::sys::net::socket::tcp::TcpSocket;
 use crate::sys::net::socket::udp::UdpSocket;
+use crate::sys::net::stat::{NetIfaces, NetSockets};
+use crate::sys::pipe::Pipe;
 use crate::sys::rng::Random;
 
 use alloc::vec;
@@ -16,19 +20,23 @@ use alloc::vec::Vec;
 use core::convert::TryFrom;
 use core::convert::TryInto;
 
-#[derive(PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[repr(u8)]
 pub enum DeviceType {
-    Null      = 0,
-    File      = 1,
-    Console   = 2,
-    Random    = 3,
-    Uptime    = 4,
-    Realtime  = 5,
-    RTC       = 6,
-    TcpSocket = 7,
-    UdpSocket = 8,
-    Drive     = 9,
+    Null       = 0,
+    File       = 1,
+    Console    = 2,
+    Random     = 3,
+    Uptime     = 4,
+    Realtime   = 5,
+    RTC        = 6,
+    TcpSocket  = 7,
+    UdpSocket  = 8,
+    Drive      = 9,
+    DiskUsage  = 10,
+    IcmpSocket = 11,
+    NetSockets = 12,
+    NetIfaces  = 13,
 }
 
 impl TryFrom<&[u8]> for DeviceType {
@@ -46,6 +54,10 @@ impl TryFrom<&[u8]> for DeviceType {
             7 => Ok(DeviceType::TcpSocket),
             8 => Ok(DeviceType::UdpSocket),
             9 => Ok(DeviceType::Drive),
+            10 => Ok(DeviceType::DiskUsage),
+            11 => Ok(DeviceType::IcmpSocket),
+            12 => Ok(DeviceType::NetSockets),
+            13 => Ok(DeviceType::NetIfaces),
             _ => Err(()),
         }
     }
@@ -57,14 +69,18 @@ impl DeviceType {
     // store specific device informations.
     pub fn buf(self) -> Vec<u8> {
         let len = match self {
-            DeviceType::RTC       => RTC::size(),
-            DeviceType::Uptime    => Uptime::size(),
-            DeviceType::Realtime  => Realtime::size(),
-            DeviceType::Console   => Console::size(),
-            DeviceType::TcpSocket => TcpSocket::size(),
-            DeviceType::UdpSocket => UdpSocket::size(),
-            DeviceType::Drive     => Drive::size(),
-            _                     => 1,
+            DeviceType::RTC        => RTC::size(),
+            DeviceType::Uptime     => Uptime::size(),
+            DeviceType::Realtime   => Realtime::size(),
+            DeviceType::Console    => Console::size(),
+            DeviceType::TcpSocket  => TcpSocket::size(),
+            DeviceType::UdpSocket  => UdpSocket::size(),
+            DeviceType::IcmpSocket => IcmpSocket::size(),
+            DeviceType::Drive      => Drive::size(),
+            DeviceType::DiskUsage  => DiskUsage::size(),
+            DeviceType::NetSockets => NetSockets::size(),
+            DeviceType::NetIfaces  => NetIfaces::size(),
+            _                      => 1,
         };
         let mut res = vec![0; len];
         res[0] = self as u8; // Device type
@@ -83,7 +99,12 @@ pub enum Device {
     RTC(RTC),
     TcpSocket(TcpSocket),
     UdpSocket(UdpSocket),
+    IcmpSocket(IcmpSocket),
     Drive(Drive),
+    DiskUsage(DiskUsage),
+    NetSockets(NetSockets),
+    NetIfaces(NetIfaces),
+    Pipe(Pipe),
 }
 
 impl TryFrom<&[u8]> for Device {
@@ -91,15 +112,23 @@ impl TryFrom<&[u8]> for Device {
 
     fn try_from(buf: &[u8]) -> Result<Self, Self::Error> {
         match buf.try_into()? {
-            DeviceType::Null      => Ok(Device::Null),
-            DeviceType::File      => Ok(Device::File(File::new())),
-            DeviceType::Console   => Ok(Device::Console(Console::new())),
-            DeviceType::Random    => Ok(Device::Random(Random::new())),
-            DeviceType::Uptime    => Ok(Device::Uptime(Uptime::new())),
-            DeviceType::Realtime  => Ok(Device::Realtime(Realtime::new())),
-            DeviceType::RTC       => Ok(Device::RTC(RTC::new())),
-            DeviceType::TcpSocket => Ok(Device::TcpSocket(TcpSocket::new())),
-            DeviceType::UdpSocket => Ok(Device::UdpSocket(UdpSocket::new())),
+            DeviceType::Null       => Ok(Device::Null),
+            DeviceType::File       => Ok(Device::File(File::new())),
+            DeviceType::Console    => Ok(Device::Console(Console::new())),
+            DeviceType::Random     => Ok(Device::Random(Random::new())),
+            DeviceType::Uptime     => Ok(Device::Uptime(Uptime::new())),
+            DeviceType::Realtime   => Ok(Device::Realtime(Realtime::new())),
+            DeviceType::RTC        => Ok(Device::RTC(RTC::new())),
+            DeviceType::TcpSocket  => Ok(Device::TcpSocket(TcpSocket::new())),
+            DeviceType::UdpSocket  => Ok(Device::UdpSocket(UdpSocket::new())),
+            DeviceType::IcmpSocket => {
+                Ok(Device::IcmpSocket(IcmpSocket::new()))
+            }
+            DeviceType::DiskUsage  => Ok(Device::DiskUsage(DiskUsage::new())),
+            DeviceType::NetSockets => {
+                Ok(Device::NetSockets(NetSockets::new()))
+            }
+            DeviceType::NetIfaces  => Ok(Device::NetIfaces(NetIfaces::new())),
             DeviceType::Drive if buf.len() > 2 => {
                 let bus = buf[1];
                 let dsk = buf[2];
@@ -116,7 +145,7 @@ impl TryFrom<&[u8]> for Device {
 
 impl Device {
     pub fn create(pathname: &str) -> Option<Self> {
-        let pathname = realpath(pathname);
+        let pathname = resolve(pathname, true)?;
         let dirname = dirname(&pathname);
         let filename = filename(&pathname);
         if let Some(mut dir) = Dir::open(dirname) {
@@ -128,7 +157,7 @@ impl Device {
     }
 
     pub fn open(pathname: &str) -> Option<Self> {
-        let pathname = realpath(pathname);
+        let pathname = resolve(pathname, true)?;
         let dirname = dirname(&pathname);
         let filename = filename(&pathname);
         if let Some(dir) = Dir::open(dirname) {
@@ -150,60 +179,80 @@ impl FileIO for Device {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, ()> {
         match self {
             Device::Null          => Err(()),
-            Device::File(io)      => io.read(buf),
-            Device::Console(io)   => io.read(buf),
-            Device::Random(io)    => io.read(buf),
-            Device::Uptime(io)    => io.read(buf),
-            Device::Realtime(io)  => io.read(buf),
-            Device::RTC(io)       => io.read(buf),
-            Device::TcpSocket(io) => io.read(buf),
-            Device::UdpSocket(io) => io.read(buf),
-            Device::Drive(io)     => io.read(buf),
+            Device::File(io)       => io.read(buf),
+            Device::Console(io)    => io.read(buf),
+            Device::Random(io)     => io.read(buf),
+            Device::Uptime(io)     => io.read(buf),
+            Device::Realtime(io)   => io.read(buf),
+            Device::RTC(io)        => io.read(buf),
+            Device::TcpSocket(io)  => io.read(buf),
+            Device::UdpSocket(io)  => io.read(buf),
+            Device::IcmpSocket(io) => io.read(buf),
+            Device::Drive(io)      => io.read(buf),
+            Device::DiskUsage(io)  => io.read(buf),
+            Device::NetSockets(io) => io.read(buf),
+            Device::NetIfaces(io)  => io.read(buf),
+            Device::Pipe(io)       => io.read(buf),
         }
     }
 
     fn write(&mut self, buf: &[u8]) -> Result<usize, ()> {
         match self {
             Device::Null          => Ok(0),
-            Device::File(io)      => io.write(buf),
-            Device::Console(io)   => io.write(buf),
-            Device::Random(io)    => io.write(buf),
-            Device::Uptime(io)    => io.write(buf),
-            Device::Realtime(io)  => io.write(buf),
-            Device::RTC(io)       => io.write(buf),
-            Device::TcpSocket(io) => io.write(buf),
-            Device::UdpSocket(io) => io.write(buf),
-            Device::Drive(io)     => io.write(buf),
+            Device::File(io)       => io.write(buf),
+            Device::Console(io)    => io.write(buf),
+            Device::Random(io)     => io.write(buf),
+            Device::Uptime(io)     => io.write(buf),
+            Device::Realtime(io)   => io.write(buf),
+            Device::RTC(io)        => io.write(buf),
+            Device::TcpSocket(io)  => io.write(buf),
+            Device::UdpSocket(io)  => io.write(buf),
+            Device::IcmpSocket(io) => io.write(buf),
+            Device::Drive(io)      => io.write(buf),
+            Device::DiskUsage(io)  => io.write(buf),
+            Device::NetSockets(io) => io.write(buf),
+            Device::NetIfaces(io)  => io.write(buf),
+            Device::Pipe(io)       => io.write(buf),
         }
     }
 
     fn close(&mut self) {
         match self {
             Device::Null          => {}
-            Device::File(io)      => io.close(),
-            Device::Console(io)   => io.close(),
-            Device::Random(io)    => io.close(),
-            Device::Uptime(io)    => io.close(),
-            Device::Realtime(io)  => io.close(),
-            Device::RTC(io)       => io.close(),
-            Device::TcpSocket(io) => io.close(),
-            Device::UdpSocket(io) => io.close(),
-            Device::Drive(io)     => io.close(),
+            Device::File(io)       => io.close(),
+            Device::Console(io)    => io.close(),
+            Device::Random(io)     => io.close(),
+            Device::Uptime(io)     => io.close(),
+            Device::Realtime(io)   => io.close(),
+            Device::RTC(io)        => io.close(),
+            Device::TcpSocket(io)  => io.close(),
+            Device::UdpSocket(io)  => io.close(),
+            Device::IcmpSocket(io) => io.close(),
+            Device::Drive(io)      => io.close(),
+            Device::DiskUsage(io)  => io.close(),
+            Device::NetSockets(io) => io.close(),
+            Device::NetIfaces(io)  => io.close(),
+            Device::Pipe(io)       => io.close(),
         }
     }
 
     fn poll(&mut self, event: IO) -> bool {
         match self {
             Device::Null          => false,
-            Device::File(io)      => io.poll(event),
-            Device::Console(io)   => io.poll(event),
-            Device::Random(io)    => io.poll(event),
-            Device::Uptime(io)    => io.poll(event),
-            Device::Realtime(io)  => io.poll(event),
-            Device::RTC(io)       => io.poll(event),
-            Device::TcpSocket(io) => io.poll(event),
-            Device::UdpSocket(io) => io.poll(event),
-            Device::Drive(io)     => io.poll(event),
+            Device::File(io)       => io.poll(event),
+            Device::Console(io)    => io.poll(event),
+            Device::Random(io)     => io.poll(event),
+            Device::Uptime(io)     => io.poll(event),
+            Device::Realtime(io)   => io.poll(event),
+            Device::RTC(io)        => io.poll(event),
+            Device::TcpSocket(io)  => io.poll(event),
+            Device::UdpSocket(io)  => io.poll(event),
+            Device::IcmpSocket(io) => io.poll(event),
+            Device::Drive(io)      => io.poll(event),
+            Device::DiskUsage(io)  => io.poll(event),
+            Device::NetSockets(io) => io.poll(event),
+            Device::NetIfaces(io)  => io.poll(event),
+            Device::Pipe(io)       => io.poll(event),
         }
     }
 }
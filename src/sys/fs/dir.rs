@@ -1,15 +1,20 @@
 use super::bitmap_block::BitmapBlock;
 use super::block::LinkedBlock;
+use super::block_device::{device, local_addr, mounted_device};
 use super::dir_entry::DirEntry;
+use super::node::{Node, NODE_FLAG};
 use super::read_dir::ReadDir;
 use super::super_block::SuperBlock;
 use super::FileType;
-use super::{dirname, filename, realpath, FileIO, IO};
+use super::{dirname, filename, resolve, FileIO, IO};
 use crate::sys;
 
 use alloc::boxed::Box;
 use alloc::string::String;
 
+// Length of the fields of an entry preceding the length of its name
+const HEADER_LEN: usize = 39;
+
 #[derive(Debug, Clone)]
 pub struct Dir {
     parent: Option<Box<Dir>>,
@@ -21,20 +26,23 @@ pub struct Dir {
 
 impl From<DirEntry> for Dir {
     fn from(entry: DirEntry) -> Self {
-        Self {
-            parent: Some(Box::new(entry.dir())),
-            name: entry.name(),
-            addr: entry.addr(),
-            size: entry.size(),
-            entry_index: 0,
+        // Cross the boundary of a filesystem mounted on the dir
+        if let Some(dev) = mounted_device(entry.addr()) {
+            return Dir::root_of(dev);
         }
+        Dir::from_entry(entry)
     }
 }
 
 impl Dir {
     pub fn root() -> Self {
+        Dir::root_of(0)
+    }
+
+    // Root dir of the filesystem of a mounted device
+    pub fn root_of(dev: usize) -> Self {
         let name = String::new();
-        let addr = SuperBlock::read().data_area();
+        let addr = SuperBlock::read(dev).data_area();
         let mut root = Self {
             parent: None,
             name,
@@ -46,12 +54,24 @@ impl Dir {
         root
     }
 
+    // Dir of an entry on its own filesystem, even if another one is
+    // mounted on it
+    pub fn from_entry(entry: DirEntry) -> Self {
+        Self {
+            parent: Some(Box::new(entry.dir())),
+            name: entry.name(),
+            addr: entry.addr(),
+            size: entry.size(),
+            entry_index: 0,
+        }
+    }
+
     pub fn is_root(&self) -> bool {
         self.parent.is_none()
     }
 
     pub fn create(pathname: &str) -> Option<Self> {
-        let pathname = realpath(pathname);
+        let pathname = resolve(pathname, true)?;
         let dirname = dirname(&pathname);
         let filename = filename(&pathname);
         if let Some(mut dir) = Dir::open(dirname) {
@@ -68,7 +88,7 @@ impl Dir {
         }
 
         let mut dir = Dir::root();
-        let pathname = realpath(pathname);
+        let pathname = resolve(pathname, true)?;
 
         if pathname == "/" {
             return Some(dir);
@@ -93,22 +113,126 @@ impl Dir {
 
     // TODO: return a Result
     pub fn create_file(&mut self, name: &str) -> Option<DirEntry> {
-        self.create_entry(FileType::File, name)
+        self.create_entry(FileType::File, name, None)
     }
 
     pub fn create_dir(&mut self, name: &str) -> Option<DirEntry> {
-        self.create_entry(FileType::Dir, name)
+        self.create_entry(FileType::Dir, name, None)
     }
 
     pub fn create_device(&mut self, name: &str) -> Option<DirEntry> {
-        self.create_entry(FileType::Device, name)
+        self.create_entry(FileType::Device, name, None)
     }
 
-    fn create_entry(&mut self, kind: FileType, name: &str) -> Option<DirEntry> {
+    pub fn create_link(&mut self, name: &str) -> Option<DirEntry> {
+        self.create_entry(FileType::Link, name, None)
+    }
+
+    // Create a hard link to the file of a node
+    pub fn create_hard_link(
+        &mut self,
+        kind: FileType,
+        name: &str,
+        node: &mut Node
+    ) -> Option<DirEntry> {
+        let entry = self.create_entry(kind, name, Some(node))?;
+        node.set_links(node.links() + 1);
+        node.write();
+        Some(entry.with_node(node))
+    }
+
+    // Point the entry of a file to a new node holding its metadata to
+    // share it with hard links
+    pub fn attach_node(&self, name: &str) -> Option<Node> {
+        let mut entries = self.entries();
+        for entry in &mut entries {
+            if entry.name() == name {
+                if let Some(addr) = entry.node() {
+                    return Some(Node::read(addr));
+                }
+                let node = Node::create(&entry)?;
+                let i = entries.block_offset() - entry.len();
+                let data = entries.block.data_mut();
+                data[i] |= NODE_FLAG;
+                let addr = local_addr(node.addr()).to_be_bytes();
+                data[(i + 1)..(i + 5)].clone_from_slice(&addr);
+                entries.block.write();
+                return Some(node);
+            }
+        }
+        None
+    }
+
+    fn create_entry(
+        &mut self,
+        kind: FileType,
+        name: &str,
+        node: Option<&Node>
+    ) -> Option<DirEntry> {
         if self.find(name).is_some() {
             return None;
         }
 
+        // Create a new entry pointing to a new block or to the node of
+        // a hard link
+        let (entry_kind, entry_addr, entry_size, entry_mode) = match node {
+            Some(node) => {
+                let kind = kind as u8 | NODE_FLAG;
+                (kind, node.addr(), node.size(), node.mode())
+            }
+            None => {
+                let dev = device(self.addr);
+                let entry_block = LinkedBlock::alloc(dev).unwrap();
+                (kind as u8, entry_block.addr(), 0u32, kind.default_mode())
+            }
+        };
+
+        // The times of a new file are set to its time of creation
+        let (entry_ctime, entry_mtime, entry_atime) = match node {
+            Some(node) => (node.ctime(), node.mtime(), node.atime()),
+            None => {
+                let time = sys::clock::realtime() as u64;
+                (time, time, time)
+            }
+        };
+
+        // A new file is owned by the user of the process creating it
+        let (entry_uid, entry_gid) = match node {
+            Some(node) => (node.uid(), node.gid()),
+            None => (sys::process::uid(), sys::process::gid()),
+        };
+        let mut header = [0; HEADER_LEN];
+        header[0] = entry_kind;
+        header[1..5].clone_from_slice(&local_addr(entry_addr).to_be_bytes());
+        header[5..9].clone_from_slice(&entry_size.to_be_bytes());
+        header[9..17].clone_from_slice(&entry_ctime.to_be_bytes());
+        header[17..25].clone_from_slice(&entry_mtime.to_be_bytes());
+        header[25..33].clone_from_slice(&entry_atime.to_be_bytes());
+        header[33..35].clone_from_slice(&entry_mode.to_be_bytes());
+        header[35..37].clone_from_slice(&entry_uid.to_be_bytes());
+        header[37..39].clone_from_slice(&entry_gid.to_be_bytes());
+        let entry_name = self.append_entry(&header, name)?;
+
+        Some(DirEntry::new(
+            self.clone(),
+            kind,
+            entry_addr,
+            entry_size,
+            entry_mode,
+            &entry_name,
+        ).with_times(
+            entry_ctime,
+            entry_mtime,
+            entry_atime,
+        ).with_owner(entry_uid, entry_gid))
+    }
+
+    // Write an entry at the end of the dir from its header and name
+    fn append_entry(
+        &mut self,
+        header: &[u8; HEADER_LEN],
+        name: &str
+    ) -> Option<String> {
         // Read the whole dir to add an entry at the end
         let mut entries = self.entries();
         while entries.next().is_some() {}
@@ -127,40 +251,30 @@ impl Dir {
             }
         }
 
-        // Create a new entry
-        let entry_block = LinkedBlock::alloc().unwrap();
-        let entry_kind = kind as u8;
-        let entry_addr = entry_block.addr();
-        let entry_size = 0u32;
-        let entry_time = sys::clock::realtime() as u64;
         let entry_name = truncate(name, u8::MAX as usize);
         let n = entry_name.len();
         let i = entries.block_offset();
         let data = entries.block.data_mut();
 
-        data[i] = entry_kind;
-        data[(i + 1)..(i + 5)].clone_from_slice(&entry_addr.to_be_bytes());
-        data[(i + 5)..(i + 9)].clone_from_slice(&entry_size.to_be_bytes());
-        data[(i + 9)..(i + 17)].clone_from_slice(&entry_time.to_be_bytes());
-        data[i + 17] = n as u8;
-        data[(i + 18)..(i + 18 + n)].clone_from_slice(entry_name.as_bytes());
+        data[i..(i + HEADER_LEN)].clone_from_slice(header);
+        data[i + 39] = n as u8;
+        data[(i + 40)..(i + 40 + n)].clone_from_slice(entry_name.as_bytes());
 
         entries.block.write();
         self.update_size();
-
-        Some(DirEntry::new(
-            self.clone(),
-            kind,
-            entry_addr,
-            entry_size,
-            entry_time,
-            &entry_name,
-        ))
+        Some(entry_name)
     }
 
     // Deleting an entry is done by setting the entry address to 0
     // TODO: If the entry is a directory, remove its entries recursively
     pub fn delete_entry(&mut self, name: &str) -> Result<(), ()> {
+        let entry = self.remove_entry(name).ok_or(())?;
+        free_entry(&entry);
+        Ok(())
+    }
+
+    // Remove an entry from the dir without freeing its blocks
+    fn remove_entry(&mut self, name: &str) -> Option<DirEntry> {
         let mut entries = self.entries();
         for entry in &mut entries {
             if entry.name() == name {
@@ -173,37 +287,175 @@ impl Dir {
                 data[i + 4] = 0;
                 entries.block.write();
                 self.update_size();
+                return Some(entry);
+            }
+        }
+        None
+    }
 
-                // Freeing entry blocks
-                let mut entry_block = LinkedBlock::read(entry.addr());
-                loop {
-                    BitmapBlock::free(entry_block.addr());
-                    match entry_block.next() {
-                        Some(next_block) => entry_block = next_block,
-                        None => break,
-                    }
-                }
+    // Move an entry to another dir or to another name in the same dir.
+    //
+    // An existing destination is replaced by writing the header of the
+    // source entry over its own in a single block write, so that the
+    // destination name always points to either the old or the new file.
+    pub fn rename_entry(
+        &mut self,
+        name: &str,
+        dest: &mut Dir,
+        dest_name: &str
+    ) -> Result<(), ()> {
+        if device(self.addr) != device(dest.addr) {
+            return Err(()); // Entries cannot be moved across filesystems
+        }
+        let src = self.find(name).ok_or(())?;
+        let header = self.read_header(name).ok_or(())?;
+
+        if let Some(old) = dest.find(dest_name) {
+            if self.addr == dest.addr && name == dest_name {
                 return Ok(());
             }
+            if old.node().is_some() && old.node() == src.node() {
+                // Both names are hard links to the same file
+                return self.delete_entry(name);
+            }
+            let mut entries = dest.entries();
+            for entry in &mut entries {
+                if entry.name() == dest_name {
+                    let i = entries.block_offset() - entry.len();
+                    let data = entries.block.data_mut();
+                    data[i..(i + HEADER_LEN)].clone_from_slice(&header);
+                    entries.block.write();
+                    break;
+                }
+            }
+            dest.update_size();
+            self.remove_entry(name).ok_or(())?;
+            free_entry(&old);
+        } else {
+            dest.append_entry(&header, dest_name).ok_or(())?;
+            self.remove_entry(name).ok_or(())?;
         }
-        Err(())
+        Ok(())
+    }
+
+    // Read the raw header of an entry preceding its name
+    fn read_header(&self, name: &str) -> Option<[u8; HEADER_LEN]> {
+        let mut entries = self.entries();
+        for entry in &mut entries {
+            if entry.name() == name {
+                let i = entries.block_offset() - entry.len();
+                let data = entries.block.data();
+                let mut header = [0; HEADER_LEN];
+                header.clone_from_slice(&data[i..(i + HEADER_LEN)]);
+                return Some(header);
+            }
+        }
+        None
     }
 
+    // Update the size of an entry after a write, with its modification time
     pub fn update_entry(&self, name: &str, size: u32) {
         let time = sys::clock::realtime() as u64;
         let mut entries = self.entries();
         for entry in &mut entries {
             if entry.name() == name {
+                // The metadata of a hard link is shared in its node
+                if let Some(addr) = entry.node() {
+                    let mut node = Node::read(addr);
+                    node.set_size(size);
+                    node.set_mtime(time);
+                    node.write();
+                    break;
+                }
                 let i = entries.block_offset() - entry.len();
                 let data = entries.block.data_mut();
                 data[(i + 5)..(i + 9)].clone_from_slice(&size.to_be_bytes());
-                data[(i + 9)..(i + 17)].clone_from_slice(&time.to_be_bytes());
+                data[(i + 17)..(i + 25)].clone_from_slice(&time.to_be_bytes());
                 entries.block.write();
                 break;
             }
         }
     }
 
+    // Set the access and modification times of an entry, or only one of them
+    pub fn set_times(
+        &self,
+        name: &str,
+        atime: Option<u64>,
+        mtime: Option<u64>
+    ) -> Result<(), ()> {
+        let mut entries = self.entries();
+        for entry in &mut entries {
+            if entry.name() == name {
+                if let Some(addr) = entry.node() {
+                    let mut node = Node::read(addr);
+                    if let Some(time) = atime {
+                        node.set_atime(time);
+                    }
+                    if let Some(time) = mtime {
+                        node.set_mtime(time);
+                    }
+                    node.write();
+                    return Ok(());
+                }
+                let i = entries.block_offset() - entry.len();
+                let data = entries.block.data_mut();
+                if let Some(time) = mtime {
+                    let bytes = time.to_be_bytes();
+                    data[(i + 17)..(i + 25)].clone_from_slice(&bytes);
+                }
+                if let Some(time) = atime {
+                    let bytes = time.to_be_bytes();
+                    data[(i + 25)..(i + 33)].clone_from_slice(&bytes);
+                }
+                entries.block.write();
+                return Ok(());
+            }
+        }
+        Err(())
+    }
+
+    pub fn set_mode(&self, name: &str, mode: u16) -> Result<(), ()> {
+        let mut entries = self.entries();
+        for entry in &mut entries {
+            if entry.name() == name {
+                if let Some(addr) = entry.node() {
+                    let mut node = Node::read(addr);
+                    node.set_mode(mode);
+                    node.write();
+                    return Ok(());
+                }
+                let i = entries.block_offset() - entry.len();
+                let data = entries.block.data_mut();
+                data[(i + 33)..(i + 35)].clone_from_slice(&mode.to_be_bytes());
+                entries.block.write();
+                return Ok(());
+            }
+        }
+        Err(())
+    }
+
+    pub fn set_owner(&self, name: &str, uid: u16, gid: u16) -> Result<(), ()> {
+        let mut entries = self.entries();
+        for entry in &mut entries {
+            if entry.name() == name {
+                if let Some(addr) = entry.node() {
+                    let mut node = Node::read(addr);
+                    node.set_owner(uid, gid);
+                    node.write();
+                    return Ok(());
+                }
+                let i = entries.block_offset() - entry.len();
+                let data = entries.block.data_mut();
+                data[(i + 35)..(i + 37)].clone_from_slice(&uid.to_be_bytes());
+                data[(i + 37)..(i + 39)].clone_from_slice(&gid.to_be_bytes());
+                entries.block.write();
+                return Ok(());
+            }
+        }
+        Err(())
+    }
+
     pub fn entries(&self) -> ReadDir {
         ReadDir::from(self.clone())
     }
@@ -213,7 +465,7 @@ impl Dir {
     }
 
     pub fn delete(pathname: &str) -> Result<(), ()> {
-        let pathname = realpath(pathname);
+        let pathname = resolve(pathname, false).ok_or(())?;
         let dirname = dirname(&pathname);
         let filename = filename(&pathname);
         if let Some(mut dir) = Dir::open(dirname) {
@@ -265,6 +517,31 @@ impl FileIO for Dir {
     }
 }
 
+// Free the blocks of a removed entry, or release its node if it was a hard
+// link and free the node when it was the last one
+fn free_entry(entry: &DirEntry) {
+    if let Some(addr) = entry.node() {
+        let mut node = Node::read(addr);
+        match node.links() {
+            0 | 1 => node.free(),
+            n => {
+                node.set_links(n - 1);
+                node.write();
+            }
+        }
+        return;
+    }
+
+    let mut entry_block = LinkedBlock::read(entry.addr());
+    loop {
+        BitmapBlock::free(entry_block.addr());
+        match entry_block.next() {
+            Some(next_block) => entry_block = next_block,
+            None => break,
+        }
+    }
+}
+
 // Truncate to the given number of bytes at most
 // while respecting char boundaries.
 fn truncate(s: &str, max: usize) -> String {
@@ -296,3 +573,36 @@ fn test_dir_delete() {
     assert!(Dir::open("/test").is_none());
     super::dismount();
 }
+
+#[test_case]
+fn test_dir_rename() {
+    use super::{File, FileIO};
+
+    super::mount_mem();
+    super::format_mem();
+    assert!(Dir::create("/a").is_some());
+    assert!(Dir::create("/b").is_some());
+    let mut file = File::create("/a/foo").unwrap();
+    assert_eq!(file.write(b"foo"), Ok(3));
+    let mut file = File::create("/b/bar").unwrap();
+    assert_eq!(file.write(b"bar"), Ok(3));
+
+    // Rename a file in the same dir
+    assert!(super::rename("/a/foo", "/a/baz").is_ok());
+    assert!(File::open("/a/foo").is_none());
+    assert_eq!(File::open("/a/baz").unwrap().size(), 3);
+
+    // Replace a file in another dir
+    assert!(super::rename("/a/baz", "/b/bar").is_ok());
+    assert!(File::open("/a/baz").is_none());
+    let mut buf = [0; 3];
+    assert_eq!(File::open("/b/bar").unwrap().read(&mut buf), Ok(3));
+    assert_eq!(&buf, b"foo");
+
+    // Move a dir but not inside itself
+    assert!(super::rename("/b", "/a/b").is_ok());
+    assert!(Dir::open("/b").is_none());
+    assert!(File::open("/a/b/bar").is_some());
+    assert!(super::rename("/a", "/a/b/a").is_err());
+    super::dismount();
+}
@@ -1,25 +1,36 @@
+use super::block::LinkedBlock;
 use super::dir::Dir;
-use super::{dirname, filename, realpath, FileType};
+use super::node::Node;
+use super::{dirname, filename, resolve, DeviceType, FileType};
 
 use alloc::string::String;
 use alloc::vec::Vec;
-use core::convert::TryInto;
+use core::convert::{TryFrom, TryInto};
 
 #[derive(Clone)]
 pub struct DirEntry {
     dir: Dir,
     addr: u32,
+    node: Option<u32>,
+    links: u32,
 
     // FileInfo
     kind: FileType,
     size: u32,
-    time: u64,
+    ctime: u64,
+    mtime: u64,
+    atime: u64,
+    mode: u16,
+    uid: u16,
+    gid: u16,
     name: String,
 }
 
 impl DirEntry {
+    // The symbolic links in the parent dirs of the path are followed but
+    // not the last one
     pub fn open(pathname: &str) -> Option<Self> {
-        let pathname = realpath(pathname);
+        let pathname = resolve(pathname, false)?;
         let dirname = dirname(&pathname);
         let filename = filename(&pathname);
         if let Some(dir) = Dir::open(dirname) {
@@ -33,7 +44,7 @@ impl DirEntry {
         kind: FileType,
         addr: u32,
         size: u32,
-        time: u64,
+        mode: u16,
         name: &str
     ) -> Self {
         let name = String::from(name);
@@ -41,14 +52,49 @@ impl DirEntry {
             dir,
             kind,
             addr,
+            node: None,
+            links: 1,
             size,
-            time,
+            ctime: 0,
+            mtime: 0,
+            atime: 0,
+            mode,
+            uid: 0,
+            gid: 0,
             name,
         }
     }
 
+    pub fn with_times(mut self, ctime: u64, mtime: u64, atime: u64) -> Self {
+        self.ctime = ctime;
+        self.mtime = mtime;
+        self.atime = atime;
+        self
+    }
+
+    pub fn with_owner(mut self, uid: u16, gid: u16) -> Self {
+        self.uid = uid;
+        self.gid = gid;
+        self
+    }
+
+    // The metadata of a hard link is read from the node of its file
+    pub fn with_node(mut self, node: &Node) -> Self {
+        self.addr = node.data_addr();
+        self.node = Some(node.addr());
+        self.links = node.links();
+        self.size = node.size();
+        self.ctime = node.ctime();
+        self.mtime = node.mtime();
+        self.atime = node.atime();
+        self.mode = node.mode();
+        self.uid = node.uid();
+        self.gid = node.gid();
+        self
+    }
+
     pub fn empty_len() -> usize {
-        1 + 4 + 4 + 8 + 1
+        1 + 4 + 4 + 8 + 8 + 8 + 2 + 2 + 2 + 1
     }
 
     pub fn len(&self) -> usize {
@@ -75,10 +121,23 @@ impl DirEntry {
         self.kind == FileType::Device
     }
 
+    pub fn is_link(&self) -> bool {
+        self.kind == FileType::Link
+    }
+
     pub fn addr(&self) -> u32 {
         self.addr
     }
 
+    // Address of the node shared by the hard links of the file
+    pub fn node(&self) -> Option<u32> {
+        self.node
+    }
+
+    pub fn links(&self) -> u32 {
+        self.links
+    }
+
     pub fn dir(&self) -> Dir {
         self.dir.clone()
     }
@@ -91,16 +150,50 @@ impl DirEntry {
         self.size
     }
 
-    pub fn time(&self) -> u64 {
-        self.time
+    pub fn ctime(&self) -> u64 {
+        self.ctime
+    }
+
+    pub fn mtime(&self) -> u64 {
+        self.mtime
+    }
+
+    pub fn atime(&self) -> u64 {
+        self.atime
+    }
+
+    pub fn mode(&self) -> u16 {
+        self.mode
+    }
+
+    pub fn uid(&self) -> u16 {
+        self.uid
+    }
+
+    pub fn gid(&self) -> u16 {
+        self.gid
     }
 
     pub fn info(&self) -> FileInfo {
+        // The type of a device is stored in the first byte of its file
+        let device = if self.is_device() {
+            LinkedBlock::read(self.addr).data()[0]
+        } else {
+            0
+        };
         FileInfo {
             kind: self.kind,
             name: self.name(),
             size: self.size(),
-            time: self.time,
+            ctime: self.ctime,
+            mtime: self.mtime,
+            atime: self.atime,
+            addr: self.addr,
+            device,
+            links: self.links,
+            mode: self.mode,
+            uid: self.uid,
+            gid: self.gid,
         }
     }
 }
@@ -109,7 +202,15 @@ impl DirEntry {
 pub struct FileInfo {
     kind: FileType,
     size: u32,
-    time: u64,
+    ctime: u64,
+    mtime: u64,
+    atime: u64,
+    addr: u32,
+    device: u8,
+    links: u32,
+    mode: u16,
+    uid: u16,
+    gid: u16,
     name: String,
 }
 
@@ -119,29 +220,125 @@ impl FileInfo {
             kind: FileType::File,
             name: String::new(),
             size: 0,
-            time: 0,
+            ctime: 0,
+            mtime: 0,
+            atime: 0,
+            addr: 0,
+            device: 0,
+            links: 1,
+            mode: FileType::File.default_mode(),
+            uid: 0,
+            gid: 0,
+        }
+    }
+
+    // Info of a file of another filesystem than MFS, having a single time
+    // and no owner
+    pub fn from_parts(
+        kind: FileType,
+        name: &str,
+        size: u32,
+        time: u64,
+        mode: u16
+    ) -> Self {
+        Self {
+            kind,
+            name: name.into(),
+            size,
+            ctime: time,
+            mtime: time,
+            atime: time,
+            addr: 0,
+            device: 0,
+            links: 1,
+            mode,
+            uid: 0,
+            gid: 0,
         }
     }
 
     pub fn root() -> Self {
         let kind = FileType::Dir;
         let name = String::new();
-        let size = Dir::root().size() as u32;
-        let time = 0;
+        let root = Dir::root();
+        let size = root.size() as u32;
+        let (ctime, mtime, atime) = (0, 0, 0);
+        let addr = root.addr();
+        let device = 0;
+        let links = 1;
+        let mode = kind.default_mode();
+        let (uid, gid) = (0, 0); // Root
         Self {
             kind,
             name,
             size,
-            time,
+            ctime,
+            mtime,
+            atime,
+            addr,
+            device,
+            links,
+            mode,
+            uid,
+            gid,
         }
     }
 
+    pub fn empty_len() -> usize {
+        1 + 4 + 8 + 8 + 8 + 4 + 1 + 4 + 2 + 2 + 2 + 1
+    }
+
     pub fn size(&self) -> u32 {
         self.size
     }
 
-    pub fn time(&self) -> u64 {
-        self.time
+    // Time of the creation of the file
+    pub fn ctime(&self) -> u64 {
+        self.ctime
+    }
+
+    // Time of the last write to the file
+    pub fn mtime(&self) -> u64 {
+        self.mtime
+    }
+
+    // Time of the last read of the file
+    pub fn atime(&self) -> u64 {
+        self.atime
+    }
+
+    // Address of the first block of the file
+    pub fn addr(&self) -> u32 {
+        self.addr
+    }
+
+    // Type of the device if the file is a device
+    pub fn device(&self) -> Option<DeviceType> {
+        if self.is_device() {
+            DeviceType::try_from(&[self.device][..]).ok()
+        } else {
+            None
+        }
+    }
+
+    // Number of hard links to the file
+    pub fn links(&self) -> u32 {
+        self.links
+    }
+
+    // Permission bits of the file
+    pub fn mode(&self) -> u16 {
+        self.mode
+    }
+
+    // User ID of the owner of the file
+    pub fn uid(&self) -> u16 {
+        self.uid
+    }
+
+    // Group ID of the file
+    pub fn gid(&self) -> u16 {
+        self.gid
     }
 
     pub fn name(&self) -> String {
@@ -165,13 +362,25 @@ impl FileInfo {
         self.kind == FileType::Device
     }
 
+    pub fn is_link(&self) -> bool {
+        self.kind == FileType::Link
+    }
+
     // TODO: Use bincode?
     pub fn as_bytes(&self) -> Vec<u8> {
         debug_assert!(self.name.len() < 256);
         let mut res = Vec::new();
         res.push(self.kind as u8);
         res.extend_from_slice(&self.size.to_be_bytes());
-        res.extend_from_slice(&self.time.to_be_bytes());
+        res.extend_from_slice(&self.ctime.to_be_bytes());
+        res.extend_from_slice(&self.mtime.to_be_bytes());
+        res.extend_from_slice(&self.atime.to_be_bytes());
+        res.extend_from_slice(&self.addr.to_be_bytes());
+        res.push(self.device);
+        res.extend_from_slice(&self.links.to_be_bytes());
+        res.extend_from_slice(&self.mode.to_be_bytes());
+        res.extend_from_slice(&self.uid.to_be_bytes());
+        res.extend_from_slice(&self.gid.to_be_bytes());
         res.push(self.name.len() as u8);
         res.extend_from_slice(self.name.as_bytes());
         res
@@ -185,17 +394,34 @@ impl From<&[u8]> for FileInfo {
             0 => FileType::Dir,
             1 => FileType::File,
             2 => FileType::Device,
+            3 => FileType::Link,
             _ => panic!(),
         };
         let size = u32::from_be_bytes(buf[1..5].try_into().unwrap());
-        let time = u64::from_be_bytes(buf[5..13].try_into().unwrap());
-        let i = 14 + buf[13] as usize;
-        let name = String::from_utf8_lossy(&buf[14..i]).into();
+        let ctime = u64::from_be_bytes(buf[5..13].try_into().unwrap());
+        let mtime = u64::from_be_bytes(buf[13..21].try_into().unwrap());
+        let atime = u64::from_be_bytes(buf[21..29].try_into().unwrap());
+        let addr = u32::from_be_bytes(buf[29..33].try_into().unwrap());
+        let device = buf[33];
+        let links = u32::from_be_bytes(buf[34..38].try_into().unwrap());
+        let mode = u16::from_be_bytes(buf[38..40].try_into().unwrap());
+        let uid = u16::from_be_bytes(buf[40..42].try_into().unwrap());
+        let gid = u16::from_be_bytes(buf[42..44].try_into().unwrap());
+        let i = 45 + buf[44] as usize;
+        let name = String::from_utf8_lossy(&buf[45..i]).into();
         Self {
             kind,
             name,
             size,
-            time,
+            ctime,
+            mtime,
+            atime,
+            addr,
+            device,
+            links,
+            mode,
+            uid,
+            gid,
         }
     }
 }
@@ -1,12 +1,18 @@
+use super::bitmap_block::BitmapBlock;
 use super::block::LinkedBlock;
+use super::block_device::device;
 use super::dir::Dir;
 use super::dir_entry::DirEntry;
-use super::{dirname, filename, realpath, FileIO, IO};
+use super::{dirname, filename, resolve, FileIO, IO};
+use crate::sys;
 
 use alloc::boxed::Box;
 use alloc::string::{String, ToString};
 use alloc::vec;
 
+// The access time of a file is only updated when it is older than a day
+const ATIME_DELAY: u64 = 24 * 60 * 60;
+
 pub enum SeekFrom {
     Start(u32),
     Current(i32),
@@ -20,6 +26,8 @@ pub struct File {
     addr: u32,
     size: u32,
     offset: u32,
+    accessed: bool,
+    append: bool,
 }
 
 impl From<DirEntry> for File {
@@ -30,10 +38,21 @@ impl From<DirEntry> for File {
             addr: entry.addr(),
             size: entry.size(),
             offset: 0,
+            accessed: !is_atime_stale(&entry),
+            append: false,
         }
     }
 }
 
+// Like with the `relatime` option of Linux, the access time is only stale
+// when it is older than the last write or than a day, to avoid writing the
+// metadata of a file each time it is read.
+fn is_atime_stale(entry: &DirEntry) -> bool {
+    let atime = entry.atime();
+    let now = sys::clock::realtime() as u64;
+    atime <= entry.mtime() || atime + ATIME_DELAY <= now
+}
+
 impl File {
     pub fn new() -> Self {
         Self {
@@ -42,11 +61,13 @@ impl File {
             addr: 0,
             size: 0,
             offset: 0,
+            accessed: false,
+            append: false,
         }
     }
 
     pub fn create(pathname: &str) -> Option<Self> {
-        let pathname = realpath(pathname);
+        let pathname = resolve(pathname, true)?;
         let dirname = dirname(&pathname);
         let filename = filename(&pathname);
         if let Some(mut dir) = Dir::open(dirname) {
@@ -58,7 +79,7 @@ impl File {
     }
 
     pub fn open(pathname: &str) -> Option<Self> {
-        let pathname = realpath(pathname);
+        let pathname = resolve(pathname, true)?;
         let dirname = dirname(&pathname);
         let filename = filename(&pathname);
         if let Some(dir) = Dir::open(dirname) {
@@ -75,11 +96,20 @@ impl File {
         self.name.clone()
     }
 
+    // Every write of a file in append mode goes to its end, even if other
+    // handles have written to it since it was opened
+    pub fn set_append(&mut self, append: bool) {
+        self.append = append;
+    }
+
     pub fn size(&self) -> usize {
         self.size as usize
     }
 
     pub fn seek(&mut self, pos: SeekFrom) -> Result<u32, ()> {
+        if let SeekFrom::End(_) = pos {
+            self.reload_size();
+        }
         let offset = match pos {
             SeekFrom::Start(i)   => i as i32,
             SeekFrom::Current(i) => i + self.offset as i32,
@@ -107,8 +137,34 @@ impl File {
         self.addr
     }
 
+    // Empty the file, its first block being reused by the next writes and
+    // the others being freed
+    pub fn truncate(&mut self) {
+        let mut block = LinkedBlock::read(self.addr);
+        if let Some(next_block) = block.next() {
+            free_blocks(next_block);
+            block.set_next_addr(0);
+            block.write_data();
+        }
+        self.size = 0;
+        self.offset = 0;
+        if let Some(dir) = self.parent.clone() {
+            dir.update_entry(&self.name, self.size);
+        }
+    }
+
+    // Read the size of the file that could have been changed by another
+    // handle since it was opened
+    fn reload_size(&mut self) {
+        if let Some(dir) = self.parent.clone() {
+            if let Some(entry) = dir.find(&self.name) {
+                self.size = entry.size();
+            }
+        }
+    }
+
     pub fn delete(pathname: &str) -> Result<(), ()> {
-        let pathname = realpath(pathname);
+        let pathname = resolve(pathname, false).ok_or(())?;
         let dirname = dirname(&pathname);
         let filename = filename(&pathname);
         if let Some(mut dir) = Dir::open(dirname) {
@@ -119,8 +175,28 @@ impl File {
     }
 }
 
+// Free a block and the blocks linked after it
+fn free_blocks(mut block: LinkedBlock) {
+    loop {
+        BitmapBlock::free(block.addr());
+        match block.next() {
+            Some(next_block) => block = next_block,
+            None => break,
+        }
+    }
+}
+
 impl FileIO for File {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, ()> {
+        // The access time is updated by the first read of an open file when
+        // it is stale
+        if !self.accessed {
+            self.accessed = true;
+            if let Some(dir) = self.parent.clone() {
+                let time = sys::clock::realtime() as u64;
+                dir.set_times(&self.name, Some(time), None).ok();
+            }
+        }
         let buf_len = buf.len();
         let mut addr = self.addr;
         let mut bytes = 0; // Number of bytes read
@@ -148,11 +224,16 @@ impl FileIO for File {
     }
 
     fn write(&mut self, buf: &[u8]) -> Result<usize, ()> {
+        if self.append {
+            self.reload_size();
+            self.offset = self.size;
+        }
         let buf_len = buf.len();
         let mut addr = self.addr;
         let mut bytes = 0; // Number of bytes written
         let mut pos = 0; // Position in the file
-        while bytes < buf_len {
+        let mut is_full = false;
+        while bytes < buf_len && !is_full {
             let mut block = LinkedBlock::read(addr);
             let data = block.data_mut();
             let data_len = data.len();
@@ -168,20 +249,26 @@ impl FileIO for File {
                 pos += 1;
             }
 
+            // The next blocks are kept when writing in the middle of the file
             addr = match block.next() {
                 Some(next_block) => {
-                    if bytes < buf_len {
+                    if bytes < buf_len || self.offset < self.size {
                         next_block.addr()
                     } else {
-                        // TODO: Free the next block(s)
+                        free_blocks(next_block);
                         0
                     }
                 }
                 None => {
                     if bytes < buf_len {
-                        match LinkedBlock::alloc() {
+                        match LinkedBlock::alloc(device(self.addr)) {
                             Some(next_block) => next_block.addr(),
-                            None => return Err(()),
+                            None => {
+                                // The bytes written so far are kept when
+                                // the filesystem is full
+                                is_full = true;
+                                0
+                            }
                         }
                     } else {
                         0
@@ -190,12 +277,15 @@ impl FileIO for File {
             };
 
             block.set_next_addr(addr);
-            block.write();
+            block.write_data();
         }
-        self.size = self.offset;
+        self.size = self.size.max(self.offset);
         if let Some(dir) = self.parent.clone() {
             dir.update_entry(&self.name, self.size);
         }
+        if is_full {
+            return Err(());
+        }
         Ok(bytes)
     }
 
@@ -255,6 +345,59 @@ fn test_file_read() {
     super::dismount();
 }
 
+#[test_case]
+fn test_file_append() {
+    super::mount_mem();
+    super::format_mem();
+    let mut a = File::create("/test").unwrap();
+    let mut b = a.clone();
+    a.set_append(true);
+    b.set_append(true);
+    a.write(b"Hello, ").unwrap();
+    b.write(b"World!").unwrap();
+    a.seek(SeekFrom::Start(0)).unwrap();
+    a.write(b"\n").unwrap();
+    let mut file = File::open("/test").unwrap();
+    assert_eq!(file.read_to_string(), "Hello, World!\n");
+    super::dismount();
+}
+
+#[test_case]
+fn test_file_seek() {
+    super::mount_mem();
+    super::format_mem();
+    let mut file = File::create("/test").unwrap();
+    file.write(b"Hello, World!").unwrap();
+    assert_eq!(file.seek(SeekFrom::Start(7)), Ok(7));
+    file.write(b"MOROS").unwrap();
+    assert_eq!(file.seek(SeekFrom::End(-1)), Ok(12));
+    assert_eq!(file.seek(SeekFrom::Current(2)), Err(()));
+    let mut buf = [0; 1];
+    assert_eq!(file.read(&mut buf), Ok(1));
+    assert_eq!(&buf, b"!");
+    file.truncate();
+    file.write(b"Hi").unwrap();
+    let mut file = File::open("/test").unwrap();
+    assert_eq!(file.read_to_string(), "Hi");
+    super::dismount();
+}
+
+#[test_case]
+fn test_file_truncate() {
+    super::mount_mem();
+    super::format_mem();
+    let mut file = File::create("/test").unwrap();
+    let free = super::disk_free();
+    file.write(&[0; 4 * super::BLOCK_SIZE]).unwrap();
+    assert!(super::disk_free() < free);
+    file.truncate();
+    file.write(b"Hello").unwrap();
+    assert_eq!(super::disk_free(), free);
+    let mut file = File::open("/test").unwrap();
+    assert_eq!(file.read_to_string(), "Hello");
+    super::dismount();
+}
+
 #[test_case]
 fn test_file_delete() {
     super::mount_mem();
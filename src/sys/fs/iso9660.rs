@@ -0,0 +1,412 @@
+use super::block_device::{find_mount, Filesystem};
+use super::dir_entry::FileInfo;
+use super::{filename, FileIO, FileType, IO};
+use crate::sys;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use time::{Date, UtcOffset};
+
+// ISO 9660 is the read-only filesystem of CD-ROMs, with the Joliet extension
+// adding long Unicode names in a supplementary volume descriptor.
+//
+// Volume descriptor structure:
+// 0      => type (1 for primary, 2 for supplementary, 255 for terminator)
+// 1..6   => identifier "CD001"
+// 88..91 => escape sequence of the Joliet level in a supplementary descriptor
+// 156..190 => directory record of the root dir
+//
+// Directory record structure:
+// 0      => length of the record
+// 2..6   => first sector of the file (little endian)
+// 10..14 => size of the file (little endian)
+// 18..25 => date of the file
+// 25     => flags (bit 1 set for a dir)
+// 32     => length of the name
+// 33..   => name
+
+pub const SECTOR_SIZE: usize = 2048;
+
+// The volume descriptors start after a system area of 16 sectors
+const DESCRIPTORS_SECTOR: u32 = 16;
+const MAX_DESCRIPTORS: u32 = 32;
+const SIGNATURE: &[u8; 5] = b"CD001";
+const JOLIET_LEVELS: [&[u8; 3]; 3] = [b"%/@", b"%/C", b"%/E"];
+
+#[derive(Debug, Clone)]
+pub enum IsoDevice {
+    Atapi(u8, u8),
+    Mem(Arc<Vec<u8>>),
+}
+
+impl IsoDevice {
+    fn read(&self, sector: u32, buf: &mut [u8]) -> Result<(), ()> {
+        match self {
+            IsoDevice::Atapi(bus, dsk) => {
+                sys::ata::read_atapi(*bus, *dsk, sector, buf)
+            }
+            IsoDevice::Mem(image) => {
+                let a = sector as usize * SECTOR_SIZE;
+                let b = a + SECTOR_SIZE;
+                if b > image.len() {
+                    return Err(());
+                }
+                buf.copy_from_slice(&image[a..b]);
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Record {
+    name: String,
+    sector: u32,
+    size: u32,
+    time: u64,
+    is_dir: bool,
+}
+
+impl Record {
+    fn parse(buf: &[u8], joliet: bool) -> Option<Self> {
+        let len = *buf.first()? as usize;
+        if len < 34 || len > buf.len() {
+            return None;
+        }
+        let sector = u32::from_le_bytes(buf[2..6].try_into().unwrap());
+        let size = u32::from_le_bytes(buf[10..14].try_into().unwrap());
+        let time = timestamp(&buf[18..25]);
+        let is_dir = buf[25] & 2 != 0;
+        let n = buf[32] as usize;
+        if 33 + n > len {
+            return None;
+        }
+        let name = match &buf[33..(33 + n)] {
+            [0] => ".".into(),
+            [1] => "..".into(),
+            bytes if joliet => decode_ucs2(bytes),
+            bytes => String::from_utf8_lossy(bytes).to_lowercase(),
+        };
+        let name = clean_name(&name, is_dir);
+        Some(Self { name, sector, size, time, is_dir })
+    }
+
+    fn info(&self, name: &str) -> FileInfo {
+        let (kind, mode) = if self.is_dir {
+            (FileType::Dir, 0o555)
+        } else {
+            (FileType::File, 0o444)
+        };
+        FileInfo::from_parts(kind, name, self.size, self.time, mode)
+    }
+}
+
+// Names are encoded in big endian UCS-2 with Joliet
+fn decode_ucs2(bytes: &[u8]) -> String {
+    let chars = bytes.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]]));
+    char::decode_utf16(chars).map(|c| c.unwrap_or('?')).collect()
+}
+
+// Remove the version number of a file name, and the dot at the end of a name
+// without extension
+fn clean_name(name: &str, is_dir: bool) -> String {
+    if name == "." || name == ".." {
+        return name.into();
+    }
+    let name = match name.find(';') {
+        Some(i) if !is_dir => &name[..i],
+        _ => name,
+    };
+    name.trim_end_matches('.').into()
+}
+
+// Convert a date made of the years since 1900, the month, the day, the hour,
+// the minute, the second, and the offset from UTC in intervals of 15 minutes
+fn timestamp(buf: &[u8]) -> u64 {
+    let year = 1900 + buf[0] as i32;
+    let date = match Date::try_from_ymd(year, buf[1], buf[2]) {
+        Ok(date) => date,
+        Err(_) => return 0,
+    };
+    let date_time = match date.try_with_hms(buf[3], buf[4], buf[5]) {
+        Ok(date_time) => date_time,
+        Err(_) => return 0,
+    };
+    let offset = UtcOffset::minutes(15 * (buf[6] as i8) as i16);
+    let ts = date_time.assume_offset(offset).unix_timestamp();
+    if ts < 0 { 0 } else { ts as u64 }
+}
+
+#[derive(Debug, Clone)]
+pub struct Iso {
+    dev: IsoDevice,
+    root: Record,
+    joliet: bool,
+}
+
+impl Iso {
+    // Read the volume descriptors of an image, the Joliet descriptor being
+    // preferred to the primary one for its long names
+    pub fn open(dev: IsoDevice) -> Option<Self> {
+        let mut buf = [0; SECTOR_SIZE];
+        let mut root = None;
+        let mut joliet = false;
+        let a = DESCRIPTORS_SECTOR;
+        let b = DESCRIPTORS_SECTOR + MAX_DESCRIPTORS;
+        for sector in a..b {
+            dev.read(sector, &mut buf).ok()?;
+            if &buf[1..6] != SIGNATURE {
+                return None;
+            }
+            match buf[0] {
+                1 if root.is_none() => {
+                    root = Record::parse(&buf[156..190], false);
+                }
+                2 if JOLIET_LEVELS.iter().any(|l| &buf[88..91] == *l) => {
+                    root = Record::parse(&buf[156..190], true);
+                    joliet = root.is_some();
+                }
+                255 => break,
+                _ => {}
+            }
+        }
+        let root = root?;
+        Some(Self { dev, root, joliet })
+    }
+
+    pub fn source(&self) -> String {
+        match self.dev {
+            IsoDevice::Atapi(bus, dsk) => format!("/dev/ata/{}/{}", bus, dsk),
+            IsoDevice::Mem(_) => "mem".into(),
+        }
+    }
+
+    fn read_dir(&self, dir: &Record) -> Vec<Record> {
+        let mut records = Vec::new();
+        let mut buf = [0; SECTOR_SIZE];
+        let n = (dir.size as usize + SECTOR_SIZE - 1) / SECTOR_SIZE;
+        for i in 0..n {
+            if self.dev.read(dir.sector + i as u32, &mut buf).is_err() {
+                break;
+            }
+
+            // Records don't cross sectors and the end of a sector is padded
+            // with zeros
+            let mut j = 0;
+            while j < SECTOR_SIZE && buf[j] != 0 {
+                match Record::parse(&buf[j..], self.joliet) {
+                    Some(r) if r.name != "." && r.name != ".." => {
+                        records.push(r);
+                    }
+                    Some(_) => {}
+                    None => break,
+                }
+                j += buf[j] as usize;
+            }
+        }
+        records
+    }
+
+    // Find the record of a file from its path in the image
+    fn find(&self, path: &str) -> Option<Record> {
+        let mut record = self.root.clone();
+        for name in path.split('/').filter(|n| !n.is_empty()) {
+            if !record.is_dir {
+                return None;
+            }
+            let records = self.read_dir(&record);
+            record = records.into_iter().find(|r| r.name == name)?;
+        }
+        Some(record)
+    }
+}
+
+// Find the image mounted on a path and the path of the file in the image
+fn mounted(path: &str) -> Option<(Iso, String)> {
+    find_mount(path, |fs| match fs {
+        Filesystem::Iso(iso) => Some(iso.clone()),
+        _ => None,
+    })
+}
+
+// Check if a path is on a mounted image, the mount point included
+pub fn is_mounted(path: &str) -> bool {
+    mounted(path).is_some()
+}
+
+pub fn info(path: &str) -> Option<FileInfo> {
+    IsoFile::open(path).map(|file| file.info())
+}
+
+#[derive(Debug, Clone)]
+pub struct IsoFile {
+    iso: Iso,
+    name: String,
+    record: Record,
+    offset: u32,
+    entry_index: usize,
+}
+
+impl IsoFile {
+    pub fn open(path: &str) -> Option<Self> {
+        let (iso, rest) = mounted(path)?;
+        let record = iso.find(&rest)?;
+        let name = filename(path).into();
+        Some(Self { iso, name, record, offset: 0, entry_index: 0 })
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.record.is_dir
+    }
+
+    pub fn info(&self) -> FileInfo {
+        self.record.info(&self.name)
+    }
+}
+
+impl FileIO for IsoFile {
+    // The entries of a dir are read as file infos like in MFS
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ()> {
+        let mut i = 0;
+        if self.is_dir() {
+            let records = self.iso.read_dir(&self.record);
+            for record in records.iter().skip(self.entry_index) {
+                let bytes = record.info(&record.name).as_bytes();
+                let j = i + bytes.len();
+                if j < buf.len() {
+                    buf[i..j].copy_from_slice(&bytes);
+                    self.entry_index += 1;
+                    i = j;
+                } else {
+                    break;
+                }
+            }
+            return Ok(i);
+        }
+
+        let mut sector = [0; SECTOR_SIZE];
+        while i < buf.len() && self.offset < self.record.size {
+            let k = self.offset as usize / SECTOR_SIZE;
+            let offset = self.offset as usize % SECTOR_SIZE;
+            self.iso.dev.read(self.record.sector + k as u32, &mut sector)?;
+            let left = (self.record.size - self.offset) as usize;
+            let n = (SECTOR_SIZE - offset).min(buf.len() - i).min(left);
+            buf[i..(i + n)].copy_from_slice(&sector[offset..(offset + n)]);
+            self.offset += n as u32;
+            i += n;
+        }
+        Ok(i)
+    }
+
+    fn write(&mut self, _buf: &[u8]) -> Result<usize, ()> {
+        Err(())
+    }
+
+    fn close(&mut self) {}
+
+    fn poll(&mut self, event: IO) -> bool {
+        match event {
+            IO::Read if self.is_dir() => {
+                self.entry_index < self.iso.read_dir(&self.record).len()
+            }
+            IO::Read => self.offset < self.record.size,
+            IO::Write => false,
+        }
+    }
+}
+
+#[cfg(test)]
+fn test_record(name: &[u8], sector: u32, size: u32, is_dir: bool) -> Vec<u8> {
+    let n = name.len();
+    let len = 33 + n + (33 + n) % 2; // Padded to an even length
+    let mut buf = alloc::vec![0; len];
+    buf[0] = len as u8;
+    buf[2..6].clone_from_slice(&sector.to_le_bytes());
+    buf[10..14].clone_from_slice(&size.to_le_bytes());
+    buf[18..25].clone_from_slice(&[126, 10, 15, 12, 0, 0, 0]);
+    buf[25] = if is_dir { 2 } else { 0 };
+    buf[32] = n as u8;
+    buf[33..(33 + n)].clone_from_slice(name);
+    buf
+}
+
+// Build an image with a primary and a Joliet descriptor, each having a root
+// dir with a file, and the content of the file
+#[cfg(test)]
+fn test_image() -> Vec<u8> {
+    let mut image = alloc::vec![0; 22 * SECTOR_SIZE];
+    let content = b"Hello, World!";
+    let size = content.len() as u32;
+    let dir_size = SECTOR_SIZE as u32;
+    let joliet_name: Vec<u8> = "Hello World.txt".encode_utf16()
+        .flat_map(|c| c.to_be_bytes()).collect();
+
+    let descriptors = [(16, 1, 19), (17, 2, 20), (18, 255, 0)];
+    for (sector, kind, root) in descriptors {
+        let i = sector * SECTOR_SIZE;
+        image[i] = kind;
+        image[(i + 1)..(i + 6)].clone_from_slice(SIGNATURE);
+        if kind == 2 {
+            image[(i + 88)..(i + 91)].clone_from_slice(b"%/E");
+        }
+        if root > 0 {
+            let record = test_record(&[0], root, dir_size, true);
+            image[(i + 156)..(i + 190)].clone_from_slice(&record);
+        }
+    }
+
+    let dirs = [(19, b"HELLO.TXT;1".to_vec()), (20, joliet_name)];
+    for (sector, name) in dirs {
+        let mut i = sector as usize * SECTOR_SIZE;
+        let records = [
+            test_record(&[0], sector, dir_size, true),
+            test_record(&[1], sector, dir_size, true),
+            test_record(&name, 21, size, false),
+        ];
+        for record in records {
+            image[i..(i + record.len())].clone_from_slice(&record);
+            i += record.len();
+        }
+    }
+
+    let i = 21 * SECTOR_SIZE;
+    image[i..(i + content.len())].clone_from_slice(content);
+    image
+}
+
+#[test_case]
+fn test_iso9660() {
+    let dev = IsoDevice::Mem(Arc::new(test_image()));
+    let iso = Iso::open(dev).unwrap();
+    assert!(iso.joliet);
+    assert!(iso.find("/hello.txt").is_none());
+    let record = iso.find("/Hello World.txt").unwrap();
+    assert_eq!(record.size, 13);
+    assert_eq!(record.time, 1792065600); // 2026-10-15 12:00:00 UTC
+
+    super::mount_mem();
+    super::format_mem();
+    assert!(super::Dir::create("/cd").is_some());
+    assert!(super::mount(Filesystem::Iso(iso), "/cd").is_ok());
+
+    let info = super::info("/cd/../cd/Hello World.txt").unwrap();
+    assert!(info.is_file());
+    assert_eq!(info.mode(), 0o444);
+    assert!(super::info("/cd").unwrap().is_dir());
+
+    let flags = super::OpenFlag::Write as usize;
+    assert!(super::open("/cd/Hello World.txt", flags).is_none());
+    let mut file = IsoFile::open("/cd/Hello World.txt").unwrap();
+    let mut buf = [0; 32];
+    assert_eq!(file.read(&mut buf), Ok(13));
+    assert_eq!(&buf[0..13], b"Hello, World!");
+    assert_eq!(file.read(&mut buf), Ok(0));
+
+    assert!(super::delete("/cd/Hello World.txt").is_err());
+    assert!(super::umount("/cd").is_ok());
+    assert!(super::info("/cd/Hello World.txt").is_none());
+    super::dismount();
+}
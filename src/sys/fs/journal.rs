@@ -0,0 +1,192 @@
+use super::block::Block;
+use super::block_device::{global_addr, local_addr, sync, MOUNTS};
+use super::block_device::{BlockDevice, Filesystem};
+use super::super_block::SuperBlock;
+use crate::sys;
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+
+// The metadata of a filesystem on a hard drive, like the allocation bitmap
+// and the dir entries, is written in a journal before being written in
+// place, so that the filesystem stays consistent if the system is stopped
+// in the middle of a flush of the block cache: the blocks of a transaction
+// are first copied into the blocks of the journal, then the header of the
+// journal is committed with the addresses where they belong, then they are
+// written in place, and finally the header is cleared. A committed journal
+// found when the filesystem is mounted is replayed.
+//
+// Header structure:
+// 0     => state (1 when a transaction is committed, 0 otherwise)
+// 1     => number of blocks in the transaction
+// 8..   => entries of the address of a block of the journal followed by the
+//          address of its copy in place
+
+const COMMITTED: u8 = 1;
+const HEADER_LEN: usize = 8;
+
+// Number of blocks of a transaction
+pub const JOURNAL_SIZE: usize = (super::BLOCK_SIZE - HEADER_LEN) / 8;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Journal {
+    addr: u32,
+    slots: Vec<u32>,
+}
+
+impl Journal {
+    // Parse the header of a journal, returning the blocks of a committed
+    // transaction with the addresses of their copies in place
+    fn parse(addr: u32, buf: &[u8]) -> (Self, Vec<(u32, u32)>) {
+        let mut slots = Vec::with_capacity(JOURNAL_SIZE);
+        let mut entries = Vec::new();
+        let n = if buf[0] == COMMITTED { buf[1] as usize } else { 0 };
+        for i in 0..JOURNAL_SIZE {
+            let j = HEADER_LEN + i * 8;
+            let k = j + 4;
+            let slot = u32::from_be_bytes(buf[j..k].try_into().unwrap());
+            let dest = u32::from_be_bytes(buf[k..k + 4].try_into().unwrap());
+            slots.push(slot);
+            if i < n {
+                entries.push((slot, dest));
+            }
+        }
+        (Self { addr, slots }, entries)
+    }
+
+    fn header(&self, dests: &[u32]) -> [u8; super::BLOCK_SIZE] {
+        let mut buf = [0; super::BLOCK_SIZE];
+        if !dests.is_empty() {
+            buf[0] = COMMITTED;
+            buf[1] = dests.len() as u8;
+        }
+        for (i, slot) in self.slots.iter().enumerate() {
+            let j = HEADER_LEN + i * 8;
+            let dest = dests.get(i).copied().unwrap_or(0);
+            buf[j..j + 4].clone_from_slice(&slot.to_be_bytes());
+            buf[j + 4..j + 8].clone_from_slice(&dest.to_be_bytes());
+        }
+        buf
+    }
+
+    // Write a transaction of at most JOURNAL_SIZE blocks on a drive, the
+    // addresses being local to the drive. The write cache of the drive is
+    // flushed between each step because it could otherwise write the header
+    // before the blocks it points to.
+    pub fn commit(
+        &self,
+        drive: &sys::ata::Drive,
+        blocks: &[(u32, &[u8])]
+    ) -> Result<(), ()> {
+        debug_assert!(blocks.len() <= JOURNAL_SIZE);
+        let (bus, dsk) = (drive.bus, drive.dsk);
+        for (slot, (_, buf)) in self.slots.iter().zip(blocks) {
+            sys::ata::write(bus, dsk, *slot, buf)?;
+        }
+        sys::ata::flush_cache(bus, dsk)?;
+        let dests: Vec<u32> = blocks.iter().map(|(addr, _)| *addr).collect();
+        sys::ata::write(bus, dsk, self.addr, &self.header(&dests))?;
+        sys::ata::flush_cache(bus, dsk)?;
+        for (addr, buf) in blocks {
+            sys::ata::write(bus, dsk, *addr, buf)?;
+        }
+        sys::ata::flush_cache(bus, dsk)?;
+        sys::ata::write(bus, dsk, self.addr, &self.header(&[]))?;
+        sys::ata::flush_cache(bus, dsk)
+    }
+}
+
+// Create the journal of a filesystem with blocks allocated in its data area
+fn create(dev: usize) -> Option<u32> {
+    let header = Block::alloc(dev)?;
+    let mut slots = Vec::with_capacity(JOURNAL_SIZE);
+    for _ in 0..JOURNAL_SIZE {
+        slots.push(local_addr(Block::alloc(dev)?.addr()));
+    }
+    let addr = local_addr(header.addr());
+    let journal = Journal { addr, slots };
+    let mut block = Block::new(header.addr());
+    block.data_mut().clone_from_slice(&journal.header(&[]));
+    block.write();
+
+    let mut sb = SuperBlock::read(dev);
+    sb.journal = addr;
+    sb.write();
+    Some(addr)
+}
+
+// Replay the journal of a filesystem mounted from a hard drive, creating
+// the journal if the filesystem doesn't have one yet, then use it for the
+// next flushes of the block cache
+pub fn load(dev: usize) -> Result<(), ()> {
+    set(dev, None);
+    let is_ata = match MOUNTS.lock().get(dev) {
+        Some(Some(mount)) => {
+            matches!(mount.fs, Filesystem::Mfs(BlockDevice::Ata(_)))
+        }
+        _ => false,
+    };
+    if !is_ata {
+        return Ok(());
+    }
+    let addr = match SuperBlock::read(dev).journal {
+        0 => create(dev).ok_or(())?,
+        addr => addr,
+    };
+
+    // The blocks are written in place before the header is cleared
+    let header = Block::read(global_addr(dev, addr));
+    let (journal, entries) = Journal::parse(addr, header.data());
+    for (slot, dest) in entries {
+        let block = Block::read(global_addr(dev, slot));
+        let mut copy = Block::new(global_addr(dev, dest));
+        copy.data_mut().clone_from_slice(block.data());
+        copy.write();
+    }
+    sync()?;
+    let mut block = Block::new(header.addr());
+    block.data_mut().clone_from_slice(&journal.header(&[]));
+    block.write();
+    sync()?;
+
+    set(dev, Some(journal));
+    Ok(())
+}
+
+// Addresses of the blocks of the journal of a filesystem
+pub fn blocks(dev: usize) -> Vec<u32> {
+    let addr = SuperBlock::read(dev).journal;
+    if addr == 0 {
+        return Vec::new();
+    }
+    let header = Block::read(global_addr(dev, addr));
+    let (journal, _) = Journal::parse(addr, header.data());
+    let mut blocks = vec![global_addr(dev, addr)];
+    blocks.extend(journal.slots.iter().map(|slot| global_addr(dev, *slot)));
+    blocks
+}
+
+// Set the journal used by the block cache of a hard drive
+pub fn set(dev: usize, journal: Option<Journal>) {
+    let mut mounts = MOUNTS.lock();
+    if let Some(Some(mount)) = mounts.get_mut(dev) {
+        if let Some(BlockDevice::Ata(ata)) = mount.block_device() {
+            ata.set_journal(journal);
+        }
+    }
+}
+
+#[test_case]
+fn test_journal() {
+    let slots = (10..(10 + JOURNAL_SIZE as u32)).collect();
+    let journal = Journal { addr: 9, slots };
+
+    let (parsed, entries) = Journal::parse(9, &journal.header(&[]));
+    assert_eq!(parsed, journal);
+    assert!(entries.is_empty());
+
+    let (parsed, entries) = Journal::parse(9, &journal.header(&[42, 7]));
+    assert_eq!(parsed, journal);
+    assert_eq!(entries, [(10, 42), (11, 7)]);
+}
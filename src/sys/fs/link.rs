@@ -0,0 +1,244 @@
+use super::block::LinkedBlock;
+use super::block_device::{self, device};
+use super::dir::Dir;
+use super::dir_entry::DirEntry;
+use super::file::File;
+use super::{dirname, filename, is_read_only, realpath, FileIO};
+
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+// Symbolic links followed while resolving a path are counted to detect loops
+const MAX_LINKS: usize = 16;
+
+// A symbolic link is a dir entry with its own kind storing the path of its
+// target in its data blocks.
+pub fn symlink(target: &str, pathname: &str) -> Result<(), ()> {
+    if target.is_empty() {
+        return Err(());
+    }
+    let pathname = resolve(pathname, false).ok_or(())?;
+    if is_read_only(&pathname) {
+        return Err(());
+    }
+    let dirname = dirname(&pathname);
+    let filename = filename(&pathname);
+    let mut dir = Dir::open(dirname).ok_or(())?;
+    let entry = dir.create_link(filename).ok_or(())?;
+    let mut file = File::from(entry);
+    match file.write(target.as_bytes()) {
+        Ok(n) if n == target.len() => Ok(()),
+        _ => {
+            dir.delete_entry(filename).ok();
+            Err(())
+        }
+    }
+}
+
+// A hard link is a dir entry sharing the node of a file with the other
+// links of the file, the node being created with the first link.
+pub fn link(source: &str, pathname: &str) -> Result<(), ()> {
+    let source = resolve(source, true).ok_or(())?;
+    if is_read_only(&source) {
+        return Err(());
+    }
+    let entry = DirEntry::open(&source).ok_or(())?;
+    if entry.is_dir() {
+        return Err(());
+    }
+    let pathname = resolve(pathname, false).ok_or(())?;
+    if is_read_only(&pathname) {
+        return Err(());
+    }
+    let dirname = dirname(&pathname);
+    let filename = filename(&pathname);
+    let mut dir = Dir::open(dirname).ok_or(())?;
+    if dir.find(filename).is_some() {
+        return Err(());
+    }
+    if device(dir.addr()) != device(entry.dir().addr()) {
+        return Err(()); // Hard links cannot cross filesystems
+    }
+    let mut node = entry.dir().attach_node(&entry.name()).ok_or(())?;
+    match dir.create_hard_link(entry.kind(), filename, &mut node) {
+        Some(_) => Ok(()),
+        None => Err(()),
+    }
+}
+
+pub fn read_link(pathname: &str) -> Option<String> {
+    let entry = DirEntry::open(pathname)?;
+    if entry.is_link() {
+        Some(target(entry))
+    } else {
+        None
+    }
+}
+
+// The target is read directly from the blocks of the link to avoid updating
+// its access time each time a path crossing it is resolved
+fn target(entry: DirEntry) -> String {
+    let size = entry.size() as usize;
+    let mut buf = Vec::with_capacity(size);
+    let mut block = LinkedBlock::read(entry.addr());
+    loop {
+        buf.extend_from_slice(block.data());
+        if buf.len() >= size {
+            break;
+        }
+        match block.next() {
+            Some(next_block) => block = next_block,
+            None => break,
+        }
+    }
+    buf.truncate(size);
+    String::from_utf8_lossy(&buf).to_string()
+}
+
+// Return the absolute path of a file with every symbolic link followed,
+// except the last component when `follow` is false. The last component
+// doesn't have to exist so that the path can be used to create a file.
+// Nothing is returned when a parent dir is missing or when too many links
+// are followed.
+pub fn resolve(pathname: &str, follow: bool) -> Option<String> {
+    if !super::is_mounted() {
+        return None;
+    }
+
+    let mut names = VecDeque::new();
+    push_names(&mut names, &realpath(pathname));
+
+    let mut dirs = vec![Dir::root()];
+    let mut path: Vec<String> = Vec::new();
+    let mut links = 0;
+    while let Some(name) = names.pop_front() {
+        if name == ".." {
+            if path.pop().is_some() {
+                dirs.pop();
+            }
+            continue;
+        }
+        let is_last = names.is_empty();
+        let dir = dirs.last()?;
+        match dir.find(&name) {
+            Some(entry) if entry.is_link() && (follow || !is_last) => {
+                links += 1;
+                if links > MAX_LINKS {
+                    return None;
+                }
+                let target = target(entry);
+                if target.starts_with('/') {
+                    path.clear();
+                    dirs.truncate(1);
+                }
+                push_names(&mut names, &target);
+            }
+            Some(entry) if entry.is_dir() && !is_last => {
+                dirs.push(entry.into());
+                path.push(name);
+
+                // The rest of a path on a mounted image or on a virtual
+                // filesystem has no links
+                let mount_point = format!("/{}", path.join("/"));
+                if block_device::is_path_mount_point(&mount_point) {
+                    while let Some(name) = names.pop_front() {
+                        if name == ".." {
+                            path.pop();
+                        } else {
+                            path.push(name);
+                        }
+                    }
+                }
+            }
+            _ if is_last => path.push(name),
+            _ => return None,
+        }
+    }
+    Some(format!("/{}", path.join("/")))
+}
+
+fn push_names(names: &mut VecDeque<String>, pathname: &str) {
+    let names_iter = pathname.split('/');
+    for name in names_iter.filter(|n| !n.is_empty() && *n != ".").rev() {
+        names.push_front(name.to_string());
+    }
+}
+
+#[test_case]
+fn test_symlink() {
+    super::mount_mem();
+    super::format_mem();
+    assert!(Dir::create("/tmp").is_some());
+    let mut file = File::create("/tmp/test").unwrap();
+    file.write("Hello, World!".as_bytes()).unwrap();
+
+    assert!(symlink("/tmp/test", "/tmp/abs").is_ok());
+    assert!(symlink("test", "/tmp/rel").is_ok());
+    assert!(symlink("/tmp", "/dir").is_ok());
+    assert!(symlink("/tmp/test", "/tmp/abs").is_err());
+    assert_eq!(read_link("/tmp/abs"), Some("/tmp/test".to_string()));
+    assert_eq!(read_link("/tmp/rel"), Some("test".to_string()));
+    assert_eq!(read_link("/tmp/test"), None);
+
+    let dir = Dir::open("/tmp").unwrap();
+    assert!(dir.set_times("abs", Some(0), None).is_ok());
+    assert_eq!(resolve("/tmp/abs", true), Some("/tmp/test".to_string()));
+    assert_eq!(DirEntry::open("/tmp/abs").unwrap().atime(), 0);
+
+    assert_eq!(resolve("/tmp/abs", true), Some("/tmp/test".to_string()));
+    assert_eq!(resolve("/tmp/abs", false), Some("/tmp/abs".to_string()));
+    assert_eq!(resolve("/dir/rel", true), Some("/tmp/test".to_string()));
+    assert_eq!(resolve("/dir/../tmp", true), Some("/tmp".to_string()));
+    assert_eq!(resolve("/dir/new", true), Some("/tmp/new".to_string()));
+    assert_eq!(resolve("/none/new", true), None);
+
+    let mut file = File::open("/dir/rel").unwrap();
+    assert_eq!(file.read_to_string(), "Hello, World!");
+    super::dismount();
+}
+
+#[test_case]
+fn test_hard_link() {
+    super::mount_mem();
+    super::format_mem();
+    let mut file = File::create("/a").unwrap();
+    file.write("Hello".as_bytes()).unwrap();
+    let used = super::disk_used();
+
+    assert!(link("/a", "/b").is_ok());
+    assert!(link("/a", "/b").is_err());
+    assert!(link("/", "/c").is_err());
+    assert_eq!(DirEntry::open("/a").unwrap().links(), 2);
+    assert_eq!(DirEntry::open("/b").unwrap().links(), 2);
+
+    let mut file = File::open("/b").unwrap();
+    file.write("Hello, World!".as_bytes()).unwrap();
+    let mut file = File::open("/a").unwrap();
+    assert_eq!(file.read_to_string(), "Hello, World!");
+    assert_eq!(DirEntry::open("/a").unwrap().size(), 13);
+
+    assert!(File::delete("/a").is_ok());
+    assert!(File::open("/a").is_none());
+    assert_eq!(DirEntry::open("/b").unwrap().links(), 1);
+    let mut file = File::open("/b").unwrap();
+    assert_eq!(file.read_to_string(), "Hello, World!");
+
+    assert!(File::delete("/b").is_ok());
+    assert!(super::disk_used() < used);
+    super::dismount();
+}
+
+#[test_case]
+fn test_symlink_loop() {
+    super::mount_mem();
+    super::format_mem();
+    assert!(symlink("/b", "/a").is_ok());
+    assert!(symlink("/a", "/b").is_ok());
+    assert_eq!(resolve("/a", false), Some("/a".to_string()));
+    assert_eq!(resolve("/a", true), None);
+    assert!(File::open("/a").is_none());
+    super::dismount();
+}
@@ -1,10 +1,16 @@
 mod bitmap_block;
 mod block;
 mod block_device;
+mod check;
 mod device;
 mod dir;
 mod dir_entry;
 mod file;
+mod iso9660;
+mod journal;
+mod link;
+mod node;
+mod procfs;
 mod read_dir;
 mod super_block;
 
@@ -14,19 +20,28 @@ pub use crate::api::fs::{dirname, filename, realpath, FileIO, IO};
 pub use crate::sys::ata::BLOCK_SIZE;
 pub use bitmap_block::BITMAP_SIZE;
 pub use block_device::{
-    dismount, format_ata, format_mem, is_mounted, mount_ata, mount_mem
+    dismount, format_ata, format_mem, is_mounted, mount_ata, mount_mem, mounts
 };
+pub use block_device::{mfs_device, sync, sync_if_due};
+pub use check::{check, Problem};
+pub use block_device::{AtaBlockDevice, BlockDevice, Filesystem, MemBlockDevice};
+pub use iso9660::{Iso, IsoDevice};
 pub use device::{Device, DeviceType};
 pub use dir::Dir;
 pub use dir_entry::FileInfo;
 pub use file::{File, SeekFrom};
+pub use link::{link, read_link, resolve, symlink};
 
 use dir_entry::DirEntry;
+use iso9660::IsoFile;
+use procfs::ProcFile;
 use super_block::SuperBlock;
 
+use alloc::format;
 use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 
-pub const VERSION: u8 = 1;
+pub const VERSION: u8 = 4;
 
 // TODO: Move that to API
 #[derive(Clone, Copy)]
@@ -48,6 +63,14 @@ impl OpenFlag {
 }
 
 pub fn open(path: &str, flags: usize) -> Option<Resource> {
+    if !is_permitted(path, flags) {
+        return None;
+    }
+    if let Some(path) = resolve(path, true) {
+        if is_read_only(&path) {
+            return open_read_only(&path, flags);
+        }
+    }
     if OpenFlag::Dir.is_set(flags) {
         let res = Dir::open(path);
         if res.is_none() && OpenFlag::Create.is_set(flags) {
@@ -63,35 +86,247 @@ pub fn open(path: &str, flags: usize) -> Option<Resource> {
             res
         }.map(Resource::Device)
     } else {
-        let mut res = File::open(path);
+        let res = File::open(path);
         if res.is_none() && OpenFlag::Create.is_set(flags) {
             File::create(path)
         } else {
+            res
+        }.map(|mut file| {
+            if OpenFlag::Truncate.is_set(flags) {
+                file.truncate();
+            }
             if OpenFlag::Append.is_set(flags) {
-                if let Some(ref mut file) = res {
-                    file.seek(SeekFrom::End(0)).ok();
-                }
+                file.set_append(true);
+                file.seek(SeekFrom::End(0)).ok();
             }
-            res
-        }.map(Resource::File)
+            Resource::File(file)
+        })
     }
 }
 
+// The files of a mounted image or of the proc filesystem can only be read,
+// and the dirs hidden below them must not be changed through their paths
+fn is_read_only(path: &str) -> bool {
+    iso9660::is_mounted(path) || procfs::is_mounted(path)
+}
+
+fn open_read_only(path: &str, flags: usize) -> Option<Resource> {
+    let write_flags = [
+        OpenFlag::Write,
+        OpenFlag::Append,
+        OpenFlag::Create,
+        OpenFlag::Truncate,
+        OpenFlag::Device,
+    ];
+    if write_flags.iter().any(|flag| flag.is_set(flags)) {
+        return None;
+    }
+    let (res, is_dir) = if iso9660::is_mounted(path) {
+        let file = IsoFile::open(path)?;
+        let is_dir = file.is_dir();
+        (Resource::Iso(file), is_dir)
+    } else {
+        let file = ProcFile::open(path)?;
+        let is_dir = file.is_dir();
+        (Resource::Proc(file), is_dir)
+    };
+    if is_dir != OpenFlag::Dir.is_set(flags) {
+        return None;
+    }
+    Some(res)
+}
+
+// Check the permissions of a file before opening it, or the permissions of
+// its parent dir when it has to be created
+fn is_permitted(path: &str, flags: usize) -> bool {
+    let write_flags = [
+        OpenFlag::Write,
+        OpenFlag::Append,
+        OpenFlag::Create,
+        OpenFlag::Truncate,
+    ];
+    let perm = if OpenFlag::Dir.is_set(flags) {
+        Permission::Read
+    } else if write_flags.iter().any(|flag| flag.is_set(flags)) {
+        Permission::Write
+    } else {
+        Permission::Read
+    };
+    match info(path) {
+        Some(info) => perm.is_granted(&info),
+        None if OpenFlag::Create.is_set(flags) => is_writable_dir(path),
+        None => true,
+    }
+}
+
+fn is_writable_dir(path: &str) -> bool {
+    match resolve(path, false) {
+        Some(path) => match info(dirname(&path)) {
+            Some(info) => Permission::Write.is_granted(&info),
+            None => false,
+        },
+        None => false,
+    }
+}
+
+// A symbolic link is deleted instead of its target
 pub fn delete(path: &str) -> Result<(), ()> {
-    if let Some(info) = info(path) {
+    if !is_writable_dir(path) || is_mount_point(path) {
+        return Err(());
+    }
+    if resolve(path, false).map_or(true, |path| is_read_only(&path)) {
+        return Err(());
+    }
+    if let Some(info) = link_info(path) {
         if info.is_dir() {
             return Dir::delete(path);
-        } else if info.is_file() || info.is_device() {
+        } else if info.is_file() || info.is_device() || info.is_link() {
             return File::delete(path);
         }
     }
     Err(())
 }
 
+// A file is renamed by moving its entry to another dir without copying its
+// content, replacing the destination if it exists and is not a dir
+pub fn rename(source: &str, dest: &str) -> Result<(), ()> {
+    let source = resolve(source, false).ok_or(())?;
+    let dest = resolve(dest, false).ok_or(())?;
+    if !is_writable_dir(&source) || !is_writable_dir(&dest) {
+        return Err(());
+    }
+    if is_read_only(&source) || is_read_only(&dest) {
+        return Err(());
+    }
+    if source == dest {
+        return Ok(());
+    }
+    if source == "/" || dest.starts_with(&format!("{}/", source)) {
+        return Err(()); // A dir cannot be moved inside itself
+    }
+    if let Some(info) = link_info(&dest) {
+        if info.is_dir() {
+            return Err(());
+        }
+    }
+    if is_mount_point(&source) {
+        return Err(());
+    }
+    let mut src_dir = Dir::open(dirname(&source)).ok_or(())?;
+    let mut dst_dir = Dir::open(dirname(&dest)).ok_or(())?;
+    src_dir.rename_entry(filename(&source), &mut dst_dir, filename(&dest))
+}
+
+// Mount the filesystem of a device on a dir, hiding its content until the
+// filesystem is unmounted. Users can mount filesystems on the dirs they own.
+pub fn mount(fs: Filesystem, path: &str) -> Result<(), ()> {
+    let path = resolve(path, true).ok_or(())?;
+    let entry = DirEntry::open(&path).ok_or(())?;
+    if path == "/" || !entry.is_dir() || !is_owner(&entry.info()) {
+        return Err(());
+    }
+
+    // Only the root dir of MFS is reached through the dir it covers
+    let covered = match fs {
+        Filesystem::Mfs(_) => entry.addr(),
+        _ => 0,
+    };
+    block_device::mount(fs, &path, covered)
+}
+
+fn is_mount_point(path: &str) -> bool {
+    match resolve(path, false) {
+        Some(path) => block_device::is_mount_point(&path),
+        None => false,
+    }
+}
+
+// Check if two paths are in the same filesystem, files in different ones
+// cannot be renamed from one to the other
+pub fn is_same_filesystem(a: &str, b: &str) -> bool {
+    let a = resolve(a, true).and_then(|path| block_device::mount_point(&path));
+    let b = resolve(b, true).and_then(|path| block_device::mount_point(&path));
+    a.is_some() && a == b
+}
+
+// The entry of a mount point is the one of the dir it covers, so the user
+// who could mount a filesystem on it can also unmount it
+pub fn umount(path: &str) -> Result<(), ()> {
+    let path = resolve(path, true).ok_or(())?;
+    let entry = DirEntry::open(&path).ok_or(())?;
+    if !is_owner(&entry.info()) {
+        return Err(());
+    }
+    block_device::umount(&path)
+}
+
 pub fn info(pathname: &str) -> Option<FileInfo> {
-    if pathname == "/" {
+    resolve(pathname, true).and_then(|pathname| link_info(&pathname))
+}
+
+// The mode of a symbolic link is ignored so the mode of its target is changed
+pub fn chmod(pathname: &str, mode: u16) -> Result<(), ()> {
+    let pathname = resolve(pathname, true).ok_or(())?;
+    if is_read_only(&pathname) {
+        return Err(());
+    }
+    let entry = DirEntry::open(&pathname).ok_or(())?;
+    if !is_owner(&entry.info()) {
+        return Err(());
+    }
+    entry.dir().set_mode(&entry.name(), mode & 0o777)
+}
+
+// Only root can give a file to another user, and the owner of a file can
+// only change its group to their own group
+pub fn chown(pathname: &str, uid: u16, gid: u16) -> Result<(), ()> {
+    let pathname = resolve(pathname, true).ok_or(())?;
+    if is_read_only(&pathname) {
+        return Err(());
+    }
+    let entry = DirEntry::open(&pathname).ok_or(())?;
+    let info = entry.info();
+    if !is_owner(&info) {
+        return Err(());
+    }
+    if sys::process::uid() != 0 {
+        if uid != info.uid() {
+            return Err(());
+        }
+        if gid != info.gid() && gid != sys::process::gid() {
+            return Err(());
+        }
+    }
+    entry.dir().set_owner(&entry.name(), uid, gid)
+}
+
+// The times of a file can be set by its owner or by a user allowed to
+// write to it
+pub fn utime(pathname: &str, atime: u64, mtime: u64) -> Result<(), ()> {
+    let pathname = resolve(pathname, true).ok_or(())?;
+    if is_read_only(&pathname) {
+        return Err(());
+    }
+    let entry = DirEntry::open(&pathname).ok_or(())?;
+    let info = entry.info();
+    if !is_owner(&info) && !Permission::Write.is_granted(&info) {
+        return Err(());
+    }
+    entry.dir().set_times(&entry.name(), Some(atime), Some(mtime))
+}
+
+// Return the info of a symbolic link instead of its target
+pub fn link_info(pathname: &str) -> Option<FileInfo> {
+    let path = resolve(pathname, false)?;
+    if path == "/" {
         return Some(FileInfo::root());
     }
+    if iso9660::is_mounted(&path) {
+        return iso9660::info(&path);
+    }
+    if procfs::is_mounted(&path) {
+        return procfs::info(&path);
+    }
     DirEntry::open(pathname).map(|e| e.info())
 }
 
@@ -100,6 +335,53 @@ pub enum FileType {
     Dir = 0,
     File = 1,
     Device = 2,
+    Link = 3,
+}
+
+impl FileType {
+    pub fn default_mode(&self) -> u16 {
+        match self {
+            FileType::Dir => 0o755,
+            FileType::File => 0o644,
+            FileType::Device => 0o666,
+            FileType::Link => 0o777,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u16)]
+pub enum Permission {
+    Read = 4,
+    Write = 2,
+    Exec = 1,
+}
+
+impl Permission {
+    // The permissions of a file are checked with the bits of its mode for
+    // its owner, its group, or the others depending on the user of the
+    // process, except for root who only needs one of the execute bits
+    pub fn is_granted(&self, info: &FileInfo) -> bool {
+        let uid = sys::process::uid();
+        let mode = info.mode();
+        if uid == 0 {
+            return *self != Permission::Exec || mode & 0o111 != 0;
+        }
+        let bits = if uid == info.uid() {
+            mode >> 6
+        } else if sys::process::gid() == info.gid() {
+            mode >> 3
+        } else {
+            mode
+        };
+        bits & (*self as u16) != 0
+    }
+}
+
+// Only root and the owner of a file can change its mode and ownership
+fn is_owner(info: &FileInfo) -> bool {
+    let uid = sys::process::uid();
+    uid == 0 || uid == info.uid()
 }
 
 #[derive(Debug, Clone)]
@@ -107,6 +389,18 @@ pub enum Resource {
     Dir(Dir),
     File(File),
     Device(Device),
+    Iso(IsoFile),
+    Proc(ProcFile),
+}
+
+impl Resource {
+    // Only files can be read or written at an arbitrary position
+    pub fn seek(&mut self, pos: SeekFrom) -> Result<u32, ()> {
+        match self {
+            Resource::File(io) => io.seek(pos),
+            _ => Err(()),
+        }
+    }
 }
 
 impl FileIO for Resource {
@@ -115,6 +409,8 @@ impl FileIO for Resource {
             Resource::Dir(io) => io.read(buf),
             Resource::File(io) => io.read(buf),
             Resource::Device(io) => io.read(buf),
+            Resource::Iso(io) => io.read(buf),
+            Resource::Proc(io) => io.read(buf),
         }
     }
 
@@ -123,6 +419,8 @@ impl FileIO for Resource {
             Resource::Dir(io) => io.write(buf),
             Resource::File(io) => io.write(buf),
             Resource::Device(io) => io.write(buf),
+            Resource::Iso(io) => io.write(buf),
+            Resource::Proc(io) => io.write(buf),
         }
     }
 
@@ -131,6 +429,8 @@ impl FileIO for Resource {
             Resource::Dir(io) => io.close(),
             Resource::File(io) => io.close(),
             Resource::Device(io) => io.close(),
+            Resource::Iso(io) => io.close(),
+            Resource::Proc(io) => io.close(),
         }
     }
 
@@ -139,6 +439,8 @@ impl FileIO for Resource {
             Resource::Dir(io) => io.poll(event),
             Resource::File(io) => io.poll(event),
             Resource::Device(io) => io.poll(event),
+            Resource::Iso(io) => io.poll(event),
+            Resource::Proc(io) => io.poll(event),
         }
     }
 }
@@ -157,24 +459,103 @@ pub fn canonicalize(path: &str) -> Result<String, ()> {
 }
 
 pub fn disk_size() -> usize {
-    (SuperBlock::read().block_count as usize) * BLOCK_SIZE
+    (SuperBlock::read(0).block_count as usize) * BLOCK_SIZE
 }
 
 pub fn disk_used() -> usize {
-    (SuperBlock::read().alloc_count as usize) * BLOCK_SIZE
+    (SuperBlock::read(0).alloc_count as usize) * BLOCK_SIZE
 }
 
 pub fn disk_free() -> usize {
     disk_size() - disk_used()
 }
 
+// Device exposing the usage of each mounted MFS filesystem on a line made
+// of the block size, the number of blocks, the number of allocated blocks,
+// and the path of the mount point.
+#[derive(Debug, Clone)]
+pub struct DiskUsage;
+
+impl DiskUsage {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub fn size() -> usize {
+        1024
+    }
+
+    pub fn lines() -> Vec<String> {
+        block_device::mfs_mounts().into_iter().map(|(dev, path)| {
+            let sb = SuperBlock::read(dev);
+            format!(
+                "{} {} {} {}",
+                sb.block_size(), sb.block_count, sb.alloc_count, path
+            )
+        }).collect()
+    }
+}
+
+impl Default for DiskUsage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileIO for DiskUsage {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ()> {
+        if !is_mounted() {
+            return Err(());
+        }
+        let mut n = 0;
+        for line in Self::lines() {
+            let m = line.len() + 1;
+            if n + m > buf.len() {
+                break;
+            }
+            buf[n..n + m - 1].copy_from_slice(line.as_bytes());
+            buf[n + m - 1] = b'\n';
+            n += m;
+        }
+        Ok(n)
+    }
+
+    fn write(&mut self, _buf: &[u8]) -> Result<usize, ()> {
+        Err(())
+    }
+
+    fn close(&mut self) {}
+
+    fn poll(&mut self, event: IO) -> bool {
+        match event {
+            IO::Read => true,
+            IO::Write => false,
+        }
+    }
+}
+
+// Version of the filesystem of a hard drive, which can only be mounted if
+// it is the current version
+pub fn version_ata(bus: u8, dsk: u8) -> Option<u8> {
+    SuperBlock::version_ata(bus, dsk)
+}
+
 pub fn init() {
     for bus in 0..2 {
         for dsk in 0..2 {
-            if SuperBlock::check_ata(bus, dsk) {
-                log!("MFS Superblock found in ATA {}:{}", bus, dsk);
-                mount_ata(bus, dsk);
-                return;
+            match SuperBlock::version_ata(bus, dsk) {
+                Some(VERSION) => {
+                    log!("MFS Superblock found in ATA {}:{}", bus, dsk);
+                    mount_ata(bus, dsk);
+                    return;
+                }
+                Some(version) => {
+                    log!(
+                        "MFS v{} found in ATA {}:{} is not supported (v{})",
+                        version, bus, dsk, VERSION
+                    );
+                }
+                None => {}
             }
         }
     }
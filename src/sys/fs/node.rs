@@ -0,0 +1,150 @@
+use super::bitmap_block::BitmapBlock;
+use super::block::{Block, LinkedBlock};
+use super::block_device::{device, global_addr, local_addr};
+use super::dir_entry::DirEntry;
+
+use core::convert::TryInto;
+
+// The kind of a dir entry pointing to a node has this bit set
+pub const NODE_FLAG: u8 = 0x80;
+
+// A node is a block shared by the hard links of a file to keep its metadata
+// in one place, the dir entry of each link pointing to the node instead of
+// the first block of the file.
+//
+// Node structure:
+// 0..4   => number of links
+// 4..8   => address of the first block of the file
+// 8..12  => size of the file
+// 12..20 => creation time of the file
+// 20..28 => modification time of the file
+// 28..36 => access time of the file
+// 36..38 => mode of the file
+// 38..40 => user ID of the owner of the file
+// 40..42 => group ID of the file
+#[derive(Clone)]
+pub struct Node {
+    block: Block,
+}
+
+impl Node {
+    pub fn create(entry: &DirEntry) -> Option<Self> {
+        let block = Block::alloc(device(entry.addr()))?;
+        let mut node = Self { block };
+        node.set_links(1);
+        node.set_u32(4, local_addr(entry.addr()));
+        node.set_size(entry.size());
+        node.set_u64(12, entry.ctime());
+        node.set_mtime(entry.mtime());
+        node.set_atime(entry.atime());
+        node.set_mode(entry.mode());
+        node.set_owner(entry.uid(), entry.gid());
+        node.write();
+        Some(node)
+    }
+
+    pub fn read(addr: u32) -> Self {
+        Self { block: Block::read(addr) }
+    }
+
+    pub fn write(&self) {
+        self.block.write();
+    }
+
+    // Address of the node
+    pub fn addr(&self) -> u32 {
+        self.block.addr()
+    }
+
+    pub fn links(&self) -> u32 {
+        self.u32(0)
+    }
+
+    pub fn set_links(&mut self, links: u32) {
+        self.set_u32(0, links);
+    }
+
+    // Address of the first block of the file
+    pub fn data_addr(&self) -> u32 {
+        global_addr(device(self.addr()), self.u32(4))
+    }
+
+    pub fn size(&self) -> u32 {
+        self.u32(8)
+    }
+
+    pub fn set_size(&mut self, size: u32) {
+        self.set_u32(8, size);
+    }
+
+    pub fn ctime(&self) -> u64 {
+        self.u64(12)
+    }
+
+    pub fn mtime(&self) -> u64 {
+        self.u64(20)
+    }
+
+    pub fn set_mtime(&mut self, time: u64) {
+        self.set_u64(20, time);
+    }
+
+    pub fn atime(&self) -> u64 {
+        self.u64(28)
+    }
+
+    pub fn set_atime(&mut self, time: u64) {
+        self.set_u64(28, time);
+    }
+
+    pub fn mode(&self) -> u16 {
+        u16::from_be_bytes(self.block.data()[36..38].try_into().unwrap())
+    }
+
+    pub fn set_mode(&mut self, mode: u16) {
+        self.block.data_mut()[36..38].clone_from_slice(&mode.to_be_bytes());
+    }
+
+    pub fn uid(&self) -> u16 {
+        u16::from_be_bytes(self.block.data()[38..40].try_into().unwrap())
+    }
+
+    pub fn gid(&self) -> u16 {
+        u16::from_be_bytes(self.block.data()[40..42].try_into().unwrap())
+    }
+
+    pub fn set_owner(&mut self, uid: u16, gid: u16) {
+        let data = self.block.data_mut();
+        data[38..40].clone_from_slice(&uid.to_be_bytes());
+        data[40..42].clone_from_slice(&gid.to_be_bytes());
+    }
+
+    // Free the blocks of the file with the node
+    pub fn free(self) {
+        let mut block = LinkedBlock::read(self.data_addr());
+        loop {
+            BitmapBlock::free(block.addr());
+            match block.next() {
+                Some(next_block) => block = next_block,
+                None => break,
+            }
+        }
+        BitmapBlock::free(self.addr());
+    }
+
+    fn u32(&self, i: usize) -> u32 {
+        u32::from_be_bytes(self.block.data()[i..(i + 4)].try_into().unwrap())
+    }
+
+    fn set_u32(&mut self, i: usize, val: u32) {
+        self.block.data_mut()[i..(i + 4)].clone_from_slice(&val.to_be_bytes());
+    }
+
+    fn u64(&self, i: usize) -> u64 {
+        u64::from_be_bytes(self.block.data()[i..(i + 8)].try_into().unwrap())
+    }
+
+    fn set_u64(&mut self, i: usize, val: u64) {
+        self.block.data_mut()[i..(i + 8)].clone_from_slice(&val.to_be_bytes());
+    }
+}
@@ -0,0 +1,222 @@
+use super::block_device::{find_mount, Filesystem};
+use super::dir_entry::FileInfo;
+use super::{filename, FileIO, FileType, IO};
+use crate::sys;
+use crate::sys::net::stat::{NetIfaces, NetSockets};
+use crate::sys::process::MAX_PID;
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::sync::atomic::Ordering;
+
+// The proc filesystem is a virtual filesystem giving a view of the kernel
+// data as read-only text files generated when they are opened:
+//
+// /<pid>/status   => fields of a running process
+// /meminfo        => size of the memory with the bytes used and free
+// /net/interfaces => counters of the network interfaces
+// /net/sockets    => table of the open sockets
+// /uptime         => seconds elapsed since boot
+//
+// Each line of a file is made of a field and its value, or of values
+// separated by spaces for a table, to be easily parsed by scripts.
+
+#[derive(Debug, Clone)]
+enum Node {
+    Dir(Vec<String>),
+    File(Vec<u8>),
+}
+
+// Generate the node at a path of the filesystem
+fn node(path: &str) -> Option<Node> {
+    let names: Vec<&str> = path.split('/').filter(|n| !n.is_empty()).collect();
+    match names.as_slice() {
+        [] => {
+            let n = MAX_PID.load(Ordering::SeqCst);
+            let mut names: Vec<String> = (0..n).map(|id| {
+                id.to_string()
+            }).collect();
+            names.extend(["meminfo", "net", "uptime"].map(String::from));
+            Some(Node::Dir(names))
+        }
+        ["meminfo"] => {
+            let size = sys::allocator::memory_size();
+            let used = sys::allocator::memory_used();
+            let free = size - used;
+            let text = format!(
+                "MemTotal: {}\nMemUsed: {}\nMemFree: {}\n", size, used, free
+            );
+            Some(Node::File(text.into_bytes()))
+        }
+        ["net"] => {
+            Some(Node::Dir(vec!["interfaces".into(), "sockets".into()]))
+        }
+        ["net", "interfaces"] => Some(Node::File(lines(NetIfaces::lines()))),
+        ["net", "sockets"] => Some(Node::File(lines(NetSockets::lines()))),
+        ["uptime"] => {
+            let text = format!("{:.6}\n", sys::clock::uptime());
+            Some(Node::File(text.into_bytes()))
+        }
+        [pid] => {
+            status(pid)?;
+            Some(Node::Dir(vec!["status".into()]))
+        }
+        [pid, "status"] => status(pid).map(|s| Node::File(s.into_bytes())),
+        _ => None,
+    }
+}
+
+fn lines(lines: Vec<String>) -> Vec<u8> {
+    lines.iter().flat_map(|line| {
+        line.bytes().chain(core::iter::once(b'\n'))
+    }).collect()
+}
+
+// The name of a process dir is its ID written without leading zeros
+fn status(name: &str) -> Option<String> {
+    let id: usize = name.parse().ok()?;
+    if id.to_string() != name {
+        return None;
+    }
+    sys::process::status(id)
+}
+
+fn node_info(name: &str, node: &Node) -> FileInfo {
+    let time = sys::clock::realtime() as u64;
+    match node {
+        Node::Dir(_) => {
+            FileInfo::from_parts(FileType::Dir, name, 0, time, 0o555)
+        }
+        Node::File(buf) => {
+            let size = buf.len() as u32;
+            FileInfo::from_parts(FileType::File, name, size, time, 0o444)
+        }
+    }
+}
+
+// Find the path of a file in the proc filesystem mounted on a path
+fn mounted(path: &str) -> Option<String> {
+    find_mount(path, |fs| match fs {
+        Filesystem::Proc => Some(()),
+        _ => None,
+    }).map(|(_, rest)| rest)
+}
+
+// Check if a path is on the proc filesystem, the mount point included
+pub fn is_mounted(path: &str) -> bool {
+    mounted(path).is_some()
+}
+
+pub fn info(path: &str) -> Option<FileInfo> {
+    ProcFile::open(path).map(|file| file.info())
+}
+
+#[derive(Debug, Clone)]
+pub struct ProcFile {
+    name: String,
+    path: String,
+    node: Node,
+    offset: usize,
+    entry_index: usize,
+}
+
+impl ProcFile {
+    pub fn open(path: &str) -> Option<Self> {
+        let rest = mounted(path)?;
+        let node = node(&rest)?;
+        let name = filename(path).into();
+        Some(Self { name, path: rest, node, offset: 0, entry_index: 0 })
+    }
+
+    pub fn is_dir(&self) -> bool {
+        matches!(self.node, Node::Dir(_))
+    }
+
+    pub fn info(&self) -> FileInfo {
+        node_info(&self.name, &self.node)
+    }
+}
+
+impl FileIO for ProcFile {
+    // The entries of a dir are read as file infos like in MFS
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ()> {
+        let mut i = 0;
+        match &self.node {
+            Node::Dir(names) => {
+                for name in names.iter().skip(self.entry_index) {
+                    let path = format!("{}/{}", self.path, name);
+                    let bytes = match node(&path) {
+                        Some(node) => node_info(name, &node).as_bytes(),
+                        None => {
+                            // The process has exited since the dir was open
+                            self.entry_index += 1;
+                            continue;
+                        }
+                    };
+                    let j = i + bytes.len();
+                    if j < buf.len() {
+                        buf[i..j].copy_from_slice(&bytes);
+                        self.entry_index += 1;
+                        i = j;
+                    } else {
+                        break;
+                    }
+                }
+            }
+            Node::File(content) => {
+                let j = self.offset;
+                let n = buf.len().min(content.len() - j);
+                buf[0..n].copy_from_slice(&content[j..(j + n)]);
+                self.offset += n;
+                i = n;
+            }
+        }
+        Ok(i)
+    }
+
+    fn write(&mut self, _buf: &[u8]) -> Result<usize, ()> {
+        Err(())
+    }
+
+    fn close(&mut self) {}
+
+    fn poll(&mut self, event: IO) -> bool {
+        match (&self.node, event) {
+            (Node::Dir(names), IO::Read) => self.entry_index < names.len(),
+            (Node::File(content), IO::Read) => self.offset < content.len(),
+            (_, IO::Write) => false,
+        }
+    }
+}
+
+#[test_case]
+fn test_procfs() {
+    super::mount_mem();
+    super::format_mem();
+    assert!(super::Dir::create("/proc").is_some());
+    assert!(super::File::create("/proc/uptime").is_some());
+    assert!(super::mount(Filesystem::Proc, "/proc").is_ok());
+
+    assert!(super::info("/proc").unwrap().is_dir());
+    assert!(super::info("/proc/0").unwrap().is_dir());
+    assert!(super::info("/proc/00").is_none());
+    assert!(super::info("/proc/net/../uptime").unwrap().is_file());
+
+    let mut file = ProcFile::open("/proc/0/status").unwrap();
+    let mut buf = [0; 256];
+    let n = file.read(&mut buf).unwrap();
+    assert!(buf[0..n].starts_with(b"Pid: 0\n"));
+    assert_eq!(file.read(&mut buf), Ok(0));
+
+    let flags = super::OpenFlag::Write as usize;
+    assert!(super::open("/proc/meminfo", flags).is_none());
+    assert!(super::delete("/proc/uptime").is_err());
+    assert!(super::rename("/proc/uptime", "/uptime").is_err());
+
+    // The file hidden by the mount is left untouched
+    assert!(super::umount("/proc").is_ok());
+    assert_eq!(super::info("/proc/uptime").unwrap().size(), 0);
+    super::dismount();
+}
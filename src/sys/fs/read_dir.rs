@@ -1,6 +1,8 @@
 use super::block::LinkedBlock;
+use super::block_device::{device, global_addr};
 use super::dir::Dir;
 use super::dir_entry::DirEntry;
+use super::node::{Node, NODE_FLAG};
 use super::FileType;
 
 use alloc::string::String;
@@ -54,6 +56,7 @@ impl ReadDir {
     }
 
     read_uint_fn!(read_u8, u8);
+    read_uint_fn!(read_u16, u16);
     read_uint_fn!(read_u32, u32);
     read_uint_fn!(read_u64, u64);
 
@@ -79,19 +82,27 @@ impl Iterator for ReadDir {
                     break;
                 }
 
-                let entry_kind = match self.read_u8() {
+                let kind = self.read_u8();
+                let entry_kind = match kind & !NODE_FLAG {
                     0 => FileType::Dir,
                     1 => FileType::File,
                     2 => FileType::Device,
+                    3 => FileType::Link,
                     _ => {
                         self.block_offset = offset; // Rewind the cursor
                         break;
                     }
                 };
 
-                let entry_addr = self.read_u32();
+                let dev = device(self.block_addr());
+                let entry_addr = global_addr(dev, self.read_u32());
                 let entry_size = self.read_u32();
-                let entry_time = self.read_u64();
+                let entry_ctime = self.read_u64();
+                let entry_mtime = self.read_u64();
+                let entry_atime = self.read_u64();
+                let entry_mode = self.read_u16();
+                let entry_uid = self.read_u16();
+                let entry_gid = self.read_u16();
 
                 let n = self.read_u8() as usize;
                 if n == 0 || n >= self.block.len() - self.block_offset {
@@ -108,14 +119,22 @@ impl Iterator for ReadDir {
                 }
 
                 let dir = self.dir.clone();
-                return Some(DirEntry::new(
+                let entry = DirEntry::new(
                     dir,
                     entry_kind,
                     entry_addr,
                     entry_size,
-                    entry_time,
+                    entry_mode,
                     &entry_name,
-                ));
+                ).with_times(
+                    entry_ctime,
+                    entry_mtime,
+                    entry_atime,
+                ).with_owner(entry_uid, entry_gid);
+                if kind & NODE_FLAG != 0 {
+                    return Some(entry.with_node(&Node::read(entry_addr)));
+                }
+                return Some(entry);
             }
 
             match self.block.next() {
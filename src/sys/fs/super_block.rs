@@ -1,60 +1,89 @@
 use super::block::Block;
-use super::block_device::BlockDeviceIO;
+use super::block_device::{global_addr, BlockDeviceIO, MAX_BLOCKS, MOUNTS};
 use crate::sys;
 use crate::KERNEL_SIZE;
 use core::convert::TryInto;
 
-const SUPERBLOCK_ADDR: u32 = (KERNEL_SIZE / super::BLOCK_SIZE) as u32;
+pub const SUPERBLOCK_ADDR: u32 = (KERNEL_SIZE / super::BLOCK_SIZE) as u32;
 const SIGNATURE: &[u8; 8] = b"MOROS FS";
 
 #[derive(Debug)]
 pub struct SuperBlock {
+    dev: usize,
     signature: &'static [u8; 8],
     version: u8,
     block_size: u32,
     pub block_count: u32,
     pub alloc_count: u32,
+
+    // Local address of the header of the journal, or 0 without a journal
+    pub journal: u32,
 }
 
 impl SuperBlock {
-    pub fn check_ata(bus: u8, dsk: u8) -> bool {
+    // Version of the filesystem found on a hard drive
+    pub fn version_ata(bus: u8, dsk: u8) -> Option<u8> {
         let mut buf = [0u8; super::BLOCK_SIZE];
         if sys::ata::read(bus, dsk, SUPERBLOCK_ADDR, &mut buf).is_err() {
-            return false;
+            return None;
+        }
+        if &buf[0..8] != SIGNATURE {
+            return None;
         }
-        &buf[0..8] == SIGNATURE
+        Some(buf[8])
     }
 
-    pub fn new() -> Option<Self> {
-        if let Some(ref dev) = *super::block_device::BLOCK_DEVICE.lock() {
-            Some(Self {
-                signature: SIGNATURE,
-                version: super::VERSION,
-                block_size: dev.block_size() as u32,
-                block_count: dev.block_count() as u32,
-                alloc_count: 0,
-            })
-        } else {
-            None
+    pub fn new(dev: usize) -> Option<Self> {
+        let mut mounts = MOUNTS.lock();
+        let dev_io = mounts.get_mut(dev)?.as_mut()?.block_device()?;
+        let block_size = dev_io.block_size() as u32;
+        let block_count = (dev_io.block_count() as u32).min(MAX_BLOCKS);
+        drop(mounts);
+
+        // The device must be large enough to have at least one bitmap block
+        let bs = super::BITMAP_SIZE as u32;
+        if block_count <= SUPERBLOCK_ADDR + 2 + bs {
+            return None;
         }
+
+        Some(Self {
+            dev,
+            signature: SIGNATURE,
+            version: super::VERSION,
+            block_size,
+            block_count,
+            alloc_count: 0,
+            journal: 0,
+        })
     }
 
-    // NOTE: FS must be mounted
-    pub fn read() -> Self {
-        let block = Block::read(SUPERBLOCK_ADDR);
+    // Check the signature and the version of the filesystem of a mounted
+    // device, the layout of the dir entries having changed between versions
+    pub fn check(dev: usize) -> bool {
+        let block = Block::read(global_addr(dev, SUPERBLOCK_ADDR));
+        let data = block.data();
+        &data[0..8] == SIGNATURE && data[8] == super::VERSION
+    }
+
+    // NOTE: FS must be mounted and checked
+    pub fn read(dev: usize) -> Self {
+        let block = Block::read(global_addr(dev, SUPERBLOCK_ADDR));
         let data = block.data();
         debug_assert_eq!(&data[0..8], SIGNATURE);
+        debug_assert_eq!(data[8], super::VERSION);
         Self {
+            dev,
             signature: SIGNATURE,
             version: data[8],
             block_size: 2 << (8 + data[9] as u32),
             block_count: u32::from_be_bytes(data[10..14].try_into().unwrap()),
             alloc_count: u32::from_be_bytes(data[14..18].try_into().unwrap()),
+            journal: u32::from_be_bytes(data[18..22].try_into().unwrap()),
         }
     }
 
     pub fn write(&self) {
-        let mut block = Block::new(SUPERBLOCK_ADDR);
+        let mut block = Block::new(global_addr(self.dev, SUPERBLOCK_ADDR));
         let data = block.data_mut();
 
         data[0..8].clone_from_slice(self.signature);
@@ -66,6 +95,7 @@ impl SuperBlock {
         data[9] = (size.trailing_zeros() as u8) - 9; // 2 ^ (9 + n)
         data[10..14].clone_from_slice(&self.block_count.to_be_bytes());
         data[14..18].clone_from_slice(&self.alloc_count.to_be_bytes());
+        data[18..22].clone_from_slice(&self.journal.to_be_bytes());
 
         block.write();
     }
@@ -78,27 +108,43 @@ impl SuperBlock {
         self.block_count
     }
 
+    // The areas of a filesystem are given with the device in their address
     pub fn bitmap_area(&self) -> u32 {
-        SUPERBLOCK_ADDR + 2
+        global_addr(self.dev, SUPERBLOCK_ADDR + 2)
     }
 
     pub fn data_area(&self) -> u32 {
         let bs = super::BITMAP_SIZE as u32;
         let total = self.block_count;
-        let offset = self.bitmap_area();
+        let offset = SUPERBLOCK_ADDR + 2;
         let rest = (total - offset) * bs / (bs + 1);
         self.bitmap_area() + rest / bs
     }
 }
 
-pub fn inc_alloc_count() {
-    let mut sb = SuperBlock::read();
+pub fn inc_alloc_count(dev: usize) {
+    let mut sb = SuperBlock::read(dev);
     sb.alloc_count += 1;
     sb.write();
 }
 
-pub fn dec_alloc_count() {
-    let mut sb = SuperBlock::read();
+pub fn dec_alloc_count(dev: usize) {
+    let mut sb = SuperBlock::read(dev);
     sb.alloc_count -= 1;
     sb.write();
 }
+
+#[test_case]
+fn test_check_version() {
+    super::mount_mem();
+    super::format_mem();
+    assert!(SuperBlock::check(0));
+
+    // A filesystem formatted by another version is refused
+    let mut block = Block::read(global_addr(0, SUPERBLOCK_ADDR));
+    block.data_mut()[8] = 1;
+    block.write();
+    assert!(!SuperBlock::check(0));
+
+    super::dismount();
+}
@@ -53,6 +53,7 @@ pub mod mem;
 pub mod net;
 pub mod pci;
 pub mod pic;
+pub mod pipe;
 pub mod process;
 pub mod rng;
 pub mod serial;
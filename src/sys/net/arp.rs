@@ -0,0 +1,202 @@
+use crate::sys;
+
+use alloc::collections::btree_map::BTreeMap;
+use alloc::collections::vec_deque::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+use lazy_static::lazy_static;
+use smoltcp::wire::{
+    ArpOperation, ArpPacket, ArpRepr, EthernetAddress, EthernetFrame,
+    EthernetProtocol, EthernetRepr, Ipv4Address,
+};
+use spin::Mutex;
+
+// The neighbor cache of smoltcp is private, so the kernel keeps its own
+// table of the addresses found in the ARP packets received by the device,
+// along with static entries that are resolved by replying locally to the
+// requests sent by smoltcp.
+
+const MAX_NEIGHBORS: usize = 32;
+
+type Table = BTreeMap<Ipv4Address, Neighbor>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Neighbor {
+    pub mac: EthernetAddress,
+    pub updated_at: f64,
+    pub is_static: bool,
+}
+
+lazy_static! {
+    static ref NEIGHBORS: Mutex<Table> = Mutex::new(BTreeMap::new());
+    static ref REPLIES: Mutex<VecDeque<Vec<u8>>> = Mutex::new(VecDeque::new());
+}
+
+pub fn neighbors() -> Vec<(Ipv4Address, Neighbor)> {
+    NEIGHBORS.lock().iter().map(|(ip, neighbor)| (*ip, *neighbor)).collect()
+}
+
+pub fn add(ip: Ipv4Address, mac: EthernetAddress) {
+    let updated_at = sys::clock::uptime();
+    let neighbor = Neighbor { mac, updated_at, is_static: true };
+    NEIGHBORS.lock().insert(ip, neighbor);
+}
+
+pub fn remove(ip: Ipv4Address) -> bool {
+    NEIGHBORS.lock().remove(&ip).is_some()
+}
+
+// Remove the entries learned from the network
+pub fn flush() {
+    NEIGHBORS.lock().retain(|_, neighbor| neighbor.is_static);
+}
+
+// Learn the address of the sender of an ARP packet received by the device
+pub fn receive(frame: &[u8]) {
+    if let Some(ArpRepr::EthernetIpv4 {
+        source_hardware_addr, source_protocol_addr, ..
+    }) = parse(frame) {
+        let now = sys::clock::uptime();
+        let mut table = NEIGHBORS.lock();
+        learn(&mut table, source_protocol_addr, source_hardware_addr, now);
+    }
+}
+
+// Queue a reply to an ARP request transmitted by a device for a static
+// entry, to be received by that device instead of its next packet
+pub fn transmit(frame: &[u8]) {
+    if let Some(reply) = static_reply(&NEIGHBORS.lock(), frame) {
+        REPLIES.lock().push_back(reply);
+    }
+}
+
+pub fn pending_reply(mac: EthernetAddress) -> Option<Vec<u8>> {
+    let mut replies = REPLIES.lock();
+    let i = replies.iter().position(|reply| {
+        EthernetFrame::new_unchecked(reply).dst_addr() == mac
+    })?;
+    replies.remove(i)
+}
+
+fn learn(table: &mut Table, ip: Ipv4Address, mac: EthernetAddress, now: f64) {
+    if !ip.is_unicast() || !mac.is_unicast() {
+        return;
+    }
+    if let Some(neighbor) = table.get(&ip) {
+        if neighbor.is_static {
+            return;
+        }
+    } else if table.len() >= MAX_NEIGHBORS {
+        // Replace the entry that was updated the longest time ago
+        let oldest = table.iter().filter(|(_, n)| !n.is_static).min_by(|a, b| {
+            a.1.updated_at.partial_cmp(&b.1.updated_at).unwrap()
+        }).map(|(ip, _)| *ip);
+        match oldest {
+            Some(oldest) => table.remove(&oldest),
+            None => return,
+        };
+    }
+    let neighbor = Neighbor { mac, updated_at: now, is_static: false };
+    table.insert(ip, neighbor);
+}
+
+fn static_reply(table: &Table, frame: &[u8]) -> Option<Vec<u8>> {
+    if let ArpRepr::EthernetIpv4 {
+        operation: ArpOperation::Request,
+        source_hardware_addr,
+        source_protocol_addr,
+        target_protocol_addr,
+        ..
+    } = parse(frame)? {
+        let neighbor = table.get(&target_protocol_addr)?;
+        if !neighbor.is_static {
+            return None;
+        }
+        let arp_repr = ArpRepr::EthernetIpv4 {
+            operation: ArpOperation::Reply,
+            source_hardware_addr: neighbor.mac,
+            source_protocol_addr: target_protocol_addr,
+            target_hardware_addr: source_hardware_addr,
+            target_protocol_addr: source_protocol_addr,
+        };
+        let eth_repr = EthernetRepr {
+            src_addr: neighbor.mac,
+            dst_addr: source_hardware_addr,
+            ethertype: EthernetProtocol::Arp,
+        };
+        let len = eth_repr.buffer_len() + arp_repr.buffer_len();
+        let mut buf = vec![0; len];
+        let mut frame = EthernetFrame::new_unchecked(&mut buf);
+        eth_repr.emit(&mut frame);
+        arp_repr.emit(&mut ArpPacket::new_unchecked(frame.payload_mut()));
+        return Some(buf);
+    }
+    None
+}
+
+fn parse(frame: &[u8]) -> Option<ArpRepr> {
+    let frame = EthernetFrame::new_checked(frame).ok()?;
+    if frame.ethertype() != EthernetProtocol::Arp {
+        return None;
+    }
+    let packet = ArpPacket::new_checked(frame.payload()).ok()?;
+    ArpRepr::parse(&packet).ok()
+}
+
+#[test_case]
+fn test_static_reply() {
+    let our_mac = EthernetAddress([0x52, 0x54, 0, 0x12, 0x34, 0x56]);
+    let our_ip = Ipv4Address::new(10, 0, 2, 15);
+    let gw_mac = EthernetAddress([0x52, 0x55, 0x0A, 0, 2, 2]);
+    let gw_ip = Ipv4Address::new(10, 0, 2, 2);
+
+    let request = ArpRepr::EthernetIpv4 {
+        operation: ArpOperation::Request,
+        source_hardware_addr: our_mac,
+        source_protocol_addr: our_ip,
+        target_hardware_addr: EthernetAddress([0; 6]),
+        target_protocol_addr: gw_ip,
+    };
+    let eth_repr = EthernetRepr {
+        src_addr: our_mac,
+        dst_addr: EthernetAddress::BROADCAST,
+        ethertype: EthernetProtocol::Arp,
+    };
+    let mut buf = vec![0; eth_repr.buffer_len() + request.buffer_len()];
+    let mut frame = EthernetFrame::new_unchecked(&mut buf);
+    eth_repr.emit(&mut frame);
+    request.emit(&mut ArpPacket::new_unchecked(frame.payload_mut()));
+
+    let mut table = Table::new();
+    assert_eq!(static_reply(&table, &buf), None);
+    learn(&mut table, gw_ip, gw_mac, 1.0);
+    assert_eq!(static_reply(&table, &buf), None);
+
+    let neighbor = Neighbor { mac: gw_mac, updated_at: 1.0, is_static: true };
+    table.insert(gw_ip, neighbor);
+    let reply = static_reply(&table, &buf).unwrap();
+    assert_eq!(EthernetFrame::new_checked(&reply).unwrap().dst_addr(), our_mac);
+    assert_eq!(parse(&reply), Some(ArpRepr::EthernetIpv4 {
+        operation: ArpOperation::Reply,
+        source_hardware_addr: gw_mac,
+        source_protocol_addr: gw_ip,
+        target_hardware_addr: our_mac,
+        target_protocol_addr: our_ip,
+    }));
+}
+
+#[test_case]
+fn test_learn() {
+    let mac = EthernetAddress([0x52, 0x55, 0x0A, 0, 2, 2]);
+    let mut table = Table::new();
+    learn(&mut table, Ipv4Address::BROADCAST, mac, 1.0);
+    learn(&mut table, Ipv4Address::new(10, 0, 2, 2), mac, 1.0);
+    assert_eq!(table.len(), 1);
+
+    for i in 0..(MAX_NEIGHBORS as u8) {
+        learn(&mut table, Ipv4Address::new(10, 0, 3, i), mac, 2.0 + i as f64);
+    }
+    assert_eq!(table.len(), MAX_NEIGHBORS);
+    assert!(!table.contains_key(&Ipv4Address::new(10, 0, 2, 2)));
+    assert!(table.contains_key(&Ipv4Address::new(10, 0, 3, 31)));
+}
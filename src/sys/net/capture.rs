@@ -0,0 +1,62 @@
+use super::firewall::Direction;
+use crate::sys;
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+use spin::Mutex;
+
+// The packets captured but not read yet are dropped beyond this limit to
+// keep a slow reader from using all the memory
+const MAX_PACKETS: usize = 256;
+
+#[derive(Debug, Clone)]
+pub struct Packet {
+    pub time: f64, // Seconds since Unix Epoch
+    pub direction: Direction,
+    pub data: Vec<u8>,
+}
+
+// The packets received and transmitted by a device are queued while a
+// capture is running, the received ones before going through the firewall
+// and the transmitted ones after.
+#[derive(Default)]
+pub struct Capture {
+    packets: Mutex<Option<VecDeque<Packet>>>,
+    dropped: AtomicU64,
+}
+
+impl Capture {
+    pub fn start(&self) {
+        *self.packets.lock() = Some(VecDeque::new());
+        self.dropped.store(0, Ordering::Relaxed);
+    }
+
+    pub fn stop(&self) {
+        *self.packets.lock() = None;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.packets.lock().is_some()
+    }
+
+    pub fn push(&self, direction: Direction, data: &[u8]) {
+        if let Some(packets) = self.packets.lock().as_mut() {
+            if packets.len() < MAX_PACKETS {
+                let time = sys::clock::realtime();
+                let data = data.to_vec();
+                packets.push_back(Packet { time, direction, data });
+            } else {
+                self.dropped.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    }
+
+    pub fn pop(&self) -> Option<Packet> {
+        self.packets.lock().as_mut().and_then(|packets| packets.pop_front())
+    }
+
+    pub fn dropped_count(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
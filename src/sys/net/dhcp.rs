@@ -0,0 +1,404 @@
+use crate::sys;
+use crate::sys::net::{arp, mdns, EthernetDeviceIO, NetworkInterface, Route};
+
+use alloc::vec;
+use alloc::vec::Vec;
+use lazy_static::lazy_static;
+use smoltcp::wire::{
+    DhcpMessageType, DhcpPacket, DhcpRepr, EthernetAddress, EthernetFrame,
+    EthernetProtocol, IpAddress, IpCidr, IpProtocol, Ipv4Address, Ipv4Cidr,
+    Ipv4Packet, UdpPacket,
+};
+use spin::Mutex;
+
+// The lease obtained by the `dhcp` command for the first interface is kept
+// by the kernel that renews it from the network poll path: a request is sent
+// to the server that granted it once the renewal time (T1) is reached, then
+// broadcasted to any server once the rebinding time (T2) is reached, and the
+// address is removed from the interface when the lease expires (RFC 2131).
+// The DNS servers are only configured by the command.
+
+pub const CLIENT_PORT: u16 = 68;
+pub const SERVER_PORT: u16 = 67;
+pub const PARAMETER_REQUEST_LIST: [u8; 3] = [1, 3, 6]; // Mask, router, DNS
+
+const DEFAULT_LEASE_DURATION: u32 = 120;
+const MIN_RETRY_DELAY: f64 = 60.0;
+const REQUEST_TIMEOUT: f64 = 5.0;
+
+lazy_static! {
+    static ref LEASE: Mutex<Option<Lease>> = Mutex::new(None);
+    static ref REQUEST: Mutex<Option<Request>> = Mutex::new(None);
+    static ref REPLY: Mutex<Option<Reply>> = Mutex::new(None);
+}
+
+// The timers of a lease are kept in seconds of uptime to be unaffected by
+// changes to the real time clock
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lease {
+    pub address: Ipv4Cidr,
+    pub server: Ipv4Address,
+    pub renew_at: f64,
+    pub rebind_at: f64,
+    pub expires_at: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LeaseState {
+    Bound,
+    Renewing,
+    Rebinding,
+    Expired,
+}
+
+impl Lease {
+    // Set the renewal (T1) and rebinding (T2) times to 50% and 87.5% of the
+    // lease duration unless the server gave other values (RFC 2131)
+    pub fn new(
+        address: Ipv4Cidr,
+        server: Ipv4Address,
+        ack: &DhcpRepr,
+        now: f64
+    ) -> Self {
+        let duration = ack.lease_duration.unwrap_or(DEFAULT_LEASE_DURATION);
+        let duration = duration as f64;
+        let rebind = ack.rebind_duration.map_or(duration * 0.875, |t| t as f64);
+        let rebind = rebind.min(duration);
+        let renew = ack.renew_duration.map_or(duration * 0.5, |t| t as f64);
+        let renew = renew.min(rebind);
+        Self {
+            address,
+            server,
+            renew_at: now + renew,
+            rebind_at: now + rebind,
+            expires_at: now + duration,
+        }
+    }
+
+    pub fn state(&self, now: f64) -> LeaseState {
+        if now >= self.expires_at {
+            LeaseState::Expired
+        } else if now >= self.rebind_at {
+            LeaseState::Rebinding
+        } else if now >= self.renew_at {
+            LeaseState::Renewing
+        } else {
+            LeaseState::Bound
+        }
+    }
+
+    // Wait one-half of the remaining time until T2 while renewing, or until
+    // the end of the lease while rebinding, down to a minimum of 60 seconds
+    // before trying again (RFC 2131)
+    pub fn retry(&mut self, now: f64) {
+        match self.state(now) {
+            LeaseState::Renewing => {
+                let delay = (self.rebind_at - now) / 2.0;
+                let delay = delay.max(MIN_RETRY_DELAY);
+                self.renew_at = (now + delay).min(self.rebind_at);
+            }
+            LeaseState::Rebinding => {
+                let delay = (self.expires_at - now) / 2.0;
+                let delay = delay.max(MIN_RETRY_DELAY);
+                self.rebind_at = (now + delay).min(self.expires_at);
+            }
+            _ => {}
+        }
+    }
+}
+
+pub struct Config {
+    pub address: Ipv4Cidr,
+    pub router: Option<Ipv4Address>,
+    pub dns_servers: Vec<Ipv4Address>,
+}
+
+pub enum Reply {
+    Ack(Config, Lease),
+    Nak,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Request {
+    xid: u32,
+    sent_at: f64,
+}
+
+pub fn lease() -> Option<Lease> {
+    *LEASE.lock()
+}
+
+// Replace the lease renewed by the kernel, cancelling its pending request
+pub fn set_lease(lease: Option<Lease>) {
+    *REQUEST.lock() = None;
+    *REPLY.lock() = None;
+    *LEASE.lock() = lease;
+}
+
+// Check if the interfaces have to be polled for the lease
+pub fn is_due() -> bool {
+    match lease() {
+        Some(lease) => {
+            lease.state(sys::clock::uptime()) != LeaseState::Bound
+        }
+        None => false,
+    }
+}
+
+pub fn message(message_type: DhcpMessageType, mac: EthernetAddress)
+    -> DhcpRepr<'static>
+{
+    DhcpRepr {
+        message_type,
+        transaction_id: sys::rng::get_u64() as u32,
+        secs: 0,
+        client_hardware_address: mac,
+        client_ip: Ipv4Address::UNSPECIFIED,
+        your_ip: Ipv4Address::UNSPECIFIED,
+        server_ip: Ipv4Address::UNSPECIFIED,
+        router: None,
+        subnet_mask: None,
+        relay_agent_ip: Ipv4Address::UNSPECIFIED,
+        broadcast: false,
+        requested_ip: None,
+        client_identifier: Some(mac),
+        server_identifier: None,
+        parameter_request_list: None,
+        max_size: None,
+        lease_duration: None,
+        renew_duration: None,
+        rebind_duration: None,
+        dns_servers: None,
+        additional_options: &[],
+    }
+}
+
+// Parse the reply to a message sent for the given lease, whose prefix and
+// server are kept when the reply omits them
+pub fn parse_reply(packet: &DhcpPacket<&[u8]>, xid: u32, lease: &Lease)
+    -> Option<Reply>
+{
+    let repr = DhcpRepr::parse(packet).ok()?;
+    if repr.transaction_id != xid {
+        return None;
+    }
+    match repr.message_type {
+        DhcpMessageType::Ack => {
+            let prefix_len = match repr.subnet_mask {
+                Some(mask) => u32::from_be_bytes(mask.0).leading_ones() as u8,
+                None => lease.address.prefix_len(),
+            };
+            let address = Ipv4Cidr::new(repr.your_ip, prefix_len);
+            let server = repr.server_identifier.unwrap_or(lease.server);
+            let now = sys::clock::uptime();
+            let lease = Lease::new(address, server, &repr, now);
+            let dns_servers = repr.dns_servers.as_ref().map_or(Vec::new(), |s|
+                s.to_vec()
+            );
+            let router = repr.router;
+            Some(Reply::Ack(Config { address, router, dns_servers }, lease))
+        }
+        DhcpMessageType::Nak => Some(Reply::Nak),
+        _ => None,
+    }
+}
+
+// Keep the reply to the pending request of the kernel received by a device,
+// returning true when the frame was consumed
+pub fn receive(frame: &[u8]) -> bool {
+    let xid = match *REQUEST.lock() {
+        Some(request) => request.xid,
+        None => return false,
+    };
+    let lease = match lease() {
+        Some(lease) => lease,
+        None => return false,
+    };
+    let reply = parse_frame(frame).and_then(|payload| {
+        let packet = DhcpPacket::new_checked(payload.as_slice()).ok()?;
+        parse_reply(&packet, xid, &lease)
+    });
+    match reply {
+        Some(reply) => {
+            *REPLY.lock() = Some(reply);
+            true
+        }
+        None => false,
+    }
+}
+
+pub fn poll(interfaces: &mut [NetworkInterface]) {
+    let mut lease = match lease() {
+        Some(lease) => lease,
+        None => return,
+    };
+    let net = match interfaces.first_mut() {
+        Some(net) => net,
+        None => return,
+    };
+
+    // The lease was replaced by a static configuration
+    if ipv4_cidr(net) != Some(lease.address) {
+        set_lease(None);
+        return;
+    }
+
+    let reply = REPLY.lock().take();
+    match reply {
+        Some(Reply::Ack(config, lease)) => {
+            *REQUEST.lock() = None;
+            configure(net, &config);
+            *LEASE.lock() = Some(lease);
+            return;
+        }
+        Some(Reply::Nak) => {
+            log!("DHCP lease of {} refused", lease.address);
+            deconfigure(net);
+            set_lease(None);
+            return;
+        }
+        None => {}
+    }
+
+    let now = sys::clock::uptime();
+    match lease.state(now) {
+        LeaseState::Bound => {}
+        LeaseState::Expired => {
+            log!("DHCP lease of {} expired", lease.address);
+            deconfigure(net);
+            set_lease(None);
+        }
+        LeaseState::Renewing | LeaseState::Rebinding => {
+            let request = *REQUEST.lock();
+            match request {
+                Some(request) if now - request.sent_at < REQUEST_TIMEOUT => {}
+                Some(_) => {
+                    *REQUEST.lock() = None;
+                    lease.retry(now);
+                    *LEASE.lock() = Some(lease);
+                }
+                None => {
+                    if let Some(xid) = request_lease(net, &lease, now) {
+                        let sent_at = now;
+                        *REQUEST.lock() = Some(Request { xid, sent_at });
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Send a request to extend the lease, to the server that granted it while
+// renewing or to any server while rebinding
+fn request_lease(net: &mut NetworkInterface, lease: &Lease, now: f64)
+    -> Option<u32>
+{
+    let mac = net.device.config().mac()?;
+    let mut repr = message(DhcpMessageType::Request, mac);
+    repr.client_ip = lease.address.address();
+    repr.parameter_request_list = Some(&PARAMETER_REQUEST_LIST);
+    let mut payload = vec![0; repr.buffer_len()];
+    repr.emit(&mut DhcpPacket::new_unchecked(&mut payload)).ok()?;
+
+    let (dst_mac, dst_addr) = match lease.state(now) {
+        LeaseState::Rebinding => {
+            (EthernetAddress::BROADCAST, Ipv4Address::BROADCAST)
+        }
+        _ => (neighbor_mac(net, lease), lease.server),
+    };
+    let src_addr = lease.address.address();
+    let frame = mdns::udp_frame(
+        mac, src_addr, CLIENT_PORT, dst_mac, dst_addr, SERVER_PORT, &payload
+    );
+    net.device.transmit_frame(&frame);
+    Some(repr.transaction_id)
+}
+
+// Hardware address of the server, or of the gateway used to reach it, found
+// in the ARP table. The frame is broadcasted when the address is unknown.
+fn neighbor_mac(net: &mut NetworkInterface, lease: &Lease)
+    -> EthernetAddress
+{
+    let routes = super::routes(core::slice::from_mut(net));
+    let addr = match super::find_route(&routes, lease.server.into()) {
+        Some(Route { gateway: Some(IpAddress::Ipv4(addr)), .. }) => addr,
+        _ => lease.server,
+    };
+    let neighbors = arp::neighbors();
+    match neighbors.iter().find(|(ip, _)| *ip == addr) {
+        Some((_, neighbor)) => neighbor.mac,
+        None => EthernetAddress::BROADCAST,
+    }
+}
+
+fn configure(net: &mut NetworkInterface, config: &Config) {
+    net.iface.update_ip_addrs(|addrs| {
+        addrs.clear();
+        addrs.push(IpCidr::Ipv4(config.address)).unwrap();
+    });
+    net.iface.routes_mut().remove_default_ipv4_route();
+    if let Some(router) = config.router {
+        net.iface.routes_mut().add_default_ipv4_route(router).ok();
+    }
+}
+
+fn deconfigure(net: &mut NetworkInterface) {
+    net.iface.update_ip_addrs(|addrs| {
+        addrs.clear();
+        let addr = Ipv4Cidr::new(Ipv4Address::UNSPECIFIED, 0);
+        addrs.push(IpCidr::Ipv4(addr)).unwrap();
+    });
+    net.iface.routes_mut().remove_default_ipv4_route();
+}
+
+fn ipv4_cidr(net: &NetworkInterface) -> Option<Ipv4Cidr> {
+    net.iface.ip_addrs().first().map(|cidr| match cidr {
+        IpCidr::Ipv4(cidr) => *cidr,
+    })
+}
+
+// Payload of a frame sent to the client port
+fn parse_frame(frame: &[u8]) -> Option<Vec<u8>> {
+    let frame = EthernetFrame::new_checked(frame).ok()?;
+    if frame.ethertype() != EthernetProtocol::Ipv4 {
+        return None;
+    }
+    let ip = Ipv4Packet::new_checked(frame.payload()).ok()?;
+    if ip.next_header() != IpProtocol::Udp {
+        return None;
+    }
+    let udp = UdpPacket::new_checked(ip.payload()).ok()?;
+    if udp.dst_port() != CLIENT_PORT || udp.src_port() != SERVER_PORT {
+        return None;
+    }
+    Some(udp.payload().to_vec())
+}
+
+#[test_case]
+fn test_lease() {
+    let address = Ipv4Cidr::new(Ipv4Address::new(10, 0, 2, 15), 24);
+    let server = Ipv4Address::new(10, 0, 2, 2);
+    let mut ack = message(DhcpMessageType::Ack, EthernetAddress([0; 6]));
+    ack.lease_duration = Some(1000);
+    let mut lease = Lease::new(address, server, &ack, 100.0);
+    assert_eq!(lease.renew_at, 600.0);
+    assert_eq!(lease.rebind_at, 975.0);
+    assert_eq!(lease.expires_at, 1100.0);
+    assert_eq!(lease.state(599.0), LeaseState::Bound);
+    assert_eq!(lease.state(600.0), LeaseState::Renewing);
+    assert_eq!(lease.state(975.0), LeaseState::Rebinding);
+    assert_eq!(lease.state(1100.0), LeaseState::Expired);
+
+    lease.retry(700.0);
+    assert_eq!(lease.renew_at, 837.5);
+    lease.retry(950.0);
+    assert_eq!(lease.renew_at, 975.0);
+    lease.retry(1000.0);
+    assert_eq!(lease.rebind_at, 1060.0);
+
+    ack.renew_duration = Some(2000);
+    ack.rebind_duration = Some(1500);
+    let lease = Lease::new(address, server, &ack, 0.0);
+    assert_eq!(lease.rebind_at, 1000.0);
+    assert_eq!(lease.renew_at, 1000.0);
+}
@@ -0,0 +1,380 @@
+use crate::sys;
+
+use alloc::collections::btree_map::BTreeMap;
+use alloc::vec::Vec;
+use core::fmt;
+use lazy_static::lazy_static;
+use smoltcp::wire::{
+    EthernetFrame, EthernetProtocol, Icmpv4Message, Icmpv4Packet,
+    IpProtocol, Ipv4Address, Ipv4Cidr, Ipv4Packet, TcpPacket, UdpPacket,
+};
+use spin::Mutex;
+
+// The rules are evaluated in order on the IPv4 packets received and
+// transmitted by the devices, the first matching rule deciding the fate of
+// the packet, or the policy of its direction when none match. The packets
+// of the flows already accepted in one direction are accepted in the other
+// one to let the replies through, until the rules are changed.
+
+const MAX_FLOWS: usize = 64;
+const FLOW_TIMEOUT: f64 = 300.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Allow,
+    Deny,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    In,
+    Out,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Protocol {
+    Icmp,
+    Tcp,
+    Udp,
+}
+
+// The port of a rule is the local port of the packets received and the
+// remote port of the packets transmitted, and its address is the remote
+// address of both.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rule {
+    pub action: Action,
+    pub direction: Direction,
+    pub protocol: Option<Protocol>,
+    pub port: Option<u16>,
+    pub addr: Option<Ipv4Cidr>,
+}
+
+// A flow is identified from our side of the connection whatever the
+// direction of its packets, with the identifier of ICMP echo messages
+// used in place of both ports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Flow {
+    protocol: Protocol,
+    remote_addr: Ipv4Address,
+    remote_port: u16,
+    local_port: u16,
+}
+
+struct Firewall {
+    rules: Vec<Rule>,
+    input: Action,
+    output: Action,
+}
+
+lazy_static! {
+    static ref FIREWALL: Mutex<Firewall> = Mutex::new(Firewall {
+        rules: Vec::new(),
+        input: Action::Allow,
+        output: Action::Allow,
+    });
+    static ref FLOWS: Mutex<BTreeMap<Flow, f64>> = {
+        Mutex::new(BTreeMap::new())
+    };
+}
+
+pub fn rules() -> Vec<Rule> {
+    FIREWALL.lock().rules.clone()
+}
+
+pub fn add(rule: Rule) {
+    FIREWALL.lock().rules.push(rule);
+    FLOWS.lock().clear();
+}
+
+pub fn remove(i: usize) -> Option<Rule> {
+    let mut firewall = FIREWALL.lock();
+    if i < firewall.rules.len() {
+        let rule = firewall.rules.remove(i);
+        FLOWS.lock().clear();
+        Some(rule)
+    } else {
+        None
+    }
+}
+
+pub fn flush() {
+    FIREWALL.lock().rules.clear();
+    FLOWS.lock().clear();
+}
+
+pub fn policy(direction: Direction) -> Action {
+    let firewall = FIREWALL.lock();
+    match direction {
+        Direction::In => firewall.input,
+        Direction::Out => firewall.output,
+    }
+}
+
+pub fn set_policy(direction: Direction, action: Action) {
+    let mut firewall = FIREWALL.lock();
+    match direction {
+        Direction::In => firewall.input = action,
+        Direction::Out => firewall.output = action,
+    }
+    FLOWS.lock().clear();
+}
+
+// Return true if a frame can be received or transmitted by a device
+pub fn accept(direction: Direction, frame: &[u8]) -> bool {
+    let flow = match parse(direction, frame) {
+        Some(flow) => flow,
+        None => return true, // Not a packet that can be filtered
+    };
+    let now = sys::clock::uptime();
+    let mut flows = FLOWS.lock();
+    let action = if is_active(&flows, &flow, now) {
+        Action::Allow
+    } else {
+        let firewall = FIREWALL.lock();
+        let policy = match direction {
+            Direction::In => firewall.input,
+            Direction::Out => firewall.output,
+        };
+        filter(&firewall.rules, policy, direction, &flow)
+    };
+    if action == Action::Allow {
+        track(&mut flows, flow, now);
+        true
+    } else {
+        false
+    }
+}
+
+fn filter(
+    rules: &[Rule],
+    policy: Action,
+    direction: Direction,
+    flow: &Flow
+) -> Action {
+    let port = match direction {
+        Direction::In => flow.local_port,
+        Direction::Out => flow.remote_port,
+    };
+    for rule in rules {
+        if rule.direction != direction {
+            continue;
+        }
+        if rule.protocol.map_or(false, |p| p != flow.protocol) {
+            continue;
+        }
+        if rule.port.map_or(false, |p| p != port) {
+            continue;
+        }
+        if rule.addr.map_or(false, |a| !a.contains_addr(&flow.remote_addr)) {
+            continue;
+        }
+        return rule.action;
+    }
+    policy
+}
+
+fn is_active(flows: &BTreeMap<Flow, f64>, flow: &Flow, now: f64) -> bool {
+    flows.get(flow).map_or(false, |t| now - t < FLOW_TIMEOUT)
+}
+
+fn track(flows: &mut BTreeMap<Flow, f64>, flow: Flow, now: f64) {
+    if !flows.contains_key(&flow) && flows.len() >= MAX_FLOWS {
+        // Replace the flow that was seen the longest time ago
+        let oldest = flows.iter().min_by(|a, b| {
+            a.1.partial_cmp(b.1).unwrap()
+        }).map(|(flow, _)| *flow);
+        if let Some(oldest) = oldest {
+            flows.remove(&oldest);
+        }
+    }
+    flows.insert(flow, now);
+}
+
+fn parse(direction: Direction, frame: &[u8]) -> Option<Flow> {
+    let frame = EthernetFrame::new_checked(frame).ok()?;
+    if frame.ethertype() != EthernetProtocol::Ipv4 {
+        return None;
+    }
+    let packet = Ipv4Packet::new_checked(frame.payload()).ok()?;
+    let remote_addr = match direction {
+        Direction::In => packet.src_addr(),
+        Direction::Out => packet.dst_addr(),
+    };
+
+    // The ports are only found in the first fragment of a packet
+    let payload = packet.payload();
+    let is_first = packet.frag_offset() == 0;
+    let (protocol, src_port, dst_port) = match packet.next_header() {
+        IpProtocol::Tcp => {
+            let ports = TcpPacket::new_checked(payload).ok().filter(|_| {
+                is_first
+            }).map(|p| (p.src_port(), p.dst_port()));
+            let (src, dst) = ports.unwrap_or((0, 0));
+            (Protocol::Tcp, src, dst)
+        }
+        IpProtocol::Udp => {
+            let ports = UdpPacket::new_checked(payload).ok().filter(|_| {
+                is_first
+            }).map(|p| (p.src_port(), p.dst_port()));
+            let (src, dst) = ports.unwrap_or((0, 0));
+            (Protocol::Udp, src, dst)
+        }
+        IpProtocol::Icmp => {
+            let ident = Icmpv4Packet::new_checked(payload).ok().filter(|p| {
+                is_first && matches!(
+                    p.msg_type(),
+                    Icmpv4Message::EchoRequest | Icmpv4Message::EchoReply
+                )
+            }).map(|p| p.echo_ident()).unwrap_or(0);
+            (Protocol::Icmp, ident, ident)
+        }
+        _ => return None,
+    };
+    let (remote_port, local_port) = match direction {
+        Direction::In => (src_port, dst_port),
+        Direction::Out => (dst_port, src_port),
+    };
+    Some(Flow { protocol, remote_addr, remote_port, local_port })
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Action::Allow => write!(f, "allow"),
+            Action::Deny => write!(f, "deny"),
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Direction::In => write!(f, "in"),
+            Direction::Out => write!(f, "out"),
+        }
+    }
+}
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Protocol::Icmp => write!(f, "icmp"),
+            Protocol::Tcp => write!(f, "tcp"),
+            Protocol::Udp => write!(f, "udp"),
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.action, self.direction)?;
+        match self.protocol {
+            Some(protocol) => write!(f, " {}", protocol)?,
+            None => write!(f, " any")?,
+        }
+        if let Some(port) = self.port {
+            write!(f, " {}", port)?;
+        }
+        if let Some(addr) = self.addr {
+            let keyword = match self.direction {
+                Direction::In => "from",
+                Direction::Out => "to",
+            };
+            write!(f, " {} {}", keyword, addr)?;
+        }
+        Ok(())
+    }
+}
+
+#[test_case]
+fn test_filter() {
+    let flow = Flow {
+        protocol: Protocol::Tcp,
+        remote_addr: Ipv4Address::new(10, 0, 2, 2),
+        remote_port: 49152,
+        local_port: 80,
+    };
+    let rule = |action, protocol, port, addr| {
+        Rule { action, direction: Direction::In, protocol, port, addr }
+    };
+    let lan = Some(Ipv4Cidr::new(Ipv4Address::new(10, 0, 2, 0), 24));
+    let wan = Some(Ipv4Cidr::new(Ipv4Address::new(10, 0, 3, 0), 24));
+
+    let rules = [];
+    let policy = Action::Deny;
+    assert_eq!(filter(&rules, policy, Direction::In, &flow), Action::Deny);
+
+    let rules = [rule(Action::Allow, Some(Protocol::Tcp), Some(80), lan)];
+    assert_eq!(filter(&rules, policy, Direction::In, &flow), Action::Allow);
+    assert_eq!(filter(&rules, policy, Direction::Out, &flow), Action::Deny);
+
+    let rules = [
+        rule(Action::Allow, Some(Protocol::Udp), Some(80), None),
+        rule(Action::Allow, Some(Protocol::Tcp), Some(22), None),
+        rule(Action::Allow, None, None, wan),
+    ];
+    assert_eq!(filter(&rules, policy, Direction::In, &flow), Action::Deny);
+
+    let rules = [
+        rule(Action::Deny, None, None, lan),
+        rule(Action::Allow, None, Some(80), None),
+    ];
+    let policy = Action::Allow;
+    assert_eq!(filter(&rules, policy, Direction::In, &flow), Action::Deny);
+}
+
+#[test_case]
+fn test_parse() {
+    let mut frame = [0; 42];
+    frame[12..14].copy_from_slice(&[0x08, 0x00]); // IPv4
+    frame[14] = 0x45; // Version 4, 20 bytes header
+    frame[16..18].copy_from_slice(&[0, 28]); // Total length
+    frame[23] = 17; // UDP
+    frame[26..30].copy_from_slice(&[10, 0, 2, 3]);
+    frame[30..34].copy_from_slice(&[10, 0, 2, 15]);
+    frame[34..36].copy_from_slice(&53u16.to_be_bytes());
+    frame[36..38].copy_from_slice(&49152u16.to_be_bytes());
+    frame[38..40].copy_from_slice(&[0, 8]); // UDP length
+
+    let flow = Flow {
+        protocol: Protocol::Udp,
+        remote_addr: Ipv4Address::new(10, 0, 2, 3),
+        remote_port: 53,
+        local_port: 49152,
+    };
+    assert_eq!(parse(Direction::In, &frame), Some(flow));
+
+    let flow = Flow {
+        protocol: Protocol::Udp,
+        remote_addr: Ipv4Address::new(10, 0, 2, 15),
+        remote_port: 49152,
+        local_port: 53,
+    };
+    assert_eq!(parse(Direction::Out, &frame), Some(flow));
+
+    frame[12..14].copy_from_slice(&[0x08, 0x06]); // ARP
+    assert_eq!(parse(Direction::In, &frame), None);
+}
+
+#[test_case]
+fn test_track() {
+    let flow = |i| Flow {
+        protocol: Protocol::Udp,
+        remote_addr: Ipv4Address::new(10, 0, 2, 3),
+        remote_port: 53,
+        local_port: 49152 + i,
+    };
+    let mut flows = BTreeMap::new();
+    track(&mut flows, flow(0), 1.0);
+    assert!(is_active(&flows, &flow(0), 2.0));
+    assert!(!is_active(&flows, &flow(0), 1.0 + FLOW_TIMEOUT));
+    assert!(!is_active(&flows, &flow(1), 2.0));
+
+    for i in 1..(MAX_FLOWS as u16) {
+        track(&mut flows, flow(i), 2.0 + i as f64);
+    }
+    track(&mut flows, flow(MAX_FLOWS as u16), 100.0);
+    assert_eq!(flows.len(), MAX_FLOWS);
+    assert!(!flows.contains_key(&flow(0)));
+}
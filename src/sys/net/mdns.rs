@@ -0,0 +1,325 @@
+use crate::sys;
+use crate::sys::net::{EthernetDeviceIO, NetworkInterface};
+use crate::usr::host::{read_name, skip_name};
+
+use alloc::collections::btree_map::BTreeMap;
+use alloc::collections::vec_deque::VecDeque;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use lazy_static::lazy_static;
+use smoltcp::phy::ChecksumCapabilities;
+use smoltcp::wire::{
+    EthernetAddress, EthernetFrame, EthernetProtocol, EthernetRepr, IpAddress,
+    IpCidr, IpProtocol, Ipv4Address, Ipv4Packet, Ipv4Repr, UdpPacket, UdpRepr,
+};
+use spin::Mutex;
+
+// The kernel answers the mDNS queries (RFC 6762) received by the devices for
+// the name of the host in the ".local" domain, given by `$HOSTNAME`, and
+// announces it on each interface when its address changes. The name is not
+// probed for conflicts before being announced.
+
+pub const MDNS_PORT: u16 = 5353;
+pub const MDNS_ADDR: Ipv4Address = Ipv4Address([224, 0, 0, 251]);
+
+const MDNS_MAC: EthernetAddress = EthernetAddress([1, 0, 0x5E, 0, 0, 0xFB]);
+const MAX_QUERIES: usize = 16;
+const TTL: u32 = 120;
+const LEGACY_TTL: u32 = 10; // For queries not sent from the mDNS port
+const ANNOUNCEMENTS: usize = 2;
+const ANNOUNCEMENT_INTERVAL: f64 = 1.0;
+
+const TYPE_A: u16 = 1;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+const CACHE_FLUSH: u16 = 0x8000;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Query {
+    id: u16,
+    questions: Vec<(String, u16)>, // Names with their types
+}
+
+#[derive(Debug, Clone)]
+struct Sender {
+    mac: EthernetAddress,
+    addr: Ipv4Address,
+    port: u16,
+}
+
+#[derive(Debug, Clone)]
+struct Announcement {
+    name: String,
+    addr: Ipv4Address,
+    count: usize,
+    sent_at: f64,
+}
+
+lazy_static! {
+    static ref QUERIES: Mutex<VecDeque<(EthernetAddress, Sender, Query)>> =
+        Mutex::new(VecDeque::new());
+    static ref ANNOUNCEMENTS_SENT: Mutex<BTreeMap<usize, Announcement>> =
+        Mutex::new(BTreeMap::new());
+}
+
+pub fn hostname() -> String {
+    let name = sys::process::env("HOSTNAME").unwrap_or("moros".to_string());
+    name.to_ascii_lowercase() + ".local"
+}
+
+// Queue a query received by the device with the given address, to be
+// answered after the next poll of its interface
+pub fn receive(mac: EthernetAddress, frame: &[u8]) {
+    if let Some((sender, query)) = parse_frame(frame) {
+        let mut queries = QUERIES.lock();
+        if queries.len() < MAX_QUERIES {
+            queries.push_back((mac, sender, query));
+        }
+    }
+}
+
+pub fn poll(interfaces: &mut [NetworkInterface]) {
+    let mut queries = QUERIES.lock();
+    let mut announcements = ANNOUNCEMENTS_SENT.lock();
+    if queries.is_empty() && interfaces.iter().all(|net| {
+        ipv4_addr(net).is_none()
+    }) {
+        return;
+    }
+    let name = hostname();
+    let now = sys::clock::uptime();
+    for (i, net) in interfaces.iter_mut().enumerate() {
+        let mac = match net.device.config().mac() {
+            Some(mac) => mac,
+            None => continue,
+        };
+        let addr = match ipv4_addr(net) {
+            Some(addr) => addr,
+            None => {
+                announcements.remove(&i);
+                continue;
+            }
+        };
+
+        let announcement = announcements.entry(i).or_insert(Announcement {
+            name: String::new(),
+            addr,
+            count: 0,
+            sent_at: 0.0,
+        });
+        if announcement.name != name || announcement.addr != addr {
+            announcement.name = name.clone();
+            announcement.addr = addr;
+            announcement.count = 0;
+        }
+        let is_due = now - announcement.sent_at >= ANNOUNCEMENT_INTERVAL;
+        if announcement.count < ANNOUNCEMENTS && is_due {
+            let payload = response(&name, addr, 0, None);
+            let frame = udp_frame(
+                mac, addr, MDNS_PORT, MDNS_MAC, MDNS_ADDR, MDNS_PORT, &payload
+            );
+            net.device.transmit_frame(&frame);
+            announcement.count += 1;
+            announcement.sent_at = now;
+        }
+
+        while let Some(j) = queries.iter().position(|(m, _, _)| *m == mac) {
+            let (_, sender, query) = queries.remove(j).unwrap();
+            let question = query.questions.iter().find(|(qname, qtype)| {
+                qname.eq_ignore_ascii_case(&name) &&
+                    (*qtype == TYPE_A || *qtype == TYPE_ANY)
+            });
+            if question.is_none() {
+                continue;
+            }
+            // Legacy resolvers sending queries from another port expect a
+            // unicast response with their ID and question
+            let frame = if sender.port == MDNS_PORT {
+                let payload = response(&name, addr, 0, None);
+                udp_frame(
+                    mac, addr, MDNS_PORT,
+                    MDNS_MAC, MDNS_ADDR, MDNS_PORT,
+                    &payload
+                )
+            } else {
+                let payload = response(&name, addr, query.id, Some(TYPE_A));
+                udp_frame(
+                    mac, addr, MDNS_PORT,
+                    sender.mac, sender.addr, sender.port,
+                    &payload
+                )
+            };
+            net.device.transmit_frame(&frame);
+        }
+    }
+    // Forget the queries received by devices without interfaces
+    queries.clear();
+}
+
+fn ipv4_addr(net: &NetworkInterface) -> Option<Ipv4Address> {
+    net.iface.ip_addrs().iter().find_map(|cidr| match cidr {
+        IpCidr::Ipv4(cidr) if !cidr.address().is_unspecified() => {
+            Some(cidr.address())
+        }
+        _ => None,
+    })
+}
+
+fn parse_frame(frame: &[u8]) -> Option<(Sender, Query)> {
+    let frame = EthernetFrame::new_checked(frame).ok()?;
+    if frame.ethertype() != EthernetProtocol::Ipv4 {
+        return None;
+    }
+    let ip = Ipv4Packet::new_checked(frame.payload()).ok()?;
+    if ip.next_header() != IpProtocol::Udp || ip.dst_addr() != MDNS_ADDR {
+        return None;
+    }
+    let udp = UdpPacket::new_checked(ip.payload()).ok()?;
+    if udp.dst_port() != MDNS_PORT {
+        return None;
+    }
+    let query = parse_query(udp.payload())?;
+    let sender = Sender {
+        mac: frame.src_addr(),
+        addr: ip.src_addr(),
+        port: udp.src_port(),
+    };
+    Some((sender, query))
+}
+
+fn parse_query(buf: &[u8]) -> Option<Query> {
+    let id = u16::from_be_bytes([*buf.first()?, *buf.get(1)?]);
+    let flags = u16::from_be_bytes([*buf.get(2)?, *buf.get(3)?]);
+    if flags & 0xF800 != 0 {
+        return None; // Response or not a standard query
+    }
+    let qdcount = u16::from_be_bytes([*buf.get(4)?, *buf.get(5)?]);
+    let mut questions = Vec::new();
+    let mut i = 12;
+    for _ in 0..qdcount {
+        let name = read_name(buf, i)?;
+        i = skip_name(buf, i)?;
+        let qtype = u16::from_be_bytes([*buf.get(i)?, *buf.get(i + 1)?]);
+        questions.push((name, qtype));
+        i += 4; // QTYPE + QCLASS
+    }
+    Some(Query { id, questions })
+}
+
+fn push_name(buf: &mut Vec<u8>, name: &str) {
+    for label in name.split('.') {
+        buf.push(label.len() as u8);
+        buf.extend_from_slice(label.as_bytes());
+    }
+    buf.push(0);
+}
+
+// Build a response with the address of a name, repeating the question of a
+// legacy query that will be answered with its ID
+fn response(
+    name: &str, addr: Ipv4Address, id: u16, question: Option<u16>
+) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&id.to_be_bytes());
+    buf.extend_from_slice(&0x8400u16.to_be_bytes()); // Response + AA
+    let qdcount = if question.is_some() { 1u16 } else { 0 };
+    buf.extend_from_slice(&qdcount.to_be_bytes());
+    buf.extend_from_slice(&1u16.to_be_bytes()); // Answers
+    buf.extend_from_slice(&[0, 0, 0, 0]); // Authority + Additional
+    if let Some(qtype) = question {
+        push_name(&mut buf, name);
+        buf.extend_from_slice(&qtype.to_be_bytes());
+        buf.extend_from_slice(&CLASS_IN.to_be_bytes());
+    }
+    let (class, ttl) = if question.is_some() {
+        (CLASS_IN, LEGACY_TTL)
+    } else {
+        (CLASS_IN | CACHE_FLUSH, TTL)
+    };
+    push_name(&mut buf, name);
+    buf.extend_from_slice(&TYPE_A.to_be_bytes());
+    buf.extend_from_slice(&class.to_be_bytes());
+    buf.extend_from_slice(&ttl.to_be_bytes());
+    buf.extend_from_slice(&4u16.to_be_bytes());
+    buf.extend_from_slice(addr.as_bytes());
+    buf
+}
+
+pub fn udp_frame(
+    src_mac: EthernetAddress, src_addr: Ipv4Address, src_port: u16,
+    dst_mac: EthernetAddress, dst_addr: Ipv4Address, dst_port: u16,
+    payload: &[u8]
+) -> Vec<u8> {
+    let eth_repr = EthernetRepr {
+        src_addr: src_mac,
+        dst_addr: dst_mac,
+        ethertype: EthernetProtocol::Ipv4,
+    };
+    let udp_repr = UdpRepr { src_port, dst_port };
+    let udp_len = udp_repr.header_len() + payload.len();
+    let ip_repr = Ipv4Repr {
+        src_addr,
+        dst_addr,
+        next_header: IpProtocol::Udp,
+        payload_len: udp_len,
+        hop_limit: 255,
+    };
+    let len = eth_repr.buffer_len() + ip_repr.buffer_len() + udp_len;
+    let mut buf = vec![0; len];
+    let caps = ChecksumCapabilities::default();
+    let mut frame = EthernetFrame::new_unchecked(&mut buf);
+    eth_repr.emit(&mut frame);
+    let mut ip = Ipv4Packet::new_unchecked(frame.payload_mut());
+    ip_repr.emit(&mut ip, &caps);
+    udp_repr.emit(
+        &mut UdpPacket::new_unchecked(ip.payload_mut()),
+        &IpAddress::from(src_addr),
+        &IpAddress::from(dst_addr),
+        payload.len(),
+        |buf| buf.copy_from_slice(payload),
+        &caps
+    );
+    buf
+}
+
+#[test_case]
+fn test_parse_query() {
+    let mut buf = vec![0x12, 0x34, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+    push_name(&mut buf, "moros.local");
+    buf.extend_from_slice(&[0, 1, 0x80, 1]); // A with unicast response bit
+    assert_eq!(parse_query(&buf), Some(Query {
+        id: 0x1234,
+        questions: vec![("moros.local".into(), TYPE_A)],
+    }));
+
+    buf[2] = 0x84; // Response
+    assert_eq!(parse_query(&buf), None);
+    buf[2] = 0;
+    buf.truncate(buf.len() - 6);
+    assert_eq!(parse_query(&buf), None);
+}
+
+#[test_case]
+fn test_response() {
+    let addr = Ipv4Address::new(10, 0, 2, 15);
+    let buf = response("moros.local", addr, 0x1234, Some(TYPE_A));
+    assert_eq!(buf[0..2], [0x12, 0x34]);
+    assert_eq!(buf[4..8], [0, 1, 0, 1]);
+    assert_eq!(read_name(&buf, 12), Some("moros.local".into()));
+    assert_eq!(buf[(buf.len() - 4)..], [10, 0, 2, 15]);
+
+    let mac = EthernetAddress([0x52, 0x54, 0, 0x12, 0x34, 0x56]);
+    let mut query = vec![0x12, 0x34, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+    push_name(&mut query, "moros.local");
+    query.extend_from_slice(&[0, 1, 0, 1]);
+    let frame = udp_frame(
+        mac, addr, 49152, MDNS_MAC, MDNS_ADDR, MDNS_PORT, &query
+    );
+    assert_eq!(frame.len(), 14 + 20 + 8 + query.len());
+    let (sender, query) = parse_frame(&frame).unwrap();
+    assert_eq!(sender.mac, mac);
+    assert_eq!(sender.addr, addr);
+    assert_eq!(sender.port, 49152);
+    assert_eq!(query.questions, vec![("moros.local".into(), TYPE_A)]);
+}
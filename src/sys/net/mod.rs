@@ -1,20 +1,43 @@
+pub mod arp;
+pub mod capture;
+pub mod dhcp;
+pub mod firewall;
+pub mod mdns;
 mod nic;
 pub mod socket;
+pub mod stat;
 
 use crate::{sys, usr};
+use crate::sys::net::capture::Capture;
+use crate::sys::net::firewall::Direction;
 use crate::sys::pci::DeviceConfig;
 
 use alloc::format;
+use alloc::string::String;
 use alloc::sync::Arc;
+use alloc::vec;
 use alloc::vec::Vec;
 use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use smoltcp::iface::Interface;
+use smoltcp::iface::{Interface, SocketSet};
 use smoltcp::phy::DeviceCapabilities;
-use smoltcp::time::Instant;
-use smoltcp::wire::EthernetAddress;
+use smoltcp::time::{Duration, Instant};
+use smoltcp::wire::{EthernetAddress, IpAddress, IpCidr};
 use spin::Mutex;
 
-pub static NET: Mutex<Option<(Interface, EthernetDevice)>> = Mutex::new(None);
+pub static NET: Mutex<Vec<NetworkInterface>> = Mutex::new(Vec::new());
+
+pub struct NetworkInterface {
+    pub name: String,
+    pub iface: Interface,
+    pub device: EthernetDevice,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Route {
+    pub cidr: IpCidr,
+    pub gateway: Option<IpAddress>,
+    pub index: usize,
+}
 
 #[repr(u8)]
 pub enum SocketStatus {
@@ -31,6 +54,90 @@ fn time() -> Instant {
     Instant::from_micros((sys::clock::realtime() * 1000000.0) as i64)
 }
 
+// The sockets are shared by every interface, each of them only accepting
+// the packets sent to its addresses and only transmitting the packets it
+// has a route for.
+pub fn poll(interfaces: &mut [NetworkInterface], sockets: &mut SocketSet) {
+    let now = time();
+    for net in interfaces.iter_mut() {
+        net.iface.poll(now, &mut net.device, sockets);
+    }
+    mdns::poll(interfaces);
+    dhcp::poll(interfaces);
+}
+
+// Poll the interfaces from the idle point of the kernel when the DHCP lease
+// has to be renewed, unless they are being used
+pub fn poll_if_due() {
+    if !dhcp::is_due() {
+        return;
+    }
+    if let Some(mut net) = NET.try_lock() {
+        if let Some(mut sockets) = socket::SOCKETS.try_lock() {
+            poll(&mut net, &mut sockets);
+        }
+    }
+}
+
+pub fn poll_delay(
+    interfaces: &mut [NetworkInterface],
+    sockets: &SocketSet
+) -> Option<Duration> {
+    let now = time();
+    interfaces.iter_mut().filter_map(|net| {
+        net.iface.poll_delay(now, sockets)
+    }).min()
+}
+
+// The routing table is made of the networks of the addresses of each
+// interface followed by the routes to their gateways.
+pub fn routes(interfaces: &mut [NetworkInterface]) -> Vec<Route> {
+    let mut routes = Vec::new();
+    for (index, net) in interfaces.iter_mut().enumerate() {
+        for addr in net.iface.ip_addrs() {
+            if addr.address().is_unspecified() {
+                continue;
+            }
+            let cidr = match addr {
+                IpCidr::Ipv4(cidr) => IpCidr::Ipv4(cidr.network()),
+            };
+            routes.push(Route { cidr, gateway: None, index });
+        }
+    }
+    for (index, net) in interfaces.iter_mut().enumerate() {
+        net.iface.routes_mut().update(|storage| {
+            for route in storage.iter() {
+                let cidr = route.cidr;
+                let gateway = Some(route.via_router);
+                routes.push(Route { cidr, gateway, index });
+            }
+        });
+    }
+    routes
+}
+
+// Return the index of the interface used to reach an address
+pub fn route(
+    interfaces: &mut [NetworkInterface],
+    addr: IpAddress
+) -> Option<usize> {
+    find_route(&routes(interfaces), addr).map(|route| route.index)
+}
+
+// Find the most specific route to an address, the first one winning a tie
+fn find_route(routes: &[Route], addr: IpAddress) -> Option<Route> {
+    let mut found: Option<Route> = None;
+    for route in routes {
+        if route.cidr.contains_addr(&addr) {
+            let len = route.cidr.prefix_len();
+            if found.map_or(true, |r| r.cidr.prefix_len() < len) {
+                found = Some(*route);
+            }
+        }
+    }
+    found
+}
+
 #[derive(Clone)]
 pub enum EthernetDevice {
     RTL8139(nic::rtl8139::Device),
@@ -39,12 +146,41 @@ pub enum EthernetDevice {
     //VirtIO,
 }
 
+impl EthernetDevice {
+    pub fn driver(&self) -> &'static str {
+        match self {
+            EthernetDevice::RTL8139(_) => "RTL8139",
+            EthernetDevice::PCNET(_) => "PCNET",
+            EthernetDevice::E1000(_) => "E1000",
+        }
+    }
+
+    // Transmit a frame built by smoltcp or by the kernel
+    fn transmit_frame(&mut self, buf: &[u8]) {
+        let len = buf.len();
+        if self.config().is_debug_enabled() {
+            debug!("NET Packet Transmitted");
+            usr::hex::print_hex(buf);
+        }
+        arp::transmit(buf);
+        if firewall::accept(Direction::Out, buf) {
+            self.next_tx_buffer(len).copy_from_slice(buf);
+            self.transmit_packet(len);
+            self.stats().tx_add(len as u64);
+            self.config().capture.push(Direction::Out, buf);
+        } else {
+            self.stats().tx_drop();
+        }
+    }
+}
+
 pub trait EthernetDeviceIO {
     fn config(&self) -> Arc<Config>;
     fn stats(&self) -> Arc<Stats>;
     fn receive_packet(&mut self) -> Option<Vec<u8>>;
     fn transmit_packet(&mut self, len: usize);
     fn next_tx_buffer(&mut self, len: usize) -> &mut [u8];
+    fn set_promiscuous(&mut self, enabled: bool);
 }
 
 impl EthernetDeviceIO for EthernetDevice {
@@ -87,6 +223,14 @@ impl EthernetDeviceIO for EthernetDevice {
             EthernetDevice::E1000(dev) => dev.next_tx_buffer(len),
         }
     }
+
+    fn set_promiscuous(&mut self, enabled: bool) {
+        match self {
+            EthernetDevice::RTL8139(dev) => dev.set_promiscuous(enabled),
+            EthernetDevice::PCNET(dev) => dev.set_promiscuous(enabled),
+            EthernetDevice::E1000(dev) => dev.set_promiscuous(enabled),
+        }
+    }
 }
 
 impl<'a> smoltcp::phy::Device for EthernetDevice {
@@ -104,20 +248,37 @@ impl<'a> smoltcp::phy::Device for EthernetDevice {
         &mut self,
         _instant: smoltcp::time::Instant,
     ) -> Option<(Self::RxToken<'a>, Self::TxToken<'a>)> {
-        if let Some(buffer) = self.receive_packet() {
-            if self.config().is_debug_enabled() {
-                debug!("NET Packet Received");
-                usr::hex::print_hex(&buffer);
+        let reply = self.config().mac().and_then(arp::pending_reply);
+        let buffer = match reply {
+            Some(buffer) => buffer, // Resolved from a static ARP entry
+            None => loop {
+                let buffer = self.receive_packet()?;
+                if self.config().is_debug_enabled() {
+                    debug!("NET Packet Received");
+                    usr::hex::print_hex(&buffer);
+                }
+                self.stats().rx_add(buffer.len() as u64);
+                self.config().capture.push(Direction::In, &buffer);
+                arp::receive(&buffer);
+                if firewall::accept(Direction::In, &buffer) {
+                    if let Some(mac) = self.config().mac() {
+                        mdns::receive(mac, &buffer);
+                    }
+                    // The replies to the requests of the kernel are not
+                    // passed to smoltcp that has no socket for them
+                    if dhcp::receive(&buffer) {
+                        continue;
+                    }
+                    break buffer;
+                }
+                self.stats().rx_drop();
             }
-            self.stats().rx_add(buffer.len() as u64);
-            let rx = RxToken { buffer };
-            let tx = TxToken {
-                device: self.clone(),
-            };
-            Some((rx, tx))
-        } else {
-            None
-        }
+        };
+        let rx = RxToken { buffer };
+        let tx = TxToken {
+            device: self.clone(),
+        };
+        Some((rx, tx))
     }
 
     fn transmit(
@@ -154,15 +315,12 @@ impl smoltcp::phy::TxToken for TxToken {
     where
         F: FnOnce(&mut [u8]) -> R,
     {
-        let config = self.device.config();
-        let buf = self.device.next_tx_buffer(len);
-        let res = f(buf);
-        if config.is_debug_enabled() {
-            debug!("NET Packet Transmitted");
-            usr::hex::print_hex(buf);
-        }
-        self.device.transmit_packet(len);
-        self.device.stats().tx_add(len as u64);
+        // The packet is built outside of the buffers of the device that
+        // have to be transmitted in order once taken, in case the firewall
+        // drops it.
+        let mut buf = vec![0; len];
+        let res = f(&mut buf);
+        self.device.transmit_frame(&buf);
         res
     }
 }
@@ -170,6 +328,7 @@ impl smoltcp::phy::TxToken for TxToken {
 pub struct Config {
     debug: AtomicBool,
     mac: Mutex<Option<EthernetAddress>>,
+    pub capture: Capture,
 }
 
 impl Config {
@@ -177,6 +336,7 @@ impl Config {
         Self {
             debug: AtomicBool::new(false),
             mac: Mutex::new(None),
+            capture: Capture::default(),
         }
     }
 
@@ -206,6 +366,9 @@ pub struct Stats {
     tx_bytes_count: AtomicU64,
     rx_packets_count: AtomicU64,
     tx_packets_count: AtomicU64,
+    rx_errors_count: AtomicU64,
+    rx_dropped_count: AtomicU64,
+    tx_dropped_count: AtomicU64,
 }
 
 impl Stats {
@@ -215,6 +378,9 @@ impl Stats {
             tx_bytes_count: AtomicU64::new(0),
             rx_packets_count: AtomicU64::new(0),
             tx_packets_count: AtomicU64::new(0),
+            rx_errors_count: AtomicU64::new(0),
+            rx_dropped_count: AtomicU64::new(0),
+            tx_dropped_count: AtomicU64::new(0),
         }
     }
 
@@ -234,6 +400,32 @@ impl Stats {
         self.tx_packets_count.load(Ordering::Relaxed)
     }
 
+    // Packets received with an error reported by the device
+    pub fn rx_errors_count(&self) -> u64 {
+        self.rx_errors_count.load(Ordering::Relaxed)
+    }
+
+    // Packets dropped by the firewall
+    pub fn rx_dropped_count(&self) -> u64 {
+        self.rx_dropped_count.load(Ordering::Relaxed)
+    }
+
+    pub fn tx_dropped_count(&self) -> u64 {
+        self.tx_dropped_count.load(Ordering::Relaxed)
+    }
+
+    pub fn rx_error(&self) {
+        self.rx_errors_count.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn rx_drop(&self) {
+        self.rx_dropped_count.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn tx_drop(&self) {
+        self.tx_dropped_count.fetch_add(1, Ordering::SeqCst);
+    }
+
     pub fn rx_add(&self, bytes_count: u64) {
         self.rx_packets_count.fetch_add(1, Ordering::SeqCst);
         self.rx_bytes_count.fetch_add(bytes_count, Ordering::SeqCst);
@@ -267,26 +459,28 @@ const E1000_DEVICES: [u16; 9] = [
 ];
 
 pub fn init() {
-    let add = |mut device: EthernetDevice, name| {
+    let add = |mut device: EthernetDevice| {
         if let Some(mac) = device.config().mac() {
             let addr = format!("{}", mac).to_uppercase();
-            log!("NET {} MAC {}", name, addr);
+            log!("NET {} MAC {}", device.driver(), addr);
 
             let config = smoltcp::iface::Config::new(mac.into());
             let iface = Interface::new(config, &mut device, time());
 
-            *NET.lock() = Some((iface, device));
+            let mut interfaces = NET.lock();
+            let name = format!("eth{}", interfaces.len());
+            interfaces.push(NetworkInterface { name, iface, device });
         }
     };
     if let Some(dev) = find_device(0x10EC, 0x8139) {
         let io = dev.io_base();
         let nic = nic::rtl8139::Device::new(io);
-        add(EthernetDevice::RTL8139(nic), "RTL8139");
+        add(EthernetDevice::RTL8139(nic));
     }
     if let Some(dev) = find_device(0x1022, 0x2000) {
         let io = dev.io_base();
         let nic = nic::pcnet::Device::new(io);
-        add(EthernetDevice::PCNET(nic), "PCNET");
+        add(EthernetDevice::PCNET(nic));
     }
     for id in E1000_DEVICES {
         if let Some(dev) = find_device(0x8086, id) {
@@ -294,7 +488,26 @@ pub fn init() {
             let mem = dev.mem_base();
             let bar = dev.bar_type();
             let nic = nic::e1000::Device::new(io, mem, bar);
-            add(EthernetDevice::E1000(nic), "E1000");
+            add(EthernetDevice::E1000(nic));
         }
     }
 }
+
+#[test_case]
+fn test_find_route() {
+    let addr = |a, b, c, d| IpAddress::v4(a, b, c, d);
+    let route = |cidr, prefix_len, gateway, index| {
+        let cidr = IpCidr::new(cidr, prefix_len);
+        Route { cidr, gateway, index }
+    };
+    let routes = [
+        route(addr(10, 0, 2, 0), 24, None, 0),
+        route(addr(10, 0, 3, 0), 24, None, 1),
+        route(addr(0, 0, 0, 0), 0, Some(addr(10, 0, 3, 2)), 1),
+    ];
+    let find = |a| find_route(&routes, a).map(|route| route.index);
+    assert_eq!(find(addr(10, 0, 2, 2)), Some(0));
+    assert_eq!(find(addr(10, 0, 3, 2)), Some(1));
+    assert_eq!(find(addr(1, 1, 1, 1)), Some(1));
+    assert_eq!(find_route(&routes[0..2], addr(1, 1, 1, 1)), None);
+}
@@ -47,6 +47,8 @@ const ICR_RXDMT0: u32 = 1 << 4; // Receive Descriptor Minimum Threshold Reached
 const ICR_RXT0: u32 =   1 << 7; // Receiver Timer Interrupt
 
 const RCTL_EN: u32 =    1 << 1;  // Receiver Enable
+const RCTL_UPE: u32 =   1 << 3;  // Unicast Promiscuous Enable
+const RCTL_MPE: u32 =   1 << 4;  // Multicast Promiscuous Enable
 const RCTL_BAM: u32 =   1 << 15; // Broadcast Accept Mode
 const RCTL_SECRC: u32 = 1 << 26; // Strip Ethernet CRC
 
@@ -220,8 +222,10 @@ impl Device {
         self.write(REG_RDH, 0);
         self.write(REG_RDT, (n - 1) as u32);
 
-        // Control Register
-        self.write(REG_RCTL, RCTL_EN | RCTL_BAM | RCTL_SECRC | RCTL_BSIZE);
+        // Control Register, accepting every multicast packet instead of
+        // filtering them with the table
+        let flags = RCTL_EN | RCTL_BAM | RCTL_MPE | RCTL_SECRC | RCTL_BSIZE;
+        self.write(REG_RCTL, flags);
     }
 
     fn init_tx(&mut self) {
@@ -414,8 +418,13 @@ impl EthernetDeviceIO for Device {
             self.rx_id.store((rx_id + 1) % RX_BUFFERS_COUNT, Ordering::SeqCst);
             let n = rx_descs[rx_id].len as usize;
             let buf = self.rx_buffers[rx_id][0..n].to_vec();
+            let errors = rx_descs[rx_id].errors;
             rx_descs[rx_id].status = 0; // Driver is done
             self.write(REG_RDT, rx_id as u32);
+            if errors > 0 {
+                self.stats.rx_error();
+                return None;
+            }
             return Some(buf);
         }
 
@@ -441,6 +450,16 @@ impl EthernetDeviceIO for Device {
         self.tx_id.store(tx_id, Ordering::SeqCst);
         &mut self.tx_buffers[tx_id][0..len]
     }
+
+    fn set_promiscuous(&mut self, enabled: bool) {
+        let mut rctl = self.read(REG_RCTL);
+        if enabled {
+            rctl |= RCTL_UPE;
+        } else {
+            rctl &= !RCTL_UPE;
+        }
+        self.write(REG_RCTL, rctl);
+    }
 }
 
 #[test_case]
@@ -5,6 +5,7 @@ use crate::sys::net::{Config, EthernetDeviceIO, Stats};
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use bit_field::BitField;
+use core::hint::spin_loop;
 use core::sync::atomic::{AtomicUsize, Ordering};
 use smoltcp::wire::EthernetAddress;
 use x86_64::instructions::port::Port;
@@ -26,13 +27,17 @@ const CSR0_IDON: usize = 8;
 //const CSR0_BABL: usize = 14;
 //const CSR0_ERR: usize = 0;
 
+const CSR5_SPND: usize = 0;
+
+const CSR15_PROM: usize = 15;
+
 const DE_ENP: usize = 0;
 const DE_STP: usize = 1;
 //const DE_BUFF: usize = 2;
 //const DE_CRC:  usize = 3;
 //const DE_OFLO: usize = 4;
 //const DE_FRAM: usize = 5;
-//const DE_ERR:  usize = 6;
+const DE_ERR: usize = 6;
 const DE_OWN: usize = 7;
 
 #[derive(Clone)]
@@ -205,6 +210,9 @@ impl Device {
         init_struct[7] = mac[3];
         init_struct[8] = mac[4];
         init_struct[9] = mac[5];
+        for i in 12..20 {
+            init_struct[i] = 0xFF; // Accept every multicast packet
+        }
         let rx_addr = self.rx_des.addr().to_le_bytes();
         init_struct[20] = rx_addr[0];
         init_struct[21] = rx_addr[1];
@@ -282,10 +290,12 @@ impl EthernetDeviceIO for Device {
 
     fn receive_packet(&mut self) -> Option<Vec<u8>> {
         let mut packet = Vec::new();
+        let mut is_error = false;
         let mut rx_id = self.rx_id.load(Ordering::SeqCst);
         while is_buffer_owner(&self.rx_des, rx_id) {
             let rmd1 = self.rx_des[rx_id * DE_LEN + 7];
             let end_of_packet = rmd1.get_bit(DE_ENP);
+            is_error |= rmd1.get_bit(DE_ERR);
 
             // Read packet size
             let packet_size = u16::from_le_bytes([
@@ -311,7 +321,10 @@ impl EthernetDeviceIO for Device {
             }
         }
 
-        if !packet.is_empty() {
+        if is_error {
+            self.stats.rx_error();
+            None
+        } else if !packet.is_empty() {
             Some(packet)
         } else {
             None
@@ -344,4 +357,21 @@ impl EthernetDeviceIO for Device {
         let tx_id = self.tx_id.load(Ordering::SeqCst);
         &mut self.tx_buffers[tx_id][0..len]
     }
+
+    fn set_promiscuous(&mut self, enabled: bool) {
+        // The mode can only be changed while the card is suspended
+        let mut csr_5 = self.ports.read_csr_32(5);
+        csr_5.set_bit(CSR5_SPND, true);
+        self.ports.write_csr_32(5, csr_5);
+        while !self.ports.read_csr_32(5).get_bit(CSR5_SPND) {
+            spin_loop();
+        }
+
+        let mut csr_15 = self.ports.read_csr_32(15);
+        csr_15.set_bit(CSR15_PROM, enabled);
+        self.ports.write_csr_32(15, csr_15);
+
+        csr_5.set_bit(CSR5_SPND, false);
+        self.ports.write_csr_32(5, csr_5);
+    }
 }
@@ -76,6 +76,9 @@ pub struct Ports {
     // ID Registers (IDR0 ... IDR5)
     pub mac: [Port<u8>; 6],
 
+    // Multicast Registers (MAR0 ... MAR7)
+    pub mar: [Port<u32>; 2],
+
     // Transmit Status of Descriptors (TSD0 .. TSD3)
     pub tx_cmds: [Port<u32>; TX_BUFFERS_COUNT],
 
@@ -121,6 +124,10 @@ impl Ports {
                 Port::new(io_base + 0x04),
                 Port::new(io_base + 0x05),
             ],
+            mar: [
+                Port::new(io_base + 0x08),
+                Port::new(io_base + 0x0C),
+            ],
             tx_cmds: [
                 Port::new(io_base + 0x10),
                 Port::new(io_base + 0x14),
@@ -230,8 +237,13 @@ impl Device {
             unsafe { self.ports.tx_addrs[i].write(tx_addr as u32) }
         }
 
+        // Accept every multicast packet instead of filtering their hashes
+        for i in 0..2 {
+            unsafe { self.ports.mar[i].write(0xFFFFFFFF) }
+        }
+
         // Configure receive buffer (RCR)
-        let flags = RCR_RBLEN | RCR_WRAP | RCR_AB | RCR_AM | RCR_APM | RCR_AAP;
+        let flags = RCR_RBLEN | RCR_WRAP | RCR_AB | RCR_AM | RCR_APM;
         unsafe { self.ports.rx_config.write(flags) }
 
         // Configure transmit buffer (TCR)
@@ -271,6 +283,7 @@ impl EthernetDeviceIO for Device {
         );
 
         if header & ROK != ROK {
+            self.stats.rx_error();
             let capr = ((cba as usize) % RX_BUFFER_LEN) - RX_BUFFER_PAD;
             unsafe { self.ports.capr.write(capr as u16) }
             return None;
@@ -320,6 +333,17 @@ impl EthernetDeviceIO for Device {
         self.tx_id.store(tx_id, Ordering::SeqCst);
         &mut self.tx_buffers[tx_id][0..len]
     }
+
+    fn set_promiscuous(&mut self, enabled: bool) {
+        unsafe {
+            let flags = self.ports.rx_config.read();
+            if enabled {
+                self.ports.rx_config.write(flags | RCR_AAP);
+            } else {
+                self.ports.rx_config.write(flags & !RCR_AAP);
+            }
+        }
+    }
 }
 
 /*
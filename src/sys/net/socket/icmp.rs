@@ -0,0 +1,176 @@
+use crate::sys;
+
+use crate::api::fs::{FileIO, IO};
+use crate::sys::net::SocketStatus;
+
+use super::SOCKETS;
+use super::wait;
+
+use alloc::vec;
+use bit_field::BitField;
+use smoltcp::iface::SocketHandle;
+use smoltcp::phy::Device;
+use smoltcp::socket::icmp;
+use smoltcp::wire::IpAddress;
+
+fn icmp_socket_status(socket: &icmp::Socket) -> u8 {
+    let mut status = 0;
+    status.set_bit(SocketStatus::IsOpen as usize, socket.is_open());
+    status.set_bit(SocketStatus::CanSend as usize, socket.can_send());
+    status.set_bit(SocketStatus::CanRecv as usize, socket.can_recv());
+    status
+}
+
+// A raw ICMP socket sending and receiving whole ICMP messages, bound to the
+// identifier of the echo requests given instead of a port when connecting, to
+// receive their replies.
+#[derive(Debug, Clone)]
+pub struct IcmpSocket {
+    pub handle: SocketHandle,
+    pub remote_addr: Option<IpAddress>,
+}
+
+impl IcmpSocket {
+    pub fn size() -> usize {
+        if let Some(net) = sys::net::NET.lock().first() {
+            let mtu = net.device.capabilities().max_transmission_unit;
+            let eth_header = 14;
+            let ip_header = 20;
+            mtu - eth_header - ip_header
+        } else {
+            1
+        }
+    }
+
+    pub fn new() -> Self {
+        let mut sockets = SOCKETS.lock();
+        let icmp_rx_buffer = icmp::PacketBuffer::new(
+            vec![icmp::PacketMetadata::EMPTY; 4], vec![0; 4096]
+        );
+        let icmp_tx_buffer = icmp::PacketBuffer::new(
+            vec![icmp::PacketMetadata::EMPTY; 4], vec![0; 4096]
+        );
+        let icmp_socket = icmp::Socket::new(icmp_rx_buffer, icmp_tx_buffer);
+        let handle = sockets.add(icmp_socket);
+        let remote_addr = None;
+
+        Self {
+            handle,
+            remote_addr,
+        }
+    }
+
+    pub fn connect(&mut self, addr: IpAddress, ident: u16) -> Result<(), ()> {
+        let mut sockets = SOCKETS.lock();
+        let socket = sockets.get_mut::<icmp::Socket>(self.handle);
+        if !socket.is_open() {
+            let endpoint = icmp::Endpoint::Ident(ident);
+            socket.bind(endpoint).map_err(|_| ())?;
+        }
+        self.remote_addr = Some(addr);
+        Ok(())
+    }
+}
+
+impl Default for IcmpSocket {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileIO for IcmpSocket {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ()> {
+        let timeout = 5.0;
+        let started = sys::clock::realtime();
+        let mut net = sys::net::NET.lock();
+        if !net.is_empty() {
+            let bytes;
+            let mut sockets = SOCKETS.lock();
+            loop {
+                if sys::clock::realtime() - started > timeout {
+                    return Err(());
+                }
+                sys::net::poll(&mut net, &mut sockets);
+                let socket = sockets.get_mut::<icmp::Socket>(self.handle);
+
+                if buf.len() == 1 {
+                    // 1 byte status read
+                    buf[0] = icmp_socket_status(socket);
+                    return Ok(1);
+                }
+
+                if socket.can_recv() {
+                    (bytes, _) = socket.recv_slice(buf).map_err(|_| ())?;
+                    break;
+                }
+                if let Some(d) = sys::net::poll_delay(&mut net, &sockets) {
+                    wait(d);
+                }
+                sys::time::halt();
+            }
+            Ok(bytes)
+        } else {
+            Err(())
+        }
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize, ()> {
+        let timeout = 5.0;
+        let started = sys::clock::realtime();
+        let mut sent = false;
+        let mut net = sys::net::NET.lock();
+        if !net.is_empty() {
+            let mut sockets = SOCKETS.lock();
+            loop {
+                if sys::clock::realtime() - started > timeout {
+                    return Err(());
+                }
+                sys::net::poll(&mut net, &mut sockets);
+                let socket = sockets.get_mut::<icmp::Socket>(self.handle);
+
+                if sent {
+                    break;
+                }
+                if socket.can_send() {
+                    if let Some(addr) = self.remote_addr {
+                        if socket.send_slice(buf.as_ref(), addr).is_err() {
+                            return Err(());
+                        }
+                    } else {
+                        return Err(());
+                    }
+                    sent = true; // Break after next poll
+                }
+
+                if let Some(d) = sys::net::poll_delay(&mut net, &sockets) {
+                    wait(d);
+                }
+                sys::time::halt();
+            }
+            Ok(buf.len())
+        } else {
+            Err(())
+        }
+    }
+
+    fn close(&mut self) {
+        // NOTE: An ICMP socket has no connection to close and stays bound to
+        // its identifier, like the other sockets are kept in the socket set.
+    }
+
+    fn poll(&mut self, event: IO) -> bool {
+        let mut net = sys::net::NET.lock();
+        if !net.is_empty() {
+            let mut sockets = SOCKETS.lock();
+            sys::net::poll(&mut net, &mut sockets);
+            let socket = sockets.get_mut::<icmp::Socket>(self.handle);
+
+            match event {
+                IO::Read => socket.can_recv(),
+                IO::Write => socket.can_send(),
+            }
+        } else {
+            false
+        }
+    }
+}
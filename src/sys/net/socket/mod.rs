@@ -1,5 +1,6 @@
 use crate::sys;
 
+pub mod icmp;
 pub mod tcp;
 pub mod udp;
 
@@ -8,7 +8,7 @@ use super::{random_port, wait};
 
 use alloc::vec;
 use bit_field::BitField;
-use smoltcp::iface::SocketHandle;
+use smoltcp::iface::{SocketHandle, SocketSet};
 use smoltcp::phy::Device;
 use smoltcp::socket::tcp;
 use smoltcp::wire::IpAddress;
@@ -25,15 +25,51 @@ fn tcp_socket_status(socket: &tcp::Socket) -> u8 {
     status
 }
 
+// Send a whole buffer through a socket, a part at a time when it doesn't
+// fit in the send buffer, calling the poll function with the number of
+// bytes sent before each part and once more after the last one
+fn send_all<F>(
+    sockets: &mut SocketSet<'static>,
+    handle: SocketHandle,
+    buf: &[u8],
+    mut poll: F
+) -> Result<usize, ()>
+where
+    F: FnMut(&mut SocketSet<'static>, usize) -> Result<(), ()>,
+{
+    let mut sent = 0;
+    loop {
+        poll(sockets, sent)?;
+        if sent == buf.len() {
+            return Ok(sent);
+        }
+        let socket = sockets.get_mut::<tcp::Socket>(handle);
+        if socket.can_send() {
+            sent += socket.send_slice(&buf[sent..]).map_err(|_| ())?;
+        } else if !socket.is_active() {
+            return Err(());
+        }
+    }
+}
+
+fn tcp_socket() -> tcp::Socket<'static> {
+    let tcp_rx_buffer = tcp::SocketBuffer::new(vec![0; 1024]);
+    let tcp_tx_buffer = tcp::SocketBuffer::new(vec![0; 1024]);
+    tcp::Socket::new(tcp_rx_buffer, tcp_tx_buffer)
+}
+
+// The port of a listening socket is kept because smoltcp only gives the
+// local endpoint of a connected socket.
 #[derive(Debug, Clone)]
 pub struct TcpSocket {
     pub handle: SocketHandle,
+    pub listen_port: Option<u16>,
 }
 
 impl TcpSocket {
     pub fn size() -> usize {
-        if let Some((_, ref mut device)) = *sys::net::NET.lock() {
-            let mtu = device.capabilities().max_transmission_unit;
+        if let Some(net) = sys::net::NET.lock().first() {
+            let mtu = net.device.capabilities().max_transmission_unit;
             let eth_header = 14;
             let ip_header = 20;
             let tcp_header = 20;
@@ -45,25 +81,25 @@ impl TcpSocket {
 
     pub fn new() -> Self {
         let mut sockets = SOCKETS.lock();
-        let tcp_rx_buffer = tcp::SocketBuffer::new(vec![0; 1024]);
-        let tcp_tx_buffer = tcp::SocketBuffer::new(vec![0; 1024]);
-        let tcp_socket = tcp::Socket::new(tcp_rx_buffer, tcp_tx_buffer);
-        let handle = sockets.add(tcp_socket);
+        let handle = sockets.add(tcp_socket());
+
+        let listen_port = None;
 
-        Self { handle }
+        Self { handle, listen_port }
     }
 
     pub fn connect(&mut self, addr: IpAddress, port: u16) -> Result<(), ()> {
         let mut connecting = false;
         let timeout = 5.0;
         let started = sys::clock::realtime();
-        if let Some((ref mut iface, ref mut device)) = *sys::net::NET.lock() {
+        let mut net = sys::net::NET.lock();
+        if !net.is_empty() {
             loop {
                 if sys::clock::realtime() - started > timeout {
                     return Err(());
                 }
                 let mut sockets = SOCKETS.lock();
-                iface.poll(sys::net::time(), device, &mut sockets);
+                sys::net::poll(&mut net, &mut sockets);
                 let socket = sockets.get_mut::<tcp::Socket>(self.handle);
 
                 match socket.state() {
@@ -71,7 +107,8 @@ impl TcpSocket {
                         if connecting {
                             return Err(());
                         }
-                        let cx = iface.context();
+                        let i = sys::net::route(&mut net, addr).ok_or(())?;
+                        let cx = net[i].iface.context();
                         let dest = (addr, port);
                         if socket.connect(cx, dest, random_port()).is_err() {
                             return Err(());
@@ -88,7 +125,7 @@ impl TcpSocket {
                     }
                 }
 
-                if let Some(d) = iface.poll_delay(sys::net::time(), &sockets) {
+                if let Some(d) = sys::net::poll_delay(&mut net, &sockets) {
                     wait(d);
                 }
                 sys::time::halt();
@@ -98,16 +135,18 @@ impl TcpSocket {
     }
 
     pub fn listen(&mut self, port: u16) -> Result<(), ()> {
-        if let Some((ref mut iface, ref mut device)) = *sys::net::NET.lock() {
+        let mut net = sys::net::NET.lock();
+        if !net.is_empty() {
             let mut sockets = SOCKETS.lock();
-            iface.poll(sys::net::time(), device, &mut sockets);
+            sys::net::poll(&mut net, &mut sockets);
             let socket = sockets.get_mut::<tcp::Socket>(self.handle);
 
             if socket.listen(port).is_err() {
                 return Err(());
             }
+            self.listen_port = Some(port);
 
-            if let Some(d) = iface.poll_delay(sys::net::time(), &sockets) {
+            if let Some(d) = sys::net::poll_delay(&mut net, &sockets) {
                 wait(d);
             }
             sys::time::halt();
@@ -117,23 +156,38 @@ impl TcpSocket {
         }
     }
 
-    pub fn accept(&mut self) -> Result<IpAddress, ()> {
+    // Return a new socket for the connection received by a listening socket,
+    // which is replaced by a new socket listening to the same port.
+    pub fn accept(&mut self) -> Result<(Self, IpAddress), ()> {
         let timeout = 5.0;
         let started = sys::clock::realtime();
-        if let Some((ref mut iface, ref mut device)) = *sys::net::NET.lock() {
+        let mut net = sys::net::NET.lock();
+        if !net.is_empty() {
             loop {
                 if sys::clock::realtime() - started > timeout {
                     return Err(());
                 }
                 let mut sockets = SOCKETS.lock();
-                iface.poll(sys::net::time(), device, &mut sockets);
+                sys::net::poll(&mut net, &mut sockets);
                 let socket = sockets.get_mut::<tcp::Socket>(self.handle);
 
-                if let Some(endpoint) = socket.remote_endpoint() {
-                    return Ok(endpoint.addr);
+                let endpoints = socket.local_endpoint().zip(
+                    socket.remote_endpoint()
+                );
+                if let Some((local, remote)) = endpoints {
+                    let mut listener = tcp_socket();
+                    if listener.listen(local.port).is_err() {
+                        return Err(());
+                    }
+                    let connection = Self {
+                        handle: self.handle,
+                        listen_port: None,
+                    };
+                    self.handle = sockets.add(listener);
+                    return Ok((connection, remote.addr));
                 }
 
-                if let Some(d) = iface.poll_delay(sys::net::time(), &sockets) {
+                if let Some(d) = sys::net::poll_delay(&mut net, &sockets) {
                     wait(d);
                 }
                 sys::time::halt();
@@ -149,13 +203,14 @@ impl FileIO for TcpSocket {
         let timeout = 5.0;
         let started = sys::clock::realtime();
         let mut bytes = 0;
-        if let Some((ref mut iface, ref mut device)) = *sys::net::NET.lock() {
+        let mut net = sys::net::NET.lock();
+        if !net.is_empty() {
             let mut sockets = SOCKETS.lock();
             loop {
                 if sys::clock::realtime() - started > timeout {
                     return Err(());
                 }
-                iface.poll(sys::net::time(), device, &mut sockets);
+                sys::net::poll(&mut net, &mut sockets);
                 let socket = sockets.get_mut::<tcp::Socket>(self.handle);
 
                 if buf.len() == 1 {
@@ -171,7 +226,7 @@ impl FileIO for TcpSocket {
                 if !socket.may_recv() {
                     break;
                 }
-                if let Some(d) = iface.poll_delay(sys::net::time(), &sockets) {
+                if let Some(d) = sys::net::poll_delay(&mut net, &sockets) {
                     wait(d);
                 }
                 sys::time::halt();
@@ -182,46 +237,44 @@ impl FileIO for TcpSocket {
         }
     }
 
+    // The whole buffer is sent unless the connection is closed or stalls
+    // for more than the timeout
     fn write(&mut self, buf: &[u8]) -> Result<usize, ()> {
         let timeout = 5.0;
-        let started = sys::clock::realtime();
-        let mut sent = false;
-        if let Some((ref mut iface, ref mut device)) = *sys::net::NET.lock() {
-            let mut sockets = SOCKETS.lock();
-            loop {
-                if sys::clock::realtime() - started > timeout {
-                    return Err(());
-                }
-                iface.poll(sys::net::time(), device, &mut sockets);
-                let socket = sockets.get_mut::<tcp::Socket>(self.handle);
-
-                if sent {
-                    break;
-                }
-                if socket.can_send() {
-                    if socket.send_slice(buf.as_ref()).is_err() {
-                        return Err(());
-                    }
-                    sent = true; // Break after next poll
-                }
-
-                if let Some(d) = iface.poll_delay(sys::net::time(), &sockets) {
+        let mut started = sys::clock::realtime();
+        let mut last_sent = 0;
+        let mut is_polled = false;
+        let mut net = sys::net::NET.lock();
+        if net.is_empty() {
+            return Err(());
+        }
+        let mut sockets = SOCKETS.lock();
+        send_all(&mut sockets, self.handle, buf, |sockets, sent| {
+            if sent > last_sent {
+                last_sent = sent;
+                started = sys::clock::realtime();
+            } else if sys::clock::realtime() - started > timeout {
+                return Err(());
+            }
+            if is_polled {
+                if let Some(d) = sys::net::poll_delay(&mut net, sockets) {
                     wait(d);
                 }
                 sys::time::halt();
             }
-            Ok(buf.len())
-        } else {
-            Err(())
-        }
+            is_polled = true;
+            sys::net::poll(&mut net, sockets);
+            Ok(())
+        })
     }
 
     fn close(&mut self) {
         let mut closed = false;
-        if let Some((ref mut iface, ref mut device)) = *sys::net::NET.lock() {
+        let mut net = sys::net::NET.lock();
+        if !net.is_empty() {
             let mut sockets = SOCKETS.lock();
             loop {
-                iface.poll(sys::net::time(), device, &mut sockets);
+                sys::net::poll(&mut net, &mut sockets);
                 let socket = sockets.get_mut::<tcp::Socket>(self.handle);
 
                 if closed {
@@ -230,7 +283,7 @@ impl FileIO for TcpSocket {
                 socket.close();
                 closed = true;
 
-                if let Some(d) = iface.poll_delay(sys::net::time(), &sockets) {
+                if let Some(d) = sys::net::poll_delay(&mut net, &sockets) {
                     wait(d);
                 }
                 sys::time::halt();
@@ -239,9 +292,10 @@ impl FileIO for TcpSocket {
     }
 
     fn poll(&mut self, event: IO) -> bool {
-        if let Some((ref mut iface, ref mut device)) = *sys::net::NET.lock() {
+        let mut net = sys::net::NET.lock();
+        if !net.is_empty() {
             let mut sockets = SOCKETS.lock();
-            iface.poll(sys::net::time(), device, &mut sockets);
+            sys::net::poll(&mut net, &mut sockets);
             let socket = sockets.get_mut::<tcp::Socket>(self.handle);
 
             match event {
@@ -253,3 +307,58 @@ impl FileIO for TcpSocket {
         }
     }
 }
+
+#[test_case]
+fn test_send_all() {
+    use alloc::vec::Vec;
+    use smoltcp::iface::{Config, Interface};
+    use smoltcp::phy::{Loopback, Medium};
+    use smoltcp::time::Instant;
+    use smoltcp::wire::{EthernetAddress, IpCidr};
+
+    let mut device = Loopback::new(Medium::Ethernet);
+    let mac = EthernetAddress([0x02, 0, 0, 0, 0, 1]);
+    let mut iface = Interface::new(
+        Config::new(mac.into()), &mut device, Instant::ZERO
+    );
+    let addr = IpAddress::v4(127, 0, 0, 1);
+    iface.update_ip_addrs(|addrs| {
+        addrs.push(IpCidr::new(addr, 8)).unwrap();
+    });
+    let mut sockets = SocketSet::new(vec![]);
+    let server = sockets.add(tcp_socket());
+    let client = sockets.add(tcp_socket());
+    sockets.get_mut::<tcp::Socket>(server).listen(1234).unwrap();
+    let socket = sockets.get_mut::<tcp::Socket>(client);
+    socket.connect(iface.context(), (addr, 1234), 49152).unwrap();
+
+    // The buffer doesn't fit in the send buffer of the socket
+    let buf: Vec<u8> = (0..4096).map(|i| i as u8).collect();
+    let mut received = Vec::new();
+    let mut now = 0;
+    let mut poll = |sockets: &mut SocketSet<'static>| {
+        now += 10;
+        iface.poll(Instant::from_millis(now), &mut device, sockets);
+        let socket = sockets.get_mut::<tcp::Socket>(server);
+        while socket.can_recv() {
+            socket.recv(|data| {
+                received.extend_from_slice(data);
+                (data.len(), ())
+            }).unwrap();
+        }
+    };
+    let mut polls = 0;
+    let res = send_all(&mut sockets, client, &buf, |sockets, _| {
+        polls += 1;
+        if polls > 1000 {
+            return Err(());
+        }
+        poll(sockets);
+        Ok(())
+    });
+    assert_eq!(res, Ok(buf.len()));
+    for _ in 0..100 {
+        poll(&mut sockets);
+    }
+    assert_eq!(received, buf);
+}
@@ -21,16 +21,19 @@ fn udp_socket_status(socket: &udp::Socket) -> u8 {
     status
 }
 
+// A listening socket replies to the sender of the last datagram received,
+// while a connected socket always sends to its remote endpoint.
 #[derive(Debug, Clone)]
 pub struct UdpSocket {
     pub handle: SocketHandle,
     pub remote_endpoint: Option<IpEndpoint>,
+    pub is_listening: bool,
 }
 
 impl UdpSocket {
     pub fn size() -> usize {
-        if let Some((_, ref mut device)) = *sys::net::NET.lock() {
-            let mtu = device.capabilities().max_transmission_unit;
+        if let Some(net) = sys::net::NET.lock().first() {
+            let mtu = net.device.capabilities().max_transmission_unit;
             let eth_header = 14;
             let ip_header = 20;
             let udp_header = 8;
@@ -51,23 +54,26 @@ impl UdpSocket {
         let udp_socket = udp::Socket::new(udp_rx_buffer, udp_tx_buffer);
         let handle = sockets.add(udp_socket);
         let remote_endpoint = None;
+        let is_listening = false;
 
         Self {
             handle,
             remote_endpoint,
+            is_listening,
         }
     }
 
     pub fn connect(&mut self, addr: IpAddress, port: u16) -> Result<(), ()> {
         let timeout = 5.0;
         let started = sys::clock::realtime();
-        if let Some((ref mut iface, ref mut device)) = *sys::net::NET.lock() {
+        let mut net = sys::net::NET.lock();
+        if !net.is_empty() {
             loop {
                 if sys::clock::realtime() - started > timeout {
                     return Err(());
                 }
                 let mut sockets = SOCKETS.lock();
-                iface.poll(sys::net::time(), device, &mut sockets);
+                sys::net::poll(&mut net, &mut sockets);
                 let socket = sockets.get_mut::<udp::Socket>(self.handle);
 
                 if !socket.is_open() {
@@ -76,7 +82,7 @@ impl UdpSocket {
                     break;
                 }
 
-                if let Some(d) = iface.poll_delay(sys::net::time(), &sockets) {
+                if let Some(d) = sys::net::poll_delay(&mut net, &sockets) {
                     wait(d);
                 }
                 sys::time::halt();
@@ -86,12 +92,51 @@ impl UdpSocket {
         Ok(())
     }
 
-    pub fn listen(&mut self, _port: u16) -> Result<(), ()> {
-        todo!()
+    pub fn listen(&mut self, port: u16) -> Result<(), ()> {
+        let mut sockets = SOCKETS.lock();
+        let socket = sockets.get_mut::<udp::Socket>(self.handle);
+        if socket.is_open() {
+            return Err(());
+        }
+        let local_endpoint = IpListenEndpoint::from(port);
+        socket.bind(local_endpoint).map_err(|_| ())?;
+        self.is_listening = true;
+        Ok(())
     }
 
-    pub fn accept(&mut self) -> Result<IpAddress, ()> {
-        todo!()
+    // Wait for the first datagram received by a listening socket and return
+    // a copy of the socket connected to its sender, without removing the
+    // datagram from the socket.
+    pub fn accept(&mut self) -> Result<(Self, IpAddress), ()> {
+        let timeout = 5.0;
+        let started = sys::clock::realtime();
+        let mut net = sys::net::NET.lock();
+        if !net.is_empty() {
+            loop {
+                if sys::clock::realtime() - started > timeout {
+                    return Err(());
+                }
+                let mut sockets = SOCKETS.lock();
+                sys::net::poll(&mut net, &mut sockets);
+                let socket = sockets.get_mut::<udp::Socket>(self.handle);
+
+                if let Ok((_, meta)) = socket.peek() {
+                    let connection = Self {
+                        handle: self.handle,
+                        remote_endpoint: Some(meta.endpoint),
+                        is_listening: false,
+                    };
+                    return Ok((connection, meta.endpoint.addr));
+                }
+
+                if let Some(d) = sys::net::poll_delay(&mut net, &sockets) {
+                    wait(d);
+                }
+                sys::time::halt();
+            }
+        } else {
+            Err(())
+        }
     }
 }
 
@@ -99,14 +144,15 @@ impl FileIO for UdpSocket {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, ()> {
         let timeout = 5.0;
         let started = sys::clock::realtime();
-        if let Some((ref mut iface, ref mut device)) = *sys::net::NET.lock() {
+        let mut net = sys::net::NET.lock();
+        if !net.is_empty() {
             let bytes;
             let mut sockets = SOCKETS.lock();
             loop {
                 if sys::clock::realtime() - started > timeout {
                     return Err(());
                 }
-                iface.poll(sys::net::time(), device, &mut sockets);
+                sys::net::poll(&mut net, &mut sockets);
                 let socket = sockets.get_mut::<udp::Socket>(self.handle);
 
                 if buf.len() == 1 {
@@ -116,10 +162,14 @@ impl FileIO for UdpSocket {
                 }
 
                 if socket.can_recv() {
-                    (bytes, _) = socket.recv_slice(buf).map_err(|_| ())?;
+                    let meta;
+                    (bytes, meta) = socket.recv_slice(buf).map_err(|_| ())?;
+                    if self.is_listening {
+                        self.remote_endpoint = Some(meta.endpoint);
+                    }
                     break;
                 }
-                if let Some(d) = iface.poll_delay(sys::net::time(), &sockets) {
+                if let Some(d) = sys::net::poll_delay(&mut net, &sockets) {
                     wait(d);
                 }
                 sys::time::halt();
@@ -134,13 +184,14 @@ impl FileIO for UdpSocket {
         let timeout = 5.0;
         let started = sys::clock::realtime();
         let mut sent = false;
-        if let Some((ref mut iface, ref mut device)) = *sys::net::NET.lock() {
+        let mut net = sys::net::NET.lock();
+        if !net.is_empty() {
             let mut sockets = SOCKETS.lock();
             loop {
                 if sys::clock::realtime() - started > timeout {
                     return Err(());
                 }
-                iface.poll(sys::net::time(), device, &mut sockets);
+                sys::net::poll(&mut net, &mut sockets);
                 let socket = sockets.get_mut::<udp::Socket>(self.handle);
 
                 if sent {
@@ -157,7 +208,7 @@ impl FileIO for UdpSocket {
                     sent = true; // Break after next poll
                 }
 
-                if let Some(d) = iface.poll_delay(sys::net::time(), &sockets) {
+                if let Some(d) = sys::net::poll_delay(&mut net, &sockets) {
                     wait(d);
                 }
                 sys::time::halt();
@@ -170,10 +221,11 @@ impl FileIO for UdpSocket {
 
     fn close(&mut self) {
         let mut closed = false;
-        if let Some((ref mut iface, ref mut device)) = *sys::net::NET.lock() {
+        let mut net = sys::net::NET.lock();
+        if !net.is_empty() {
             let mut sockets = SOCKETS.lock();
             loop {
-                iface.poll(sys::net::time(), device, &mut sockets);
+                sys::net::poll(&mut net, &mut sockets);
                 let socket = sockets.get_mut::<udp::Socket>(self.handle);
 
                 if closed {
@@ -182,7 +234,7 @@ impl FileIO for UdpSocket {
                 socket.close();
                 closed = true;
 
-                if let Some(d) = iface.poll_delay(sys::net::time(), &sockets) {
+                if let Some(d) = sys::net::poll_delay(&mut net, &sockets) {
                     wait(d);
                 }
                 sys::time::halt();
@@ -191,9 +243,10 @@ impl FileIO for UdpSocket {
     }
 
     fn poll(&mut self, event: IO) -> bool {
-        if let Some((ref mut iface, ref mut device)) = *sys::net::NET.lock() {
+        let mut net = sys::net::NET.lock();
+        if !net.is_empty() {
             let mut sockets = SOCKETS.lock();
-            iface.poll(sys::net::time(), device, &mut sockets);
+            sys::net::poll(&mut net, &mut sockets);
             let socket = sockets.get_mut::<udp::Socket>(self.handle);
 
             match event {
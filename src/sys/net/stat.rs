@@ -0,0 +1,190 @@
+use crate::api::fs::{FileIO, IO};
+use crate::sys;
+use crate::sys::fs::{Device, Resource};
+use crate::sys::net::socket::SOCKETS;
+use crate::sys::net::EthernetDeviceIO;
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use smoltcp::iface::SocketHandle;
+use smoltcp::socket::{tcp, Socket};
+
+// Copy as many whole lines as possible into the buffer
+fn write_lines(lines: &[String], buf: &mut [u8]) -> usize {
+    let mut n = 0;
+    for line in lines {
+        let m = line.len() + 1;
+        if n + m > buf.len() {
+            break;
+        }
+        buf[n..n + m - 1].copy_from_slice(line.as_bytes());
+        buf[n + m - 1] = b'\n';
+        n += m;
+    }
+    n
+}
+
+// Return the ID of the process owning a socket, with its resource
+fn owner(handle: SocketHandle) -> Option<(usize, Device)> {
+    sys::process::find_handle(|resource| match resource {
+        Resource::Device(Device::TcpSocket(dev)) => dev.handle == handle,
+        Resource::Device(Device::UdpSocket(dev)) => dev.handle == handle,
+        Resource::Device(Device::IcmpSocket(dev)) => dev.handle == handle,
+        _ => false,
+    }).and_then(|(id, resource)| match resource {
+        Resource::Device(dev) => Some((id, dev)),
+        _ => None,
+    })
+}
+
+// A table of the open sockets, with a line for each socket made of its
+// protocol, its local and remote addresses, its state, and the ID of the
+// process owning it:
+//
+//     tcp 10.0.2.15:49152 10.0.2.2:80 ESTABLISHED 1
+//     udp *:68 * LISTEN 0
+#[derive(Debug, Clone, Default)]
+pub struct NetSockets;
+
+impl NetSockets {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn size() -> usize {
+        4096
+    }
+
+    pub fn lines() -> Vec<String> {
+        let mut lines = Vec::new();
+        let sockets = SOCKETS.lock();
+        for (handle, socket) in sockets.iter() {
+            let owner = owner(handle);
+            let id = owner.as_ref().map_or("-".into(), |(id, _)| {
+                id.to_string()
+            });
+            let dev = owner.map(|(_, dev)| dev);
+            let line = match socket {
+                Socket::Tcp(socket) if socket.state() != tcp::State::Closed => {
+                    let local = match (socket.local_endpoint(), &dev) {
+                        (Some(endpoint), _) => endpoint.to_string(),
+                        (None, Some(Device::TcpSocket(dev))) => {
+                            match dev.listen_port {
+                                Some(port) => format!("*:{}", port),
+                                None => "*".into(),
+                            }
+                        }
+                        _ => "*".into(),
+                    };
+                    let remote = socket.remote_endpoint().map_or(
+                        "*".into(), |endpoint| endpoint.to_string()
+                    );
+                    let state = socket.state();
+                    format!("tcp {} {} {} {}", local, remote, state, id)
+                }
+                Socket::Udp(socket) if socket.is_open() => {
+                    let local = socket.endpoint();
+                    let (remote, state) = match &dev {
+                        Some(Device::UdpSocket(dev)) => (
+                            dev.remote_endpoint.map_or("*".into(), |e| {
+                                e.to_string()
+                            }),
+                            if dev.is_listening { "LISTEN" } else { "OPEN" },
+                        ),
+                        _ => ("*".into(), "OPEN"),
+                    };
+                    format!("udp {} {} {} {}", local, remote, state, id)
+                }
+                Socket::Icmp(socket) if socket.is_open() => {
+                    let remote = match &dev {
+                        Some(Device::IcmpSocket(dev)) => {
+                            dev.remote_addr.map_or("*".into(), |addr| {
+                                addr.to_string()
+                            })
+                        }
+                        _ => "*".into(),
+                    };
+                    format!("icmp * {} OPEN {}", remote, id)
+                }
+                _ => continue,
+            };
+            lines.push(line);
+        }
+        lines
+    }
+}
+
+impl FileIO for NetSockets {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ()> {
+        Ok(write_lines(&Self::lines(), buf))
+    }
+
+    fn write(&mut self, _buf: &[u8]) -> Result<usize, ()> {
+        Err(())
+    }
+
+    fn close(&mut self) {}
+
+    fn poll(&mut self, event: IO) -> bool {
+        match event {
+            IO::Read => true,
+            IO::Write => false,
+        }
+    }
+}
+
+// A table of the network interfaces, with a line for each interface made of
+// its name followed by the counters of received packets, bytes, errors, and
+// dropped packets, and the counters of transmitted packets, bytes, and
+// dropped packets:
+//
+//     eth0 42 4096 0 0 24 2048 0
+#[derive(Debug, Clone, Default)]
+pub struct NetIfaces;
+
+impl NetIfaces {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn size() -> usize {
+        1024
+    }
+
+    pub fn lines() -> Vec<String> {
+        sys::net::NET.lock().iter().map(|net| {
+            let stats = net.device.stats();
+            format!(
+                "{} {} {} {} {} {} {} {}",
+                net.name,
+                stats.rx_packets_count(),
+                stats.rx_bytes_count(),
+                stats.rx_errors_count(),
+                stats.rx_dropped_count(),
+                stats.tx_packets_count(),
+                stats.tx_bytes_count(),
+                stats.tx_dropped_count()
+            )
+        }).collect()
+    }
+}
+
+impl FileIO for NetIfaces {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ()> {
+        Ok(write_lines(&Self::lines(), buf))
+    }
+
+    fn write(&mut self, _buf: &[u8]) -> Result<usize, ()> {
+        Err(())
+    }
+
+    fn close(&mut self) {}
+
+    fn poll(&mut self, event: IO) -> bool {
+        match event {
+            IO::Read => true,
+            IO::Write => false,
+        }
+    }
+}
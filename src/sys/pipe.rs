@@ -0,0 +1,82 @@
+use crate::api::fs::{FileIO, IO};
+
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use spin::Mutex;
+
+// Maximum number of bytes kept in a pipe
+pub const PIPE_SIZE: usize = 1 << 20;
+
+// A pipe is a buffer shared between the handles cloned from it, with the
+// data written to one of them being read from the others. Reading an empty
+// pipe is the end of the file since the writer has already finished when
+// the reader is running. The writer cannot wait for the reader to make room
+// in the buffer, so a write that doesn't fit in it fails.
+#[derive(Debug, Clone, Default)]
+pub struct Pipe {
+    buf: Arc<Mutex<VecDeque<u8>>>,
+}
+
+impl Pipe {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl FileIO for Pipe {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ()> {
+        let mut data = self.buf.lock();
+        let n = buf.len().min(data.len());
+        for (i, byte) in data.drain(..n).enumerate() {
+            buf[i] = byte;
+        }
+        Ok(n)
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize, ()> {
+        let mut data = self.buf.lock();
+        if data.len() + buf.len() > PIPE_SIZE {
+            return Err(());
+        }
+        data.extend(buf);
+        Ok(buf.len())
+    }
+
+    fn close(&mut self) {}
+
+    fn poll(&mut self, event: IO) -> bool {
+        match event {
+            IO::Read => !self.buf.lock().is_empty(),
+            IO::Write => self.buf.lock().len() < PIPE_SIZE,
+        }
+    }
+}
+
+#[test_case]
+fn test_pipe() {
+    let mut writer = Pipe::new();
+    let mut reader = writer.clone();
+    assert_eq!(writer.write(b"hello"), Ok(5));
+    assert!(reader.poll(IO::Read));
+    let mut buf = [0; 3];
+    assert_eq!(reader.read(&mut buf), Ok(3));
+    assert_eq!(&buf, b"hel");
+    assert_eq!(reader.read(&mut buf), Ok(2));
+    assert_eq!(&buf[..2], b"lo");
+    assert_eq!(reader.read(&mut buf), Ok(0));
+    assert!(!reader.poll(IO::Read));
+}
+
+#[test_case]
+fn test_pipe_size() {
+    let mut writer = Pipe::new();
+    let mut reader = writer.clone();
+    let buf = alloc::vec![0; PIPE_SIZE / 2];
+    assert_eq!(writer.write(&buf), Ok(buf.len()));
+    assert_eq!(writer.write(&buf), Ok(buf.len()));
+    assert!(!writer.poll(IO::Write));
+    assert_eq!(writer.write(b"a"), Err(()));
+    let mut buf = [0; 1];
+    assert_eq!(reader.read(&mut buf), Ok(1));
+    assert_eq!(writer.write(b"a"), Ok(1));
+}
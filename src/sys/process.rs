@@ -6,6 +6,7 @@ use crate::sys::gdt::GDT;
 
 use alloc::boxed::Box;
 use alloc::collections::btree_map::BTreeMap;
+use alloc::format;
 use alloc::string::{String, ToString};
 use alloc::sync::Arc;
 use alloc::vec::Vec;
@@ -23,8 +24,8 @@ use x86_64::structures::paging::{
 };
 use x86_64::VirtAddr;
 
-const ELF_MAGIC: [u8; 4] = [0x7F, b'E', b'L', b'F'];
-const BIN_MAGIC: [u8; 4] = [0x7F, b'B', b'I', b'N'];
+pub const ELF_MAGIC: [u8; 4] = [0x7F, b'E', b'L', b'F'];
+pub const BIN_MAGIC: [u8; 4] = [0x7F, b'B', b'I', b'N'];
 
 const MAX_HANDLES: usize = 64;
 const MAX_PROCS: usize = 4; // TODO: Increase this
@@ -65,6 +66,8 @@ pub struct ProcessData {
     env: BTreeMap<String, String>,
     dir: String,
     user: Option<String>,
+    uid: u16,
+    gid: u16,
     handles: [Option<Box<Resource>>; MAX_HANDLES],
 }
 
@@ -84,7 +87,8 @@ impl ProcessData {
         handles[2] = Some(Box::new(stderr));
         handles[3] = Some(Box::new(stdnull));
 
-        Self { env, dir, user, handles }
+        let (uid, gid) = (0, 0); // Root
+        Self { env, dir, user, uid, gid, handles }
     }
 }
 
@@ -120,22 +124,42 @@ pub fn user() -> Option<String> {
     proc.data.user.clone()
 }
 
+pub fn uid() -> u16 {
+    let table = PROCESS_TABLE.read();
+    let proc = &table[id()];
+    proc.data.uid
+}
+
+pub fn gid() -> u16 {
+    let table = PROCESS_TABLE.read();
+    let proc = &table[id()];
+    proc.data.gid
+}
+
 pub fn set_env(key: &str, val: &str) {
     let mut table = PROCESS_TABLE.write();
     let proc = &mut table[id()];
     proc.data.env.insert(key.into(), val.into());
 }
 
+pub fn delete_env(key: &str) {
+    let mut table = PROCESS_TABLE.write();
+    let proc = &mut table[id()];
+    proc.data.env.remove(key);
+}
+
 pub fn set_dir(dir: &str) {
     let mut table = PROCESS_TABLE.write();
     let proc = &mut table[id()];
     proc.data.dir = dir.into();
 }
 
-pub fn set_user(user: &str) {
+pub fn set_user(user: &str, uid: u16, gid: u16) {
     let mut table = PROCESS_TABLE.write();
     let proc = &mut table[id()];
-    proc.data.user = Some(user.into())
+    proc.data.user = Some(user.into());
+    proc.data.uid = uid;
+    proc.data.gid = gid;
 }
 
 pub fn create_handle(file: Resource) -> Result<usize, ()> {
@@ -177,6 +201,47 @@ pub fn handles() -> Vec<Option<Box<Resource>>> {
     proc.data.handles.to_vec()
 }
 
+// Return the ID of the first running process having a handle to a resource
+// matching the predicate, with a copy of this resource
+pub fn find_handle<F>(predicate: F) -> Option<(usize, Resource)>
+where
+    F: Fn(&Resource) -> bool,
+{
+    let table = PROCESS_TABLE.read();
+    let n = MAX_PID.load(Ordering::SeqCst);
+    for proc in table.iter().take(n) {
+        for resource in proc.data.handles.iter().flatten() {
+            if predicate(resource) {
+                return Some((proc.id, (**resource).clone()));
+            }
+        }
+    }
+    None
+}
+
+// Status of a running process, with a line for each field and its value
+pub fn status(id: usize) -> Option<String> {
+    if id >= MAX_PID.load(Ordering::SeqCst) {
+        return None;
+    }
+    let table = PROCESS_TABLE.read();
+    let proc = &table[id];
+    let user = proc.data.user.as_deref().unwrap_or("-");
+    let handles = proc.data.handles.iter().flatten().count();
+    Some(format!(
+        "Pid: {}\nPPid: {}\nUser: {}\nUid: {}\nGid: {}\nDir: {}\n\
+         Handles: {}\nCode: {:#X}\n",
+        proc.id,
+        proc.parent_id,
+        user,
+        proc.data.uid,
+        proc.data.gid,
+        proc.data.dir,
+        handles,
+        proc.code_addr
+    ))
+}
+
 pub fn code_addr() -> u64 {
     let table = PROCESS_TABLE.read();
     let proc = &table[id()];
@@ -432,33 +497,28 @@ impl Process {
 
         let heap_addr = self.code_addr + (self.stack_addr - self.code_addr) / 2;
 
-        sys::allocator::alloc_pages(&mut mapper, heap_addr, 1).
-            expect("proc heap alloc");
-
         let args_ptr = ptr_from_addr(args_ptr as u64) as usize;
         let args: &[&str] = unsafe {
             core::slice::from_raw_parts(args_ptr as *const &str, args_len)
         };
-        let mut addr = heap_addr;
-        let vec: Vec<&str> = args.iter().map(|arg| {
-            let ptr = addr as *mut u8;
-            addr += arg.len() as u64;
-            unsafe {
-                let s = core::slice::from_raw_parts_mut(ptr, arg.len());
-                s.copy_from_slice(arg.as_bytes());
-                core::str::from_utf8_unchecked(s)
-            }
+
+        // The environment block is a list of `KEY=VALUE` strings copied
+        // after the arguments on the heap of the process
+        let env: Vec<String> = self.data.env.iter().map(|(key, val)| {
+            format!("{}={}", key, val)
         }).collect();
-        let align = core::mem::align_of::<&str>() as u64;
-        addr += align - (addr % align);
-        let args = vec.as_slice();
-        let ptr = addr as *mut &str;
-        let args: &[&str] = unsafe {
-            let s = core::slice::from_raw_parts_mut(ptr, args.len());
-            s.copy_from_slice(args);
-            s
-        };
-        let args_ptr = args.as_ptr() as u64;
+        let env: Vec<&str> = env.iter().map(String::as_str).collect();
+
+        let ptr_size = core::mem::size_of::<&str>();
+        let size = args.iter().chain(env.iter()).map(|s| {
+            s.len() + ptr_size
+        }).sum::<usize>() + 2 * ptr_size;
+        sys::allocator::alloc_pages(&mut mapper, heap_addr, size).
+            expect("proc heap alloc");
+
+        let mut addr = heap_addr;
+        let (args_ptr, args_len) = copy_strs(&mut addr, args);
+        let (env_ptr, env_len) = copy_strs(&mut addr, &env);
 
         let heap_addr = addr;
         let heap_size = ((self.stack_addr - heap_addr) / 2) as usize;
@@ -486,7 +546,32 @@ impl Process {
                 in(reg) self.code_addr + self.entry_point_addr,
                 in("rdi") args_ptr,
                 in("rsi") args_len,
+                in("rdx") env_ptr,
+                in("rcx") env_len,
             );
         }
     }
 }
+
+// Copy a list of strings at the given address, followed by the list of their
+// references, and return the pointer and the length of the latter.
+fn copy_strs(addr: &mut u64, strs: &[&str]) -> (u64, usize) {
+    let vec: Vec<&str> = strs.iter().map(|s| {
+        let ptr = *addr as *mut u8;
+        *addr += s.len() as u64;
+        unsafe {
+            let buf = core::slice::from_raw_parts_mut(ptr, s.len());
+            buf.copy_from_slice(s.as_bytes());
+            core::str::from_utf8_unchecked(buf)
+        }
+    }).collect();
+    let align = core::mem::align_of::<&str>() as u64;
+    *addr += align - (*addr % align);
+    let ptr = *addr as *mut &str;
+    unsafe {
+        let buf = core::slice::from_raw_parts_mut(ptr, vec.len());
+        buf.copy_from_slice(&vec);
+    }
+    *addr += (vec.len() * core::mem::size_of::<&str>()) as u64;
+    (ptr as u64, vec.len())
+}
@@ -77,6 +77,12 @@ pub fn dispatcher(
             let new_handle = arg2;
             service::dup(old_handle, new_handle) as usize
         }
+        number::SEEK => {
+            let handle = arg1;
+            let offset = arg2 as isize;
+            let whence = arg3;
+            service::seek(handle, offset, whence) as usize
+        }
         number::SPAWN => {
             let path_ptr = sys::process::ptr_from_addr(arg1 as u64);
             let path_len = arg2;
@@ -114,9 +120,9 @@ pub fn dispatcher(
             let ptr = sys::process::ptr_from_addr(arg2 as u64);
             let len = arg3;
             let buf = unsafe { core::slice::from_raw_parts_mut(ptr, len) };
-            if let Ok(addr) = service::accept(handle) {
+            if let Ok((handle, addr)) = service::accept(handle) {
                 buf[0..len].clone_from_slice(addr.as_bytes());
-                0
+                handle
             } else {
                 -1 as isize as usize
             }
@@ -133,6 +139,67 @@ pub fn dispatcher(
             service::free(ptr, size, align);
             0
         }
+        number::PIPE => service::pipe() as usize,
+        number::SYMLINK => {
+            let ptr = sys::process::ptr_from_addr(arg1 as u64);
+            let len = arg2;
+            let target = utf8_from_raw_parts(ptr, len);
+            let ptr = sys::process::ptr_from_addr(arg3 as u64);
+            let len = arg4;
+            let path = utf8_from_raw_parts(ptr, len);
+            service::symlink(target, path) as usize
+        }
+        number::LINK => {
+            let ptr = sys::process::ptr_from_addr(arg1 as u64);
+            let len = arg2;
+            let source = utf8_from_raw_parts(ptr, len);
+            let ptr = sys::process::ptr_from_addr(arg3 as u64);
+            let len = arg4;
+            let path = utf8_from_raw_parts(ptr, len);
+            service::link(source, path) as usize
+        }
+        number::CHMOD => {
+            let ptr = sys::process::ptr_from_addr(arg1 as u64);
+            let len = arg2;
+            let path = utf8_from_raw_parts(ptr, len);
+            let mode = arg3 as u16;
+            service::chmod(path, mode) as usize
+        }
+        number::CHOWN => {
+            let ptr = sys::process::ptr_from_addr(arg1 as u64);
+            let len = arg2;
+            let path = utf8_from_raw_parts(ptr, len);
+            let uid = arg3 as u16;
+            let gid = arg4 as u16;
+            service::chown(path, uid, gid) as usize
+        }
+        number::UTIME => {
+            let ptr = sys::process::ptr_from_addr(arg1 as u64);
+            let len = arg2;
+            let path = utf8_from_raw_parts(ptr, len);
+            let atime = arg3 as u64;
+            let mtime = arg4 as u64;
+            service::utime(path, atime, mtime) as usize
+        }
+        number::READLINK => {
+            let ptr = sys::process::ptr_from_addr(arg1 as u64);
+            let len = arg2;
+            let path = utf8_from_raw_parts(ptr, len);
+            let ptr = sys::process::ptr_from_addr(arg3 as u64);
+            let len = arg4;
+            let buf = unsafe { core::slice::from_raw_parts_mut(ptr, len) };
+            service::read_link(path, buf) as usize
+        }
+        number::RENAME => {
+            let ptr = sys::process::ptr_from_addr(arg1 as u64);
+            let len = arg2;
+            let source = utf8_from_raw_parts(ptr, len);
+            let ptr = sys::process::ptr_from_addr(arg3 as u64);
+            let len = arg4;
+            let dest = utf8_from_raw_parts(ptr, len);
+            service::rename(source, dest) as usize
+        }
+        number::SYNC => service::sync() as usize,
         _ => {
             unimplemented!();
         }
@@ -1,17 +1,27 @@
-pub const EXIT:    usize = 0x1;
-pub const SPAWN:   usize = 0x2;
-pub const READ:    usize = 0x3;
-pub const WRITE:   usize = 0x4;
-pub const OPEN:    usize = 0x5;
-pub const CLOSE:   usize = 0x6;
-pub const INFO:    usize = 0x7;
-pub const DUP:     usize = 0x8;
-pub const DELETE:  usize = 0x9;
-pub const STOP:    usize = 0xA;
-pub const SLEEP:   usize = 0xB;
-pub const POLL:    usize = 0xC;
-pub const CONNECT: usize = 0xD;
-pub const LISTEN:  usize = 0xE;
-pub const ACCEPT:  usize = 0xF;
-pub const ALLOC:   usize = 0x10;
-pub const FREE:    usize = 0x11;
+pub const EXIT:     usize = 0x1;
+pub const SPAWN:    usize = 0x2;
+pub const READ:     usize = 0x3;
+pub const WRITE:    usize = 0x4;
+pub const OPEN:     usize = 0x5;
+pub const CLOSE:    usize = 0x6;
+pub const INFO:     usize = 0x7;
+pub const DUP:      usize = 0x8;
+pub const DELETE:   usize = 0x9;
+pub const STOP:     usize = 0xA;
+pub const SLEEP:    usize = 0xB;
+pub const POLL:     usize = 0xC;
+pub const CONNECT:  usize = 0xD;
+pub const LISTEN:   usize = 0xE;
+pub const ACCEPT:   usize = 0xF;
+pub const ALLOC:    usize = 0x10;
+pub const FREE:     usize = 0x11;
+pub const PIPE:     usize = 0x12;
+pub const SYMLINK:  usize = 0x13;
+pub const READLINK: usize = 0x14;
+pub const LINK:     usize = 0x15;
+pub const CHMOD:    usize = 0x16;
+pub const CHOWN:    usize = 0x17;
+pub const UTIME:    usize = 0x18;
+pub const SEEK:     usize = 0x19;
+pub const RENAME:   usize = 0x1A;
+pub const SYNC:     usize = 0x1B;
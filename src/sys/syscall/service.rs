@@ -3,7 +3,10 @@ use crate::api::process::ExitCode;
 use crate::sys;
 use crate::sys::fs::Device;
 use crate::sys::fs::FileInfo;
+use crate::sys::fs::Permission;
 use crate::sys::fs::Resource;
+use crate::sys::fs::SeekFrom;
+use crate::sys::pipe::Pipe;
 use crate::sys::process::Process;
 
 use alloc::vec;
@@ -17,7 +20,10 @@ pub fn exit(code: ExitCode) -> ExitCode {
 }
 
 pub fn sleep(seconds: f64) {
-    sys::time::sleep(seconds);
+    let start = sys::clock::uptime();
+    while sys::clock::uptime() - start < seconds {
+        sys::time::idle();
+    }
 }
 
 pub fn delete(path: &str) -> isize {
@@ -62,6 +68,24 @@ pub fn dup(old_handle: usize, new_handle: usize) -> isize {
     -1
 }
 
+// Move the offset of a file relative to its start (0), the current offset
+// (1), or its end (2), and return the new offset
+pub fn seek(handle: usize, offset: isize, whence: usize) -> isize {
+    let pos = match whence {
+        0 if offset >= 0 => SeekFrom::Start(offset as u32),
+        1 => SeekFrom::Current(offset as i32),
+        2 => SeekFrom::End(offset as i32),
+        _ => return -1,
+    };
+    if let Some(mut file) = sys::process::handle(handle) {
+        if let Ok(offset) = file.seek(pos) {
+            sys::process::update_handle(handle, *file);
+            return offset as isize;
+        }
+    }
+    -1
+}
+
 pub fn read(handle: usize, buf: &mut [u8]) -> isize {
     if let Some(mut file) = sys::process::handle(handle) {
         if let Ok(bytes) = file.read(buf) {
@@ -94,6 +118,11 @@ pub fn spawn(path: &str, args_ptr: usize, args_len: usize) -> ExitCode {
         Ok(path) => path,
         Err(_) => return ExitCode::OpenError,
     };
+    if let Some(info) = sys::fs::info(&path) {
+        if !Permission::Exec.is_granted(&info) {
+            return ExitCode::ExecError;
+        }
+    }
     if let Some(mut file) = sys::fs::File::open(&path) {
         let mut buf = vec![0; file.size()];
         if let Ok(bytes) = file.read(&mut buf) {
@@ -114,11 +143,13 @@ pub fn spawn(path: &str, args_ptr: usize, args_len: usize) -> ExitCode {
 pub fn stop(code: usize) -> usize {
     match code {
         0xCAFE => { // Reboot
+            sys::fs::sync().ok();
             unsafe {
                 asm!("xor rax, rax", "mov cr3, rax");
             }
         }
         0xDEAD => { // Halt
+            sys::fs::sync().ok();
             sys::process::exit();
             sys::acpi::shutdown();
         }
@@ -149,6 +180,9 @@ pub fn connect(handle: usize, addr: IpAddress, port: u16) -> isize {
             Resource::Device(Device::UdpSocket(ref mut dev)) => {
                 dev.connect(addr, port)
             }
+            Resource::Device(Device::IcmpSocket(ref mut dev)) => {
+                dev.connect(addr, port) // The port is the echo identifier
+            }
             _ => Err(()),
         };
         if res.is_ok() {
@@ -160,26 +194,42 @@ pub fn connect(handle: usize, addr: IpAddress, port: u16) -> isize {
 }
 
 pub fn listen(handle: usize, port: u16) -> isize {
-    if let Some(file) = sys::process::handle(handle) {
+    if let Some(mut file) = sys::process::handle(handle) {
         let res = match *file {
-            Resource::Device(Device::TcpSocket(mut dev)) => dev.listen(port),
-            Resource::Device(Device::UdpSocket(mut dev)) => dev.listen(port),
+            Resource::Device(Device::TcpSocket(ref mut dev)) => {
+                dev.listen(port)
+            }
+            Resource::Device(Device::UdpSocket(ref mut dev)) => {
+                dev.listen(port)
+            }
             _ => Err(()),
         };
         if res.is_ok() {
+            sys::process::update_handle(handle, *file);
             return 0;
         }
     }
     -1
 }
 
-pub fn accept(handle: usize) -> Result<IpAddress, ()> {
-    if let Some(file) = sys::process::handle(handle) {
-        return match *file {
-            Resource::Device(Device::TcpSocket(mut dev)) => dev.accept(),
-            Resource::Device(Device::UdpSocket(mut dev)) => dev.accept(),
+pub fn accept(handle: usize) -> Result<(usize, IpAddress), ()> {
+    if let Some(mut file) = sys::process::handle(handle) {
+        let res = match *file {
+            Resource::Device(Device::TcpSocket(ref mut dev)) => {
+                dev.accept().map(|(dev, addr)| (Device::TcpSocket(dev), addr))
+            }
+            Resource::Device(Device::UdpSocket(ref mut dev)) => {
+                dev.accept().map(|(dev, addr)| (Device::UdpSocket(dev), addr))
+            }
             _ => Err(()),
         };
+        if let Ok((dev, addr)) = res {
+            sys::process::update_handle(handle, *file);
+            let resource = Resource::Device(dev);
+            if let Ok(handle) = sys::process::create_handle(resource) {
+                return Ok((handle, addr));
+            }
+        }
     }
     Err(())
 }
@@ -197,3 +247,115 @@ pub fn free(ptr: *mut u8, size: usize, align: usize) {
         unsafe { sys::process::free(ptr, layout) };
     }
 }
+
+pub fn pipe() -> isize {
+    let resource = Resource::Device(Device::Pipe(Pipe::new()));
+    if let Ok(handle) = sys::process::create_handle(resource) {
+        return handle as isize;
+    }
+    -1
+}
+
+pub fn symlink(target: &str, path: &str) -> isize {
+    let path = match sys::fs::canonicalize(path) {
+        Ok(path) => path,
+        Err(_) => return -1,
+    };
+    if sys::fs::symlink(target, &path).is_ok() {
+        0
+    } else {
+        -1
+    }
+}
+
+pub fn link(source: &str, path: &str) -> isize {
+    let source = match sys::fs::canonicalize(source) {
+        Ok(source) => source,
+        Err(_) => return -1,
+    };
+    let path = match sys::fs::canonicalize(path) {
+        Ok(path) => path,
+        Err(_) => return -1,
+    };
+    if sys::fs::link(&source, &path).is_ok() {
+        0
+    } else {
+        -1
+    }
+}
+
+pub fn chmod(path: &str, mode: u16) -> isize {
+    let path = match sys::fs::canonicalize(path) {
+        Ok(path) => path,
+        Err(_) => return -1,
+    };
+    if sys::fs::chmod(&path, mode).is_ok() {
+        0
+    } else {
+        -1
+    }
+}
+
+pub fn chown(path: &str, uid: u16, gid: u16) -> isize {
+    let path = match sys::fs::canonicalize(path) {
+        Ok(path) => path,
+        Err(_) => return -1,
+    };
+    if sys::fs::chown(&path, uid, gid).is_ok() {
+        0
+    } else {
+        -1
+    }
+}
+
+pub fn rename(source: &str, dest: &str) -> isize {
+    let source = match sys::fs::canonicalize(source) {
+        Ok(source) => source,
+        Err(_) => return -1,
+    };
+    let dest = match sys::fs::canonicalize(dest) {
+        Ok(dest) => dest,
+        Err(_) => return -1,
+    };
+    if sys::fs::rename(&source, &dest).is_ok() {
+        0
+    } else {
+        -1
+    }
+}
+
+pub fn sync() -> isize {
+    if sys::fs::sync().is_ok() {
+        0
+    } else {
+        -1
+    }
+}
+
+pub fn utime(path: &str, atime: u64, mtime: u64) -> isize {
+    let path = match sys::fs::canonicalize(path) {
+        Ok(path) => path,
+        Err(_) => return -1,
+    };
+    if sys::fs::utime(&path, atime, mtime).is_ok() {
+        0
+    } else {
+        -1
+    }
+}
+
+// Copy the target of a symbolic link into the buffer and return its length,
+// which is bigger than the buffer if the target was truncated
+pub fn read_link(path: &str, buf: &mut [u8]) -> isize {
+    let path = match sys::fs::canonicalize(path) {
+        Ok(path) => path,
+        Err(_) => return -1,
+    };
+    if let Some(target) = sys::fs::read_link(&path) {
+        let n = target.len().min(buf.len());
+        buf[0..n].copy_from_slice(&target.as_bytes()[0..n]);
+        target.len() as isize
+    } else {
+        -1
+    }
+}
@@ -44,6 +44,16 @@ fn rdtsc() -> u64 {
     }
 }
 
+// The time spent waiting for the user or for the end of the sleep of a
+// process is used for the background work of the kernel, like writing back
+// the blocks cached by the filesystems or renewing the DHCP lease, because
+// no other kernel code is running at that point
+pub fn idle() {
+    sys::fs::sync_if_due();
+    sys::net::poll_if_due();
+    halt();
+}
+
 pub fn sleep(seconds: f64) {
     let start = sys::clock::uptime();
     while sys::clock::uptime() - start < seconds {
@@ -0,0 +1,111 @@
+use crate::api::console::Style;
+use crate::api::process::ExitCode;
+use crate::sys;
+use crate::sys::net::arp;
+
+use alloc::format;
+use core::str::FromStr;
+use smoltcp::wire::{EthernetAddress, Ipv4Address};
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    match *args.get(1).unwrap_or(&"list") {
+        "-h" | "--help" => {
+            help();
+            Ok(())
+        }
+        "list" if args.len() <= 2 => {
+            list();
+            Ok(())
+        }
+        "add" if args.len() == 4 => {
+            let ip = parse_ip(args[2])?;
+            let mac = match EthernetAddress::from_str(args[3]) {
+                Ok(mac) if mac.is_unicast() => mac,
+                _ => {
+                    error!("Could not parse MAC address '{}'", args[3]);
+                    return Err(ExitCode::UsageError);
+                }
+            };
+            arp::add(ip, mac);
+            flush_cache();
+            Ok(())
+        }
+        "delete" if args.len() == 3 => {
+            let ip = parse_ip(args[2])?;
+            if arp::remove(ip) {
+                flush_cache();
+                Ok(())
+            } else {
+                error!("Could not find '{}'", ip);
+                Err(ExitCode::Failure)
+            }
+        }
+        "flush" if args.len() == 2 => {
+            arp::flush();
+            flush_cache();
+            Ok(())
+        }
+        _ => {
+            help();
+            Err(ExitCode::UsageError)
+        }
+    }
+}
+
+fn list() {
+    let now = sys::clock::uptime();
+    for (ip, neighbor) in arp::neighbors() {
+        let age = if neighbor.is_static {
+            "static".into()
+        } else {
+            format!("{:.0}s", now - neighbor.updated_at)
+        };
+        println!("{:15} {} {}", ip, neighbor.mac, age);
+    }
+}
+
+// Updating the addresses of an interface flushes the neighbor cache of
+// smoltcp that cannot be accessed otherwise
+fn flush_cache() {
+    for net in sys::net::NET.lock().iter_mut() {
+        net.iface.update_ip_addrs(|_| {});
+    }
+}
+
+fn parse_ip(s: &str) -> Result<Ipv4Address, ExitCode> {
+    match Ipv4Address::from_str(s) {
+        Ok(ip) if ip.is_unicast() => Ok(ip),
+        _ => {
+            error!("Could not parse IP address '{}'", s);
+            Err(ExitCode::UsageError)
+        }
+    }
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} arp {}<command>{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Commands:{}", csi_title, csi_reset);
+    println!(
+        "  {}list{}                Display the neighbor table",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {}add <ip> <mac>{}      Add a static entry",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {}delete <ip>{}         Delete an entry",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {}flush{}               Delete the entries learned from the network",
+        csi_option, csi_reset
+    );
+}
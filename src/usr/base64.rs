@@ -1,19 +1,86 @@
+use crate::api::console::Style;
+use crate::api::fs;
+use crate::api::io;
 use crate::api::process::ExitCode;
+use crate::api::syscall;
 
 use alloc::string::String;
+use alloc::vec;
 use alloc::vec::Vec;
 
+// Encoded lines are wrapped like in MIME
+const LINE_LENGTH: usize = 76;
+
 pub fn main(args: &[&str]) -> Result<(), ExitCode> {
-    if args.len() != 2 {
-        Err(ExitCode::UsageError)
+    let mut decode_mode = false;
+    let mut path = None;
+    for arg in &args[1..] {
+        match *arg {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-d" | "--decode" => decode_mode = true,
+            _ => {
+                if path.is_some() {
+                    help();
+                    return Err(ExitCode::UsageError);
+                }
+                path = Some(*arg);
+            }
+        }
+    }
+
+    let input = if let Some(path) = path {
+        if let Ok(buf) = fs::read_to_bytes(path) {
+            buf
+        } else {
+            error!("Could not read '{}'", path);
+            return Err(ExitCode::Failure);
+        }
+    } else {
+        io::stdin().read_to_string().into_bytes()
+    };
+
+    if decode_mode {
+        if let Some(buf) = decode_text(&input) {
+            syscall::write(1, &buf);
+            Ok(())
+        } else {
+            error!("Could not decode invalid input");
+            Err(ExitCode::DataError)
+        }
     } else {
-        let buf = encode(args[1].as_bytes());
-        let encoded = String::from_utf8(buf).unwrap();
-        println!("{}", encoded);
+        print!("{}", encode_text(&input));
         Ok(())
     }
 }
 
+// Encode with padding and wrap the lines
+fn encode_text(s: &[u8]) -> String {
+    let mut buf = vec![0; (s.len() + 2) / 3 * 4];
+    let n = base64::encode_config_slice(s, base64::STANDARD, &mut buf);
+    buf.truncate(n);
+    let mut res = String::new();
+    for line in buf.chunks(LINE_LENGTH) {
+        res.push_str(&String::from_utf8_lossy(line));
+        res.push('\n');
+    }
+    res
+}
+
+// Decode with or without padding and ignore whitespaces
+fn decode_text(s: &[u8]) -> Option<Vec<u8>> {
+    let s: Vec<u8> = s.iter().copied().filter(|b| {
+        !b.is_ascii_whitespace() && *b != b'='
+    }).collect();
+    let mut buf = vec![0; s.len()];
+    let config = base64::STANDARD_NO_PAD;
+    let n = base64::decode_config_slice(&s, config, &mut buf).ok()?;
+    buf.truncate(n);
+    Some(buf)
+}
+
 pub fn encode(s: &[u8]) -> Vec<u8> {
     let mut buf: Vec<u8> = Vec::new();
     buf.resize(s.len() * 4 / 3 + 4, 0); // Resize to base64 + padding
@@ -33,3 +100,35 @@ pub fn decode(s: &[u8]) -> Vec<u8> {
     buf.resize(bytes_written, 0);
     buf
 }
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} base64 {}<options> [<path>]{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-d{1}, {0}--decode{1}   Decode data",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_base64() {
+    assert_eq!(encode_text(b"Hello, World!"), "SGVsbG8sIFdvcmxkIQ==\n");
+    assert_eq!(encode_text(b""), "");
+    assert_eq!(encode_text(&[0; 60]).lines().count(), 2);
+
+    let decoded = decode_text(b"SGVsbG8sIFdvcmxkIQ==\n");
+    assert_eq!(decoded, Some(b"Hello, World!".to_vec()));
+    let decoded = decode_text(b"SGVsbG8s\nIFdvcmxkIQ");
+    assert_eq!(decoded, Some(b"Hello, World!".to_vec()));
+    assert_eq!(decode_text(b"SGVsbG8*"), None);
+
+    let buf = [0, 1, 2, 254, 255];
+    assert_eq!(decode_text(encode_text(&buf).as_bytes()), Some(buf.to_vec()));
+}
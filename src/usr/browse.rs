@@ -0,0 +1,941 @@
+use crate::api::console;
+use crate::api::console::Style;
+use crate::api::process::ExitCode;
+use crate::api::syscall;
+use crate::api::tls::TlsStream;
+use crate::sys;
+use crate::sys::fs::OpenFlag;
+use crate::usr;
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp;
+use core::fmt;
+use core::str::FromStr;
+use smoltcp::wire::IpAddress;
+
+const MAX_REDIRECTS: usize = 5;
+const MAX_HISTORY: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Scheme {
+    Gemini,
+    Gopher,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Url {
+    scheme: Scheme,
+    host: String,
+    port: u16,
+    path: String,
+    query: Option<String>,
+}
+
+impl Url {
+    fn parse(url: &str) -> Option<Self> {
+        let (scheme, url) = if let Some(url) = url.strip_prefix("gemini://") {
+            (Scheme::Gemini, url)
+        } else if let Some(url) = url.strip_prefix("gopher://") {
+            (Scheme::Gopher, url)
+        } else if url.contains("://") {
+            return None;
+        } else {
+            (Scheme::Gemini, url)
+        };
+        let (server, path) = match url.find('/') {
+            Some(i) => url.split_at(i),
+            None => (url, "/"),
+        };
+        let default_port = match scheme {
+            Scheme::Gemini => 1965,
+            Scheme::Gopher => 70,
+        };
+        let (host, port) = match server.split_once(':') {
+            Some((host, port)) => (host, port.parse().ok()?),
+            None => (server, default_port),
+        };
+        if host.is_empty() {
+            return None;
+        }
+        let separator = match scheme {
+            Scheme::Gemini => "?",
+            Scheme::Gopher => "%09",
+        };
+        let (path, query) = match path.split_once(separator) {
+            Some((path, query)) => (path, Some(query.to_string())),
+            None => (path, None),
+        };
+        Some(Self {
+            scheme,
+            host: host.into(),
+            port,
+            path: path.into(),
+            query,
+        })
+    }
+
+    // Resolve a link relative to this URL
+    fn join(&self, link: &str) -> Option<Self> {
+        if link.contains("://") {
+            return Self::parse(link);
+        }
+        let scheme = match self.scheme {
+            Scheme::Gemini => "gemini:",
+            Scheme::Gopher => "gopher:",
+        };
+        if link.starts_with("//") {
+            return Self::parse(&format!("{}{}", scheme, link));
+        }
+        let mut url = self.clone();
+        url.query = None;
+        let link = match link.split_once('?') {
+            Some((link, query)) => {
+                url.query = Some(query.to_string());
+                link
+            }
+            None => link,
+        };
+        if link.starts_with('/') {
+            url.path = normalize(link);
+        } else if !link.is_empty() {
+            let i = self.path.rfind('/').map_or(0, |i| i + 1);
+            url.path = normalize(&format!("{}{}", &self.path[..i], link));
+        }
+        Some(url)
+    }
+
+    // Return the item type and the selector of a gopher URL
+    fn selector(&self) -> (char, &str) {
+        let mut chars = self.path.trim_start_matches('/').chars();
+        match chars.next() {
+            Some(t) => (t, chars.as_str()),
+            None => ('1', ""),
+        }
+    }
+}
+
+impl fmt::Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (scheme, default_port, separator) = match self.scheme {
+            Scheme::Gemini => ("gemini", 1965, "?"),
+            Scheme::Gopher => ("gopher", 70, "%09"),
+        };
+        write!(f, "{}://{}", scheme, self.host)?;
+        if self.port != default_port {
+            write!(f, ":{}", self.port)?;
+        }
+        write!(f, "{}", self.path)?;
+        if let Some(query) = &self.query {
+            write!(f, "{}{}", separator, query)?;
+        }
+        Ok(())
+    }
+}
+
+// Remove the "." and ".." segments of a path
+fn normalize(path: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+    let parts: Vec<&str> = path.split('/').skip(1).collect();
+    let n = parts.len();
+    for (i, part) in parts.iter().enumerate() {
+        match *part {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            part => segments.push(part),
+        }
+        // Keep the trailing slash of directories
+        if i == n - 1 && (*part == "." || *part == "..") {
+            segments.push("");
+        }
+    }
+    format!("/{}", segments.join("/"))
+}
+
+fn percent_encode(s: &str) -> String {
+    let mut res = String::new();
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+            res.push(b as char);
+        } else {
+            res.push_str(&format!("%{:02X}", b));
+        }
+    }
+    res
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    Text,
+    Preformatted,
+    Heading,
+    Link(usize),
+    Quote,
+    Error,
+}
+
+#[derive(Debug, PartialEq)]
+struct Line {
+    kind: Kind,
+    text: String,
+}
+
+impl Line {
+    fn new(kind: Kind, text: &str) -> Self {
+        Self { kind, text: text.replace('\t', "    ") }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Page {
+    lines: Vec<Line>,
+    links: Vec<String>,
+}
+
+impl Page {
+    fn add_link(&mut self, link: String, label: &str) {
+        self.links.push(link);
+        let n = self.links.len();
+        self.lines.push(Line::new(Kind::Link(n), label));
+    }
+
+    // Return the rows to display on the screen with their color
+    fn rows(&self, cols: usize) -> Vec<(String, &'static str)> {
+        let mut rows = Vec::new();
+        for line in &self.lines {
+            let (text, color) = match line.kind {
+                Kind::Text => (line.text.clone(), "LightGray"),
+                Kind::Preformatted => {
+                    let text = line.text.chars().take(cols).collect();
+                    rows.push((text, "White"));
+                    continue;
+                }
+                Kind::Heading => (line.text.clone(), "Yellow"),
+                Kind::Link(n) => {
+                    (format!("[{}] {}", n, line.text), "LightCyan")
+                }
+                Kind::Quote => (format!("> {}", line.text), "DarkGray"),
+                Kind::Error => (line.text.clone(), "Red"),
+            };
+            for row in wrap(&text, cols) {
+                rows.push((row, color));
+            }
+        }
+        rows
+    }
+}
+
+// Split a line at the spaces between words to fit the width of the screen
+fn wrap(text: &str, cols: usize) -> Vec<String> {
+    let mut rows = Vec::new();
+    let mut row = String::new();
+    let mut n = 0;
+    for word in text.split(' ') {
+        let m = word.chars().count();
+        if n > 0 && n + 1 + m > cols {
+            rows.push(row);
+            row = String::new();
+            n = 0;
+        }
+        if n > 0 {
+            row.push(' ');
+            n += 1;
+        }
+        // Split the words longer than a row
+        let mut chars = word.chars().peekable();
+        while chars.peek().is_some() {
+            if n == cols {
+                rows.push(row);
+                row = String::new();
+                n = 0;
+            }
+            row.push(chars.next().unwrap());
+            n += 1;
+        }
+    }
+    rows.push(row);
+    rows
+}
+
+// See https://geminiprotocol.net/docs/gemtext-specification.gmi
+fn parse_gemtext(text: &str, base: &Url) -> Page {
+    let mut page = Page::default();
+    let mut preformatted = false;
+    for line in text.lines() {
+        if line.starts_with("```") {
+            preformatted = !preformatted;
+        } else if preformatted {
+            page.lines.push(Line::new(Kind::Preformatted, line));
+        } else if let Some(link) = line.strip_prefix("=>") {
+            let link = link.trim();
+            let (link, label) = match link.split_once(char::is_whitespace) {
+                Some((link, label)) => (link, label.trim()),
+                None => (link, link),
+            };
+            let link = match base.join(link) {
+                Some(url) => url.to_string(),
+                None => link.to_string(),
+            };
+            page.add_link(link, label);
+        } else if line.starts_with('#') {
+            page.lines.push(Line::new(Kind::Heading, line));
+        } else if let Some(quote) = line.strip_prefix('>') {
+            page.lines.push(Line::new(Kind::Quote, quote.trim_start()));
+        } else if let Some(item) = line.strip_prefix("* ") {
+            page.lines.push(Line::new(Kind::Text, &format!("- {}", item)));
+        } else {
+            page.lines.push(Line::new(Kind::Text, line));
+        }
+    }
+    page
+}
+
+// See RFC 1436 (The Internet Gopher Protocol)
+fn parse_gopher_menu(text: &str) -> Page {
+    let mut page = Page::default();
+    for line in text.lines() {
+        if line == "." {
+            break;
+        }
+        let mut chars = line.chars();
+        let t = match chars.next() {
+            Some(t) => t,
+            None => continue,
+        };
+        let fields: Vec<&str> = chars.as_str().split('\t').collect();
+        let display = fields[0];
+        let selector = fields.get(1).copied().unwrap_or("");
+        let host = fields.get(2).copied().unwrap_or("");
+        let port = fields.get(3).copied().unwrap_or("70").trim();
+        match t {
+            '0' | '1' | '7' => {
+                let link = if port == "70" {
+                    format!("gopher://{}/{}{}", host, t, selector)
+                } else {
+                    format!("gopher://{}:{}/{}{}", host, port, t, selector)
+                };
+                page.add_link(link, display);
+            }
+            'h' if selector.starts_with("URL:") => {
+                page.add_link(selector[4..].to_string(), display);
+            }
+            '3' => page.lines.push(Line::new(Kind::Error, display)),
+            _ => page.lines.push(Line::new(Kind::Text, display)),
+        }
+    }
+    page
+}
+
+fn parse_text(text: &str) -> Page {
+    let mut page = Page::default();
+    for line in text.lines() {
+        page.lines.push(Line::new(Kind::Preformatted, line));
+    }
+    page
+}
+
+// Parse the header of a gemini response like "20 text/gemini"
+fn parse_header(line: &str) -> Option<(u8, String)> {
+    let status = line.get(..2)?.parse().ok()?;
+    let meta = line.get(2..).unwrap_or("").trim();
+    Some((status, meta.to_string()))
+}
+
+enum Response {
+    Page(Page),
+    Input(String, bool),
+    Redirect(String),
+}
+
+fn resolve(host: &str) -> Result<IpAddress, String> {
+    if host.ends_with(char::is_numeric) {
+        IpAddress::from_str(host).map_err(|_| {
+            format!("Invalid address '{}'", host)
+        })
+    } else {
+        usr::host::resolve(host).map_err(|e| {
+            format!("Could not resolve host: {:?}", e)
+        })
+    }
+}
+
+// Send a request and read the whole response until the server closes the
+// connection
+fn request(url: &Url, req: &str) -> Result<Vec<u8>, String> {
+    let socket_path = "/dev/net/tcp";
+    let buf_len = match syscall::info(socket_path) {
+        Some(info) => info.size() as usize,
+        None => return Err(format!("Could not open '{}'", socket_path)),
+    };
+    let addr = resolve(&url.host)?;
+    let flags = OpenFlag::Device as usize;
+    let handle = match syscall::open(socket_path, flags) {
+        Some(handle) => handle,
+        None => return Err(format!("Could not open '{}'", socket_path)),
+    };
+    if syscall::connect(handle, addr, url.port).is_err() {
+        syscall::close(handle);
+        return Err(format!("Could not connect to {}:{}", addr, url.port));
+    }
+    let mut tls = None;
+    if url.scheme == Scheme::Gemini {
+        match TlsStream::connect_insecure(handle, &url.host) {
+            Ok(stream) => tls = Some(stream),
+            Err(()) => {
+                syscall::close(handle);
+                return Err("Could not establish TLS connection".into());
+            }
+        }
+    }
+    let sent = match &mut tls {
+        Some(stream) => stream.write(req.as_bytes()),
+        None => syscall::write(handle, req.as_bytes()),
+    };
+    let mut res = Vec::new();
+    if sent.is_some() {
+        let mut buf = vec![0; buf_len];
+        loop {
+            if sys::console::end_of_text() {
+                break;
+            }
+            let n = match &mut tls {
+                Some(stream) => stream.read(&mut buf),
+                None => syscall::read(handle, &mut buf),
+            };
+            match n {
+                Some(0) | None => break,
+                Some(n) => res.extend_from_slice(&buf[..n]),
+            }
+        }
+    }
+    if let Some(stream) = &mut tls {
+        stream.close();
+    }
+    syscall::close(handle);
+    if res.is_empty() {
+        return Err("Could not receive response".into());
+    }
+    Ok(res)
+}
+
+fn fetch(url: &Url, is_insecure: bool) -> Result<Response, String> {
+    match url.scheme {
+        Scheme::Gemini if !TlsStream::is_enabled() => {
+            Err("Could not use experimental TLS client".into())
+        }
+        // The TLS client doesn't verify the certificate of the server
+        Scheme::Gemini if !is_insecure => {
+            Err(format!("Could not verify certificate of '{}'", url.host))
+        }
+        Scheme::Gemini => {
+            let buf = request(url, &format!("{}\r\n", url))?;
+            let i = buf.iter().position(|&b| b == b'\n').unwrap_or(buf.len());
+            let header = String::from_utf8_lossy(&buf[..i]);
+            let body = String::from_utf8_lossy(buf.get(i + 1..).unwrap_or(&[]));
+            let (status, meta) = match parse_header(&header) {
+                Some(header) => header,
+                None => return Err("Invalid response".into()),
+            };
+            let is_gemtext = meta.is_empty() || meta.starts_with("text/gemini");
+            match status {
+                10..=19 => Ok(Response::Input(meta, status == 11)),
+                20..=29 if is_gemtext => {
+                    Ok(Response::Page(parse_gemtext(&body, url)))
+                }
+                20..=29 if meta.starts_with("text/") => {
+                    Ok(Response::Page(parse_text(&body)))
+                }
+                20..=29 => Err(format!("Unsupported content type '{}'", meta)),
+                30..=39 => Ok(Response::Redirect(meta)),
+                _ => Err(format!("Error {}: {}", status, meta)),
+            }
+        }
+        Scheme::Gopher => {
+            let (t, selector) = url.selector();
+            let req = match &url.query {
+                Some(query) if t == '7' => {
+                    format!("{}\t{}\r\n", selector, query)
+                }
+                _ => format!("{}\r\n", selector),
+            };
+            let buf = request(url, &req)?;
+            let text = String::from_utf8_lossy(&buf);
+            match t {
+                '1' | '7' => Ok(Response::Page(parse_gopher_menu(&text))),
+                '0' => {
+                    let text = text.strip_suffix(".\r\n").unwrap_or(&text);
+                    Ok(Response::Page(parse_text(text)))
+                }
+                _ => Err(format!("Unsupported item type '{}'", t)),
+            }
+        }
+    }
+}
+
+struct Browser {
+    url: Option<Url>,
+    page: Page,
+    rows: Vec<(String, &'static str)>,
+    history: Vec<Url>,
+    offset: usize,
+    status: Option<String>,
+    is_insecure: bool,
+}
+
+impl Browser {
+    fn new(is_insecure: bool) -> Self {
+        Self {
+            url: None,
+            page: Page::default(),
+            rows: Vec::new(),
+            history: Vec::new(),
+            offset: 0,
+            status: None,
+            is_insecure,
+        }
+    }
+
+    // Load a page and add the current one to the history
+    fn open(&mut self, url: Url) {
+        let previous = self.url.clone();
+        if self.load(url) {
+            if let Some(previous) = previous {
+                self.history.push(previous);
+                if self.history.len() > MAX_HISTORY {
+                    self.history.remove(0);
+                }
+            }
+        }
+    }
+
+    fn back(&mut self) {
+        match self.history.pop() {
+            Some(url) => {
+                self.load(url);
+            }
+            None => self.status = Some("No previous page".into()),
+        }
+    }
+
+    fn load(&mut self, mut url: Url) -> bool {
+        for _ in 0..MAX_REDIRECTS {
+            self.status = Some(format!("Loading {}", url));
+            self.print_status();
+
+            // The query of a gopher search is asked before the request
+            let (t, _) = url.selector();
+            if url.scheme == Scheme::Gopher && t == '7' && url.query.is_none() {
+                let query = self.prompt("Search: ", false);
+                if query.is_empty() {
+                    self.status = None;
+                    return false;
+                }
+                url.query = Some(query);
+            }
+            match fetch(&url, self.is_insecure) {
+                Ok(Response::Page(page)) => {
+                    self.page = page;
+                    self.rows = self.page.rows(console::cols());
+                    self.url = Some(url);
+                    self.offset = 0;
+                    self.status = None;
+                    return true;
+                }
+                Ok(Response::Input(prompt, sensitive)) => {
+                    let prompt = format!("{}: ", prompt);
+                    let input = self.prompt(&prompt, sensitive);
+                    if input.is_empty() {
+                        self.status = None;
+                        return false;
+                    }
+                    url.query = Some(percent_encode(&input));
+                }
+                Ok(Response::Redirect(link)) => match url.join(&link) {
+                    Some(next) => url = next,
+                    None => {
+                        let e = format!("Invalid redirect '{}'", link);
+                        self.status = Some(e);
+                        return false;
+                    }
+                },
+                Err(e) => {
+                    self.status = Some(e);
+                    return false;
+                }
+            }
+        }
+        self.status = Some("Too many redirects".into());
+        false
+    }
+
+    fn follow(&mut self, n: usize) {
+        let link = match n.checked_sub(1).and_then(|i| self.page.links.get(i)) {
+            Some(link) => link.clone(),
+            None => {
+                self.status = Some(format!("Could not find link {}", n));
+                return;
+            }
+        };
+        match Url::parse(&link) {
+            Some(url) if link.contains("://") => self.open(url),
+            _ => self.status = Some(format!("Unsupported link '{}'", link)),
+        }
+    }
+
+    fn run(&mut self, url: Url) -> Result<(), ExitCode> {
+        print!("\x1b[2J\x1b[?25l"); // Clear screen and disable cursor
+        self.open(url);
+        if self.url.is_none() {
+            print!("\x1b[2J\x1b[1;1H"); // Clear screen and move to top
+            print!("\x1b[?25h"); // Enable cursor
+            if let Some(status) = self.status.take() {
+                error!("{}", status);
+            }
+            return Err(ExitCode::Failure);
+        }
+        let mut escape = false;
+        let mut csi = false;
+        let mut csi_params = String::new();
+        let mut number = String::new();
+        loop {
+            self.print_screen();
+            if !number.is_empty() {
+                self.status = Some(format!("Link: {}", number));
+                self.print_status();
+            }
+            let c = sys::console::read_char();
+            match c {
+                '\x1B' => { // ESC
+                    escape = true;
+                    continue;
+                }
+                '[' if escape => {
+                    csi = true;
+                    csi_params.clear();
+                    continue;
+                }
+                '0'..='9' if csi => {
+                    csi_params.push(c);
+                    continue;
+                }
+                '0'..='9' => {
+                    number.push(c);
+                    continue;
+                }
+                '\n' if !number.is_empty() => {
+                    self.status = None;
+                    let n = number.parse().unwrap_or(0);
+                    number.clear();
+                    self.follow(n);
+                    continue;
+                }
+                '\x08' if !number.is_empty() => {
+                    number.pop();
+                    self.status = None;
+                    continue;
+                }
+                'q' | console::ETX_KEY | console::EOT_KEY => {
+                    break;
+                }
+                'A' if csi => self.scroll_up(1), // Arrow Up
+                'B' if csi => self.scroll_down(1), // Arrow Down
+                '~' if csi && csi_params == "5" => { // Page Up
+                    self.scroll_up(self.height());
+                }
+                '~' if csi && csi_params == "6" => { // Page Down
+                    self.scroll_down(self.height());
+                }
+                'k' => self.scroll_up(1),
+                'j' | '\n' => self.scroll_down(1),
+                'b' => self.scroll_up(self.height()),
+                ' ' | 'f' => self.scroll_down(self.height()),
+                'g' => self.offset = 0,
+                'G' => self.offset = self.max_offset(),
+                'h' | '\x08' => self.back(),
+                'r' => {
+                    if let Some(url) = self.url.clone() {
+                        self.load(url);
+                    }
+                }
+                'o' => {
+                    let input = self.prompt("URL: ", false);
+                    if !input.is_empty() {
+                        match Url::parse(&input) {
+                            Some(url) => self.open(url),
+                            None => {
+                                let e = format!("Invalid URL '{}'", input);
+                                self.status = Some(e);
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+            number.clear();
+            escape = false;
+            csi = false;
+        }
+        print!("\x1b[2J\x1b[1;1H"); // Clear screen and move to top
+        print!("\x1b[?25h"); // Enable cursor
+        Ok(())
+    }
+
+    fn print_screen(&mut self) {
+        let cols = console::cols();
+        let reset = Style::reset();
+        let mut rows: Vec<String> = Vec::new();
+        for y in self.offset..(self.offset + self.height()) {
+            let row = match self.rows.get(y) {
+                Some((text, color)) => {
+                    let n = text.chars().count();
+                    let color = Style::color(color);
+                    let space = " ".repeat(cols - n);
+                    format!("{}{}{}{}", color, text, reset, space)
+                }
+                None => " ".repeat(cols),
+            };
+            rows.push(row);
+        }
+        print!("\x1b[1;1H{}\n", rows.join("\n"));
+        self.print_status();
+    }
+
+    fn print_status(&mut self) {
+        let cols = console::cols();
+        let status = match self.status.take() {
+            Some(status) => status,
+            None => {
+                let url = self.url.as_ref().map_or(String::new(), |url| {
+                    url.to_string()
+                });
+                let n = self.rows.len();
+                let end = cmp::min(self.offset + self.height(), n);
+                let percent = if n > 0 { end * 100 / n } else { 100 };
+                format!("{} {}%", url, percent)
+            }
+        };
+        let status: String = status.chars().take(cols).collect();
+        let color = Style::color("Black").with_background("LightGray");
+        let reset = Style::reset();
+        print!(
+            "\x1b[{};1H{}{:cols$}{}", self.height() + 1, color, status, reset,
+            cols = cols
+        );
+    }
+
+    // Read a line on the status line
+    fn prompt(&mut self, prefix: &str, sensitive: bool) -> String {
+        let mut res = String::new();
+        print!("\x1b[?25h"); // Enable cursor
+        loop {
+            let text = if sensitive {
+                "*".repeat(res.chars().count())
+            } else {
+                res.clone()
+            };
+            print!("\x1b[{};1H\x1b[2K{}{}", self.height() + 1, prefix, text);
+            match sys::console::read_char() {
+                '\n' => break,
+                console::ETX_KEY | '\x1B' => {
+                    res.clear();
+                    break;
+                }
+                '\x08' => {
+                    res.pop();
+                }
+                c if console::is_printable(c) => res.push(c),
+                _ => {}
+            }
+        }
+        print!("\x1b[?25l"); // Disable cursor
+        res
+    }
+
+    fn scroll_up(&mut self, n: usize) {
+        self.offset -= cmp::min(n, self.offset);
+    }
+
+    fn scroll_down(&mut self, n: usize) {
+        self.offset = cmp::min(self.offset + n, self.max_offset());
+    }
+
+    fn max_offset(&self) -> usize {
+        self.rows.len().saturating_sub(self.height())
+    }
+
+    fn height(&self) -> usize {
+        console::rows() - 1 // Leave out one line for status line
+    }
+}
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut is_insecure = false;
+    let mut url = None;
+    for arg in &args[1..] {
+        match *arg {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-k" | "--insecure" => is_insecure = true,
+            _ if url.is_none() => url = Some(*arg),
+            _ => {
+                help();
+                return Err(ExitCode::UsageError);
+            }
+        }
+    }
+    let url = match url {
+        Some(url) => url,
+        None => {
+            help();
+            return Err(ExitCode::UsageError);
+        }
+    };
+    let url = match Url::parse(url) {
+        Some(url) => url,
+        None => {
+            error!("Invalid URL '{}'", url);
+            return Err(ExitCode::UsageError);
+        }
+    };
+    Browser::new(is_insecure).run(url)
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} browse {}[<options>] <url>{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-k{1}, {0}--insecure{1}   Allow Gemini without verifying the \
+        server",
+        csi_option, csi_reset
+    );
+    println!();
+    println!("{}Keys:{}", csi_title, csi_reset);
+    println!(
+        "  {0}j{1}, {0}k{1}, {0}Up{1}, {0}Down{1}            \
+        Scroll by one line",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}f{1}, {0}b{1}, {0}Space{1}, {0}PgUp{1}, {0}PgDn{1}   \
+        Scroll by one page",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}g{1}, {0}G{1}                      Go to the top or the bottom",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}<number>{1} {0}Enter{1}            Follow a link",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}h{1}, {0}Backspace{1}              Go back to the previous page",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}o{1}                         Open a URL",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}r{1}                         Reload the page",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}q{1}                         Quit",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_url() {
+    let url = Url::parse("gemini://example.com/docs/index.gmi").unwrap();
+    assert_eq!(url.port, 1965);
+    assert_eq!(url.path, "/docs/index.gmi");
+    assert_eq!(url.to_string(), "gemini://example.com/docs/index.gmi");
+
+    let join = |link| url.join(link).unwrap().to_string();
+    assert_eq!(join("faq.gmi"), "gemini://example.com/docs/faq.gmi");
+    assert_eq!(join("../"), "gemini://example.com/");
+    assert_eq!(join("/a/./b.gmi"), "gemini://example.com/a/b.gmi");
+    assert_eq!(join("?q=1"), "gemini://example.com/docs/index.gmi?q=1");
+    assert_eq!(join("//example.org"), "gemini://example.org/");
+    let link = "gopher://example.org:7070/1/";
+    assert_eq!(join(link), link);
+    assert_eq!(url.join("https://example.org"), None);
+
+    let url = Url::parse("gopher://example.com/7/search%09moros").unwrap();
+    assert_eq!(url.selector(), ('7', "/search"));
+    assert_eq!(url.query, Some("moros".into()));
+    let url = Url::parse("gopher://example.com").unwrap();
+    assert_eq!(url.selector(), ('1', ""));
+    assert_eq!(Url::parse("example.com").unwrap().scheme, Scheme::Gemini);
+    assert_eq!(percent_encode("a b/c"), "a%20b%2Fc");
+}
+
+#[test_case]
+fn test_parse_gemtext() {
+    let url = Url::parse("gemini://example.com/").unwrap();
+    let text = "# Title\n=> /about.gmi About\n=>news.gmi\n\
+                ```\n=> code\n```\n> quote";
+    let page = parse_gemtext(text, &url);
+    assert_eq!(page.links, vec![
+        "gemini://example.com/about.gmi",
+        "gemini://example.com/news.gmi",
+    ]);
+    assert_eq!(page.lines, vec![
+        Line::new(Kind::Heading, "# Title"),
+        Line::new(Kind::Link(1), "About"),
+        Line::new(Kind::Link(2), "news.gmi"),
+        Line::new(Kind::Preformatted, "=> code"),
+        Line::new(Kind::Quote, "quote"),
+    ]);
+    let rows = page.rows(80);
+    assert_eq!(rows[1], ("[1] About".into(), "LightCyan"));
+}
+
+#[test_case]
+fn test_parse_gopher_menu() {
+    let text = "iWelcome\t\terror.host\t1\r\n\
+                1Docs\t/docs\texample.com\t70\r\n\
+                0About\t/about.txt\texample.com\t7070\r\n\
+                hWeb\tURL:https://example.com\texample.com\t70\r\n\
+                .\r\n";
+    let page = parse_gopher_menu(text);
+    assert_eq!(page.links, vec![
+        "gopher://example.com/1/docs",
+        "gopher://example.com:7070/0/about.txt",
+        "https://example.com",
+    ]);
+    assert_eq!(page.lines[0], Line::new(Kind::Text, "Welcome"));
+    let header = Some((20, "text/gemini".into()));
+    assert_eq!(parse_header("20 text/gemini"), header);
+    assert_eq!(parse_header("51"), Some((51, "".into())));
+    assert_eq!(parse_header("x"), None);
+}
+
+#[test_case]
+fn test_wrap() {
+    assert_eq!(wrap("aaa bbb ccc", 7), vec!["aaa bbb", "ccc"]);
+    assert_eq!(wrap("aaaaaaaaa", 4), vec!["aaaa", "aaaa", "a"]);
+    assert_eq!(wrap("", 4), vec![""]);
+}
+
+#[test_case]
+fn test_fetch_without_insecure() {
+    // The connection is refused before resolving the host
+    let url = Url::parse("gemini://example.com/").unwrap();
+    assert!(fetch(&url, false).is_err());
+}
@@ -0,0 +1,55 @@
+use crate::api::console::Style;
+use crate::api::process::ExitCode;
+use crate::usr::chown;
+
+use alloc::vec::Vec;
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut recursive = false;
+    let mut args: Vec<&str> = args[1..].to_vec();
+    args.retain(|arg| match *arg {
+        "-r" | "--recursive" => {
+            recursive = true;
+            false
+        }
+        _ => true,
+    });
+    if args.iter().any(|arg| *arg == "-h" || *arg == "--help") {
+        help();
+        return Ok(());
+    }
+    if args.len() < 2 {
+        help();
+        return Err(ExitCode::UsageError);
+    }
+
+    let group = args.remove(0);
+    let gid = match chown::parse_group(group) {
+        Some(gid) => gid,
+        None => {
+            error!("Could not find group '{}'", group);
+            return Err(ExitCode::Failure);
+        }
+    };
+    for path in args {
+        chown::chown(path, None, Some(gid), recursive)?;
+    }
+    Ok(())
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} chgrp {}<options> <group> <path>{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-r{1}, {0}--recursive{1}   Change group of directories \
+        recursively",
+        csi_option, csi_reset
+    );
+}
@@ -0,0 +1,134 @@
+use crate::api::console::Style;
+use crate::api::fs;
+use crate::api::process::ExitCode;
+use crate::api::syscall;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut args: Vec<&str> = args[1..].to_vec();
+    if args.iter().any(|arg| *arg == "-h" || *arg == "--help") {
+        help();
+        return Ok(());
+    }
+    if args.len() < 2 {
+        help();
+        return Err(ExitCode::UsageError);
+    }
+
+    let mode = args.remove(0);
+    for path in args {
+        let old = match syscall::info(path) {
+            Some(info) => info.mode(),
+            None => {
+                error!("Could not find file '{}'", path);
+                return Err(ExitCode::Failure);
+            }
+        };
+        let new = match parse_mode(mode, old) {
+            Some(new) => new,
+            None => {
+                error!("Invalid mode '{}'", mode);
+                return Err(ExitCode::UsageError);
+            }
+        };
+        if fs::chmod(path, new).is_err() {
+            error!("Could not change mode of '{}'", path);
+            return Err(ExitCode::Failure);
+        }
+    }
+    Ok(())
+}
+
+// Parse an octal mode like "755" or a list of symbolic modes like "u+x,go-w"
+// applied to the old mode of the file
+pub fn parse_mode(s: &str, old: u16) -> Option<u16> {
+    if s.chars().all(|c| c.is_digit(8)) {
+        return match u16::from_str_radix(s, 8) {
+            Ok(mode) if mode <= 0o777 => Some(mode),
+            _ => None,
+        };
+    }
+    let mut mode = old;
+    for clause in s.split(',') {
+        let i = clause.find(['+', '-', '='])?;
+        let (who, rest) = clause.split_at(i);
+        let mut mask = 0;
+        for c in who.chars() {
+            mask |= match c {
+                'u' => 0o700,
+                'g' => 0o070,
+                'o' => 0o007,
+                'a' => 0o777,
+                _ => return None,
+            };
+        }
+        if mask == 0 {
+            mask = 0o777;
+        }
+        let op = rest.chars().next()?;
+        let mut bits = 0;
+        for c in rest[1..].chars() {
+            bits |= match c {
+                'r' => 0o444,
+                'w' => 0o222,
+                'x' => 0o111,
+                _ => return None,
+            };
+        }
+        match op {
+            '+' => mode |= bits & mask,
+            '-' => mode &= !(bits & mask),
+            _ => mode = (mode & !mask) | (bits & mask),
+        }
+    }
+    Some(mode)
+}
+
+// Format a mode like "rwxr-xr-x"
+pub fn format_mode(mode: u16) -> String {
+    let mut res = String::new();
+    for i in (0..3).rev() {
+        let bits = mode >> (i * 3);
+        res.push(if bits & 4 != 0 { 'r' } else { '-' });
+        res.push(if bits & 2 != 0 { 'w' } else { '-' });
+        res.push(if bits & 1 != 0 { 'x' } else { '-' });
+    }
+    res
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} chmod {}<mode> <path>{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Modes:{}", csi_title, csi_reset);
+    println!(
+        "  {0}755{1}        Set the octal mode of the file",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}u+x,go-w{1}   Add or remove permissions for the user, \
+        group, or others",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_parse_mode() {
+    assert_eq!(parse_mode("755", 0o644), Some(0o755));
+    assert_eq!(parse_mode("1000", 0o644), None);
+    assert_eq!(parse_mode("+x", 0o644), Some(0o755));
+    assert_eq!(parse_mode("u+x,go-r", 0o644), Some(0o700));
+    assert_eq!(parse_mode("a=r", 0o755), Some(0o444));
+    assert_eq!(parse_mode("o=", 0o777), Some(0o770));
+    assert_eq!(parse_mode("x+r", 0o644), None);
+    assert_eq!(parse_mode("u+z", 0o644), None);
+    assert_eq!(format_mode(0o755), "rwxr-xr-x");
+    assert_eq!(format_mode(0o640), "rw-r-----");
+}
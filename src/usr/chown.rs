@@ -0,0 +1,112 @@
+use crate::api::console::Style;
+use crate::api::fs;
+use crate::api::process::ExitCode;
+use crate::api::syscall;
+use crate::usr;
+
+use alloc::format;
+use alloc::vec::Vec;
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut recursive = false;
+    let mut args: Vec<&str> = args[1..].to_vec();
+    args.retain(|arg| match *arg {
+        "-r" | "--recursive" => {
+            recursive = true;
+            false
+        }
+        _ => true,
+    });
+    if args.iter().any(|arg| *arg == "-h" || *arg == "--help") {
+        help();
+        return Ok(());
+    }
+    if args.len() < 2 {
+        help();
+        return Err(ExitCode::UsageError);
+    }
+
+    // The group is unchanged unless given after the user
+    let owner = args.remove(0);
+    let (user, group) = match owner.split_once(':') {
+        Some((user, group)) => (user, Some(group)),
+        None => (owner, None),
+    };
+    let uid = match parse_user(user) {
+        Some(uid) => uid,
+        None => {
+            error!("Could not find user '{}'", user);
+            return Err(ExitCode::Failure);
+        }
+    };
+    let gid = match group.map(parse_group) {
+        Some(Some(gid)) => Some(gid),
+        Some(None) => {
+            error!("Could not find group '{}'", group.unwrap());
+            return Err(ExitCode::Failure);
+        }
+        None => None,
+    };
+    for path in args {
+        chown(path, Some(uid), gid, recursive)?;
+    }
+    Ok(())
+}
+
+pub fn parse_user(user: &str) -> Option<u16> {
+    user.parse().ok().or_else(|| usr::user::uid(user))
+}
+
+pub fn parse_group(group: &str) -> Option<u16> {
+    group.parse().ok().or_else(|| usr::user::gid(group))
+}
+
+// Change the owner and the group of a file, or only one of them, and of
+// the files of a directory if recursive without following symbolic links
+pub fn chown(
+    path: &str,
+    uid: Option<u16>,
+    gid: Option<u16>,
+    recursive: bool
+) -> Result<(), ExitCode> {
+    let info = match syscall::info(path) {
+        Some(info) => info,
+        None => {
+            error!("Could not find file '{}'", path);
+            return Err(ExitCode::Failure);
+        }
+    };
+    let new_uid = uid.unwrap_or(info.uid());
+    let new_gid = gid.unwrap_or(info.gid());
+    if fs::chown(path, new_uid, new_gid).is_err() {
+        error!("Could not change owner of '{}'", path);
+        return Err(ExitCode::Failure);
+    }
+    if recursive && info.is_dir() && !fs::is_link(path) {
+        if let Ok(files) = fs::read_dir(path) {
+            let sep = if path.ends_with('/') { "" } else { "/" };
+            for file in files.iter().filter(|f| !f.is_link()) {
+                let path = format!("{}{}{}", path, sep, file.name());
+                chown(&path, uid, gid, recursive)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} chown {}<options> <user>[:<group>] <path>{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-r{1}, {0}--recursive{1}   Change owner of directories \
+        recursively",
+        csi_option, csi_reset
+    );
+}
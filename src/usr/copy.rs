@@ -1,57 +1,207 @@
 use crate::api::console::Style;
 use crate::api::fs;
 use crate::api::process::ExitCode;
+use crate::api::syscall;
 
 use alloc::format;
 use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+// Files bigger than this will have their copy progress displayed in
+// verbose mode.
+const PROGRESS_THRESHOLD: usize = 64 << 10;
+const CHUNK_SIZE: usize = 16 << 10;
 
 pub fn main(args: &[&str]) -> Result<(), ExitCode> {
-    let n = args.len();
-    for i in 1..n {
-        match args[i] {
+    let mut recursive = false;
+    let mut preserve = false;
+    let mut verbose = false;
+    let mut paths = Vec::new();
+    for arg in &args[1..] {
+        match *arg {
             "-h" | "--help" => {
                 help();
                 return Ok(());
             }
-            _ => continue,
+            "-r" | "--recursive" => recursive = true,
+            "-p" | "--preserve" => preserve = true,
+            "-v" | "--verbose" => verbose = true,
+            _ => paths.push(*arg),
         }
     }
-    if n != 3 {
+    if paths.len() != 2 {
         help();
         return Err(ExitCode::UsageError);
     }
 
-    if args[2].is_empty() {
+    if paths[1].is_empty() {
         error!("Could not write to ''");
         return Err(ExitCode::Failure);
     }
 
-    let source = args[1];
-    let dest = destination(args[1], args[2]);
+    let source = paths[0];
+    let dest = destination(paths[0], paths[1]);
 
     if fs::is_dir(source) {
-        error!("Could not copy directory '{}'", source);
-        return Err(ExitCode::Failure);
+        if recursive {
+            copy_dir(source, &dest, preserve, verbose)
+        } else {
+            error!("Could not copy directory '{}'", source);
+            Err(ExitCode::Failure)
+        }
+    } else {
+        copy_file(source, &dest, preserve, verbose)
     }
+}
 
-    if let Ok(contents) = fs::read_to_bytes(source) {
-        if fs::write(&dest, &contents).is_ok() {
-            Ok(())
+fn copy_dir(
+    source: &str,
+    dest: &str,
+    preserve: bool,
+    verbose: bool
+) -> Result<(), ExitCode> {
+    let source = source.trim_end_matches('/');
+    if fs::realpath(dest).starts_with(&format!("{}/", fs::realpath(source))) {
+        error!("Could not copy '{}' into itself", source);
+        return Err(ExitCode::Failure);
+    }
+    if !fs::is_dir(dest) {
+        if let Some(handle) = fs::create_dir(dest) {
+            syscall::close(handle);
         } else {
             error!("Could not write to '{}'", dest);
-            Err(ExitCode::Failure)
+            return Err(ExitCode::Failure);
+        }
+    }
+    if verbose {
+        println!("{}/", dest);
+    }
+    let info = syscall::info(source);
+    if let Ok(files) = fs::read_dir(source) {
+        for file in files {
+            let name = file.name();
+            let src = format!("{}/{}", source, name);
+            let dst = format!("{}/{}", dest, name);
+            if file.is_dir() {
+                copy_dir(&src, &dst, preserve, verbose)?;
+            } else if file.is_device() {
+                warning!("Skipping device '{}'", src);
+            } else {
+                copy_file(&src, &dst, preserve, verbose)?;
+            }
         }
+        // The times of a dir are set after its files have been copied
+        if let Some(info) = info.filter(|_| preserve) {
+            fs::utime(dest, info.atime(), info.mtime()).ok();
+        }
+        Ok(())
     } else {
-        error!("Could not read file '{}'", source);
+        error!("Could not read directory '{}'", source);
         Err(ExitCode::Failure)
     }
 }
 
-fn destination(source: &str, dest: &str) -> String {
+// A new file gets the permissions of its source, and its times are
+// preserved if asked
+fn copy_file(
+    source: &str,
+    dest: &str,
+    preserve: bool,
+    verbose: bool
+) -> Result<(), ExitCode> {
+    let is_new = !fs::exists(dest);
+    let info = syscall::info(source);
+    copy_contents(source, dest, verbose)?;
+    if let Some(info) = info {
+        if is_new {
+            fs::chmod(dest, info.mode()).ok();
+        }
+        if preserve {
+            fs::utime(dest, info.atime(), info.mtime()).ok();
+        }
+    }
+    Ok(())
+}
+
+fn copy_contents(
+    source: &str,
+    dest: &str,
+    verbose: bool
+) -> Result<(), ExitCode> {
+    let size = match syscall::info(source) {
+        Some(info) => info.size() as usize,
+        None => {
+            error!("Could not read file '{}'", source);
+            return Err(ExitCode::Failure);
+        }
+    };
+    if size < PROGRESS_THRESHOLD || !verbose {
+        if let Ok(contents) = fs::read_to_bytes(source) {
+            if fs::write(dest, &contents).is_ok() {
+                if verbose {
+                    println!("{}", dest);
+                }
+                return Ok(());
+            } else {
+                error!("Could not write to '{}'", dest);
+                return Err(ExitCode::Failure);
+            }
+        } else {
+            error!("Could not read file '{}'", source);
+            return Err(ExitCode::Failure);
+        }
+    }
+
+    // Copy large files by chunks to display the progress
+    let input = match fs::open_file(source) {
+        Some(handle) => handle,
+        None => {
+            error!("Could not read file '{}'", source);
+            return Err(ExitCode::Failure);
+        }
+    };
+    let output = match fs::create_file(dest) {
+        Some(handle) => handle,
+        None => {
+            syscall::close(input);
+            error!("Could not write to '{}'", dest);
+            return Err(ExitCode::Failure);
+        }
+    };
+    let mut res = Ok(());
+    let mut buf = vec![0; CHUNK_SIZE];
+    let mut copied = 0;
+    while copied < size {
+        match syscall::read(input, &mut buf) {
+            Some(0) => break,
+            Some(n) => {
+                if syscall::write(output, &buf[0..n]) != Some(n) {
+                    error!("Could not write to '{}'", dest);
+                    res = Err(ExitCode::Failure);
+                    break;
+                }
+                copied += n;
+                print!("\r{} {}%", dest, copied * 100 / size);
+            }
+            None => {
+                error!("Could not read file '{}'", source);
+                res = Err(ExitCode::Failure);
+                break;
+            }
+        }
+    }
+    println!();
+    syscall::close(input);
+    syscall::close(output);
+    res
+}
+
+pub fn destination(source: &str, dest: &str) -> String {
     debug_assert!(!dest.is_empty());
     let mut dest = dest.trim_end_matches('/').to_string();
     if dest.is_empty() || fs::is_dir(&dest) {
-        let file = fs::filename(source);
+        let file = fs::filename(source.trim_end_matches('/'));
         dest = format!("{}/{}", dest, file);
     }
     dest
@@ -62,9 +212,24 @@ fn help() {
     let csi_title = Style::color("Yellow");
     let csi_reset = Style::reset();
     println!(
-        "{}Usage:{} copy {}<src> <dst>{}",
+        "{}Usage:{} copy {}<options> <src> <dst>{}",
         csi_title, csi_reset, csi_option, csi_reset
     );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-r{1}, {0}--recursive{1}   Copy directories recursively",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-p{1}, {0}--preserve{1}    Preserve access and modification \
+        times",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-v{1}, {0}--verbose{1}     Show copied files and progress",
+        csi_option, csi_reset
+    );
 }
 
 #[test_case]
@@ -87,3 +252,26 @@ fn test_destination() {
 
     sys::fs::dismount();
 }
+
+#[test_case]
+fn test_copy_dir() {
+    use crate::{usr, sys};
+
+    sys::fs::mount_mem();
+    sys::fs::format_mem();
+    usr::install::copy_files(false);
+
+    assert!(main(&["copy", "/tmp/life", "/tmp/data"]).is_err());
+    assert!(main(&["copy", "-r", "/tmp/life", "/tmp/data"]).is_ok());
+    assert_eq!(
+        fs::read_to_bytes("/tmp/data/wing.cells"),
+        fs::read_to_bytes("/tmp/life/wing.cells")
+    );
+    assert!(main(&["copy", "-r", "/tmp", "/tmp/data"]).is_err());
+
+    assert!(fs::utime("/tmp/alice.txt", 1000, 2000).is_ok());
+    assert!(main(&["copy", "-p", "/tmp/alice.txt", "/tmp/bob.txt"]).is_ok());
+    assert_eq!(syscall::info("/tmp/bob.txt").map(|i| i.mtime()), Some(2000));
+
+    sys::fs::dismount();
+}
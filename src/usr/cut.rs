@@ -0,0 +1,180 @@
+use crate::api::console::Style;
+use crate::api::fs;
+use crate::api::io;
+use crate::api::process::ExitCode;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+type Ranges = Vec<(usize, usize)>;
+
+enum Mode {
+    Fields(Ranges),
+    Chars(Ranges),
+}
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut delimiter = "\t";
+    let mut mode = None;
+    let mut paths = Vec::new();
+    let mut i = 1;
+    let n = args.len();
+    while i < n {
+        match args[i] {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-d" | "--delimiter" => {
+                if i + 1 < n && !args[i + 1].is_empty() {
+                    delimiter = args[i + 1];
+                    i += 1;
+                } else {
+                    error!("Missing delimiter");
+                    return Err(ExitCode::UsageError);
+                }
+            }
+            "-f" | "--fields" | "-c" | "--chars" => {
+                let is_fields = args[i] == "-f" || args[i] == "--fields";
+                if i + 1 < n {
+                    if let Some(ranges) = parse_ranges(args[i + 1]) {
+                        mode = Some(if is_fields {
+                            Mode::Fields(ranges)
+                        } else {
+                            Mode::Chars(ranges)
+                        });
+                    } else {
+                        error!("Invalid list '{}'", args[i + 1]);
+                        return Err(ExitCode::UsageError);
+                    }
+                    i += 1;
+                } else {
+                    error!("Missing list");
+                    return Err(ExitCode::UsageError);
+                }
+            }
+            arg => paths.push(arg),
+        }
+        i += 1;
+    }
+    let mode = match mode {
+        Some(mode) => mode,
+        None => {
+            help();
+            return Err(ExitCode::UsageError);
+        }
+    };
+
+    let mut inputs = Vec::new();
+    if paths.is_empty() {
+        inputs.push(io::stdin().read_to_string());
+    }
+    for path in paths {
+        if let Ok(contents) = fs::read_to_string(path) {
+            inputs.push(contents);
+        } else {
+            error!("Could not read '{}'", path);
+            return Err(ExitCode::Failure);
+        }
+    }
+    for input in inputs {
+        for line in input.lines() {
+            println!("{}", cut(line, &mode, delimiter));
+        }
+    }
+    Ok(())
+}
+
+fn cut(line: &str, mode: &Mode, delimiter: &str) -> String {
+    match mode {
+        Mode::Fields(ranges) => {
+            // Lines without delimiter are printed unchanged
+            if !line.contains(delimiter) {
+                return line.into();
+            }
+            let fields: Vec<&str> = line.split(delimiter).enumerate().
+                filter(|(i, _)| contains(ranges, i + 1)).
+                map(|(_, field)| field).collect();
+            fields.join(delimiter)
+        }
+        Mode::Chars(ranges) => {
+            line.chars().enumerate().
+                filter(|(i, _)| contains(ranges, i + 1)).
+                map(|(_, c)| c).collect()
+        }
+    }
+}
+
+fn contains(ranges: &Ranges, i: usize) -> bool {
+    ranges.iter().any(|(a, b)| *a <= i && i <= *b)
+}
+
+// Parse lists of 1-indexed ranges like "1,3-5,7-" or "-2"
+fn parse_ranges(list: &str) -> Option<Ranges> {
+    let mut ranges = Vec::new();
+    for item in list.split(',') {
+        let range = if let Some((a, b)) = item.split_once('-') {
+            let a = if a.is_empty() { 1 } else { a.parse().ok()? };
+            let b = if b.is_empty() { usize::MAX } else { b.parse().ok()? };
+            (a, b)
+        } else {
+            let a = item.parse().ok()?;
+            (a, a)
+        };
+        if range.0 == 0 || range.0 > range.1 {
+            return None;
+        }
+        ranges.push(range);
+    }
+    Some(ranges)
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} cut {}<options> [<path>...]{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-f{1}, {0}--fields <list>{1}      Select fields in {0}<list>{1}",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-c{1}, {0}--chars <list>{1}       Select chars in {0}<list>{1}",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-d{1}, {0}--delimiter <str>{1}    Use {0}<str>{1} instead of \
+        tab for fields",
+        csi_option, csi_reset
+    );
+    println!();
+    println!("{}Lists:{}", csi_title, csi_reset);
+    println!(
+        "  {0}1,3-5,7-{1}   Select 1st, 3rd to 5th, and 7th to last",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_cut() {
+    use alloc::vec;
+
+    assert_eq!(parse_ranges("1,3-5,7-"), Some(vec![
+        (1, 1), (3, 5), (7, usize::MAX)
+    ]));
+    assert_eq!(parse_ranges("-2"), Some(vec![(1, 2)]));
+    assert_eq!(parse_ranges("0"), None);
+    assert_eq!(parse_ranges("3-1"), None);
+    assert_eq!(parse_ranges("a"), None);
+
+    let fields = Mode::Fields(parse_ranges("1,3-").unwrap());
+    assert_eq!(cut("a:b:c:d", &fields, ":"), "a:c:d");
+    assert_eq!(cut("abcd", &fields, ":"), "abcd");
+    let chars = Mode::Chars(parse_ranges("2-3").unwrap());
+    assert_eq!(cut("héllo", &chars, ":"), "él");
+}
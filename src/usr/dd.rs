@@ -0,0 +1,325 @@
+use crate::api::console::Style;
+use crate::api::fs;
+use crate::api::fs::SeekFrom;
+use crate::api::process::ExitCode;
+use crate::api::syscall;
+use crate::sys::ata::BLOCK_SIZE;
+
+use alloc::vec;
+
+#[derive(Default)]
+struct Config<'a> {
+    input: Option<&'a str>,
+    output: Option<&'a str>,
+    bs: usize,
+    count: Option<usize>,
+    skip: usize,
+    seek: usize,
+}
+
+#[derive(Default)]
+struct Stats {
+    full_in: usize,
+    partial_in: usize,
+    full_out: usize,
+    partial_out: usize,
+    bytes: usize,
+}
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut config = Config { bs: BLOCK_SIZE, ..Default::default() };
+    for arg in &args[1..] {
+        if *arg == "-h" || *arg == "--help" {
+            help();
+            return Ok(());
+        }
+        let (key, val) = match arg.split_once('=') {
+            Some(operand) => operand,
+            None => {
+                error!("Invalid operand '{}'", arg);
+                return Err(ExitCode::UsageError);
+            }
+        };
+        match key {
+            "if" => config.input = Some(val),
+            "of" => config.output = Some(val),
+            _ => {
+                let n = match parse_number(val) {
+                    Some(n) => n,
+                    None => {
+                        error!("Invalid number '{}'", val);
+                        return Err(ExitCode::UsageError);
+                    }
+                };
+                match key {
+                    "bs" if n > 0 => config.bs = n,
+                    "count" => config.count = Some(n),
+                    "skip" => config.skip = n,
+                    "seek" => config.seek = n,
+                    _ => {
+                        error!("Invalid operand '{}'", arg);
+                        return Err(ExitCode::UsageError);
+                    }
+                }
+            }
+        }
+    }
+    if config.input.is_none() {
+        error!("Missing input file");
+        return Err(ExitCode::UsageError);
+    }
+
+    let stats = copy(&config)?;
+    eprintln!("{}+{} records in", stats.full_in, stats.partial_in);
+    eprintln!("{}+{} records out", stats.full_out, stats.partial_out);
+    eprintln!("{} bytes copied", stats.bytes);
+    Ok(())
+}
+
+fn copy(config: &Config) -> Result<Stats, ExitCode> {
+    let input = config.input.unwrap_or_default();
+    let reader = match open(input) {
+        Some(handle) => handle,
+        None => {
+            error!("Could not open '{}'", input);
+            return Err(ExitCode::Failure);
+        }
+    };
+    let writer = match config.output {
+        Some(path) if fs::is_device(path) => fs::open_device(path),
+        Some(path) if config.seek > 0 && fs::exists(path) => {
+            fs::open_file(path)
+        }
+        Some(path) => fs::create_file(path),
+        None => Some(1), // Standard output
+    };
+    let writer = match writer {
+        Some(handle) => handle,
+        None => {
+            error!("Could not open '{}'", config.output.unwrap_or_default());
+            syscall::close(reader);
+            return Err(ExitCode::Failure);
+        }
+    };
+
+    let res = copy_blocks(reader, writer, config);
+    syscall::close(reader);
+    if writer != 1 {
+        syscall::close(writer);
+    }
+    if res.is_err() {
+        error!("Could not copy '{}'", input);
+    }
+    res
+}
+
+fn copy_blocks(
+    reader: usize,
+    writer: usize,
+    config: &Config
+) -> Result<Stats, ExitCode> {
+    let mut stats = Stats::default();
+    let mut buf = vec![0; config.bs];
+
+    // Files are moved forward with a seek, and devices by reading them
+    let skip = SeekFrom::Start((config.skip * config.bs) as u32);
+    if config.skip > 0 && syscall::seek(reader, skip).is_none() {
+        for _ in 0..config.skip {
+            if read_block(reader, &mut buf)? < config.bs {
+                return Ok(stats);
+            }
+        }
+    }
+
+    // Files shorter than the position of the first block are extended
+    // with zeros after reading them to their end.
+    let seek = SeekFrom::Start((config.seek * config.bs) as u32);
+    if writer != 1 && config.seek > 0 && syscall::seek(writer, seek).is_none() {
+        for _ in 0..config.seek {
+            let n = read_block(writer, &mut buf)?;
+            if n < config.bs {
+                buf.fill(0);
+                write_block(writer, &buf[n..])?;
+            }
+        }
+    }
+
+    let count = config.count.unwrap_or(usize::MAX);
+    while stats.full_in + stats.partial_in < count {
+        let n = read_block(reader, &mut buf)?;
+        if n == 0 {
+            break;
+        }
+        if n == config.bs {
+            stats.full_in += 1;
+        } else {
+            stats.partial_in += 1;
+        }
+        let m = write_block(writer, &buf[0..n])?;
+        if m == config.bs {
+            stats.full_out += 1;
+        } else {
+            stats.partial_out += 1;
+        }
+        stats.bytes += m;
+        if n < config.bs {
+            break;
+        }
+    }
+    Ok(stats)
+}
+
+fn open(path: &str) -> Option<usize> {
+    if fs::is_device(path) {
+        fs::open_device(path)
+    } else {
+        fs::open_file(path)
+    }
+}
+
+// Drives are read and written one sector at a time so the buffer is split
+// into chunks of that size.
+fn read_block(handle: usize, buf: &mut [u8]) -> Result<usize, ExitCode> {
+    let mut i = 0;
+    while i < buf.len() {
+        let j = (i + BLOCK_SIZE).min(buf.len());
+        match syscall::read(handle, &mut buf[i..j]) {
+            Some(0) => break,
+            Some(n) => i += n,
+            None => return Err(ExitCode::Failure),
+        }
+    }
+    Ok(i)
+}
+
+fn write_block(handle: usize, buf: &[u8]) -> Result<usize, ExitCode> {
+    let mut i = 0;
+    for chunk in buf.chunks(BLOCK_SIZE) {
+        match syscall::write(handle, chunk) {
+            Some(n) if n > 0 => i += n,
+            _ => return Err(ExitCode::Failure),
+        }
+    }
+    Ok(i)
+}
+
+// Parse a number of bytes with an optional multiplier
+pub fn parse_number(s: &str) -> Option<usize> {
+    let (num, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => s.split_at(i),
+        None => (s, ""),
+    };
+    let n: usize = num.parse().ok()?;
+    let m = match unit {
+        "" => 1,
+        "b" => BLOCK_SIZE,
+        "k" | "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        _ => return None,
+    };
+    n.checked_mul(m)
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} dd {}if=<file> [<operand>...]{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Operands:{}", csi_title, csi_reset);
+    println!(
+        "  {0}if=<file>{1}    Read from {0}<file>{1}",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}of=<file>{1}    Write to {0}<file>{1} instead of standard output",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}bs=<n>{1}       Copy {0}<n>{1} bytes at a time (default: 512)",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}count=<n>{1}    Copy only {0}<n>{1} blocks",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}skip=<n>{1}     Skip {0}<n>{1} blocks at the start of the input",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}seek=<n>{1}     Skip {0}<n>{1} blocks at the start of the output",
+        csi_option, csi_reset
+    );
+    println!();
+    println!("{}Units:{}", csi_title, csi_reset);
+    println!(
+        "  {0}b{1} (512), {0}k{1} (1024), {0}M{1} (1024k), {0}G{1} (1024M)",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_dd() {
+    use crate::sys;
+    use alloc::vec::Vec;
+
+    assert_eq!(parse_number("42"), Some(42));
+    assert_eq!(parse_number("2b"), Some(1024));
+    assert_eq!(parse_number("4k"), Some(4096));
+    assert_eq!(parse_number("1M"), Some(1048576));
+    assert_eq!(parse_number("1x"), None);
+    assert_eq!(parse_number("k"), None);
+
+    sys::fs::mount_mem();
+    sys::fs::format_mem();
+    let buf: Vec<u8> = (0..200).collect();
+    fs::write("/a", &buf).ok();
+    fs::write("/b", b"xyz").ok();
+    let config = Config {
+        input: Some("/a"),
+        output: Some("/b"),
+        bs: 64,
+        count: Some(2),
+        skip: 1,
+        seek: 1,
+    };
+    let stats = copy(&config).ok().unwrap();
+    assert_eq!(stats.full_in, 2);
+    assert_eq!(stats.bytes, 128);
+    let res = fs::read_to_bytes("/b").unwrap();
+    assert_eq!(res.len(), 192);
+    assert_eq!(&res[0..4], b"xyz\0");
+    assert_eq!(&res[64..192], &buf[64..192]);
+
+    // Patch the middle of a file
+    fs::write("/d", &[0xFF; 256]).ok();
+    let config = Config {
+        input: Some("/a"),
+        output: Some("/d"),
+        bs: 64,
+        count: Some(1),
+        skip: 0,
+        seek: 1,
+    };
+    assert!(copy(&config).is_ok());
+    let res = fs::read_to_bytes("/d").unwrap();
+    assert_eq!(res.len(), 256);
+    assert_eq!(&res[64..128], &buf[0..64]);
+    assert_eq!(res[128], 0xFF);
+
+    let config = Config {
+        input: Some("/a"),
+        output: Some("/c"),
+        bs: 128,
+        ..Default::default()
+    };
+    let stats = copy(&config).ok().unwrap();
+    assert_eq!((stats.full_in, stats.partial_in), (1, 1));
+    assert_eq!(fs::read_to_bytes("/c"), Ok(buf));
+    sys::fs::dismount();
+}
@@ -1,26 +1,45 @@
 use crate::api::console::Style;
 use crate::api::fs;
+use crate::api::fs::FileInfo;
+use crate::api::io;
 use crate::api::process::ExitCode;
 use crate::api::syscall;
 
+use alloc::format;
+use alloc::vec::Vec;
+
+struct Config {
+    recursive: bool,
+    force: bool,
+    interactive: bool,
+}
+
 pub fn main(args: &[&str]) -> Result<(), ExitCode> {
-    let n = args.len();
-    if n < 2 {
-        help();
-        return Err(ExitCode::UsageError);
-    }
-    for i in 1..n {
-        match args[i] {
+    let mut config = Config {
+        recursive: false,
+        force: false,
+        interactive: false,
+    };
+    let mut paths = Vec::new();
+    for arg in &args[1..] {
+        match *arg {
             "-h" | "--help" => {
                 help();
                 return Ok(());
             }
-            _ => continue,
+            "-r" | "--recursive" => config.recursive = true,
+            "-f" | "--force" => config.force = true,
+            "-i" | "--interactive" => config.interactive = true,
+            _ => paths.push(*arg),
         }
     }
+    if paths.is_empty() {
+        help();
+        return Err(ExitCode::UsageError);
+    }
 
-    for arg in &args[1..] {
-        let mut pathname = *arg;
+    for path in paths {
+        let mut pathname = path;
 
         // The commands `delete /usr/alice/` and `delete /usr/alice`
         // are equivalent, but `delete /` should not be modified.
@@ -28,36 +47,118 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
             pathname = pathname.trim_end_matches('/');
         }
 
-        if !fs::exists(pathname) {
+        if !fs::exists(pathname) && !fs::is_link(pathname) {
+            if config.force {
+                continue;
+            }
             error!("Could not find file '{}'", pathname);
             return Err(ExitCode::Failure);
         }
 
-        if let Some(info) = syscall::info(pathname) {
-            if info.is_dir() && info.size() > 0 {
+        delete(pathname, &config)?;
+    }
+    Ok(())
+}
+
+// A symbolic link is deleted without following it to its target
+fn info(pathname: &str) -> Option<FileInfo> {
+    if fs::is_link(pathname) {
+        None
+    } else {
+        syscall::info(pathname)
+    }
+}
+
+fn delete(pathname: &str, config: &Config) -> Result<(), ExitCode> {
+    if let Some(info) = info(pathname) {
+        if info.is_dir() && info.size() > 0 {
+            if !config.recursive {
                 error!("Directory '{}' not empty", pathname);
                 return Err(ExitCode::Failure);
             }
+            if let Ok(files) = fs::read_dir(pathname) {
+                let sep = if pathname.ends_with('/') { "" } else { "/" };
+                for file in files {
+                    let path = format!("{}{}{}", pathname, sep, file.name());
+                    delete(&path, config)?;
+                }
+            }
         }
+    }
 
-        if fs::delete(pathname).is_err() {
-            error!("Could not delete file '{}'", pathname);
-            return Err(ExitCode::Failure);
+    if config.interactive {
+        if let Some(info) = info(pathname) {
+            if info.is_dir() && info.size() > 0 {
+                return Ok(()); // Some entries have been kept
+            }
         }
+        if !confirm(pathname) {
+            return Ok(());
+        }
+    }
+
+    if fs::delete(pathname).is_err() {
+        error!("Could not delete file '{}'", pathname);
+        return Err(ExitCode::Failure);
     }
     Ok(())
 }
 
+fn confirm(pathname: &str) -> bool {
+    let kind = if fs::is_link(pathname) {
+        "link"
+    } else if fs::is_dir(pathname) {
+        "directory"
+    } else {
+        "file"
+    };
+    print!("Delete {} '{}'? [y/N] ", kind, pathname);
+    io::stdin().read_line().trim() == "y"
+}
+
 fn help() {
     let csi_option = Style::color("LightCyan");
     let csi_title = Style::color("Yellow");
     let csi_reset = Style::reset();
     println!(
-        "{}Usage:{} delete {}<path>{}",
+        "{}Usage:{} delete {}<options> <path>{}",
         csi_title, csi_reset, csi_option, csi_reset
     );
     println!();
     println!("{}Paths:{}", csi_title, csi_reset);
     println!("  {0}<dir>/{1}     Delete directory", csi_option, csi_reset);
     println!("  {0}<file>{1}     Delete file", csi_option, csi_reset);
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-r{1}, {0}--recursive{1}     Delete directories recursively",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-f{1}, {0}--force{1}         Ignore missing files",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-i{1}, {0}--interactive{1}   Confirm each deletion",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_delete_recursive() {
+    use crate::{usr, sys};
+
+    sys::fs::mount_mem();
+    sys::fs::format_mem();
+    usr::install::copy_files(false);
+
+    assert!(main(&["delete", "/tmp/life"]).is_err());
+    assert!(fs::exists("/tmp/life/wing.cells"));
+    assert!(main(&["delete", "-r", "/tmp/life"]).is_ok());
+    assert!(!fs::exists("/tmp/life"));
+
+    assert!(main(&["delete", "/tmp/nope"]).is_err());
+    assert!(main(&["delete", "-f", "/tmp/nope"]).is_ok());
+
+    sys::fs::dismount();
 }
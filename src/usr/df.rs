@@ -0,0 +1,132 @@
+use crate::api::console::Style;
+use crate::api::fs;
+use crate::api::process::ExitCode;
+use crate::api::unit::SizeUnit;
+
+use alloc::vec::Vec;
+
+const USAGE_DEVICE: &str = "/dev/disk/usage";
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut unit = SizeUnit::None;
+    let mut blocks = false;
+    for arg in &args[1..] {
+        match *arg {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-k" | "--blocks" => blocks = true,
+            "-b" | "--binary-size" => unit = SizeUnit::Binary,
+            "-d" | "--decimal-size" => unit = SizeUnit::Decimal,
+            _ => {
+                help();
+                return Err(ExitCode::UsageError);
+            }
+        }
+    }
+
+    let contents = match fs::read_to_string(USAGE_DEVICE) {
+        Ok(contents) => contents,
+        Err(()) => {
+            error!("Could not read '{}'", USAGE_DEVICE);
+            return Err(ExitCode::Failure);
+        }
+    };
+    let unit = if blocks { SizeUnit::None } else { unit };
+    let mut rows = Vec::new();
+    for line in contents.lines() {
+        let fields: Vec<_> = line.splitn(4, ' ').collect();
+        if fields.len() != 4 {
+            continue;
+        }
+        let block_size: usize = fields[0].parse().unwrap_or(0);
+        let block_count: usize = fields[1].parse().unwrap_or(0);
+        let alloc_count: usize = fields[2].parse().unwrap_or(0);
+        let k = if blocks { 1 } else { block_size };
+        let size = block_count * k;
+        let used = alloc_count * k;
+        rows.push((fields[3], size, used));
+    }
+
+    let path_width = rows.iter().fold(4, |acc, (path, _, _)|
+        core::cmp::max(acc, path.len())
+    );
+    let width = rows.iter().fold(4, |acc, (_, size, used)|
+        [*size, *used, size - used].iter().fold(acc, |acc, num|
+            core::cmp::max(acc, unit.format(*num).len())
+        )
+    );
+    let color = Style::color("LightCyan");
+    let reset = Style::reset();
+    println!(
+        "{}{:path_width$} {:>width$} {:>width$} {:>width$} Use%{}",
+        color,
+        "Path",
+        "Size",
+        "Used",
+        "Free",
+        reset,
+        path_width = path_width,
+        width = width
+    );
+    for (path, size, used) in rows {
+        let free = size - used;
+        let percent = if size > 0 { used * 100 / size } else { 0 };
+        println!(
+            "{:path_width$} {:>width$} {:>width$} {:>width$} {:>3}%",
+            path,
+            unit.format(size),
+            unit.format(used),
+            unit.format(free),
+            percent,
+            path_width = path_width,
+            width = width
+        );
+    }
+    Ok(())
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} df {}<options>{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-k{1}, {0}--blocks{1}         Count blocks instead of bytes",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-b{1}, {0}--binary-size{1}    Use binary size",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-d{1}, {0}--decimal-size{1}   Use decimal size",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_df() {
+    use crate::{sys, usr};
+
+    sys::fs::mount_mem();
+    sys::fs::format_mem();
+    usr::install::copy_files(false);
+
+    assert!(fs::create_dir("/mnt").is_some());
+    assert!(usr::mount::main(&["mount", "mem", "/mnt"]).is_ok());
+    let usage = fs::read_to_string(USAGE_DEVICE).unwrap();
+    let paths: Vec<_> = usage.lines().filter_map(|line| {
+        line.splitn(4, ' ').nth(3)
+    }).collect();
+    assert_eq!(paths, ["/", "/mnt"]);
+    assert!(main(&["df"]).is_ok());
+
+    sys::fs::dismount();
+}
@@ -4,6 +4,8 @@ use crate::api::process::ExitCode;
 use crate::api::syscall;
 use crate::sys::console;
 use crate::sys::net;
+use crate::sys::net::dhcp::{self, Config, Lease, LeaseState, Reply};
+use crate::sys::net::dhcp::{CLIENT_PORT, PARAMETER_REQUEST_LIST, SERVER_PORT};
 use crate::usr::shell;
 
 use alloc::format;
@@ -11,23 +13,45 @@ use alloc::string::ToString;
 use alloc::vec;
 use alloc::vec::Vec;
 use smoltcp::iface::SocketSet;
-use smoltcp::socket::dhcpv4;
+use smoltcp::socket::{dhcpv4, udp};
 use smoltcp::time::Instant;
+use smoltcp::wire::{
+    DhcpMessageType, DhcpPacket, DhcpRepr, EthernetAddress, IpEndpoint,
+    Ipv4Address,
+};
 
 pub fn main(args: &[&str]) -> Result<(), ExitCode> {
     let mut verbose = false;
-    let dhcp_config;
+    let mut command = None;
 
-    for arg in args {
+    for arg in &args[1..] {
         match *arg {
             "-h" | "--help" => return help(),
             "-v" | "--verbose" => verbose = true,
-            _ => {}
+            "renew" | "release" if command.is_none() => command = Some(*arg),
+            _ => {
+                help()?;
+                return Err(ExitCode::UsageError);
+            }
         }
     }
 
-    if let Some((ref mut iface, ref mut device)) = *net::NET.lock() {
-        let dhcp_socket = dhcpv4::Socket::new();
+    match command {
+        Some("renew") => renew(verbose),
+        Some("release") => release(verbose),
+        _ => discover(verbose),
+    }
+}
+
+fn discover(verbose: bool) -> Result<(), ExitCode> {
+    let dhcp_config;
+    let mut packet_buffer = [0; 1500];
+
+    let mut interfaces = net::NET.lock();
+    if let Some(eth) = interfaces.first_mut() {
+        let (iface, device) = (&mut eth.iface, &mut eth.device);
+        let mut dhcp_socket = dhcpv4::Socket::new();
+        dhcp_socket.set_receive_packet_buffer(&mut packet_buffer);
         let mut sockets = SocketSet::new(vec![]);
         let dhcp_handle = sockets.add(dhcp_socket);
         if verbose {
@@ -53,9 +77,19 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
             match event {
                 None => {}
                 Some(dhcpv4::Event::Configured(config)) => {
-                    dhcp_config = Some(
-                        (config.address, config.router, config.dns_servers)
-                    );
+                    let now = clock::uptime();
+                    let ack = config.packet.as_ref().and_then(|packet| {
+                        DhcpRepr::parse(packet).ok()
+                    });
+                    let lease = ack.map(|ack| {
+                        let server = config.server.identifier;
+                        Lease::new(config.address, server, &ack, now)
+                    });
+                    dhcp_config = Some((Config {
+                        address: config.address,
+                        router: config.router,
+                        dns_servers: config.dns_servers.to_vec(),
+                    }, lease));
                     if verbose {
                         debug!("DHCP Offer received");
                     }
@@ -74,27 +108,212 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
         return Err(ExitCode::Failure);
     }
 
-    if let Some((address, router, dns_servers)) = dhcp_config {
-        shell::exec(&format!("net config ip {}", address)).ok();
-        shell::exec("net config ip").ok();
+    if let Some((config, lease)) = dhcp_config {
+        configure(&config, true);
+        if verbose {
+            if let Some(lease) = lease {
+                let duration = lease.expires_at - clock::uptime();
+                debug!("DHCP lease of {:.0} seconds", duration);
+            }
+        }
+        dhcp::set_lease(lease);
+        return Ok(());
+    }
+
+    Err(ExitCode::Failure)
+}
 
-        if let Some(router) = router {
-            shell::exec(&format!("net config gw {}", router)).ok();
-        } else {
-            shell::exec("net config gw 0.0.0.0").ok();
+fn renew(verbose: bool) -> Result<(), ExitCode> {
+    let lease = match dhcp::lease() {
+        Some(lease) => lease,
+        None => {
+            error!("No DHCP lease to renew");
+            return Err(ExitCode::Failure);
         }
-        shell::exec("net config gw").ok();
+    };
+    if lease.state(clock::uptime()) == LeaseState::Expired {
+        if verbose {
+            debug!("DHCP lease expired");
+        }
+        expire();
+        return discover(verbose);
+    }
+    match request(&lease, verbose) {
+        Ok(true) => Ok(()),
+        Ok(false) => discover(verbose),
+        Err(()) => {
+            error!("Could not renew DHCP lease");
+            Err(ExitCode::Failure)
+        }
+    }
+}
 
-        let dns: Vec<_> = dns_servers.iter().map(|s| s.to_string()).collect();
-        if !dns.is_empty() {
-            shell::exec(&format!("net config dns {}", dns.join(","))).ok();
+fn release(verbose: bool) -> Result<(), ExitCode> {
+    let lease = match dhcp::lease() {
+        Some(lease) => lease,
+        None => {
+            error!("No DHCP lease to release");
+            return Err(ExitCode::Failure);
         }
-        shell::exec("net config dns").ok();
+    };
+    dhcp::set_lease(None);
+    let res = match mac_address() {
+        Some(mac) => {
+            let mut repr = dhcp::message(DhcpMessageType::Release, mac);
+            repr.client_ip = lease.address.address();
+            repr.server_identifier = Some(lease.server);
+            exchange(&repr, lease.server, &lease, 0.5).map(|_| ())
+        }
+        None => Err(()),
+    };
+    if verbose {
+        debug!("DHCP Release transmitted");
+    }
+    deconfigure();
+    if res.is_err() {
+        error!("Could not send DHCP release");
+        return Err(ExitCode::Failure);
+    }
+    Ok(())
+}
 
-        return Ok(());
+// Extend the lease with a request sent to the server that granted it, or
+// broadcasted to any server once the rebinding time has been reached. The
+// address is removed when the server refuses the request.
+fn request(lease: &Lease, verbose: bool) -> Result<bool, ()> {
+    let rebinding = lease.state(clock::uptime()) == LeaseState::Rebinding;
+    let dest = if rebinding {
+        Ipv4Address::BROADCAST
+    } else {
+        lease.server
+    };
+    let mac = mac_address().ok_or(())?;
+
+    // The pending request of the kernel is cancelled for the reply to be
+    // received here
+    dhcp::set_lease(Some(*lease));
+
+    let mut repr = dhcp::message(DhcpMessageType::Request, mac);
+    repr.client_ip = lease.address.address();
+    repr.parameter_request_list = Some(&PARAMETER_REQUEST_LIST);
+    if verbose {
+        debug!("DHCP Request transmitted to {}", dest);
+    }
+    match exchange(&repr, dest, lease, 5.0) {
+        Ok(Some(Reply::Ack(config, lease))) => {
+            if verbose {
+                debug!("DHCP Ack received");
+            }
+            configure(&config, verbose);
+            dhcp::set_lease(Some(lease));
+            Ok(true)
+        }
+        Ok(Some(Reply::Nak)) => {
+            if verbose {
+                debug!("DHCP Nak received");
+            }
+            expire();
+            Ok(false)
+        }
+        _ => Err(()),
     }
+}
 
-    Err(ExitCode::Failure)
+// Send a message from the client port and wait for the reply of the server
+// until the timeout is reached
+fn exchange(repr: &DhcpRepr, dest: Ipv4Address, lease: &Lease, timeout: f64)
+    -> Result<Option<Reply>, ()>
+{
+    let mut buf = vec![0; repr.buffer_len()];
+    repr.emit(&mut DhcpPacket::new_unchecked(&mut buf)).map_err(|_| ())?;
+
+    let mut interfaces = net::NET.lock();
+    if let Some(eth) = interfaces.first_mut() {
+        let (iface, device) = (&mut eth.iface, &mut eth.device);
+        let rx_buffer = udp::PacketBuffer::new(
+            vec![udp::PacketMetadata::EMPTY; 4], vec![0; 4096]
+        );
+        let tx_buffer = udp::PacketBuffer::new(
+            vec![udp::PacketMetadata::EMPTY], vec![0; 1024]
+        );
+        let mut socket = udp::Socket::new(rx_buffer, tx_buffer);
+        socket.bind(CLIENT_PORT).map_err(|_| ())?;
+        let endpoint = IpEndpoint::new(dest.into(), SERVER_PORT);
+        socket.send_slice(&buf, endpoint).map_err(|_| ())?;
+        let mut sockets = SocketSet::new(vec![]);
+        let handle = sockets.add(socket);
+
+        // The client has to keep polling the interface for a while even
+        // when no reply is expected to resolve the hardware address of the
+        // server before transmitting the message.
+        let started = clock::realtime();
+        while clock::realtime() - started < timeout {
+            let ms = (clock::realtime() * 1000000.0) as i64;
+            let time = Instant::from_micros(ms);
+            iface.poll(time, device, &mut sockets);
+            let socket = sockets.get_mut::<udp::Socket>(handle);
+            while let Ok((data, _)) = socket.recv() {
+                let reply = DhcpPacket::new_checked(data).ok().and_then(|p| {
+                    dhcp::parse_reply(&p, repr.transaction_id, lease)
+                });
+                if reply.is_some() {
+                    return Ok(reply);
+                }
+            }
+            if console::end_of_text() || console::end_of_transmission() {
+                eprintln!();
+                return Err(());
+            }
+            syscall::sleep(0.01);
+        }
+        Ok(None)
+    } else {
+        Err(())
+    }
+}
+
+fn mac_address() -> Option<EthernetAddress> {
+    if let Some(eth) = net::NET.lock().first() {
+        let mac = eth.iface.hardware_addr();
+        return Some(EthernetAddress::from_bytes(mac.as_bytes()));
+    }
+    None
+}
+
+fn configure(config: &Config, print: bool) {
+    shell::exec(&format!("net config ip {}", config.address)).ok();
+    if print {
+        shell::exec("net config ip").ok();
+    }
+
+    if let Some(router) = config.router {
+        shell::exec(&format!("net config gw {}", router)).ok();
+    } else {
+        shell::exec("net config gw 0.0.0.0").ok();
+    }
+    if print {
+        shell::exec("net config gw").ok();
+    }
+
+    let dns: Vec<_> = config.dns_servers.iter().map(|s| {
+        s.to_string()
+    }).collect();
+    if !dns.is_empty() {
+        shell::exec(&format!("net config dns {}", dns.join(","))).ok();
+    }
+    if print {
+        shell::exec("net config dns").ok();
+    }
+}
+
+fn deconfigure() {
+    shell::exec("net config ip 0.0.0.0/0").ok();
+    shell::exec("net config gw 0.0.0.0").ok();
+}
+
+fn expire() {
+    dhcp::set_lease(None);
+    deconfigure();
 }
 
 fn help() -> Result<(), ExitCode> {
@@ -102,10 +321,20 @@ fn help() -> Result<(), ExitCode> {
     let csi_title = Style::color("Yellow");
     let csi_reset = Style::reset();
     println!(
-        "{}Usage:{} dhcp {}<options>{1}",
+        "{}Usage:{} dhcp {}<options> [<command>]{1}",
         csi_title, csi_reset, csi_option
     );
     println!();
+    println!("{}Commands:{}", csi_title, csi_reset);
+    println!(
+        "  {0}renew{1}                      Renew the lease",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}release{1}                    Release the lease",
+        csi_option, csi_reset
+    );
+    println!();
     println!("{}Options:{}", csi_title, csi_reset);
     println!(
         "  {0}-v{1}, {0}--verbose{1}              Increase verbosity",
@@ -0,0 +1,295 @@
+use crate::api::console::Style;
+use crate::api::fs;
+use crate::api::process::ExitCode;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+// Lines of context around the changes in unified mode
+const CONTEXT: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Edit {
+    Keep(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut unified = false;
+    let mut paths = Vec::new();
+    for arg in &args[1..] {
+        match *arg {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-u" | "--unified" => unified = true,
+            _ => paths.push(*arg),
+        }
+    }
+    if paths.len() != 2 {
+        help();
+        return Err(ExitCode::UsageError);
+    }
+
+    let mut contents = Vec::new();
+    for path in &paths {
+        if let Ok(text) = fs::read_to_string(path) {
+            contents.push(text);
+        } else {
+            error!("Could not read '{}'", path);
+            return Err(ExitCode::Failure);
+        }
+    }
+    let a: Vec<&str> = contents[0].lines().collect();
+    let b: Vec<&str> = contents[1].lines().collect();
+    let edits = diff(&a, &b);
+    if edits.iter().all(|edit| matches!(edit, Edit::Keep(_, _))) {
+        return Ok(());
+    }
+
+    let lines = if unified {
+        let mut lines = vec![
+            format!("--- {}", paths[0]),
+            format!("+++ {}", paths[1]),
+        ];
+        lines.extend(unified_format(&edits, &a, &b));
+        lines
+    } else {
+        normal_format(&edits, &a, &b)
+    };
+    let reset = Style::reset();
+    for line in lines {
+        let color = if line.starts_with("---") || line.starts_with("+++") {
+            Style::color("Yellow")
+        } else {
+            match line.chars().next() {
+                Some('-') | Some('<') => Style::color("LightRed"),
+                Some('+') | Some('>') => Style::color("LightGreen"),
+                Some('@') | Some('0'..='9') => Style::color("LightCyan"),
+                _ => reset,
+            }
+        };
+        println!("{}{}{}", color, line, reset);
+    }
+
+    // Like `cmp`, the exit code tells if the files are different
+    Err(ExitCode::Failure)
+}
+
+// See "An O(ND) Difference Algorithm and Its Variations" by Eugene W. Myers
+fn diff(a: &[&str], b: &[&str]) -> Vec<Edit> {
+    let n = a.len() as isize;
+    let m = b.len() as isize;
+    let max = n + m;
+    let offset = max + 1;
+    let mut v = vec![0; 2 * max as usize + 3];
+    let mut trace = Vec::new();
+
+    // Find the shortest edit script by following the furthest reaching
+    // paths on each diagonal `k` for an increasing number of edits `d`.
+    'search: for d in 0..=max {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let i = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[i - 1] < v[i + 1]) {
+                v[i + 1]
+            } else {
+                v[i - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[i] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    // Walk back the trace to build the edit script
+    let mut edits = Vec::new();
+    let mut x = n;
+    let mut y = m;
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let i = (k + offset) as usize;
+        let prev_k = if k == -d || (k != d && v[i - 1] < v[i + 1]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = v[(prev_k + offset) as usize];
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            edits.push(Edit::Keep(x as usize, y as usize));
+        }
+        if d > 0 {
+            if x == prev_x {
+                edits.push(Edit::Insert(prev_y as usize));
+            } else {
+                edits.push(Edit::Delete(prev_x as usize));
+            }
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    edits.reverse();
+    edits
+}
+
+// Return the positions in both files before each edit
+fn positions(edits: &[Edit]) -> Vec<(usize, usize)> {
+    let mut res = Vec::new();
+    let mut i = 0;
+    let mut j = 0;
+    for edit in edits {
+        res.push((i, j));
+        match edit {
+            Edit::Keep(_, _) => {
+                i += 1;
+                j += 1;
+            }
+            Edit::Delete(_) => i += 1,
+            Edit::Insert(_) => j += 1,
+        }
+    }
+    res.push((i, j));
+    res
+}
+
+fn unified_format(edits: &[Edit], a: &[&str], b: &[&str]) -> Vec<String> {
+    let pos = positions(edits);
+    let changes: Vec<usize> = (0..edits.len()).filter(|i| {
+        !matches!(edits[*i], Edit::Keep(_, _))
+    }).collect();
+
+    // Group the changes close enough to share their context
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for i in changes {
+        let start = i.saturating_sub(CONTEXT);
+        let end = (i + CONTEXT + 1).min(edits.len());
+        match hunks.last_mut() {
+            Some((_, prev_end)) if start <= *prev_end => *prev_end = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut lines = Vec::new();
+    for (start, end) in hunks {
+        let (i, j) = pos[start];
+        let (a_len, b_len) = (pos[end].0 - i, pos[end].1 - j);
+        lines.push(format!(
+            "@@ -{} +{} @@", hunk_range(i, a_len), hunk_range(j, b_len)
+        ));
+        for edit in &edits[start..end] {
+            lines.push(match edit {
+                Edit::Keep(i, _) => format!(" {}", a[*i]),
+                Edit::Delete(i) => format!("-{}", a[*i]),
+                Edit::Insert(j) => format!("+{}", b[*j]),
+            });
+        }
+    }
+    lines
+}
+
+fn hunk_range(start: usize, len: usize) -> String {
+    // An empty range starts at the line before the hunk
+    let start = if len == 0 { start } else { start + 1 };
+    if len == 1 {
+        format!("{}", start)
+    } else {
+        format!("{},{}", start, len)
+    }
+}
+
+fn normal_format(edits: &[Edit], a: &[&str], b: &[&str]) -> Vec<String> {
+    let pos = positions(edits);
+    let mut lines = Vec::new();
+    let mut k = 0;
+    while k < edits.len() {
+        if let Edit::Keep(_, _) = edits[k] {
+            k += 1;
+            continue;
+        }
+        let start = k;
+        while k < edits.len() && !matches!(edits[k], Edit::Keep(_, _)) {
+            k += 1;
+        }
+        let (i1, j1) = pos[start];
+        let (i2, j2) = pos[k];
+        let cmd = match (i2 > i1, j2 > j1) {
+            (true, true) => {
+                format!("{}c{}", line_range(i1, i2), line_range(j1, j2))
+            }
+            (true, false) => format!("{}d{}", line_range(i1, i2), j1),
+            _ => format!("{}a{}", i1, line_range(j1, j2)),
+        };
+        lines.push(cmd);
+        for line in &a[i1..i2] {
+            lines.push(format!("< {}", line));
+        }
+        if i2 > i1 && j2 > j1 {
+            lines.push("---".into());
+        }
+        for line in &b[j1..j2] {
+            lines.push(format!("> {}", line));
+        }
+    }
+    lines
+}
+
+fn line_range(start: usize, end: usize) -> String {
+    if end - start == 1 {
+        format!("{}", end)
+    } else {
+        format!("{},{}", start + 1, end)
+    }
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} diff {}<options> <path1> <path2>{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-u{1}, {0}--unified{1}   Use the unified output format",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_diff() {
+    let a = ["a", "b", "c", "a", "b", "b", "a"];
+    let b = ["c", "b", "a", "b", "a", "c"];
+    let edits = diff(&a, &b);
+    let n = edits.iter().filter(|e| !matches!(e, Edit::Keep(_, _))).count();
+    assert_eq!(n, 5); // The shortest edit script from the paper
+
+    assert_eq!(diff(&[], &[]), []);
+    assert_eq!(diff(&["a"], &[]), [Edit::Delete(0)]);
+    assert_eq!(diff(&[], &["a"]), [Edit::Insert(0)]);
+
+    let a = ["a", "b", "c"];
+    let b = ["a", "x", "c", "d"];
+    let edits = diff(&a, &b);
+    assert_eq!(normal_format(&edits, &a, &b), [
+        "2c2", "< b", "---", "> x", "3a4", "> d"
+    ]);
+    assert_eq!(unified_format(&edits, &a, &b), [
+        "@@ -1,3 +1,4 @@", " a", "-b", "+x", " c", "+d"
+    ]);
+}
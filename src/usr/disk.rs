@@ -29,7 +29,7 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
     }
 }
 
-fn parse_disk_path(pathname: &str) -> Result<(u8, u8), String> {
+pub fn parse_disk_path(pathname: &str) -> Result<(u8, u8), String> {
     let path: Vec<_> = pathname.split('/').collect();
     if !pathname.starts_with("/dev/ata/") || path.len() != 5 {
         return Err(format!("Could not find disk at '{}'", pathname));
@@ -0,0 +1,119 @@
+use crate::api::console::Style;
+use crate::api::fs;
+use crate::api::process::ExitCode;
+use crate::api::syscall;
+use crate::api::unit::SizeUnit;
+use crate::sys;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut path: &str = &sys::process::dir(); // TODO: use '.'
+    let mut summary = false;
+    let mut unit = SizeUnit::None;
+    for arg in &args[1..] {
+        match *arg {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-s" | "--summary" => summary = true,
+            "-b" | "--binary-size" => unit = SizeUnit::Binary,
+            "-d" | "--decimal-size" => unit = SizeUnit::Decimal,
+            _ => path = arg,
+        }
+    }
+
+    // The commands `du /usr/alice/` and `du /usr/alice` are equivalent,
+    // but `du /` should not be modified.
+    if path.len() > 1 {
+        path = path.trim_end_matches('/');
+    }
+
+    if let Some(info) = syscall::info(path) {
+        let mut sizes = Vec::new();
+        if info.is_dir() {
+            let size = dir_size(path, &mut sizes);
+            if summary {
+                sizes.clear();
+                sizes.push((size, path.into()));
+            }
+        } else {
+            sizes.push((info.size() as usize, path.into()));
+        }
+        let width = sizes.iter().fold(0, |acc, (size, _)|
+            core::cmp::max(acc, unit.format(*size).len())
+        );
+        for (size, path) in sizes {
+            println!("{:>width$} {}", unit.format(size), path, width = width);
+        }
+        Ok(())
+    } else {
+        error!("Could not find file or directory '{}'", path);
+        Err(ExitCode::Failure)
+    }
+}
+
+// Return the sum of the sizes of the files in the given directory and its
+// subdirectories, and add the size of each directory to the list.
+fn dir_size(path: &str, sizes: &mut Vec<(usize, String)>) -> usize {
+    let mut total = 0;
+    if let Ok(files) = fs::read_dir(path) {
+        let sep = if path.ends_with('/') { "" } else { "/" };
+        for file in files {
+            if file.is_dir() {
+                let subdir = format!("{}{}{}", path, sep, file.name());
+                total += dir_size(&subdir, sizes);
+            } else if file.is_file() {
+                total += file.size() as usize;
+            }
+        }
+    }
+    sizes.push((total, path.into()));
+    total
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} du {}<options> [<path>]{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-s{1}, {0}--summary{1}        Show only the total size",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-b{1}, {0}--binary-size{1}    Use binary size",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-d{1}, {0}--decimal-size{1}   Use decimal size",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_dir_size() {
+    sys::fs::mount_mem();
+    sys::fs::format_mem();
+
+    fs::create_dir("/a");
+    fs::create_dir("/a/b");
+    fs::write("/a/foo.txt", b"hello").ok();
+    fs::write("/a/b/bar.txt", b"world!").ok();
+
+    let mut sizes = Vec::new();
+    assert_eq!(dir_size("/a", &mut sizes), 11);
+    assert_eq!(sizes.len(), 2);
+    assert_eq!(sizes[0], (6, "/a/b".into()));
+    assert_eq!(sizes[1], (11, "/a".into()));
+
+    sys::fs::dismount();
+}
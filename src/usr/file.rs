@@ -0,0 +1,109 @@
+use crate::api::console::Style;
+use crate::api::fs;
+use crate::api::process::ExitCode;
+use crate::api::syscall;
+use crate::sys::process::{BIN_MAGIC, ELF_MAGIC};
+
+use alloc::format;
+use alloc::string::String;
+
+const SIGNATURES: [(&[u8], &str); 9] = [
+    (&ELF_MAGIC, "ELF executable"),
+    (&BIN_MAGIC, "MOROS executable"),
+    (b"\x89PNG\r\n\x1A\n", "PNG image"),
+    (b"GIF87a", "GIF image"),
+    (b"GIF89a", "GIF image"),
+    (b"\xFF\xD8\xFF", "JPEG image"),
+    (b"BM", "BMP image"),
+    (b"\x1F\x8B", "gzip compressed data"),
+    (b"PK\x03\x04", "Zip archive"),
+];
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    if args.len() < 2 {
+        help();
+        return Err(ExitCode::UsageError);
+    }
+    for arg in &args[1..] {
+        if *arg == "-h" || *arg == "--help" {
+            help();
+            return Ok(());
+        }
+    }
+    let width = args[1..].iter().map(|path| path.len()).max().unwrap_or(0);
+    for path in &args[1..] {
+        if let Some(description) = file(path) {
+            let path = format!("{}:", path);
+            println!("{:<width$} {}", path, description, width = width + 1);
+        } else {
+            error!("Could not read '{}'", path);
+            return Err(ExitCode::Failure);
+        }
+    }
+    Ok(())
+}
+
+fn file(path: &str) -> Option<String> {
+    let info = syscall::info(path)?;
+    if info.is_dir() {
+        return Some("directory".into());
+    }
+    if let Some(device) = info.device() {
+        return Some(format!("{:?} device", device));
+    }
+    let buf = fs::read_to_bytes(path).ok()?;
+    Some(sniff(&buf))
+}
+
+fn sniff(buf: &[u8]) -> String {
+    if buf.is_empty() {
+        return "empty".into();
+    }
+    for (magic, description) in SIGNATURES {
+        if buf.starts_with(magic) {
+            return description.into();
+        }
+    }
+    if buf.len() > 262 && &buf[257..262] == b"ustar" {
+        return "tar archive".into();
+    }
+    if buf.starts_with(b"#!") {
+        let line = buf.split(|b| *b == b'\n').next().unwrap_or(&[]);
+        return format!("script for {}", String::from_utf8_lossy(&line[2..]));
+    }
+    match core::str::from_utf8(buf) {
+        Ok(text) if text.chars().all(is_text) => {
+            if text.is_ascii() {
+                "ASCII text".into()
+            } else {
+                "UTF-8 text".into()
+            }
+        }
+        _ => "data".into(),
+    }
+}
+
+fn is_text(c: char) -> bool {
+    !c.is_control() || matches!(c, '\n' | '\r' | '\t' | '\x1B')
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} file {}<path>...{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_sniff() {
+    assert_eq!(sniff(b""), "empty");
+    assert_eq!(sniff(b"hello\n"), "ASCII text");
+    assert_eq!(sniff("héllo\n".as_bytes()), "UTF-8 text");
+    assert_eq!(sniff(b"\x7FELF\x02\x01"), "ELF executable");
+    assert_eq!(sniff(b"\x89PNG\r\n\x1A\n\0\0"), "PNG image");
+    assert_eq!(sniff(b"#!/bin/lisp\n(+ 1 2)"), "script for /bin/lisp");
+    assert_eq!(sniff(b"\x00\x01\x02"), "data");
+}
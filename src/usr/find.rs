@@ -1,12 +1,16 @@
 use crate::api::console::Style;
 use crate::api::fs;
 use crate::api::process::ExitCode;
+use crate::api::fs::{FileInfo, FileType};
 use crate::api::regex::Regex;
+use crate::api::syscall;
 use crate::sys;
+use crate::usr::shell;
 
 use alloc::format;
 use alloc::string::{String, ToString};
 use alloc::vec::Vec;
+use core::cmp::Ordering;
 use core::iter::FromIterator;
 
 struct PrintingState {
@@ -23,10 +27,54 @@ impl PrintingState {
     }
 }
 
-// > find /tmp -name *.txt -line hello
+// One of the creation, modification, or access time of a file
+type FileTime = fn(&FileInfo) -> u64;
+
+#[derive(Default)]
+struct Filter {
+    name: Option<Regex>,
+    kind: Option<FileType>,
+    size: Option<(Ordering, u64)>,
+    newer: Option<(FileTime, u64)>,
+}
+
+impl Filter {
+    fn is_empty(&self) -> bool {
+        self.name.is_none()
+            && self.kind.is_none()
+            && self.size.is_none()
+            && self.newer.is_none()
+    }
+
+    fn is_match(&self, file: &FileInfo) -> bool {
+        if let Some(re) = &self.name {
+            if !re.is_match(&file.name()) {
+                return false;
+            }
+        }
+        if let Some(kind) = self.kind {
+            if file.kind() != kind {
+                return false;
+            }
+        }
+        if let Some((ordering, size)) = self.size {
+            if (file.size() as u64).cmp(&size) != ordering {
+                return false;
+            }
+        }
+        if let Some((file_time, time)) = self.newer {
+            if file_time(file) <= time {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+// > find /tmp --name "*.txt" --type f --line hello
 pub fn main(args: &[&str]) -> Result<(), ExitCode> {
     let mut path: &str = &sys::process::dir(); // TODO: use '.'
-    let mut name = None;
+    let mut filter = Filter::default();
     let mut line = None;
     let mut i = 1;
     let n = args.len();
@@ -38,13 +86,67 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
             }
             "-n" | "--name" => {
                 if i + 1 < n {
-                    name = Some(args[i + 1]);
+                    let re = shell::glob_to_regex(args[i + 1]);
+                    filter.name = Some(Regex::new(&re));
                     i += 1;
                 } else {
                     error!("Missing name");
                     return Err(ExitCode::UsageError);
                 }
             }
+            "-t" | "--type" => {
+                if i + 1 < n {
+                    filter.kind = match args[i + 1] {
+                        "f" => Some(FileType::File),
+                        "d" => Some(FileType::Dir),
+                        "c" => Some(FileType::Device),
+                        "l" => Some(FileType::Link),
+                        _ => {
+                            error!("Invalid type '{}'", args[i + 1]);
+                            return Err(ExitCode::UsageError);
+                        }
+                    };
+                    i += 1;
+                } else {
+                    error!("Missing type");
+                    return Err(ExitCode::UsageError);
+                }
+            }
+            "-s" | "--size" => {
+                if i + 1 < n {
+                    if let Some(size) = parse_size(args[i + 1]) {
+                        filter.size = Some(size);
+                    } else {
+                        error!("Invalid size '{}'", args[i + 1]);
+                        return Err(ExitCode::UsageError);
+                    }
+                    i += 1;
+                } else {
+                    error!("Missing size");
+                    return Err(ExitCode::UsageError);
+                }
+            }
+            // The time of the files is compared to the modification time
+            // of the path
+            "--newer" | "--anewer" | "--cnewer" => {
+                let file_time: FileTime = match args[i] {
+                    "--anewer" => FileInfo::atime,
+                    "--cnewer" => FileInfo::ctime,
+                    _ => FileInfo::mtime,
+                };
+                if i + 1 < n {
+                    if let Some(info) = syscall::info(args[i + 1]) {
+                        filter.newer = Some((file_time, info.mtime()));
+                    } else {
+                        error!("Could not find file '{}'", args[i + 1]);
+                        return Err(ExitCode::Failure);
+                    }
+                    i += 1;
+                } else {
+                    error!("Missing file");
+                    return Err(ExitCode::UsageError);
+                }
+            }
             "-l" | "--line" => {
                 if i + 1 < n {
                     line = Some(args[i + 1]);
@@ -63,25 +165,83 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
         path = path.trim_end_matches('/');
     }
 
-    if name.is_none() && line.is_none() {
+    if filter.is_empty() && line.is_none() {
         usage();
         return Err(ExitCode::UsageError);
     }
 
-    if name.is_some() {
-        // TODO
-        error!("`--name` is not implemented");
+    if !fs::exists(path) {
+        error!("Could not find file or directory '{}'", path);
         return Err(ExitCode::Failure);
     }
 
     let mut state = PrintingState::new();
     if let Some(pattern) = line {
-        print_matching_lines(path, pattern, &mut state);
+        if filter.is_empty() {
+            print_matching_lines(path, pattern, &mut state);
+        } else {
+            state.is_recursive = true;
+            for file in find_files(path, &filter) {
+                if fs::is_file(&file) {
+                    print_matching_lines_in_file(&file, pattern, &mut state);
+                }
+            }
+        }
+    } else {
+        for file in find_files(path, &filter) {
+            println!("{}", file);
+        }
     }
 
     Ok(())
 }
 
+// Parse sizes like "512", "+4k" or "-1M" into the ordering that the size
+// of a file must have with the given number of bytes to match.
+fn parse_size(arg: &str) -> Option<(Ordering, u64)> {
+    let (ordering, arg) = if let Some(s) = arg.strip_prefix('+') {
+        (Ordering::Greater, s)
+    } else if let Some(s) = arg.strip_prefix('-') {
+        (Ordering::Less, s)
+    } else {
+        (Ordering::Equal, arg)
+    };
+    let (arg, multiplier) = match arg.chars().last() {
+        Some('k') | Some('K') => (&arg[..arg.len() - 1], 1 << 10),
+        Some('M') => (&arg[..arg.len() - 1], 1 << 20),
+        Some('G') => (&arg[..arg.len() - 1], 1 << 30),
+        _ => (arg, 1),
+    };
+    arg.parse::<u64>().ok().map(|size| (ordering, size * multiplier))
+}
+
+fn find_files(path: &str, filter: &Filter) -> Vec<String> {
+    let mut files = Vec::new();
+    if fs::is_dir(path) {
+        walk(path, filter, &mut files);
+    } else if let Some(info) = syscall::info(path) {
+        if filter.is_match(&info) {
+            files.push(path.to_string());
+        }
+    }
+    files
+}
+
+fn walk(path: &str, filter: &Filter, found: &mut Vec<String>) {
+    if let Ok(files) = fs::read_dir(path) {
+        let sep = if path.ends_with('/') { "" } else { "/" };
+        for file in files {
+            let file_path = format!("{}{}{}", path, sep, file.name());
+            if filter.is_match(&file) {
+                found.push(file_path.clone());
+            }
+            if file.is_dir() {
+                walk(&file_path, filter, found);
+            }
+        }
+    }
+}
+
 fn print_matching_lines(path: &str, pattern: &str, state: &mut PrintingState) {
     if let Ok(files) = fs::read_dir(path) {
         state.is_recursive = true;
@@ -186,4 +346,70 @@ fn usage() {
         Find lines matching {0}<pattern>{1}",
         csi_option, csi_reset
     );
+    println!(
+        "  {0}-t{1}, {0}--type <f|d|c|l>{1}      \
+        Find files, directories, devices, or links",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-s{1}, {0}--size [+-]<size>{1}     \
+        Find files bigger, smaller, or equal to {0}<size>{1}",
+        csi_option, csi_reset
+    );
+    println!(
+        "      {0}--newer <path>{1}        \
+        Find files modified after {0}<path>{1}",
+        csi_option, csi_reset
+    );
+    println!(
+        "      {0}--anewer <path>{1}       \
+        Find files accessed after {0}<path>{1} was modified",
+        csi_option, csi_reset
+    );
+    println!(
+        "      {0}--cnewer <path>{1}       \
+        Find files created after {0}<path>{1} was modified",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_find_files() {
+    use crate::usr;
+
+    sys::fs::mount_mem();
+    sys::fs::format_mem();
+    usr::install::copy_files(false);
+
+    let filter = Filter {
+        name: Some(Regex::new(&shell::glob_to_regex("*.cells"))),
+        ..Default::default()
+    };
+    let files = find_files("/tmp", &filter);
+    assert!(files.contains(&"/tmp/life/wing.cells".to_string()));
+    assert!(files.iter().all(|f| f.ends_with(".cells")));
+
+    let filter = Filter {
+        kind: Some(FileType::Dir),
+        ..Default::default()
+    };
+    assert!(find_files("/tmp", &filter).contains(&"/tmp/life".to_string()));
+
+    let filter = Filter {
+        size: parse_size("+1M"),
+        ..Default::default()
+    };
+    assert!(find_files("/tmp", &filter).is_empty());
+
+    let filter = Filter {
+        newer: Some((FileInfo::ctime, u64::MAX)),
+        ..Default::default()
+    };
+    assert!(find_files("/tmp", &filter).is_empty());
+
+    assert_eq!(parse_size("4k"), Some((Ordering::Equal, 4096)));
+    assert_eq!(parse_size("-12"), Some((Ordering::Less, 12)));
+    assert_eq!(parse_size("+x"), None);
+
+    sys::fs::dismount();
 }
@@ -0,0 +1,100 @@
+use crate::api::console::Style;
+use crate::api::io;
+use crate::api::process::ExitCode;
+use crate::api::syscall;
+use crate::sys;
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut is_yes = false;
+    let mut path = None;
+    for arg in &args[1..] {
+        match *arg {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-y" | "--yes" => is_yes = true,
+            _ if path.is_none() => path = Some(*arg),
+            _ => {
+                help();
+                return Err(ExitCode::UsageError);
+            }
+        }
+    }
+    let path = path.unwrap_or("/");
+    let dev = match sys::fs::mfs_device(path) {
+        Some(dev) => dev,
+        None => {
+            error!("Could not find filesystem mounted on '{}'", path);
+            return Err(ExitCode::Failure);
+        }
+    };
+
+    // The entries of a dir are only checked after its blocks have been
+    // repaired, so the filesystem is checked again after each repair
+    let mut last = None;
+    let res = loop {
+        let problems = sys::fs::check(dev);
+        if problems.is_empty() {
+            break Ok(());
+        }
+        if last.as_ref() == Some(&problems) {
+            break Err(ExitCode::Failure);
+        }
+        let mut is_repaired = true;
+        for problem in &problems {
+            println!("{}", problem);
+            if !is_yes && !confirm(problem.repair_info()) {
+                is_repaired = false;
+            } else if problem.repair(dev).is_err() {
+                error!("Could not repair filesystem");
+                is_repaired = false;
+            }
+        }
+        if !is_repaired {
+            break Err(ExitCode::Failure);
+        }
+        last = Some(problems);
+    };
+    if last.is_some() && syscall::sync().is_err() {
+        error!("Could not write cached blocks to disk");
+        return Err(ExitCode::Failure);
+    }
+    res
+}
+
+fn confirm(action: &str) -> bool {
+    print!("{}? [y/N] ", action);
+    io::stdin().read_line().trim() == "y"
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} fsck {}[<options>] [<path>]{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-y{1}, {0}--yes{1}   Repair problems without confirmation",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_fsck() {
+    use crate::api::fs;
+
+    sys::fs::mount_mem();
+    sys::fs::format_mem();
+
+    assert!(fs::write("/a.txt", b"a").is_ok());
+    assert!(main(&["fsck"]).is_ok());
+    assert!(main(&["fsck", "-y", "/"]).is_ok());
+    assert!(main(&["fsck", "/tmp"]).is_err());
+
+    sys::fs::dismount();
+}
@@ -0,0 +1,487 @@
+use crate::api::console::Style;
+use crate::api::fs;
+use crate::api::process::ExitCode;
+use crate::api::prompt::Prompt;
+use crate::api::syscall;
+use crate::sys::console;
+use crate::sys::fs::OpenFlag;
+use crate::usr;
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::str::FromStr;
+use smoltcp::wire::IpAddress;
+
+const DEFAULT_PORT: u16 = 21;
+const ANONYMOUS_USER: &str = "anonymous";
+const ANONYMOUS_PASSWORD: &str = "moros@";
+
+// A client keeps the control connection open during the session, and
+// opens a new data connection in passive mode for each transfer.
+struct Client {
+    addr: IpAddress,
+    control: usize,
+    buf: Vec<u8>,
+    buf_len: usize,
+    verbose: bool,
+}
+
+impl Client {
+    fn connect(addr: IpAddress, port: u16, verbose: bool) -> Result<Self, ()> {
+        let socket_path = "/dev/net/tcp";
+        let buf_len = match syscall::info(socket_path) {
+            Some(info) => info.size() as usize,
+            None => {
+                error!("Could not open '{}'", socket_path);
+                return Err(());
+            }
+        };
+        let control = open_connection(addr, port)?;
+        let buf = Vec::new();
+        let mut client = Self { addr, control, buf, buf_len, verbose };
+        match client.reply() {
+            Ok((220, _)) => Ok(client),
+            _ => {
+                error!("Could not connect to server");
+                client.close();
+                Err(())
+            }
+        }
+    }
+
+    fn login(&mut self, user: &str, password: &str) -> Result<(), ()> {
+        let code = match self.command(&format!("USER {}", user))? {
+            (331, _) => self.command(&format!("PASS {}", password))?.0,
+            (code, _) => code,
+        };
+        if code != 230 {
+            error!("Could not login as '{}'", user);
+            return Err(());
+        }
+        self.command("TYPE I")?;
+        Ok(())
+    }
+
+    fn command(&mut self, cmd: &str) -> Result<(u16, String), ()> {
+        if self.verbose {
+            if cmd.starts_with("PASS ") {
+                debug!("PASS ****");
+            } else {
+                debug!("{}", cmd);
+            }
+        }
+        let req = format!("{}\r\n", cmd);
+        if syscall::write(self.control, req.as_bytes()).is_none() {
+            error!("Could not send command");
+            return Err(());
+        }
+        self.reply()
+    }
+
+    fn reply(&mut self) -> Result<(u16, String), ()> {
+        loop {
+            if let Some((code, text, n)) = parse_reply(&self.buf) {
+                self.buf.drain(..n);
+                if self.verbose {
+                    debug!("{} {}", code, text);
+                }
+                return Ok((code, text));
+            }
+            if console::end_of_text() || console::end_of_transmission() {
+                return Err(());
+            }
+            let mut data = vec![0; self.buf_len];
+            match syscall::read(self.control, &mut data) {
+                Some(0) | None => {
+                    error!("Connection closed by server");
+                    return Err(());
+                }
+                Some(n) => self.buf.extend_from_slice(&data[..n]),
+            }
+        }
+    }
+
+    // Send a command transferring data on a new connection to the port
+    // given by the server, whose address is ignored in favor of the one of
+    // the control connection in case the server is behind a NAT.
+    fn transfer(&mut self, cmd: &str, upload: Option<&[u8]>)
+        -> Result<Vec<u8>, ()>
+    {
+        let port = match self.command("PASV")? {
+            (227, text) => match parse_pasv(&text) {
+                Some(port) => port,
+                None => {
+                    error!("Could not parse passive mode reply");
+                    return Err(());
+                }
+            },
+            (_, text) => {
+                error!("{}", text);
+                return Err(());
+            }
+        };
+        let data = open_connection(self.addr, port)?;
+        let res = self.transfer_data(data, cmd, upload);
+        syscall::close(data);
+        let res = res?;
+        match self.reply()? {
+            (226, _) | (250, _) => Ok(res),
+            (_, text) => {
+                error!("{}", text);
+                Err(())
+            }
+        }
+    }
+
+    fn transfer_data(&mut self, data: usize, cmd: &str, upload: Option<&[u8]>)
+        -> Result<Vec<u8>, ()>
+    {
+        match self.command(cmd)? {
+            (125, _) | (150, _) => {}
+            (_, text) => {
+                error!("{}", text);
+                return Err(());
+            }
+        }
+        let mut res = Vec::new();
+        if let Some(buf) = upload {
+            let mut i = 0;
+            while i < buf.len() {
+                if console::end_of_text() || console::end_of_transmission() {
+                    return Err(());
+                }
+                let j = (i + self.buf_len).min(buf.len());
+                match syscall::write(data, &buf[i..j]) {
+                    Some(n) if n > 0 => i += n,
+                    _ => {
+                        error!("Could not send data");
+                        return Err(());
+                    }
+                }
+            }
+        } else {
+            loop {
+                if console::end_of_text() || console::end_of_transmission() {
+                    return Err(());
+                }
+                let mut buf = vec![0; self.buf_len];
+                match syscall::read(data, &mut buf) {
+                    Some(0) | None => break,
+                    Some(n) => res.extend_from_slice(&buf[..n]),
+                }
+            }
+        }
+        Ok(res)
+    }
+
+    fn close(&mut self) {
+        syscall::close(self.control);
+    }
+}
+
+fn open_connection(addr: IpAddress, port: u16) -> Result<usize, ()> {
+    let socket_path = "/dev/net/tcp";
+    let flags = OpenFlag::Device as usize;
+    if let Some(handle) = syscall::open(socket_path, flags) {
+        if syscall::connect(handle, addr, port).is_ok() {
+            return Ok(handle);
+        }
+        syscall::close(handle);
+    }
+    error!("Could not connect to {}:{}", addr, port);
+    Err(())
+}
+
+// Parse a complete reply made of a code followed by a space on its last
+// line, the lines before it starting with the code followed by a dash,
+// and return it with the number of bytes it used.
+fn parse_reply(buf: &[u8]) -> Option<(u16, String, usize)> {
+    let mut start = 0;
+    let mut code = None;
+    let mut text = String::new();
+    while let Some(i) = buf[start..].iter().position(|&b| b == b'\n') {
+        let end = start + i + 1;
+        let line = String::from_utf8_lossy(&buf[start..end]);
+        let line = line.trim_end();
+        start = end;
+        let n = match line.get(..3).and_then(|s| s.parse::<u16>().ok()) {
+            Some(n) => n,
+            None => continue, // Continuation line of a multiline reply
+        };
+        if code.is_none() {
+            code = Some(n);
+            text = line[3..].trim_start_matches(['-', ' ']).to_string();
+        }
+        if code == Some(n) && line.as_bytes().get(3) != Some(&b'-') {
+            return Some((n, text, end));
+        }
+    }
+    None
+}
+
+// Return the port of a passive mode reply like
+// "Entering Passive Mode (192,168,1,2,195,80)"
+fn parse_pasv(text: &str) -> Option<u16> {
+    let i = text.find('(')?;
+    let j = text[i..].find(')')? + i;
+    let fields: Vec<_> = text[i + 1..j].split(',').map(|s| {
+        s.trim().parse::<u8>().ok()
+    }).collect::<Option<_>>()?;
+    if fields.len() != 6 {
+        return None;
+    }
+    Some(u16::from_be_bytes([fields[4], fields[5]]))
+}
+
+fn resolve(host: &str) -> Result<IpAddress, ExitCode> {
+    if host.ends_with(char::is_numeric) {
+        match IpAddress::from_str(host) {
+            Ok(addr) => Ok(addr),
+            Err(_) => {
+                error!("Invalid address '{}'", host);
+                Err(ExitCode::UsageError)
+            }
+        }
+    } else {
+        match usr::host::resolve(host) {
+            Ok(addr) => Ok(addr),
+            Err(e) => {
+                error!("Could not resolve host {:?}", e);
+                Err(ExitCode::Failure)
+            }
+        }
+    }
+}
+
+fn exec(client: &mut Client, args: &[&str]) -> Result<(), ()> {
+    match args {
+        ["ls"] | ["list"] => list(client, None),
+        ["ls", path] | ["list", path] => list(client, Some(path)),
+        ["cd", path] => {
+            match client.command(&format!("CWD {}", path))? {
+                (250, _) => Ok(()),
+                (_, text) => {
+                    error!("{}", text);
+                    Err(())
+                }
+            }
+        }
+        ["pwd"] => {
+            match client.command("PWD")? {
+                (257, text) => {
+                    let path = text.split('"').nth(1).unwrap_or(&text);
+                    println!("{}", path);
+                    Ok(())
+                }
+                (_, text) => {
+                    error!("{}", text);
+                    Err(())
+                }
+            }
+        }
+        ["get", remote] => get(client, remote, fs::filename(remote)),
+        ["get", remote, local] => get(client, remote, local),
+        ["put", local] => put(client, local, fs::filename(local)),
+        ["put", local, remote] => put(client, local, remote),
+        _ => {
+            error!("Invalid command");
+            Err(())
+        }
+    }
+}
+
+fn list(client: &mut Client, path: Option<&str>) -> Result<(), ()> {
+    let cmd = match path {
+        Some(path) => format!("LIST {}", path),
+        None => "LIST".to_string(),
+    };
+    let buf = client.transfer(&cmd, None)?;
+    print!("{}", String::from_utf8_lossy(&buf).replace("\r\n", "\n"));
+    Ok(())
+}
+
+fn get(client: &mut Client, remote: &str, local: &str) -> Result<(), ()> {
+    let buf = client.transfer(&format!("RETR {}", remote), None)?;
+    if fs::write(local, &buf).is_err() {
+        error!("Could not write to '{}'", local);
+        return Err(());
+    }
+    if client.verbose {
+        debug!("Received {} bytes", buf.len());
+    }
+    Ok(())
+}
+
+fn put(client: &mut Client, local: &str, remote: &str) -> Result<(), ()> {
+    let buf = match fs::read_to_bytes(local) {
+        Ok(buf) => buf,
+        Err(()) => {
+            error!("Could not read '{}'", local);
+            return Err(());
+        }
+    };
+    client.transfer(&format!("STOR {}", remote), Some(&buf))?;
+    if client.verbose {
+        debug!("Sent {} bytes", buf.len());
+    }
+    Ok(())
+}
+
+fn repl(client: &mut Client) {
+    let csi_color = Style::color("Cyan");
+    let csi_reset = Style::reset();
+    let prompt_string = format!("{}ftp>{} ", csi_color, csi_reset);
+
+    let mut prompt = Prompt::new();
+    while let Some(line) = prompt.input(&prompt_string) {
+        let args: Vec<_> = line.split_whitespace().collect();
+        match args[..] {
+            [] => {
+                println!();
+                continue;
+            }
+            ["q"] | ["quit"] => break,
+            _ => exec(client, &args).ok(),
+        };
+        prompt.history.add(&line);
+    }
+}
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut user = ANONYMOUS_USER;
+    let mut password = ANONYMOUS_PASSWORD;
+    let mut verbose = false;
+    let mut params = Vec::new();
+    let mut i = 1;
+    let n = args.len();
+    while i < n {
+        match args[i] {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-v" | "--verbose" => {
+                verbose = true;
+            }
+            "-u" | "--user" if i + 1 < n => {
+                user = args[i + 1];
+                i += 1;
+            }
+            "-p" | "--password" if i + 1 < n => {
+                password = args[i + 1];
+                i += 1;
+            }
+            arg if arg.starts_with('-') && params.is_empty() => {
+                error!("Invalid option '{}'", arg);
+                return Err(ExitCode::UsageError);
+            }
+            arg => {
+                params.push(arg);
+            }
+        }
+        i += 1;
+    }
+    if params.is_empty() {
+        help();
+        return Err(ExitCode::UsageError);
+    }
+
+    let (host, port) = match params[0].split_once(':') {
+        Some((host, port)) => match port.parse() {
+            Ok(port) => (host, port),
+            Err(_) => {
+                error!("Could not parse port '{}'", port);
+                return Err(ExitCode::UsageError);
+            }
+        },
+        None => (params[0], DEFAULT_PORT),
+    };
+    let addr = resolve(host)?;
+    let mut client = match Client::connect(addr, port, verbose) {
+        Ok(client) => client,
+        Err(()) => return Err(ExitCode::Failure),
+    };
+    let mut res = client.login(user, password);
+    if res.is_ok() {
+        if params.len() > 1 {
+            res = exec(&mut client, &params[1..]);
+        } else {
+            repl(&mut client);
+        }
+    }
+    client.command("QUIT").ok();
+    client.close();
+    res.map_err(|_| ExitCode::Failure)
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} ftp {}<options> <host>[:<port>] [<command>]{1}",
+        csi_title, csi_reset, csi_option
+    );
+    println!();
+    println!("{}Commands:{}", csi_title, csi_reset);
+    println!(
+        "  {}ls [<path>]{}               List a directory",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {}cd <path>{}                 Change directory",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {}pwd{}                       Print directory",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {}get <remote> [<local>]{}    Download a file",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {}put <local> [<remote>]{}    Upload a file",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {}quit{}                      Close the session",
+        csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-u{1}, {0}--user <name>{1}         Login as user instead of {2}",
+        csi_option, csi_reset, ANONYMOUS_USER
+    );
+    println!(
+        "  {0}-p{1}, {0}--password <pass>{1}     Password of the user",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-v{1}, {0}--verbose{1}             Show the commands and replies",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_parse_reply() {
+    assert_eq!(parse_reply(b"220 Ready"), None);
+    assert_eq!(
+        parse_reply(b"220 Ready\r\n331 Password"),
+        Some((220, "Ready".to_string(), 11))
+    );
+    let buf = b"230-Welcome\r\n  to MOROS\r\n230-\r\n230 Logged in\r\n";
+    assert_eq!(parse_reply(&buf[0..25]), None);
+    assert_eq!(parse_reply(buf), Some((230, "Welcome".to_string(), buf.len())));
+}
+
+#[test_case]
+fn test_parse_pasv() {
+    let text = "Entering Passive Mode (192,168,1,2,195,80).";
+    assert_eq!(parse_pasv(text), Some(50000));
+    assert_eq!(parse_pasv("Entering Passive Mode (192,168,1,2,195)"), None);
+    assert_eq!(parse_pasv("Entering Passive Mode"), None);
+}
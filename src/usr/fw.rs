@@ -0,0 +1,264 @@
+use crate::api::console::Style;
+use crate::api::fs;
+use crate::api::process::ExitCode;
+use crate::sys::net::firewall;
+use crate::sys::net::firewall::{Action, Direction, Protocol, Rule};
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::str::FromStr;
+use smoltcp::wire::{Ipv4Address, Ipv4Cidr};
+
+const FW_FILE: &str = "/ini/fw";
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    match *args.get(1).unwrap_or(&"list") {
+        "-h" | "--help" => {
+            help();
+            Ok(())
+        }
+        "save" if args.len() == 2 => save(),
+        "load" if args.len() == 2 => load(),
+        _ => command(&args[1..]),
+    }
+}
+
+// Run a command of the firewall, that can also be a line of its
+// configuration file
+fn command(args: &[&str]) -> Result<(), ExitCode> {
+    match *args.first().unwrap_or(&"list") {
+        "list" if args.len() <= 1 => {
+            list();
+            Ok(())
+        }
+        "policy" if args.len() == 3 => {
+            let direction = parse_direction(args[1]);
+            let action = parse_action(args[2]);
+            if let (Some(direction), Some(action)) = (direction, action) {
+                firewall::set_policy(direction, action);
+                Ok(())
+            } else {
+                help();
+                Err(ExitCode::UsageError)
+            }
+        }
+        "delete" if args.len() == 2 => {
+            let removed = args[1].parse::<usize>().ok().and_then(|n| {
+                n.checked_sub(1).and_then(firewall::remove)
+            });
+            if removed.is_some() {
+                Ok(())
+            } else {
+                error!("Could not find rule '{}'", args[1]);
+                Err(ExitCode::Failure)
+            }
+        }
+        "flush" if args.len() == 1 => {
+            firewall::flush();
+            Ok(())
+        }
+        "allow" | "deny" => {
+            if let Some(rule) = parse_rule(args) {
+                firewall::add(rule);
+                Ok(())
+            } else {
+                error!("Could not parse rule '{}'", args.join(" "));
+                Err(ExitCode::UsageError)
+            }
+        }
+        _ => {
+            help();
+            Err(ExitCode::UsageError)
+        }
+    }
+}
+
+fn list() {
+    let csi_color = Style::color("LightCyan");
+    let csi_reset = Style::reset();
+    for direction in [Direction::In, Direction::Out] {
+        let action = firewall::policy(direction);
+        println!("{}policy{} {} {}", csi_color, csi_reset, direction, action);
+    }
+    for (i, rule) in firewall::rules().iter().enumerate() {
+        println!("{}{}{} {}", csi_color, i + 1, csi_reset, rule);
+    }
+}
+
+// Save the policies and the rules in a file made of lines of commands
+fn save() -> Result<(), ExitCode> {
+    let mut contents = String::new();
+    for direction in [Direction::In, Direction::Out] {
+        let action = firewall::policy(direction);
+        contents.push_str(&format!("policy {} {}\n", direction, action));
+    }
+    for rule in firewall::rules() {
+        contents.push_str(&format!("{}\n", rule));
+    }
+    if fs::write(FW_FILE, contents.as_bytes()).is_err() {
+        error!("Could not write to '{}'", FW_FILE);
+        return Err(ExitCode::Failure);
+    }
+    Ok(())
+}
+
+fn load() -> Result<(), ExitCode> {
+    if !fs::exists(FW_FILE) {
+        return Ok(()); // Nothing to load without a configuration
+    }
+    let contents = match fs::read_to_string(FW_FILE) {
+        Ok(contents) => contents,
+        Err(()) => {
+            error!("Could not read '{}'", FW_FILE);
+            return Err(ExitCode::Failure);
+        }
+    };
+    firewall::flush();
+    for line in contents.lines() {
+        let args: Vec<_> = line.split_whitespace().collect();
+        if args.is_empty() || args[0] == "list" {
+            continue;
+        }
+        command(&args)?;
+    }
+    Ok(())
+}
+
+fn parse_action(s: &str) -> Option<Action> {
+    match s {
+        "allow" => Some(Action::Allow),
+        "deny" => Some(Action::Deny),
+        _ => None,
+    }
+}
+
+fn parse_direction(s: &str) -> Option<Direction> {
+    match s {
+        "in" => Some(Direction::In),
+        "out" => Some(Direction::Out),
+        _ => None,
+    }
+}
+
+fn parse_addr(s: &str) -> Option<Ipv4Cidr> {
+    if s.contains('/') {
+        Ipv4Cidr::from_str(s).ok()
+    } else {
+        Ipv4Address::from_str(s).ok().map(|addr| Ipv4Cidr::new(addr, 32))
+    }
+}
+
+// Parse a rule made of an action, a direction, and a protocol, followed
+// by an optional port and an optional address after 'from' or 'to'
+fn parse_rule(args: &[&str]) -> Option<Rule> {
+    let action = parse_action(args.first()?)?;
+    let direction = parse_direction(args.get(1)?)?;
+    let protocol = match *args.get(2)? {
+        "any" => None,
+        "icmp" => Some(Protocol::Icmp),
+        "tcp" => Some(Protocol::Tcp),
+        "udp" => Some(Protocol::Udp),
+        _ => return None,
+    };
+    let mut i = 3;
+    let mut port = None;
+    if let Some(arg) = args.get(i) {
+        if let Ok(n) = arg.parse::<u16>() {
+            match protocol {
+                Some(Protocol::Tcp) | Some(Protocol::Udp) => port = Some(n),
+                _ => return None,
+            }
+            i += 1;
+        }
+    }
+    let mut addr = None;
+    let keyword = match direction {
+        Direction::In => "from",
+        Direction::Out => "to",
+    };
+    if args.get(i) == Some(&keyword) {
+        addr = Some(parse_addr(args.get(i + 1)?)?);
+        i += 2;
+    }
+    if i != args.len() {
+        return None;
+    }
+    Some(Rule { action, direction, protocol, port, addr })
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} fw {}<command>{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!(
+        "{}Usage:{} fw {}allow|deny in|out <protocol> [<port>] \
+        [from|to <address>]{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Commands:{}", csi_title, csi_reset);
+    println!(
+        "  {}list{}                   Display the policies and the rules",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {}policy <dir> <action>{}  Set the action of unmatched packets",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {}delete <number>{}        Delete a rule",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {}flush{}                  Delete all the rules",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {}save{}                   Save the configuration to '{}'",
+        csi_option, csi_reset, FW_FILE
+    );
+    println!(
+        "  {}load{}                   Load the configuration from '{}'",
+        csi_option, csi_reset, FW_FILE
+    );
+    println!();
+    println!("{}Protocols:{}", csi_title, csi_reset);
+    println!(
+        "  {0}any{1}, {0}icmp{1}, {0}tcp{1}, {0}udp{1}",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_parse_rule() {
+    let rules = [
+        "allow in tcp 80",
+        "deny in any from 10.0.2.0/24",
+        "allow in udp 68 from 10.0.2.2/32",
+        "deny out icmp to 1.1.1.1/32",
+    ];
+    for rule in rules {
+        let args: Vec<_> = rule.split(' ').collect();
+        assert_eq!(format!("{}", parse_rule(&args).unwrap()), rule);
+    }
+
+    let rule = parse_rule(&["deny", "out", "tcp", "25", "to", "10.0.2.2"]);
+    assert_eq!(rule, Some(Rule {
+        action: Action::Deny,
+        direction: Direction::Out,
+        protocol: Some(Protocol::Tcp),
+        port: Some(25),
+        addr: Some(Ipv4Cidr::new(Ipv4Address::new(10, 0, 2, 2), 32)),
+    }));
+
+    assert_eq!(parse_rule(&["allow", "in", "any", "80"]), None);
+    assert_eq!(parse_rule(&["allow", "in", "icmp", "80"]), None);
+    assert_eq!(parse_rule(&["allow", "in", "tcp", "to", "10.0.2.2"]), None);
+    assert_eq!(parse_rule(&["allow", "in", "tcp", "80", "443"]), None);
+    assert_eq!(parse_rule(&["allow", "in"]), None);
+}
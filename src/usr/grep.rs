@@ -0,0 +1,255 @@
+use crate::api::console::Style;
+use crate::api::fs;
+use crate::api::io;
+use crate::api::process::ExitCode;
+use crate::api::regex::Regex;
+use crate::sys;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::iter::FromIterator;
+
+struct Config {
+    ignore_case: bool,
+    line_number: bool,
+    invert: bool,
+    count: bool,
+    recursive: bool,
+    show_name: bool,
+}
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut config = Config {
+        ignore_case: false,
+        line_number: false,
+        invert: false,
+        count: false,
+        recursive: false,
+        show_name: false,
+    };
+    let mut pattern = None;
+    let mut paths = Vec::new();
+    for arg in &args[1..] {
+        match *arg {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-i" | "--ignore-case" => config.ignore_case = true,
+            "-n" | "--line-number" => config.line_number = true,
+            "-v" | "--invert-match" => config.invert = true,
+            "-c" | "--count" => config.count = true,
+            "-r" | "--recursive" => config.recursive = true,
+            _ => {
+                if pattern.is_none() {
+                    pattern = Some(*arg);
+                } else {
+                    paths.push(*arg);
+                }
+            }
+        }
+    }
+    let pattern = match pattern {
+        Some(pattern) => pattern,
+        None => {
+            help();
+            return Err(ExitCode::UsageError);
+        }
+    };
+    let re = if config.ignore_case {
+        Regex::new(&lowercase_pattern(pattern))
+    } else {
+        Regex::new(pattern)
+    };
+
+    let cwd = sys::process::dir(); // TODO: use '.'
+    if paths.is_empty() && config.recursive {
+        paths.push(&cwd);
+    }
+    config.show_name = config.recursive || paths.len() > 1;
+
+    let mut found = false;
+    if paths.is_empty() {
+        let text = io::stdin().read_to_string();
+        found = grep_text(&re, "", &text, &config);
+    }
+    for path in paths {
+        if fs::is_dir(path) {
+            if config.recursive {
+                found |= grep_dir(&re, path, &config);
+            } else {
+                warning!("Skipping directory '{}'", path);
+            }
+        } else if let Ok(text) = fs::read_to_string(path) {
+            found |= grep_text(&re, path, &text, &config);
+        } else {
+            error!("Could not read file '{}'", path);
+            return Err(ExitCode::Failure);
+        }
+    }
+
+    if found {
+        Ok(())
+    } else {
+        Err(ExitCode::Failure)
+    }
+}
+
+fn grep_dir(re: &Regex, path: &str, config: &Config) -> bool {
+    let mut found = false;
+    if let Ok(files) = fs::read_dir(path) {
+        let sep = if path.ends_with('/') { "" } else { "/" };
+        for file in files {
+            let file_path = format!("{}{}{}", path, sep, file.name());
+            if file.is_dir() {
+                found |= grep_dir(re, &file_path, config);
+            } else if file.is_file() {
+                // Binary files are skipped because they are not valid UTF-8
+                if let Ok(text) = fs::read_to_string(&file_path) {
+                    found |= grep_text(re, &file_path, &text, config);
+                }
+            }
+        }
+    }
+    found
+}
+
+fn grep_text(re: &Regex, path: &str, text: &str, config: &Config) -> bool {
+    let name_color = Style::color("Yellow");
+    let line_color = Style::color("LightCyan");
+    let reset = Style::reset();
+
+    let prefix = if config.show_name {
+        format!("{}{}{}:", name_color, path, reset)
+    } else {
+        String::new()
+    };
+    let mut count = 0;
+    for (i, line) in text.lines().enumerate() {
+        let highlighted = highlight(re, line, config.ignore_case);
+        let line = match (highlighted, config.invert) {
+            (Some(highlighted), false) => highlighted,
+            (None, true) => line.into(),
+            _ => continue,
+        };
+        count += 1;
+        if config.count {
+            continue;
+        }
+        if config.line_number {
+            println!("{}{}{}{}:{}", prefix, line_color, i + 1, reset, line);
+        } else {
+            println!("{}{}", prefix, line);
+        }
+    }
+    if config.count {
+        println!("{}{}", prefix, count);
+    }
+    count > 0
+}
+
+// Return the line with its matches colored, or nothing if the pattern
+// doesn't match.
+pub fn highlight(re: &Regex, line: &str, ignore_case: bool) -> Option<String> {
+    let match_color = Style::color("LightRed");
+    let reset = Style::reset();
+
+    let chars: Vec<char> = line.chars().collect();
+    let text: Vec<char> = if ignore_case {
+        chars.iter().map(|c| lowercase(*c)).collect()
+    } else {
+        chars.clone()
+    };
+    let mut res = String::new();
+    let mut found = false;
+    let mut i = 0;
+    while let Some((a, b)) = re.find(&String::from_iter(&text[i..])) {
+        let m = i + a;
+        let n = i + b;
+        let before = String::from_iter(&chars[i..m]);
+        let matched = String::from_iter(&chars[m..n]);
+        res = format!("{}{}{}{}{}", res, before, match_color, matched, reset);
+        found = true;
+        i = n;
+        if m == n || n >= chars.len() {
+            // Empty matches would never move the cursor on the line
+            break;
+        }
+    }
+    if found {
+        res.push_str(&String::from_iter(&chars[i..]));
+        Some(res)
+    } else {
+        None
+    }
+}
+
+// Convert chars one by one to keep the positions of the matches unchanged
+fn lowercase(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+// Lowercase every char of the pattern except the escaped ones, otherwise
+// `\W` would become `\w`.
+fn lowercase_pattern(pattern: &str) -> String {
+    let mut res = String::new();
+    let mut escaped = false;
+    for c in pattern.chars() {
+        if escaped {
+            res.push(c);
+            escaped = false;
+        } else {
+            escaped = c == '\\';
+            res.push(lowercase(c));
+        }
+    }
+    res
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} grep {}<options> <pattern> [<path>...]{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-i{1}, {0}--ignore-case{1}    Ignore case distinctions",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-n{1}, {0}--line-number{1}    Print line numbers",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-v{1}, {0}--invert-match{1}   Select non-matching lines",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-c{1}, {0}--count{1}          Print only the count of lines",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-r{1}, {0}--recursive{1}      Search directories recursively",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_highlight() {
+    let re = Regex::new("b+");
+    assert!(highlight(&re, "aaa", false).is_none());
+    let line = highlight(&re, "abba", false).unwrap();
+    assert!(line.starts_with('a') && line.ends_with('a'));
+    assert!(line.contains("bb"));
+
+    let re = Regex::new(&lowercase_pattern("HELLO\\W"));
+    assert!(highlight(&re, "Hello, World!", false).is_none());
+    assert!(highlight(&re, "Hello, World!", true).is_some());
+    assert!(highlight(&re, "Hello World!", true).is_some());
+    assert!(highlight(&re, "HelloWorld!", true).is_none());
+}
@@ -0,0 +1,114 @@
+use crate::api::console::Style;
+use crate::api::fs;
+use crate::api::gzip;
+use crate::api::process::ExitCode;
+use crate::api::syscall;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+const EXT: &str = ".gz";
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let cmd = args[0];
+    let mut decompress = cmd == "gunzip";
+    let mut keep = false;
+    let mut stdout = false;
+    let mut paths = Vec::new();
+    for arg in &args[1..] {
+        match *arg {
+            "-h" | "--help" => {
+                help(cmd);
+                return Ok(());
+            }
+            "-d" | "--decompress" => decompress = true,
+            "-k" | "--keep" => keep = true,
+            "-c" | "--stdout" => stdout = true,
+            _ if arg.starts_with('-') => {
+                error!("Unknown option '{}'", arg);
+                return Err(ExitCode::UsageError);
+            }
+            _ => paths.push(*arg),
+        }
+    }
+    if paths.is_empty() {
+        help(cmd);
+        return Err(ExitCode::UsageError);
+    }
+
+    for path in paths {
+        let dest = if decompress {
+            match path.strip_suffix(EXT) {
+                Some(dest) if !dest.is_empty() => String::from(dest),
+                _ => {
+                    error!("Unknown suffix in '{}'", path);
+                    return Err(ExitCode::Failure);
+                }
+            }
+        } else {
+            format!("{}{}", path, EXT)
+        };
+        if !stdout && fs::exists(&dest) {
+            error!("Could not overwrite '{}'", dest);
+            return Err(ExitCode::Failure);
+        }
+
+        let input = match fs::read_to_bytes(path) {
+            Ok(buf) => buf,
+            Err(_) => {
+                error!("Could not read '{}'", path);
+                return Err(ExitCode::Failure);
+            }
+        };
+        let output = if decompress {
+            match gzip::decompress(&input) {
+                Ok(buf) => buf,
+                Err(_) => {
+                    error!("Could not decompress '{}'", path);
+                    return Err(ExitCode::DataError);
+                }
+            }
+        } else {
+            gzip::compress(&input)
+        };
+
+        if stdout {
+            syscall::write(1, &output);
+            continue;
+        }
+        if fs::write(&dest, &output).is_err() {
+            error!("Could not write to '{}'", dest);
+            return Err(ExitCode::Failure);
+        }
+        if !keep && fs::delete(path).is_err() {
+            error!("Could not delete '{}'", path);
+            return Err(ExitCode::Failure);
+        }
+    }
+    Ok(())
+}
+
+fn help(cmd: &str) {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} {} {}<options> <file>...{}",
+        csi_title, csi_reset, cmd, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-d{1}, {0}--decompress{1}   Decompress files",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-k{1}, {0}--keep{1}         Keep input files",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-c{1}, {0}--stdout{1}       Write to standard output",
+        csi_option, csi_reset
+    );
+}
@@ -1,19 +1,52 @@
 use crate::api;
-use crate::api::console::Style;
+use crate::api::console::{self, Style};
 use crate::api::process::ExitCode;
 use crate::api::syscall;
 
 use alloc::format;
 use alloc::string::String;
 use alloc::vec::Vec;
+use core::convert::TryInto;
 use core::str;
 use sha2::{Digest, Sha256};
 
+#[derive(Clone, Copy)]
+enum Algorithm {
+    Sha256,
+    Sha1,
+    Md5,
+}
+
+impl Algorithm {
+    fn from(name: &str) -> Option<Self> {
+        match name {
+            "sha256" => Some(Algorithm::Sha256),
+            "sha1" => Some(Algorithm::Sha1),
+            "md5" => Some(Algorithm::Md5),
+            _ => None,
+        }
+    }
+
+    fn hash(&self, buf: &[u8]) -> Vec<u8> {
+        match self {
+            Algorithm::Sha256 => {
+                let mut hasher = Sha256::new();
+                hasher.update(buf);
+                hasher.finalize().to_vec()
+            }
+            Algorithm::Sha1 => sha1(buf).to_vec(),
+            Algorithm::Md5 => md5(buf).to_vec(),
+        }
+    }
+}
+
 pub fn main(args: &[&str]) -> Result<(), ExitCode> {
     let mut i = 1;
     let n = args.len();
     let mut paths = Vec::new();
     let mut full = false;
+    let mut check = false;
+    let mut algo = Algorithm::Sha256;
     while i < n {
         match args[i] {
             "-h" | "--help" => {
@@ -23,6 +56,23 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
             "-f" | "--full" => {
                 full = true;
             }
+            "-c" | "--check" => {
+                check = true;
+            }
+            "-a" | "--algo" => {
+                if i + 1 < n {
+                    if let Some(a) = Algorithm::from(args[i + 1]) {
+                        algo = a;
+                    } else {
+                        error!("Unknown algorithm '{}'", args[i + 1]);
+                        return Err(ExitCode::UsageError);
+                    }
+                    i += 1;
+                } else {
+                    error!("Missing algorithm");
+                    return Err(ExitCode::UsageError);
+                }
+            }
             arg => {
                 if arg.starts_with('-') {
                     error!("Unknown option '{}'", arg);
@@ -34,28 +84,31 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
         i += 1;
     }
 
+    if check {
+        let mut res = Ok(());
+        for path in paths {
+            if check_hashes(path, algo).is_err() {
+                res = Err(ExitCode::Failure);
+            }
+        }
+        return res;
+    }
+
     paths.sort();
     for path in paths {
-        print_hash(path, full)?;
+        print_hash(path, full, algo)?;
     }
     Ok(())
 }
 
-pub fn print_hash(path: &str, full: bool) -> Result<(), ExitCode> {
-    let n = if full { 32 } else { 16 };
+fn hash_file(path: &str, algo: Algorithm) -> Result<String, ExitCode> {
     if let Some(info) = syscall::info(path) {
         if info.is_file() {
             if let Ok(bytes) = api::fs::read_to_bytes(path) {
-                let mut hasher = Sha256::new();
-                hasher.update(bytes);
-                let res = hasher.finalize();
-                let hex = res.iter().map(|byte|
+                let hex = algo.hash(&bytes).iter().map(|byte|
                     format!("{:02X}", byte)
-                ).take(n).collect::<Vec<String>>().join("");
-                let pink = Style::color("Pink");
-                let reset = Style::reset();
-                println!("{}{}{} {}", pink, hex, reset, path);
-                Ok(())
+                ).collect::<Vec<String>>().join("");
+                Ok(hex)
             } else {
                 error!("Could not read '{}'", path);
                 Err(ExitCode::Failure)
@@ -70,18 +123,198 @@ pub fn print_hash(path: &str, full: bool) -> Result<(), ExitCode> {
     }
 }
 
+fn print_hash(
+    path: &str,
+    full: bool,
+    algo: Algorithm
+) -> Result<(), ExitCode> {
+    let mut hex = hash_file(path, algo)?;
+    if !full {
+        hex.truncate(hex.len() / 2);
+    }
+    let pink = Style::color("Pink");
+    let reset = Style::reset();
+    println!("{}{}{} {}", pink, hex, reset, path);
+    Ok(())
+}
+
+// Verify the hashes listed in a file with the format of the output of the
+// command. Short hashes are compared with the beginning of the full hashes.
+fn check_hashes(path: &str, algo: Algorithm) -> Result<(), ExitCode> {
+    let contents = match api::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(_) => {
+            error!("Could not read '{}'", path);
+            return Err(ExitCode::Failure);
+        }
+    };
+    let green = Style::color("LightGreen");
+    let red = Style::color("LightRed");
+    let reset = Style::reset();
+    let mut res = Ok(());
+    for line in contents.lines() {
+        let line = console::strip_escapes(line);
+        let (expected, file) = match line.trim().split_once(' ') {
+            Some((hex, file)) if !hex.is_empty() => (hex, file.trim()),
+            _ => continue,
+        };
+        let ok = match hash_file(file, algo) {
+            Ok(hex) => hex.starts_with(&expected.to_uppercase()),
+            Err(_) => false,
+        };
+        if ok {
+            println!("{}: {}OK{}", file, green, reset);
+        } else {
+            println!("{}: {}FAILED{}", file, red, reset);
+            res = Err(ExitCode::Failure);
+        }
+    }
+    res
+}
+
+// See RFC 3174
+fn sha1(buf: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [
+        0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0
+    ];
+    for chunk in pad(buf, false).chunks(64) {
+        let mut w = [0u32; 80];
+        for i in 0..16 {
+            w[i] = u32::from_be_bytes(chunk[i * 4..i * 4 + 4].try_into().
+                unwrap());
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, wi) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let t = a.rotate_left(5).wrapping_add(f).wrapping_add(e).
+                wrapping_add(k).wrapping_add(*wi);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = t;
+        }
+        for (x, y) in h.iter_mut().zip([a, b, c, d, e]) {
+            *x = x.wrapping_add(y);
+        }
+    }
+    let mut res = [0; 20];
+    for (i, x) in h.iter().enumerate() {
+        res[i * 4..i * 4 + 4].copy_from_slice(&x.to_be_bytes());
+    }
+    res
+}
+
+// See RFC 1321
+fn md5(buf: &[u8]) -> [u8; 16] {
+    const S: [u32; 64] = [
+        7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22,
+        5,  9, 14, 20, 5,  9, 14, 20, 5,  9, 14, 20, 5,  9, 14, 20,
+        4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23,
+        6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+    ];
+    let mut k = [0u32; 64];
+    for (i, x) in k.iter_mut().enumerate() {
+        *x = (libm::fabs(libm::sin(i as f64 + 1.0)) * 4294967296.0) as u32;
+    }
+    let mut h: [u32; 4] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476];
+    for chunk in pad(buf, true).chunks(64) {
+        let mut m = [0u32; 16];
+        for (i, x) in m.iter_mut().enumerate() {
+            *x = u32::from_le_bytes(chunk[i * 4..i * 4 + 4].try_into().
+                unwrap());
+        }
+        let [mut a, mut b, mut c, mut d] = h;
+        for i in 0..64 {
+            let (f, g) = match i {
+                0..=15 => ((b & c) | (!b & d), i),
+                16..=31 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                32..=47 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let f = f.wrapping_add(a).wrapping_add(k[i]).wrapping_add(m[g]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(f.rotate_left(S[i]));
+        }
+        for (x, y) in h.iter_mut().zip([a, b, c, d]) {
+            *x = x.wrapping_add(y);
+        }
+    }
+    let mut res = [0; 16];
+    for (i, x) in h.iter().enumerate() {
+        res[i * 4..i * 4 + 4].copy_from_slice(&x.to_le_bytes());
+    }
+    res
+}
+
+// Append a 1 bit, zeros, and the length in bits of the message to fill the
+// last 512 bits block.
+fn pad(buf: &[u8], little_endian: bool) -> Vec<u8> {
+    let len = (buf.len() as u64).wrapping_mul(8);
+    let mut res = buf.to_vec();
+    res.push(0x80);
+    while res.len() % 64 != 56 {
+        res.push(0);
+    }
+    if little_endian {
+        res.extend_from_slice(&len.to_le_bytes());
+    } else {
+        res.extend_from_slice(&len.to_be_bytes());
+    }
+    res
+}
+
 fn help() {
     let csi_option = Style::color("LightCyan");
     let csi_title = Style::color("Yellow");
     let csi_reset = Style::reset();
     println!(
-        "{}Usage:{} hash {}<file>{}",
+        "{}Usage:{} hash {}<options> <file>...{}",
         csi_title, csi_reset, csi_option, csi_reset
     );
     println!();
     println!("{}Options:{}", csi_title, csi_reset);
     println!(
-        "  {0}-f{1}, {0}--full{1}     Show full hash",
+        "  {0}-f{1}, {0}--full{1}          Show full hash",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-a{1}, {0}--algo <name>{1}   Use {0}sha256{1} (default), \
+        {0}sha1{1}, or {0}md5{1}",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-c{1}, {0}--check{1}         Verify the hashes listed in files",
         csi_option, csi_reset
     );
 }
+
+#[test_case]
+fn test_hash() {
+    fn hex(buf: &[u8]) -> String {
+        buf.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    assert_eq!(hex(&md5(b"")), "d41d8cd98f00b204e9800998ecf8427e");
+    assert_eq!(hex(&md5(b"abc")), "900150983cd24fb0d6963f7d28e17f72");
+    assert_eq!(
+        hex(&md5(&[b'a'; 100])),
+        "36a92cc94a9e0fa21f625f8bfb007adf"
+    );
+    assert_eq!(hex(&sha1(b"")), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+    assert_eq!(hex(&sha1(b"abc")), "a9993e364706816aba3e25717850c26c9cd0d89d");
+    assert_eq!(
+        hex(&sha1(&[b'a'; 100])),
+        "7f9000257a4918d7072655ea468540cdcbd42e0c"
+    );
+}
@@ -0,0 +1,90 @@
+use crate::api::console::Style;
+use crate::api::fs;
+use crate::api::io;
+use crate::api::process::ExitCode;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut count = 10;
+    let mut paths = Vec::new();
+    let mut i = 1;
+    let n = args.len();
+    while i < n {
+        match args[i] {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-n" | "--lines" => {
+                if i + 1 < n {
+                    if let Ok(lines) = args[i + 1].parse() {
+                        count = lines;
+                    } else {
+                        error!("Invalid number of lines '{}'", args[i + 1]);
+                        return Err(ExitCode::UsageError);
+                    }
+                    i += 1;
+                } else {
+                    error!("Missing number of lines");
+                    return Err(ExitCode::UsageError);
+                }
+            }
+            arg => paths.push(arg),
+        }
+        i += 1;
+    }
+
+    if paths.is_empty() {
+        print!("{}", head(&io::stdin().read_to_string(), count));
+        return Ok(());
+    }
+
+    let name_color = Style::color("Yellow");
+    let reset = Style::reset();
+    let show_name = paths.len() > 1;
+    for (i, path) in paths.iter().enumerate() {
+        if let Ok(contents) = fs::read_to_string(path) {
+            if show_name {
+                if i > 0 {
+                    println!();
+                }
+                println!("{}{}{}", name_color, path, reset);
+            }
+            print!("{}", head(&contents, count));
+        } else {
+            error!("Could not read '{}'", path);
+            return Err(ExitCode::Failure);
+        }
+    }
+    Ok(())
+}
+
+fn head(text: &str, count: usize) -> String {
+    text.split_inclusive('\n').take(count).collect()
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} head {}<options> [<path>...]{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-n{1}, {0}--lines <n>{1}   Print the first {0}<n>{1} lines \
+        (default: 10)",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_head() {
+    assert_eq!(head("a\nb\nc\n", 2), "a\nb\n");
+    assert_eq!(head("a\nb\nc", 5), "a\nb\nc");
+    assert_eq!(head("a\nb\nc\n", 0), "");
+}
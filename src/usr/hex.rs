@@ -1,49 +1,182 @@
-use crate::api::console::Style;
+use crate::api::console::{self, Style};
 use crate::api::fs;
+use crate::api::fs::SeekFrom;
+use crate::api::io;
 use crate::api::process::ExitCode;
+use crate::api::syscall;
 
 use alloc::format;
 use alloc::string::String;
+use alloc::vec;
 use alloc::vec::Vec;
 
-// TODO: add `--skip` and `--length` params
+#[derive(Clone, Copy)]
+struct Config {
+    cols: usize,
+    group: usize,
+    skip: usize,
+    length: Option<usize>,
+}
+
+impl Config {
+    fn new() -> Self {
+        Self { cols: 16, group: 2, skip: 0, length: None }
+    }
+
+    // Width of the hex part of a line
+    fn width(&self) -> usize {
+        let groups = (self.cols + self.group - 1) / self.group;
+        2 * self.cols + groups - 1
+    }
+}
+
 pub fn main(args: &[&str]) -> Result<(), ExitCode> {
-    if args.len() != 2 {
-        help();
+    let mut config = Config::new();
+    let mut reverse = false;
+    let mut paths = Vec::new();
+    let mut i = 1;
+    let n = args.len();
+    while i < n {
+        match args[i] {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-r" | "--reverse" => reverse = true,
+            "-c" | "--cols" | "-g" | "--group" | "-s" | "--skip" |
+            "-l" | "--length" => {
+                if i + 1 >= n {
+                    error!("Missing value for '{}'", args[i]);
+                    return Err(ExitCode::UsageError);
+                }
+                let val = match parse_number(args[i + 1]) {
+                    Some(val) => val,
+                    None => {
+                        error!("Invalid number '{}'", args[i + 1]);
+                        return Err(ExitCode::UsageError);
+                    }
+                };
+                match args[i] {
+                    "-c" | "--cols" => config.cols = val,
+                    "-g" | "--group" => config.group = val,
+                    "-s" | "--skip" => config.skip = val,
+                    _ => config.length = Some(val),
+                }
+                i += 1;
+            }
+            arg => paths.push(arg),
+        }
+        i += 1;
+    }
+    if config.cols == 0 || config.group == 0 {
+        error!("Invalid number of columns or group size");
         return Err(ExitCode::UsageError);
     }
-    if args[1] == "-h" || args[1] == "--help" {
-        help();
+
+    if reverse {
+        if paths.is_empty() || paths.len() > 2 {
+            help();
+            return Err(ExitCode::UsageError);
+        }
+        let dest = paths.pop().unwrap();
+        let dump = if let Some(path) = paths.pop() {
+            match fs::read_to_string(path) {
+                Ok(dump) => dump,
+                Err(_) => {
+                    error!("Could not read file '{}'", path);
+                    return Err(ExitCode::Failure);
+                }
+            }
+        } else {
+            io::stdin().read_to_string()
+        };
+        let buf = parse_hex(&dump, &config);
+        if fs::write(dest, &buf).is_err() {
+            error!("Could not write to '{}'", dest);
+            return Err(ExitCode::Failure);
+        }
         return Ok(());
     }
-    let pathname = args[1];
-    if let Ok(buf) = fs::read_to_bytes(pathname) {
-        // TODO: read chunks
-        print_hex(&buf);
-        Ok(())
+
+    if paths.len() != 1 {
+        help();
+        return Err(ExitCode::UsageError);
+    }
+    let pathname = paths[0];
+    if let Some(handle) = fs::open_file(pathname) {
+        let res = print_file(handle, &config);
+        syscall::close(handle);
+        if res.is_err() {
+            error!("Could not read file '{}'", pathname);
+        }
+        res
     } else {
         error!("Could not read file '{}'", pathname);
         Err(ExitCode::Failure)
     }
 }
 
+// Read the file by chunks to avoid loading it whole into memory
+fn print_file(handle: usize, config: &Config) -> Result<(), ExitCode> {
+    let mut buf = vec![0; config.cols * 64];
+    let end = config.length.map(|len| config.skip + len);
+
+    // Skip to the first byte directly when the handle is a file, or by
+    // reading the bytes before it otherwise
+    let skip = SeekFrom::Start(config.skip as u32);
+    let mut offset = syscall::seek(handle, skip).unwrap_or(0);
+    let mut addr = config.skip; // Offset of the first pending byte
+    let mut pending = Vec::new();
+    loop {
+        let n = match syscall::read(handle, &mut buf) {
+            Some(0) => break,
+            Some(n) => n,
+            None => return Err(ExitCode::Failure),
+        };
+        let a = config.skip.saturating_sub(offset).min(n);
+        let b = match end {
+            Some(end) => end.saturating_sub(offset).min(n),
+            None => n,
+        };
+        if a < b {
+            pending.extend_from_slice(&buf[a..b]);
+        }
+        offset += n;
+
+        // Print whole lines and keep the rest for the next chunk
+        let m = pending.len() - pending.len() % config.cols;
+        print_lines(&pending[..m], addr, config);
+        pending.drain(..m);
+        addr += m;
+        if end.map_or(false, |end| offset >= end) {
+            break;
+        }
+    }
+    print_lines(&pending, addr, config);
+    Ok(())
+}
+
 // TODO: move this to api::hex::print_hex
 pub fn print_hex(buf: &[u8]) {
     print_hex_at(buf, 0)
 }
 
 pub fn print_hex_at(buf: &[u8], offset: usize) {
+    print_lines(buf, offset, &Config::new())
+}
+
+fn print_lines(buf: &[u8], offset: usize, config: &Config) {
     let null = 0 as char;
     let cyan = Style::color("LightCyan");
     let gray = Style::color("DarkGray");
     let pink = Style::color("Pink");
     let reset = Style::reset();
 
-    for (index, chunk) in buf.chunks(16).enumerate() {
-        let addr = offset + index * 16;
+    for (index, chunk) in buf.chunks(config.cols).enumerate() {
+        let addr = offset + index * config.cols;
 
-        let hex = chunk.chunks(2).map(|pair|
-            pair.iter().map(|byte|
+        let hex = chunk.chunks(config.group).map(|group|
+            group.iter().map(|byte|
                 format!("{:02X}", byte)
             ).collect::<Vec<String>>().join("")
         ).collect::<Vec<String>>().join(" ");
@@ -58,7 +191,47 @@ pub fn print_hex_at(buf: &[u8], offset: usize) {
 
         let text = ascii.replace(null, &format!("{}.{}", gray, reset));
 
-        println!("{}{:08X}: {}{:40}{}{}", cyan, addr, pink, hex, reset, text);
+        println!(
+            "{}{:08X}: {}{:width$}{} {}",
+            cyan, addr, pink, hex, reset, text, width = config.width()
+        );
+    }
+}
+
+// Convert a dump back into binary, using the addresses of the lines to
+// place the bytes.
+fn parse_hex(dump: &str, config: &Config) -> Vec<u8> {
+    let mut res = Vec::new();
+    for line in dump.lines() {
+        let line = console::strip_escapes(line);
+        let (addr, rest) = match line.split_once(": ") {
+            Some(parts) => parts,
+            None => continue,
+        };
+        let mut addr = match usize::from_str_radix(addr.trim(), 16) {
+            Ok(addr) => addr,
+            Err(_) => continue,
+        };
+        let hex: String = rest.chars().take(config.width()).
+            filter(|c| c.is_ascii_hexdigit()).collect();
+        for i in (0..hex.len().saturating_sub(1)).step_by(2) {
+            if let Ok(byte) = u8::from_str_radix(&hex[i..i + 2], 16) {
+                if res.len() <= addr {
+                    res.resize(addr + 1, 0);
+                }
+                res[addr] = byte;
+                addr += 1;
+            }
+        }
+    }
+    res
+}
+
+fn parse_number(s: &str) -> Option<usize> {
+    if let Some(hex) = s.strip_prefix("0x") {
+        usize::from_str_radix(hex, 16).ok()
+    } else {
+        s.parse().ok()
     }
 }
 
@@ -67,7 +240,64 @@ fn help() {
     let csi_title = Style::color("Yellow");
     let csi_reset = Style::reset();
     println!(
-        "{}Usage:{} hex {}<file>{}",
+        "{}Usage:{} hex {}<options> <file>{}",
         csi_title, csi_reset, csi_option, csi_reset
     );
+    println!(
+        "{}Usage:{} hex {}--reverse [<dump>] <file>{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-c{1}, {0}--cols <n>{1}     Print {0}<n>{1} bytes per line \
+        (default: 16)",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-g{1}, {0}--group <n>{1}    Group bytes by {0}<n>{1} \
+        (default: 2)",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-s{1}, {0}--skip <n>{1}     Start at offset {0}<n>{1}",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-l{1}, {0}--length <n>{1}   Stop after {0}<n>{1} bytes",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-r{1}, {0}--reverse{1}      Convert a dump back into binary",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_hex() {
+    use crate::sys;
+
+    let config = Config::new();
+    assert_eq!(config.width(), 39);
+    let config = Config { cols: 8, group: 4, skip: 0, length: None };
+    assert_eq!(config.width(), 17);
+
+    let dump = format!(
+        "\x1b[96m00000000: \x1b[95m{:39}\x1b[0m {}\n0000000E: {:39} {}",
+        "4865 6C6C 6F2C 2057 6F72 6C64 210A", "Hello, World!.", "4142", "AB"
+    );
+    let buf = parse_hex(&dump, &Config::new());
+    assert_eq!(buf, b"Hello, World!\nAB");
+
+    assert_eq!(parse_number("0x10"), Some(16));
+    assert_eq!(parse_number("10"), Some(10));
+
+    sys::fs::mount_mem();
+    sys::fs::format_mem();
+    fs::write("/test", &[0; 300]).ok();
+    let handle = fs::open_file("/test").unwrap();
+    let config = Config { cols: 16, group: 2, skip: 10, length: Some(100) };
+    assert!(print_file(handle, &config).is_ok());
+    syscall::close(handle);
+    sys::fs::dismount();
 }
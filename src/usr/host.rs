@@ -1,29 +1,67 @@
+use crate::api::clock;
 use crate::api::console::Style;
+use crate::api::fs;
 use crate::api::process::ExitCode;
 use crate::api::rng;
 use crate::api::syscall;
 use crate::sys::fs::OpenFlag;
+use crate::sys::net::mdns::{MDNS_ADDR, MDNS_PORT};
 use crate::usr;
+use alloc::collections::btree_map::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
 use alloc::vec;
 use alloc::vec::Vec;
 use bit_field::BitField;
 use core::convert::TryInto;
+use core::fmt;
 use core::str;
 use core::str::FromStr;
+use lazy_static::lazy_static;
 use smoltcp::wire::{IpAddress, Ipv4Address};
+use spin::Mutex;
 
 // See RFC 1035 for implementation details
 
+const HOSTS: &str = "/ini/hosts";
+const MAX_CACHE_LEN: usize = 64;
+
+// Resolved addresses with the uptime at which they expire
+type Cache = BTreeMap<String, (IpAddress, f64)>;
+
+lazy_static! {
+    static ref CACHE: Mutex<Cache> = Mutex::new(BTreeMap::new());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 #[repr(u16)]
-enum QueryType {
+pub enum QueryType {
     A = 1,
     // NS = 2,
     // MD = 3,
     // MF = 4,
-    // CNAME = 5,
+    CNAME = 5,
     // SOA = 6,
-    // MX = 15,
-    // TXT = 16,
+    PTR = 12,
+    MX = 15,
+    TXT = 16,
+    AAAA = 28,
+}
+
+impl FromStr for QueryType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "A"     => Ok(QueryType::A),
+            "AAAA"  => Ok(QueryType::AAAA),
+            "CNAME" => Ok(QueryType::CNAME),
+            "MX"    => Ok(QueryType::MX),
+            "PTR"   => Ok(QueryType::PTR),
+            "TXT"   => Ok(QueryType::TXT),
+            _       => Err(()),
+        }
+    }
 }
 
 #[repr(u16)]
@@ -45,11 +83,53 @@ pub enum ResponseCode {
     NetworkError,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum Record {
+    A(Ipv4Address),
+    AAAA([u8; 16]),
+    CNAME(String),
+    MX(u16, String),
+    PTR(String),
+    TXT(Vec<String>),
+}
+
+impl Record {
+    pub fn query_type(&self) -> QueryType {
+        match self {
+            Record::A(_) => QueryType::A,
+            Record::AAAA(_) => QueryType::AAAA,
+            Record::CNAME(_) => QueryType::CNAME,
+            Record::MX(_, _) => QueryType::MX,
+            Record::PTR(_) => QueryType::PTR,
+            Record::TXT(_) => QueryType::TXT,
+        }
+    }
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Record::A(addr) => write!(f, "{}", addr),
+            Record::AAAA(addr) => write!(f, "{}", format_ipv6(addr)),
+            Record::CNAME(name) => write!(f, "{}", name),
+            Record::MX(pref, name) => write!(f, "{} {}", pref, name),
+            Record::PTR(name) => write!(f, "{}", name),
+            Record::TXT(strings) => {
+                let strings: Vec<_> = strings.iter().map(|s| {
+                    format!("{:?}", s)
+                }).collect();
+                write!(f, "{}", strings.join(" "))
+            }
+        }
+    }
+}
+
 struct Message {
     pub datagram: Vec<u8>,
 }
 
 const FLAG_RD: u16 = 0x0100; // Recursion desired
+const CACHE_FLUSH: u16 = 0x8000;
 
 impl Message {
     pub fn from(datagram: &[u8]) -> Self {
@@ -58,14 +138,16 @@ impl Message {
         }
     }
 
-    pub fn query(qname: &str, qtype: QueryType, qclass: QueryClass) -> Self {
+    pub fn query(
+        qname: &str, qtype: QueryType, qclass: QueryClass, flags: u16
+    ) -> Self {
         let mut datagram = Vec::new();
 
         let id = rng::get_u16();
         for b in id.to_be_bytes().iter() {
             datagram.push(*b); // Transaction ID
         }
-        for b in FLAG_RD.to_be_bytes().iter() {
+        for b in flags.to_be_bytes().iter() {
             datagram.push(*b); // Flags
         }
         for b in (1 as u16).to_be_bytes().iter() {
@@ -114,6 +196,191 @@ impl Message {
             _ => ResponseCode::UnknownError,
         }
     }
+
+    // Return the records of the supported types found in the answers with
+    // their TTL
+    pub fn answers(&self) -> Option<Vec<(Record, u32)>> {
+        let buf = &self.datagram;
+        let qdcount = u16::from_be_bytes(buf.get(4..6)?.try_into().ok()?);
+        let ancount = u16::from_be_bytes(buf.get(6..8)?.try_into().ok()?);
+        let mut i = 12;
+        for _ in 0..qdcount {
+            i = skip_name(buf, i)? + 4; // QNAME + QTYPE + QCLASS
+        }
+        let mut answers = Vec::new();
+        for _ in 0..ancount {
+            i = skip_name(buf, i)?;
+            let rr = buf.get(i..(i + 10))?;
+            let rtype = u16::from_be_bytes(rr[0..2].try_into().unwrap());
+            let class = u16::from_be_bytes(rr[2..4].try_into().unwrap());
+            let class = class & !CACHE_FLUSH; // Set in mDNS responses
+            let ttl = u32::from_be_bytes(rr[4..8].try_into().unwrap());
+            let len = u16::from_be_bytes(rr[8..10].try_into().unwrap());
+            i += 10;
+            let rdata = buf.get(i..(i + len as usize))?;
+            if class == QueryClass::IN as u16 {
+                if let Some(record) = self.record(rtype, rdata, i) {
+                    answers.push((record, ttl));
+                }
+            }
+            i += len as usize;
+        }
+        Some(answers)
+    }
+
+    // Parse the data of a resource record starting at index `i` in the
+    // datagram, where names can point to earlier parts of the message
+    fn record(&self, rtype: u16, rdata: &[u8], i: usize) -> Option<Record> {
+        let buf = &self.datagram;
+        match rtype {
+            1 if rdata.len() == 4 => {
+                Some(Record::A(Ipv4Address::from_bytes(rdata)))
+            }
+            28 if rdata.len() == 16 => {
+                Some(Record::AAAA(rdata.try_into().unwrap()))
+            }
+            5 => Some(Record::CNAME(read_name(buf, i)?)),
+            12 => Some(Record::PTR(read_name(buf, i)?)),
+            15 if rdata.len() > 2 => {
+                let preference = u16::from_be_bytes([rdata[0], rdata[1]]);
+                Some(Record::MX(preference, read_name(buf, i + 2)?))
+            }
+            16 => {
+                let mut strings = Vec::new();
+                let mut j = 0;
+                while j < rdata.len() {
+                    let n = rdata[j] as usize;
+                    let s = rdata.get((j + 1)..(j + 1 + n))?;
+                    strings.push(String::from_utf8_lossy(s).into());
+                    j += 1 + n;
+                }
+                Some(Record::TXT(strings))
+            }
+            _ => None,
+        }
+    }
+}
+
+// Return the index following a domain name that may end with a pointer
+pub fn skip_name(buf: &[u8], mut i: usize) -> Option<usize> {
+    loop {
+        let len = *buf.get(i)? as usize;
+        if len == 0 {
+            return Some(i + 1);
+        }
+        if len & 0xC0 == 0xC0 {
+            return Some(i + 2);
+        }
+        i += 1 + len;
+    }
+}
+
+// Read a domain name made of labels that may end with a pointer to another
+// name in the message
+pub fn read_name(buf: &[u8], mut i: usize) -> Option<String> {
+    let mut labels = Vec::new();
+    let mut jumps = 0;
+    loop {
+        let len = *buf.get(i)? as usize;
+        if len == 0 {
+            break;
+        }
+        if len & 0xC0 == 0xC0 {
+            jumps += 1;
+            if jumps > 16 {
+                return None; // Pointer loop
+            }
+            let lo = *buf.get(i + 1)? as usize;
+            i = ((len & 0x3F) << 8) | lo;
+            continue;
+        }
+        let label = buf.get((i + 1)..(i + 1 + len))?;
+        labels.push(String::from_utf8_lossy(label).into_owned());
+        i += 1 + len;
+    }
+    Some(labels.join("."))
+}
+
+// Format an IPv6 address with the longest run of zero groups compressed
+fn format_ipv6(addr: &[u8; 16]) -> String {
+    let groups: Vec<u16> = addr.chunks(2).map(|c| {
+        u16::from_be_bytes([c[0], c[1]])
+    }).collect();
+    let (mut start, mut len) = (0, 0);
+    let mut i = 0;
+    while i < 8 {
+        let n = groups[i..].iter().take_while(|&&g| g == 0).count();
+        if n > len {
+            start = i;
+            len = n;
+        }
+        i += n.max(1);
+    }
+    let hex = |groups: &[u16]| -> String {
+        let groups: Vec<_> = groups.iter().map(|g| {
+            format!("{:x}", g)
+        }).collect();
+        groups.join(":")
+    };
+    if len < 2 {
+        hex(&groups)
+    } else {
+        format!("{}::{}", hex(&groups[..start]), hex(&groups[(start + len)..]))
+    }
+}
+
+// Return the domain name used for the reverse lookup of an IPv4 address
+fn reverse_name(addr: &Ipv4Address) -> String {
+    let b = addr.as_bytes();
+    format!("{}.{}.{}.{}.in-addr.arpa", b[3], b[2], b[1], b[0])
+}
+
+// Find the address of a name in the content of a hosts file with lines
+// made of an address followed by one or more names
+fn lookup_hosts(contents: &str, name: &str) -> Option<IpAddress> {
+    for line in contents.lines() {
+        let line = line.split('#').next().unwrap_or("");
+        let mut fields = line.split_whitespace();
+        if let Some(addr) = fields.next() {
+            if fields.any(|field| field.eq_ignore_ascii_case(name)) {
+                if let Ok(addr) = IpAddress::from_str(addr) {
+                    return Some(addr);
+                }
+            }
+        }
+    }
+    None
+}
+
+fn cache_get(cache: &mut Cache, name: &str, now: f64) -> Option<IpAddress> {
+    match cache.get(name) {
+        Some(&(addr, expiration)) if now < expiration => Some(addr),
+        Some(_) => {
+            cache.remove(name);
+            None
+        }
+        None => None,
+    }
+}
+
+fn cache_insert(
+    cache: &mut Cache, name: &str, addr: IpAddress, ttl: u32, now: f64
+) {
+    if ttl == 0 {
+        return;
+    }
+    cache.retain(|_, &mut (_, expiration)| now < expiration);
+    if cache.len() >= MAX_CACHE_LEN {
+        // Evict the entry expiring first to make room for the new one
+        let soonest = cache.iter().min_by(|(_, a), (_, b)| {
+            a.1.partial_cmp(&b.1).unwrap()
+        });
+        if let Some((name, _)) = soonest {
+            let name = name.clone();
+            cache.remove(&name);
+        }
+    }
+    cache.insert(name.into(), (addr, now + ttl as f64));
 }
 
 fn dns_address() -> Option<IpAddress> {
@@ -128,9 +395,61 @@ fn dns_address() -> Option<IpAddress> {
 }
 
 pub fn resolve(name: &str) -> Result<IpAddress, ResponseCode> {
-    let addr = dns_address().unwrap_or(IpAddress::v4(8, 8, 8, 8));
-    let port = 53;
-    let query = Message::query(name, QueryType::A, QueryClass::IN);
+    let name = name.trim_end_matches('.').to_ascii_lowercase();
+    if let Ok(contents) = fs::read_to_string(HOSTS) {
+        if let Some(addr) = lookup_hosts(&contents, &name) {
+            return Ok(addr);
+        }
+    }
+    if let Some(addr) = cache_get(&mut CACHE.lock(), &name, clock::uptime()) {
+        return Ok(addr);
+    }
+    let answers = query(&name, QueryType::A)?;
+    for (record, ttl) in answers {
+        if let Record::A(ipv4) = record {
+            if !ipv4.is_unspecified() {
+                let addr = IpAddress::from(ipv4);
+                let now = clock::uptime();
+                cache_insert(&mut CACHE.lock(), &name, addr, ttl, now);
+                return Ok(addr);
+            }
+        }
+    }
+    Err(ResponseCode::NameError)
+}
+
+// Return the records of the given type for a name, or for an IPv4 address
+// with a reverse lookup when the type is PTR
+pub fn lookup(
+    name: &str, qtype: QueryType
+) -> Result<Vec<Record>, ResponseCode> {
+    let name = match Ipv4Address::from_str(name) {
+        Ok(addr) if qtype == QueryType::PTR => reverse_name(&addr),
+        _ => name.trim_end_matches('.').to_ascii_lowercase(),
+    };
+    let records: Vec<Record> = query(&name, qtype)?.into_iter()
+        .map(|(record, _)| record)
+        .filter(|record| record.query_type() == qtype)
+        .collect();
+    if records.is_empty() {
+        Err(ResponseCode::NameError)
+    } else {
+        Ok(records)
+    }
+}
+
+fn query(
+    name: &str, qtype: QueryType
+) -> Result<Vec<(Record, u32)>, ResponseCode> {
+    // The names of the local link are resolved with a multicast query sent
+    // from another port than the mDNS port, to get a unicast response
+    let (addr, port, flags) = if name.ends_with(".local") {
+        (IpAddress::from(MDNS_ADDR), MDNS_PORT, 0)
+    } else {
+        let addr = dns_address().unwrap_or(IpAddress::v4(8, 8, 8, 8));
+        (addr, 53, FLAG_RD)
+    };
+    let query = Message::query(name, qtype, QueryClass::IN, flags);
 
     let socket_path = "/dev/net/udp";
     let buf_len = if let Some(info) = syscall::info(socket_path) {
@@ -163,16 +482,7 @@ pub fn resolve(name: &str) -> Result<IpAddress, ResponseCode> {
                     //usr::hex::print_hex(&message.datagram);
                     return match message.code() {
                         ResponseCode::NoError => {
-                            // TODO: Parse the datagram instead of extracting
-                            // the last 4 bytes
-                            let n = message.datagram.len();
-                            let data = &message.datagram[(n - 4)..];
-                            let ipv4 = Ipv4Address::from_bytes(data);
-                            if ipv4.is_unspecified() {
-                                Err(ResponseCode::NameError) // FIXME
-                            } else {
-                                Ok(IpAddress::from(ipv4))
-                            }
+                            message.answers().ok_or(ResponseCode::FormatError)
                         }
                         code => Err(code),
                     };
@@ -188,14 +498,65 @@ pub fn resolve(name: &str) -> Result<IpAddress, ResponseCode> {
 
 pub fn main(args: &[&str]) -> Result<(), ExitCode> {
     // TODO: Add `--server <address>` option
-    if args.len() != 2 {
-        help();
-        return Err(ExitCode::UsageError);
-    }
-    let domain = args[1];
-    match resolve(domain) {
-        Ok(addr) => {
-            println!("{}", addr);
+    let mut qtype = None;
+    let mut domain = None;
+    let mut i = 1;
+    let n = args.len();
+    while i < n {
+        match args[i] {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-t" | "--type" => {
+                match args.get(i + 1).and_then(|s| s.parse().ok()) {
+                    Some(t) => qtype = Some(t),
+                    None => {
+                        error!("Missing or invalid record type");
+                        return Err(ExitCode::UsageError);
+                    }
+                }
+                i += 1;
+            }
+            arg if domain.is_none() && !arg.starts_with('-') => {
+                domain = Some(arg);
+            }
+            _ => {
+                help();
+                return Err(ExitCode::UsageError);
+            }
+        }
+        i += 1;
+    }
+    let domain = match domain {
+        Some(domain) => domain,
+        None => {
+            help();
+            return Err(ExitCode::UsageError);
+        }
+    };
+
+    // Addresses are looked up in reverse by default
+    let qtype = qtype.unwrap_or_else(|| {
+        if Ipv4Address::from_str(domain).is_ok() {
+            QueryType::PTR
+        } else {
+            QueryType::A
+        }
+    });
+
+    let res = if qtype == QueryType::A {
+        resolve(domain).map(|addr| vec![addr.to_string()])
+    } else {
+        lookup(domain, qtype).map(|records| {
+            records.iter().map(|record| record.to_string()).collect()
+        })
+    };
+    match res {
+        Ok(lines) => {
+            for line in lines {
+                println!("{}", line);
+            }
             Ok(())
         }
         Err(e) => {
@@ -210,7 +571,109 @@ fn help() {
     let csi_title = Style::color("Yellow");
     let csi_reset = Style::reset();
     println!(
-        "{}Usage:{} host {}<domain>{1}",
+        "{}Usage:{} host {}<options> <domain>{1}",
         csi_title, csi_reset, csi_option
     );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-t{1}, {0}--type <type>{1}  Query A, AAAA, CNAME, MX, PTR, TXT",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_lookup_hosts() {
+    let contents = "# Static hosts\n\
+                    127.0.0.1 localhost\n\
+                    10.0.2.2  gateway gw # QEMU\n";
+    let gateway = IpAddress::v4(10, 0, 2, 2);
+    assert_eq!(lookup_hosts(contents, "gw"), Some(gateway));
+    assert_eq!(lookup_hosts(contents, "GATEWAY"), Some(gateway));
+    assert_eq!(lookup_hosts(contents, "qemu"), None);
+    assert_eq!(lookup_hosts(contents, "example.com"), None);
+}
+
+#[test_case]
+fn test_message_answers() {
+    let qclass = QueryClass::IN;
+    let query = Message::query("moros.cc", QueryType::A, qclass, FLAG_RD);
+    let mut datagram = query.datagram;
+    datagram[2] = 0x81; // Response
+    datagram[7] = 4; // Answers
+    datagram.extend_from_slice(&[
+        0xC0, 0x0C, 0, 5, 0, 1, 0, 0, 0, 60, 0, 6, // CNAME
+        3, b'w', b'w', b'w', 0xC0, 0x0C,
+        0xC0, 0x0C, 0, 1, 0, 1, 0, 0, 1, 44, 0, 4, // A
+        1, 2, 3, 4,
+        0xC0, 0x0C, 0, 15, 0, 1, 0, 0, 1, 44, 0, 4, // MX
+        0, 10, 0xC0, 0x0C,
+        0xC0, 0x0C, 0, 16, 0, 1, 0, 0, 1, 44, 0, 6, // TXT
+        2, b'h', b'i', 2, b'o', b'k',
+    ]);
+    let answers = Message::from(&datagram).answers().unwrap();
+    assert_eq!(answers, vec![
+        (Record::CNAME("www.moros.cc".into()), 60),
+        (Record::A(Ipv4Address::new(1, 2, 3, 4)), 300),
+        (Record::MX(10, "moros.cc".into()), 300),
+        (Record::TXT(vec!["hi".into(), "ok".into()]), 300),
+    ]);
+    assert_eq!(answers[2].0.to_string(), "10 moros.cc");
+    assert_eq!(answers[3].0.to_string(), "\"hi\" \"ok\"");
+
+    datagram.truncate(datagram.len() - 2);
+    assert_eq!(Message::from(&datagram).answers(), None);
+}
+
+#[test_case]
+fn test_read_name() {
+    let buf = [3, b'f', b'o', b'o', 0, 3, b'b', b'a', b'r', 0xC0, 0];
+    assert_eq!(read_name(&buf, 0), Some("foo".into()));
+    assert_eq!(read_name(&buf, 5), Some("bar.foo".into()));
+    assert_eq!(read_name(&[0xC0, 0], 0), None); // Loop
+    assert_eq!(read_name(&[3, b'f'], 0), None); // Truncated
+}
+
+#[test_case]
+fn test_format_ipv6() {
+    let mut addr = [0; 16];
+    assert_eq!(format_ipv6(&addr), "::");
+    addr[15] = 1;
+    assert_eq!(format_ipv6(&addr), "::1");
+    addr[0] = 0x20;
+    addr[1] = 0x01;
+    addr[2] = 0x0d;
+    addr[3] = 0xb8;
+    assert_eq!(format_ipv6(&addr), "2001:db8::1");
+    addr[5] = 1;
+    addr[9] = 1;
+    assert_eq!(format_ipv6(&addr), "2001:db8:1:0:1::1");
+    let addr = [0, 1, 0, 2, 0, 3, 0, 4, 0, 5, 0, 6, 0, 7, 0, 8];
+    assert_eq!(format_ipv6(&addr), "1:2:3:4:5:6:7:8");
+}
+
+#[test_case]
+fn test_reverse_name() {
+    let addr = Ipv4Address::new(10, 0, 2, 3);
+    assert_eq!(reverse_name(&addr), "3.2.0.10.in-addr.arpa");
+}
+
+#[test_case]
+fn test_cache() {
+    let mut cache = Cache::new();
+    let addr = IpAddress::v4(1, 2, 3, 4);
+    cache_insert(&mut cache, "moros.cc", addr, 60, 10.0);
+    cache_insert(&mut cache, "example.com", addr, 0, 10.0);
+    assert_eq!(cache_get(&mut cache, "moros.cc", 69.0), Some(addr));
+    assert_eq!(cache_get(&mut cache, "moros.cc", 70.0), None);
+    assert_eq!(cache_get(&mut cache, "example.com", 10.0), None);
+    assert!(cache.is_empty());
+
+    for i in 0..(MAX_CACHE_LEN + 1) {
+        let name = alloc::format!("{}.moros.cc", i);
+        cache_insert(&mut cache, &name, addr, 60 + i as u32, 10.0);
+    }
+    assert_eq!(cache.len(), MAX_CACHE_LEN);
+    assert_eq!(cache_get(&mut cache, "0.moros.cc", 10.0), None);
+    assert_eq!(cache_get(&mut cache, "1.moros.cc", 10.0), Some(addr));
 }
@@ -1,6 +1,8 @@
 use crate::api::console::Style;
+use crate::api::fs;
 use crate::api::process::ExitCode;
 use crate::api::syscall;
+use crate::api::tls::TlsStream;
 use crate::sys::console;
 use crate::sys::fs::OpenFlag;
 use crate::usr;
@@ -8,11 +10,15 @@ use crate::usr;
 use alloc::format;
 use alloc::string::{String, ToString};
 use alloc::vec;
+use alloc::vec::Vec;
 use core::str::{self, FromStr};
 use smoltcp::wire::IpAddress;
 
+const MAX_REDIRECTS: usize = 10;
+
 #[derive(Debug)]
 struct URL {
+    pub is_secure: bool,
     pub host: String,
     pub port: u16,
     pub path: String,
@@ -25,36 +31,171 @@ enum ResponseState {
 
 impl URL {
     pub fn parse(url: &str) -> Option<Self> {
-        if !url.starts_with("http://") {
+        let (is_secure, url) = if let Some(url) = url.strip_prefix("http://") {
+            (false, url)
+        } else if let Some(url) = url.strip_prefix("https://") {
+            (true, url)
+        } else {
             return None;
-        }
-        let url = &url[7..];
+        };
+        let default_port = if is_secure { 443 } else { 80 };
         let (server, path) = match url.find('/') {
             Some(i) => url.split_at(i),
             None => (url, "/"),
         };
         let (host, port) = match server.find(':') {
-            Some(i) => server.split_at(i),
-            None => (server, ":80"),
+            Some(i) => (&server[..i], server[i + 1..].parse().ok()),
+            None => (server, None),
         };
-        let port = &port[1..];
         Some(Self {
+            is_secure,
             host: host.into(),
-            port: port.parse().unwrap_or(80),
+            port: port.unwrap_or(default_port),
             path: path.into(),
         })
     }
+
+    // Resolve the location of a redirect relative to this URL
+    pub fn join(&self, location: &str) -> Option<Self> {
+        let scheme = if self.is_secure { "https:" } else { "http:" };
+        if location.starts_with("http://") || location.starts_with("https://") {
+            Self::parse(location)
+        } else if location.starts_with("//") {
+            Self::parse(&format!("{}{}", scheme, location))
+        } else {
+            let path = if location.starts_with('/') {
+                location.to_string()
+            } else {
+                let i = self.path.rfind('/').map_or(0, |i| i + 1);
+                format!("{}{}", &self.path[..i], location)
+            };
+            Some(Self {
+                is_secure: self.is_secure,
+                host: self.host.clone(),
+                port: self.port,
+                path,
+            })
+        }
+    }
 }
 
-pub fn main(args: &[&str]) -> Result<(), ExitCode> {
-    let csi_verbose = Style::color("LightBlue");
-    let csi_reset = Style::reset();
+enum ChunkState {
+    Size,
+    Data(usize),
+    DataEnd,
+    Trailer,
+    Done,
+}
+
+// Decode a body sent with `Transfer-Encoding: chunked` that can be received
+// in multiple parts.
+struct ChunkedDecoder {
+    state: ChunkState,
+    line: Vec<u8>,
+}
+
+impl ChunkedDecoder {
+    pub fn new() -> Self {
+        Self { state: ChunkState::Size, line: Vec::new() }
+    }
+
+    pub fn decode(&mut self, buf: &[u8]) -> Vec<u8> {
+        let mut res = Vec::new();
+        let mut i = 0;
+        let n = buf.len();
+        while i < n {
+            match self.state {
+                ChunkState::Size | ChunkState::Trailer if buf[i] != b'\n' => {
+                    self.line.push(buf[i]);
+                    i += 1;
+                }
+                ChunkState::Size => {
+                    let line = String::from_utf8_lossy(&self.line);
+                    let size = line.split(';').next().unwrap_or("").trim();
+                    let size = usize::from_str_radix(size, 16).unwrap_or(0);
+                    self.state = if size > 0 {
+                        ChunkState::Data(size)
+                    } else {
+                        ChunkState::Trailer
+                    };
+                    self.line.clear();
+                    i += 1;
+                }
+                ChunkState::Data(size) => {
+                    let m = size.min(n - i);
+                    res.extend_from_slice(&buf[i..i + m]);
+                    self.state = if m < size {
+                        ChunkState::Data(size - m)
+                    } else {
+                        ChunkState::DataEnd
+                    };
+                    i += m;
+                }
+                ChunkState::DataEnd => {
+                    if buf[i] == b'\n' {
+                        self.state = ChunkState::Size;
+                    }
+                    i += 1;
+                }
+                ChunkState::Trailer => {
+                    if self.line.iter().all(|b| b.is_ascii_whitespace()) {
+                        self.state = ChunkState::Done;
+                    }
+                    self.line.clear();
+                    i += 1;
+                }
+                ChunkState::Done => {
+                    break;
+                }
+            }
+        }
+        res
+    }
+}
+
+enum Connection {
+    Tcp(usize),
+    Tls(usize, TlsStream),
+}
+
+impl Connection {
+    fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        match self {
+            Connection::Tcp(handle) => syscall::read(*handle, buf),
+            Connection::Tls(_, stream) => stream.read(buf),
+        }
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Option<usize> {
+        match self {
+            Connection::Tcp(handle) => syscall::write(*handle, buf),
+            Connection::Tls(_, stream) => stream.write(buf),
+        }
+    }
+
+    fn close(&mut self) {
+        match self {
+            Connection::Tcp(handle) => syscall::close(*handle),
+            Connection::Tls(handle, stream) => {
+                stream.close();
+                syscall::close(*handle);
+            }
+        }
+    }
+}
 
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
     // Parse command line options
     let mut is_verbose = false;
+    let mut is_insecure = false;
+    let mut is_secure = false;
     let mut host = "";
     let mut path = "";
     let mut timeout = 5.0;
+    let mut method = None;
+    let mut data = None;
+    let mut headers = Vec::new();
+    let mut output = None;
     let mut i = 1;
     let n = args.len();
     while i < n {
@@ -65,6 +206,9 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
             "-v" | "--verbose" => {
                 is_verbose = true;
             }
+            "-k" | "--insecure" => {
+                is_insecure = true;
+            }
             "-t" | "--timeout" => {
                 if i + 1 < n {
                     timeout = args[i + 1].parse().unwrap_or(timeout);
@@ -74,11 +218,48 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
                     return Err(ExitCode::UsageError);
                 }
             }
+            "-X" | "--request" => {
+                if i + 1 < n {
+                    method = Some(args[i + 1]);
+                    i += 1;
+                } else {
+                    error!("Missing request method");
+                    return Err(ExitCode::UsageError);
+                }
+            }
+            "-d" | "--data" => {
+                if i + 1 < n {
+                    data = Some(args[i + 1]);
+                    i += 1;
+                } else {
+                    error!("Missing request data");
+                    return Err(ExitCode::UsageError);
+                }
+            }
+            "-H" | "--header" => {
+                if i + 1 < n && args[i + 1].contains(':') {
+                    headers.push(args[i + 1]);
+                    i += 1;
+                } else {
+                    error!("Missing or invalid header");
+                    return Err(ExitCode::UsageError);
+                }
+            }
+            "-o" | "--output" => {
+                if i + 1 < n {
+                    output = Some(args[i + 1]);
+                    i += 1;
+                } else {
+                    error!("Missing output file");
+                    return Err(ExitCode::UsageError);
+                }
+            }
             _ => {
                 if args[i].starts_with('-') {
                     error!("Invalid option '{}'", args[i]);
                     return Err(ExitCode::UsageError);
                 } else if host.is_empty() {
+                    is_secure = args[i].starts_with("https://");
                     host = args[i].
                         trim_start_matches("http://").
                         trim_start_matches("https://");
@@ -104,8 +285,81 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
         }
     }
 
-    let url = "http://".to_string() + host + path;
-    let url = URL::parse(&url).expect("invalid URL format");
+    let scheme = if is_secure { "https://" } else { "http://" };
+    let url = scheme.to_string() + host + path;
+    let mut url = URL::parse(&url).expect("invalid URL format");
+
+    // The data can be read from a file when it starts with '@'
+    let mut body = match data {
+        Some(data) if data.starts_with('@') => {
+            match fs::read_to_bytes(&data[1..]) {
+                Ok(bytes) => bytes,
+                Err(_) => {
+                    error!("Could not read '{}'", &data[1..]);
+                    return Err(ExitCode::Failure);
+                }
+            }
+        }
+        Some(data) => data.as_bytes().to_vec(),
+        None => Vec::new(),
+    };
+    let mut method = match (method, data) {
+        (Some(method), _) => method,
+        (None, Some(_)) => "POST",
+        (None, None) => "GET",
+    };
+
+    for _ in 0..=MAX_REDIRECTS {
+        let (status, location) = match fetch(
+            &url, method, &headers, &body, output, is_verbose, is_insecure
+        )? {
+            Some(redirect) => redirect,
+            None => return Ok(()),
+        };
+        url = match url.join(&location) {
+            Some(url) => url,
+            None => {
+                error!("Invalid redirect location '{}'", location);
+                return Err(ExitCode::Failure);
+            }
+        };
+
+        // Like browsers we use GET after a 301 or 302 redirect of a POST
+        // request, while 307 and 308 redirects keep the method and body.
+        if status == 303 || (method == "POST" && matches!(status, 301 | 302)) {
+            method = "GET";
+            body.clear();
+        }
+    }
+    error!("Too many redirects");
+    Err(ExitCode::Failure)
+}
+
+// Send a request and write the body of the response, or return the status
+// and location of a redirect response.
+fn fetch(
+    url: &URL,
+    method: &str,
+    headers: &[&str],
+    body: &[u8],
+    output: Option<&str>,
+    is_verbose: bool,
+    is_insecure: bool,
+) -> Result<Option<(u16, String)>, ExitCode> {
+    let csi_verbose = Style::color("LightBlue");
+    let csi_reset = Style::reset();
+
+    if url.is_secure && !TlsStream::is_enabled() {
+        error!("Could not use experimental TLS client (build with tls=true)");
+        return Err(ExitCode::Failure);
+    }
+
+    // The TLS client doesn't verify the certificate of the server
+    if url.is_secure && !is_insecure {
+        error!("Could not verify certificate of '{}'", url.host);
+        return Err(ExitCode::Failure);
+    }
+
     let port = url.port;
     let addr = if url.host.ends_with(char::is_numeric) {
         match IpAddress::from_str(&url.host) {
@@ -134,89 +388,165 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
     };
 
     let flags = OpenFlag::Device as usize;
-    if let Some(handle) = syscall::open(socket_path, flags) {
-        if syscall::connect(handle, addr, port).is_err() {
-            error!("Could not connect to {}:{}", addr, port);
-            syscall::close(handle);
-            return Err(ExitCode::Failure);
+    let handle = match syscall::open(socket_path, flags) {
+        Some(handle) => handle,
+        None => return Err(ExitCode::Failure),
+    };
+    if syscall::connect(handle, addr, port).is_err() {
+        error!("Could not connect to {}:{}", addr, port);
+        syscall::close(handle);
+        return Err(ExitCode::Failure);
+    }
+    let mut conn = if url.is_secure {
+        match TlsStream::connect_insecure(handle, &url.host) {
+            Ok(stream) => Connection::Tls(handle, stream),
+            Err(_) => {
+                error!("Could not establish TLS connection");
+                syscall::close(handle);
+                return Err(ExitCode::Failure);
+            }
         }
-        let req = vec![
-            format!("GET {} HTTP/1.1\r\n", url.path),
-            format!("Host: {}\r\n", url.host),
-            format!("User-Agent: MOROS/{}\r\n", env!("CARGO_PKG_VERSION")),
-            format!("Connection: close\r\n"),
-            format!("\r\n"),
-        ];
-        if is_verbose {
-            print!("{}", csi_verbose);
-            for line in &req {
-                print!("> {}", line);
-            }
-            print!("{}", csi_reset);
+    } else {
+        Connection::Tcp(handle)
+    };
+    let mut req = vec![
+        format!("{} {} HTTP/1.1\r\n", method, url.path),
+        format!("Host: {}\r\n", url.host),
+        format!("User-Agent: MOROS/{}\r\n", env!("CARGO_PKG_VERSION")),
+        format!("Connection: close\r\n"),
+    ];
+    if !body.is_empty() {
+        req.push(format!("Content-Length: {}\r\n", body.len()));
+    }
+
+    // Custom headers replace the default ones with the same name
+    for header in headers {
+        let name = header_name(header);
+        req.retain(|line| !header_name(line).eq_ignore_ascii_case(name));
+        req.push(format!("{}\r\n", header.trim()));
+    }
+    req.push("\r\n".to_string());
+    if is_verbose {
+        print!("{}", csi_verbose);
+        for line in &req {
+            print!("> {}", line);
         }
-        let req = req.join("");
-        syscall::write(handle, req.as_bytes());
+        print!("{}", csi_reset);
+    }
+    let req = req.join("");
+    conn.write(req.as_bytes());
+    if !body.is_empty() {
+        conn.write(body);
+    }
 
-        let mut response_state = ResponseState::Headers;
-        loop {
-            if console::end_of_text() || console::end_of_transmission() {
-                eprintln!();
-                syscall::close(handle);
-                return Err(ExitCode::Failure);
+    // The response body is written to the standard output or to a file
+    let stdout = 1;
+    let mut out = stdout;
+    let mut res = Ok(None);
+    let mut received = Vec::new();
+    let mut chunked = None;
+    let mut response_state = ResponseState::Headers;
+    loop {
+        if console::end_of_text() || console::end_of_transmission() {
+            eprintln!();
+            res = Err(ExitCode::Failure);
+            break;
+        }
+        let mut data = vec![0; buf_len];
+        let n = match conn.read(&mut data) {
+            Some(0) => break,
+            Some(n) => n,
+            None => {
+                error!("Could not read from {}:{}", addr, port);
+                res = Err(ExitCode::Failure);
+                break;
             }
-            let mut data = vec![0; buf_len];
-            if let Some(n) = syscall::read(handle, &mut data) {
-                if n == 0 {
-                    break;
+        };
+        data.resize(n, 0);
+        let data = match response_state {
+            ResponseState::Headers => {
+                // The headers can be split across multiple reads
+                received.extend_from_slice(&data);
+                let i = match headers_end(&received) {
+                    Some(i) => i,
+                    None => continue,
+                };
+                let head = String::from_utf8_lossy(&received[0..i]);
+                let mut lines = head.lines();
+                let status = lines.next().unwrap_or("");
+                let status: u16 = status.split(' ').nth(1).
+                    and_then(|code| code.parse().ok()).unwrap_or(0);
+                let mut location = None;
+                if is_verbose {
+                    print!("{}", csi_verbose);
+                    for line in head.lines() {
+                        println!("< {}", line);
+                    }
+                    print!("{}", csi_reset);
                 }
-                data.resize(n, 0);
-                let mut i = 0;
-                while i < n {
-                    match response_state {
-                        ResponseState::Headers => {
-                            let mut j = i;
-                            while j < n {
-                                if data[j] == b'\n' {
-                                    break;
-                                }
-                                j += 1;
-                            }
-                            // TODO: check i == j
-                            let line = String::from_utf8_lossy(&data[i..j]);
-                            if is_verbose {
-                                if i == 0 {
-                                    print!("{}", csi_verbose);
-                                }
-                                println!("< {}", line);
-                            }
-                            if line.trim().is_empty() {
-                                if is_verbose {
-                                    print!("{}", csi_reset);
-                                }
-                                response_state = ResponseState::Body;
-                            }
-                            i = j + 1;
+                for line in lines {
+                    if let Some((name, value)) = line.split_once(':') {
+                        let value = value.trim();
+                        if name.eq_ignore_ascii_case("Location") {
+                            location = Some(value.to_string());
+                        }
+                        if name.eq_ignore_ascii_case("Transfer-Encoding") &&
+                           value.eq_ignore_ascii_case("chunked") {
+                            chunked = Some(ChunkedDecoder::new());
                         }
-                        ResponseState::Body => {
-                            // NOTE: The buffer may not be convertible to a
-                            // UTF-8 string so we write it to STDOUT directly
-                            // instead of using print.
-                            syscall::write(1, &data[i..n]);
+                    }
+                }
+                if let Some(location) = location {
+                    if matches!(status, 301 | 302 | 303 | 307 | 308) {
+                        res = Ok(Some((status, location)));
+                        break;
+                    }
+                }
+                if let Some(path) = output {
+                    match fs::create_file(path) {
+                        Some(handle) => out = handle,
+                        None => {
+                            error!("Could not open '{}'", path);
+                            res = Err(ExitCode::Failure);
                             break;
                         }
                     }
                 }
-            } else {
-                error!("Could not read from {}:{}", addr, port);
-                syscall::close(handle);
-                return Err(ExitCode::Failure);
+                response_state = ResponseState::Body;
+                received.split_off(i)
+            }
+            ResponseState::Body => data,
+        };
+        let data = match chunked {
+            Some(ref mut decoder) => decoder.decode(&data),
+            None => data,
+        };
+
+        // NOTE: The buffer may not be convertible to a UTF-8 string so we
+        // write it directly instead of using print.
+        syscall::write(out, &data);
+    }
+    if out != stdout {
+        syscall::close(out);
+    }
+    conn.close();
+    res
+}
+
+// Return the index of the body after the empty line ending the headers
+fn headers_end(buf: &[u8]) -> Option<usize> {
+    let n = buf.len();
+    for i in 0..n {
+        if buf[i] == b'\n' {
+            if buf[i + 1..].starts_with(b"\r\n") {
+                return Some(i + 3);
+            }
+            if buf[i + 1..].starts_with(b"\n") {
+                return Some(i + 2);
             }
         }
-        syscall::close(handle);
-        Ok(())
-    } else {
-        Err(ExitCode::Failure)
     }
+    None
 }
 
 fn help() -> Result<(), ExitCode> {
@@ -233,9 +563,75 @@ fn help() -> Result<(), ExitCode> {
         "  {0}-v{1}, {0}--verbose{1}              Increase verbosity",
         csi_option, csi_reset
     );
+    println!(
+        "  {0}-k{1}, {0}--insecure{1}             Allow HTTPS without \
+        verifying the server",
+        csi_option, csi_reset
+    );
     println!(
         "  {0}-t{1}, {0}--timeout <seconds>{1}    Request timeout",
         csi_option, csi_reset
     );
+    println!(
+        "  {0}-X{1}, {0}--request <method>{1}     Request method",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-d{1}, {0}--data <data>{1}          Request body or @file",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-H{1}, {0}--header <header>{1}      Request header",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-o{1}, {0}--output <file>{1}        Write body to file",
+        csi_option, csi_reset
+    );
     Ok(())
 }
+
+fn header_name(header: &str) -> &str {
+    header.split(':').next().unwrap_or("").trim()
+}
+
+#[test_case]
+fn test_header_name() {
+    assert_eq!(header_name("Host: moros.cc\r\n"), "Host");
+    assert_eq!(header_name("Content-Type:text/plain"), "Content-Type");
+    assert_eq!(header_name("GET / HTTP/1.1\r\n"), "GET / HTTP/1.1");
+}
+
+#[test_case]
+fn test_url_join() {
+    let url = URL::parse("http://moros.cc:8080/a/b.html").unwrap();
+    let join = |location| {
+        let url = url.join(location).unwrap();
+        format!("{}:{}:{}{}", url.is_secure, url.host, url.port, url.path)
+    };
+    assert_eq!(join("https://moros.cc/c"), "true:moros.cc:443/c");
+    assert_eq!(join("//example.com/c"), "false:example.com:80/c");
+    assert_eq!(join("/c"), "false:moros.cc:8080/c");
+    assert_eq!(join("c.html"), "false:moros.cc:8080/a/c.html");
+}
+
+#[test_case]
+fn test_chunked_decoder() {
+    let body = b"4\r\nWiki\r\n7;ext=1\r\npedia i\r\n0\r\nA: b\r\n\r\nextra";
+    let mut decoder = ChunkedDecoder::new();
+    assert_eq!(decoder.decode(body), b"Wikipedia i");
+
+    // The same body split at every byte
+    let mut decoder = ChunkedDecoder::new();
+    let mut res = Vec::new();
+    for b in body.iter() {
+        res.extend(decoder.decode(&[*b]));
+    }
+    assert_eq!(res, b"Wikipedia i");
+}
+
+#[test_case]
+fn test_https_without_insecure() {
+    // The connection is refused before resolving the host
+    assert!(main(&["http", "https://moros.cc/"]).is_err());
+}
@@ -1,12 +1,16 @@
 use crate::api::clock;
-use crate::api::clock::DATE_TIME_ZONE;
+use crate::api::clock::{DATE_TIME, DATE_TIME_ZONE};
 use crate::api::console::Style;
 use crate::api::fs;
+use crate::api::process;
 use crate::api::process::ExitCode;
 use crate::api::syscall;
 use crate::api::time;
+use crate::api::unit::SizeUnit;
 use crate::sys;
 use crate::sys::console;
+use crate::sys::fs::FileInfo;
+use crate::usr;
 
 use alloc::collections::btree_map::BTreeMap;
 use alloc::collections::vec_deque::VecDeque;
@@ -15,13 +19,14 @@ use alloc::string::{String, ToString};
 use alloc::vec;
 use alloc::vec::Vec;
 use core::fmt;
-use smoltcp::iface::SocketSet;
+use smoltcp::iface::{SocketHandle, SocketSet};
 use smoltcp::phy::Device;
 use smoltcp::socket::tcp;
-use smoltcp::time::Instant;
 use smoltcp::wire::IpAddress;
 
 const MAX_CONNECTIONS: usize = 32;
+const MAX_REQUEST_LEN: usize = 1 << 20;
+const IDLE_TIMEOUT: f64 = 10.0;
 const POLL_DELAY_DIV: usize = 128;
 const INDEX: [&str; 4] = ["", "/index.html", "/index.htm", "/index.txt"];
 
@@ -30,6 +35,7 @@ struct Request {
     addr: IpAddress,
     verb: String,
     path: String,
+    query: String,
     body: Vec<u8>,
     headers: BTreeMap<String, String>,
 }
@@ -40,6 +46,7 @@ impl Request {
             addr,
             verb: String::new(),
             path: String::new(),
+            query: String::new(),
             body: Vec::new(),
             headers: BTreeMap::new(),
         }
@@ -56,7 +63,10 @@ impl Request {
                     let fields: Vec<_> = line.split(' ').collect();
                     if fields.len() >= 2 {
                         req.verb = fields[0].to_string();
-                        req.path = fields[1].to_string();
+                        let (path, query) = fields[1].split_once('?').
+                            unwrap_or((fields[1], ""));
+                        req.path = path.to_string();
+                        req.query = query.to_string();
                     }
                 } else if is_header {
                     // Message header
@@ -159,10 +169,17 @@ impl Response {
     fn status(&self) -> String {
         let msg = match self.code {
             200 => "OK",
+            201 => "Created",
+            204 => "No Content",
             301 => "Moved Permanently",
+            302 => "Found",
+            303 => "See Other",
+            307 => "Temporary Redirect",
             400 => "Bad Request",
+            401 => "Unauthorized",
             403 => "Forbidden",
             404 => "Not Found",
+            405 => "Method Not Allowed",
             500 => "Internal Server Error",
             _ => "Unknown Error",
         };
@@ -202,6 +219,79 @@ impl fmt::Display for Response {
     }
 }
 
+struct Server {
+    dir: String,
+    read_only: bool,
+    exec_handlers: bool,
+}
+
+impl Server {
+    fn respond(&self, req: Request) -> Response {
+        let mut res = Response::new(req.clone());
+        res.real_path = join_path(&self.dir, &req.path);
+        let is_exec = self.exec_handlers && is_handler(&res.real_path);
+        match req.verb.as_str() {
+            _ if is_exec => {
+                exec(&req, &mut res)
+            }
+            "GET" => {
+                get(&req, &mut res)
+            }
+            "PUT" if !self.read_only => {
+                put(&req, &mut res)
+            }
+            "DELETE" if !self.read_only => {
+                delete(&req, &mut res)
+            }
+            _ => {
+                let s = b"<h1>Bad Request</h1>\r\n";
+                res.body.extend_from_slice(s);
+                res.code = 400;
+                res.mime = "text/html".to_string();
+            }
+        }
+        res.end();
+        res
+    }
+}
+
+#[derive(PartialEq)]
+enum ConnectionState {
+    Idle,
+    Receiving,
+    Sending,
+    Closing,
+}
+
+struct Connection {
+    handle: SocketHandle,
+    state: ConnectionState,
+    buf: Vec<u8>,
+    send_queue: VecDeque<Vec<u8>>,
+    keep_alive: bool,
+    last_activity: f64,
+}
+
+impl Connection {
+    pub fn new(handle: SocketHandle) -> Self {
+        Self {
+            handle,
+            state: ConnectionState::Idle,
+            buf: Vec::new(),
+            send_queue: VecDeque::new(),
+            keep_alive: true,
+            last_activity: 0.0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.state = ConnectionState::Idle;
+        self.buf.clear();
+        self.send_queue.clear();
+        self.keep_alive = true;
+    }
+}
+
 fn get(req: &Request, res: &mut Response) {
     if fs::is_dir(&res.real_path) && !req.path.ends_with('/') {
         res.code = 301;
@@ -236,22 +326,11 @@ fn get(req: &Request, res: &mut Response) {
             }
         }
         if not_found {
-            if let Ok(mut files) = fs::read_dir(&res.real_path) {
+            if let Ok(files) = fs::read_dir(&res.real_path) {
                 res.code = 200;
                 res.mime = "text/html".to_string();
-                res.body.extend_from_slice(
-                    format!("<h1>Index of {}</h1>\r\n", req.path).as_bytes()
-                );
-                files.sort_by_key(|f| f.name());
-                for file in files {
-                    let path = format!("{}{}", req.path, file.name());
-                    let link = format!(
-                        "<li><a href=\"{}\">{}</a></li>\n",
-                        path,
-                        file.name()
-                    );
-                    res.body.extend_from_slice(link.as_bytes());
-                }
+                let html = dir_listing(&req.path, files);
+                res.body.extend_from_slice(html.as_bytes());
             } else {
                 res.code = 404;
                 res.mime = "text/html".to_string();
@@ -297,10 +376,145 @@ fn delete(_req: &Request, res: &mut Response) {
     res.mime = "text/plain".to_string();
 }
 
+// Lisp and binary files can be executed as handlers in the style of CGI:
+// the request is given in environment variables and in the standard input,
+// and the response is read from the standard output of the handler with
+// optional headers like `Content-Type` or `Status` before an empty line.
+fn exec(req: &Request, res: &mut Response) {
+    let mut vars = vec![
+        ("REQUEST_METHOD".to_string(), req.verb.clone()),
+        ("SCRIPT_NAME".to_string(), req.path.clone()),
+        ("QUERY_STRING".to_string(), req.query.clone()),
+        ("REMOTE_ADDR".to_string(), req.addr.to_string()),
+        ("CONTENT_LENGTH".to_string(), req.body.len().to_string()),
+    ];
+    for (key, val) in &req.headers {
+        let key = key.to_ascii_uppercase().replace('-', "_");
+        vars.push((format!("HTTP_{}", key), val.clone()));
+    }
+    for (key, val) in &vars {
+        sys::process::set_env(key, val);
+    }
+    let output = exec_handler(&res.real_path, &req.body);
+    for (key, _) in &vars {
+        sys::process::delete_env(key);
+    }
+    match output {
+        Some(output) => handler_response(&output, res),
+        None => {
+            res.code = 500;
+            res.mime = "text/html".to_string();
+            let s = b"<h1>Internal Server Error</h1>\r\n";
+            res.body.extend_from_slice(s);
+        }
+    }
+}
+
+fn exec_handler(path: &str, body: &[u8]) -> Option<Vec<u8>> {
+    let stdin = syscall::pipe()?;
+    let stdout = syscall::pipe()?;
+    syscall::write(stdin, body);
+
+    let saved: Vec<_> = [0, 1].iter().map(|&h| {
+        (h, sys::process::handle(h))
+    }).collect();
+    syscall::dup(stdin, 0);
+    syscall::dup(stdout, 1);
+
+    // NOTE: The handler is executed while the network interface is locked
+    // by the server so it can't use the network.
+    let res = if path.ends_with(".lsp") {
+        usr::lisp::main(&["lisp", path])
+    } else {
+        process::spawn(path, &[path])
+    };
+
+    for (handle, resource) in saved {
+        match resource {
+            Some(resource) => sys::process::update_handle(handle, *resource),
+            None => sys::process::delete_handle(handle),
+        }
+    }
+
+    let mut output = Vec::new();
+    let mut buf = [0; 256];
+    while let Some(n) = syscall::read(stdout, &mut buf) {
+        if n == 0 {
+            break;
+        }
+        output.extend_from_slice(&buf[..n]);
+    }
+    syscall::close(stdin);
+    syscall::close(stdout);
+    res.ok().map(|_| output)
+}
+
+fn handler_response(output: &[u8], res: &mut Response) {
+    res.code = 200;
+    res.mime = "text/plain".to_string();
+
+    // The output starts with headers when its first line is a header
+    let first = output.split(|b| *b == b'\n').next().unwrap_or(&[]);
+    let n = match headers_end(output) {
+        Some(n) if first.contains(&b':') => n,
+        _ => 0,
+    };
+    let head = String::from_utf8_lossy(&output[..n]);
+    for line in head.lines() {
+        if let Some((key, val)) = line.split_once(':') {
+            let (key, val) = (key.trim(), val.trim());
+            match key.to_ascii_lowercase().as_str() {
+                "status" => {
+                    let code = val.split(' ').next().unwrap_or("");
+                    res.code = code.parse().unwrap_or(500);
+                }
+                "content-type" => {
+                    res.mime = val.to_string();
+                }
+                "location" => {
+                    res.code = 302;
+                    res.headers.insert(key.to_string(), val.to_string());
+                }
+                _ => {
+                    res.headers.insert(key.to_string(), val.to_string());
+                }
+            }
+        }
+    }
+    res.body.extend_from_slice(&output[n..]);
+}
+
+// Return the index of the body after the empty line ending the headers
+fn headers_end(buf: &[u8]) -> Option<usize> {
+    let n = buf.len();
+    for i in 0..n {
+        if buf[i] == b'\n' {
+            if buf[i + 1..].starts_with(b"\r\n") {
+                return Some(i + 3);
+            }
+            if buf[i + 1..].starts_with(b"\n") {
+                return Some(i + 2);
+            }
+        }
+    }
+    None
+}
+
+fn is_handler(path: &str) -> bool {
+    if path.ends_with(".lsp") {
+        return fs::is_file(path);
+    }
+    let mut buf = [0; 4];
+    fs::is_file(path) && fs::read(path, &mut buf).is_ok() && buf == *b"\x7FELF"
+}
+
 pub fn main(args: &[&str]) -> Result<(), ExitCode> {
     let csi_color = Style::color("Yellow");
     let csi_reset = Style::reset();
     let mut read_only = false;
+    let mut exec_handlers = false;
+    let mut max_connections = MAX_CONNECTIONS;
+    let mut timeout = IDLE_TIMEOUT;
     let mut port = 80;
     let mut dir = sys::process::dir();
     let mut i = 1;
@@ -314,6 +528,9 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
             "-r" | "--read-only" => {
                 read_only = true;
             }
+            "-x" | "--exec" => {
+                exec_handlers = true;
+            }
             "-p" | "--port" => {
                 if i + 1 < n {
                     port = args[i + 1].parse().unwrap_or(port);
@@ -332,6 +549,26 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
                     return Err(ExitCode::UsageError);
                 }
             }
+            "-c" | "--connections" => {
+                match args.get(i + 1).and_then(|s| s.parse().ok()) {
+                    Some(n) if n > 0 => max_connections = n,
+                    _ => {
+                        error!("Missing or invalid number of connections");
+                        return Err(ExitCode::UsageError);
+                    }
+                }
+                i += 1;
+            }
+            "-t" | "--timeout" => {
+                match args.get(i + 1).and_then(|s| s.parse().ok()) {
+                    Some(x) if x > 0.0 => timeout = x,
+                    _ => {
+                        error!("Missing or invalid timeout");
+                        return Err(ExitCode::UsageError);
+                    }
+                }
+                i += 1;
+            }
             _ => {}
         }
         i += 1;
@@ -340,21 +577,20 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
     // NOTE: This specific format is needed by `join_path`
     let dir = format!("/{}", fs::realpath(&dir).trim_matches('/'));
 
-    if let Some((ref mut iface, ref mut device)) = *sys::net::NET.lock() {
+    let server = Server { dir, read_only, exec_handlers };
+    let mut net = sys::net::NET.lock();
+    if let Some(eth) = net.first() {
+        let mtu = eth.device.capabilities().max_transmission_unit;
         let mut sockets = SocketSet::new(vec![]);
 
-        let mtu = device.capabilities().max_transmission_unit;
         let buf_len = mtu - 14 - 20 - 20; // ETH+TCP+IP headers
         let mut connections = Vec::new();
-        for _ in 0..MAX_CONNECTIONS {
+        for _ in 0..max_connections {
             let tcp_rx_buffer = tcp::SocketBuffer::new(vec![0; buf_len]);
             let tcp_tx_buffer = tcp::SocketBuffer::new(vec![0; buf_len]);
             let tcp_socket = tcp::Socket::new(tcp_rx_buffer, tcp_tx_buffer);
             let tcp_handle = sockets.add(tcp_socket);
-
-            let send_queue: VecDeque<Vec<u8>> = VecDeque::new();
-            let keep_alive = true;
-            connections.push((tcp_handle, send_queue, keep_alive));
+            connections.push(Connection::new(tcp_handle));
         }
 
         println!(
@@ -368,90 +604,98 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
                 return Ok(());
             }
 
-            let ms = (clock::realtime() * 1000000.0) as i64;
-            let time = Instant::from_micros(ms);
-            iface.poll(time, device, &mut sockets);
+            sys::net::poll(&mut net, &mut sockets);
 
-            for (tcp_handle, send_queue, keep_alive) in &mut connections {
-                let socket = sockets.get_mut::<tcp::Socket>(*tcp_handle);
+            // Each connection is a state machine advanced by a small step
+            // at each iteration, so that a slow client doesn't stall the
+            // other ones.
+            for conn in &mut connections {
+                let socket = sockets.get_mut::<tcp::Socket>(conn.handle);
 
                 if !socket.is_open() {
                     socket.listen(port).unwrap();
+                    conn.reset();
                 }
                 let endpoint = match socket.remote_endpoint() {
                     Some(endpoint) => endpoint,
                     None => continue,
                 };
-                if socket.may_recv() {
-                    // The amount of octets queued in the receive buffer may be
-                    // larger than the contiguous slice returned by `recv` so
-                    // we need to loop over chunks of it until it is empty.
-                    let recv_queue = socket.recv_queue();
-                    let mut receiving = true;
-                    let mut buf = vec![];
-                    while receiving {
-                        let res = socket.recv(|chunk| {
-                            buf.extend_from_slice(chunk);
-                            if buf.len() < recv_queue {
-                                return (chunk.len(), None);
+                let now = clock::realtime();
+                if conn.state == ConnectionState::Idle {
+                    conn.state = ConnectionState::Receiving;
+                    conn.last_activity = now;
+                }
+                if now - conn.last_activity > timeout {
+                    socket.abort();
+                    conn.reset();
+                    continue;
+                }
+                match conn.state {
+                    ConnectionState::Idle => {}
+                    ConnectionState::Receiving => {
+                        while socket.can_recv() {
+                            let mut buf = vec![0; buf_len];
+                            match socket.recv_slice(&mut buf) {
+                                Ok(n) => conn.buf.extend_from_slice(&buf[..n]),
+                                Err(_) => break,
                             }
-                            receiving = false;
-
-                            let addr = endpoint.addr;
-                            if let Some(req) = Request::from(addr, &buf) {
-                                let mut res = Response::new(req.clone());
-                                res.real_path = join_path(&dir, &req.path);
-
-                                match req.verb.as_str() {
-                                    "GET" => {
-                                        get(&req, &mut res)
-                                    }
-                                    "PUT" if !read_only => {
-                                        put(&req, &mut res)
-                                    }
-                                    "DELETE" if !read_only => {
-                                        delete(&req, &mut res)
-                                    }
-                                    _ => {
-                                        let s = b"<h1>Bad Request</h1>\r\n";
-                                        res.body.extend_from_slice(s);
-                                        res.code = 400;
-                                        res.mime = "text/html".to_string();
+                            conn.last_activity = now;
+                        }
+                        let addr = endpoint.addr;
+                        match request_len(&conn.buf) {
+                            Some(n) => {
+                                let req = Request::from(addr, &conn.buf[..n]);
+                                conn.buf.drain(..n);
+                                if let Some(req) = req {
+                                    let res = server.respond(req);
+                                    println!("{}", res);
+                                    conn.keep_alive = res.is_persistent();
+                                    let queue = &mut conn.send_queue;
+                                    for chunk in res.buf.chunks(buf_len) {
+                                        queue.push_back(chunk.to_vec());
                                     }
+                                    conn.state = ConnectionState::Sending;
                                 }
-                                res.end();
-                                println!("{}", res);
-                                (chunk.len(), Some(res))
-                            } else {
-                                (0, None)
                             }
-                        });
-                        if receiving {
-                            continue;
-                        }
-                        if let Ok(Some(res)) = res {
-                            *keep_alive = res.is_persistent();
-                            for chunk in res.buf.chunks(buf_len) {
-                                send_queue.push_back(chunk.to_vec());
+                            None if conn.buf.len() > MAX_REQUEST_LEN => {
+                                socket.abort();
+                                conn.reset();
+                            }
+                            None if !socket.may_recv() => {
+                                socket.close();
+                                conn.state = ConnectionState::Closing;
                             }
+                            None => {}
                         }
                     }
-                    if socket.can_send() {
-                        if let Some(chunk) = send_queue.pop_front() {
-                            let sent = socket.send_slice(&chunk).
-                                expect("Could not send chunk");
-                            debug_assert!(sent == chunk.len());
+                    ConnectionState::Sending => {
+                        if socket.can_send() {
+                            if let Some(chunk) = conn.send_queue.pop_front() {
+                                let sent = socket.send_slice(&chunk).
+                                    unwrap_or(0);
+                                if sent < chunk.len() {
+                                    let rest = chunk[sent..].to_vec();
+                                    conn.send_queue.push_front(rest);
+                                }
+                                conn.last_activity = now;
+                            }
+                        }
+                        if conn.send_queue.is_empty() {
+                            if conn.keep_alive && socket.may_recv() {
+                                conn.state = ConnectionState::Receiving;
+                            } else {
+                                socket.close();
+                                conn.state = ConnectionState::Closing;
+                            }
                         }
                     }
-                    if send_queue.is_empty() && !*keep_alive {
-                        socket.close();
+                    ConnectionState::Closing => {
+                        // Wait for the socket to be closed by the remote
+                        // host, or for the timeout
                     }
-                } else if socket.may_send() {
-                    socket.close();
-                    send_queue.clear();
                 }
             }
-            if let Some(delay) = iface.poll_delay(time, &sockets) {
+            if let Some(delay) = sys::net::poll_delay(&mut net, &sockets) {
                 let d = delay.total_micros() / POLL_DELAY_DIV as u64;
                 if d > 0 {
                     syscall::sleep((d as f64) / 1000000.0);
@@ -464,20 +708,100 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
     }
 }
 
+// Return the length of the first request of the buffer when it has been
+// fully received
+fn request_len(buf: &[u8]) -> Option<usize> {
+    let n = headers_end(buf)?;
+    let head = String::from_utf8_lossy(&buf[..n]);
+    let mut len = 0;
+    for line in head.lines() {
+        if let Some((key, val)) = line.split_once(':') {
+            if key.trim().eq_ignore_ascii_case("Content-Length") {
+                len = val.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    if buf.len() >= n + len {
+        Some(n + len)
+    } else {
+        None
+    }
+}
+
+const LISTING_STYLE: &str = "\
+body { font-family: monospace; margin: 2em; }
+table { border-collapse: collapse; }
+th { text-align: left; border-bottom: 1px solid #888; }
+td, th { padding: 0.2em 1em 0.2em 0; }
+td.size { text-align: right; }
+a { text-decoration: none; }
+a:hover { text-decoration: underline; }
+";
+
+// Generate an HTML page listing the files of a directory, with the
+// directories first
+fn dir_listing(path: &str, mut files: Vec<FileInfo>) -> String {
+    files.sort_by_key(|f| (!f.is_dir(), f.name()));
+    let title = format!("Index of {}", escape_html(path));
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\r\n<html>\r\n<head>\r\n");
+    html.push_str("<meta charset=\"utf-8\">\r\n");
+    html.push_str(&format!("<title>{}</title>\r\n", title));
+    html.push_str(&format!("<style>\r\n{}</style>\r\n", LISTING_STYLE));
+    html.push_str("</head>\r\n<body>\r\n");
+    html.push_str(&format!("<h1>{}</h1>\r\n<table>\r\n", title));
+    html.push_str("<tr><th>Name</th><th>Size</th><th>Modified</th></tr>\r\n");
+    if path != "/" {
+        html.push_str("<tr><td><a href=\"../\">../</a></td></tr>\r\n");
+    }
+    for file in files {
+        let (name, size) = if file.is_dir() {
+            (format!("{}/", file.name()), "-".to_string())
+        } else {
+            (file.name(), SizeUnit::Binary.format(file.size() as usize))
+        };
+        let time = time::from_timestamp(file.mtime() as i64).format(DATE_TIME);
+        let name = escape_html(&name);
+        html.push_str(&format!(
+            "<tr><td><a href=\"{}\">{}</a></td>\
+             <td class=\"size\">{}</td><td>{}</td></tr>\r\n",
+            name, name, size, time
+        ));
+    }
+    html.push_str("</table>\r\n</body>\r\n</html>\r\n");
+    html
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").
+        replace('<', "&lt;").
+        replace('>', "&gt;").
+        replace('"', "&quot;")
+}
+
 fn content_type(path: &str) -> String {
     let ext = path.rsplit_once('.').unwrap_or(("", "")).1;
-    match ext {
+    match ext.to_ascii_lowercase().as_str() {
         "css"          => "text/css",
         "csv"          => "text/csv",
-        "gif"          => "text/gif",
+        "gif"          => "image/gif",
+        "gz"           => "application/gzip",
         "htm" | "html" => "text/html",
+        "ico"          => "image/x-icon",
         "jpg" | "jpeg" => "image/jpeg",
         "js"           => "text/javascript",
         "json"         => "application/json",
         "lsp" | "lisp" => "text/plain",
+        "md"           => "text/markdown",
+        "pdf"          => "application/pdf",
         "png"          => "image/png",
         "sh"           => "application/x-sh",
+        "svg"          => "image/svg+xml",
+        "tar"          => "application/x-tar",
         "txt"          => "text/plain",
+        "wasm"         => "application/wasm",
+        "webp"         => "image/webp",
+        "xml"          => "application/xml",
         _              => "application/octet-stream",
     }.to_string()
 }
@@ -513,6 +837,18 @@ fn usage() {
         "  {0}-r{1}, {0}--read-only{1}        Set read-only mode",
         csi_option, csi_reset
     );
+    println!(
+        "  {0}-x{1}, {0}--exec{1}             Execute lisp and binary files",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-c{1}, {0}--connections <n>{1}  Set max connections",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-t{1}, {0}--timeout <secs>{1}   Close idle connections",
+        csi_option, csi_reset
+    );
 }
 
 #[test_case]
@@ -524,3 +860,44 @@ fn test_join_path() {
     assert_eq!(join_path("/", "/bar"), "/bar");
     assert_eq!(join_path("/", "/"), "/");
 }
+
+#[test_case]
+fn test_content_type() {
+    assert_eq!(content_type("/index.html"), "text/html");
+    assert_eq!(content_type("/img/logo.PNG"), "image/png");
+    assert_eq!(content_type("/img/logo.gif"), "image/gif");
+    assert_eq!(content_type("/archive.tar.gz"), "application/gzip");
+    assert_eq!(content_type("/README"), "application/octet-stream");
+}
+
+#[test_case]
+fn test_escape_html() {
+    assert_eq!(escape_html("<a href=\"x\">&</a>"),
+        "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;");
+}
+
+#[test_case]
+fn test_handler_response() {
+    let req = Request::new(IpAddress::v4(127, 0, 0, 1));
+
+    let mut res = Response::new(req.clone());
+    handler_response(b"Status: 404\nContent-Type: text/html\n\n<p>", &mut res);
+    assert_eq!(res.code, 404);
+    assert_eq!(res.mime, "text/html");
+    assert_eq!(res.body, b"<p>");
+
+    let mut res = Response::new(req.clone());
+    handler_response(b"Hello, World!\n\nBye\n", &mut res);
+    assert_eq!(res.code, 200);
+    assert_eq!(res.mime, "text/plain");
+    assert_eq!(res.body, b"Hello, World!\n\nBye\n");
+}
+
+#[test_case]
+fn test_request_len() {
+    assert_eq!(request_len(b"GET / HTTP/1.1\r\nHost: a\r\n"), None);
+    assert_eq!(request_len(b"GET / HTTP/1.1\r\nHost: a\r\n\r\n"), Some(27));
+    let req = b"PUT /a HTTP/1.1\r\ncontent-length: 5\r\n\r\nhello";
+    assert_eq!(request_len(&req[..40]), None);
+    assert_eq!(request_len(req), Some(req.len()));
+}
@@ -15,8 +15,10 @@ pub fn copy_files(verbose: bool) {
     create_dir("/ini", verbose); // Initializers
     create_dir("/lib", verbose); // Libraries
     create_dir("/net", verbose); // Network
+    create_dir("/proc", verbose); // Processes
     create_dir("/src", verbose); // Sources
     create_dir("/tmp", verbose); // Temporaries
+    fs::chmod("/tmp", 0o777).ok(); // Writable by every user
     create_dir("/usr", verbose); // User directories
     create_dir("/var", verbose); // Variables
 
@@ -24,7 +26,6 @@ pub fn copy_files(verbose: bool) {
     //copy_file("/bin/exec", include_bytes!("../../dsk/bin/exec"), verbose);
     copy_file("/bin/halt", include_bytes!("../../dsk/bin/halt"), verbose);
     //copy_file("/bin/hello", include_bytes!("../../dsk/bin/hello"), verbose);
-    copy_file("/bin/ntp", include_bytes!("../../dsk/bin/ntp"), verbose);
     copy_file("/bin/print", include_bytes!("../../dsk/bin/print"), verbose);
     copy_file(
         "/bin/reboot",
@@ -47,9 +48,14 @@ pub fn copy_files(verbose: bool) {
     create_dev("/dev/null", DeviceType::Null, verbose);
     create_dev("/dev/random", DeviceType::Random, verbose);
     create_dev("/dev/console", DeviceType::Console, verbose);
+    create_dir("/dev/disk", verbose); // Filesystem
+    create_dev("/dev/disk/usage", DeviceType::DiskUsage, verbose);
     create_dir("/dev/net", verbose); // Network
     create_dev("/dev/net/tcp", DeviceType::TcpSocket, verbose);
     create_dev("/dev/net/udp", DeviceType::UdpSocket, verbose);
+    create_dev("/dev/net/icmp", DeviceType::IcmpSocket, verbose);
+    create_dev("/dev/net/sockets", DeviceType::NetSockets, verbose);
+    create_dev("/dev/net/interfaces", DeviceType::NetIfaces, verbose);
 
     copy_file(
         "/ini/banner.txt",
@@ -61,6 +67,11 @@ pub fn copy_files(verbose: bool) {
         include_bytes!("../../dsk/ini/boot.sh"),
         verbose,
     );
+    copy_file(
+        "/ini/hosts",
+        include_bytes!("../../dsk/ini/hosts"),
+        verbose,
+    );
     copy_file(
         "/ini/lisp.lsp",
         include_bytes!("../../dsk/ini/lisp.lsp"),
@@ -320,7 +331,9 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
         if sys::process::user().is_none() {
             println!();
             println!("{}Creating user...{}", csi_color, csi_reset);
-            let res = usr::user::main(&["user", "create"]);
+            print!("Username: ");
+            let username: String = io::stdin().read_line().trim_end().into();
+            let res = usr::user::main(&["user", "create", &username]);
             if res == Err(ExitCode::Failure) {
                 return res;
             }
@@ -380,6 +393,9 @@ fn copy_file(pathname: &str, buf: &[u8], verbose: bool) {
     } else {
         fs::write(pathname, buf).ok();
     }
+    if pathname.starts_with("/bin/") {
+        fs::chmod(pathname, 0o755).ok();
+    }
     // TODO: add File::write_all to split buf if needed
     if verbose {
         println!("Copied '{}'", pathname);
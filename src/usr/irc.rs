@@ -0,0 +1,615 @@
+use crate::api::console;
+use crate::api::console::Style;
+use crate::api::fs::IO;
+use crate::api::process::ExitCode;
+use crate::api::syscall;
+use crate::sys;
+use crate::sys::fs::OpenFlag;
+use crate::sys::net::SocketStatus;
+use crate::usr;
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use bit_field::BitField;
+use core::str::FromStr;
+use smoltcp::wire::IpAddress;
+
+const DEFAULT_PORT: u16 = 6667;
+const DEFAULT_NICK: &str = "moros";
+const VERSION: &str = "MOROS IRC v0.1.0";
+const MAX_LINES: usize = 1000;
+
+#[derive(Debug, PartialEq)]
+struct Message {
+    prefix: Option<String>,
+    command: String,
+    params: Vec<String>,
+}
+
+impl Message {
+    // Return the nick of the sender from a prefix like "nick!user@host"
+    fn nick(&self) -> &str {
+        match &self.prefix {
+            Some(prefix) => prefix.split('!').next().unwrap_or(prefix),
+            None => "",
+        }
+    }
+
+    fn param(&self, i: usize) -> &str {
+        self.params.get(i).map_or("", |s| s.as_str())
+    }
+}
+
+// Parse a line like ":nick!user@host PRIVMSG #channel :Hello, world!"
+fn parse_message(line: &str) -> Option<Message> {
+    let mut line = line.trim_end_matches(['\r', '\n']);
+    let mut prefix = None;
+    if let Some(rest) = line.strip_prefix(':') {
+        let (p, rest) = rest.split_once(' ')?;
+        prefix = Some(p.to_string());
+        line = rest;
+    }
+    let (line, trailing) = match line.split_once(" :") {
+        Some((line, trailing)) => (line, Some(trailing)),
+        None => (line, None),
+    };
+    let mut words = line.split_whitespace();
+    let command = words.next()?.to_uppercase();
+    let mut params: Vec<String> = words.map(|s| s.to_string()).collect();
+    if let Some(trailing) = trailing {
+        params.push(trailing.to_string());
+    }
+    Some(Message { prefix, command, params })
+}
+
+// Split a CTCP request like "\x01PING 123\x01" into its command and its
+// argument
+fn parse_ctcp(text: &str) -> Option<(&str, &str)> {
+    let text = text.strip_prefix('\x01')?;
+    let text = text.strip_suffix('\x01').unwrap_or(text);
+    Some(text.split_once(' ').unwrap_or((text, "")))
+}
+
+// Return true for numeric replies like "001" or "433"
+fn is_numeric(cmd: &str) -> bool {
+    cmd.len() == 3 && cmd.bytes().all(|b| b.is_ascii_digit())
+}
+
+// Split a line into rows fitting the width of the screen
+fn wrap(line: &str, cols: usize) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    if chars.is_empty() {
+        return vec![String::new()];
+    }
+    chars.chunks(cols).map(|chunk| chunk.iter().collect()).collect()
+}
+
+// Remove the control characters and escape sequences of the keys that
+// don't have a meaning in the input line
+fn clean_input(input: &str) -> String {
+    let mut res = String::new();
+    let mut escape = false;
+    for c in input.chars() {
+        match c {
+            '\x1B' => escape = true,
+            '[' if escape => {}
+            c if escape => escape = !c.is_ascii_alphabetic() && c != '~',
+            c if c.is_control() => {}
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+struct Client {
+    handle: usize,
+    buf: Vec<u8>,
+    buf_len: usize,
+    nick: String,
+    channel: Option<String>,
+    autojoin: Option<String>,
+    lines: Vec<(String, &'static str)>,
+    is_dirty: bool,
+    is_running: bool,
+}
+
+impl Client {
+    fn send(&mut self, line: &str) {
+        let req = format!("{}\r\n", line);
+        if syscall::write(self.handle, req.as_bytes()).is_none() {
+            self.print("Could not send message", "Red");
+        }
+    }
+
+    fn print(&mut self, line: &str, color: &'static str) {
+        self.lines.push((line.to_string(), color));
+        if self.lines.len() > MAX_LINES {
+            self.lines.remove(0);
+        }
+        self.is_dirty = true;
+    }
+
+    fn receive(&mut self) {
+        let mut data = vec![0; self.buf_len];
+        if let Some(n) = syscall::read(self.handle, &mut data) {
+            self.buf.extend_from_slice(&data[..n]);
+        }
+        while let Some(i) = self.buf.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buf.drain(..=i).collect();
+            let line = String::from_utf8_lossy(&line).to_string();
+            if let Some(msg) = parse_message(&line) {
+                self.handle_message(&msg);
+            }
+        }
+    }
+
+    fn handle_message(&mut self, msg: &Message) {
+        let nick = msg.nick().to_string();
+        let is_me = nick == self.nick;
+        match msg.command.as_str() {
+            "PING" => {
+                self.send(&format!("PONG :{}", msg.param(0)));
+            }
+            "001" => {
+                self.print(msg.param(1), "DarkGray");
+                if let Some(channel) = self.autojoin.take() {
+                    self.send(&format!("JOIN {}", channel));
+                }
+            }
+            "433" => { // Nickname is already in use
+                self.nick.push('_');
+                let nick = self.nick.clone();
+                self.send(&format!("NICK {}", nick));
+            }
+            "PRIVMSG" => {
+                let target = msg.param(0);
+                let text = msg.param(1);
+                if let Some((cmd, arg)) = parse_ctcp(text) {
+                    match cmd {
+                        "ACTION" => {
+                            self.print(&format!("* {} {}", nick, arg), "Pink");
+                        }
+                        "VERSION" => {
+                            let reply = format!("\x01VERSION {}\x01", VERSION);
+                            self.send(&format!("NOTICE {} :{}", nick, reply));
+                        }
+                        "PING" => {
+                            let reply = format!("\x01PING {}\x01", arg);
+                            self.send(&format!("NOTICE {} :{}", nick, reply));
+                        }
+                        _ => {}
+                    }
+                } else if target == self.nick {
+                    self.print(&format!("*{}* {}", nick, text), "Yellow");
+                } else if Some(target) == self.channel.as_deref() {
+                    self.print(&format!("<{}> {}", nick, text), "LightGray");
+                } else {
+                    let line = format!("[{}] <{}> {}", target, nick, text);
+                    self.print(&line, "LightGray");
+                }
+            }
+            "NOTICE" => {
+                let text = msg.param(1);
+                if parse_ctcp(text).is_none() {
+                    let nick = if nick.is_empty() { "*" } else { &nick };
+                    let line = format!("-{}- {}", nick, text);
+                    self.print(&line, "Yellow");
+                }
+            }
+            "JOIN" => {
+                let channel = msg.param(0).to_string();
+                let line = format!("-!- {} joined {}", nick, channel);
+                self.print(&line, "DarkGray");
+                if is_me {
+                    self.channel = Some(channel);
+                }
+            }
+            "PART" => {
+                let channel = msg.param(0).to_string();
+                let line = format!("-!- {} left {}", nick, channel);
+                self.print(&line, "DarkGray");
+                if is_me && self.channel == Some(channel) {
+                    self.channel = None;
+                }
+            }
+            "QUIT" => {
+                let line = format!("-!- {} quit ({})", nick, msg.param(0));
+                self.print(&line, "DarkGray");
+            }
+            "NICK" => {
+                let new = msg.param(0).to_string();
+                let line = format!("-!- {} is now known as {}", nick, new);
+                self.print(&line, "DarkGray");
+                if is_me {
+                    self.nick = new;
+                }
+            }
+            "KICK" => {
+                let line = format!(
+                    "-!- {} was kicked from {} by {} ({})",
+                    msg.param(1), msg.param(0), nick, msg.param(2)
+                );
+                self.print(&line, "DarkGray");
+                if msg.param(1) == self.nick {
+                    self.channel = None;
+                }
+            }
+            "TOPIC" => {
+                let line = format!("-!- {} changed the topic to: {}",
+                    nick, msg.param(1)
+                );
+                self.print(&line, "DarkGray");
+            }
+            "ERROR" => {
+                self.print(msg.param(0), "Red");
+            }
+            cmd if is_numeric(cmd) => {
+                // The first parameter of a numeric reply is our nick
+                let text = msg.params.get(1..).unwrap_or(&[]).join(" ");
+                let is_error = cmd.starts_with('4');
+                let color = if is_error { "Red" } else { "DarkGray" };
+                self.print(&text, color);
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_input(&mut self, input: &str) {
+        let input = input.trim();
+        if input.is_empty() {
+            return;
+        }
+        let (cmd, arg) = if let Some(rest) = input.strip_prefix('/') {
+            rest.split_once(' ').unwrap_or((rest, ""))
+        } else {
+            ("", input)
+        };
+        match cmd {
+            "" | "me" => {
+                let channel = match self.channel.clone() {
+                    Some(channel) => channel,
+                    None => {
+                        self.print("No channel joined", "Red");
+                        return;
+                    }
+                };
+                if cmd == "me" {
+                    let text = format!("\x01ACTION {}\x01", arg);
+                    self.send(&format!("PRIVMSG {} :{}", channel, text));
+                    let line = format!("* {} {}", self.nick, arg);
+                    self.print(&line, "Pink");
+                } else {
+                    self.send(&format!("PRIVMSG {} :{}", channel, arg));
+                    let line = format!("<{}> {}", self.nick, arg);
+                    self.print(&line, "LightCyan");
+                }
+            }
+            "msg" => {
+                if let Some((target, text)) = arg.split_once(' ') {
+                    self.send(&format!("PRIVMSG {} :{}", target, text));
+                    let line = format!("-> *{}* {}", target, text);
+                    self.print(&line, "LightCyan");
+                } else {
+                    self.print("Usage: /msg <nick> <text>", "Red");
+                }
+            }
+            "join" if !arg.is_empty() => {
+                if let Some(channel) = self.channel.clone() {
+                    self.send(&format!("PART {}", channel));
+                }
+                self.send(&format!("JOIN {}", arg));
+            }
+            "part" => {
+                if let Some(channel) = self.channel.clone() {
+                    self.send(&format!("PART {} :{}", channel, arg));
+                }
+            }
+            "nick" if !arg.is_empty() => {
+                self.send(&format!("NICK {}", arg));
+            }
+            "quote" if !arg.is_empty() => {
+                self.send(arg);
+            }
+            "quit" => {
+                self.quit(arg);
+            }
+            _ => {
+                self.print(&format!("Invalid command '/{}'", cmd), "Red");
+            }
+        }
+    }
+
+    fn quit(&mut self, reason: &str) {
+        let reason = if reason.is_empty() { VERSION } else { reason };
+        self.send(&format!("QUIT :{}", reason));
+        self.is_running = false;
+    }
+
+    fn draw_lines(&mut self) {
+        let cols = console::cols();
+        let height = console::rows() - 2;
+        let csi_reset = Style::reset();
+        let mut rows = Vec::new();
+        for (line, color) in self.lines.iter().rev() {
+            for row in wrap(line, cols).iter().rev() {
+                let n = row.chars().count();
+                let csi_color = Style::color(color);
+                let row = format!(
+                    "{}{}{}{}", csi_color, row, csi_reset, " ".repeat(cols - n)
+                );
+                rows.push(row);
+            }
+            if rows.len() >= height {
+                break;
+            }
+        }
+        rows.truncate(height);
+        while rows.len() < height {
+            rows.push(" ".repeat(cols));
+        }
+        rows.reverse();
+        print!("\x1b[1;1H{}", rows.join("\n"));
+
+        let status = match &self.channel {
+            Some(channel) => format!("[{}] {}", self.nick, channel),
+            None => format!("[{}]", self.nick),
+        };
+        let color = Style::color("Black").with_background("LightGray");
+        print!("\n{}{:cols$}{}", color, status, csi_reset, cols = cols);
+        self.is_dirty = false;
+    }
+
+    fn draw_input(&self, input: &str) {
+        let cols = console::cols();
+        let rows = console::rows();
+        let n = input.chars().count();
+        let skip = n.saturating_sub(cols - 3);
+        let visible: String = input.chars().skip(skip).collect();
+        print!("\x1b[{};1H\x1b[2K> {}", rows, visible);
+    }
+
+    fn is_closed(&self) -> bool {
+        let mut data = vec![0; 1]; // 1 byte status read
+        match syscall::read(self.handle, &mut data) {
+            Some(1) => !data[0].get_bit(SocketStatus::MayRecv as usize),
+            _ => false,
+        }
+    }
+}
+
+fn run(client: &mut Client) {
+    let nick = client.nick.clone();
+    client.send(&format!("NICK {}", nick));
+    client.send(&format!("USER {} 0 * :{}", nick, VERSION));
+
+    // The keys typed by the user are kept in the console until a whole line
+    // can be read, and the input line is drawn from them in the meantime.
+    sys::console::disable_echo();
+    print!("\x1b[2J");
+    let mut input = None;
+    while client.is_running {
+        if sys::console::end_of_text() || sys::console::end_of_transmission() {
+            client.quit("");
+            break;
+        }
+        let typed = sys::console::peek();
+        if typed.ends_with('\n') {
+            let line = clean_input(&sys::console::read_line());
+            client.handle_input(&line);
+            input = None;
+            continue;
+        }
+        if syscall::poll(&[(client.handle, IO::Read)]).is_some() {
+            client.receive();
+        } else if client.is_closed() {
+            break;
+        } else {
+            syscall::sleep(0.01);
+        }
+        let typed = clean_input(&typed);
+        if client.is_dirty {
+            client.draw_lines();
+            input = None;
+        }
+        if input.as_ref() != Some(&typed) {
+            client.draw_input(&typed);
+            input = Some(typed);
+        }
+    }
+    sys::console::drain();
+    sys::console::enable_echo();
+    print!("\x1b[2J\x1b[1;1H"); // Clear screen and move to top
+}
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let user = sys::process::env("USER");
+    let mut nick = user.as_deref().unwrap_or(DEFAULT_NICK);
+    let mut params = Vec::new();
+    let mut i = 1;
+    let n = args.len();
+    while i < n {
+        match args[i] {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-n" | "--nick" if i + 1 < n => {
+                nick = args[i + 1];
+                i += 1;
+            }
+            arg if arg.starts_with('-') => {
+                error!("Invalid option '{}'", arg);
+                return Err(ExitCode::UsageError);
+            }
+            arg => {
+                params.push(arg);
+            }
+        }
+        i += 1;
+    }
+    if params.is_empty() || params.len() > 2 {
+        help();
+        return Err(ExitCode::UsageError);
+    }
+    let (host, port) = match params[0].split_once(':') {
+        Some((host, port)) => match port.parse() {
+            Ok(port) => (host, port),
+            Err(_) => {
+                error!("Could not parse port '{}'", port);
+                return Err(ExitCode::UsageError);
+            }
+        },
+        None => (params[0], DEFAULT_PORT),
+    };
+    let autojoin = params.get(1).map(|channel| {
+        if channel.starts_with(['#', '&']) {
+            channel.to_string()
+        } else {
+            format!("#{}", channel)
+        }
+    });
+
+    let addr = if host.ends_with(char::is_numeric) {
+        match IpAddress::from_str(host) {
+            Ok(addr) => addr,
+            Err(_) => {
+                error!("Invalid address '{}'", host);
+                return Err(ExitCode::UsageError);
+            }
+        }
+    } else {
+        match usr::host::resolve(host) {
+            Ok(addr) => addr,
+            Err(e) => {
+                error!("Could not resolve host {:?}", e);
+                return Err(ExitCode::Failure);
+            }
+        }
+    };
+
+    let socket_path = "/dev/net/tcp";
+    let buf_len = match syscall::info(socket_path) {
+        Some(info) => info.size() as usize,
+        None => {
+            error!("Could not open '{}'", socket_path);
+            return Err(ExitCode::Failure);
+        }
+    };
+    let flags = OpenFlag::Device as usize;
+    let handle = match syscall::open(socket_path, flags) {
+        Some(handle) => handle,
+        None => {
+            error!("Could not open '{}'", socket_path);
+            return Err(ExitCode::Failure);
+        }
+    };
+    if syscall::connect(handle, addr, port).is_err() {
+        error!("Could not connect to {}:{}", addr, port);
+        syscall::close(handle);
+        return Err(ExitCode::Failure);
+    }
+
+    let mut client = Client {
+        handle,
+        buf: Vec::new(),
+        buf_len,
+        nick: nick.to_string(),
+        channel: None,
+        autojoin,
+        lines: Vec::new(),
+        is_dirty: true,
+        is_running: true,
+    };
+    run(&mut client);
+    syscall::close(handle);
+    if client.is_running {
+        error!("Connection closed by server");
+        return Err(ExitCode::Failure);
+    }
+    Ok(())
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} irc {}<options> <host>[:<port>] [<channel>]{1}",
+        csi_title, csi_reset, csi_option
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-n{1}, {0}--nick <name>{1}    Set nickname",
+        csi_option, csi_reset
+    );
+    println!();
+    println!("{}Commands:{}", csi_title, csi_reset);
+    println!(
+        "  {}/join <channel>{}       Join a channel",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {}/part [<message>]{}     Leave the channel",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {}/msg <nick> <text>{}    Send a private message",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {}/me <text>{}            Send an action",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {}/nick <name>{}          Change nickname",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {}/quote <line>{}         Send a raw line to the server",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {}/quit [<message>]{}     Disconnect from the server",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_parse_message() {
+    let line = ":nick!user@host PRIVMSG #moros :Hello, world!\r\n";
+    let msg = parse_message(line);
+    assert_eq!(msg, Some(Message {
+        prefix: Some("nick!user@host".to_string()),
+        command: "PRIVMSG".to_string(),
+        params: vec!["#moros".to_string(), "Hello, world!".to_string()],
+    }));
+    assert_eq!(msg.unwrap().nick(), "nick");
+
+    let msg = parse_message("PING :irc.example.com").unwrap();
+    assert_eq!(msg.prefix, None);
+    assert_eq!(msg.command, "PING");
+    assert_eq!(msg.param(0), "irc.example.com");
+    assert_eq!(msg.param(1), "");
+
+    let msg = parse_message(":irc.example.com 001 moros :Welcome").unwrap();
+    assert_eq!(msg.params, vec!["moros", "Welcome"]);
+    assert_eq!(parse_message(""), None);
+}
+
+#[test_case]
+fn test_parse_ctcp() {
+    assert_eq!(parse_ctcp("\x01VERSION\x01"), Some(("VERSION", "")));
+    assert_eq!(parse_ctcp("\x01PING 1234\x01"), Some(("PING", "1234")));
+    assert_eq!(parse_ctcp("\x01ACTION waves"), Some(("ACTION", "waves")));
+    assert_eq!(parse_ctcp("Hello"), None);
+}
+
+#[test_case]
+fn test_clean_input() {
+    assert_eq!(clean_input("hello\x1b[A world\x1b[3~"), "hello world");
+    assert_eq!(clean_input("a\tb\n"), "ab");
+    assert_eq!(wrap("abcde", 2), vec!["ab", "cd", "e"]);
+}
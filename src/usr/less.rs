@@ -0,0 +1,263 @@
+use crate::api::console::Style;
+use crate::api::process::ExitCode;
+use crate::api::regex::Regex;
+use crate::api::{console, fs, io};
+use crate::sys;
+use crate::usr::grep;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp;
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    if args.len() > 2 {
+        help();
+        return Err(ExitCode::UsageError);
+    }
+    let (name, contents) = if args.len() == 2 {
+        if args[1] == "-h" || args[1] == "--help" {
+            help();
+            return Ok(());
+        }
+        match fs::read_to_string(args[1]) {
+            Ok(contents) => (args[1], contents),
+            Err(_) => {
+                error!("Could not read '{}'", args[1]);
+                return Err(ExitCode::Failure);
+            }
+        }
+    } else {
+        ("stdin", io::stdin().read_to_string())
+    };
+
+    let mut pager = Pager::new(name, &contents);
+    pager.run()
+}
+
+struct Pager {
+    name: String,
+    lines: Vec<String>,
+    offset: usize,
+    search: Option<Regex>,
+    status: Option<String>,
+}
+
+impl Pager {
+    fn new(name: &str, contents: &str) -> Self {
+        let lines = contents.lines().map(|line| {
+            line.replace('\t', "    ")
+        }).collect();
+        Self {
+            name: name.into(),
+            lines,
+            offset: 0,
+            search: None,
+            status: None,
+        }
+    }
+
+    fn run(&mut self) -> Result<(), ExitCode> {
+        print!("\x1b[?25l"); // Disable cursor
+        let mut escape = false;
+        let mut csi = false;
+        let mut csi_params = String::new();
+        loop {
+            self.print_screen();
+
+            // Keys are read from the console because the text itself can
+            // come from the standard input.
+            let c = sys::console::read_char();
+            match c {
+                '\x1B' => { // ESC
+                    escape = true;
+                    continue;
+                }
+                '[' if escape => {
+                    csi = true;
+                    csi_params.clear();
+                    continue;
+                }
+                '0'..='9' if csi => {
+                    csi_params.push(c);
+                    continue;
+                }
+                'q' | console::ETX_KEY | console::EOT_KEY => {
+                    break;
+                }
+                'A' if csi => self.scroll_up(1), // Arrow Up
+                'B' if csi => self.scroll_down(1), // Arrow Down
+                '~' if csi && csi_params == "5" => { // Page Up
+                    self.scroll_up(self.rows());
+                }
+                '~' if csi && csi_params == "6" => { // Page Down
+                    self.scroll_down(self.rows());
+                }
+                'k' => self.scroll_up(1),
+                'j' | '\n' => self.scroll_down(1),
+                'b' => self.scroll_up(self.rows()),
+                ' ' | 'f' => self.scroll_down(self.rows()),
+                'g' => self.offset = 0,
+                'G' => self.offset = self.max_offset(),
+                '/' => {
+                    let pattern = self.prompt("/");
+                    if !pattern.is_empty() {
+                        self.search = Some(Regex::new(&pattern));
+                    }
+                    self.find_next(self.offset);
+                }
+                'n' => self.find_next(self.offset + 1),
+                'N' => self.find_previous(),
+                _ => {}
+            }
+            escape = false;
+            csi = false;
+        }
+        print!("\x1b[2J\x1b[1;1H"); // Clear screen and move to top
+        print!("\x1b[?25h"); // Enable cursor
+        Ok(())
+    }
+
+    fn print_screen(&mut self) {
+        let cols = self.cols();
+        let mut rows: Vec<String> = Vec::new();
+        for y in self.offset..(self.offset + self.rows()) {
+            let row = if y < self.lines.len() {
+                let line: String = self.lines[y].chars().take(cols).collect();
+                let highlighted = self.search.as_ref().and_then(|re| {
+                    grep::highlight(re, &line, false)
+                });
+                let n = line.chars().count();
+                let line = highlighted.unwrap_or(line);
+                format!("{}{}", line, " ".repeat(cols - n))
+            } else {
+                format!("~{}", " ".repeat(cols - 1))
+            };
+            rows.push(row);
+        }
+        println!("\x1b[1;1H{}", rows.join("\n"));
+
+        let status = match self.status.take() {
+            Some(status) => status,
+            None => {
+                let n = self.lines.len();
+                let end = cmp::min(self.offset + self.rows(), n);
+                let percent = if n > 0 { end * 100 / n } else { 100 };
+                format!("{} {}%", self.name, percent)
+            }
+        };
+        let color = Style::color("Black").with_background("LightGray");
+        let reset = Style::reset();
+        print!("{}{:cols$}{}", color, status, reset, cols = cols);
+    }
+
+    // Read a line on the status line
+    fn prompt(&mut self, prefix: &str) -> String {
+        let mut res = String::new();
+        loop {
+            print!("\x1b[{};1H\x1b[2K{}{}", self.rows() + 1, prefix, res);
+            match sys::console::read_char() {
+                '\n' => break,
+                console::ETX_KEY | '\x1B' => return String::new(),
+                '\x08' => {
+                    res.pop();
+                }
+                c if console::is_printable(c) => res.push(c),
+                _ => {}
+            }
+        }
+        res
+    }
+
+    fn find_next(&mut self, start: usize) {
+        if let Some(re) = &self.search {
+            let n = self.lines.len();
+            if let Some(y) = (start..n).find(|y| re.is_match(&self.lines[*y])) {
+                self.offset = y;
+                return;
+            }
+        }
+        self.status = Some("Pattern not found".into());
+    }
+
+    fn find_previous(&mut self) {
+        if let Some(re) = &self.search {
+            let start = self.offset;
+            if let Some(y) = (0..start).rev().find(|y| {
+                re.is_match(&self.lines[*y])
+            }) {
+                self.offset = y;
+                return;
+            }
+        }
+        self.status = Some("Pattern not found".into());
+    }
+
+    fn scroll_up(&mut self, n: usize) {
+        self.offset -= cmp::min(n, self.offset);
+    }
+
+    fn scroll_down(&mut self, n: usize) {
+        self.offset = cmp::min(self.offset + n, self.max_offset());
+    }
+
+    fn max_offset(&self) -> usize {
+        self.lines.len().saturating_sub(self.rows())
+    }
+
+    fn rows(&self) -> usize {
+        console::rows() - 1 // Leave out one line for status line
+    }
+
+    fn cols(&self) -> usize {
+        console::cols()
+    }
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} less {}[<file>]{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Keys:{}", csi_title, csi_reset);
+    println!(
+        "  {0}j{1}, {0}k{1}, {0}Up{1}, {0}Down{1}            \
+        Scroll by one line",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}f{1}, {0}b{1}, {0}Space{1}, {0}PgUp{1}, {0}PgDn{1}   \
+        Scroll by one page",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}g{1}, {0}G{1}                      Go to the top or the bottom",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}/{1}, {0}n{1}, {0}N{1}                   Search a pattern, go to \
+        next or previous match",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}q{1}                         Quit",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_pager() {
+    let text = "a\nb\tc\nd\n";
+    let mut pager = Pager::new("test", text);
+    assert_eq!(pager.lines, ["a", "b    c", "d"]);
+    pager.search = Some(Regex::new("d"));
+    pager.find_next(0);
+    assert_eq!(pager.offset, 2);
+    pager.find_previous();
+    assert_eq!(pager.offset, 2);
+    assert_eq!(pager.status, Some("Pattern not found".into()));
+}
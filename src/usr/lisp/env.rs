@@ -251,6 +251,10 @@ pub fn default_env() -> Rc<RefCell<Env>> {
         "date".to_string(),
         Exp::Primitive(primitive::lisp_date),
     );
+    data.insert(
+        "env/get".to_string(),
+        Exp::Primitive(primitive::lisp_env_get),
+    );
 
     // Setup autocompletion
     *FUNCTIONS.lock() = data.keys().cloned().
@@ -6,6 +6,7 @@ use super::{Err, Exp, Number};
 use crate::api;
 use crate::api::regex::Regex;
 use crate::api::syscall;
+use crate::sys;
 use crate::sys::fs::OpenFlag;
 use crate::usr::host;
 use crate::usr::shell;
@@ -483,7 +484,7 @@ pub fn lisp_file_open(args: &[Exp]) -> Result<Exp, Err> {
     let mut flags = match mode.as_ref() {
         "a" => OpenFlag::Append as usize,
         "r" => OpenFlag::Read as usize,
-        "w" => OpenFlag::Write as usize,
+        "w" => OpenFlag::Write as usize | OpenFlag::Truncate as usize,
         _ => return expected!("valid mode"),
     };
     flags |= match syscall::info(&path) {
@@ -574,8 +575,9 @@ pub fn lisp_socket_listen(args: &[Exp]) -> Result<Exp, Err> {
 pub fn lisp_socket_accept(args: &[Exp]) -> Result<Exp, Err> {
     ensure_length_eq!(args, 1);
     let handle: usize = number(&args[0])?.try_into()?;
-    if let Ok(addr) = syscall::accept(handle) {
-        Ok(Exp::Str(format!("{}", addr)))
+    if let Ok((handle, addr)) = syscall::accept(handle) {
+        let handle = Exp::Num(Number::from(handle));
+        Ok(Exp::List(vec![handle, Exp::Str(format!("{}", addr))]))
     } else {
         could_not!("accept connections")
     }
@@ -662,6 +664,15 @@ pub fn lisp_host(args: &[Exp]) -> Result<Exp, Err> {
     }
 }
 
+pub fn lisp_env_get(args: &[Exp]) -> Result<Exp, Err> {
+    ensure_length_eq!(args, 1);
+    let key = string(&args[0])?;
+    match sys::process::env(&key) {
+        Some(val) => Ok(Exp::Str(val)),
+        None => Ok(Exp::List(vec![])),
+    }
+}
+
 pub fn lisp_date(args: &[Exp]) -> Result<Exp, Err> {
     ensure_length_eq!(args, 1);
     let ts = usize::try_from(number(&args[0])?)? as i64;
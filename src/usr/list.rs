@@ -7,26 +7,72 @@ use crate::api::syscall;
 use crate::api::time;
 use crate::api::unit::SizeUnit;
 use crate::sys;
+use crate::usr;
 
-use alloc::string::ToString;
+use alloc::collections::btree_map::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 
+// Default colors that can be overridden with the `LIST_COLORS` variable:
+// > env LIST_COLORS "exe=Green:*.txt=White"
+const DEFAULT_COLORS: [(&str, &str); 15] = [
+    ("dir", "LightCyan"),
+    ("dev", "Yellow"),
+    ("link", "Cyan"),
+    ("exe", "LightGreen"),
+    ("*.gz", "LightRed"),
+    ("*.tar", "LightRed"),
+    ("*.zip", "LightRed"),
+    ("*.bmp", "Pink"),
+    ("*.gif", "Pink"),
+    ("*.jpg", "Pink"),
+    ("*.png", "Pink"),
+    ("*.ppm", "Pink"),
+    ("*.cells", "Magenta"),
+    ("*.lsp", "LightBlue"),
+    ("*.sh", "LightBlue"),
+];
+
+struct Config {
+    colors: BTreeMap<String, String>,
+    sort: &'static str,
+    time: fn(&FileInfo) -> u64,
+    reverse: bool,
+    recursive: bool,
+    long: bool,
+    hide_dot_files: bool,
+    unit: SizeUnit,
+}
+
 pub fn main(args: &[&str]) -> Result<(), ExitCode> {
     let mut path: &str = &sys::process::dir(); // TODO: use '.'
-    let mut sort = "name";
-    let mut hide_dot_files = true;
-    let mut unit = SizeUnit::None;
+    let mut config = Config {
+        colors: colors(),
+        sort: "name",
+        time: FileInfo::mtime,
+        reverse: false,
+        recursive: false,
+        long: false,
+        hide_dot_files: true,
+        unit: SizeUnit::None,
+    };
 
     let n = args.len();
     for i in 1..n {
         match args[i] {
             "-h" | "--help" => return help(),
-            "-a" | "--all" => hide_dot_files = false,
-            "-n" | "--name" => sort = "name",
-            "-s" | "--size" => sort = "size",
-            "-t" | "--time" => sort = "time",
-            "-b" | "--binary-size" => unit = SizeUnit::Binary,
-            "-d" | "--decimal-size" => unit = SizeUnit::Decimal,
+            "-a" | "--all" => config.hide_dot_files = false,
+            "-n" | "--name" => config.sort = "name",
+            "-s" | "--size" => config.sort = "size",
+            "-t" | "--time" => config.sort = "time",
+            "-c" | "--created" => config.time = FileInfo::ctime,
+            "-u" | "--accessed" => config.time = FileInfo::atime,
+            "-r" | "--reverse" => config.reverse = true,
+            "-R" | "--recursive" => config.recursive = true,
+            "-l" | "--long" => config.long = true,
+            "-b" | "--binary-size" => config.unit = SizeUnit::Binary,
+            "-d" | "--decimal-size" => config.unit = SizeUnit::Decimal,
             _ => path = args[i],
         }
     }
@@ -39,36 +85,10 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
 
     if let Some(info) = syscall::info(path) {
         if info.is_dir() {
-            if let Ok(entries) = fs::read_dir(path) {
-                let mut files: Vec<_> = entries.iter().filter(|entry|
-                    !(entry.name().starts_with('.') && hide_dot_files)
-                ).collect();
-
-                match sort {
-                    "name" => files.sort_by_key(|f| f.name()),
-                    "size" => files.sort_by_key(|f| f.size()),
-                    "time" => files.sort_by_key(|f| f.time()),
-                    _ => {
-                        error!("Invalid sort key '{}'", sort);
-                        return Err(ExitCode::Failure);
-                    }
-                }
-
-                let width = files.iter().fold(0, |max_len, file| {
-                    let len = unit.format(file.size() as usize).len();
-                    core::cmp::max(max_len, len)
-                });
-
-                for file in files {
-                    print_file(file, width, unit.clone());
-                }
-                Ok(())
-            } else {
-                error!("Could not read directory '{}'", path);
-                Err(ExitCode::Failure)
-            }
+            list_dir(path, &config, true)
         } else {
-            print_file(&info, info.size().to_string().len(), unit);
+            let widths = widths(&[&info], &config);
+            print_file(&info, path, &widths, &config);
             Ok(())
         }
     } else {
@@ -77,28 +97,178 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
     }
 }
 
-fn print_file(file: &FileInfo, width: usize, unit: SizeUnit) {
-    let csi_dir_color = Style::color("LightCyan");
-    let csi_dev_color = Style::color("Yellow");
-    let csi_reset = Style::reset();
+fn list_dir(
+    path: &str,
+    config: &Config,
+    is_first: bool
+) -> Result<(), ExitCode> {
+    if let Ok(entries) = fs::read_dir(path) {
+        let mut files: Vec<_> = entries.iter().filter(|entry|
+            !(entry.name().starts_with('.') && config.hide_dot_files)
+        ).collect();
+
+        match config.sort {
+            "name" => files.sort_by_key(|f| f.name()),
+            "size" => files.sort_by_key(|f| f.size()),
+            "time" => files.sort_by_key(|f| (config.time)(f)),
+            _ => {
+                error!("Invalid sort key '{}'", config.sort);
+                return Err(ExitCode::Failure);
+            }
+        }
+        if config.reverse {
+            files.reverse();
+        }
+
+        let widths = widths(&files, config);
+
+        if config.recursive {
+            let csi_title = Style::color("Yellow");
+            let csi_reset = Style::reset();
+            if !is_first {
+                println!();
+            }
+            println!("{}{}:{}", csi_title, path, csi_reset);
+        }
+        let sep = if path.ends_with('/') { "" } else { "/" };
+        for file in &files {
+            let file_path = format!("{}{}{}", path, sep, file.name());
+            print_file(file, &file_path, &widths, config);
+        }
+
+        if config.recursive {
+            for file in files.iter().filter(|f| f.is_dir()) {
+                let subdir = format!("{}{}{}", path, sep, file.name());
+                list_dir(&subdir, config, false)?;
+            }
+        }
+        Ok(())
+    } else {
+        error!("Could not read directory '{}'", path);
+        Err(ExitCode::Failure)
+    }
+}
+
+fn colors() -> BTreeMap<String, String> {
+    let mut colors = BTreeMap::new();
+    for (key, val) in DEFAULT_COLORS {
+        colors.insert(key.to_string(), val.to_string());
+    }
+    if let Some(list_colors) = sys::process::env("LIST_COLORS") {
+        for item in list_colors.split(':') {
+            if let Some((key, val)) = item.split_once('=') {
+                colors.insert(key.trim().to_string(), val.trim().to_string());
+            }
+        }
+    }
+    colors
+}
 
-    let size = unit.format(file.size() as usize);
-    let time = time::from_timestamp(file.time() as i64).format(DATE_TIME);
-    let color = if file.is_dir() {
-        csi_dir_color
+// The files are not read to find their type, which would also update their
+// access time
+fn is_executable(file: &FileInfo) -> bool {
+    file.is_file() && file.mode() & 0o111 != 0
+}
+
+fn file_color(file: &FileInfo, config: &Config) -> Style {
+    let key = if file.is_dir() {
+        "dir".to_string()
     } else if file.is_device() {
-        csi_dev_color
+        "dev".to_string()
+    } else if file.is_link() {
+        "link".to_string()
+    } else if is_executable(file) {
+        "exe".to_string()
+    } else if let Some((_, ext)) = file.name().rsplit_once('.') {
+        format!("*.{}", ext)
     } else {
-        csi_reset
+        return Style::reset();
     };
+    match config.colors.get(&key) {
+        Some(color) => Style::color(color),
+        None => Style::reset(),
+    }
+}
+
+fn mode(file: &FileInfo) -> String {
+    let kind = if file.is_dir() {
+        'd'
+    } else if file.is_device() {
+        'c'
+    } else if file.is_link() {
+        'l'
+    } else {
+        '-'
+    };
+    format!("{}{}", kind, usr::chmod::format_mode(file.mode()))
+}
+
+// The name of the owner and group of a file, or their IDs without a name
+fn owner(file: &FileInfo) -> (String, String) {
+    let uid = file.uid();
+    let gid = file.gid();
+    let user = usr::user::username(uid).unwrap_or(uid.to_string());
+    let group = usr::user::groupname(gid).unwrap_or(gid.to_string());
+    (user, group)
+}
+
+// The width of the columns aligned between the files of a listing
+struct Widths {
+    size: usize,
+    user: usize,
+    group: usize,
+}
+
+fn widths(files: &[&FileInfo], config: &Config) -> Widths {
+    let mut widths = Widths { size: 0, user: 0, group: 0 };
+    for file in files {
+        let size = config.unit.format(file.size() as usize).len();
+        widths.size = widths.size.max(size);
+        if config.long {
+            let (user, group) = owner(file);
+            widths.user = widths.user.max(user.len());
+            widths.group = widths.group.max(group.len());
+        }
+    }
+    widths
+}
+
+fn print_file(file: &FileInfo, path: &str, widths: &Widths, config: &Config) {
+    let csi_color = file_color(file, config);
+    let csi_reset = Style::reset();
+
+    if config.long {
+        let (user, group) = owner(file);
+        print!(
+            "{} {:<uw$} {:<gw$} ",
+            mode(file),
+            user,
+            group,
+            uw = widths.user,
+            gw = widths.group
+        );
+    }
+
+    // The target of a symbolic link is shown after its name
+    let mut target = String::new();
+    if config.long && file.is_link() {
+        if let Some(path) = fs::read_link(path) {
+            target = format!(" -> {}", path);
+        }
+    }
+
+    let size = config.unit.format(file.size() as usize);
+    let time = time::from_timestamp((config.time)(file) as i64);
+    let time = time.format(DATE_TIME);
     println!(
-        "{:>width$} {} {}{}{}",
+        "{:>width$} {} {}{}{}{}",
         size,
         time,
-        color,
+        csi_color,
         file.name(),
         csi_reset,
-        width = width
+        target,
+        width = widths.size
     );
 }
 
@@ -116,6 +286,10 @@ fn help() -> Result<(), ExitCode> {
         "  {0}-b{1}, {0}--binary-size{1}   Use binary size",
         csi_option, csi_reset
     );
+    println!(
+        "  {0}-d{1}, {0}--decimal-size{1}  Use decimal size",
+        csi_option, csi_reset
+    );
     println!(
         "  {0}-a{1}, {0}--all{1}           Show dot files",
         csi_option, csi_reset
@@ -132,5 +306,53 @@ fn help() -> Result<(), ExitCode> {
         "  {0}-t{1}, {0}--time{1}          Sort by time",
         csi_option, csi_reset
     );
+    println!(
+        "  {0}-c{1}, {0}--created{1}       Use creation time",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-u{1}, {0}--accessed{1}      Use access time",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-r{1}, {0}--reverse{1}       Reverse sort order",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-R{1}, {0}--recursive{1}     List subdirectories recursively",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-l{1}, {0}--long{1}          Show permissions and ownership",
+        csi_option, csi_reset
+    );
     Ok(())
 }
+
+#[test_case]
+fn test_file_color() {
+    use crate::api::fs::FileType;
+
+    let config = Config {
+        colors: colors(),
+        sort: "name",
+        time: FileInfo::mtime,
+        reverse: false,
+        recursive: false,
+        long: false,
+        hide_dot_files: true,
+        unit: SizeUnit::None,
+    };
+    let color = |kind, name, mode| {
+        let file = FileInfo::from_parts(kind, name, 0, 0, mode);
+        file_color(&file, &config).to_string()
+    };
+    let exe = Style::color("LightGreen").to_string();
+    let sh = Style::color("LightBlue").to_string();
+    let none = Style::reset().to_string();
+    assert_eq!(color(FileType::File, "hello", 0o755), exe);
+    assert_eq!(color(FileType::File, "hello.sh", 0o744), exe);
+    assert_eq!(color(FileType::File, "hello.sh", 0o644), sh);
+    assert_eq!(color(FileType::File, "hello", 0o644), none);
+    assert_ne!(color(FileType::Dir, "bin", 0o755), exe);
+}
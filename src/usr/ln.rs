@@ -0,0 +1,90 @@
+use crate::api::console::Style;
+use crate::api::fs;
+use crate::api::process::ExitCode;
+use crate::api::syscall;
+use crate::usr::copy;
+
+use alloc::vec::Vec;
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut symbolic = false;
+    let mut force = false;
+    let mut paths = Vec::new();
+    for arg in &args[1..] {
+        match *arg {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-s" | "--symbolic" => symbolic = true,
+            "-f" | "--force" => force = true,
+            _ => paths.push(*arg),
+        }
+    }
+    if paths.len() != 2 {
+        help();
+        return Err(ExitCode::UsageError);
+    }
+
+    if paths[1].is_empty() {
+        error!("Could not write to ''");
+        return Err(ExitCode::Failure);
+    }
+
+    let target = paths[0];
+    let path = copy::destination(target, paths[1]);
+
+    if is_same_file(target, &path) {
+        error!("Could not link '{}' to itself", path);
+        return Err(ExitCode::Failure);
+    }
+
+    let exists = fs::exists(&path) || fs::is_link(&path);
+    if exists && force && !fs::is_dir(&path) && fs::delete(&path).is_err() {
+        error!("Could not replace '{}'", path);
+        return Err(ExitCode::Failure);
+    }
+
+    // The target of a symbolic link is kept as given, a relative target
+    // being resolved from the directory of the link.
+    if symbolic {
+        if fs::symlink(target, &path).is_err() {
+            error!("Could not create symbolic link '{}'", path);
+            return Err(ExitCode::Failure);
+        }
+    } else if fs::is_dir(target) {
+        error!("Could not create hard link to directory '{}'", target);
+        return Err(ExitCode::Failure);
+    } else if fs::link(target, &path).is_err() {
+        error!("Could not create hard link '{}'", path);
+        return Err(ExitCode::Failure);
+    }
+    Ok(())
+}
+
+fn is_same_file(a: &str, b: &str) -> bool {
+    match (syscall::info(a), syscall::info(b)) {
+        (Some(a), Some(b)) => a.addr() == b.addr(),
+        _ => false,
+    }
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} ln {}<options> <target> <path>{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-s{1}, {0}--symbolic{1}   Create a symbolic link",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-f{1}, {0}--force{1}      Replace an existing file",
+        csi_option, csi_reset
+    );
+}
@@ -0,0 +1,549 @@
+use crate::api::console::Style;
+use crate::api::io;
+use crate::api::process::ExitCode;
+use crate::api::syscall;
+use crate::api::time;
+use crate::api::tls::TlsStream;
+use crate::sys;
+use crate::sys::console;
+use crate::sys::fs::OpenFlag;
+use crate::usr;
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::str::FromStr;
+use smoltcp::wire::IpAddress;
+
+const SMTP_PORT: u16 = 25;
+const SMTPS_PORT: u16 = 465;
+
+enum Connection {
+    Tcp(usize),
+    Tls(usize, TlsStream),
+}
+
+// A client sends the commands of a single SMTP session, upgrading the
+// connection to TLS with STARTTLS when the server supports it.
+struct Client {
+    conn: Connection,
+    host: String,
+    buf: Vec<u8>,
+    buf_len: usize,
+    verbose: bool,
+}
+
+impl Client {
+    fn handle(&self) -> usize {
+        match self.conn {
+            Connection::Tcp(handle) | Connection::Tls(handle, _) => handle,
+        }
+    }
+
+    fn is_secure(&self) -> bool {
+        matches!(self.conn, Connection::Tls(..))
+    }
+
+    fn start_tls(&mut self) -> Result<(), ()> {
+        let handle = self.handle();
+        match TlsStream::connect_insecure(handle, &self.host) {
+            Ok(stream) => {
+                self.conn = Connection::Tls(handle, stream);
+                Ok(())
+            }
+            Err(()) => {
+                error!("Could not establish TLS connection");
+                Err(())
+            }
+        }
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<(), ()> {
+        let mut i = 0;
+        while i < buf.len() {
+            if console::end_of_text() || console::end_of_transmission() {
+                return Err(());
+            }
+            let j = (i + self.buf_len).min(buf.len());
+            let res = match &mut self.conn {
+                Connection::Tcp(handle) => syscall::write(*handle, &buf[i..j]),
+                Connection::Tls(_, stream) => stream.write(&buf[i..j]),
+            };
+            match res {
+                Some(n) if n > 0 => i += n,
+                _ => {
+                    error!("Could not send data");
+                    return Err(());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn command(&mut self, cmd: &str) -> Result<(u16, Vec<String>), ()> {
+        if self.verbose {
+            if cmd.starts_with("AUTH ") {
+                debug!("AUTH ****");
+            } else {
+                debug!("{}", cmd);
+            }
+        }
+        self.write(format!("{}\r\n", cmd).as_bytes())?;
+        self.reply()
+    }
+
+    // Send a command and check that the code of the reply is the expected
+    // one, displaying the text of the reply otherwise
+    fn expect(&mut self, cmd: &str, expected: u16) -> Result<Vec<String>, ()> {
+        match self.command(cmd)? {
+            (code, lines) if code == expected => Ok(lines),
+            (code, lines) => {
+                error!("{} {}", code, lines.join(" "));
+                Err(())
+            }
+        }
+    }
+
+    fn reply(&mut self) -> Result<(u16, Vec<String>), ()> {
+        loop {
+            if let Some((code, lines, n)) = parse_reply(&self.buf) {
+                self.buf.drain(..n);
+                if self.verbose {
+                    for line in &lines {
+                        debug!("{} {}", code, line);
+                    }
+                }
+                return Ok((code, lines));
+            }
+            if console::end_of_text() || console::end_of_transmission() {
+                return Err(());
+            }
+            let mut data = vec![0; self.buf_len];
+            let res = match &mut self.conn {
+                Connection::Tcp(handle) => syscall::read(*handle, &mut data),
+                Connection::Tls(_, stream) => stream.read(&mut data),
+            };
+            match res {
+                Some(0) | None => {
+                    error!("Connection closed by server");
+                    return Err(());
+                }
+                Some(n) => self.buf.extend_from_slice(&data[..n]),
+            }
+        }
+    }
+
+    fn reply_code(&mut self, expected: u16) -> Result<(), ()> {
+        match self.reply()? {
+            (code, _) if code == expected => Ok(()),
+            (code, lines) => {
+                error!("{} {}", code, lines.join(" "));
+                Err(())
+            }
+        }
+    }
+
+    // Return the extensions supported by the server
+    fn hello(&mut self, domain: &str) -> Result<Vec<String>, ()> {
+        let lines = self.expect(&format!("EHLO {}", domain), 250)?;
+        Ok(lines.into_iter().skip(1).collect())
+    }
+
+    fn login(
+        &mut self, extensions: &[String], user: &str, password: &str
+    ) -> Result<(), ()> {
+        let mechanisms: Vec<String> = extensions.iter().find_map(|ext| {
+            ext.strip_prefix("AUTH ").map(|list| {
+                list.split_whitespace().map(|s| s.to_uppercase()).collect()
+            })
+        }).unwrap_or_default();
+        if mechanisms.iter().any(|m| m == "PLAIN") {
+            let credentials = format!("\0{}\0{}", user, password);
+            let cmd = format!("AUTH PLAIN {}", encode(credentials.as_bytes()));
+            self.expect(&cmd, 235)?;
+        } else if mechanisms.iter().any(|m| m == "LOGIN") {
+            self.expect("AUTH LOGIN", 334)?;
+            self.expect(&encode(user.as_bytes()), 334)?;
+            self.expect(&encode(password.as_bytes()), 235)?;
+        } else {
+            error!("Could not find a supported authentication mechanism");
+            return Err(());
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        match &mut self.conn {
+            Connection::Tcp(handle) => syscall::close(*handle),
+            Connection::Tls(handle, stream) => {
+                stream.close();
+                syscall::close(*handle);
+            }
+        }
+    }
+}
+
+// Parse a complete reply made of lines like "250-First" and "250 Last",
+// returning its code, the text of its lines, and its length
+fn parse_reply(buf: &[u8]) -> Option<(u16, Vec<String>, usize)> {
+    let mut start = 0;
+    let mut lines = Vec::new();
+    while let Some(i) = buf[start..].iter().position(|&b| b == b'\n') {
+        let end = start + i + 1;
+        let line = String::from_utf8_lossy(&buf[start..end]);
+        let line = line.trim_end();
+        start = end;
+        let code = line.get(..3).and_then(|s| s.parse::<u16>().ok())?;
+        lines.push(line.get(4..).unwrap_or("").to_string());
+        if line.as_bytes().get(3) != Some(&b'-') {
+            return Some((code, lines, end));
+        }
+    }
+    None
+}
+
+// Convert the line endings of the body to CRLF, and double the dots at the
+// beginning of lines to keep them from ending the data early
+fn encode_body(body: &str) -> String {
+    let mut res = String::new();
+    for line in body.lines() {
+        if line.starts_with('.') {
+            res.push('.');
+        }
+        res.push_str(line);
+        res.push_str("\r\n");
+    }
+    res.push_str(".\r\n");
+    res
+}
+
+fn encode(s: &[u8]) -> String {
+    let mut buf = vec![0; s.len().div_ceil(3) * 4];
+    let n = base64::encode_config_slice(s, base64::STANDARD, &mut buf);
+    buf.truncate(n);
+    String::from_utf8_lossy(&buf).to_string()
+}
+
+// Return the address of a mailbox like "Name <user@example.com>"
+fn address(mailbox: &str) -> &str {
+    match (mailbox.rfind('<'), mailbox.rfind('>')) {
+        (Some(i), Some(j)) if i < j => &mailbox[i + 1..j],
+        _ => mailbox.trim(),
+    }
+}
+
+fn resolve(host: &str) -> Result<IpAddress, ExitCode> {
+    if host.ends_with(char::is_numeric) {
+        match IpAddress::from_str(host) {
+            Ok(addr) => Ok(addr),
+            Err(_) => {
+                error!("Invalid address format");
+                Err(ExitCode::UsageError)
+            }
+        }
+    } else {
+        match usr::host::resolve(host) {
+            Ok(addr) => Ok(addr),
+            Err(e) => {
+                error!("Could not resolve host: {:?}", e);
+                Err(ExitCode::Failure)
+            }
+        }
+    }
+}
+
+fn open_connection(addr: IpAddress, port: u16) -> Result<usize, ()> {
+    let socket_path = "/dev/net/tcp";
+    let flags = OpenFlag::Device as usize;
+    let handle = match syscall::open(socket_path, flags) {
+        Some(handle) => handle,
+        None => {
+            error!("Could not open '{}'", socket_path);
+            return Err(());
+        }
+    };
+    if syscall::connect(handle, addr, port).is_err() {
+        error!("Could not connect to {}:{}", addr, port);
+        syscall::close(handle);
+        return Err(());
+    }
+    Ok(handle)
+}
+
+struct Mail<'a> {
+    from: String,
+    to: Vec<&'a str>,
+    subject: Option<&'a str>,
+    body: String,
+}
+
+impl Mail<'_> {
+    fn to_message(&self, domain: &str) -> String {
+        let date = time::now_utc().format("%a, %d %b %Y %H:%M:%S +0000");
+        let id = time::now_utc().unix_timestamp_nanos();
+        let mut headers = vec![
+            format!("Date: {}", date),
+            format!("From: {}", self.from),
+            format!("To: {}", self.to.join(", ")),
+        ];
+        if let Some(subject) = self.subject {
+            headers.push(format!("Subject: {}", subject));
+        }
+        headers.push(format!("Message-ID: <{}@{}>", id, domain));
+        headers.push("MIME-Version: 1.0".to_string());
+        headers.push("Content-Type: text/plain; charset=utf-8".to_string());
+        format!("{}\n\n{}", headers.join("\n"), self.body)
+    }
+}
+
+fn send(
+    client: &mut Client,
+    mail: &Mail,
+    credentials: Option<(&str, &str)>,
+    domain: &str,
+    is_insecure: bool,
+) -> Result<(), ()> {
+    client.reply_code(220)?;
+    let mut extensions = client.hello(domain)?;
+    let has_starttls = extensions.iter().any(|ext| ext == "STARTTLS");
+    if !client.is_secure() && has_starttls && is_insecure {
+        client.expect("STARTTLS", 220)?;
+        client.start_tls()?;
+        extensions = client.hello(domain)?;
+    }
+    if let Some((user, password)) = credentials {
+        if !client.is_secure() {
+            error!("Could not authenticate without TLS");
+            return Err(());
+        }
+        client.login(&extensions, user, password)?;
+    }
+    client.expect(&format!("MAIL FROM:<{}>", address(&mail.from)), 250)?;
+    for to in &mail.to {
+        match client.command(&format!("RCPT TO:<{}>", address(to)))? {
+            (250, _) | (251, _) => {}
+            (code, lines) => {
+                error!("{} {}", code, lines.join(" "));
+                return Err(());
+            }
+        }
+    }
+    client.expect("DATA", 354)?;
+    if client.verbose {
+        debug!("<message>");
+    }
+    let data = encode_body(&mail.to_message(domain));
+    client.write(data.as_bytes())?;
+    client.reply_code(250)?;
+    client.command("QUIT").ok();
+    Ok(())
+}
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut verbose = false;
+    let mut is_insecure = false;
+    let mut tls = false;
+    let mut from = None;
+    let mut subject = None;
+    let mut user = None;
+    let mut password = None;
+    let mut params = Vec::new();
+    let mut i = 1;
+    let n = args.len();
+    while i < n {
+        match args[i] {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-v" | "--verbose" => {
+                verbose = true;
+            }
+            "-t" | "--tls" => {
+                tls = true;
+            }
+            "-k" | "--insecure" => {
+                is_insecure = true;
+            }
+            "-f" | "--from" | "-s" | "--subject" | "-u" | "--user" |
+            "-p" | "--password" if i + 1 == n => {
+                error!("Missing value for '{}'", args[i]);
+                return Err(ExitCode::UsageError);
+            }
+            "-f" | "--from" => {
+                i += 1;
+                from = Some(args[i]);
+            }
+            "-s" | "--subject" => {
+                i += 1;
+                subject = Some(args[i]);
+            }
+            "-u" | "--user" => {
+                i += 1;
+                user = Some(args[i]);
+            }
+            "-p" | "--password" => {
+                i += 1;
+                password = Some(args[i]);
+            }
+            arg if arg.starts_with('-') => {
+                error!("Invalid option '{}'", arg);
+                return Err(ExitCode::UsageError);
+            }
+            arg => {
+                params.push(arg);
+            }
+        }
+        i += 1;
+    }
+    if params.len() < 2 {
+        help();
+        return Err(ExitCode::UsageError);
+    }
+    let credentials = match (user, password) {
+        (Some(user), Some(password)) => Some((user, password)),
+        (None, None) => None,
+        _ => {
+            error!("Missing user or password");
+            return Err(ExitCode::UsageError);
+        }
+    };
+    let to = params[1..].to_vec();
+    if let Some(to) = to.iter().find(|to| !address(to).contains('@')) {
+        error!("Invalid recipient '{}'", to);
+        return Err(ExitCode::UsageError);
+    }
+
+    let domain = sys::process::env("HOSTNAME").unwrap_or("localhost".into());
+    let from = match from {
+        Some(from) => from.to_string(),
+        None => {
+            let name = sys::process::env("USER").unwrap_or("moros".into());
+            format!("{}@{}", name, domain)
+        }
+    };
+
+    let default_port = if tls { SMTPS_PORT } else { SMTP_PORT };
+    let (host, port) = match params[0].split_once(':') {
+        Some((host, port)) => match port.parse() {
+            Ok(port) => (host, port),
+            Err(_) => {
+                error!("Invalid port '{}'", port);
+                return Err(ExitCode::UsageError);
+            }
+        },
+        None => (params[0], default_port),
+    };
+
+    if (tls || credentials.is_some()) && !TlsStream::is_enabled() {
+        error!("Could not use experimental TLS client (build with tls=true)");
+        return Err(ExitCode::Failure);
+    }
+
+    // The TLS client doesn't verify the certificate of the server, and the
+    // credentials are only sent over TLS
+    if (tls || credentials.is_some()) && !is_insecure {
+        error!("Could not verify certificate of '{}'", host);
+        return Err(ExitCode::Failure);
+    }
+
+    // The body of the message is read before connecting to the server to
+    // avoid keeping the connection idle while it's being typed
+    let body = io::stdin().read_to_string();
+    let mail = Mail { from, to, subject, body };
+
+    let socket_path = "/dev/net/tcp";
+    let buf_len = match syscall::info(socket_path) {
+        Some(info) => info.size() as usize,
+        None => {
+            error!("Could not open '{}'", socket_path);
+            return Err(ExitCode::Failure);
+        }
+    };
+    let addr = resolve(host)?;
+    let handle = open_connection(addr, port).map_err(|_| ExitCode::Failure)?;
+    let mut client = Client {
+        conn: Connection::Tcp(handle),
+        host: host.to_string(),
+        buf: Vec::new(),
+        buf_len,
+        verbose,
+    };
+    if tls && client.start_tls().is_err() {
+        client.close();
+        return Err(ExitCode::Failure);
+    }
+    let res = send(&mut client, &mail, credentials, &domain, is_insecure);
+    client.close();
+    res.map_err(|_| ExitCode::Failure)
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} mail {}<options> <server>[:<port>] <recipient>...{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-f{1}, {0}--from <address>{1}        Set sender address",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-s{1}, {0}--subject <text>{1}        Set subject",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-u{1}, {0}--user <name>{1}           Authenticate with user",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-p{1}, {0}--password <password>{1}   Authenticate with password",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-t{1}, {0}--tls{1}                   Connect with TLS",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-k{1}, {0}--insecure{1}              Allow TLS without \
+        verifying the server",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-v{1}, {0}--verbose{1}               Increase verbosity",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_parse_reply() {
+    let buf = b"250-smtp.example.com\r\n250-STARTTLS\r\n250 AUTH PLAIN\r\n";
+    let (code, lines, n) = parse_reply(buf).unwrap();
+    assert_eq!(code, 250);
+    assert_eq!(lines, vec!["smtp.example.com", "STARTTLS", "AUTH PLAIN"]);
+    assert_eq!(n, buf.len());
+
+    let buf = b"220 smtp.example.com ESMTP\r\n250 OK";
+    let (code, lines, n) = parse_reply(buf).unwrap();
+    assert_eq!(code, 220);
+    assert_eq!(lines, vec!["smtp.example.com ESMTP"]);
+    assert_eq!(n, 28);
+
+    assert_eq!(parse_reply(b"250-smtp.example.com\r\n"), None);
+    assert_eq!(parse_reply(b"250 OK"), None);
+}
+
+#[test_case]
+fn test_encode_body() {
+    let body = "Hello\r\n..\r\n...world\r\n.\r\n";
+    assert_eq!(encode_body("Hello\n.\n..world\n"), body);
+    assert_eq!(encode_body("a\r\nb"), "a\r\nb\r\n.\r\n");
+    assert_eq!(encode("\0user\0pass".as_bytes()), "AHVzZXIAcGFzcw==");
+    assert_eq!(address("Vinc <vinc@example.com>"), "vinc@example.com");
+    assert_eq!(address("vinc@example.com"), "vinc@example.com");
+}
@@ -1,38 +1,78 @@
+pub mod arp;
 pub mod base64;
 pub mod beep;
+pub mod browse;
 pub mod calc;
 pub mod chess;
+pub mod chgrp;
+pub mod chmod;
+pub mod chown;
 pub mod copy;
+pub mod cut;
 pub mod date;
+pub mod dd;
 pub mod delete;
+pub mod df;
 pub mod dhcp;
+pub mod diff;
 pub mod disk;
+pub mod du;
 pub mod editor;
 pub mod elf;
 pub mod env;
+pub mod file;
 pub mod find;
+pub mod fsck;
+pub mod ftp;
+pub mod fw;
+pub mod grep;
+pub mod gzip;
 pub mod hash;
+pub mod head;
 pub mod help;
 pub mod hex;
 pub mod host;
 pub mod http;
 pub mod httpd;
 pub mod install;
+pub mod irc;
 pub mod keyboard;
+pub mod less;
 pub mod life;
 pub mod lisp;
 pub mod list;
+pub mod ln;
+pub mod mail;
 pub mod memory;
+pub mod mount;
 pub mod r#move;
+pub mod nc;
 pub mod net;
+pub mod netstat;
+pub mod ntp;
 pub mod pci;
 pub mod pi;
+pub mod ping;
 pub mod pow;
+pub mod printf;
 pub mod read;
+pub mod seq;
 pub mod shell;
 pub mod socket;
+pub mod sort;
+pub mod stat;
+pub mod sync;
+pub mod tail;
 pub mod tcp;
+pub mod tcpdump;
+pub mod tee;
 pub mod time;
+pub mod touch;
+pub mod tr;
+pub mod umount;
+pub mod uniq;
 pub mod user;
 pub mod vga;
+pub mod watch;
+pub mod wc;
 pub mod write;
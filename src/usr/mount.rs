@@ -0,0 +1,193 @@
+use crate::api::console::Style;
+use crate::api::process::ExitCode;
+use crate::sys;
+use crate::sys::fs::{AtaBlockDevice, BlockDevice, Filesystem, MemBlockDevice};
+use crate::sys::fs::{Iso, IsoDevice};
+use crate::usr;
+
+use alloc::vec::Vec;
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    if args.iter().any(|arg| *arg == "-h" || *arg == "--help") {
+        help();
+        return Ok(());
+    }
+    let mut size = None;
+    let mut paths = Vec::new();
+    let mut i = 1;
+    let n = args.len();
+    while i < n {
+        match args[i] {
+            "-s" | "--size" if i + 1 < n => {
+                i += 1;
+                match usr::dd::parse_number(args[i]) {
+                    Some(n) if n > 0 => size = Some(n),
+                    _ => {
+                        error!("Invalid size '{}'", args[i]);
+                        return Err(ExitCode::UsageError);
+                    }
+                }
+            }
+            arg => paths.push(arg),
+        }
+        i += 1;
+    }
+    match paths.len() {
+        0 if size.is_none() => {
+            list();
+            Ok(())
+        }
+        2 => mount(paths[0], paths[1], size),
+        _ => {
+            help();
+            Err(ExitCode::UsageError)
+        }
+    }
+}
+
+fn list() {
+    for (source, path) in sys::fs::mounts() {
+        println!("{} on {}", source, path);
+    }
+}
+
+fn mount(
+    source: &str,
+    path: &str,
+    size: Option<usize>
+) -> Result<(), ExitCode> {
+    if size.is_some() && source != "mem" {
+        error!("Could not set the size of '{}'", source);
+        return Err(ExitCode::UsageError);
+    }
+    let fs = if source == "mem" {
+        // A quarter of the allocatable memory by default
+        let size = size.unwrap_or(sys::allocator::memory_size() / 4);
+        let dev = MemBlockDevice::with_capacity(size);
+        Filesystem::Mfs(BlockDevice::Mem(dev))
+    } else if source == "proc" {
+        Filesystem::Proc
+    } else {
+        let (bus, dsk) = match usr::disk::parse_disk_path(source) {
+            Ok(disk) => disk,
+            Err(msg) => {
+                error!("{}", msg);
+                return Err(ExitCode::Failure);
+            }
+        };
+        if sys::ata::is_atapi(bus, dsk) {
+            // The disc of a CD-ROM drive is read as an ISO 9660 image
+            match Iso::open(IsoDevice::Atapi(bus, dsk)) {
+                Some(iso) => Filesystem::Iso(iso),
+                None => {
+                    error!("Could not read disc in '{}'", source);
+                    return Err(ExitCode::Failure);
+                }
+            }
+        } else {
+            // The dir entries of another version would be misparsed
+            match sys::fs::version_ata(bus, dsk) {
+                Some(version) if version != sys::fs::VERSION => {
+                    error!(
+                        "Could not mount MFS v{} from '{}' (v{} expected)",
+                        version, source, sys::fs::VERSION
+                    );
+                    return Err(ExitCode::Failure);
+                }
+                _ => {}
+            }
+            match AtaBlockDevice::new(bus, dsk) {
+                Some(dev) => Filesystem::Mfs(BlockDevice::Ata(dev)),
+                None => {
+                    error!("Could not find disk at '{}'", source);
+                    return Err(ExitCode::Failure);
+                }
+            }
+        }
+    };
+    if sys::fs::mount(fs, path).is_err() {
+        error!("Could not mount '{}' to '{}'", source, path);
+        return Err(ExitCode::Failure);
+    }
+    Ok(())
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} mount {}[<options>] [<source> <path>]{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-s{1}, {0}--size <size>{1}   Size of a filesystem in memory",
+        csi_option, csi_reset
+    );
+    println!();
+    println!("{}Sources:{}", csi_title, csi_reset);
+    println!(
+        "  {0}/dev/ata/<bus>/<dsk>{1}   Disk formatted with MFS or CD-ROM",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}mem{1}                    Empty filesystem in memory",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}proc{1}                   Kernel data like processes and memory",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_mount() {
+    use crate::api::{fs, syscall};
+
+    sys::fs::mount_mem();
+    sys::fs::format_mem();
+
+    assert!(fs::create_dir("/mnt").is_some());
+    assert!(fs::write("/mnt/a.txt", b"a").is_ok());
+    assert!(main(&["mount", "mem", "/mnt"]).is_ok());
+    assert!(!fs::exists("/mnt/a.txt"));
+    assert!(fs::write("/mnt/b.txt", b"b").is_ok());
+    assert!(fs::create_dir("/mnt/tmp").is_some());
+    assert!(fs::exists("/mnt/tmp/../b.txt"));
+
+    // Files cannot be moved across filesystems
+    assert!(fs::rename("/mnt/b.txt", "/b.txt").is_err());
+
+    assert!(main(&["mount", "mem", "/mnt"]).is_err());
+    assert!(main(&["mount", "mem", "/nope"]).is_err());
+
+    assert!(usr::umount::main(&["umount", "/mnt"]).is_ok());
+    assert!(fs::exists("/mnt/a.txt"));
+    assert!(!fs::exists("/mnt/b.txt"));
+
+    // The size of a filesystem in memory is rounded up to 2 MB
+    assert!(main(&["mount", "-s", "1M", "mem", "/mnt"]).is_ok());
+    let buf = alloc::vec![0; 512 << 10];
+    assert!(fs::write("/mnt/a.bin", &buf).is_ok());
+    assert!(fs::write("/mnt/b.bin", &buf).is_ok());
+    assert!(fs::write("/mnt/c.bin", &buf).is_ok());
+    assert!(fs::write("/mnt/d.bin", &buf).is_err());
+
+    // A write past the limit keeps the bytes written so far and leaves the
+    // filesystem consistent
+    let size = syscall::info("/mnt/d.bin").map(|info| info.size()).unwrap();
+    assert!(0 < size && size < buf.len() as u32);
+    let dev = sys::fs::mfs_device("/mnt").unwrap();
+    assert!(sys::fs::check(dev).is_empty());
+    assert!(fs::delete("/mnt/d.bin").is_ok());
+    assert!(fs::delete("/mnt/c.bin").is_ok());
+    assert!(fs::write("/mnt/c.bin", &buf).is_ok());
+    assert!(usr::umount::main(&["umount", "/mnt"]).is_ok());
+
+    assert!(main(&["mount", "-s", "1M", "proc", "/mnt"]).is_err());
+    assert!(main(&["mount", "-s", "0", "mem", "/mnt"]).is_err());
+
+    sys::fs::dismount();
+}
@@ -1,29 +1,79 @@
 use crate::api::console::Style;
+use crate::api::fs;
+use crate::api::io;
 use crate::api::process::ExitCode;
+use crate::sys;
 use crate::usr;
 
+use alloc::vec::Vec;
+
 pub fn main(args: &[&str]) -> Result<(), ExitCode> {
-    let n = args.len();
-    if n != 3 {
-        help();
-        return Err(ExitCode::UsageError);
-    }
-    for i in 1..n {
-        match args[i] {
+    let mut interactive = false;
+    let mut paths = Vec::new();
+    for arg in &args[1..] {
+        match *arg {
             "-h" | "--help" => {
                 help();
                 return Ok(());
             }
-            _ => continue,
+            "-i" | "--interactive" => interactive = true,
+            _ => paths.push(*arg),
         }
     }
+    if paths.len() < 2 {
+        help();
+        return Err(ExitCode::UsageError);
+    }
+
+    let dest = paths.pop().unwrap();
+    if paths.len() > 1 && !fs::is_dir(dest) {
+        error!("Could not move multiple files to '{}'", dest);
+        return Err(ExitCode::Failure);
+    }
 
-    // TODO: Avoid doing copy+delete
-    if usr::copy::main(args).is_ok() {
-        usr::delete::main(&args[0..2])
-    } else {
-        Err(ExitCode::Failure)
+    for source in paths {
+        move_path(source, dest, interactive)?;
     }
+    Ok(())
+}
+
+fn move_path(
+    source: &str,
+    dest: &str,
+    interactive: bool
+) -> Result<(), ExitCode> {
+    if !fs::exists(source) {
+        error!("Could not find file '{}'", source);
+        return Err(ExitCode::Failure);
+    }
+
+    let target = usr::copy::destination(source, dest);
+    if interactive && fs::exists(&target) {
+        print!("Overwrite '{}'? [y/N] ", target);
+        if io::stdin().read_line().trim() != "y" {
+            return Ok(());
+        }
+    }
+
+    // Files are copied then deleted when they cannot be renamed because they
+    // are moved to another filesystem
+    let src = fs::realpath(source.trim_end_matches('/'));
+    let dst = fs::realpath(&target);
+    if !sys::fs::is_same_filesystem(fs::dirname(&src), fs::dirname(&dst)) {
+        if fs::is_dir(source) {
+            usr::copy::main(&["copy", "-r", source, dest])?;
+            return usr::delete::main(&["delete", "-r", source]);
+        } else {
+            usr::copy::main(&["copy", source, dest])?;
+            return usr::delete::main(&["delete", source]);
+        }
+    }
+
+    if fs::rename(source, &target).is_err() {
+        error!("Could not move '{}' to '{}'", source, target);
+        return Err(ExitCode::Failure);
+    }
+    Ok(())
 }
 
 fn help() {
@@ -31,7 +81,53 @@ fn help() {
     let csi_title = Style::color("Yellow");
     let csi_reset = Style::reset();
     println!(
-        "{}Usage:{} move {}<src> <dst>{}",
+        "{}Usage:{} move {}<options> <src>... <dst>{}",
         csi_title, csi_reset, csi_option, csi_reset
     );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-i{1}, {0}--interactive{1}   Confirm before overwriting",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_move() {
+    sys::fs::mount_mem();
+    sys::fs::format_mem();
+    usr::install::copy_files(false);
+
+    assert!(main(&["move", "/tmp/alice.txt", "/tmp/life/"]).is_ok());
+    assert!(!fs::exists("/tmp/alice.txt"));
+    assert!(fs::exists("/tmp/life/alice.txt"));
+
+    assert!(main(&["move", "/tmp/life", "/tmp/lisp", "/usr"]).is_ok());
+    assert!(fs::exists("/usr/life/alice.txt"));
+    assert!(fs::exists("/usr/lisp"));
+
+    assert!(main(&["move", "/usr/life", "/usr/lisp", "/nope"]).is_err());
+
+    // Overwrite an existing file
+    let contents = fs::read_to_string("/usr/life/alice.txt").unwrap();
+    assert!(fs::write("/tmp/test.txt", b"test").is_ok());
+    assert!(main(&["move", "/usr/life/alice.txt", "/tmp/test.txt"]).is_ok());
+    assert!(!fs::exists("/usr/life/alice.txt"));
+    assert_eq!(fs::read_to_string("/tmp/test.txt"), Ok(contents));
+
+    // Move a dir inside itself
+    assert!(main(&["move", "/usr/life", "/usr/life/test"]).is_err());
+
+    // Move files to another filesystem
+    assert!(usr::mount::main(&["mount", "mem", "/tmp"]).is_ok());
+    assert!(fs::write("/tmp/a.txt", b"a").is_ok());
+    assert!(main(&["move", "/tmp/a.txt", "/usr/a.txt"]).is_ok());
+    assert!(!fs::exists("/tmp/a.txt"));
+    assert_eq!(fs::read_to_string("/usr/a.txt"), Ok("a".into()));
+    assert!(main(&["move", "/usr/lisp", "/tmp"]).is_ok());
+    assert!(!fs::exists("/usr/lisp"));
+    assert!(fs::exists("/tmp/lisp"));
+    assert!(usr::umount::main(&["umount", "/tmp"]).is_ok());
+
+    sys::fs::dismount();
 }
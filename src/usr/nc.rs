@@ -0,0 +1,227 @@
+use crate::api::console::Style;
+use crate::api::fs::IO;
+use crate::api::process::ExitCode;
+use crate::api::syscall;
+use crate::sys::console;
+use crate::sys::fs::OpenFlag;
+use crate::sys::net::SocketStatus;
+use crate::usr;
+
+use alloc::vec;
+use alloc::vec::Vec;
+use bit_field::BitField;
+use core::str::FromStr;
+use smoltcp::wire::IpAddress;
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut listen = false;
+    let mut udp = false;
+    let mut crlf = false;
+    let mut verbose = false;
+    let mut params = Vec::new();
+    for arg in &args[1..] {
+        match *arg {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-l" | "--listen" => listen = true,
+            "-u" | "--udp" => udp = true,
+            "-c" | "--crlf" => crlf = true,
+            "-v" | "--verbose" => verbose = true,
+            arg if arg.starts_with('-') => {
+                error!("Invalid option '{}'", arg);
+                return Err(ExitCode::UsageError);
+            }
+            arg => params.push(arg),
+        }
+    }
+
+    let (host, port) = match (listen, params.len()) {
+        (true, 1) => ("0.0.0.0", params[0]),
+        (false, 2) => (params[0], params[1]),
+        _ => {
+            help();
+            return Err(ExitCode::UsageError);
+        }
+    };
+    let port: u16 = match port.parse() {
+        Ok(n) => n,
+        Err(_) => {
+            error!("Could not parse port '{}'", port);
+            return Err(ExitCode::UsageError);
+        }
+    };
+    let addr = if host.ends_with(char::is_numeric) {
+        match IpAddress::from_str(host) {
+            Ok(addr) => addr,
+            Err(_) => {
+                error!("Invalid address '{}'", host);
+                return Err(ExitCode::UsageError);
+            }
+        }
+    } else {
+        match usr::host::resolve(host) {
+            Ok(addr) => addr,
+            Err(e) => {
+                error!("Could not resolve host {:?}", e);
+                return Err(ExitCode::Failure);
+            }
+        }
+    };
+
+    let socket_path = if udp { "/dev/net/udp" } else { "/dev/net/tcp" };
+    let buf_len = if let Some(info) = syscall::info(socket_path) {
+        info.size() as usize
+    } else {
+        error!("Could not open '{}'", socket_path);
+        return Err(ExitCode::Failure);
+    };
+    let flags = OpenFlag::Device as usize;
+    let listener = match syscall::open(socket_path, flags) {
+        Some(handle) => handle,
+        None => {
+            error!("Could not open '{}'", socket_path);
+            return Err(ExitCode::Failure);
+        }
+    };
+
+    let mut handle = listener;
+    let mut connected = false;
+    if listen {
+        if syscall::listen(handle, port).is_err() {
+            error!("Could not listen to port {}", port);
+            syscall::close(handle);
+            return Err(ExitCode::Failure);
+        }
+        if verbose {
+            debug!("Listening to port {}", port);
+        }
+    } else {
+        if syscall::connect(handle, addr, port).is_err() {
+            error!("Could not connect to {}:{}", addr, port);
+            syscall::close(handle);
+            return Err(ExitCode::Failure);
+        }
+        if verbose {
+            debug!("Connected to {}:{}", addr, port);
+        }
+        connected = true;
+    }
+
+    // The data read from the standard input is sent to the socket, and the
+    // data received from the socket is written to the standard output, until
+    // the connection is closed by the remote host or by pressing ^C or ^D.
+    let stdin = 0;
+    let stdout = 1;
+    let mut res = Ok(());
+    loop {
+        if console::end_of_text() || console::end_of_transmission() {
+            break;
+        }
+
+        if !connected {
+            if let Ok((connection, remote)) = syscall::accept(listener) {
+                if verbose {
+                    debug!("Accepted connection from {}", remote);
+                }
+                handle = connection;
+                connected = true;
+            }
+            continue;
+        }
+
+        let list = [(stdin, IO::Read), (handle, IO::Read)];
+        match syscall::poll(&list) {
+            Some((h, _)) if h == stdin => {
+                let mut data = vec![0; buf_len];
+                if let Some(bytes) = syscall::read(stdin, &mut data) {
+                    data.resize(bytes, 0);
+                    if crlf {
+                        data = to_crlf(&data);
+                    }
+                    if syscall::write(handle, &data).is_none() {
+                        error!("Could not send data");
+                        res = Err(ExitCode::Failure);
+                        break;
+                    }
+                }
+            }
+            Some(_) => {
+                let mut data = vec![0; buf_len];
+                if let Some(bytes) = syscall::read(handle, &mut data) {
+                    data.resize(bytes, 0);
+                    syscall::write(stdout, &data);
+                }
+            }
+            None => {
+                syscall::sleep(0.01);
+                if !udp && is_closed(handle) {
+                    break;
+                }
+            }
+        }
+    }
+    syscall::close(handle);
+    if listen {
+        syscall::close(listener);
+    }
+    res
+}
+
+fn is_closed(handle: usize) -> bool {
+    let mut data = vec![0; 1]; // 1 byte status read
+    match syscall::read(handle, &mut data) {
+        Some(1) => !data[0].get_bit(SocketStatus::MayRecv as usize),
+        _ => false,
+    }
+}
+
+fn to_crlf(data: &[u8]) -> Vec<u8> {
+    let mut res = Vec::with_capacity(data.len());
+    for (i, b) in data.iter().enumerate() {
+        if *b == b'\n' && (i == 0 || data[i - 1] != b'\r') {
+            res.push(b'\r');
+        }
+        res.push(*b);
+    }
+    res
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} nc {}<options> <host> <port>{1}",
+        csi_title, csi_reset, csi_option
+    );
+    println!(
+        "{}Usage:{} nc {}<options> --listen <port>{1}",
+        csi_title, csi_reset, csi_option
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-l{1}, {0}--listen{1}    Listen to a local port",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-u{1}, {0}--udp{1}       Use UDP instead of TCP",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-c{1}, {0}--crlf{1}      Send CRLF line endings",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-v{1}, {0}--verbose{1}   Increase verbosity",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_to_crlf() {
+    assert_eq!(to_crlf(b"a\nb\r\n"), b"a\r\nb\r\n");
+    assert_eq!(to_crlf(b"\n"), b"\r\n");
+}
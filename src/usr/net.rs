@@ -1,11 +1,9 @@
-use crate::api::clock;
 use crate::api::console::Style;
 use crate::api::fs;
 use crate::api::process::ExitCode;
 use crate::api::syscall;
 use crate::sys;
 use crate::sys::console;
-use crate::sys::net;
 use crate::sys::net::EthernetDeviceIO;
 use alloc::format;
 
@@ -13,14 +11,37 @@ use alloc::borrow::ToOwned;
 use alloc::string::String;
 use alloc::string::ToString;
 use alloc::vec;
+use alloc::vec::Vec;
 use core::str::FromStr;
 use smoltcp::iface::SocketSet;
 use smoltcp::phy::Device;
 use smoltcp::socket::tcp;
-use smoltcp::time::Instant;
-use smoltcp::wire::{EthernetFrame, IpCidr, Ipv4Address, PrettyPrinter};
+use smoltcp::wire::{
+    EthernetFrame, IpAddress, IpCidr, Ipv4Address, PrettyPrinter,
+};
 
 pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut args = args.to_vec();
+    let mut index = None;
+    if let Some(i) = args.iter().position(|a| {
+        *a == "-i" || *a == "--interface"
+    }) {
+        let name = match args.get(i + 1) {
+            Some(name) => *name,
+            None => {
+                error!("Missing interface name");
+                return Err(ExitCode::UsageError);
+            }
+        };
+        match find_interface(name) {
+            Some(i) => index = Some(i),
+            None => {
+                error!("Could not find interface '{}'", name);
+                return Err(ExitCode::Failure);
+            }
+        }
+        args.drain(i..i + 2);
+    }
     match *args.get(1).unwrap_or(&"") {
         "-h" | "--help" => {
             help();
@@ -28,21 +49,31 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
         }
         "c" | "config" => {
             if args.len() < 3 {
-                print_config("mac");
-                print_config("ip");
-                print_config("gw");
-                print_config("dns");
+                print_config("mac", index);
+                print_config("ip", index);
+                print_config("gw", index);
+                print_config("dns", index);
             } else if args[2] == "-h" || args[2] == "--help" {
                 help_config();
                 return Ok(());
+            } else if args[2] == "save" {
+                return save_config();
+            } else if args[2] == "load" {
+                return load_config();
             } else if args.len() < 4 {
-                print_config(args[2]);
+                print_config(args[2], index);
             } else {
-                set_config(args[2], args[3]);
+                set_config(args[2], args[3], index);
             }
         }
+        "l" | "list" => {
+            list();
+        }
+        "r" | "route" => {
+            route();
+        }
         "s" | "stat" => {
-            stat();
+            stat(index);
         }
         "m" | "monitor" => {
             monitor();
@@ -60,17 +91,31 @@ fn help() {
     let csi_title = Style::color("Yellow");
     let csi_reset = Style::reset();
     println!(
-        "{}Usage:{} net {}<command>{}",
+        "{}Usage:{} net {}[<options>] <command>{}",
         csi_title, csi_reset, csi_option, csi_reset
     );
     println!();
     println!("{}Commands:{}", csi_title, csi_reset);
     println!("  {}config{}   Configure network", csi_option, csi_reset);
+    println!(
+        "  {}list{}     List network interfaces",
+        csi_option, csi_reset
+    );
     println!("  {}monitor{}  Monitor network", csi_option, csi_reset);
+    println!(
+        "  {}route{}    Display routing table",
+        csi_option, csi_reset
+    );
     println!(
         "  {}stat{}     Display network status",
         csi_option, csi_reset
     );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-i{1}, {0}--interface <name>{1}  Select network interface",
+        csi_option, csi_reset
+    );
 }
 
 fn help_config() {
@@ -81,18 +126,110 @@ fn help_config() {
         "{}Usage:{} net config {}<attribute> <value>{}",
         csi_title, csi_reset, csi_option, csi_reset
     );
+    println!(
+        "{}Usage:{} net config {}save|load{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
     println!();
     println!("{}Attributes:{}", csi_title, csi_reset);
     println!("  {}mac{}  MAC Address", csi_option, csi_reset);
-    println!("  {}ip{}   IP Address", csi_option, csi_reset);
+    println!(
+        "  {}ip{}   IP Address with prefix or netmask",
+        csi_option, csi_reset
+    );
     println!("  {}gw{}   Gateway Address", csi_option, csi_reset);
     println!("  {}dns{}  Domain Name Servers", csi_option, csi_reset);
+    println!();
+    println!("{}Commands:{}", csi_title, csi_reset);
+    println!(
+        "  {}save{} Save IP and gateway addresses to '{}'",
+        csi_option, csi_reset, NET_FILE
+    );
+    println!(
+        "  {}load{} Load IP and gateway addresses from '{}'",
+        csi_option, csi_reset, NET_FILE
+    );
+}
+
+const NET_FILE: &str = "/ini/net";
+
+// Save the static configuration of the network in a file made of lines of
+// attributes followed by their value and by the name of the interface when
+// it's not the first one, the DNS servers being already saved in their own
+// file.
+fn save_config() -> Result<(), ExitCode> {
+    let mut contents = String::new();
+    for (i, name) in interface_names().iter().enumerate() {
+        for attribute in ["ip", "gw"] {
+            if let Some(value) = get_interface_config(attribute, Some(i)) {
+                contents.push_str(&format!("{} {}", attribute, value));
+                if i > 0 {
+                    contents.push_str(&format!(" {}", name));
+                }
+                contents.push('\n');
+            }
+        }
+    }
+    if fs::write(NET_FILE, contents.as_bytes()).is_err() {
+        error!("Could not write to '{}'", NET_FILE);
+        return Err(ExitCode::Failure);
+    }
+    Ok(())
+}
+
+fn load_config() -> Result<(), ExitCode> {
+    if !fs::exists(NET_FILE) {
+        return Ok(()); // Nothing to load without a static configuration
+    }
+    let contents = match fs::read_to_string(NET_FILE) {
+        Ok(contents) => contents,
+        Err(()) => {
+            error!("Could not read '{}'", NET_FILE);
+            return Err(ExitCode::Failure);
+        }
+    };
+    for line in contents.lines() {
+        let fields: Vec<_> = line.split_whitespace().collect();
+        match fields[..] {
+            [] => {}
+            [attribute, value] if attribute != "debug" => {
+                set_config(attribute, value, None);
+            }
+            [attribute, value, name] if attribute != "debug" => {
+                match find_interface(name) {
+                    Some(i) => set_config(attribute, value, Some(i)),
+                    None => warning!("Could not find interface '{}'", name),
+                }
+            }
+            _ => {
+                error!("Could not parse '{}'", line);
+                return Err(ExitCode::Failure);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Parse an address with either a prefix length or a netmask
+fn parse_ip(value: &str) -> Option<IpCidr> {
+    if let Some((addr, mask)) = value.split_once('/') {
+        if let Ok(mask) = Ipv4Address::from_str(mask) {
+            let bits = u32::from_be_bytes(mask.0);
+            if bits.leading_ones() + bits.trailing_zeros() != 32 {
+                return None;
+            }
+            let addr = Ipv4Address::from_str(addr).ok()?;
+            let prefix_len = bits.leading_ones() as u8;
+            return Some(IpCidr::new(addr.into(), prefix_len));
+        }
+    }
+    IpCidr::from_str(value).ok()
 }
 
-fn print_config(attribute: &str) {
+fn print_config(attribute: &str, index: Option<usize>) {
     let csi_color = Style::color("LightCyan");
     let csi_reset = Style::reset();
-    if let Some(value) = get_config(attribute) {
+    if let Some(value) = get_interface_config(attribute, index) {
         let width = 4 - attribute.len();
         println!(
             "{}{}:{}{:width$}{}",
@@ -106,6 +243,14 @@ fn print_config(attribute: &str) {
     }
 }
 
+fn find_interface(name: &str) -> Option<usize> {
+    interface_names().iter().position(|n| n == name)
+}
+
+fn interface_names() -> Vec<String> {
+    sys::net::NET.lock().iter().map(|eth| eth.name.clone()).collect()
+}
+
 const DNS_FILE: &str = "/ini/dns";
 
 fn dns_config() -> Option<String> {
@@ -123,23 +268,33 @@ fn dns_config() -> Option<String> {
     }
 }
 
-fn gw_config() -> Option<String> {
+// Return the default gateway of the interface, or of the first interface
+// having one when none is given
+fn gw_config(index: Option<usize>) -> Option<String> {
     let mut res = None;
-    if let Some((ref mut iface, _)) = *sys::net::NET.lock() {
-        iface.routes_mut().update(|storage| {
+    let mut net = sys::net::NET.lock();
+    if net.is_empty() {
+        error!("Network error");
+    }
+    for (i, eth) in net.iter_mut().enumerate() {
+        if index.is_some() && index != Some(i) {
+            continue;
+        }
+        eth.iface.routes_mut().update(|storage| {
             if let Some(route) = storage.iter().next() {
                 res = Some(route.via_router.to_string());
             }
         });
-    } else {
-        error!("Network error");
+        if res.is_some() {
+            break;
+        }
     }
     res
 }
 
-fn ip_config() -> Option<String> {
-    if let Some((ref mut iface, _)) = *sys::net::NET.lock() {
-        if let Some(ip_cidr) = iface.ip_addrs().iter().next() {
+fn ip_config(index: Option<usize>) -> Option<String> {
+    if let Some(eth) = sys::net::NET.lock().get(index.unwrap_or(0)) {
+        if let Some(ip_cidr) = eth.iface.ip_addrs().iter().next() {
             return Some(format!(
                 "{}/{}", ip_cidr.address(), ip_cidr.prefix_len()
             ));
@@ -150,21 +305,28 @@ fn ip_config() -> Option<String> {
     None
 }
 
-fn mac_config() -> Option<String> {
-    if let Some((ref mut iface, _)) = *sys::net::NET.lock() {
-        return Some(iface.hardware_addr().to_string());
+fn mac_config(index: Option<usize>) -> Option<String> {
+    if let Some(eth) = sys::net::NET.lock().get(index.unwrap_or(0)) {
+        return Some(eth.iface.hardware_addr().to_string());
     } else {
         error!("Network error");
     }
     None
 }
 
+// Return an attribute of the configuration of the first interface
 pub fn get_config(attribute: &str) -> Option<String> {
+    get_interface_config(attribute, None)
+}
+
+fn get_interface_config(attribute: &str, index: Option<usize>)
+    -> Option<String>
+{
     match attribute {
         "dns" => dns_config(),
-        "gw" => gw_config(),
-        "ip" => ip_config(),
-        "mac" => mac_config(),
+        "gw" => gw_config(index),
+        "ip" => ip_config(index),
+        "mac" => mac_config(index),
         _ => {
             error!("Invalid config attribute");
             None
@@ -172,44 +334,64 @@ pub fn get_config(attribute: &str) -> Option<String> {
     }
 }
 
-pub fn set_config(attribute: &str, value: &str) {
+// Set an attribute of the configuration of an interface, the first one
+// being used by default except for the gateway that goes to the interface
+// on its network, and the debug mode that goes to all of them.
+pub fn set_config(attribute: &str, value: &str, index: Option<usize>) {
+    let mut net = sys::net::NET.lock();
+    if net.is_empty() && attribute != "dns" {
+        error!("Network error");
+        return;
+    }
     match attribute {
         "debug" => {
-            if let Some((_, ref mut device)) = *sys::net::NET.lock() {
+            for (i, eth) in net.iter_mut().enumerate() {
+                if index.is_some() && index != Some(i) {
+                    continue;
+                }
                 match value {
-                    "1" | "true" => device.config().enable_debug(),
-                    "0" | "false" => device.config().disable_debug(),
-                    _ => error!("Invalid config value"),
+                    "1" | "true" => eth.device.config().enable_debug(),
+                    "0" | "false" => eth.device.config().disable_debug(),
+                    _ => {
+                        error!("Invalid config value");
+                        return;
+                    }
                 }
-            } else {
-                error!("Network error");
             }
         }
         "ip" => {
-            if let Ok(addr) = IpCidr::from_str(value) {
-                if let Some((ref mut iface, _)) = *sys::net::NET.lock() {
-                    iface.update_ip_addrs(|addrs| {
-                        addrs.clear();
-                        addrs.push(addr).unwrap();
-                    });
-                } else {
-                    error!("Network error");
-                }
+            if let Some(addr) = parse_ip(value) {
+                net[index.unwrap_or(0)].iface.update_ip_addrs(|addrs| {
+                    addrs.clear();
+                    addrs.push(addr).unwrap();
+                });
             } else {
                 error!("Could not parse address");
             }
         }
         "gw" => {
-            if let Some((ref mut iface, _)) = *sys::net::NET.lock() {
-                if value == "0.0.0.0" {
-                    iface.routes_mut().remove_default_ipv4_route();
-                } else if let Ok(ip) = Ipv4Address::from_str(value) {
-                    iface.routes_mut().add_default_ipv4_route(ip).unwrap();
-                } else {
-                    error!("Could not parse address");
+            if value == "0.0.0.0" {
+                for (i, eth) in net.iter_mut().enumerate() {
+                    if index.is_none() || index == Some(i) {
+                        eth.iface.routes_mut().remove_default_ipv4_route();
+                    }
                 }
+            } else if let Ok(ip) = Ipv4Address::from_str(value) {
+                // There is only one default route to keep the interfaces
+                // from sending packets with the address of another one
+                let i = index.or_else(|| {
+                    let routes = sys::net::routes(&mut net);
+                    routes.iter().find(|route| {
+                        route.gateway.is_none() &&
+                            route.cidr.contains_addr(&ip.into())
+                    }).map(|route| route.index)
+                }).unwrap_or(0);
+                for eth in net.iter_mut() {
+                    eth.iface.routes_mut().remove_default_ipv4_route();
+                }
+                net[i].iface.routes_mut().add_default_ipv4_route(ip).unwrap();
             } else {
-                error!("Network error");
+                error!("Could not parse address");
             }
         }
         "dns" => {
@@ -229,24 +411,59 @@ pub fn set_config(attribute: &str, value: &str) {
     }
 }
 
-pub fn stat() {
-    if let Some((_, ref mut device)) = *sys::net::NET.lock() {
-        let stats = device.stats();
+fn list() {
+    let csi_color = Style::color("LightCyan");
+    let csi_reset = Style::reset();
+    for eth in sys::net::NET.lock().iter() {
+        let addrs: Vec<_> = eth.iface.ip_addrs().iter().map(|addr| {
+            addr.to_string()
+        }).collect();
+        println!(
+            "{}{:6}{} {:8} {} {}",
+            csi_color,
+            eth.name,
+            csi_reset,
+            eth.device.driver(),
+            eth.iface.hardware_addr(),
+            addrs.join(",")
+        );
+    }
+}
+
+fn route() {
+    let mut net = sys::net::NET.lock();
+    let unspecified = IpAddress::Ipv4(Ipv4Address::UNSPECIFIED);
+    for route in sys::net::routes(&mut net) {
+        println!(
+            "{:18} {:15} {}",
+            route.cidr.to_string(),
+            route.gateway.unwrap_or(unspecified).to_string(),
+            net[route.index].name
+        );
+    }
+}
+
+pub fn stat(index: Option<usize>) {
+    if let Some(eth) = sys::net::NET.lock().get(index.unwrap_or(0)) {
+        let stats = eth.device.stats();
         let csi_color = Style::color("LightCyan");
         let csi_reset = Style::reset();
         println!(
-            "{}rx:{} {} packets ({} bytes)",
+            "{}rx:{} {} packets ({} bytes), {} errors, {} dropped",
             csi_color,
             csi_reset,
             stats.rx_packets_count(),
-            stats.rx_bytes_count()
+            stats.rx_bytes_count(),
+            stats.rx_errors_count(),
+            stats.rx_dropped_count()
         );
         println!(
-            "{}tx:{} {} packets ({} bytes)",
+            "{}tx:{} {} packets ({} bytes), {} dropped",
             csi_color,
             csi_reset,
             stats.tx_packets_count(),
-            stats.tx_bytes_count()
+            stats.tx_bytes_count(),
+            stats.tx_dropped_count()
         );
     } else {
         error!("Network error");
@@ -254,10 +471,13 @@ pub fn stat() {
 }
 
 fn monitor() {
-    if let Some((ref mut iface, ref mut device)) = *net::NET.lock() {
-        device.config().enable_debug();
+    let mut net = sys::net::NET.lock();
+    if let Some(eth) = net.first() {
+        let mtu = eth.device.capabilities().max_transmission_unit;
+        for eth in net.iter() {
+            eth.device.config().enable_debug();
+        }
 
-        let mtu = device.capabilities().max_transmission_unit;
         let tcp_rx_buffer = tcp::SocketBuffer::new(vec![0; mtu]);
         let tcp_tx_buffer = tcp::SocketBuffer::new(vec![0; mtu]);
         let tcp_socket = tcp::Socket::new(tcp_rx_buffer, tcp_tx_buffer);
@@ -271,9 +491,7 @@ fn monitor() {
             }
             syscall::sleep(0.1);
 
-            let ms = (clock::realtime() * 1000000.0) as i64;
-            let time = Instant::from_micros(ms);
-            iface.poll(time, device, &mut sockets);
+            sys::net::poll(&mut net, &mut sockets);
             let socket = sockets.get_mut::<tcp::Socket>(tcp_handle);
             if socket.may_recv() {
                 socket.recv(|buffer| {
@@ -291,3 +509,14 @@ fn monitor() {
         error!("Network error");
     }
 }
+
+#[test_case]
+fn test_parse_ip() {
+    let addr = Ipv4Address::new(10, 0, 2, 15);
+    let cidr = Some(IpCidr::new(addr.into(), 24));
+    assert_eq!(parse_ip("10.0.2.15/24"), cidr);
+    assert_eq!(parse_ip("10.0.2.15/255.255.255.0"), cidr);
+    assert_eq!(parse_ip("10.0.2.15/255.0.255.0"), None);
+    assert_eq!(parse_ip("10.0.2/255.255.255.0"), None);
+    assert_eq!(parse_ip("10.0.2.15"), None);
+}
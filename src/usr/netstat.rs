@@ -0,0 +1,174 @@
+use crate::api::console::Style;
+use crate::api::fs;
+use crate::api::process::ExitCode;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+const SOCKETS_DEVICE: &str = "/dev/net/sockets";
+const INTERFACES_DEVICE: &str = "/dev/net/interfaces";
+
+#[derive(Debug, PartialEq)]
+struct SocketEntry<'a> {
+    protocol: &'a str,
+    local: &'a str,
+    remote: &'a str,
+    state: &'a str,
+    owner: &'a str,
+}
+
+#[derive(Debug, PartialEq)]
+struct InterfaceEntry<'a> {
+    name: &'a str,
+    rx: [u64; 4], // Packets, bytes, errors, and dropped packets
+    tx: [u64; 3], // Packets, bytes, and dropped packets
+}
+
+fn parse_socket(line: &str) -> Option<SocketEntry> {
+    let fields: Vec<_> = line.split_whitespace().collect();
+    if fields.len() != 5 {
+        return None;
+    }
+    Some(SocketEntry {
+        protocol: fields[0],
+        local: fields[1],
+        remote: fields[2],
+        state: fields[3],
+        owner: fields[4],
+    })
+}
+
+fn parse_interface(line: &str) -> Option<InterfaceEntry> {
+    let fields: Vec<_> = line.split_whitespace().collect();
+    if fields.len() != 8 {
+        return None;
+    }
+    let counters = fields[1..].iter().map(|s| s.parse().ok()).
+        collect::<Option<Vec<u64>>>()?;
+    Some(InterfaceEntry {
+        name: fields[0],
+        rx: [counters[0], counters[1], counters[2], counters[3]],
+        tx: [counters[4], counters[5], counters[6]],
+    })
+}
+
+fn read(path: &str) -> Result<String, ExitCode> {
+    fs::read_to_string(path).map_err(|_| {
+        error!("Could not read '{}'", path);
+        ExitCode::Failure
+    })
+}
+
+fn print_sockets(protocols: &[&str]) -> Result<(), ExitCode> {
+    let contents = read(SOCKETS_DEVICE)?;
+    let color = Style::color("LightCyan");
+    let reset = Style::reset();
+    println!(
+        "{}{:5} {:21} {:21} {:12} {}{}",
+        color, "Proto", "Local", "Remote", "State", "PID", reset
+    );
+    for entry in contents.lines().filter_map(parse_socket) {
+        if !protocols.is_empty() && !protocols.contains(&entry.protocol) {
+            continue;
+        }
+        println!(
+            "{:5} {:21} {:21} {:12} {}",
+            entry.protocol, entry.local, entry.remote, entry.state, entry.owner
+        );
+    }
+    Ok(())
+}
+
+fn print_interfaces() -> Result<(), ExitCode> {
+    let contents = read(INTERFACES_DEVICE)?;
+    let color = Style::color("LightCyan");
+    let reset = Style::reset();
+    println!(
+        "{}{:6} {:>8} {:>10} {:>6} {:>6} {:>8} {:>10} {:>6}{}",
+        color, "Iface", "RX-Pkts", "RX-Bytes", "RX-Err", "RX-Drp",
+        "TX-Pkts", "TX-Bytes", "TX-Drp", reset
+    );
+    for entry in contents.lines().filter_map(parse_interface) {
+        println!(
+            "{:6} {:>8} {:>10} {:>6} {:>6} {:>8} {:>10} {:>6}",
+            entry.name, entry.rx[0], entry.rx[1], entry.rx[2], entry.rx[3],
+            entry.tx[0], entry.tx[1], entry.tx[2]
+        );
+    }
+    Ok(())
+}
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut interfaces = false;
+    let mut protocols = Vec::new();
+    for arg in &args[1..] {
+        match *arg {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-i" | "--interfaces" => interfaces = true,
+            "-t" | "--tcp" => protocols.push("tcp"),
+            "-u" | "--udp" => protocols.push("udp"),
+            "--icmp" => protocols.push("icmp"),
+            _ => {
+                help();
+                return Err(ExitCode::UsageError);
+            }
+        }
+    }
+    if interfaces {
+        print_interfaces()
+    } else {
+        print_sockets(&protocols)
+    }
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} netstat {}<options>{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-i{1}, {0}--interfaces{1}   List the counters of the interfaces",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-t{1}, {0}--tcp{1}          List TCP sockets",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-u{1}, {0}--udp{1}          List UDP sockets",
+        csi_option, csi_reset
+    );
+    println!(
+        "      {0}--icmp{1}         List ICMP sockets",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_parse() {
+    let line = "tcp 10.0.2.15:49152 10.0.2.2:80 ESTABLISHED 1";
+    assert_eq!(parse_socket(line), Some(SocketEntry {
+        protocol: "tcp",
+        local: "10.0.2.15:49152",
+        remote: "10.0.2.2:80",
+        state: "ESTABLISHED",
+        owner: "1",
+    }));
+    assert_eq!(parse_socket("udp *:68 *"), None);
+
+    let line = "eth0 42 4096 1 2 24 2048 3";
+    assert_eq!(parse_interface(line), Some(InterfaceEntry {
+        name: "eth0",
+        rx: [42, 4096, 1, 2],
+        tx: [24, 2048, 3],
+    }));
+    assert_eq!(parse_interface("eth0 42 4096 1 2 24 2048 x"), None);
+}
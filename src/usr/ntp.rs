@@ -0,0 +1,330 @@
+use crate::api::clock;
+use crate::api::clock::DATE_TIME;
+use crate::api::console::Style;
+use crate::api::fs;
+use crate::api::fs::IO;
+use crate::api::process::ExitCode;
+use crate::api::syscall;
+use crate::api::time;
+use crate::sys::console;
+use crate::sys::fs::OpenFlag;
+use crate::usr;
+
+use alloc::string::ToString;
+use alloc::vec;
+use core::convert::TryInto;
+use core::str::FromStr;
+use smoltcp::wire::IpAddress;
+
+const CONFIG: &str = "/ini/ntp";
+const DEFAULT_SERVER: &str = "time.cloudflare.com";
+const PORT: u16 = 123;
+const PACKET_LEN: usize = 48;
+const TIMEOUT: f64 = 5.0;
+
+// Seconds between the NTP era (1900-01-01) and the Unix Epoch (1970-01-01)
+const NTP_EPOCH_OFFSET: f64 = 2_208_988_800.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Sample {
+    offset: f64,
+    delay: f64,
+}
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut set = false;
+    let mut verbose = false;
+    let mut interval = None;
+    let mut server = None;
+    let mut i = 1;
+    let n = args.len();
+    while i < n {
+        match args[i] {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-s" | "--set" => {
+                set = true;
+            }
+            "-v" | "--verbose" => {
+                verbose = true;
+            }
+            "-i" | "--interval" => {
+                match args.get(i + 1).and_then(|s| s.parse::<f64>().ok()) {
+                    Some(x) if x > 0.0 => interval = Some(x),
+                    _ => {
+                        error!("Missing or invalid interval");
+                        return Err(ExitCode::UsageError);
+                    }
+                }
+                i += 1;
+            }
+            arg if server.is_none() && !arg.starts_with('-') => {
+                server = Some(arg.to_string());
+            }
+            _ => {
+                help();
+                return Err(ExitCode::UsageError);
+            }
+        }
+        i += 1;
+    }
+    let server = server.unwrap_or_else(|| {
+        match fs::read_to_string(CONFIG) {
+            Ok(s) if !s.trim().is_empty() => s.trim().to_string(),
+            _ => DEFAULT_SERVER.to_string(),
+        }
+    });
+
+    let interval = match interval {
+        Some(interval) => interval,
+        None => {
+            let sample = sync(&server, set, verbose)?;
+            print_time(if set { 0.0 } else { sample.offset });
+            return Ok(());
+        }
+    };
+
+    // Periodic synchronization always updates the clock and keeps going
+    // after a failure until interrupted.
+    loop {
+        let started = clock::realtime();
+        if sync(&server, true, verbose).is_ok() {
+            print_time(0.0);
+        }
+        while clock::realtime() - started < interval {
+            if console::end_of_text() || console::end_of_transmission() {
+                return Ok(());
+            }
+            syscall::sleep(0.1);
+        }
+    }
+}
+
+fn sync(server: &str, set: bool, verbose: bool) -> Result<Sample, ExitCode> {
+    let addr = if server.ends_with(char::is_numeric) {
+        match IpAddress::from_str(server) {
+            Ok(addr) => addr,
+            Err(_) => {
+                error!("Invalid address '{}'", server);
+                return Err(ExitCode::UsageError);
+            }
+        }
+    } else {
+        match usr::host::resolve(server) {
+            Ok(addr) => addr,
+            Err(e) => {
+                error!("Could not resolve host {:?}", e);
+                return Err(ExitCode::Failure);
+            }
+        }
+    };
+
+    let socket_path = "/dev/net/udp";
+    let flags = OpenFlag::Device as usize;
+    let handle = match syscall::open(socket_path, flags) {
+        Some(handle) => handle,
+        None => {
+            error!("Could not open '{}'", socket_path);
+            return Err(ExitCode::Failure);
+        }
+    };
+    let res = query(handle, addr);
+    syscall::close(handle);
+    let sample = match res {
+        Ok(sample) => sample,
+        Err(msg) => {
+            error!("{} from {}", msg, server);
+            return Err(ExitCode::Failure);
+        }
+    };
+    if verbose {
+        debug!("Server {} ({})", server, addr);
+        debug!("Offset {:+.6} seconds", sample.offset);
+        debug!("Delay  {:.6} seconds", sample.delay);
+    }
+    if set {
+        set_clock(sample.offset)?;
+    }
+    Ok(sample)
+}
+
+fn query(handle: usize, addr: IpAddress) -> Result<Sample, &'static str> {
+    if syscall::connect(handle, addr, PORT).is_err() {
+        return Err("Could not connect");
+    }
+    let t1 = clock::realtime();
+    let req = request(t1);
+    if syscall::write(handle, &req).is_none() {
+        return Err("Could not send request");
+    }
+    loop {
+        if console::end_of_text() || console::end_of_transmission() {
+            return Err("Interrupted waiting for response");
+        }
+        if clock::realtime() - t1 > TIMEOUT {
+            return Err("Timeout waiting for response");
+        }
+        if syscall::poll(&[(handle, IO::Read)]).is_none() {
+            syscall::sleep(0.001);
+            continue;
+        }
+        let mut res = vec![0; PACKET_LEN];
+        let bytes = match syscall::read(handle, &mut res) {
+            Some(bytes) => bytes,
+            None => continue,
+        };
+        let t4 = clock::realtime();
+        res.resize(bytes, 0);
+        return match parse_response(&res, &req) {
+            Ok((t2, t3)) => Ok(sample(t1, t2, t3, t4)),
+            Err(()) => Err("Invalid response"),
+        };
+    }
+}
+
+// The RTC only stores whole seconds so we wait for the start of the next
+// second on the server clock before writing it to minimize the error.
+fn set_clock(offset: f64) -> Result<(), ExitCode> {
+    let now = clock::realtime() + offset;
+    let next = libm::floor(now) + 1.0;
+    syscall::sleep(next - now);
+    let date = time::from_timestamp_utc(next as i64).format(DATE_TIME);
+
+    let path = "/dev/rtc";
+    if let Some(handle) = fs::open_device(path) {
+        let res = syscall::write(handle, date.as_bytes());
+        syscall::close(handle);
+        if res.is_some() {
+            return Ok(());
+        }
+    }
+    error!("Could not write to '{}'", path);
+    Err(ExitCode::Failure)
+}
+
+fn print_time(offset: f64) {
+    let now = clock::realtime() + offset;
+    println!("{}", time::from_timestamp_utc(now as i64).format(DATE_TIME));
+}
+
+// Build a client request carrying our transmit time that the server will
+// echo back in the originate timestamp of its response.
+fn request(t1: f64) -> [u8; PACKET_LEN] {
+    let mut buf = [0; PACKET_LEN];
+    buf[0] = 0x23; // LI = 0, VN = 4, Mode = 3 (client)
+    buf[40..48].copy_from_slice(&ntp_timestamp(t1));
+    buf
+}
+
+// Return the receive and transmit times of a valid server response
+fn parse_response(buf: &[u8], req: &[u8]) -> Result<(f64, f64), ()> {
+    if buf.len() < PACKET_LEN {
+        return Err(());
+    }
+    let leap = buf[0] >> 6;
+    let mode = buf[0] & 0x7;
+    let stratum = buf[1];
+    if leap == 3 || mode != 4 || stratum == 0 || stratum > 15 {
+        return Err(()); // Unsynchronized server or kiss-o'-death packet
+    }
+    if buf[24..32] != req[40..48] {
+        return Err(()); // Not a response to our request
+    }
+    let t2 = unix_time(&buf[32..40]);
+    let t3 = unix_time(&buf[40..48]);
+    if t3 == 0.0 {
+        return Err(());
+    }
+    Ok((t2, t3))
+}
+
+fn sample(t1: f64, t2: f64, t3: f64, t4: f64) -> Sample {
+    Sample {
+        offset: ((t2 - t1) + (t3 - t4)) / 2.0,
+        delay: (t4 - t1) - (t3 - t2),
+    }
+}
+
+fn ntp_timestamp(t: f64) -> [u8; 8] {
+    let t = t + NTP_EPOCH_OFFSET;
+    let secs = libm::floor(t);
+    let frac = ((t - secs) * 4_294_967_296.0) as u32;
+    let mut buf = [0; 8];
+    buf[0..4].copy_from_slice(&(secs as u32).to_be_bytes());
+    buf[4..8].copy_from_slice(&frac.to_be_bytes());
+    buf
+}
+
+fn unix_time(buf: &[u8]) -> f64 {
+    let secs = u32::from_be_bytes(buf[0..4].try_into().unwrap());
+    let frac = u32::from_be_bytes(buf[4..8].try_into().unwrap());
+    if secs == 0 && frac == 0 {
+        return 0.0;
+    }
+    (secs as f64) - NTP_EPOCH_OFFSET + (frac as f64) / 4_294_967_296.0
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} ntp {}<options> [<server>]{1}",
+        csi_title, csi_reset, csi_option
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-s{1}, {0}--set{1}                 Set the clock",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-i{1}, {0}--interval <seconds>{1}  Set the clock periodically",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-v{1}, {0}--verbose{1}             Show offset and delay",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_ntp_timestamp() {
+    let t = 1682105344.5;
+    let buf = ntp_timestamp(t);
+    assert_eq!(buf, [0xE7, 0xED, 0x62, 0x80, 0x80, 0, 0, 0]);
+    assert_eq!(unix_time(&buf), t);
+    assert_eq!(unix_time(&[0; 8]), 0.0);
+}
+
+#[test_case]
+fn test_parse_response() {
+    let req = request(1682105344.0);
+    let mut res = [0; PACKET_LEN];
+    res[0] = 0x24; // LI = 0, VN = 4, Mode = 4 (server)
+    res[1] = 2;
+    res[24..32].copy_from_slice(&req[40..48]);
+    res[32..40].copy_from_slice(&ntp_timestamp(1682105354.25));
+    res[40..48].copy_from_slice(&ntp_timestamp(1682105354.5));
+    assert_eq!(parse_response(&res, &req), Ok((1682105354.25, 1682105354.5)));
+
+    let mut bad = res;
+    bad[1] = 0; // Kiss-o'-death
+    assert_eq!(parse_response(&bad, &req), Err(()));
+
+    let mut bad = res;
+    bad[24] = 0; // Wrong originate timestamp
+    assert_eq!(parse_response(&bad, &req), Err(()));
+
+    assert_eq!(parse_response(&res[0..40], &req), Err(()));
+}
+
+#[test_case]
+fn test_sample() {
+    // The server clock is 10 seconds ahead, each way takes 0.25 seconds, and
+    // the server takes another 0.25 seconds to respond
+    let s = sample(100.0, 110.25, 110.5, 100.75);
+    assert_eq!(s, Sample { offset: 10.0, delay: 0.5 });
+}
@@ -0,0 +1,234 @@
+use crate::api::clock;
+use crate::api::console::Style;
+use crate::api::fs::IO;
+use crate::api::process::ExitCode;
+use crate::api::rng;
+use crate::api::syscall;
+use crate::sys::console;
+use crate::sys::fs::OpenFlag;
+use crate::usr;
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::str::FromStr;
+use smoltcp::phy::ChecksumCapabilities;
+use smoltcp::wire::{IpAddress, Icmpv4Packet, Icmpv4Repr};
+
+const DATA_LEN: usize = 56;
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut count = None;
+    let mut interval = 1.0;
+    let mut host = None;
+    let mut i = 1;
+    let n = args.len();
+    while i < n {
+        match args[i] {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-c" | "--count" => {
+                match args.get(i + 1).and_then(|s| s.parse::<usize>().ok()) {
+                    Some(n) => count = Some(n),
+                    None => {
+                        error!("Missing or invalid count");
+                        return Err(ExitCode::UsageError);
+                    }
+                }
+                i += 1;
+            }
+            "-i" | "--interval" => {
+                match args.get(i + 1).and_then(|s| s.parse::<f64>().ok()) {
+                    Some(x) if x > 0.0 => interval = x,
+                    _ => {
+                        error!("Missing or invalid interval");
+                        return Err(ExitCode::UsageError);
+                    }
+                }
+                i += 1;
+            }
+            arg if host.is_none() && !arg.starts_with('-') => {
+                host = Some(arg);
+            }
+            _ => {
+                help();
+                return Err(ExitCode::UsageError);
+            }
+        }
+        i += 1;
+    }
+    let host = match host {
+        Some(host) => host,
+        None => {
+            help();
+            return Err(ExitCode::UsageError);
+        }
+    };
+
+    let addr = if host.ends_with(char::is_numeric) {
+        match IpAddress::from_str(host) {
+            Ok(addr) => addr,
+            Err(_) => {
+                error!("Invalid address '{}'", host);
+                return Err(ExitCode::UsageError);
+            }
+        }
+    } else {
+        match usr::host::resolve(host) {
+            Ok(addr) => addr,
+            Err(e) => {
+                error!("Could not resolve host {:?}", e);
+                return Err(ExitCode::Failure);
+            }
+        }
+    };
+
+    let socket_path = "/dev/net/icmp";
+    let buf_len = if let Some(info) = syscall::info(socket_path) {
+        info.size() as usize
+    } else {
+        error!("Could not open '{}'", socket_path);
+        return Err(ExitCode::Failure);
+    };
+
+    let flags = OpenFlag::Device as usize;
+    let handle = match syscall::open(socket_path, flags) {
+        Some(handle) => handle,
+        None => {
+            error!("Could not open '{}'", socket_path);
+            return Err(ExitCode::Failure);
+        }
+    };
+
+    // The identifier of the echo requests is used like a port to receive
+    // the replies on the socket.
+    let ident = rng::get_u16();
+    if syscall::connect(handle, addr, ident).is_err() {
+        error!("Could not connect to {}", addr);
+        syscall::close(handle);
+        return Err(ExitCode::Failure);
+    }
+
+    println!("PING {} ({}): {} data bytes", host, addr, DATA_LEN);
+    let mut sent = 0;
+    let mut rtts = Vec::new();
+    let mut seq_no: u16 = 0;
+    'ping: while count.map_or(true, |n| sent < n) {
+        let started = clock::realtime();
+        let request = echo_request(ident, seq_no);
+        if syscall::write(handle, &request).is_none() {
+            error!("Could not send echo request to {}", addr);
+            break;
+        }
+        sent += 1;
+
+        let timeout = interval.max(1.0);
+        loop {
+            if console::end_of_text() || console::end_of_transmission() {
+                break 'ping;
+            }
+            let elapsed = clock::realtime() - started;
+            if elapsed > timeout {
+                println!("Request timeout for icmp_seq={}", seq_no);
+                break;
+            }
+            if syscall::poll(&[(handle, IO::Read)]).is_none() {
+                syscall::sleep(0.001);
+                continue;
+            }
+            let mut data = vec![0; buf_len];
+            let bytes = match syscall::read(handle, &mut data) {
+                Some(bytes) => bytes,
+                None => continue,
+            };
+            data.resize(bytes, 0);
+            if is_echo_reply(&data, ident, seq_no) {
+                let rtt = 1000.0 * elapsed;
+                println!(
+                    "{} bytes from {}: icmp_seq={} time={:.3} ms",
+                    bytes, addr, seq_no, rtt
+                );
+                rtts.push(rtt);
+                break;
+            }
+        }
+
+        seq_no = seq_no.wrapping_add(1);
+        if count.map_or(true, |n| sent < n) {
+            while clock::realtime() - started < interval {
+                if console::end_of_text() || console::end_of_transmission() {
+                    break 'ping;
+                }
+                syscall::sleep(0.01);
+            }
+        }
+    }
+    syscall::close(handle);
+
+    let received = rtts.len();
+    let loss = if sent > 0 {
+        100.0 * ((sent - received) as f64) / (sent as f64)
+    } else {
+        0.0
+    };
+    println!();
+    println!("--- {} ping statistics ---", host);
+    println!(
+        "{} packets transmitted, {} packets received, {:.1}% packet loss",
+        sent, received, loss
+    );
+    if received > 0 {
+        let min = rtts.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = rtts.iter().cloned().fold(0.0, f64::max);
+        let avg = rtts.iter().sum::<f64>() / (received as f64);
+        println!(
+            "round-trip min/avg/max = {:.3}/{:.3}/{:.3} ms",
+            min, avg, max
+        );
+        Ok(())
+    } else {
+        Err(ExitCode::Failure)
+    }
+}
+
+fn echo_request(ident: u16, seq_no: u16) -> Vec<u8> {
+    let data: Vec<u8> = (0..DATA_LEN).map(|i| i as u8).collect();
+    let repr = Icmpv4Repr::EchoRequest { ident, seq_no, data: &data };
+    let mut buf = vec![0; repr.buffer_len()];
+    let mut packet = Icmpv4Packet::new_unchecked(&mut buf);
+    repr.emit(&mut packet, &ChecksumCapabilities::default());
+    buf
+}
+
+fn is_echo_reply(buf: &[u8], ident: u16, seq_no: u16) -> bool {
+    let checksum = ChecksumCapabilities::default();
+    let repr = Icmpv4Packet::new_checked(buf).and_then(|packet| {
+        Icmpv4Repr::parse(&packet, &checksum)
+    });
+    matches!(
+        repr,
+        Ok(Icmpv4Repr::EchoReply { ident: i, seq_no: n, .. })
+            if i == ident && n == seq_no
+    )
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} ping {}<options> <host>{1}",
+        csi_title, csi_reset, csi_option
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-c{1}, {0}--count <n>{1}          Stop after sending n requests",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-i{1}, {0}--interval <seconds>{1} Wait between requests",
+        csi_option, csi_reset
+    );
+}
@@ -0,0 +1,250 @@
+use crate::api::console::Style;
+use crate::api::process::ExitCode;
+use crate::usr::shell;
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+#[derive(Default)]
+struct Spec {
+    left: bool,
+    zero: bool,
+    plus: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    if args.len() < 2 {
+        help();
+        return Err(ExitCode::UsageError);
+    }
+    if args[1] == "-h" || args[1] == "--help" {
+        help();
+        return Ok(());
+    }
+    match format(&shell::parse_str(args[1]), &args[2..]) {
+        Ok(s) => {
+            print!("{}", s);
+            Ok(())
+        }
+        Err(msg) => {
+            error!("{}", msg);
+            Err(ExitCode::DataError)
+        }
+    }
+}
+
+// The format is reused until all the arguments have been consumed
+fn format(fmt: &str, args: &[&str]) -> Result<String, String> {
+    let chars: Vec<char> = fmt.chars().collect();
+    let n = chars.len();
+    let mut args = args.iter();
+    let mut res = String::new();
+    loop {
+        let mut consumed = false;
+        let mut i = 0;
+        while i < n {
+            if chars[i] != '%' {
+                res.push(chars[i]);
+                i += 1;
+                continue;
+            }
+            i += 1;
+            if i < n && chars[i] == '%' {
+                res.push('%');
+                i += 1;
+                continue;
+            }
+
+            let mut spec = Spec::default();
+            while i < n {
+                match chars[i] {
+                    '-' => spec.left = true,
+                    '0' => spec.zero = true,
+                    '+' => spec.plus = true,
+                    _ => break,
+                }
+                i += 1;
+            }
+            while i < n && chars[i].is_ascii_digit() {
+                let digit = chars[i] as usize - '0' as usize;
+                spec.width = spec.width * 10 + digit;
+                i += 1;
+            }
+            if i < n && chars[i] == '.' {
+                let mut precision = 0;
+                i += 1;
+                while i < n && chars[i].is_ascii_digit() {
+                    let digit = chars[i] as usize - '0' as usize;
+                    precision = precision * 10 + digit;
+                    i += 1;
+                }
+                spec.precision = Some(precision);
+            }
+            if i == n {
+                return Err("Missing conversion".to_string());
+            }
+
+            let conv = chars[i];
+            i += 1;
+            let arg = match args.next() {
+                Some(arg) => {
+                    consumed = true;
+                    *arg
+                }
+                None => "",
+            };
+            res.push_str(&convert(conv, arg, &spec)?);
+        }
+        if !consumed || args.len() == 0 {
+            break;
+        }
+    }
+    Ok(res)
+}
+
+fn convert(conv: char, arg: &str, spec: &Spec) -> Result<String, String> {
+    let s = match conv {
+        's' => match spec.precision {
+            Some(precision) => arg.chars().take(precision).collect(),
+            None => arg.to_string(),
+        },
+        'c' => arg.chars().take(1).collect(),
+        'd' | 'i' => {
+            let n = parse_int(arg)?;
+            sign(format!("{}", n), n >= 0, spec)
+        }
+        'o' => format!("{:o}", parse_int(arg)?),
+        'x' => format!("{:x}", parse_int(arg)?),
+        'X' => format!("{:X}", parse_int(arg)?),
+        'f' | 'F' => {
+            let x = parse_float(arg)?;
+            let precision = spec.precision.unwrap_or(6);
+            sign(format!("{:.*}", precision, x), x >= 0.0, spec)
+        }
+        'e' | 'E' => {
+            let x = parse_float(arg)?;
+            let precision = spec.precision.unwrap_or(6);
+            let s = format!("{:.*e}", precision, x);
+            let s = if conv == 'E' { s.to_uppercase() } else { s };
+            sign(s, x >= 0.0, spec)
+        }
+        _ => return Err(format!("Invalid conversion '%{}'", conv)),
+    };
+    Ok(pad(s, spec, conv != 's' && conv != 'c'))
+}
+
+fn sign(s: String, positive: bool, spec: &Spec) -> String {
+    if spec.plus && positive {
+        format!("+{}", s)
+    } else {
+        s
+    }
+}
+
+fn pad(s: String, spec: &Spec, numeric: bool) -> String {
+    let n = s.chars().count();
+    if n >= spec.width {
+        return s;
+    }
+    let fill = spec.width - n;
+    if spec.left {
+        format!("{}{}", s, " ".repeat(fill))
+    } else if spec.zero && numeric {
+        let i = if s.starts_with(['-', '+']) { 1 } else { 0 };
+        format!("{}{}{}", &s[..i], "0".repeat(fill), &s[i..])
+    } else {
+        format!("{}{}", " ".repeat(fill), s)
+    }
+}
+
+fn parse_int(arg: &str) -> Result<i64, String> {
+    let res = if arg.is_empty() {
+        Ok(0)
+    } else if let Some(hex) = arg.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16)
+    } else {
+        arg.parse()
+    };
+    res.map_err(|_| format!("Invalid number '{}'", arg))
+}
+
+fn parse_float(arg: &str) -> Result<f64, String> {
+    if arg.is_empty() {
+        return Ok(0.0);
+    }
+    arg.parse().map_err(|_| format!("Invalid number '{}'", arg))
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} printf {}<format> [<arg>...]{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Conversions:{}", csi_title, csi_reset);
+    println!(
+        "  {0}%s{1}, {0}%c{1}               String and character",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}%d{1}, {0}%x{1}, {0}%X{1}, {0}%o{1}     Decimal, hexadecimal, \
+        and octal integer",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}%f{1}, {0}%e{1}               Float in decimal and scientific \
+        notation",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}%%{1}                   Percent sign",
+        csi_option, csi_reset
+    );
+    println!();
+    println!("{}Flags:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-{1}, {0}0{1}, {0}+{1}              Align left, pad with zeros, \
+        and show sign",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}<width>.<precision>{1}  Minimum width and precision",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_printf() {
+    assert_eq!(format("%s\n", &["a"]), Ok("a\n".into()));
+    assert_eq!(format("%s-", &["a", "b", "c"]), Ok("a-b-c-".into()));
+    assert_eq!(format("%s %s|", &["a", "b", "c"]), Ok("a b|c |".into()));
+    assert_eq!(format("100%%", &[]), Ok("100%".into()));
+    assert_eq!(
+        format("[%5s][%-5s]", &["ab", "cd"]),
+        Ok("[   ab][cd   ]".into())
+    );
+    assert_eq!(format("%.2s", &["abcd"]), Ok("ab".into()));
+    assert_eq!(format("%c", &["abc"]), Ok("a".into()));
+    assert_eq!(format("%d %i", &["42", "-7"]), Ok("42 -7".into()));
+    assert_eq!(format("%05d %+d", &["-42", "7"]), Ok("-0042 +7".into()));
+    assert_eq!(
+        format("%x %X %o", &["255", "0xff", "8"]),
+        Ok("ff FF 10".into())
+    );
+    assert_eq!(format("%f", &["3.14"]), Ok("3.140000".into()));
+    assert_eq!(
+        format("%.2f %8.3f", &["2", "-1.5"]),
+        Ok("2.00   -1.500".into())
+    );
+    assert_eq!(format("%.1e", &["1234"]), Ok("1.2e3".into()));
+    assert_eq!(format("%d", &[]), Ok("0".into()));
+    assert!(format("%d", &["a"]).is_err());
+    assert!(format("%y", &["a"]).is_err());
+    assert!(format("%", &[]).is_err());
+}
@@ -0,0 +1,114 @@
+use crate::api::console::Style;
+use crate::api::process::ExitCode;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut separator = String::from("\n");
+    let mut nums = Vec::new();
+    let mut i = 1;
+    let n = args.len();
+    while i < n {
+        match args[i] {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-s" | "--separator" => {
+                if i + 1 < n {
+                    separator = args[i + 1].into();
+                    i += 1;
+                } else {
+                    error!("Missing separator");
+                    return Err(ExitCode::UsageError);
+                }
+            }
+            arg => nums.push(arg),
+        }
+        i += 1;
+    }
+
+    let (first, step, last) = match nums.len() {
+        1 => ("1", "1", nums[0]),
+        2 => (nums[0], "1", nums[1]),
+        3 => (nums[0], nums[1], nums[2]),
+        _ => {
+            help();
+            return Err(ExitCode::UsageError);
+        }
+    };
+    let mut values = Vec::new();
+    for s in [first, step, last] {
+        if let Ok(x) = s.parse::<f64>() {
+            values.push(x);
+        } else {
+            error!("Invalid number '{}'", s);
+            return Err(ExitCode::UsageError);
+        }
+    }
+    if values[1] == 0.0 {
+        error!("Invalid zero step");
+        return Err(ExitCode::UsageError);
+    }
+
+    // Print as many decimals as the first value and the step
+    let precision = [first, step].iter().map(|s| {
+        s.find('.').map_or(0, |i| s.len() - i - 1)
+    }).max().unwrap_or(0);
+
+    let seq = sequence(values[0], values[1], values[2]);
+    let lines: Vec<String> = seq.iter().map(|x| {
+        format!("{:.*}", precision, x)
+    }).collect();
+    if !lines.is_empty() {
+        println!("{}", lines.join(&separator));
+    }
+    Ok(())
+}
+
+// The values are computed from the first one instead of being accumulated
+// to avoid rounding errors with floats.
+fn sequence(first: f64, step: f64, last: f64) -> Vec<f64> {
+    let mut res = Vec::new();
+    let epsilon = libm::fabs(step) * 1e-9;
+    let mut i = 0;
+    loop {
+        let x = first + (i as f64) * step;
+        if step > 0.0 && x > last + epsilon {
+            break;
+        }
+        if step < 0.0 && x < last - epsilon {
+            break;
+        }
+        res.push(x);
+        i += 1;
+    }
+    res
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} seq {}<options> [<first> [<step>]] <last>{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-s{1}, {0}--separator <str>{1}   Separate numbers with \
+        {0}<str>{1} instead of newlines",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_seq() {
+    assert_eq!(sequence(1.0, 1.0, 3.0), [1.0, 2.0, 3.0]);
+    assert_eq!(sequence(5.0, -2.0, 0.0), [5.0, 3.0, 1.0]);
+    assert_eq!(sequence(1.0, 1.0, 0.0), []);
+    assert_eq!(sequence(0.0, 0.1, 0.3).len(), 4);
+}
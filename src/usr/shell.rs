@@ -4,27 +4,37 @@ use crate::api::process::ExitCode;
 use crate::api::prompt::Prompt;
 use crate::api::regex::Regex;
 use crate::api::syscall;
-use crate::sys::fs::FileType;
+use crate::sys::fs::{FileType, Resource};
 use crate::{api, sys, usr};
 
+use alloc::boxed::Box;
 use alloc::collections::btree_map::BTreeMap;
 use alloc::format;
 use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 use core::sync::atomic::{fence, Ordering};
 
-// TODO: Scan /bin
-const AUTOCOMPLETE_COMMANDS: [&str; 36] = [
-    "2048", "base64", "calc", "copy", "date", "delete", "dhcp", "disk", "edit",
-    "elf", "env", "goto", "hash", "help", "hex", "host", "http", "httpd",
-    "install", "keyboard", "life", "lisp", "list", "memory", "move", "net",
-    "pci", "quit", "read", "shell", "socket", "tcp", "time", "user", "vga",
+// The binaries in /bin are completed in addition to these commands
+const AUTOCOMPLETE_COMMANDS: [&str; 88] = [
+    "2048", "alias", "arp", "base64", "beep", "browse", "calc", "chess",
+    "chgrp", "chmod", "chown", "copy", "cut", "date", "dd", "delete", "df",
+    "dhcp", "diff", "disk", "du", "edit", "elf", "env", "export", "file",
+    "find", "fsck", "ftp", "fw", "goto", "grep", "gunzip", "gzip", "hash",
+    "head", "help", "hex", "host", "http", "httpd", "install", "irc",
+    "keyboard", "less", "life", "lisp", "list", "ln", "logs", "mail", "memory",
+    "mount", "move", "nc", "net", "netstat", "ntp", "pci", "pi", "ping",
+    "printf", "quit", "read", "seq", "set", "shell", "socket", "sort", "stat",
+    "sync", "tail", "tcp", "tcpdump", "tee", "time", "touch", "tr", "umount",
+    "unalias", "uniq", "unset", "user", "version", "vga", "watch", "wc",
     "write",
 ];
 
+#[derive(Clone)]
 struct Config {
     env: BTreeMap<String, String>,
     aliases: BTreeMap<String, String>,
+    functions: BTreeMap<String, Vec<Statement>>,
+    returning: bool,
 }
 
 impl Config {
@@ -37,7 +47,9 @@ impl Config {
         }
         env.insert("DIR".to_string(), sys::process::dir());
         env.insert("status".to_string(), "0".to_string());
-        Config { env, aliases }
+        let functions = BTreeMap::new();
+        let returning = false;
+        Config { env, aliases, functions, returning }
     }
 }
 
@@ -51,6 +63,8 @@ fn autocomplete_commands() -> Vec<String> {
             res.push(file.name());
         }
     }
+    res.sort();
+    res.dedup();
     res
 }
 
@@ -98,26 +112,67 @@ fn shell_completer(line: &str) -> Vec<String> {
     entries
 }
 
+// The prompt can be customized with the `PS1` variable using `%` escapes
+const DEFAULT_PROMPT: &str = "%{Blue}%w%{}\n%c>%{} ";
+
 pub fn prompt_string(success: bool) -> String {
-    let csi_line1 = Style::color("Blue");
-    let csi_line2 = Style::color("Magenta");
-    let csi_error = Style::color("Red");
-    let csi_reset = Style::reset();
+    let code = if success { ExitCode::Success } else { ExitCode::Failure };
+    expand_prompt(DEFAULT_PROMPT, code)
+}
 
-    let mut current_dir = sys::process::dir();
-    if let Some(home) = sys::process::env("HOME") {
-        if current_dir.starts_with(&home) {
-            let n = home.len();
-            current_dir.replace_range(..n, "~");
+fn expand_prompt(prompt: &str, code: ExitCode) -> String {
+    let mut res = String::new();
+    let mut chars = prompt.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            res.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('w') => { // Current directory
+                let mut dir = sys::process::dir();
+                if let Some(home) = sys::process::env("HOME") {
+                    if dir.starts_with(&home) {
+                        dir.replace_range(..home.len(), "~");
+                    }
+                }
+                res.push_str(&dir);
+            }
+            Some('u') => { // Current user
+                res.push_str(&sys::process::user().unwrap_or_default());
+            }
+            Some('h') => { // Hostname
+                let host = sys::process::env("HOSTNAME");
+                res.push_str(&host.unwrap_or("moros".to_string()));
+            }
+            Some('t') => { // Current time
+                res.push_str(&api::time::now().format("%H:%M:%S"));
+            }
+            Some('?') => { // Exit code of the last command
+                res.push_str(&format!("{}", code as u8));
+            }
+            Some('c') => { // Color of the exit code of the last command
+                let name = match code {
+                    ExitCode::Success => "Magenta",
+                    _ => "Red",
+                };
+                res.push_str(&format!("{}", Style::color(name)));
+            }
+            Some('{') => { // Color name or reset
+                let name: String = chars.by_ref().take_while(|c| {
+                    *c != '}'
+                }).collect();
+                if name.is_empty() {
+                    res.push_str(&format!("{}", Style::reset()));
+                } else {
+                    res.push_str(&format!("{}", Style::color(&name)));
+                }
+            }
+            Some(c) => res.push(c),
+            None => res.push('%'),
         }
     }
-    let line1 = format!("{}{}{}", csi_line1, current_dir, csi_reset);
-    let line2 = format!(
-        "{}>{} ",
-        if success { csi_line2 } else { csi_error },
-        csi_reset
-    );
-    format!("{}\n{}", line1, line2)
+    res
 }
 
 fn is_globbing(arg: &str) -> bool {
@@ -132,24 +187,62 @@ fn is_globbing(arg: &str) -> bool {
     if arg[0] == '\'' && arg[n - 1] == '\'' {
         return false;
     }
-    for i in 0..n {
-        if arg[i] == '*' || arg[i] == '?' {
+    let mut is_escaped = false;
+    for c in arg.iter() {
+        if !is_escaped && (*c == '*' || *c == '?' || *c == '[') {
             return true;
         }
+        is_escaped = *c == '\\' && !is_escaped;
     }
     false
 }
 
-fn glob_to_regex(pattern: &str) -> String {
-    format!(
-        "^{}$",
-        pattern.replace('\\', "\\\\") // `\` string literal
-               .replace('.', "\\.") // `.` string literal
-               .replace('*', ".*") // `*` match zero or more chars except `/`
-               .replace('?', ".") // `?` match any char except `/`
-    )
+pub fn glob_to_regex(pattern: &str) -> String {
+    let chars: Vec<char> = pattern.chars().collect();
+    let n = chars.len();
+    let mut res = String::from("^");
+    let mut i = 0;
+    while i < n {
+        match chars[i] {
+            '*' => res.push_str(".*"), // match zero or more chars
+            '?' => res.push('.'), // match any char
+            '[' => {
+                // `[abc]`, `[a-z]`, and `[!a-z]` match a char of a set
+                let negated = matches!(chars.get(i + 1), Some('!' | '^'));
+                let start = if negated { i + 2 } else { i + 1 };
+                match (start + 1..n).find(|&j| chars[j] == ']') {
+                    Some(end) => {
+                        res.push('[');
+                        if negated {
+                            res.push('^');
+                        }
+                        for &c in &chars[start..end] {
+                            if c == '\\' {
+                                res.push('\\');
+                            }
+                            res.push(c);
+                        }
+                        res.push(']');
+                        i = end;
+                    }
+                    None => res.push_str("\\["), // unclosed `[` literal
+                }
+            }
+            c @ ('\\' | '.' | '+' | '^' | '$' | ']') => { // string literals
+                res.push('\\');
+                res.push(c);
+            }
+            c => res.push(c),
+        }
+        i += 1;
+    }
+    res.push('$');
+    res
 }
 
+// Expand the pattern to the sorted list of matching paths, or to the pattern
+// itself when nothing matches. Hidden files are only matched by a pattern
+// starting with a dot.
 fn glob(arg: &str) -> Vec<String> {
     let mut matches = Vec::new();
     if is_globbing(arg) {
@@ -165,6 +258,9 @@ fn glob(arg: &str) -> Vec<String> {
         if let Ok(files) = fs::read_dir(&dir) {
             for file in files {
                 let name = file.name();
+                if name.starts_with('.') && !pattern.starts_with('.') {
+                    continue;
+                }
                 if re.is_match(&name) {
                     if show_dir {
                         matches.push(format!("{}{}{}", dir, sep, name));
@@ -174,7 +270,9 @@ fn glob(arg: &str) -> Vec<String> {
                 }
             }
         }
-    } else {
+        matches.sort();
+    }
+    if matches.is_empty() {
         matches.push(arg.to_string());
     }
     matches
@@ -205,55 +303,113 @@ pub fn parse_str(s: &str) -> String {
 }
 
 pub fn split_args(cmd: &str) -> Vec<String> {
-    let mut args = Vec::new();
-    let mut i = 0;
-    let mut n = cmd.len();
-    let mut is_quote = false;
-    let mut is_escaped = false;
+    split_words(cmd, true)
+}
 
-    for (j, c) in cmd.char_indices() {
-        if c == '#' && !is_quote {
-            n = j; // Discard comments
-            break;
-        } else if c == ' ' && !is_quote {
-            if i != j && !cmd[i..j].trim().is_empty() {
-                if args.is_empty() {
-                    args.push(cmd[i..j].to_string()) // program name
-                } else {
-                    args.extend(glob(&cmd[i..j])) // program args
+// Split a command into words separated by spaces outside of quotes. Single
+// quotes keep every char of a word, double quotes keep every char except for
+// escape sequences, and a backslash keeps the next char outside of quotes.
+// The wildcards outside of quotes are expanded, except in the command name.
+fn split_words(cmd: &str, is_command: bool) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut word = String::new();
+    let mut is_word = false;
+    let mut is_glob = false;
+    let mut is_tilde = false;
+    let mut quote = None;
+    let mut chars = cmd.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some('\''), '\'') | (Some('"'), '"') => {
+                quote = None;
+            }
+            (Some('"'), '\\') => {
+                let esc = match chars.peek() {
+                    Some('\\') => Some('\\'),
+                    Some('"') => Some('"'),
+                    Some('$') => Some('$'),
+                    Some('`') => Some('`'),
+                    Some('n') => Some('\n'),
+                    Some('r') => Some('\r'),
+                    Some('t') => Some('\t'),
+                    Some('b') => Some('\x08'),
+                    Some('e') => Some('\x1B'),
+                    _ => None,
+                };
+                match esc {
+                    Some(esc) => {
+                        word.push(esc);
+                        chars.next();
+                    }
+                    None => word.push(c),
                 }
             }
-            i = j + 1;
-        } else if c == '"' && !is_escaped {
-            is_quote = !is_quote;
-            if !is_quote {
-                args.push(parse_str(&cmd[i..j]));
+            (Some(_), c) => {
+                word.push(c);
+            }
+            (None, ' ' | '\t') => {
+                if is_word {
+                    let is_glob = is_glob && !(is_command && args.is_empty());
+                    push_word(&mut args, &word, is_glob, is_tilde);
+                }
+                word.clear();
+                is_word = false;
+                is_glob = false;
+                is_tilde = false;
+            }
+            (None, '#') if !is_word => {
+                break; // Discard comments
+            }
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                is_word = true;
+            }
+            (None, '\\') => {
+                if let Some(c) = chars.next() {
+                    word.push(c);
+                }
+                is_word = true;
+            }
+            (None, c) => {
+                if c == '*' || c == '?' || c == '[' {
+                    is_glob = true;
+                }
+                if c == '~' && !is_word {
+                    is_tilde = true;
+                }
+                word.push(c);
+                is_word = true;
             }
-            i = j + 1;
-        }
-        if c == '\\' && !is_escaped {
-            is_escaped = true;
-        } else {
-            is_escaped = false;
         }
     }
-
-    if i < n {
-        if is_quote {
-            n -= 1;
-            args.push(cmd[i..n].to_string());
-        } else if args.is_empty() {
-            args.push(cmd[i..n].to_string());
-        } else if !cmd[i..n].trim().is_empty() {
-            args.extend(glob(&cmd[i..n]))
-        }
+    if is_word {
+        let is_glob = is_glob && !(is_command && args.is_empty());
+        push_word(&mut args, &word, is_glob, is_tilde);
     }
 
-    if n == 0 {
+    if args.is_empty() {
         args.push("".to_string());
     }
 
-    args.iter().map(|s| tilde_expansion(s)).collect()
+    args
+}
+
+fn push_word(
+    args: &mut Vec<String>,
+    word: &str,
+    is_glob: bool,
+    is_tilde: bool
+) {
+    let word = if is_tilde {
+        tilde_expansion(word)
+    } else {
+        word.to_string()
+    };
+    if is_glob {
+        args.extend(glob(&word));
+    } else {
+        args.push(word);
+    }
 }
 
 // Replace `~` with the value of `$HOME` when it's at the begining of an arg
@@ -267,23 +423,154 @@ fn tilde_expansion(arg: &str) -> String {
     arg.to_string()
 }
 
-fn variables_expansion(cmd: &str, config: &mut Config) -> String {
-    let mut cmd = cmd.to_string();
+// Track the quotes of a command to find the chars that can be expanded
+#[derive(Default)]
+struct Quotes {
+    quote: Option<char>,
+    is_escaped: bool,
+}
 
-    // Special cases for none alphanum (\w) variables
-    cmd = cmd.replace("$?", "$status");
-    cmd = cmd.replace("$*", "$1 $2 $3 $4 $5 $6 $7 $8 $9");
+impl Quotes {
+    // Update the state with the next char and return true if it is neither
+    // escaped nor inside single quotes.
+    fn is_expandable(&mut self, c: char) -> bool {
+        if self.is_escaped {
+            self.is_escaped = false;
+            return false;
+        }
+        match (self.quote, c) {
+            (Some('\''), '\'') | (Some('"'), '"') => {
+                self.quote = None;
+                false
+            }
+            (Some('\''), _) => false,
+            (_, '\\') => {
+                self.is_escaped = true;
+                false
+            }
+            (None, '\'' | '"') => {
+                self.quote = Some(c);
+                false
+            }
+            _ => true,
+        }
+    }
+}
 
-    // Replace alphanum `$key` with its value in the environment
-    // or an empty string.
-    let re = Regex::new("\\$\\w+");
-    while let Some((a, b)) = re.find(&cmd) {
-        let key: String = cmd.chars().skip(a + 1).take(b - a - 1).collect();
-        let val = config.env.get(&key).map_or("", String::as_str);
-        cmd = cmd.replace(&format!("${}", key), val);
+fn variables_expansion(cmd: &str, config: &mut Config) -> String {
+    let chars: Vec<char> = cmd.chars().collect();
+    let n = chars.len();
+    let mut res = String::new();
+    let mut quotes = Quotes::default();
+    let mut i = 0;
+    while i < n {
+        let c = chars[i];
+        if !quotes.is_expandable(c) || c != '$' || i + 1 == n {
+            res.push(c);
+            i += 1;
+            continue;
+        }
+        let j = (i + 1..n).find(|&j| {
+            !chars[j].is_alphanumeric() && chars[j] != '_'
+        }).unwrap_or(n);
+        let val = match chars[i + 1] {
+            // Special cases for none alphanum variables
+            '?' => {
+                i += 2;
+                config.env.get("status").cloned().unwrap_or_default()
+            }
+            '*' => {
+                i += 2;
+                let params: Vec<&str> = (1..).map_while(|k: usize| {
+                    config.env.get(&k.to_string()).map(String::as_str)
+                }).collect();
+                params.join(" ")
+            }
+            // Replace alphanum `$key` with its value in the environment
+            // or an empty string.
+            _ if j > i + 1 => {
+                let key: String = chars[i + 1..j].iter().collect();
+                i = j;
+                config.env.get(&key).cloned().unwrap_or_default()
+            }
+            _ => {
+                res.push(c);
+                i += 1;
+                continue;
+            }
+        };
+        res.push_str(&val);
     }
+    res
+}
 
-    cmd
+// Replace `$(cmd)` and `` `cmd` `` with the output of the command
+fn command_substitution(cmd: &str, config: &mut Config) -> String {
+    let chars: Vec<char> = cmd.chars().collect();
+    let n = chars.len();
+    let mut res = String::new();
+    let mut quotes = Quotes::default();
+    let mut i = 0;
+    while i < n {
+        if !quotes.is_expandable(chars[i]) {
+            res.push(chars[i]);
+            i += 1;
+            continue;
+        }
+        let is_dollar = chars[i] == '$' && chars.get(i + 1) == Some(&'(');
+        let (start, end) = if is_dollar {
+            let mut depth = 0;
+            let end = (i + 1..n).find(|&j| {
+                match chars[j] {
+                    '(' => depth += 1,
+                    ')' => depth -= 1,
+                    _ => {}
+                }
+                depth == 0
+            });
+            (i + 2, end)
+        } else if chars[i] == '`' {
+            (i + 1, (i + 1..n).find(|&j| chars[j] == '`'))
+        } else {
+            res.push(chars[i]);
+            i += 1;
+            continue;
+        };
+        match end {
+            Some(end) => {
+                let sub: String = chars[start..end].iter().collect();
+                let output = capture_output(&sub, config);
+                let output = output.trim_end_matches('\n');
+                res.push_str(&output.replace('\n', " "));
+                i = end + 1;
+            }
+            None => {
+                res.extend(&chars[i..]);
+                break;
+            }
+        }
+    }
+    res
+}
+
+fn capture_output(cmd: &str, config: &mut Config) -> String {
+    let mut res = String::new();
+    if let Some(pipe) = syscall::pipe() {
+        let mut saved = Vec::new();
+        save_handle(1, &mut saved);
+        syscall::dup(pipe, 1);
+        exec_script(cmd, config).ok();
+        restore_handles(saved);
+        let mut buf = [0; 256];
+        while let Some(n) = syscall::read(pipe, &mut buf) {
+            if n == 0 {
+                break;
+            }
+            res.push_str(&String::from_utf8_lossy(&buf[..n]));
+        }
+        syscall::close(pipe);
+    }
+    res
 }
 
 fn cmd_change_dir(args: &[&str], config: &mut Config) -> Result<(), ExitCode> {
@@ -310,18 +597,34 @@ fn cmd_change_dir(args: &[&str], config: &mut Config) -> Result<(), ExitCode> {
     }
 }
 
+// An alias can be defined with `alias ll "list -l"` or `alias ll='list -l'`
 fn cmd_alias(args: &[&str], config: &mut Config) -> Result<(), ExitCode> {
-    if args.len() != 3 {
-        let csi_option = Style::color("LightCyan");
-        let csi_title = Style::color("Yellow");
-        let csi_reset = Style::reset();
-        eprintln!(
-            "{}Usage:{} alias {}<key> <val>{1}",
-            csi_title, csi_reset, csi_option
-        );
-        return Err(ExitCode::UsageError);
+    if args.len() == 1 {
+        for (key, val) in &config.aliases {
+            println!("alias {} \"{}\"", key, val);
+        }
+        return Ok(());
     }
-    config.aliases.insert(args[1].to_string(), args[2].to_string());
+    if args.len() == 2 && !args[1].contains('=') {
+        return match config.aliases.get(args[1]) {
+            Some(val) => {
+                println!("alias {} \"{}\"", args[1], val);
+                Ok(())
+            }
+            None => {
+                error!("Could not find alias '{}'", args[1]);
+                Err(ExitCode::Failure)
+            }
+        };
+    }
+
+    let def = args[1..].join(" ");
+    let (key, val) = match def.split_once('=') {
+        Some((key, val)) if !key.contains(' ') => (key, val.trim()),
+        _ => (args[1], def[args[1].len()..].trim()),
+    };
+    let val = val.trim_matches('\'');
+    config.aliases.insert(key.to_string(), val.to_string());
     Ok(())
 }
 
@@ -377,6 +680,36 @@ fn cmd_unset(args: &[&str], config: &mut Config) -> Result<(), ExitCode> {
         error!("Could not unset '{}'", args[1]);
         return Err(ExitCode::Failure);
     }
+    sys::process::delete_env(args[1]);
+
+    Ok(())
+}
+
+// Exported variables are copied from the shell environment to the process
+// environment that is inherited by spawned processes.
+fn cmd_export(args: &[&str], config: &mut Config) -> Result<(), ExitCode> {
+    if args.len() == 1 {
+        for (key, val) in sys::process::envs() {
+            println!("{}={}", key, val);
+        }
+        return Ok(());
+    }
+
+    for arg in &args[1..] {
+        let (key, val) = match arg.split_once('=') {
+            Some((key, val)) => (key, val),
+            None => match config.env.get(*arg) {
+                Some(val) => (*arg, val.as_str()),
+                None => {
+                    error!("Could not export '{}'", arg);
+                    return Err(ExitCode::Failure);
+                }
+            },
+        };
+        let val = val.to_string();
+        sys::process::set_env(key, &val);
+        config.env.insert(key.to_string(), val);
+    }
 
     Ok(())
 }
@@ -395,24 +728,203 @@ fn cmd_version() -> Result<(), ExitCode> {
 }
 
 fn exec_with_config(cmd: &str, config: &mut Config) -> Result<(), ExitCode> {
-    let cmd = variables_expansion(cmd, config);
+    let (cmd, groups) = extract_groups(cmd);
+    let cmd = variables_expansion(&cmd, config);
+    let cmd = command_substitution(&cmd, config);
     let mut args = split_args(cmd.trim());
     if args.is_empty() {
         return Ok(());
     }
 
-    // Replace command alias
-    if let Some(alias) = config.aliases.get(&args[0]) {
-        args.remove(0);
-        for arg in alias.split(' ').rev() {
-            args.insert(0, arg.to_string())
+    // Groups
+    // { print foo; print bar; } => foo.txt
+    // (goto /tmp; list) | sort
+    for arg in args.iter_mut() {
+        let i = arg.strip_prefix(GROUP_PLACEHOLDER).and_then(|s| {
+            s.strip_suffix(GROUP_PLACEHOLDER)
+        }).and_then(|s| s.parse::<usize>().ok());
+        if let Some(group) = i.and_then(|i| groups.get(i)) {
+            *arg = group.clone();
+        }
+    }
+
+    // Replace command alias, and the alias of its replacement if it is not
+    // one of the previous aliases
+    let mut aliases = Vec::new();
+    while let Some(alias) = config.aliases.get(&args[0]) {
+        if aliases.contains(&args[0]) {
+            break;
+        }
+        aliases.push(args.remove(0));
+        args.splice(0..0, split_args(alias));
+        if args.is_empty() {
+            return Ok(());
         }
     }
 
-    let mut args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
 
+    // Variables assignment
+    // foo=42 bar="Alice and Bob"
+    let re = Regex::new("^[a-zA-Z_]\\w*=");
+    if args.iter().all(|arg| re.is_match(arg)) {
+        for arg in args {
+            if let Some((key, val)) = arg.split_once('=') {
+                config.env.insert(key.to_string(), val.to_string());
+            }
+        }
+        return Ok(());
+    }
+
+    // Background execution
+    // httpd &
+    if args.last() == Some(&"&") {
+        error!("Background execution has not been implemented yet");
+        return Err(ExitCode::Failure);
+    }
+
+    // Pipes
+    // read foo.txt | write bar.txt
+    // read foo.txt -> write bar.txt
+    // read foo.txt [2]-> write /dev/null
+    let mut stages = Vec::new();
+    let mut start = 0;
+    for (i, arg) in args.iter().enumerate() {
+        if let Some(handle) = pipe_handle(arg) {
+            stages.push((&args[start..i], handle));
+            start = i + 1;
+        }
+    }
+    if stages.is_empty() {
+        return exec_stage(args, config);
+    }
+    stages.push((&args[start..], 1));
+    if stages.iter().any(|(stage, _)| stage.is_empty()) {
+        error!("Could not parse pipe");
+        return Err(ExitCode::Failure);
+    }
+
+    // The stages are run one after the other, each of them writing its
+    // output into a pipe that will become the input of the next one.
+    let n = stages.len();
+    let mut input = None;
+    let mut res = Ok(());
+    for (i, (stage, handle)) in stages.into_iter().enumerate() {
+        let output = if i < n - 1 {
+            match syscall::pipe() {
+                Some(pipe) => Some(pipe),
+                None => {
+                    error!("Could not create pipe");
+                    res = Err(ExitCode::Failure);
+                    break;
+                }
+            }
+        } else {
+            None
+        };
+        let mut saved = Vec::new();
+        if let Some(pipe) = input {
+            save_handle(0, &mut saved);
+            syscall::dup(pipe, 0);
+        }
+        if let Some(pipe) = output {
+            save_handle(handle, &mut saved);
+            syscall::dup(pipe, handle);
+        }
+        res = exec_stage(stage.to_vec(), config);
+        restore_handles(saved);
+        if let Some(pipe) = input {
+            syscall::close(pipe);
+        }
+        input = output;
+    }
+    if let Some(pipe) = input {
+        syscall::close(pipe);
+    }
+    res
+}
+
+const GROUP_PLACEHOLDER: char = '\u{E000}';
+
+// Replace the groups of commands starting the stages of a pipeline by
+// placeholders to keep them unexpanded in a single arg until their execution.
+fn extract_groups(cmd: &str) -> (String, Vec<String>) {
+    let mut res = String::new();
+    let mut groups = Vec::new();
+    for (op, part) in split_operators(cmd, &["|", "->"]) {
+        res.push_str(op);
+        let stage = part.trim_start();
+        match group_end(stage) {
+            Some(end) => {
+                res.push_str(&part[..part.len() - stage.len()]);
+                res.push(GROUP_PLACEHOLDER);
+                res.push_str(&groups.len().to_string());
+                res.push(GROUP_PLACEHOLDER);
+                res.push_str(&stage[end + 1..]);
+                groups.push(stage[..end + 1].to_string());
+            }
+            None => res.push_str(part),
+        }
+    }
+    (res, groups)
+}
+
+fn is_group(arg: &str) -> bool {
+    group_end(arg).map_or(false, |end| end + 1 == arg.len())
+}
+
+// A group of commands between braces is executed in the current shell, while
+// a subshell between parentheses is executed with a copy of the shell and
+// process environments and of the current directory, which are restored
+// after its execution.
+fn exec_group(group: &str, config: &mut Config) -> Result<(), ExitCode> {
+    let body = &group[1..group.len() - 1];
+    let statements = match parse_script(body) {
+        Ok(statements) => statements,
+        Err(msg) => {
+            error!("{}", msg);
+            return Err(ExitCode::Failure);
+        }
+    };
+    if group.starts_with('{') {
+        return exec_statements(&statements, config);
+    }
+
+    let dir = sys::process::dir();
+    let envs = sys::process::envs();
+    let mut subshell = config.clone();
+    let res = exec_statements(&statements, &mut subshell);
+    sys::process::set_dir(&dir);
+    for key in sys::process::envs().keys() {
+        sys::process::delete_env(key);
+    }
+    for (key, val) in envs {
+        sys::process::set_env(&key, &val);
+    }
+    match res {
+        Err(ExitCode::ShellExit) => Ok(()),
+        res => res,
+    }
+}
+
+// Return the handle piped by the argument if it is a pipe
+fn pipe_handle(arg: &str) -> Option<usize> {
+    if arg == "|" {
+        Some(1)
+    } else if Regex::new("^\\[?\\d*\\]?-+>$").is_match(arg) {
+        let num: String = arg.chars().filter(char::is_ascii_digit).collect();
+        Some(num.parse().unwrap_or(1))
+    } else {
+        None
+    }
+}
+
+fn exec_stage(
+    mut args: Vec<&str>,
+    config: &mut Config
+) -> Result<(), ExitCode> {
     // Redirections
-    let mut restore_handles = false;
+    let mut saved = Vec::new();
     let mut n = args.len();
     let mut i = 0;
     loop {
@@ -420,33 +932,52 @@ fn exec_with_config(cmd: &str, config: &mut Config) -> Result<(), ExitCode> {
             break;
         }
 
-        let mut is_fat_arrow = false;
-        let mut is_thin_arrow = false;
+        // Here-strings
+        // lisp <<< "(+ 1 2)"
+        if let Some(text) = args[i].strip_prefix("<<<") {
+            let (text, count) = if text.is_empty() && i + 1 < n {
+                (args[i + 1], 2)
+            } else {
+                (text, 1)
+            };
+            match syscall::pipe() {
+                Some(pipe) => {
+                    syscall::write(pipe, format!("{}\n", text).as_bytes());
+                    save_handle(0, &mut saved);
+                    syscall::dup(pipe, 0);
+                    syscall::close(pipe);
+                }
+                None => {
+                    error!("Could not create pipe");
+                    restore_handles(saved);
+                    return Err(ExitCode::Failure);
+                }
+            }
+            args.drain(i..i + count);
+            n -= count;
+            continue;
+        }
+
         let mut head_count = 0;
         let mut left_handle;
-        if Regex::new("^[?\\d*]?-+>$").is_match(args[i]) {
-            // Pipes
-            // read foo.txt --> write bar.txt
-            // read foo.txt -> write bar.txt
-            // read foo.txt [2]-> write /dev/null
-            is_thin_arrow = true;
-            left_handle = 1;
-        } else if Regex::new("^[?\\d*]?=*>+[?\\d*]?$").is_match(args[i]) {
+        let is_output;
+        if Regex::new("^\\[?\\d*\\]?=*>+&?\\[?\\d*\\]?$").is_match(args[i]) {
             // Redirections to
             // read foo.txt ==> bar.txt
             // read foo.txt => bar.txt
             // read foo.txt > bar.txt
             // read foo.txt [1]=> /dev/null
             // read foo.txt [1]=>[3]
-            is_fat_arrow = true;
+            // read foo.txt 2>&1
             left_handle = 1;
+            is_output = true;
         } else if Regex::new("^<=*$").is_match(args[i]) {
             // Redirections from
             // write bar.txt <== foo.txt
             // write bar.txt <= foo.txt
             // write bar.txt < foo.txt
-            is_fat_arrow = true;
             left_handle = 0;
+            is_output = false;
         } else {
             i += 1;
             continue;
@@ -456,7 +987,7 @@ fn exec_with_config(cmd: &str, config: &mut Config) -> Result<(), ExitCode> {
         let mut num = String::new();
         for c in args[i].chars() {
             match c {
-                '[' | ']' | '-' | '=' => {
+                '[' | ']' | '-' | '=' | '&' => {
                     continue;
                 }
                 '<' | '>' => {
@@ -472,96 +1003,173 @@ fn exec_with_config(cmd: &str, config: &mut Config) -> Result<(), ExitCode> {
             }
         }
 
-        if is_fat_arrow {
-            // Redirections
-            restore_handles = true;
-            if !num.is_empty() {
-                // if let Ok(right_handle) = num.parse() {}
-                error!("Redirecting to a handle has not been implemented yet");
+        save_handle(left_handle, &mut saved);
+        if !num.is_empty() {
+            let res = num.parse().ok().and_then(|right_handle| {
+                syscall::dup(right_handle, left_handle)
+            });
+            if res.is_none() {
+                error!("Could not redirect to handle {}", num);
+                restore_handles(saved);
                 return Err(ExitCode::Failure);
-            } else {
-                if i == n - 1 {
-                    error!("Could not parse path for redirection");
-                    return Err(ExitCode::Failure);
-                }
-                let path = args[i + 1];
-                let append_mode = head_count > 1;
-                if api::fs::reopen(path, left_handle, append_mode).is_err() {
-                    error!("Could not open path for redirection");
-                    return Err(ExitCode::Failure);
-                }
-                args.remove(i); // Remove path from args
-                n -= 1;
             }
-            n -= 1;
-            args.remove(i); // Remove redirection from args
-        } else if is_thin_arrow {
-            error!("Piping has not been implemented yet");
+        } else if args[i].ends_with('&') {
+            error!("Could not parse handle for redirection");
+            restore_handles(saved);
             return Err(ExitCode::Failure);
+        } else {
+            if i == n - 1 {
+                error!("Could not parse path for redirection");
+                restore_handles(saved);
+                return Err(ExitCode::Failure);
+            }
+            let path = args[i + 1];
+            let mut append_mode = head_count > 1;
+
+            // A file is truncated before being opened in append mode to
+            // keep the output of redirections merged into it like `2>&1`
+            if is_output && !append_mode && !api::fs::is_device(path) {
+                api::fs::write(path, b"").ok();
+                append_mode = true;
+            }
+            if api::fs::reopen(path, left_handle, append_mode).is_err() {
+                error!("Could not open path for redirection");
+                restore_handles(saved);
+                return Err(ExitCode::Failure);
+            }
+            args.remove(i); // Remove path from args
+            n -= 1;
         }
+        n -= 1;
+        args.remove(i); // Remove redirection from args
     }
 
     fence(Ordering::SeqCst);
     let res = dispatch(&args, config);
 
     // TODO: Remove this when redirections are done in spawned process
-    if restore_handles {
-        for i in 0..3 {
-            api::fs::reopen("/dev/console", i, false).ok();
-        }
-    }
+    restore_handles(saved);
 
     res
 }
 
+// The handles replaced by a redirection or a pipe are saved to be restored
+// after the command, instead of being reopened on the console, so that the
+// redirections of a command substitution or a pipeline can be nested.
+type SavedHandles = Vec<(usize, Option<Box<Resource>>)>;
+
+fn save_handle(handle: usize, saved: &mut SavedHandles) {
+    if saved.iter().all(|(h, _)| *h != handle) {
+        saved.push((handle, sys::process::handle(handle)));
+    }
+}
+
+fn restore_handles(saved: SavedHandles) {
+    for (handle, resource) in saved.into_iter().rev() {
+        match resource {
+            Some(resource) => sys::process::update_handle(handle, *resource),
+            None => sys::process::delete_handle(handle),
+        }
+    }
+}
+
 fn dispatch(args: &[&str], config: &mut Config) -> Result<(), ExitCode> {
+    if is_group(args[0]) {
+        return exec_group(args[0], config);
+    }
+    if let Some(body) = config.functions.get(args[0]).cloned() {
+        return call_function(&body, args, config);
+    }
     match args[0] {
         ""         => Ok(()),
         "2048"     => usr::pow::main(args),
         "alias"    => cmd_alias(args, config),
+        "arp"      => usr::arp::main(args),
         "base64"   => usr::base64::main(args),
         "beep"     => usr::beep::main(args),
+        "browse"   => usr::browse::main(args),
         "calc"     => usr::calc::main(args),
         "chess"    => usr::chess::main(args),
+        "chgrp"    => usr::chgrp::main(args),
+        "chmod"    => usr::chmod::main(args),
+        "chown"    => usr::chown::main(args),
         "copy"     => usr::copy::main(args),
+        "cut"      => usr::cut::main(args),
         "date"     => usr::date::main(args),
+        "dd"       => usr::dd::main(args),
         "delete"   => usr::delete::main(args),
+        "df"       => usr::df::main(args),
         "dhcp"     => usr::dhcp::main(args),
+        "diff"     => usr::diff::main(args),
         "disk"     => usr::disk::main(args),
+        "du"       => usr::du::main(args),
         "edit"     => usr::editor::main(args),
         "elf"      => usr::elf::main(args),
         "env"      => usr::env::main(args),
+        "export"   => cmd_export(args, config),
+        "file"     => usr::file::main(args),
         "find"     => usr::find::main(args),
+        "fsck"     => usr::fsck::main(args),
+        "ftp"      => usr::ftp::main(args),
+        "fw"       => usr::fw::main(args),
         "goto"     => cmd_change_dir(args, config), // TODO: Remove this
+        "grep"     => usr::grep::main(args),
+        "gunzip"   => usr::gzip::main(args),
+        "gzip"     => usr::gzip::main(args),
         "hash"     => usr::hash::main(args),
+        "head"     => usr::head::main(args),
         "help"     => usr::help::main(args),
         "hex"      => usr::hex::main(args),
         "host"     => usr::host::main(args),
         "http"     => usr::http::main(args),
         "httpd"    => usr::httpd::main(args),
         "install"  => usr::install::main(args),
+        "irc"      => usr::irc::main(args),
         "keyboard" => usr::keyboard::main(args),
+        "less"     => usr::less::main(args),
         "life"     => usr::life::main(args),
         "lisp"     => usr::lisp::main(args),
         "list"     => usr::list::main(args),
+        "ln"       => usr::ln::main(args),
         "logs"     => cmd_logs(),
+        "mail"     => usr::mail::main(args),
         "memory"   => usr::memory::main(args),
+        "mount"    => usr::mount::main(args),
         "move"     => usr::r#move::main(args),
+        "nc"       => usr::nc::main(args),
         "net"      => usr::net::main(args),
+        "netstat"  => usr::netstat::main(args),
+        "ntp"      => usr::ntp::main(args),
         "pci"      => usr::pci::main(args),
         "pi"       => usr::pi::main(args),
+        "ping"     => usr::ping::main(args),
+        "printf"   => usr::printf::main(args),
         "quit"     => Err(ExitCode::ShellExit),
         "read"     => usr::read::main(args),
+        "return"   => cmd_return(args, config),
+        "seq"      => usr::seq::main(args),
         "set"      => cmd_set(args, config),
         "shell"    => usr::shell::main(args),
         "socket"   => usr::socket::main(args),
+        "sort"     => usr::sort::main(args),
+        "stat"     => usr::stat::main(args),
+        "sync"     => usr::sync::main(args),
+        "tail"     => usr::tail::main(args),
         "tcp"      => usr::tcp::main(args),
+        "tcpdump"  => usr::tcpdump::main(args),
+        "tee"      => usr::tee::main(args),
         "time"     => usr::time::main(args),
+        "touch"    => usr::touch::main(args),
+        "tr"       => usr::tr::main(args),
+        "umount"   => usr::umount::main(args),
         "unalias"  => cmd_unalias(args, config),
+        "uniq"     => usr::uniq::main(args),
         "unset"    => cmd_unset(args, config),
         "version"  => cmd_version(),
         "user"     => usr::user::main(args),
         "vga"      => usr::vga::main(args),
+        "watch"    => usr::watch::main(args),
+        "wc"       => usr::wc::main(args),
         "write"    => usr::write::main(args),
         "panic"    => panic!("{}", args[1..].join(" ")),
         _ => {
@@ -595,6 +1203,10 @@ fn spawn(
     // Script
     if let Ok(contents) = fs::read_to_string(path) {
         if contents.starts_with("#!") {
+            if !fs::is_executable(path) {
+                error!("Could not execute '{}'", args[0]);
+                return Err(ExitCode::ExecError);
+            }
             if let Some(line) = contents.lines().next() {
                 let mut new_args = Vec::with_capacity(args.len() + 1);
                 new_args.push(line[2..].trim());
@@ -623,6 +1235,530 @@ fn spawn(
     }
 }
 
+// Control flow statements of a script
+#[derive(Clone)]
+enum Statement {
+    Command(String),
+    Function(String, Vec<Statement>),
+    If(Vec<(String, Vec<Statement>)>, Vec<Statement>),
+    While(String, Vec<Statement>),
+    For(String, String, Vec<Statement>),
+}
+
+fn parse_script(script: &str) -> Result<Vec<Statement>, String> {
+    let mut lines = Vec::new();
+    let script = join_groups(heredocs_expansion(script))?;
+    let commands = script.iter().flat_map(|line| {
+        split_operators(line, &[";"]).into_iter().map(|(_, cmd)| cmd)
+    });
+    for line in commands {
+        let mut line = line.trim();
+        // Split `then`, `do`, and `else` from the command following them
+        loop {
+            let (word, rest) = split_keyword(line);
+            if rest.is_empty() || !["then", "do", "else"].contains(&word) {
+                break;
+            }
+            lines.push(word);
+            line = rest;
+        }
+        // Split `name() {` from the command following it
+        if function_name(line).is_some() {
+            if let Some(i) = line.find('{') {
+                let rest = line[i + 1..].trim();
+                if !rest.is_empty() {
+                    lines.push(&line[..i + 1]);
+                    line = rest;
+                }
+            }
+        }
+        if !line.is_empty() && !line.starts_with('#') {
+            lines.push(line);
+        }
+    }
+    let mut i = 0;
+    parse_block(&lines, &mut i, &[])
+}
+
+// Replace the heredoc of a line and the lines following it until its
+// delimiter by a here-string. The variables of the heredoc are expanded
+// unless its delimiter is quoted.
+fn heredocs_expansion(script: &str) -> Vec<String> {
+    let mut res = Vec::new();
+    let mut lines = script.lines();
+    while let Some(line) = lines.next() {
+        let (start, end, delim) = match find_heredoc(line) {
+            Some(heredoc) => heredoc,
+            None => {
+                res.push(line.to_string());
+                continue;
+            }
+        };
+        let is_quoted = delim.starts_with(['\'', '"']);
+        let delim = delim.trim_matches(['\'', '"']);
+        let body: Vec<&str> = lines.by_ref().take_while(|line| {
+            line.trim() != delim
+        }).collect();
+        let body = body.join("\n");
+        let body = if is_quoted {
+            format!("'{}'", body.replace('\'', "'\\''"))
+        } else {
+            format!("\"{}\"", body.replace('\\', "\\\\").replace('"', "\\\""))
+        };
+        res.push(format!("{}<<< {}{}", &line[..start], body, &line[end..]));
+    }
+    res
+}
+
+// Join the lines of a group of commands opened on a line and closed on another
+// one, without their comments, into a single line.
+fn join_groups(lines: Vec<String>) -> Result<Vec<String>, String> {
+    let mut res = Vec::new();
+    let mut group = String::new();
+    for line in lines {
+        if group.is_empty() {
+            match scan_operators(&line, &[]) {
+                (parts, Some(_)) => group = parts[0].1.trim_end().to_string(),
+                (_, None) => res.push(line),
+            }
+            continue;
+        }
+        let line = split_operators(&line, &[])[0].1.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if !group.ends_with(['(', '{']) {
+            group.push(';');
+        }
+        group.push(' ');
+        group.push_str(line);
+        if scan_operators(&group, &[]).1.is_none() {
+            res.push(core::mem::take(&mut group));
+        }
+    }
+    match scan_operators(&group, &[]).1 {
+        Some(c) => Err(format!("Missing '{}'", c)),
+        None => Ok(res),
+    }
+}
+
+// Return the position of the first heredoc of a line with its delimiter
+fn find_heredoc(line: &str) -> Option<(usize, usize, &str)> {
+    if line.trim_start().starts_with('#') {
+        return None;
+    }
+    let mut quotes = Quotes::default();
+    for (i, c) in line.char_indices() {
+        if !quotes.is_expandable(c) || quotes.quote.is_some() {
+            continue;
+        }
+        let is_heredoc = line[i..].starts_with("<<")
+            && !line[i..].starts_with("<<<")
+            && !line[..i].ends_with('<');
+        if is_heredoc {
+            let rest = &line[i + 2..];
+            let delim = rest.trim_start();
+            let n = delim.find(char::is_whitespace).unwrap_or(delim.len());
+            if n == 0 {
+                return None;
+            }
+            let end = line.len() - delim.len() + n;
+            return Some((i, end, &delim[..n]));
+        }
+    }
+    None
+}
+
+// Split a line on the given operators found outside of quotes, substitutions,
+// groups, and comments, and return the parts with the operator preceding them.
+fn split_operators<'a>(
+    line: &'a str,
+    ops: &[&'a str]
+) -> Vec<(&'a str, &'a str)> {
+    scan_operators(line, ops).0
+}
+
+// Scan a line for operators like `split_operators` and also return the closing
+// char of the innermost group left open at the end of the line.
+fn scan_operators<'a>(
+    line: &'a str,
+    ops: &[&'a str]
+) -> (Vec<(&'a str, &'a str)>, Option<char>) {
+    let bytes = line.as_bytes();
+    let mut res = Vec::new();
+    let mut op = "";
+    let mut start = 0;
+    let mut end = bytes.len();
+    let mut stack = Vec::new(); // Substitutions, groups, and parentheses
+    let mut is_quote = false;
+    let mut is_single_quote = false;
+    let mut is_backquote = false;
+    let mut is_escaped = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        if is_escaped {
+            is_escaped = false;
+            i += 1;
+            continue;
+        }
+        if is_single_quote {
+            is_single_quote = bytes[i] != b'\'';
+            i += 1;
+            continue;
+        }
+        let is_unquoted = !is_quote && !is_backquote;
+        let is_top = is_unquoted && stack.is_empty();
+        let is_word_start = i == 0 || bytes[i - 1] == b' ';
+        let top = stack.last().copied();
+        let is_substitution = top == Some('$');
+        match bytes[i] {
+            b'\\' => is_escaped = true,
+            b'\'' if !is_quote => is_single_quote = true,
+            b'"' => is_quote = !is_quote,
+            b'`' => is_backquote = !is_backquote,
+            b'(' if is_substitution || (i > 0 && bytes[i - 1] == b'$') => {
+                stack.push('$');
+            }
+            b'(' if is_unquoted => {
+                if is_group_start(&line[i..], &line[..i]) {
+                    stack.push(')');
+                } else {
+                    stack.push('(');
+                }
+            }
+            b'{' if is_unquoted && is_group_start(&line[i..], &line[..i]) => {
+                stack.push('}');
+            }
+            b')' if is_substitution || (is_unquoted && !stack.is_empty()) => {
+                stack.pop();
+            }
+            b'}' if is_unquoted && is_word_start && top == Some('}') => {
+                stack.pop();
+            }
+            b'#' if is_unquoted && is_word_start && !stack.contains(&'$') => {
+                end = i;
+                break;
+            }
+            _ if is_top => {
+                if let Some(next) = ops.iter().find(|o| {
+                    line[i..].starts_with(**o)
+                }) {
+                    res.push((op, &line[start..i]));
+                    op = next;
+                    i += next.len();
+                    start = i;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    res.push((op, &line[start..end]));
+    let group = stack.into_iter().rev().find(|c| *c == ')' || *c == '}');
+    (res, group)
+}
+
+// Return true if a group of commands starts at the beginning of the text
+// following the given prefix, with a parenthesis for a subshell or with a
+// brace followed by a space for a group executed in the current shell.
+fn is_group_start(text: &str, prefix: &str) -> bool {
+    let is_opening = text.starts_with('(') || text.starts_with("{ ")
+        || text == "{";
+    let prefix = prefix.trim_end();
+    let (_, word) = prefix.rsplit_once(' ').unwrap_or(("", prefix));
+    is_opening && (
+        prefix.is_empty()
+            || prefix.ends_with(|c| ";&|>({".contains(c))
+            || ["then", "do", "else"].contains(&word)
+    )
+}
+
+// Return the index of the char closing the group starting a command
+fn group_end(cmd: &str) -> Option<usize> {
+    if !is_group_start(cmd, "") {
+        return None;
+    }
+    let close = if cmd.starts_with('(') { ")" } else { "}" };
+    let parts = split_operators(&cmd[1..], &[close]);
+    if parts.len() > 1 {
+        Some(1 + parts[0].1.len())
+    } else {
+        None
+    }
+}
+
+// Return the name of the function defined by a line like `name() {`
+fn function_name(line: &str) -> Option<&str> {
+    let (name, rest) = line.split_once("()")?;
+    let name = name.trim();
+    let is_valid = !name.is_empty() && name.chars().all(|c| {
+        c.is_alphanumeric() || c == '_' || c == '-'
+    });
+    if is_valid && rest.trim_start().starts_with('{') {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+fn split_keyword(line: &str) -> (&str, &str) {
+    match line.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim()),
+        None => (line, ""),
+    }
+}
+
+// Parse the statements of a block until one of its end keywords
+fn parse_block(
+    lines: &[&str],
+    i: &mut usize,
+    ends: &[&str]
+) -> Result<Vec<Statement>, String> {
+    let mut res = Vec::new();
+    while *i < lines.len() {
+        let (word, rest) = split_keyword(lines[*i]);
+        if ends.contains(&word) {
+            return Ok(res);
+        }
+        *i += 1;
+        if let Some(name) = function_name(lines[*i - 1]) {
+            let body = parse_block(lines, i, &["}"])?;
+            *i += 1;
+            res.push(Statement::Function(name.to_string(), body));
+            continue;
+        }
+        match word {
+            "if" => {
+                let mut branches = Vec::new();
+                let mut cond = rest.to_string();
+                loop {
+                    expect_keyword(lines, i, "then")?;
+                    let ends = ["elif", "else", "fi"];
+                    branches.push((cond, parse_block(lines, i, &ends)?));
+                    let (word, rest) = split_keyword(lines[*i]);
+                    *i += 1;
+                    match word {
+                        "elif" => {
+                            cond = rest.to_string();
+                        }
+                        "else" => {
+                            let body = parse_block(lines, i, &["fi"])?;
+                            *i += 1;
+                            res.push(Statement::If(branches, body));
+                            break;
+                        }
+                        _ => {
+                            res.push(Statement::If(branches, Vec::new()));
+                            break;
+                        }
+                    }
+                }
+            }
+            "while" => {
+                expect_keyword(lines, i, "do")?;
+                let body = parse_block(lines, i, &["done"])?;
+                *i += 1;
+                res.push(Statement::While(rest.to_string(), body));
+            }
+            "for" => {
+                let (var, words) = split_keyword(rest);
+                let words = match split_keyword(words) {
+                    ("in", words) if !var.is_empty() => words,
+                    _ => return Err("Could not parse for loop".to_string()),
+                };
+                expect_keyword(lines, i, "do")?;
+                let body = parse_block(lines, i, &["done"])?;
+                *i += 1;
+                let (var, words) = (var.to_string(), words.to_string());
+                res.push(Statement::For(var, words, body));
+            }
+            "then" | "elif" | "else" | "fi" | "do" | "done" | "}" => {
+                return Err(format!("Unexpected '{}'", word));
+            }
+            _ => {
+                res.push(Statement::Command(lines[*i - 1].to_string()));
+            }
+        }
+    }
+    match ends.last() {
+        Some(end) => Err(format!("Missing '{}'", end)),
+        None => Ok(res),
+    }
+}
+
+fn expect_keyword(
+    lines: &[&str],
+    i: &mut usize,
+    keyword: &str
+) -> Result<(), String> {
+    if *i < lines.len() && lines[*i] == keyword {
+        *i += 1;
+        Ok(())
+    } else {
+        Err(format!("Missing '{}'", keyword))
+    }
+}
+
+// The exit code of the last command executed is the exit code of the
+// statements, and the conditions are true when their command succeeds.
+fn exec_statements(
+    statements: &[Statement],
+    config: &mut Config
+) -> Result<(), ExitCode> {
+    let mut res = Ok(());
+    for statement in statements {
+        res = match statement {
+            Statement::Command(cmd) => exec_command(cmd, config),
+            Statement::Function(name, body) => {
+                config.functions.insert(name.clone(), body.clone());
+                Ok(())
+            }
+            Statement::If(branches, otherwise) => {
+                let mut body = otherwise;
+                for (cond, then) in branches {
+                    match exec_command(cond, config) {
+                        Err(ExitCode::ShellExit) => {
+                            return Err(ExitCode::ShellExit);
+                        }
+                        Ok(()) => {
+                            body = then;
+                            break;
+                        }
+                        Err(_) => continue,
+                    }
+                }
+                exec_statements(body, config)
+            }
+            Statement::While(cond, body) => {
+                let mut res = Ok(());
+                while !sys::console::end_of_text() {
+                    match exec_command(cond, config) {
+                        Err(ExitCode::ShellExit) => {
+                            return Err(ExitCode::ShellExit);
+                        }
+                        Ok(()) => {}
+                        Err(_) => break,
+                    }
+                    res = exec_statements(body, config);
+                    if res == Err(ExitCode::ShellExit) || config.returning {
+                        break;
+                    }
+                }
+                res
+            }
+            Statement::For(var, words, body) => {
+                let words = variables_expansion(words, config);
+                let words = command_substitution(&words, config);
+                let mut words = split_args(&words);
+                if !words.is_empty() {
+                    // The first arg is not globbed by `split_args`
+                    let first = words.remove(0);
+                    words.splice(0..0, glob(&first));
+                }
+                let mut res = Ok(());
+                for word in words {
+                    config.env.insert(var.clone(), word);
+                    res = exec_statements(body, config);
+                    if res == Err(ExitCode::ShellExit) || config.returning {
+                        break;
+                    }
+                }
+                res
+            }
+        };
+        if res == Err(ExitCode::ShellExit) || config.returning {
+            break;
+        }
+    }
+    res
+}
+
+// A command can be a chain of commands separated by `&&` and `||`, the next
+// command being executed only if the previous one succeeded for `&&` or
+// failed for `||`.
+fn exec_command(cmd: &str, config: &mut Config) -> Result<(), ExitCode> {
+    let mut res = Ok(());
+    for (op, cmd) in split_operators(cmd, &["&&", "||"]) {
+        if (op == "&&" && res.is_err()) || (op == "||" && res.is_ok()) {
+            continue;
+        }
+        res = exec_with_config(cmd, config);
+        let code = match res {
+            Err(e) => e,
+            Ok(()) => ExitCode::Success,
+        };
+        config.env.insert("status".to_string(), format!("{}", code as u8));
+        if res == Err(ExitCode::ShellExit) || config.returning {
+            break;
+        }
+    }
+    res
+}
+
+fn exec_script(script: &str, config: &mut Config) -> Result<(), ExitCode> {
+    match parse_script(script) {
+        Ok(statements) => {
+            let res = exec_statements(&statements, config);
+            config.returning = false;
+            res
+        }
+        Err(msg) => {
+            error!("{}", msg);
+            Err(ExitCode::Failure)
+        }
+    }
+}
+
+// The arguments of a function replace the positional parameters `$1`, `$2`,
+// `$3`, ... of the caller during its execution.
+fn call_function(
+    body: &[Statement],
+    args: &[&str],
+    config: &mut Config
+) -> Result<(), ExitCode> {
+    let is_param = |key: &String| {
+        key != "0" && key.chars().all(|c| c.is_ascii_digit())
+    };
+    let params: Vec<String> = config.env.keys().filter(|key| {
+        is_param(key)
+    }).cloned().collect();
+    let mut saved = Vec::new();
+    for key in params {
+        if let Some(val) = config.env.remove(&key) {
+            saved.push((key, val));
+        }
+    }
+    for (i, arg) in args[1..].iter().enumerate() {
+        config.env.insert((i + 1).to_string(), arg.to_string());
+    }
+
+    let res = exec_statements(body, config);
+    config.returning = false;
+
+    config.env.retain(|key, _| !is_param(key));
+    config.env.extend(saved);
+    res
+}
+
+fn cmd_return(args: &[&str], config: &mut Config) -> Result<(), ExitCode> {
+    let code = match args.get(1) {
+        Some(arg) => match arg.parse::<usize>() {
+            Ok(code) => ExitCode::from(code),
+            Err(_) => {
+                error!("Invalid exit code '{}'", arg);
+                return Err(ExitCode::UsageError);
+            }
+        },
+        None => ExitCode::Success,
+    };
+    config.returning = true;
+    match code {
+        ExitCode::Success => Ok(()),
+        code => Err(code),
+    }
+}
+
 fn repl(config: &mut Config) -> Result<(), ExitCode> {
     println!();
 
@@ -632,14 +1768,17 @@ fn repl(config: &mut Config) -> Result<(), ExitCode> {
     prompt.completion.set(&shell_completer);
 
     let mut code = ExitCode::Success;
-    let success = code;
-    while let Some(cmd) = prompt.input(&prompt_string(code == success)) {
-        code = match exec_with_config(&cmd, config) {
+    loop {
+        let ps1 = config.env.get("PS1").map_or(DEFAULT_PROMPT, String::as_str);
+        let cmd = match prompt.input(&expand_prompt(ps1, code)) {
+            Some(cmd) => cmd,
+            None => break,
+        };
+        code = match exec_script(&cmd, config) {
             Err(ExitCode::ShellExit) => break,
             Err(e) => e,
             Ok(()) => ExitCode::Success,
         };
-        config.env.insert("status".to_string(), format!("{}", code as u8));
         prompt.history.add(&cmd);
         prompt.history.save(history_file);
         sys::console::drain();
@@ -651,16 +1790,14 @@ fn repl(config: &mut Config) -> Result<(), ExitCode> {
 
 pub fn exec(cmd: &str) -> Result<(), ExitCode> {
     let mut config = Config::new();
-    exec_with_config(cmd, &mut config)
+    exec_script(cmd, &mut config)
 }
 
 pub fn main(args: &[&str]) -> Result<(), ExitCode> {
     let mut config = Config::new();
 
     if let Ok(rc) = fs::read_to_string("/ini/shell.sh") {
-        for cmd in rc.split('\n') {
-            exec_with_config(cmd, &mut config).ok();
-        }
+        exec_script(&rc, &mut config).ok();
     }
 
     if args.len() < 2 {
@@ -680,12 +1817,10 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
 
         let path = args[1];
         if let Ok(contents) = api::fs::read_to_string(path) {
-            for line in contents.split('\n') {
-                if !line.is_empty() {
-                    exec_with_config(line, &mut config).ok();
-                }
+            match exec_script(&contents, &mut config) {
+                Err(ExitCode::ShellExit) => Ok(()),
+                res => res,
             }
-            Ok(())
         } else {
             error!("Could not read file '{}'", path);
             Err(ExitCode::Failure)
@@ -731,6 +1866,162 @@ fn test_shell() {
     assert!(api::fs::read_to_string("/tmp/test3").unwrap().
         contains("Could not read file '/nope'"));
 
+    // Append standard output
+    exec("print test4 >> /tmp/test1").ok();
+    assert_eq!(
+        api::fs::read_to_string("/tmp/test1"),
+        Ok("test1\ntest4\n".to_string())
+    );
+
+    // Truncate standard output
+    exec("print test1 => /tmp/test1").ok();
+    assert_eq!(
+        api::fs::read_to_string("/tmp/test1"),
+        Ok("test1\n".to_string())
+    );
+
+    // Merge standard error into standard output
+    exec("hex /nope => /tmp/test6 2>&1").ok();
+    assert!(api::fs::read_to_string("/tmp/test6").unwrap().
+        contains("Could not read file '/nope'"));
+
+    // Pipe standard output
+    exec("print \"b\\na\" | sort | sort -r => /tmp/test4").ok();
+    assert_eq!(
+        api::fs::read_to_string("/tmp/test4"),
+        Ok("b\na\n".to_string())
+    );
+
+    // Pipe standard error
+    exec("hex /nope [2]-> grep file => /tmp/test5").ok();
+    assert!(api::fs::read_to_string("/tmp/test5").unwrap().
+        contains("'/nope'"));
+
+    // Substitute commands with their output
+    exec("print $(print a) `print b` $(print $(print c)) => /tmp/test7").ok();
+    assert_eq!(
+        api::fs::read_to_string("/tmp/test7"),
+        Ok("a b c\n".to_string())
+    );
+
+    // Variables assignment and export
+    let mut config = Config::new();
+    exec_with_config("foo=1 bar=2", &mut config).ok();
+    exec_with_config("export bar", &mut config).ok();
+    exec("print $foo $bar => /tmp/test8").ok();
+    assert_eq!(
+        api::fs::read_to_string("/tmp/test8"),
+        Ok("2\n".to_string())
+    );
+    exec_with_config("unset bar", &mut config).ok();
+    assert_eq!(sys::process::env("bar"), None);
+
+    // Control flow
+    let mut config = Config::new();
+    let script = "
+        for x in a b c
+        do
+            if print $x => /dev/null
+            then print $x >> /tmp/test9
+            else print none >> /tmp/test9
+            fi
+        done
+        print z => /tmp/flag
+        while read /tmp/flag => /dev/null
+        do
+            delete /tmp/flag
+            print $x >> /tmp/test9
+        done
+    ";
+    exec_script(script, &mut config).ok();
+    assert_eq!(
+        api::fs::read_to_string("/tmp/test9"),
+        Ok("a\nb\nc\nc\n".to_string())
+    );
+
+    // Functions
+    let mut config = Config::new();
+    config.env.insert("1".to_string(), "x".to_string());
+    let script = "
+        greet() {
+            if print $1 => /dev/null
+            then
+                print hello $1 $2 >> /tmp/test10
+                return
+            fi
+            print unreachable >> /tmp/test10
+        }
+        greet alice bob
+        greet carol
+        print $1 >> /tmp/test10
+    ";
+    exec_script(script, &mut config).ok();
+    assert_eq!(
+        api::fs::read_to_string("/tmp/test10"),
+        Ok("hello alice bob\nhello carol\nx\n".to_string())
+    );
+
+    // Sequences and combiners
+    exec("print a => /tmp/test11; read /nope 2=> /dev/null && print b >> \
+          /tmp/test11 || print c >> /tmp/test11; print $? >> /tmp/test11").ok();
+    assert_eq!(
+        api::fs::read_to_string("/tmp/test11"),
+        Ok("a\nc\n0\n".to_string())
+    );
+
+    // Aliases
+    let mut config = Config::new();
+    exec_with_config("alias pa='print a'", &mut config).ok();
+    exec_with_config("alias pb=\"pa b\"", &mut config).ok();
+    exec_with_config("alias print print c", &mut config).ok();
+    exec_with_config("pb d => /tmp/test12", &mut config).ok();
+    assert_eq!(
+        api::fs::read_to_string("/tmp/test12"),
+        Ok("c a b d\n".to_string())
+    );
+
+    // Here-strings and heredocs
+    exec("tee /tmp/test13 <<< \"a $?\" => /dev/null").ok();
+    assert_eq!(
+        api::fs::read_to_string("/tmp/test13"),
+        Ok("a 0\n".to_string())
+    );
+    let mut config = Config::new();
+    let script = "
+        tee /tmp/test14 <<EOF => /dev/null
+        a $?
+        EOF
+        tee -a /tmp/test14 <<'EOF' => /dev/null
+        b $?
+        EOF
+    ";
+    exec_script(script, &mut config).ok();
+    assert_eq!(
+        api::fs::read_to_string("/tmp/test14"),
+        Ok("        a 0\n        b $?\n".to_string())
+    );
+
+    // Groups and subshells
+    let mut config = Config::new();
+    exec_with_config("{ print a; print b; } => /tmp/test15", &mut config).ok();
+    assert_eq!(
+        api::fs::read_to_string("/tmp/test15"),
+        Ok("a\nb\n".to_string())
+    );
+    let script = "
+        x=1
+        (
+            x=2
+            goto /tmp
+        )
+        print $x $DIR => test16
+    ";
+    exec_script(script, &mut config).ok();
+    assert_eq!(
+        api::fs::read_to_string("/test16"),
+        Ok("1 /\n".to_string())
+    );
+
     let mut config = Config::new();
     exec_with_config("set b 42", &mut config).ok();
     exec_with_config("print a $b $c d => /test", &mut config).ok();
@@ -761,6 +2052,15 @@ fn test_split_args() {
         vec!["print", "foo", "bar"]
     );
     assert_eq!(split_args("print foo \"\" "), vec!["print", "foo", ""]);
+    assert_eq!(
+        split_args("print foo\"bar baz\"'x y'"),
+        vec!["print", "foobar bazx y"]
+    );
+    assert_eq!(
+        split_args("print 'a\\n' \"a\\n\" a\\ b \\'"),
+        vec!["print", "a\\n", "a\n", "a b", "'"]
+    );
+    assert_eq!(split_args("print a#b \"#\""), vec!["print", "a#b", "#"]);
 }
 
 #[test_case]
@@ -770,6 +2070,26 @@ fn test_glob_to_regex() {
     assert_eq!(glob_to_regex("h*.txt"), "^h.*\\.txt$");
     assert_eq!(glob_to_regex("*.txt"), "^.*\\.txt$");
     assert_eq!(glob_to_regex("\\w*.txt"), "^\\\\w.*\\.txt$");
+    assert_eq!(glob_to_regex("[ab]?.rs"), "^[ab].\\.rs$");
+    assert_eq!(glob_to_regex("[!0-9]*"), "^[^0-9].*$");
+    assert_eq!(glob_to_regex("a[b"), "^a\\[b$");
+    assert_eq!(glob_to_regex("a+b"), "^a\\+b$");
+}
+
+#[test_case]
+fn test_expand_prompt() {
+    let red = Style::color("Red");
+    let reset = Style::reset();
+    assert_eq!(expand_prompt("> ", ExitCode::Success), "> ");
+    assert_eq!(expand_prompt("%? %%", ExitCode::Failure), "1 %");
+    assert_eq!(
+        expand_prompt("%c>%{} ", ExitCode::Failure),
+        format!("{}>{} ", red, reset)
+    );
+    assert_eq!(
+        expand_prompt("%{Red}%w", ExitCode::Success),
+        format!("{}{}", red, sys::process::dir())
+    );
 }
 
 #[test_case]
@@ -782,4 +2102,27 @@ fn test_variables_expansion() {
         variables_expansion("print \"Hello $bar\"", &mut config),
         "print \"Hello Alice and Bob\""
     );
+    assert_eq!(
+        variables_expansion("print '$foo' \\$foo $foo_", &mut config),
+        "print '$foo' \\$foo "
+    );
+}
+
+#[test_case]
+fn test_split_operators() {
+    let n = |line| split_operators(line, &[";"]).len();
+    assert_eq!(n("a; b; c"), 3);
+    assert_eq!(n("a; (b; c); d"), 3);
+    assert_eq!(n("a; { b; c; } => d; e"), 3);
+    assert_eq!(n("f() { b; c; }"), 3);
+    assert_eq!(n("print $(a; b) c; d"), 2);
+    assert_eq!(n("a # b; c"), 1);
+
+    assert_eq!(scan_operators("{ a", &[]).1, Some('}'));
+    assert_eq!(scan_operators("(a; { b; }", &[]).1, Some(')'));
+    assert_eq!(scan_operators("{a", &[]).1, None);
+
+    assert_eq!(group_end("(a; b) => c"), Some(5));
+    assert_eq!(group_end("{ a; (b; c); } => d"), Some(13));
+    assert_eq!(group_end("print (a)"), None);
 }
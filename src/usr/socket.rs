@@ -78,7 +78,8 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
     let stdin = 0;
     let stdout = 1;
     let flags = OpenFlag::Device as usize;
-    if let Some(handle) = syscall::open(socket_path, flags) {
+    if let Some(listener) = syscall::open(socket_path, flags) {
+        let mut handle = listener;
         if listen {
             if syscall::listen(handle, port).is_err() {
                 error!("Could not listen to {}:{}", addr, port);
@@ -108,7 +109,8 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
             }
 
             if listen && !connected {
-                if syscall::accept(handle).is_ok() {
+                if let Ok((connection, _)) = syscall::accept(listener) {
+                    handle = connection;
                     connected = true;
                 } else {
                     syscall::sleep(0.01);
@@ -140,6 +142,9 @@ pub fn main(args: &[&str]) -> Result<(), ExitCode> {
             }
         }
         syscall::close(handle);
+        if listen {
+            syscall::close(listener);
+        }
         Ok(())
     } else {
         Err(ExitCode::Failure)
@@ -0,0 +1,386 @@
+use crate::api::console::{Style, EOT_KEY};
+use crate::api::fs;
+use crate::api::process::ExitCode;
+use crate::api::syscall;
+use crate::sys;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+// Inputs bigger than this are sorted by chunks saved into temporary files
+// that are merged at the end.
+const CHUNK_SIZE: usize = 64 << 10;
+
+struct Config {
+    numeric: bool,
+    reverse: bool,
+    unique: bool,
+    key: Option<usize>,
+}
+
+// Read lines from a handle without loading the whole input into memory
+struct LineReader {
+    handle: usize,
+    buf: Vec<u8>,
+    eof: bool,
+}
+
+impl LineReader {
+    fn new(handle: usize) -> Self {
+        Self { handle, buf: Vec::new(), eof: false }
+    }
+
+    fn next_line(&mut self) -> Option<String> {
+        loop {
+            if let Some(i) = self.buf.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = self.buf.drain(..i + 1).collect();
+                let line = &line[..i];
+                return Some(String::from_utf8_lossy(line).into());
+            }
+            if self.eof {
+                if self.buf.is_empty() {
+                    return None;
+                }
+                let line = String::from_utf8_lossy(&self.buf).into();
+                self.buf.clear();
+                return Some(line);
+            }
+            let mut chunk = vec![0; 1024];
+            match syscall::read(self.handle, &mut chunk) {
+                Some(0) | None => self.eof = true,
+                Some(n) => {
+                    chunk.truncate(n);
+                    // Typing ^D on the console ends the input
+                    if let Some(i) = chunk.iter().position(|b| {
+                        *b as char == EOT_KEY
+                    }) {
+                        chunk.truncate(i);
+                        self.eof = true;
+                    }
+                    self.buf.extend_from_slice(&chunk);
+                }
+            }
+        }
+    }
+}
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut config = Config {
+        numeric: false,
+        reverse: false,
+        unique: false,
+        key: None,
+    };
+    let mut paths = Vec::new();
+    let mut i = 1;
+    let n = args.len();
+    while i < n {
+        match args[i] {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-n" | "--numeric" => config.numeric = true,
+            "-r" | "--reverse" => config.reverse = true,
+            "-u" | "--unique" => config.unique = true,
+            "-k" | "--key" => {
+                if i + 1 < n {
+                    match args[i + 1].parse() {
+                        Ok(k) if k > 0 => config.key = Some(k),
+                        _ => {
+                            error!("Invalid key '{}'", args[i + 1]);
+                            return Err(ExitCode::UsageError);
+                        }
+                    }
+                    i += 1;
+                } else {
+                    error!("Missing key");
+                    return Err(ExitCode::UsageError);
+                }
+            }
+            arg => paths.push(arg),
+        }
+        i += 1;
+    }
+
+    let mut readers = Vec::new();
+    if paths.is_empty() {
+        readers.push(LineReader::new(0));
+    }
+    for path in &paths {
+        if let Some(handle) = fs::open_file(path) {
+            readers.push(LineReader::new(handle));
+        } else {
+            error!("Could not read '{}'", path);
+            return Err(ExitCode::Failure);
+        }
+    }
+
+    let res = sort(&mut readers, &config, CHUNK_SIZE, &mut |line| {
+        println!("{}", line);
+    });
+    for reader in readers {
+        if reader.handle > 0 {
+            syscall::close(reader.handle);
+        }
+    }
+    res
+}
+
+fn sort(
+    readers: &mut [LineReader],
+    config: &Config,
+    chunk_size: usize,
+    output: &mut dyn FnMut(&str),
+) -> Result<(), ExitCode> {
+    let mut chunks = Vec::new();
+    let mut lines = Vec::new();
+    let mut size = 0;
+    for reader in readers.iter_mut() {
+        while let Some(line) = reader.next_line() {
+            size += line.len() + 1;
+            lines.push(line);
+            if size > chunk_size {
+                let path = chunk_path(chunks.len());
+                sort_chunk(&mut lines, config);
+                if save_chunk(&path, &lines).is_err() {
+                    delete_chunks(&chunks);
+                    return Err(ExitCode::Failure);
+                }
+                chunks.push(path);
+                lines.clear();
+                size = 0;
+            }
+        }
+    }
+
+    sort_chunk(&mut lines, config);
+    if chunks.is_empty() {
+        for line in &lines {
+            output(line);
+        }
+        return Ok(());
+    }
+    if !lines.is_empty() {
+        let path = chunk_path(chunks.len());
+        if save_chunk(&path, &lines).is_err() {
+            delete_chunks(&chunks);
+            return Err(ExitCode::Failure);
+        }
+        chunks.push(path);
+    }
+    drop(lines);
+
+    let res = merge_chunks(&chunks, config, output);
+    delete_chunks(&chunks);
+    res
+}
+
+fn merge_chunks(
+    chunks: &[String],
+    config: &Config,
+    output: &mut dyn FnMut(&str),
+) -> Result<(), ExitCode> {
+    let mut readers = Vec::new();
+    for path in chunks {
+        if let Some(handle) = fs::open_file(path) {
+            readers.push(LineReader::new(handle));
+        } else {
+            error!("Could not read '{}'", path);
+            for reader in readers {
+                syscall::close(reader.handle);
+            }
+            return Err(ExitCode::Failure);
+        }
+    }
+    let mut heads: Vec<_> = readers.iter_mut().map(|r| r.next_line()).collect();
+    let mut last: Option<String> = None;
+    loop {
+        let mut min: Option<usize> = None;
+        for (i, head) in heads.iter().enumerate() {
+            if let Some(line) = head {
+                let is_min = match min {
+                    Some(j) => {
+                        let other = heads[j].as_ref().unwrap();
+                        compare(line, other, config) == Ordering::Less
+                    }
+                    None => true,
+                };
+                if is_min {
+                    min = Some(i);
+                }
+            }
+        }
+        let i = match min {
+            Some(i) => i,
+            None => break,
+        };
+        let line = heads[i].take().unwrap();
+        heads[i] = readers[i].next_line();
+        if config.unique {
+            if let Some(prev) = &last {
+                if compare_keys(prev, &line, config) == Ordering::Equal {
+                    continue;
+                }
+            }
+        }
+        output(&line);
+        last = Some(line);
+    }
+    for reader in readers {
+        syscall::close(reader.handle);
+    }
+    Ok(())
+}
+
+fn sort_chunk(lines: &mut Vec<String>, config: &Config) {
+    lines.sort_by(|a, b| compare(a, b, config));
+    if config.unique {
+        lines.dedup_by(|a, b| compare_keys(a, b, config) == Ordering::Equal);
+    }
+}
+
+fn chunk_path(i: usize) -> String {
+    format!("/tmp/.sort-{}-{}", sys::process::id(), i)
+}
+
+fn save_chunk(path: &str, lines: &[String]) -> Result<(), ()> {
+    let mut contents = lines.join("\n");
+    contents.push('\n');
+    if fs::write(path, contents.as_bytes()).is_err() {
+        error!("Could not write to '{}'", path);
+        return Err(());
+    }
+    Ok(())
+}
+
+fn delete_chunks(chunks: &[String]) {
+    for path in chunks {
+        fs::delete(path).ok();
+    }
+}
+
+fn compare(a: &str, b: &str, config: &Config) -> Ordering {
+    // Lines with equal keys are sorted by their whole content
+    let ordering = compare_keys(a, b, config).then_with(|| a.cmp(b));
+    if config.reverse {
+        ordering.reverse()
+    } else {
+        ordering
+    }
+}
+
+fn compare_keys(a: &str, b: &str, config: &Config) -> Ordering {
+    let a = key(a, config);
+    let b = key(b, config);
+    if config.numeric {
+        let x = number(a);
+        let y = number(b);
+        x.partial_cmp(&y).unwrap_or(Ordering::Equal)
+    } else {
+        a.cmp(b)
+    }
+}
+
+fn key<'a>(line: &'a str, config: &Config) -> &'a str {
+    match config.key {
+        Some(k) => line.split_whitespace().nth(k - 1).unwrap_or(""),
+        None => line,
+    }
+}
+
+// Parse the number at the beginning of the text or return zero
+fn number(text: &str) -> f64 {
+    let text = text.trim_start();
+    let n = text.char_indices().find(|(i, c)| {
+        !(c.is_ascii_digit() || *c == '.' || (*i == 0 && *c == '-'))
+    }).map_or(text.len(), |(i, _)| i);
+    text[..n].parse().unwrap_or(0.0)
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} sort {}<options> [<path>...]{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-n{1}, {0}--numeric{1}     Compare numerical values",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-r{1}, {0}--reverse{1}     Reverse the result",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-u{1}, {0}--unique{1}      Print only the first of equal lines",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-k{1}, {0}--key <n>{1}     Sort by the {0}<n>{1}th field",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_sort() {
+    sys::fs::mount_mem();
+    sys::fs::format_mem();
+    fs::create_dir("/tmp");
+
+    let mut contents = String::new();
+    for i in 0..100 {
+        contents.push_str(&format!("{} {}\n", (i * 37) % 50, i));
+    }
+    fs::write("/tmp/input.txt", contents.as_bytes()).ok();
+
+    let config = Config {
+        numeric: true,
+        reverse: false,
+        unique: true,
+        key: Some(1),
+    };
+    let mut lines = Vec::new();
+    let handle = fs::open_file("/tmp/input.txt").unwrap();
+    let mut readers = [LineReader::new(handle)];
+
+    // Use a tiny chunk size to go through temporary files
+    let res = sort(&mut readers, &config, 64, &mut |line| {
+        lines.push(String::from(line))
+    });
+    syscall::close(handle);
+    assert!(res.is_ok());
+    assert_eq!(lines.len(), 50);
+    assert_eq!(lines[0], "0 0");
+    assert_eq!(lines[2], "2 46");
+    assert_eq!(lines[49], "49 27");
+    assert_eq!(fs::read_dir("/tmp").unwrap().len(), 1);
+
+    sys::fs::dismount();
+}
+
+#[test_case]
+fn test_compare() {
+    let mut config = Config {
+        numeric: false,
+        reverse: false,
+        unique: false,
+        key: None,
+    };
+    assert_eq!(compare("10", "9", &config), Ordering::Less);
+    config.numeric = true;
+    assert_eq!(compare("10", "9", &config), Ordering::Greater);
+    config.reverse = true;
+    assert_eq!(compare("10", "9", &config), Ordering::Less);
+    config.key = Some(2);
+    assert_eq!(compare("a 1", "b 2", &config), Ordering::Greater);
+    assert_eq!(number("-1.5 apples"), -1.5);
+    assert_eq!(number("apples"), 0.0);
+}
@@ -0,0 +1,113 @@
+use crate::api::clock::DATE_TIME;
+use crate::api::console::Style;
+use crate::api::fs::{FileInfo, FileType};
+use crate::api::process::ExitCode;
+use crate::api::syscall;
+use crate::api::time;
+use crate::sys;
+use crate::usr;
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    if args.len() < 2 {
+        help();
+        return Err(ExitCode::UsageError);
+    }
+    for arg in &args[1..] {
+        if *arg == "-h" || *arg == "--help" {
+            help();
+            return Ok(());
+        }
+    }
+    for (i, path) in args[1..].iter().enumerate() {
+        if let Some(info) = syscall::info(path) {
+            if i > 0 {
+                println!();
+            }
+            for (key, val) in stat(path, &info) {
+                let color = Style::color("LightCyan");
+                let reset = Style::reset();
+                println!("{}{:<9}{} {}", color, key, reset, val);
+            }
+        } else {
+            error!("Could not find file '{}'", path);
+            return Err(ExitCode::Failure);
+        }
+    }
+    Ok(())
+}
+
+fn stat(path: &str, info: &FileInfo) -> Vec<(&'static str, String)> {
+    let (kind, flags) = match info.kind() {
+        FileType::Dir => ("directory", "read, create"),
+        FileType::File => ("file", "read, write, append, create, truncate"),
+        FileType::Device => ("device", "read, write"),
+        FileType::Link => ("link", "read"),
+    };
+    // The first bytes of each linked block store the address of the next
+    let data_size = sys::fs::BLOCK_SIZE - 4;
+    let size = info.size() as usize;
+    let blocks = if info.is_device() { 1 } else { size / data_size + 1 };
+    let date = |ts: u64| time::from_timestamp(ts as i64).format(DATE_TIME);
+
+    let mut res = Vec::new();
+    res.push(("Path", path.into()));
+    res.push(("Kind", kind.into()));
+    if let Some(target) = syscall::read_link(path) {
+        res.push(("Link", target));
+    }
+    res.push(("Size", format!("{} bytes", size)));
+    res.push(("Blocks", format!("{} from {:#X}", blocks, info.addr())));
+    res.push(("Links", info.links().to_string()));
+    if let Some(device) = info.device() {
+        res.push(("Device", format!("{:?} ({})", device, device as u8)));
+    }
+    res.push(("Access", flags.into()));
+    let mode = usr::chmod::format_mode(info.mode());
+    res.push(("Mode", format!("{:04o} ({})", info.mode(), mode)));
+    let (uid, gid) = (info.uid(), info.gid());
+    let user = usr::user::username(uid).unwrap_or("?".into());
+    let group = usr::user::groupname(gid).unwrap_or("?".into());
+    res.push(("Owner", format!("{} ({})", user, uid)));
+    res.push(("Group", format!("{} ({})", group, gid)));
+    res.push(("Created", date(info.ctime())));
+    res.push(("Modified", date(info.mtime())));
+    res.push(("Accessed", date(info.atime())));
+    res
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} stat {}<path>...{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_stat() {
+    sys::fs::mount_mem();
+    sys::fs::format_mem();
+    usr::install::copy_files(false);
+
+    let info = syscall::info("/dev/console").unwrap();
+    let res = stat("/dev/console", &info);
+    assert!(res.contains(&("Kind", "device".into())));
+    assert!(res.contains(&("Device", "Console (2)".into())));
+
+    let info = syscall::info("/tmp/alice.txt").unwrap();
+    let res = stat("/tmp/alice.txt", &info);
+    assert!(res.contains(&("Kind", "file".into())));
+    assert!(res.iter().all(|(key, _)| *key != "Device"));
+    assert!(res.contains(&("Links", "1".into())));
+    assert!(res.contains(&("Mode", "0644 (rw-r--r--)".into())));
+    assert!(res.contains(&("Owner", "root (0)".into())));
+    assert!(res.contains(&("Group", "root (0)".into())));
+
+    sys::fs::dismount();
+}
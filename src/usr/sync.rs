@@ -0,0 +1,25 @@
+use crate::api::console::Style;
+use crate::api::process::ExitCode;
+use crate::api::syscall;
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    if args.len() > 1 {
+        if args[1] == "-h" || args[1] == "--help" {
+            help();
+            return Ok(());
+        }
+        help();
+        return Err(ExitCode::UsageError);
+    }
+    if syscall::sync().is_err() {
+        error!("Could not write cached blocks to disk");
+        return Err(ExitCode::Failure);
+    }
+    Ok(())
+}
+
+fn help() {
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!("{}Usage:{} sync", csi_title, csi_reset);
+}
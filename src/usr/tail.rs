@@ -0,0 +1,141 @@
+use crate::api::console::Style;
+use crate::api::fs;
+use crate::api::io;
+use crate::api::process::ExitCode;
+use crate::api::syscall;
+use crate::sys::console;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut count = 10;
+    let mut follow = false;
+    let mut paths = Vec::new();
+    let mut i = 1;
+    let n = args.len();
+    while i < n {
+        match args[i] {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-f" | "--follow" => follow = true,
+            "-n" | "--lines" => {
+                if i + 1 < n {
+                    if let Ok(lines) = args[i + 1].parse() {
+                        count = lines;
+                    } else {
+                        error!("Invalid number of lines '{}'", args[i + 1]);
+                        return Err(ExitCode::UsageError);
+                    }
+                    i += 1;
+                } else {
+                    error!("Missing number of lines");
+                    return Err(ExitCode::UsageError);
+                }
+            }
+            arg => paths.push(arg),
+        }
+        i += 1;
+    }
+
+    if paths.is_empty() {
+        if follow {
+            error!("Could not follow stdin");
+            return Err(ExitCode::UsageError);
+        }
+        print!("{}", tail(&io::stdin().read_to_string(), count));
+        return Ok(());
+    }
+    if follow && paths.len() > 1 {
+        error!("Could not follow multiple files");
+        return Err(ExitCode::UsageError);
+    }
+
+    let name_color = Style::color("Yellow");
+    let reset = Style::reset();
+    let show_name = paths.len() > 1;
+    for (i, path) in paths.iter().enumerate() {
+        if let Ok(contents) = fs::read_to_string(path) {
+            if show_name {
+                if i > 0 {
+                    println!();
+                }
+                println!("{}{}{}", name_color, path, reset);
+            }
+            print!("{}", tail(&contents, count));
+            if follow {
+                return follow_file(path, contents.len());
+            }
+        } else {
+            error!("Could not read '{}'", path);
+            return Err(ExitCode::Failure);
+        }
+    }
+    Ok(())
+}
+
+// Poll the size of the file and print the data appended to it until the
+// user presses ^C or ^D.
+fn follow_file(path: &str, mut offset: usize) -> Result<(), ExitCode> {
+    loop {
+        if console::end_of_text() || console::end_of_transmission() {
+            println!();
+            return Ok(());
+        }
+        let size = match syscall::info(path) {
+            Some(info) => info.size() as usize,
+            None => {
+                error!("Could not find file '{}'", path);
+                return Err(ExitCode::Failure);
+            }
+        };
+        if size < offset {
+            warning!("File '{}' truncated", path);
+            offset = 0;
+        }
+        if size > offset {
+            if let Ok(buf) = fs::read_to_bytes(path) {
+                let n = buf.len();
+                print!("{}", String::from_utf8_lossy(&buf[offset.min(n)..]));
+                offset = n;
+            }
+        }
+        syscall::sleep(0.1);
+    }
+}
+
+fn tail(text: &str, count: usize) -> String {
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let n = lines.len();
+    lines[n - count.min(n)..].concat()
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} tail {}<options> [<path>...]{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-n{1}, {0}--lines <n>{1}   Print the last {0}<n>{1} lines \
+        (default: 10)",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-f{1}, {0}--follow{1}      Print data appended to the file",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_tail() {
+    assert_eq!(tail("a\nb\nc\n", 2), "b\nc\n");
+    assert_eq!(tail("a\nb\nc", 5), "a\nb\nc");
+    assert_eq!(tail("a\nb\nc\n", 0), "");
+}
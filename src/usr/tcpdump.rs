@@ -0,0 +1,395 @@
+use crate::api::console::Style;
+use crate::api::fs;
+use crate::api::process::ExitCode;
+use crate::api::{syscall, time};
+use crate::sys;
+use crate::sys::console;
+use crate::sys::net::capture::Packet;
+use crate::sys::net::firewall::Direction;
+use crate::sys::net::socket::SOCKETS;
+use crate::sys::net::EthernetDeviceIO;
+use crate::usr;
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use smoltcp::wire::{
+    ArpOperation, ArpPacket, ArpRepr, EthernetFrame, EthernetProtocol,
+    Icmpv4Message, Icmpv4Packet, IpProtocol, Ipv4Packet, TcpPacket, UdpPacket,
+};
+
+// https://wiki.wireshark.org/Development/LibpcapFileFormat
+const PCAP_MAGIC: u32 = 0xA1B2C3D4;
+const PCAP_VERSION: (u16, u16) = (2, 4);
+const PCAP_SNAPLEN: u32 = 65535;
+const LINKTYPE_ETHERNET: u32 = 1;
+
+fn pcap_header() -> Vec<u8> {
+    let mut buf = Vec::with_capacity(24);
+    buf.extend_from_slice(&PCAP_MAGIC.to_le_bytes());
+    buf.extend_from_slice(&PCAP_VERSION.0.to_le_bytes());
+    buf.extend_from_slice(&PCAP_VERSION.1.to_le_bytes());
+    buf.extend_from_slice(&0i32.to_le_bytes()); // Timezone offset
+    buf.extend_from_slice(&0u32.to_le_bytes()); // Timestamps accuracy
+    buf.extend_from_slice(&PCAP_SNAPLEN.to_le_bytes());
+    buf.extend_from_slice(&LINKTYPE_ETHERNET.to_le_bytes());
+    buf
+}
+
+fn pcap_record(packet: &Packet) -> Vec<u8> {
+    let sec = packet.time as u32;
+    let usec = (((packet.time - sec as f64) * 1e6) as u32).min(999999);
+    let len = packet.data.len() as u32;
+    let mut buf = Vec::with_capacity(16 + packet.data.len());
+    buf.extend_from_slice(&sec.to_le_bytes());
+    buf.extend_from_slice(&usec.to_le_bytes());
+    buf.extend_from_slice(&len.to_le_bytes()); // Captured length
+    buf.extend_from_slice(&len.to_le_bytes()); // Original length
+    buf.extend_from_slice(&packet.data);
+    buf
+}
+
+fn timestamp(t: f64) -> String {
+    let sec = t as u64;
+    let usec = (((t - sec as f64) * 1e6) as u64).min(999999);
+    let time = time::from_timestamp(sec as i64).format("%H:%M:%S");
+    format!("{}.{:06}", time, usec)
+}
+
+fn tcp_flags(packet: &TcpPacket<&[u8]>) -> String {
+    let mut flags = String::new();
+    if packet.syn() {
+        flags.push('S');
+    }
+    if packet.fin() {
+        flags.push('F');
+    }
+    if packet.rst() {
+        flags.push('R');
+    }
+    if packet.psh() {
+        flags.push('P');
+    }
+    if packet.ack() {
+        flags.push('.');
+    }
+    flags
+}
+
+fn arp_summary(buf: &[u8]) -> String {
+    let repr = ArpPacket::new_checked(buf).and_then(|packet| {
+        ArpRepr::parse(&packet)
+    });
+    match repr {
+        Ok(ArpRepr::EthernetIpv4 {
+            operation,
+            source_hardware_addr,
+            source_protocol_addr,
+            target_protocol_addr,
+            ..
+        }) => match operation {
+            ArpOperation::Request => format!(
+                "ARP, Request who-has {} tell {}",
+                target_protocol_addr, source_protocol_addr
+            ),
+            ArpOperation::Reply => format!(
+                "ARP, Reply {} is-at {}",
+                source_protocol_addr,
+                source_hardware_addr.to_string().to_uppercase()
+            ),
+            ArpOperation::Unknown(op) => format!("ARP, Operation {}", op),
+        },
+        _ => format!("ARP, Invalid packet, length {}", buf.len()),
+    }
+}
+
+fn ipv4_summary(buf: &[u8]) -> String {
+    let ip = match Ipv4Packet::new_checked(buf) {
+        Ok(ip) => ip,
+        Err(_) => return format!("IP, Invalid packet, length {}", buf.len()),
+    };
+    let (src, dst) = (ip.src_addr(), ip.dst_addr());
+    let payload = ip.payload();
+    let len = payload.len();
+    if ip.more_frags() || ip.frag_offset() > 0 {
+        return format!("IP {} > {}: Fragment, length {}", src, dst, len);
+    }
+    match ip.next_header() {
+        IpProtocol::Tcp => match TcpPacket::new_checked(payload) {
+            Ok(tcp) => {
+                let mut s = format!(
+                    "IP {}:{} > {}:{}: TCP [{}], seq {}",
+                    src, tcp.src_port(), dst, tcp.dst_port(),
+                    tcp_flags(&tcp), tcp.seq_number()
+                );
+                if tcp.ack() {
+                    s.push_str(&format!(", ack {}", tcp.ack_number()));
+                }
+                s.push_str(&format!(
+                    ", win {}, length {}",
+                    tcp.window_len(), tcp.payload().len()
+                ));
+                s
+            }
+            Err(_) => format!("IP {} > {}: TCP, length {}", src, dst, len),
+        },
+        IpProtocol::Udp => match UdpPacket::new_checked(payload) {
+            Ok(udp) => format!(
+                "IP {}:{} > {}:{}: UDP, length {}",
+                src, udp.src_port(), dst, udp.dst_port(), udp.payload().len()
+            ),
+            Err(_) => format!("IP {} > {}: UDP, length {}", src, dst, len),
+        },
+        IpProtocol::Icmp => match Icmpv4Packet::new_checked(payload) {
+            Ok(icmp) => match icmp.msg_type() {
+                msg @ (Icmpv4Message::EchoRequest |
+                       Icmpv4Message::EchoReply) => format!(
+                    "IP {} > {}: ICMP {}, id {}, seq {}, length {}",
+                    src, dst, msg, icmp.echo_ident(), icmp.echo_seq_no(), len
+                ),
+                msg => format!(
+                    "IP {} > {}: ICMP {}, length {}", src, dst, msg, len
+                ),
+            },
+            Err(_) => format!("IP {} > {}: ICMP, length {}", src, dst, len),
+        },
+        proto => format!("IP {} > {}: {}, length {}", src, dst, proto, len),
+    }
+}
+
+fn summary(buf: &[u8]) -> String {
+    let frame = match EthernetFrame::new_checked(buf) {
+        Ok(frame) => frame,
+        Err(_) => return format!("Invalid frame, length {}", buf.len()),
+    };
+    match frame.ethertype() {
+        EthernetProtocol::Arp => arp_summary(frame.payload()),
+        EthernetProtocol::Ipv4 => ipv4_summary(frame.payload()),
+        ethertype => format!(
+            "{} > {}, ethertype {}, length {}",
+            frame.src_addr().to_string().to_uppercase(),
+            frame.dst_addr().to_string().to_uppercase(),
+            ethertype,
+            frame.payload().len()
+        ),
+    }
+}
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut interface = None;
+    let mut count = None;
+    let mut path = None;
+    let mut promiscuous = true;
+    let mut hex = false;
+    let mut i = 1;
+    let n = args.len();
+    while i < n {
+        match args[i] {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-p" | "--no-promiscuous" => {
+                promiscuous = false;
+            }
+            "-x" | "--hex" => {
+                hex = true;
+            }
+            "-i" | "--interface" | "-c" | "--count" | "-w" | "--write"
+                if i + 1 == n =>
+            {
+                error!("Missing value for '{}'", args[i]);
+                return Err(ExitCode::UsageError);
+            }
+            "-i" | "--interface" => {
+                i += 1;
+                interface = Some(args[i]);
+            }
+            "-c" | "--count" => {
+                i += 1;
+                match args[i].parse::<usize>() {
+                    Ok(n) if n > 0 => count = Some(n),
+                    _ => {
+                        error!("Could not parse count '{}'", args[i]);
+                        return Err(ExitCode::UsageError);
+                    }
+                }
+            }
+            "-w" | "--write" => {
+                i += 1;
+                path = Some(args[i]);
+            }
+            _ => {
+                help();
+                return Err(ExitCode::UsageError);
+            }
+        }
+        i += 1;
+    }
+
+    let mut net = sys::net::NET.lock();
+    if net.is_empty() {
+        error!("Network error");
+        return Err(ExitCode::Failure);
+    }
+    let indexes: Vec<usize> = match interface {
+        Some(name) => match net.iter().position(|eth| eth.name == name) {
+            Some(index) => [index].to_vec(),
+            None => {
+                error!("Could not find interface '{}'", name);
+                return Err(ExitCode::Failure);
+            }
+        },
+        None => (0..net.len()).collect(),
+    };
+    if let Some(path) = path {
+        if fs::write(path, &pcap_header()).is_err() {
+            error!("Could not write to '{}'", path);
+            return Err(ExitCode::Failure);
+        }
+    }
+
+    for &i in &indexes {
+        if promiscuous {
+            net[i].device.set_promiscuous(true);
+        }
+        net[i].device.config().capture.start();
+    }
+
+    let mut res = Ok(());
+    let mut captured = 0;
+    'capture: loop {
+        if console::end_of_text() || console::end_of_transmission() {
+            println!();
+            break;
+        }
+        sys::net::poll(&mut net, &mut SOCKETS.lock());
+
+        let mut packets = Vec::new();
+        for &i in &indexes {
+            while let Some(packet) = net[i].device.config().capture.pop() {
+                packets.push((i, packet));
+            }
+        }
+        packets.sort_by(|(_, a), (_, b)| a.time.total_cmp(&b.time));
+
+        let mut records = Vec::new();
+        for (i, packet) in packets {
+            if path.is_some() {
+                records.extend(pcap_record(&packet));
+            } else {
+                let direction = match packet.direction {
+                    Direction::In => "In ",
+                    Direction::Out => "Out",
+                };
+                println!(
+                    "{} {} {} {}",
+                    timestamp(packet.time),
+                    net[i].name,
+                    direction,
+                    summary(&packet.data)
+                );
+                if hex {
+                    usr::hex::print_hex(&packet.data);
+                }
+            }
+            captured += 1;
+            if count == Some(captured) {
+                break;
+            }
+        }
+        if let Some(path) = path {
+            if !records.is_empty() && fs::append(path, &records).is_err() {
+                error!("Could not write to '{}'", path);
+                res = Err(ExitCode::Failure);
+                break 'capture;
+            }
+        }
+        if count == Some(captured) {
+            break;
+        }
+        syscall::sleep(0.01);
+    }
+
+    let mut dropped = 0;
+    for &i in &indexes {
+        let config = net[i].device.config();
+        dropped += config.capture.dropped_count();
+        config.capture.stop();
+        if promiscuous {
+            net[i].device.set_promiscuous(false);
+        }
+    }
+    println!("{} packets captured", captured);
+    if dropped > 0 {
+        println!("{} packets dropped", dropped);
+    }
+    res
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} tcpdump {}<options>{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-i{1}, {0}--interface <name>{1}  Capture on one interface",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-c{1}, {0}--count <n>{1}         Stop after capturing n packets",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-w{1}, {0}--write <file>{1}      Write packets to a pcap file",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-p{1}, {0}--no-promiscuous{1}    Capture only packets to us",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-x{1}, {0}--hex{1}               Print packets in hexadecimal",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_pcap() {
+    let header = pcap_header();
+    assert_eq!(header.len(), 24);
+    assert_eq!(header[0..4], [0xD4, 0xC3, 0xB2, 0xA1]);
+    assert_eq!(header[20..24], [1, 0, 0, 0]);
+
+    let data = [0xFF; 60].to_vec();
+    let direction = Direction::In;
+    let packet = Packet { time: 1700000000.25, direction, data };
+    let record = pcap_record(&packet);
+    assert_eq!(record.len(), 16 + 60);
+    assert_eq!(record[0..4], 1700000000u32.to_le_bytes());
+    assert_eq!(record[4..8], 250000u32.to_le_bytes());
+    assert_eq!(record[8..12], 60u32.to_le_bytes());
+    assert_eq!(record[12..16], 60u32.to_le_bytes());
+}
+
+#[test_case]
+fn test_summary() {
+    // ARP request for 10.0.2.2 sent by 10.0.2.15
+    let frame = [
+        0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x52, 0x54, 0x00, 0x12,
+        0x34, 0x56, 0x08, 0x06, 0x00, 0x01, 0x08, 0x00, 0x06, 0x04,
+        0x00, 0x01, 0x52, 0x54, 0x00, 0x12, 0x34, 0x56, 0x0A, 0x00,
+        0x02, 0x0F, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0A, 0x00,
+        0x02, 0x02,
+    ];
+    assert_eq!(
+        summary(&frame),
+        "ARP, Request who-has 10.0.2.2 tell 10.0.2.15"
+    );
+    assert_eq!(summary(&frame[0..10]), "Invalid frame, length 10");
+}
@@ -0,0 +1,86 @@
+use crate::api::console::{Style, EOT_KEY};
+use crate::api::fs;
+use crate::api::io;
+use crate::api::process::ExitCode;
+use crate::api::syscall;
+
+use alloc::vec::Vec;
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut append = false;
+    let mut paths = Vec::new();
+    for arg in &args[1..] {
+        match *arg {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-a" | "--append" => append = true,
+            _ => paths.push(*arg),
+        }
+    }
+
+    let mut handles = Vec::new();
+    for path in &paths {
+        let handle = if append && fs::exists(path) {
+            fs::append_file(path)
+        } else {
+            fs::create_file(path)
+        };
+        if let Some(handle) = handle {
+            handles.push(handle);
+        } else {
+            error!("Could not write to '{}'", path);
+            for handle in handles {
+                syscall::close(handle);
+            }
+            return Err(ExitCode::Failure);
+        }
+    }
+
+    // Copy the input as it comes to be able to watch the output of long
+    // running commands.
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    loop {
+        let mut line = stdin.read_line();
+        if line.is_empty() {
+            break;
+        }
+        let eot = if let Some(i) = line.find(EOT_KEY) {
+            line.truncate(i);
+            true
+        } else {
+            false
+        };
+        stdout.write(&line);
+        for handle in &handles {
+            syscall::write(*handle, line.as_bytes());
+        }
+        if eot {
+            break;
+        }
+    }
+
+    for handle in handles {
+        syscall::close(handle);
+    }
+    Ok(())
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} tee {}<options> <path>...{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-a{1}, {0}--append{1}   Append to the files instead of \
+        overwriting them",
+        csi_option, csi_reset
+    );
+}
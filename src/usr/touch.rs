@@ -0,0 +1,127 @@
+use crate::api::clock::DATE_TIME;
+use crate::api::console::Style;
+use crate::api::fs;
+use crate::api::process::ExitCode;
+use crate::api::syscall;
+use crate::api::{self, clock};
+
+use alloc::format;
+use alloc::vec::Vec;
+use time::PrimitiveDateTime;
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut time = None;
+    let mut paths = Vec::new();
+    let mut i = 1;
+    let n = args.len();
+    while i < n {
+        match args[i] {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-d" | "--date" | "-t" | "--timestamp" => {
+                if i + 1 == n {
+                    error!("Missing time");
+                    return Err(ExitCode::UsageError);
+                }
+                let arg = args[i + 1];
+                time = if matches!(args[i], "-d" | "--date") {
+                    parse_date(arg)
+                } else {
+                    arg.parse().ok()
+                };
+                if time.is_none() {
+                    error!("Invalid time '{}'", arg);
+                    return Err(ExitCode::UsageError);
+                }
+                i += 1;
+            }
+            arg => paths.push(arg),
+        }
+        i += 1;
+    }
+    if paths.is_empty() {
+        help();
+        return Err(ExitCode::UsageError);
+    }
+    let time = time.unwrap_or(clock::realtime() as u64);
+    for path in paths {
+        touch(path, time)?;
+    }
+    Ok(())
+}
+
+// Parse a local date like "2026-10-15 12:00:00" or "2026-10-15" into
+// seconds since Unix Epoch
+fn parse_date(s: &str) -> Option<u64> {
+    let s = if s.len() == 10 { format!("{} 00:00:00", s) } else { s.into() };
+    let date = PrimitiveDateTime::parse(s, DATE_TIME).ok()?;
+    let offset = api::time::now().offset();
+    let ts = date.assume_offset(offset).unix_timestamp();
+    if ts < 0 { None } else { Some(ts as u64) }
+}
+
+// Set the access and modification times of a file or a dir, creating an
+// empty file if it doesn't exist
+fn touch(path: &str, time: u64) -> Result<(), ExitCode> {
+    if !fs::exists(path) {
+        match fs::create_file(path) {
+            Some(handle) => syscall::close(handle),
+            None => {
+                error!("Could not touch '{}'", path);
+                return Err(ExitCode::Failure);
+            }
+        }
+    }
+    if fs::utime(path, time, time).is_err() {
+        error!("Could not touch '{}'", path);
+        return Err(ExitCode::Failure);
+    }
+    Ok(())
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} touch {}<options> <path>...{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-d{1}, {0}--date <date>{1}        \
+        Use {0}<date>{1} like \"2026-10-15 12:00:00\"",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-t{1}, {0}--timestamp <secs>{1}   \
+        Use {0}<secs>{1} since Unix Epoch",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_touch() {
+    use crate::sys;
+
+    sys::fs::mount_mem();
+    sys::fs::format_mem();
+
+    assert!(touch("/test.txt", 1000).is_ok());
+    assert!(fs::exists("/test.txt"));
+
+    fs::write("/test.txt", b"hello").ok();
+    assert!(touch("/test.txt", 2000).is_ok());
+    assert_eq!(fs::read_to_bytes("/test.txt"), Ok(b"hello".to_vec()));
+    let info = syscall::info("/test.txt").unwrap();
+    assert_eq!(info.mtime(), 2000);
+
+    assert!(main(&["touch", "-t", "3000", "/test.txt"]).is_ok());
+    assert_eq!(syscall::info("/test.txt").map(|i| i.mtime()), Some(3000));
+    assert!(main(&["touch", "-d", "yesterday", "/test.txt"]).is_err());
+
+    sys::fs::dismount();
+}
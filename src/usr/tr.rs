@@ -0,0 +1,172 @@
+use crate::api::console::Style;
+use crate::api::io;
+use crate::api::process::ExitCode;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut delete = false;
+    let mut squeeze = false;
+    let mut sets = Vec::new();
+    for arg in &args[1..] {
+        match *arg {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-d" | "--delete" => delete = true,
+            "-s" | "--squeeze" => squeeze = true,
+            _ => sets.push(expand(arg)),
+        }
+    }
+    let n = if delete || (squeeze && sets.len() == 1) { 1 } else { 2 };
+    if sets.len() != n || sets.iter().any(|set| set.is_empty()) {
+        help();
+        return Err(ExitCode::UsageError);
+    }
+
+    let input = io::stdin().read_to_string();
+    let output = if delete {
+        let output = tr(&input, &sets[0], &[], true);
+        if squeeze {
+            squeeze_chars(&output, &sets[0])
+        } else {
+            output
+        }
+    } else if sets.len() == 1 {
+        squeeze_chars(&input, &sets[0])
+    } else {
+        let output = tr(&input, &sets[0], &sets[1], false);
+        if squeeze {
+            squeeze_chars(&output, &sets[1])
+        } else {
+            output
+        }
+    };
+    print!("{}", output);
+    Ok(())
+}
+
+fn tr(text: &str, set1: &[char], set2: &[char], delete: bool) -> String {
+    let mut res = String::new();
+    for c in text.chars() {
+        if let Some(i) = set1.iter().position(|x| *x == c) {
+            if delete {
+                continue;
+            }
+            // The last char of the second set is repeated if it's shorter
+            res.push(set2[i.min(set2.len() - 1)]);
+        } else {
+            res.push(c);
+        }
+    }
+    res
+}
+
+// Replace each sequence of a repeated char of the set by a single one
+fn squeeze_chars(text: &str, set: &[char]) -> String {
+    let mut res = String::new();
+    let mut prev = None;
+    for c in text.chars() {
+        if prev == Some(c) && set.contains(&c) {
+            continue;
+        }
+        res.push(c);
+        prev = Some(c);
+    }
+    res
+}
+
+// Expand ranges like `a-z`, classes like `[:upper:]`, and escapes like `\n`
+fn expand(set: &str) -> Vec<char> {
+    let mut res = Vec::new();
+    let chars: Vec<char> = set.chars().collect();
+    let n = chars.len();
+    let mut i = 0;
+    while i < n {
+        if chars[i] == '[' && i + 1 < n && chars[i + 1] == ':' {
+            let rest: String = chars[i..].iter().collect();
+            if let Some(end) = rest.find(":]") {
+                let name = &rest[2..end];
+                if let Some(class) = class(name) {
+                    res.extend(class);
+                    i += rest[..end + 2].chars().count();
+                    continue;
+                }
+            }
+        }
+        let c = if chars[i] == '\\' && i + 1 < n {
+            i += 1;
+            match chars[i] {
+                'n' => '\n',
+                't' => '\t',
+                'r' => '\r',
+                c => c,
+            }
+        } else {
+            chars[i]
+        };
+        if i + 2 < n && chars[i + 1] == '-' && c <= chars[i + 2] {
+            res.extend(c..=chars[i + 2]);
+            i += 3;
+        } else {
+            res.push(c);
+            i += 1;
+        }
+    }
+    res
+}
+
+fn class(name: &str) -> Option<Vec<char>> {
+    let chars = match name {
+        "upper" => ('A'..='Z').collect(),
+        "lower" => ('a'..='z').collect(),
+        "digit" => ('0'..='9').collect(),
+        "alpha" => ('A'..='Z').chain('a'..='z').collect(),
+        "alnum" => ('0'..='9').chain('A'..='Z').chain('a'..='z').collect(),
+        "space" => [' ', '\t', '\n', '\r', '\x0B', '\x0C'].to_vec(),
+        "punct" => ('!'..='~').filter(|c| c.is_ascii_punctuation()).collect(),
+        _ => return None,
+    };
+    Some(chars)
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} tr {}<options> <set1> [<set2>]{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-d{1}, {0}--delete{1}    Delete chars in {0}<set1>{1}",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-s{1}, {0}--squeeze{1}   Squeeze repeated chars of the last set",
+        csi_option, csi_reset
+    );
+    println!();
+    println!("{}Sets:{}", csi_title, csi_reset);
+    println!(
+        "  {0}a-z{1}, {0}[:upper:]{1}, {0}[:lower:]{1}, {0}[:digit:]{1}, \
+        {0}[:alpha:]{1}, {0}[:alnum:]{1}, {0}[:space:]{1}, {0}[:punct:]{1}",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_tr() {
+    let upper = expand("[:upper:]");
+    let lower = expand("[:lower:]");
+    assert_eq!(tr("Hello, World!", &lower, &upper, false), "HELLO, WORLD!");
+    assert_eq!(tr("hello", &expand("a-y"), &expand("b-z"), false), "ifmmp");
+    assert_eq!(tr("hello", &expand("lo"), &expand("x"), false), "hexxx");
+    assert_eq!(tr("hello", &expand("l"), &[], true), "heo");
+    assert_eq!(squeeze_chars("aabbbcc  d", &expand("ab ")), "abcc d");
+    assert_eq!(expand("a\\nb-d"), ['a', '\n', 'b', 'c', 'd']);
+}
@@ -0,0 +1,29 @@
+use crate::api::console::Style;
+use crate::api::process::ExitCode;
+use crate::sys;
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    if args.len() != 2 {
+        help();
+        return Err(ExitCode::UsageError);
+    }
+    if args[1] == "-h" || args[1] == "--help" {
+        help();
+        return Ok(());
+    }
+    if sys::fs::umount(args[1]).is_err() {
+        error!("Could not unmount '{}'", args[1]);
+        return Err(ExitCode::Failure);
+    }
+    Ok(())
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} umount {}<path>{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+}
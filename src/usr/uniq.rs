@@ -0,0 +1,92 @@
+use crate::api::console::Style;
+use crate::api::fs;
+use crate::api::io;
+use crate::api::process::ExitCode;
+
+use alloc::vec::Vec;
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut count = false;
+    let mut repeated = false;
+    let mut path = None;
+    for arg in &args[1..] {
+        match *arg {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-c" | "--count" => count = true,
+            "-d" | "--repeated" => repeated = true,
+            _ => {
+                if path.is_some() {
+                    help();
+                    return Err(ExitCode::UsageError);
+                }
+                path = Some(*arg);
+            }
+        }
+    }
+
+    let contents = if let Some(path) = path {
+        if let Ok(contents) = fs::read_to_string(path) {
+            contents
+        } else {
+            error!("Could not read '{}'", path);
+            return Err(ExitCode::Failure);
+        }
+    } else {
+        io::stdin().read_to_string()
+    };
+
+    for (n, line) in uniq(&contents) {
+        if repeated && n < 2 {
+            continue;
+        }
+        if count {
+            println!("{:>7} {}", n, line);
+        } else {
+            println!("{}", line);
+        }
+    }
+    Ok(())
+}
+
+// Group adjacent identical lines with their number of occurrences
+fn uniq(text: &str) -> Vec<(usize, &str)> {
+    let mut res: Vec<(usize, &str)> = Vec::new();
+    for line in text.lines() {
+        match res.last_mut() {
+            Some((n, prev)) if *prev == line => *n += 1,
+            _ => res.push((1, line)),
+        }
+    }
+    res
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} uniq {}<options> [<path>]{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-c{1}, {0}--count{1}      Prefix lines with their number of \
+        occurrences",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-d{1}, {0}--repeated{1}   Print only duplicated lines",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_uniq() {
+    let text = "a\na\nb\na\nc\nc\nc\n";
+    assert_eq!(uniq(text), [(2, "a"), (1, "b"), (1, "a"), (3, "c")]);
+    assert!(uniq("").is_empty());
+}
@@ -16,6 +16,17 @@ use sha2::Sha256;
 const USERS: &str = "/ini/users.csv";
 const DISABLE_EMPTY_PASSWORD: bool = false;
 
+// The IDs below this one are reserved, with 0 being root
+const FIRST_UID: u16 = 1000;
+
+// Every user has a group with the same name and ID
+#[derive(Clone)]
+struct User {
+    hash: String,
+    uid: u16,
+    gid: u16,
+}
+
 pub fn main(args: &[&str]) -> Result<(), ExitCode> {
     match *args.get(1).unwrap_or(&"invalid") {
         "create" => {}
@@ -57,8 +68,8 @@ pub fn login(username: &str) -> Result<(), ExitCode> {
         return main(&["user", "login"]);
     }
 
-    match hashed_password(username) {
-        Some(hash) => {
+    match read_users().get(username).cloned() {
+        Some(User { hash, uid, gid }) => {
             print!("Password: ");
             print!("\x1b[12l"); // Disable echo
             let password = io::stdin().read_line().trim_end().to_string();
@@ -69,6 +80,7 @@ pub fn login(username: &str) -> Result<(), ExitCode> {
                 syscall::sleep(1.0);
                 return main(&["user", "login"]);
             }
+            sys::process::set_user(username, uid, gid);
         }
         None => {
             println!();
@@ -78,7 +90,6 @@ pub fn login(username: &str) -> Result<(), ExitCode> {
     }
 
     let home = format!("/usr/{}", username);
-    sys::process::set_user(username);
     sys::process::set_dir(&home);
     sys::process::set_env("USER", username);
     sys::process::set_env("HOME", &home);
@@ -92,7 +103,8 @@ pub fn create(username: &str) -> Result<(), ExitCode> {
         return Err(ExitCode::Failure);
     }
 
-    if hashed_password(username).is_some() {
+    let users = read_users();
+    if users.contains_key(username) || username == "root" {
         error!("Username exists");
         return Err(ExitCode::Failure);
     }
@@ -118,14 +130,19 @@ pub fn create(username: &str) -> Result<(), ExitCode> {
         return Err(ExitCode::Failure);
     }
 
-    if save_hashed_password(username, &hash(&password)).is_err() {
+    let uid = users.values().map(|user| user.uid + 1).max().unwrap_or(0);
+    let uid = uid.max(FIRST_UID);
+    let user = User { hash: hash(&password), uid, gid: uid };
+    if save_user(username, user).is_err() {
         error!("Could not save user");
         return Err(ExitCode::Failure);
     }
 
     // Create home dir
-    if let Some(handle) = api::fs::create_dir(&format!("/usr/{}", username)) {
+    let home = format!("/usr/{}", username);
+    if let Some(handle) = api::fs::create_dir(&home) {
         api::syscall::close(handle);
+        fs::chown(&home, uid, uid).ok();
     } else {
         error!("Could not create home dir");
         return Err(ExitCode::Failure);
@@ -188,38 +205,63 @@ pub fn hash(password: &str) -> String {
     res
 }
 
-fn read_hashed_passwords() -> BTreeMap<String, String> {
-    let mut hashed_passwords = BTreeMap::new();
+fn read_users() -> BTreeMap<String, User> {
+    let mut users = BTreeMap::new();
     if let Ok(csv) = api::fs::read_to_string(USERS) {
         for line in csv.split('\n') {
-            let mut rows = line.split(',');
-            if let Some(username) = rows.next() {
-                if let Some(hash) = rows.next() {
-                    hashed_passwords.insert(username.into(), hash.into());
+            let rows: Vec<_> = line.split(',').collect();
+            if rows.len() == 4 {
+                if let (Ok(uid), Ok(gid)) = (rows[2].parse(), rows[3].parse()) {
+                    let hash = rows[1].into();
+                    users.insert(rows[0].into(), User { hash, uid, gid });
                 }
             }
         }
     }
-    hashed_passwords
-}
-
-fn hashed_password(username: &str) -> Option<String> {
-    read_hashed_passwords().get(username).map(|hash| hash.into())
+    users
 }
 
-fn save_hashed_password(username: &str, hash: &str) -> Result<usize, ()> {
-    let mut hashed_passwords = read_hashed_passwords();
-    hashed_passwords.remove(username);
-    hashed_passwords.insert(username.into(), hash.into());
+fn save_user(username: &str, user: User) -> Result<usize, ()> {
+    let mut users = read_users();
+    users.insert(username.into(), user);
 
     let mut csv = String::new();
-    for (u, h) in hashed_passwords {
-        csv.push_str(&format!("{},{}\n", u, h));
+    for (name, user) in users {
+        let (h, u, g) = (user.hash, user.uid, user.gid);
+        csv.push_str(&format!("{},{},{},{}\n", name, h, u, g));
     }
 
     fs::write(USERS, csv.as_bytes())
 }
 
+pub fn uid(username: &str) -> Option<u16> {
+    if username == "root" {
+        return Some(0);
+    }
+    read_users().get(username).map(|user| user.uid)
+}
+
+pub fn gid(groupname: &str) -> Option<u16> {
+    if groupname == "root" {
+        return Some(0);
+    }
+    read_users().get(groupname).map(|user| user.gid)
+}
+
+pub fn username(uid: u16) -> Option<String> {
+    if uid == 0 {
+        return Some("root".into());
+    }
+    read_users().into_iter().find(|(_, u)| u.uid == uid).map(|(name, _)| name)
+}
+
+pub fn groupname(gid: u16) -> Option<String> {
+    if gid == 0 {
+        return Some("root".into());
+    }
+    read_users().into_iter().find(|(_, u)| u.gid == gid).map(|(name, _)| name)
+}
+
 fn help() {
     let csi_option = Style::color("LightCyan");
     let csi_title = Style::color("Yellow");
@@ -0,0 +1,136 @@
+use crate::api::console::{strip_escapes, Style};
+use crate::api::fs;
+use crate::api::process::ExitCode;
+use crate::api::syscall;
+use crate::sys;
+use crate::sys::console;
+use crate::usr;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut interval = 2.0;
+    let mut i = 1;
+    let n = args.len();
+    while i < n {
+        match args[i] {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-n" | "--interval" => {
+                if i + 1 < n {
+                    match args[i + 1].parse::<f64>() {
+                        Ok(secs) if secs >= 0.1 => interval = secs,
+                        _ => {
+                            error!("Invalid interval '{}'", args[i + 1]);
+                            return Err(ExitCode::UsageError);
+                        }
+                    }
+                    i += 1;
+                } else {
+                    error!("Missing interval");
+                    return Err(ExitCode::UsageError);
+                }
+            }
+            _ => break,
+        }
+        i += 1;
+    }
+    if i == n {
+        help();
+        return Err(ExitCode::UsageError);
+    }
+    let cmd = args[i..].join(" ");
+
+    // The output of the command is saved in a file to compare it with the
+    // output of the previous run before printing it.
+    let path = format!("/tmp/.watch-{}", sys::process::id());
+    let mut previous = String::new();
+    loop {
+        usr::shell::exec(&format!("{} => {}", cmd, path)).ok();
+        let output = fs::read_to_string(&path).unwrap_or_default();
+        print!("\x1b[2J\x1b[1;1H"); // Clear screen and move to top
+        let color = Style::color("Yellow");
+        let reset = Style::reset();
+        println!("{}Every {:.1}s:{} {}", color, interval, reset, cmd);
+        println!();
+        print!("{}", highlight(&previous, &output));
+        previous = output;
+
+        let mut t = 0.0;
+        while t < interval {
+            if console::end_of_text() || console::end_of_transmission() {
+                fs::delete(&path).ok();
+                println!();
+                return Ok(());
+            }
+            syscall::sleep(0.1);
+            t += 0.1;
+        }
+    }
+}
+
+// Highlight the characters that changed since the previous output. Lines
+// without changes keep their colors.
+fn highlight(previous: &str, output: &str) -> String {
+    let color = Style::color("Black").with_background("LightGray");
+    let reset = Style::reset();
+    let old_lines: Vec<&str> = previous.lines().collect();
+    let mut res = String::new();
+    for (i, line) in output.lines().enumerate() {
+        let old = old_lines.get(i).copied().unwrap_or_default();
+        if previous.is_empty() || line == old {
+            res.push_str(line);
+        } else {
+            let old: Vec<char> = strip_escapes(old).chars().collect();
+            let mut changed = false;
+            for (j, c) in strip_escapes(line).chars().enumerate() {
+                let is_diff = old.get(j) != Some(&c);
+                if is_diff && !changed {
+                    res.push_str(&format!("{}", color));
+                } else if !is_diff && changed {
+                    res.push_str(&format!("{}", reset));
+                }
+                changed = is_diff;
+                res.push(c);
+            }
+            if changed {
+                res.push_str(&format!("{}", reset));
+            }
+        }
+        res.push('\n');
+    }
+    res
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} watch {}<options> <command>{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-n{1}, {0}--interval <secs>{1}   Run the command every \
+        {0}<secs>{1} (default: 2)",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_highlight() {
+    let on = format!("{}", Style::color("Black").with_background("LightGray"));
+    let off = format!("{}", Style::reset());
+    assert_eq!(highlight("", "a\nb\n"), "a\nb\n");
+    assert_eq!(highlight("a\nb\n", "a\nb\n"), "a\nb\n");
+    assert_eq!(
+        highlight("abc\n", "abd\nx\n"),
+        format!("ab{0}d{1}\n{0}x{1}\n", on, off)
+    );
+}
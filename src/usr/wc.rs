@@ -0,0 +1,148 @@
+use crate::api::console::Style;
+use crate::api::fs;
+use crate::api::io;
+use crate::api::process::ExitCode;
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Count {
+    lines: usize,
+    words: usize,
+    bytes: usize,
+}
+
+impl Count {
+    fn new(text: &str) -> Self {
+        Self {
+            lines: text.matches('\n').count(),
+            words: text.split_whitespace().count(),
+            bytes: text.len(),
+        }
+    }
+
+    fn add(&mut self, other: &Self) {
+        self.lines += other.lines;
+        self.words += other.words;
+        self.bytes += other.bytes;
+    }
+}
+
+struct Config {
+    lines: bool,
+    words: bool,
+    bytes: bool,
+}
+
+pub fn main(args: &[&str]) -> Result<(), ExitCode> {
+    let mut config = Config {
+        lines: false,
+        words: false,
+        bytes: false,
+    };
+    let mut paths = Vec::new();
+    for arg in &args[1..] {
+        match *arg {
+            "-h" | "--help" => {
+                help();
+                return Ok(());
+            }
+            "-l" | "--lines" => config.lines = true,
+            "-w" | "--words" => config.words = true,
+            "-c" | "--bytes" => config.bytes = true,
+            _ => paths.push(*arg),
+        }
+    }
+    if !config.lines && !config.words && !config.bytes {
+        config.lines = true;
+        config.words = true;
+        config.bytes = true;
+    }
+
+    let mut counts = Vec::new();
+    if paths.is_empty() {
+        counts.push((Count::new(&io::stdin().read_to_string()), ""));
+    }
+    for path in &paths {
+        if let Ok(contents) = fs::read_to_string(path) {
+            counts.push((Count::new(&contents), *path));
+        } else {
+            error!("Could not read '{}'", path);
+            return Err(ExitCode::Failure);
+        }
+    }
+    if counts.len() > 1 {
+        let mut total = Count::default();
+        for (count, _) in &counts {
+            total.add(count);
+        }
+        counts.push((total, "total"));
+    }
+
+    let width = counts.iter().fold(1, |acc, (count, _)| {
+        let n = count.lines.max(count.words).max(count.bytes);
+        acc.max(n.to_string().len())
+    });
+    for (count, path) in counts {
+        println!("{}", format_count(&count, path, &config, width));
+    }
+    Ok(())
+}
+
+fn format_count(
+    count: &Count,
+    path: &str,
+    config: &Config,
+    width: usize
+) -> String {
+    let mut fields = Vec::new();
+    if config.lines {
+        fields.push(format!("{:>width$}", count.lines, width = width));
+    }
+    if config.words {
+        fields.push(format!("{:>width$}", count.words, width = width));
+    }
+    if config.bytes {
+        fields.push(format!("{:>width$}", count.bytes, width = width));
+    }
+    if !path.is_empty() {
+        fields.push(path.into());
+    }
+    fields.join(" ")
+}
+
+fn help() {
+    let csi_option = Style::color("LightCyan");
+    let csi_title = Style::color("Yellow");
+    let csi_reset = Style::reset();
+    println!(
+        "{}Usage:{} wc {}<options> [<path>...]{}",
+        csi_title, csi_reset, csi_option, csi_reset
+    );
+    println!();
+    println!("{}Options:{}", csi_title, csi_reset);
+    println!(
+        "  {0}-l{1}, {0}--lines{1}   Print the line count",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-w{1}, {0}--words{1}   Print the word count",
+        csi_option, csi_reset
+    );
+    println!(
+        "  {0}-c{1}, {0}--bytes{1}   Print the byte count",
+        csi_option, csi_reset
+    );
+}
+
+#[test_case]
+fn test_count() {
+    let count = Count::new("hello world\nhow are you?\n");
+    assert_eq!(count, Count { lines: 2, words: 5, bytes: 25 });
+    assert_eq!(Count::new(""), Count::default());
+
+    let config = Config { lines: true, words: false, bytes: true };
+    assert_eq!(format_count(&count, "a.txt", &config, 3), "  2  25 a.txt");
+}